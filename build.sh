@@ -15,8 +15,18 @@ case "$1" in
 		# scp procmem_examples/python/repl.py deck@192.168.0.171:Documents/procmem/
 	;;
 
+	check)
+		# lints the backends of other platforms, the targets are installed with `rustup target add`
+		# the capstone features are left out because capstone needs a C cross compiler
+		for target in x86_64-apple-darwin aarch64-apple-darwin x86_64-pc-windows-msvc x86_64-pc-windows-gnu x86_64-unknown-freebsd x86_64-unknown-netbsd; do
+			cargo clippy --package procmem_access --target "$target" --all-targets --all-features -- -D warnings || exit 1
+			cargo clippy --package procmem_scan --target "$target" --all-targets --features serde,regex,bytemuck,derive -- -D warnings || exit 1
+			cargo clippy --package procmem_examples --package procmem_ffi --package procmem_jsonrpc --package procmem_python --target "$target" --all-targets -- -D warnings || exit 1
+		done
+	;;
+
 	*)
-		echo "usage: build.sh deck"
+		echo "usage: build.sh deck|check"
 		exit 1
 	;;
 esac
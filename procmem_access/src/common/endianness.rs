@@ -0,0 +1,42 @@
+/// Byte order of values in the target memory.
+///
+/// Usually this matches the host, but core dumps or remote targets may come from machines with a different byte order.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Endianness {
+	Little,
+	Big,
+}
+impl Endianness {
+	/// Byte order of the host.
+	#[cfg(target_endian = "little")]
+	pub const NATIVE: Endianness = Endianness::Little;
+	/// Byte order of the host.
+	#[cfg(target_endian = "big")]
+	pub const NATIVE: Endianness = Endianness::Big;
+
+	pub fn is_native(&self) -> bool {
+		*self == Self::NATIVE
+	}
+
+	/// Converts the bytes of one scalar value between this byte order and the native one.
+	///
+	/// The conversion is its own inverse, so this works in both directions.
+	pub fn convert_scalar(&self, bytes: &mut [u8]) {
+		if !self.is_native() {
+			bytes.reverse();
+		}
+	}
+}
+impl Default for Endianness {
+	fn default() -> Self {
+		Self::NATIVE
+	}
+}
+impl std::fmt::Display for Endianness {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		match self {
+			Endianness::Little => write!(f, "little"),
+			Endianness::Big => write!(f, "big"),
+		}
+	}
+}
@@ -0,0 +1,133 @@
+/// IEEE 754 half-precision float stored as its bits.
+///
+/// Only conversions to and from `f32` are provided, arithmetic and comparisons are meant to be done on the converted value.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default)]
+#[repr(transparent)]
+pub struct F16(u16);
+impl F16 {
+	pub const fn from_bits(bits: u16) -> Self {
+		F16(bits)
+	}
+
+	pub const fn to_bits(self) -> u16 {
+		self.0
+	}
+
+	pub const fn from_ne_bytes(bytes: [u8; 2]) -> Self {
+		F16(u16::from_ne_bytes(bytes))
+	}
+
+	pub const fn to_ne_bytes(self) -> [u8; 2] {
+		self.0.to_ne_bytes()
+	}
+
+	/// Converts from `f32`, rounding to the nearest representable value (ties to even).
+	///
+	/// Values too large for half precision become infinities, NaNs stay NaNs.
+	pub fn from_f32(value: f32) -> Self {
+		let bits = value.to_bits();
+		let sign = ((bits >> 16) & 0x8000) as u16;
+		let exponent = ((bits >> 23) & 0xFF) as i32;
+		let mantissa = bits & 0x7F_FFFF;
+
+		// infinity and NaN, keeping NaNs quiet and non-zero
+		if exponent == 0xFF {
+			let nan_bit = if mantissa != 0 { 0x0200 } else { 0 };
+			return F16(sign | 0x7C00 | nan_bit | (mantissa >> 13) as u16);
+		}
+
+		let half_exponent = exponent - 127 + 15;
+		if half_exponent >= 0x1F {
+			return F16(sign | 0x7C00);
+		}
+
+		if half_exponent <= 0 {
+			// subnormal or zero, shift the mantissa with the implicit bit into place
+			if half_exponent < -10 {
+				return F16(sign);
+			}
+
+			let mantissa = mantissa | 0x80_0000;
+			let shift = (14 - half_exponent) as u32;
+			let half_mantissa = mantissa >> shift;
+			let remainder = mantissa & ((1 << shift) - 1);
+			let halfway = 1 << (shift - 1);
+
+			let round_up = remainder > halfway || (remainder == halfway && half_mantissa & 1 == 1);
+			return F16(sign | (half_mantissa + round_up as u32) as u16);
+		}
+
+		let half = ((half_exponent as u32) << 10) | (mantissa >> 13);
+		let remainder = mantissa & 0x1FFF;
+		let round_up = remainder > 0x1000 || (remainder == 0x1000 && half & 1 == 1);
+
+		// a carry out of the mantissa correctly increments the exponent, up to infinity
+		F16(sign | (half + round_up as u32) as u16)
+	}
+
+	/// Converts to `f32`, which represents every half-precision value exactly.
+	pub fn to_f32(self) -> f32 {
+		let sign = ((self.0 & 0x8000) as u32) << 16;
+		let exponent = ((self.0 >> 10) & 0x1F) as u32;
+		let mantissa = (self.0 & 0x3FF) as u32;
+
+		let bits = match (exponent, mantissa) {
+			(0, 0) => sign,
+			(0, _) => {
+				// subnormal, normalize the mantissa
+				let shift = mantissa.leading_zeros() - 21;
+				let mantissa = (mantissa << shift) & 0x3FF;
+				let exponent = 127 - 15 + 1 - shift;
+
+				sign | (exponent << 23) | (mantissa << 13)
+			}
+			(0x1F, mantissa) => sign | 0x7F80_0000 | (mantissa << 13),
+			(exponent, mantissa) => sign | ((exponent + 127 - 15) << 23) | (mantissa << 13),
+		};
+
+		f32::from_bits(bits)
+	}
+
+	pub fn is_nan(self) -> bool {
+		self.0 & 0x7C00 == 0x7C00 && self.0 & 0x3FF != 0
+	}
+}
+impl From<F16> for f32 {
+	fn from(value: F16) -> Self {
+		value.to_f32()
+	}
+}
+impl std::fmt::Display for F16 {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		write!(f, "{}", self.to_f32())
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::F16;
+
+	#[test]
+	fn test_f16_conversions() {
+		for (value, bits) in [
+			(0.0f32, 0x0000u16),
+			(-0.0, 0x8000),
+			(1.0, 0x3C00),
+			(-2.5, 0xC100),
+			(65504.0, 0x7BFF),
+			(f32::INFINITY, 0x7C00),
+			// smallest subnormal
+			(5.9604645e-8, 0x0001),
+		] {
+			assert_eq!(F16::from_f32(value).to_bits(), bits, "{}", value);
+			assert_eq!(F16::from_bits(bits).to_f32(), value);
+		}
+
+		// rounding to nearest, overflow to infinity and NaN
+		assert_eq!(F16::from_f32(1.0004883).to_bits(), 0x3C00);
+		assert_eq!(F16::from_f32(1.0009766).to_bits(), 0x3C01);
+		assert_eq!(F16::from_f32(1e6).to_bits(), 0x7C00);
+		assert!(F16::from_f32(f32::NAN).is_nan());
+		assert!(F16::from_f32(f32::NAN).to_f32().is_nan());
+	}
+}
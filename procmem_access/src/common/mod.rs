@@ -2,10 +2,24 @@
 
 use std::{convert::TryFrom, num::NonZeroU64};
 
+mod endianness;
+mod f16;
+mod name_pattern;
+
+pub use endianness::Endianness;
+pub use f16::F16;
+pub(crate) use name_pattern::select_one;
+pub use name_pattern::{FindError, NamePattern};
+
 /// Type to represent the offset of the address space.
 ///
 /// This is basically the native pointer type, and we also assume it cannot be null.
 #[derive(Debug, Copy, Clone, PartialEq, PartialOrd, Eq, Ord, Hash)]
+#[cfg_attr(
+	feature = "serde",
+	derive(serde::Serialize, serde::Deserialize),
+	serde(transparent)
+)]
 #[repr(transparent)]
 pub struct OffsetType(NonZeroU64);
 impl OffsetType {
@@ -27,6 +41,28 @@ impl OffsetType {
 
 		OffsetType(value)
 	}
+
+	/// Rounds the offset up to the nearest multiple of `alignment`.
+	///
+	/// Returns `None` on overflow. Panics if `alignment` is zero.
+	pub fn align_up(&self, alignment: u64) -> Option<OffsetType> {
+		let aligned = self.get().checked_next_multiple_of(alignment)?;
+
+		Some(OffsetType::new_unwrap(aligned))
+	}
+
+	/// Returns an iterator over offsets from `start` (inclusive) to `end` (exclusive) in increments of `step`.
+	///
+	/// Combine with [`align_up`](OffsetType::align_up) on `start` to only walk aligned offsets. Panics if `step` is zero.
+	pub fn iter_stride(start: OffsetType, end: OffsetType, step: u64) -> StrideIter {
+		assert!(step > 0, "stride step cannot be zero");
+
+		StrideIter {
+			next: start.get(),
+			end: end.get(),
+			step,
+		}
+	}
 }
 impl TryFrom<u64> for OffsetType {
 	type Error = std::num::TryFromIntError;
@@ -45,3 +81,78 @@ impl std::fmt::Display for OffsetType {
 		write!(f, "{:x}", self.get())
 	}
 }
+
+/// Iterator over offsets in a range with a fixed step.
+///
+/// This is constructed by [`OffsetType::iter_stride`].
+#[derive(Debug, Clone)]
+pub struct StrideIter {
+	next: u64,
+	end: u64,
+	step: u64,
+}
+impl Iterator for StrideIter {
+	type Item = OffsetType;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		if self.next >= self.end {
+			return None;
+		}
+
+		let current = OffsetType::new_unwrap(self.next);
+		// on overflow there is nothing more to yield
+		self.next = self.next.checked_add(self.step).unwrap_or(self.end);
+
+		Some(current)
+	}
+
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		let remaining = if self.next >= self.end {
+			0
+		} else {
+			(self.end - self.next).div_ceil(self.step)
+		};
+
+		match usize::try_from(remaining) {
+			Ok(remaining) => (remaining, Some(remaining)),
+			Err(_) => (usize::MAX, None),
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::OffsetType;
+
+	#[test]
+	fn test_offset_align_up() {
+		assert_eq!(
+			OffsetType::new_unwrap(13).align_up(8),
+			Some(OffsetType::new_unwrap(16))
+		);
+		assert_eq!(
+			OffsetType::new_unwrap(16).align_up(8),
+			Some(OffsetType::new_unwrap(16))
+		);
+		assert_eq!(OffsetType::new_unwrap(u64::MAX).align_up(8), None);
+	}
+
+	#[test]
+	fn test_offset_iter_stride() {
+		let start = OffsetType::new_unwrap(13).align_up(4).unwrap();
+		let iter = OffsetType::iter_stride(start, OffsetType::new_unwrap(29), 4);
+		assert_eq!(iter.size_hint(), (4, Some(4)));
+		assert_eq!(iter.map(|o| o.get()).collect::<Vec<_>>(), [16, 20, 24, 28]);
+
+		// end is exclusive
+		let iter = OffsetType::iter_stride(start, OffsetType::new_unwrap(28), 4);
+		assert_eq!(iter.count(), 3);
+
+		let iter = OffsetType::iter_stride(
+			OffsetType::new_unwrap(u64::MAX - 1),
+			OffsetType::new_unwrap(u64::MAX),
+			8,
+		);
+		assert_eq!(iter.map(|o| o.get()).collect::<Vec<_>>(), [u64::MAX - 1]);
+	}
+}
@@ -0,0 +1,176 @@
+use std::fmt::{Debug, Display};
+
+use thiserror::Error;
+
+use crate::error::{ErrorKind, ProcmemError};
+
+/// Pattern to match process names against.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NamePattern<'a> {
+	/// Name is equal to the pattern.
+	Exact(&'a str),
+	/// Name contains the pattern.
+	Substring(&'a str),
+	/// Name matches a glob where `*` matches any sequence of characters and `?` matches any single character.
+	Glob(&'a str),
+}
+impl<'a> NamePattern<'a> {
+	/// Interprets user input as a glob if it contains `*` or `?` and as a substring otherwise.
+	pub fn fuzzy(pattern: &'a str) -> Self {
+		if pattern.contains(['*', '?']) {
+			NamePattern::Glob(pattern)
+		} else {
+			NamePattern::Substring(pattern)
+		}
+	}
+
+	/// Returns the text of the pattern.
+	pub fn text(&self) -> &'a str {
+		match self {
+			NamePattern::Exact(text) | NamePattern::Substring(text) | NamePattern::Glob(text) => {
+				text
+			}
+		}
+	}
+
+	pub fn matches(&self, name: &str) -> bool {
+		match self {
+			NamePattern::Exact(pattern) => name == *pattern,
+			NamePattern::Substring(pattern) => name.contains(pattern),
+			NamePattern::Glob(pattern) => {
+				let pattern: Vec<char> = pattern.chars().collect();
+				let name: Vec<char> = name.chars().collect();
+
+				glob_matches(&pattern, &name)
+			}
+		}
+	}
+}
+
+/// Error returned when looking for exactly one process by name.
+#[derive(Debug, Error)]
+pub enum FindError<P: Debug + Display> {
+	#[error("could not list processes")]
+	List(#[from] std::io::Error),
+	#[error("no process matches the pattern")]
+	NotFound,
+	#[error(
+		"pattern matches more processes: {}",
+		.0.iter().map(ToString::to_string).collect::<Vec<_>>().join(", ")
+	)]
+	Ambiguous(Vec<P>),
+}
+impl<P: Debug + Display> FindError<P> {
+	pub fn kind(&self) -> ErrorKind {
+		match self {
+			FindError::List(err) => ErrorKind::from_io(err),
+			FindError::NotFound => ErrorKind::ProcessExited,
+			FindError::Ambiguous(_) => ErrorKind::Platform,
+		}
+	}
+}
+impl<P: Debug + Display + Send + Sync + 'static> From<FindError<P>> for ProcmemError {
+	fn from(err: FindError<P>) -> Self {
+		ProcmemError::new(err.kind(), err)
+	}
+}
+
+/// Picks the one process out of `found`, which are the processes matching `pattern`.
+///
+/// When more processes match, the ones named exactly like the pattern text are preferred.
+pub(crate) fn select_one<P: Debug + Display>(
+	pattern: NamePattern,
+	mut found: Vec<P>,
+	name: impl Fn(&P) -> &str,
+) -> Result<P, FindError<P>> {
+	let text = pattern.text();
+	if found.len() > 1 && found.iter().any(|process| name(process) == text) {
+		found.retain(|process| name(process) == text);
+	}
+
+	match found.len() {
+		0 => Err(FindError::NotFound),
+		1 => Ok(found.pop().unwrap()),
+		_ => Err(FindError::Ambiguous(found)),
+	}
+}
+
+/// Matches `name` against a glob `pattern` with backtracking on the last `*`.
+fn glob_matches(pattern: &[char], name: &[char]) -> bool {
+	let (mut p, mut n) = (0, 0);
+	// position after the last star in the pattern and the name position it currently matches up to
+	let mut star: Option<(usize, usize)> = None;
+
+	while n < name.len() {
+		match pattern.get(p) {
+			Some('*') => {
+				star = Some((p + 1, n));
+				p += 1;
+			}
+			Some(&c) if c == '?' || c == name[n] => {
+				p += 1;
+				n += 1;
+			}
+			_ => match star {
+				// let the last star consume one more character
+				Some((star_p, star_n)) => {
+					star = Some((star_p, star_n + 1));
+					p = star_p;
+					n = star_n + 1;
+				}
+				None => return false,
+			},
+		}
+	}
+
+	pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod test {
+	use super::{select_one, FindError, NamePattern};
+
+	#[test]
+	fn test_name_pattern() {
+		assert!(NamePattern::Exact("firefox").matches("firefox"));
+		assert!(!NamePattern::Exact("firefox").matches("firefox-bin"));
+
+		assert!(NamePattern::Substring("fox").matches("firefox-bin"));
+		assert!(!NamePattern::Substring("chrome").matches("firefox-bin"));
+
+		assert!(NamePattern::Glob("fire*").matches("firefox"));
+		assert!(NamePattern::Glob("*fox*").matches("firefox-bin"));
+		assert!(NamePattern::Glob("Web?ontent").matches("WebContent"));
+		assert!(NamePattern::Glob("*a*b").matches("xaxxab"));
+		assert!(NamePattern::Glob("*").matches(""));
+		assert!(!NamePattern::Glob("fire?").matches("firefox"));
+		assert!(!NamePattern::Glob("*a*b").matches("xaxxabc"));
+
+		assert_eq!(NamePattern::fuzzy("fox"), NamePattern::Substring("fox"));
+		assert_eq!(NamePattern::fuzzy("fire*"), NamePattern::Glob("fire*"));
+	}
+
+	#[test]
+	fn test_select_one() {
+		let select = |pattern: &str, names: &[&'static str]| {
+			select_one(NamePattern::fuzzy(pattern), names.to_vec(), |name| name)
+		};
+
+		assert_eq!(select("fox", &["firefox"]).unwrap(), "firefox");
+		// the exact name wins over longer names containing it
+		assert_eq!(
+			select("firefox", &["firefox", "firefox-bin"]).unwrap(),
+			"firefox"
+		);
+		assert!(matches!(select("fox", &[]), Err(FindError::NotFound)));
+		assert!(matches!(
+			select("fox", &["firefox", "firefox-bin"]),
+			Err(FindError::Ambiguous(candidates)) if candidates == ["firefox", "firefox-bin"]
+		));
+		// processes sharing the exact name stay ambiguous
+		assert!(matches!(
+			select("firefox", &["firefox", "firefox", "firefox-bin"]),
+			Err(FindError::Ambiguous(candidates)) if candidates == ["firefox", "firefox"]
+		));
+	}
+}
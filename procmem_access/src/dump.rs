@@ -0,0 +1,468 @@
+//! Snapshots of process memory stored on disk.
+//!
+//! [`DumpWriter`] writes the memory map of a process and the contents of its readable pages into a file,
+//! [`MemoryDump`] loads the file back as an offline [`MemoryMap`] and [`MemoryAccess`], so that the snapshot can be
+//! scanned and compared long after the process was resumed or exited.
+//!
+//! All numbers are little endian. The file has the following layout:
+//! ```text
+//! magic "PROCMEMD", version u32
+//! data      contents of all dumped blocks, back to back
+//! index     page count u64, pages, block count u64, blocks
+//! trailer   position of the index u64
+//! ```
+//! A page is `start u64, end u64, permissions u8, offset u64, type u8, path length u32, path bytes (UTF-8)`,
+//! a block is a contiguous dumped range `start u64, length u64, position of its data u64`.
+//! Parts of readable pages which fail to read are left out of the blocks.
+
+use std::{
+	fs::File,
+	io::{BufReader, Read, Seek, SeekFrom, Write},
+	path::{Path, PathBuf},
+};
+
+use thiserror::Error;
+
+use crate::{
+	common::OffsetType,
+	error::{impl_from_kinded_error, ErrorKind},
+	memory::{
+		access::{MemoryAccess, ReadError, WriteError},
+		map::{MemoryMap, MemoryPage, MemoryPagePermissions, MemoryPageType},
+	},
+};
+
+const MAGIC: [u8; 8] = *b"PROCMEMD";
+const VERSION: u32 = 1;
+
+#[derive(Debug, Error)]
+pub enum DumpError {
+	#[error("file is not a memory dump")]
+	InvalidMagic,
+	#[error("unsupported dump version {0}")]
+	UnsupportedVersion(u32),
+	#[error("dump is corrupted: {0}")]
+	Corrupted(&'static str),
+	#[error("could not access dump file")]
+	Io(#[from] std::io::Error),
+}
+impl DumpError {
+	pub fn kind(&self) -> ErrorKind {
+		match self {
+			DumpError::Io(err) => ErrorKind::from_io(err),
+			_ => ErrorKind::Parse,
+		}
+	}
+}
+impl_from_kinded_error!(DumpError);
+
+/// Contiguous range of memory stored in a dump.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DumpBlock {
+	pub address_range: [OffsetType; 2],
+	/// Position of the first byte in the dump file.
+	position: u64,
+}
+impl DumpBlock {
+	pub const fn size(&self) -> u64 {
+		self.address_range[1].get() - self.address_range[0].get()
+	}
+}
+
+/// Writes memory dumps, see the [module documentation](self) for the format.
+#[derive(Debug, Clone)]
+pub struct DumpWriter {
+	chunk_size: usize,
+}
+impl DumpWriter {
+	/// Default value of [`chunk_size`](DumpWriter::chunk_size).
+	pub const DEFAULT_CHUNK_SIZE: usize = 1 << 20;
+
+	pub fn new() -> Self {
+		DumpWriter {
+			chunk_size: Self::DEFAULT_CHUNK_SIZE,
+		}
+	}
+
+	/// Sets the number of bytes read from the process at once.
+	///
+	/// ## Panics
+	/// * If `chunk_size` is zero.
+	pub fn with_chunk_size(mut self, chunk_size: usize) -> Self {
+		assert!(chunk_size > 0);
+
+		self.chunk_size = chunk_size;
+		self
+	}
+
+	pub const fn chunk_size(&self) -> usize {
+		self.chunk_size
+	}
+
+	/// Writes all pages of `map` and the contents of the readable ones into `writer`, returning the number of dumped bytes.
+	///
+	/// The writer is not buffered by this method.
+	///
+	/// ## Safety
+	/// * See [`MemoryAccess::read_partial`].
+	pub unsafe fn write<M: MemoryMap + ?Sized, A: MemoryAccess + ?Sized>(
+		&self,
+		mut writer: impl Write,
+		map: &M,
+		access: &mut A,
+	) -> Result<u64, DumpError> {
+		writer.write_all(&MAGIC)?;
+		writer.write_all(&VERSION.to_le_bytes())?;
+		let mut position = (MAGIC.len() + 4) as u64;
+
+		let mut blocks: Vec<DumpBlock> = Vec::new();
+		let mut buffer = Vec::new();
+		for page in map.pages().iter().filter(|page| page.permissions.read()) {
+			let mut offset = page.start();
+			while offset < page.end() {
+				let length = (page.end().get() - offset.get()).min(self.chunk_size as u64);
+				buffer.resize(length as usize, 0);
+
+				let (read, next) = match access.read_partial(offset, &mut buffer) {
+					Ok(()) => (buffer.len(), offset.saturating_add(length)),
+					Err(err) => (err.read, err.unreadable[1]),
+				};
+
+				if read > 0 {
+					writer.write_all(&buffer[..read])?;
+
+					let end = offset.saturating_add(read as u64);
+					match blocks.last_mut() {
+						Some(block) if block.address_range[1] == offset => {
+							block.address_range[1] = end
+						}
+						_ => blocks.push(DumpBlock {
+							address_range: [offset, end],
+							position,
+						}),
+					}
+					position += read as u64;
+				}
+				offset = next;
+			}
+		}
+
+		let index_position = position;
+		writer.write_all(&(map.pages().len() as u64).to_le_bytes())?;
+		for page in map.pages() {
+			write_page(&mut writer, page)?;
+		}
+		writer.write_all(&(blocks.len() as u64).to_le_bytes())?;
+		for block in blocks.iter() {
+			writer.write_all(&block.address_range[0].get().to_le_bytes())?;
+			writer.write_all(&block.size().to_le_bytes())?;
+			writer.write_all(&block.position.to_le_bytes())?;
+		}
+		writer.write_all(&index_position.to_le_bytes())?;
+
+		Ok(index_position - (MAGIC.len() + 4) as u64)
+	}
+}
+impl Default for DumpWriter {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+fn write_page(writer: &mut impl Write, page: &MemoryPage) -> Result<(), DumpError> {
+	let permissions = [
+		(page.permissions.read(), MemoryPagePermissions::MASK_READ),
+		(page.permissions.write(), MemoryPagePermissions::MASK_WRITE),
+		(page.permissions.exec(), MemoryPagePermissions::MASK_EXEC),
+		(page.permissions.shared(), MemoryPagePermissions::MASK_SHARE),
+	]
+	.iter()
+	.filter(|(set, _)| *set)
+	.fold(0u8, |bits, (_, mask)| bits | mask);
+
+	let (page_type, path) = match &page.page_type {
+		MemoryPageType::Unknown => (0u8, None),
+		MemoryPageType::Stack => (1, None),
+		MemoryPageType::Heap => (2, None),
+		MemoryPageType::Anon => (3, None),
+		MemoryPageType::ProcessExecutable(path) => (4, Some(path.to_string_lossy())),
+		MemoryPageType::File(path) => (5, Some(path.to_string_lossy())),
+	};
+	let path = path.unwrap_or_default();
+
+	writer.write_all(&page.start().get().to_le_bytes())?;
+	writer.write_all(&page.end().get().to_le_bytes())?;
+	writer.write_all(&[permissions])?;
+	writer.write_all(&page.offset.to_le_bytes())?;
+	writer.write_all(&[page_type])?;
+	writer.write_all(&(path.len() as u32).to_le_bytes())?;
+	writer.write_all(path.as_bytes())?;
+
+	Ok(())
+}
+
+fn read_u8(reader: &mut impl Read) -> Result<u8, DumpError> {
+	let mut bytes = [0u8; 1];
+	reader.read_exact(&mut bytes)?;
+
+	Ok(bytes[0])
+}
+
+fn read_u32(reader: &mut impl Read) -> Result<u32, DumpError> {
+	let mut bytes = [0u8; 4];
+	reader.read_exact(&mut bytes)?;
+
+	Ok(u32::from_le_bytes(bytes))
+}
+
+fn read_u64(reader: &mut impl Read) -> Result<u64, DumpError> {
+	let mut bytes = [0u8; 8];
+	reader.read_exact(&mut bytes)?;
+
+	Ok(u64::from_le_bytes(bytes))
+}
+
+fn read_offset(reader: &mut impl Read) -> Result<OffsetType, DumpError> {
+	OffsetType::new(read_u64(reader)?).ok_or(DumpError::Corrupted("zero address"))
+}
+
+fn read_page(reader: &mut impl Read) -> Result<MemoryPage, DumpError> {
+	let start = read_offset(reader)?;
+	let end = read_offset(reader)?;
+	if end < start {
+		return Err(DumpError::Corrupted("page ends before it starts"));
+	}
+
+	let bits = read_u8(reader)?;
+	let permissions = MemoryPagePermissions::new(
+		bits & MemoryPagePermissions::MASK_READ != 0,
+		bits & MemoryPagePermissions::MASK_WRITE != 0,
+		bits & MemoryPagePermissions::MASK_EXEC != 0,
+		bits & MemoryPagePermissions::MASK_SHARE != 0,
+	);
+	let offset = read_u64(reader)?;
+
+	let page_type = read_u8(reader)?;
+	let mut path = vec![0u8; read_u32(reader)? as usize];
+	reader.read_exact(&mut path)?;
+	let path = || -> Result<PathBuf, DumpError> {
+		String::from_utf8(path)
+			.map(PathBuf::from)
+			.map_err(|_| DumpError::Corrupted("path is not UTF-8"))
+	};
+	let page_type = match page_type {
+		0 => MemoryPageType::Unknown,
+		1 => MemoryPageType::Stack,
+		2 => MemoryPageType::Heap,
+		3 => MemoryPageType::Anon,
+		4 => MemoryPageType::ProcessExecutable(path()?),
+		5 => MemoryPageType::File(path()?),
+		_ => return Err(DumpError::Corrupted("unknown page type")),
+	};
+
+	Ok(MemoryPage {
+		address_range: [start, end],
+		permissions,
+		offset,
+		page_type,
+	})
+}
+
+/// Memory dump loaded from a file, serving as the memory map and memory of the dumped process.
+///
+/// Only the index is loaded into memory, reads are served from the underlying reader.
+/// The dump is read-only, all writes fail with [`WriteError::NotPermitted`].
+#[derive(Debug)]
+pub struct MemoryDump<R: Read + Seek = BufReader<File>> {
+	reader: R,
+	pages: Vec<MemoryPage>,
+	/// Blocks sorted by start address, not overlapping.
+	blocks: Vec<DumpBlock>,
+}
+impl MemoryDump {
+	/// Opens the dump file at `path`.
+	pub fn open(path: impl AsRef<Path>) -> Result<Self, DumpError> {
+		Self::new(BufReader::new(File::open(path)?))
+	}
+}
+impl<R: Read + Seek> MemoryDump<R> {
+	/// Loads the index of the dump stored in `reader`.
+	pub fn new(mut reader: R) -> Result<Self, DumpError> {
+		let mut magic = [0u8; 8];
+		reader.read_exact(&mut magic)?;
+		if magic != MAGIC {
+			return Err(DumpError::InvalidMagic);
+		}
+		match read_u32(&mut reader)? {
+			VERSION => (),
+			version => return Err(DumpError::UnsupportedVersion(version)),
+		}
+
+		reader.seek(SeekFrom::End(-8))?;
+		let index_position = read_u64(&mut reader)?;
+		reader.seek(SeekFrom::Start(index_position))?;
+
+		let page_count = read_u64(&mut reader)?;
+		let pages = (0..page_count)
+			.map(|_| read_page(&mut reader))
+			.collect::<Result<Vec<_>, _>>()?;
+
+		let block_count = read_u64(&mut reader)?;
+		let mut blocks: Vec<DumpBlock> = Vec::new();
+		for _ in 0..block_count {
+			let start = read_offset(&mut reader)?;
+			let length = read_u64(&mut reader)?;
+			let position = read_u64(&mut reader)?;
+
+			let end = start
+				.get()
+				.checked_add(length)
+				.and_then(OffsetType::new)
+				.ok_or(DumpError::Corrupted("block overflows the address space"))?;
+			if position.saturating_add(length) > index_position {
+				return Err(DumpError::Corrupted("block data overlaps the index"));
+			}
+			if matches!(blocks.last(), Some(last) if last.address_range[1] > start) {
+				return Err(DumpError::Corrupted("blocks are not sorted"));
+			}
+
+			blocks.push(DumpBlock {
+				address_range: [start, end],
+				position,
+			});
+		}
+
+		Ok(MemoryDump {
+			reader,
+			pages,
+			blocks,
+		})
+	}
+
+	/// Returns the dumped ranges sorted by address.
+	pub fn blocks(&self) -> &[DumpBlock] {
+		&self.blocks
+	}
+
+	pub fn into_inner(self) -> R {
+		self.reader
+	}
+}
+impl<R: Read + Seek> MemoryMap for MemoryDump<R> {
+	fn pages(&self) -> &[MemoryPage] {
+		&self.pages
+	}
+}
+impl<R: Read + Seek> MemoryAccess for MemoryDump<R> {
+	unsafe fn read(&mut self, offset: OffsetType, buffer: &mut [u8]) -> Result<(), ReadError> {
+		let mut index = self
+			.blocks
+			.partition_point(|block| block.address_range[1] <= offset);
+
+		// the read may span adjacent blocks
+		let mut offset = offset.get();
+		let mut buffer = buffer;
+		while !buffer.is_empty() {
+			let block = match self.blocks.get(index) {
+				Some(block) if block.address_range[0].get() <= offset => *block,
+				_ => return Err(ReadError::NotMapped),
+			};
+
+			let skip = offset - block.address_range[0].get();
+			let length = ((block.size() - skip) as usize).min(buffer.len());
+			let (part, rest) = buffer.split_at_mut(length);
+
+			self.reader.seek(SeekFrom::Start(block.position + skip))?;
+			self.reader.read_exact(part)?;
+
+			buffer = rest;
+			offset += length as u64;
+			index += 1;
+		}
+
+		Ok(())
+	}
+
+	unsafe fn write(&mut self, _offset: OffsetType, _data: &[u8]) -> Result<(), WriteError> {
+		Err(WriteError::NotPermitted)
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use std::io::Cursor;
+
+	use super::{DumpError, DumpWriter, MemoryDump};
+	use crate::{
+		common::OffsetType,
+		memory::{
+			access::{MemoryAccess, ReadError},
+			map::{MemoryMap, MemoryPagePermissions, MemoryPageType},
+		},
+		platform::mock::{MockMemoryAccess, MockMemoryMap},
+	};
+
+	#[test]
+	fn test_dump_roundtrip() {
+		let offset = |value: u64| OffsetType::new_unwrap(value);
+
+		let data: Vec<u8> = (0..=255).cycle().take(0x3000).collect();
+		let mut access = MockMemoryAccess::new()
+			.with_region(offset(0x10000), data.clone())
+			.with_region(offset(0x20000), vec![7; 0x100])
+			.with_fault([offset(0x11000), offset(0x12000)]);
+		let map = MockMemoryMap::new()
+			.with_page(
+				[offset(0x10000), offset(0x13000)],
+				MemoryPagePermissions::new(true, true, false, false),
+				MemoryPageType::File("/usr/lib/libc.so.6".into()),
+			)
+			.with_page(
+				[offset(0x20000), offset(0x20100)],
+				MemoryPagePermissions::new(false, false, false, false),
+				MemoryPageType::Anon,
+			);
+
+		let mut file = Vec::new();
+		let dumped = unsafe {
+			DumpWriter::new()
+				.with_chunk_size(0x1800)
+				.write(&mut file, &map, &mut access)
+				.unwrap()
+		};
+		assert_eq!(dumped, 0x2000);
+
+		let mut dump = MemoryDump::new(Cursor::new(file.as_slice())).unwrap();
+		assert_eq!(dump.pages(), map.pages());
+		let blocks: Vec<_> = dump
+			.blocks()
+			.iter()
+			.map(|block| (block.address_range[0].get(), block.size()))
+			.collect();
+		assert_eq!(blocks, [(0x10000, 0x1000), (0x12000, 0x1000)]);
+
+		let mut buffer = vec![0u8; 0x20];
+		unsafe {
+			dump.read(offset(0x12ff0), &mut buffer[..0x10]).unwrap();
+			assert_eq!(buffer[..0x10], data[0x2ff0..]);
+			dump.read(offset(0x10010), &mut buffer).unwrap();
+			assert_eq!(buffer, data[0x10..0x30]);
+
+			assert!(matches!(
+				dump.read(offset(0x10ff0), &mut buffer),
+				Err(ReadError::NotMapped)
+			));
+			assert!(matches!(
+				dump.read(offset(0x20000), &mut buffer),
+				Err(ReadError::NotMapped)
+			));
+			assert!(dump.write(offset(0x10000), &[1]).is_err());
+		}
+
+		file[0] = b'X';
+		assert!(matches!(
+			MemoryDump::new(Cursor::new(file.as_slice())),
+			Err(DumpError::InvalidMagic)
+		));
+	}
+}
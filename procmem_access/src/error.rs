@@ -0,0 +1,223 @@
+//! Crate-level error type.
+//!
+//! Each module defines its own error enums which describe the failure in detail. Code that only needs to
+//! know what kind of failure happened (for example to pick an RPC error code or an exception class) can convert
+//! any of them into [`ProcmemError`] and inspect its [`kind`](ProcmemError::kind).
+
+use thiserror::Error;
+
+/// Stable classification of errors produced by this library.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum ErrorKind {
+	/// The operation was denied by the operating system or by page permissions.
+	PermissionDenied,
+	/// The target process does not exist (anymore).
+	ProcessExited,
+	/// The requested memory range is not mapped in the target process.
+	NotMapped,
+	/// Data provided by the platform could not be parsed.
+	Parse,
+	/// Any other platform specific failure.
+	Platform,
+}
+impl ErrorKind {
+	/// Classifies an io error based on the raw os error code, falling back to [`std::io::ErrorKind`].
+	pub fn from_io(err: &std::io::Error) -> Self {
+		match err.raw_os_error().and_then(Self::from_os_error) {
+			Some(kind) => kind,
+			None => match err.kind() {
+				std::io::ErrorKind::PermissionDenied => ErrorKind::PermissionDenied,
+				std::io::ErrorKind::NotFound => ErrorKind::ProcessExited,
+				std::io::ErrorKind::InvalidData => ErrorKind::Parse,
+				_ => ErrorKind::Platform,
+			},
+		}
+	}
+
+	#[cfg(unix)]
+	fn from_os_error(code: i32) -> Option<Self> {
+		match code {
+			libc::EPERM | libc::EACCES => Some(ErrorKind::PermissionDenied),
+			libc::ESRCH | libc::ENOENT => Some(ErrorKind::ProcessExited),
+			libc::EIO | libc::EFAULT => Some(ErrorKind::NotMapped),
+			_ => None,
+		}
+	}
+
+	#[cfg(target_os = "windows")]
+	fn from_os_error(code: i32) -> Option<Self> {
+		use windows_sys::Win32::Foundation::{
+			ERROR_ACCESS_DENIED, ERROR_INVALID_PARAMETER, ERROR_PARTIAL_COPY,
+		};
+
+		match code as u32 {
+			ERROR_ACCESS_DENIED => Some(ErrorKind::PermissionDenied),
+			// OpenProcess fails with this for pids which do not exist
+			ERROR_INVALID_PARAMETER => Some(ErrorKind::ProcessExited),
+			ERROR_PARTIAL_COPY => Some(ErrorKind::NotMapped),
+			_ => None,
+		}
+	}
+
+	#[cfg(not(any(unix, target_os = "windows")))]
+	fn from_os_error(_code: i32) -> Option<Self> {
+		None
+	}
+
+	/// Classifies an arbitrary error by looking for known errors in its source chain.
+	///
+	/// Returns [`ErrorKind::Platform`] if nothing more specific is found.
+	pub fn from_error(err: &(dyn std::error::Error + 'static)) -> Self {
+		let mut current = Some(err);
+		while let Some(err) = current {
+			if let Some(err) = err.downcast_ref::<ProcmemError>() {
+				return err.kind();
+			}
+			if let Some(err) = err.downcast_ref::<std::io::Error>() {
+				return Self::from_io(err);
+			}
+			if err.is::<std::num::ParseIntError>() {
+				return ErrorKind::Parse;
+			}
+
+			current = err.source();
+		}
+
+		ErrorKind::Platform
+	}
+}
+impl std::fmt::Display for ErrorKind {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		match self {
+			ErrorKind::PermissionDenied => write!(f, "permission denied"),
+			ErrorKind::ProcessExited => write!(f, "process exited"),
+			ErrorKind::NotMapped => write!(f, "memory not mapped"),
+			ErrorKind::Parse => write!(f, "parse error"),
+			ErrorKind::Platform => write!(f, "platform error"),
+		}
+	}
+}
+
+/// Error type unifying all errors of this library.
+///
+/// The original error is preserved as the [`source`](std::error::Error::source) of this error.
+#[derive(Debug, Error)]
+#[error("{kind}")]
+pub struct ProcmemError {
+	kind: ErrorKind,
+	#[source]
+	source: Box<dyn std::error::Error + Send + Sync + 'static>,
+}
+impl ProcmemError {
+	pub fn new(
+		kind: ErrorKind,
+		source: impl Into<Box<dyn std::error::Error + Send + Sync>>,
+	) -> Self {
+		ProcmemError {
+			kind,
+			source: source.into(),
+		}
+	}
+
+	pub const fn kind(&self) -> ErrorKind {
+		self.kind
+	}
+
+	/// Returns a reference to the original error.
+	pub fn get_ref(&self) -> &(dyn std::error::Error + Send + Sync + 'static) {
+		self.source.as_ref()
+	}
+
+	/// Consumes self and returns the original error.
+	pub fn into_inner(self) -> Box<dyn std::error::Error + Send + Sync + 'static> {
+		self.source
+	}
+}
+
+/// Implements `From<$error> for ProcmemError` for errors that have a `kind()` method.
+macro_rules! impl_from_kinded_error {
+	(
+		$( $error: ty ),+ $(,)?
+	) => {
+		$(
+			impl From<$error> for $crate::error::ProcmemError {
+				fn from(err: $error) -> Self {
+					$crate::error::ProcmemError::new(err.kind(), err)
+				}
+			}
+		)+
+	};
+}
+pub(crate) use impl_from_kinded_error;
+
+impl_from_kinded_error! {
+	crate::memory::access::ReadError,
+	crate::memory::access::WriteError,
+	crate::memory::access::PartialReadError,
+	crate::memory::lock::LockError,
+	crate::memory::lock::UnlockError,
+}
+impl From<std::io::Error> for ProcmemError {
+	fn from(err: std::io::Error) -> Self {
+		ProcmemError::new(ErrorKind::from_io(&err), err)
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::{ErrorKind, ProcmemError};
+	use crate::memory::{access::ReadError, lock::LockError};
+
+	#[test]
+	fn test_error_kind_from_io() {
+		#[cfg(unix)]
+		let known = [
+			(libc::EPERM, ErrorKind::PermissionDenied),
+			(libc::ESRCH, ErrorKind::ProcessExited),
+			(libc::EIO, ErrorKind::NotMapped),
+		];
+		#[cfg(target_os = "windows")]
+		let known = [
+			(
+				windows_sys::Win32::Foundation::ERROR_ACCESS_DENIED as i32,
+				ErrorKind::PermissionDenied,
+			),
+			(
+				windows_sys::Win32::Foundation::ERROR_INVALID_PARAMETER as i32,
+				ErrorKind::ProcessExited,
+			),
+			(
+				windows_sys::Win32::Foundation::ERROR_PARTIAL_COPY as i32,
+				ErrorKind::NotMapped,
+			),
+		];
+		for (code, kind) in known {
+			assert_eq!(
+				ErrorKind::from_io(&std::io::Error::from_raw_os_error(code)),
+				kind
+			);
+		}
+
+		assert_eq!(
+			ErrorKind::from_io(&std::io::Error::other("other")),
+			ErrorKind::Platform
+		);
+	}
+
+	#[test]
+	fn test_procmem_error_source_chain() {
+		let err: ProcmemError = ReadError::Io(std::io::Error::from_raw_os_error(libc::EIO)).into();
+		assert_eq!(err.kind(), ErrorKind::NotMapped);
+
+		let source = std::error::Error::source(&err).unwrap();
+		assert!(source.is::<ReadError>());
+		assert!(std::error::Error::source(source)
+			.unwrap()
+			.is::<std::io::Error>());
+
+		let err: ProcmemError =
+			LockError::PlatformError(Box::new(std::io::Error::from_raw_os_error(libc::ESRCH)))
+				.into();
+		assert_eq!(err.kind(), ErrorKind::ProcessExited);
+	}
+}
@@ -0,0 +1,413 @@
+//! Rendering raw bytes as text and parsing text back into bytes.
+//!
+//! The REPL, the Python bindings and the JSON RPC server all display memory to users and accept values from them.
+//! A [`ValueFormatter`] does both for one type, so that the frontends agree on the syntax:
+//! * integers are decimal or hex with a `0x` prefix, hex is parsed as the bit pattern of the type, so `0xFFFF` is `-1i16`
+//! * floats are decimal, or their bit pattern in hex with a `0x` prefix
+//! * pointers are always hex, the prefix is optional when parsing
+//! * bytes are hex pairs, optionally separated by whitespace
+//! * strings are quoted and escaped like Rust strings, bytes which are not UTF-8 are written as `\xNN`
+
+use std::{fmt::Write, str::FromStr};
+
+use thiserror::Error;
+
+use crate::{
+	common::{Endianness, F16},
+	error::{impl_from_kinded_error, ErrorKind},
+};
+
+use super::{FieldType, PrimitiveType};
+
+#[derive(Debug, Error)]
+pub enum ValueFormatError {
+	#[error("unknown value type \"{0}\"")]
+	UnknownType(String),
+	#[error("invalid {value_type} value \"{value}\"")]
+	InvalidValue { value: String, value_type: String },
+	#[error("expected {expected} bytes, got {actual}")]
+	InvalidLength { expected: usize, actual: usize },
+	#[error("structures and arrays cannot be parsed")]
+	Unsupported,
+}
+impl ValueFormatError {
+	pub fn kind(&self) -> ErrorKind {
+		ErrorKind::Parse
+	}
+}
+impl_from_kinded_error!(ValueFormatError);
+
+impl FromStr for PrimitiveType {
+	type Err = ValueFormatError;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		Ok(match s {
+			"u8" => PrimitiveType::U8,
+			"i8" => PrimitiveType::I8,
+			"u16" => PrimitiveType::U16,
+			"i16" => PrimitiveType::I16,
+			"u32" => PrimitiveType::U32,
+			"i32" => PrimitiveType::I32,
+			"u64" => PrimitiveType::U64,
+			"i64" => PrimitiveType::I64,
+			"f16" => PrimitiveType::F16,
+			"f32" => PrimitiveType::F32,
+			"f64" => PrimitiveType::F64,
+			"bool" => PrimitiveType::Bool,
+			"ptr" => PrimitiveType::Pointer,
+			_ => return Err(ValueFormatError::UnknownType(s.to_string())),
+		})
+	}
+}
+
+/// Formats and parses values of one type.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValueFormatter {
+	ty: FieldType,
+	endianness: Endianness,
+	hex: bool,
+}
+impl ValueFormatter {
+	/// Creates a formatter of native byte order which writes numbers in decimal.
+	pub fn new(ty: impl Into<FieldType>) -> Self {
+		ValueFormatter {
+			ty: ty.into(),
+			endianness: Endianness::NATIVE,
+			hex: false,
+		}
+	}
+
+	/// Sets the byte order of primitives.
+	pub fn with_endianness(mut self, endianness: Endianness) -> Self {
+		self.endianness = endianness;
+
+		self
+	}
+
+	/// Writes numbers as their bit pattern in hex, parsing accepts both forms regardless.
+	pub fn with_hex(mut self, hex: bool) -> Self {
+		self.hex = hex;
+
+		self
+	}
+
+	pub fn ty(&self) -> &FieldType {
+		&self.ty
+	}
+
+	/// Number of bytes of one value.
+	pub fn size(&self) -> usize {
+		self.ty.size()
+	}
+
+	/// Renders the value at the start of `bytes`.
+	pub fn format(&self, bytes: &[u8]) -> Result<String, ValueFormatError> {
+		if bytes.len() < self.size() {
+			return Err(ValueFormatError::InvalidLength {
+				expected: self.size(),
+				actual: bytes.len(),
+			});
+		}
+
+		Ok(match &self.ty {
+			FieldType::Primitive(PrimitiveType::Bool) => (bytes[0] != 0).to_string(),
+			FieldType::Primitive(primitive) if self.hex => {
+				let mut scalar = bytes[..primitive.size()].to_vec();
+				self.endianness.convert_scalar(&mut scalar);
+				// the native order of the bits, most significant digit first
+				if Endianness::NATIVE == Endianness::Little {
+					scalar.reverse();
+				}
+
+				scalar.iter().fold(String::from("0x"), |mut text, byte| {
+					let _ = write!(text, "{:02X}", byte);
+					text
+				})
+			}
+			FieldType::CString(len) => {
+				let bytes = &bytes[..*len];
+				let end = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+
+				escape_string(&bytes[..end])
+			}
+			ty => ty.decode_as(bytes, self.endianness).to_string(),
+		})
+	}
+
+	/// Parses `text` into the bytes of a value.
+	///
+	/// Strings are nul-terminated and must be shorter than the field, they are not padded to its length.
+	pub fn parse(&self, text: &str) -> Result<Vec<u8>, ValueFormatError> {
+		let text = text.trim();
+		let invalid = || ValueFormatError::InvalidValue {
+			value: text.to_string(),
+			value_type: self.type_name(),
+		};
+
+		let bytes = match &self.ty {
+			FieldType::Primitive(primitive) => {
+				let mut bytes = parse_primitive(*primitive, text).ok_or_else(invalid)?;
+				self.endianness.convert_scalar(&mut bytes);
+
+				bytes
+			}
+			FieldType::Bytes(len) => {
+				let digits = text
+					.chars()
+					.filter(|ch| !ch.is_whitespace())
+					.collect::<Vec<_>>();
+				if digits.len() % 2 != 0 {
+					return Err(invalid());
+				}
+				let bytes = digits
+					.chunks(2)
+					.map(|pair| u8::from_str_radix(&pair.iter().collect::<String>(), 16).ok())
+					.collect::<Option<Vec<u8>>>()
+					.ok_or_else(invalid)?;
+
+				if bytes.len() != *len {
+					return Err(ValueFormatError::InvalidLength {
+						expected: *len,
+						actual: bytes.len(),
+					});
+				}
+
+				bytes
+			}
+			FieldType::CString(len) => {
+				let mut bytes = unescape_string(text).ok_or_else(invalid)?;
+				if bytes.len() >= *len {
+					return Err(ValueFormatError::InvalidLength {
+						expected: *len,
+						actual: bytes.len() + 1,
+					});
+				}
+				bytes.push(0);
+
+				bytes
+			}
+			FieldType::Struct(_) | FieldType::Array { .. } => {
+				return Err(ValueFormatError::Unsupported)
+			}
+		};
+
+		Ok(bytes)
+	}
+
+	fn type_name(&self) -> String {
+		match &self.ty {
+			FieldType::Primitive(primitive) => primitive.to_string(),
+			FieldType::Bytes(len) => format!("bytes[{}]", len),
+			FieldType::CString(len) => format!("string[{}]", len),
+			FieldType::Struct(def) => def.name.clone(),
+			FieldType::Array { .. } => "array".to_string(),
+		}
+	}
+}
+/// Parses type tags such as `i32`, `ptr`, `bytes[16]` and `string[32]`.
+impl FromStr for ValueFormatter {
+	type Err = ValueFormatError;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let s = s.trim();
+		let sized = |prefix: &str| {
+			s.strip_prefix(prefix)?
+				.strip_prefix('[')?
+				.strip_suffix(']')?
+				.parse::<usize>()
+				.ok()
+				.filter(|&len| len > 0)
+		};
+
+		let ty = if let Some(len) = sized("bytes") {
+			FieldType::Bytes(len)
+		} else if let Some(len) = sized("string") {
+			FieldType::CString(len)
+		} else {
+			s.parse::<PrimitiveType>()?.into()
+		};
+
+		Ok(ValueFormatter::new(ty))
+	}
+}
+
+/// Parses a primitive into native byte order.
+fn parse_primitive(primitive: PrimitiveType, text: &str) -> Option<Vec<u8>> {
+	let hex = text.strip_prefix("0x").or_else(|| text.strip_prefix("0X"));
+
+	macro_rules! parse {
+		($ty: ty, $bits: ty) => {
+			match hex {
+				Some(hex) => <$bits>::from_str_radix(hex, 16)
+					.ok()?
+					.to_ne_bytes()
+					.to_vec(),
+				None => text.parse::<$ty>().ok()?.to_ne_bytes().to_vec(),
+			}
+		};
+	}
+
+	Some(match primitive {
+		PrimitiveType::U8 => parse!(u8, u8),
+		PrimitiveType::I8 => parse!(i8, u8),
+		PrimitiveType::U16 => parse!(u16, u16),
+		PrimitiveType::I16 => parse!(i16, u16),
+		PrimitiveType::U32 => parse!(u32, u32),
+		PrimitiveType::I32 => parse!(i32, u32),
+		PrimitiveType::U64 => parse!(u64, u64),
+		PrimitiveType::I64 => parse!(i64, u64),
+		PrimitiveType::F16 => match hex {
+			Some(hex) => u16::from_str_radix(hex, 16).ok()?.to_ne_bytes().to_vec(),
+			None => F16::from_f32(text.parse().ok()?).to_ne_bytes().to_vec(),
+		},
+		PrimitiveType::F32 => parse!(f32, u32),
+		PrimitiveType::F64 => parse!(f64, u64),
+		PrimitiveType::Bool => match text {
+			"true" | "1" => vec![1],
+			"false" | "0" => vec![0],
+			_ => return None,
+		},
+		PrimitiveType::Pointer => usize::from_str_radix(hex.unwrap_or(text), 16)
+			.ok()?
+			.to_ne_bytes()
+			.to_vec(),
+	})
+}
+
+/// Quotes and escapes `bytes`, writing bytes which are not valid UTF-8 as `\xNN`.
+fn escape_string(bytes: &[u8]) -> String {
+	let mut text = String::from("\"");
+	for chunk in bytes.utf8_chunks() {
+		for ch in chunk.valid().chars() {
+			match ch {
+				'"' => text.push_str("\\\""),
+				'\\' => text.push_str("\\\\"),
+				'\n' => text.push_str("\\n"),
+				'\r' => text.push_str("\\r"),
+				'\t' => text.push_str("\\t"),
+				ch if ch.is_control() => {
+					let _ = write!(text, "\\u{{{:x}}}", ch as u32);
+				}
+				ch => text.push(ch),
+			}
+		}
+		for byte in chunk.invalid() {
+			let _ = write!(text, "\\x{:02X}", byte);
+		}
+	}
+	text.push('"');
+
+	text
+}
+
+/// Reverses [`escape_string`], the quotes are optional.
+fn unescape_string(text: &str) -> Option<Vec<u8>> {
+	let text = text
+		.strip_prefix('"')
+		.and_then(|text| text.strip_suffix('"'))
+		.unwrap_or(text);
+
+	let mut bytes = Vec::with_capacity(text.len());
+	let mut chars = text.chars();
+	while let Some(ch) = chars.next() {
+		if ch != '\\' {
+			let mut buffer = [0u8; 4];
+			bytes.extend_from_slice(ch.encode_utf8(&mut buffer).as_bytes());
+			continue;
+		}
+
+		match chars.next()? {
+			'"' => bytes.push(b'"'),
+			'\'' => bytes.push(b'\''),
+			'\\' => bytes.push(b'\\'),
+			'n' => bytes.push(b'\n'),
+			'r' => bytes.push(b'\r'),
+			't' => bytes.push(b'\t'),
+			'0' => bytes.push(0),
+			'x' => {
+				let digits: String = chars.by_ref().take(2).collect();
+				if digits.len() != 2 {
+					return None;
+				}
+				bytes.push(u8::from_str_radix(&digits, 16).ok()?);
+			}
+			'u' => {
+				if chars.next()? != '{' {
+					return None;
+				}
+				let digits: String = chars.by_ref().take_while(|&ch| ch != '}').collect();
+				let ch = char::from_u32(u32::from_str_radix(&digits, 16).ok()?)?;
+
+				let mut buffer = [0u8; 4];
+				bytes.extend_from_slice(ch.encode_utf8(&mut buffer).as_bytes());
+			}
+			_ => return None,
+		}
+	}
+
+	Some(bytes)
+}
+
+#[cfg(test)]
+mod test {
+	use super::ValueFormatter;
+	use crate::{
+		common::Endianness,
+		layout::{FieldType, PrimitiveType},
+	};
+
+	#[test]
+	fn test_value_formatter() {
+		let i16_formatter: ValueFormatter = "i16".parse().unwrap();
+		assert_eq!(i16_formatter.parse("-2").unwrap(), (-2i16).to_ne_bytes());
+		assert_eq!(
+			i16_formatter.parse("0xFFFE").unwrap(),
+			(-2i16).to_ne_bytes()
+		);
+		assert_eq!(i16_formatter.format(&(-2i16).to_ne_bytes()).unwrap(), "-2");
+		assert_eq!(
+			i16_formatter
+				.clone()
+				.with_hex(true)
+				.format(&(-2i16).to_ne_bytes())
+				.unwrap(),
+			"0xFFFE"
+		);
+		assert!(i16_formatter.parse("70000").is_err());
+		assert!(i16_formatter.format(&[1]).is_err());
+
+		let big = ValueFormatter::new(PrimitiveType::U32).with_endianness(Endianness::Big);
+		assert_eq!(big.parse("0xCAFEBABE").unwrap(), [0xCA, 0xFE, 0xBA, 0xBE]);
+		assert_eq!(
+			big.with_hex(true)
+				.format(&[0xCA, 0xFE, 0xBA, 0xBE])
+				.unwrap(),
+			"0xCAFEBABE"
+		);
+
+		let f32_formatter = ValueFormatter::new(PrimitiveType::F32);
+		assert_eq!(f32_formatter.parse("1.5").unwrap(), 1.5f32.to_ne_bytes());
+		assert_eq!(
+			f32_formatter.parse("0x3FC00000").unwrap(),
+			1.5f32.to_ne_bytes()
+		);
+		assert_eq!(f32_formatter.format(&1.5f32.to_ne_bytes()).unwrap(), "1.5");
+
+		let bytes: ValueFormatter = "bytes[3]".parse().unwrap();
+		assert_eq!(bytes.parse("DE AD be").unwrap(), [0xDE, 0xAD, 0xBE]);
+		assert_eq!(bytes.parse("deadbe").unwrap(), [0xDE, 0xAD, 0xBE]);
+		assert_eq!(bytes.format(&[0xDE, 0xAD, 0xBE]).unwrap(), "DE AD BE");
+		assert!(bytes.parse("DE AD").is_err());
+
+		let string: ValueFormatter = "string[16]".parse().unwrap();
+		assert_eq!(string.ty(), &FieldType::CString(16));
+		let raw = b"say \"hi\"\n\xFF\0junk!";
+		let text = string.format(raw).unwrap();
+		assert_eq!(text, r#""say \"hi\"\n\xFF""#);
+		assert_eq!(string.parse(&text).unwrap(), &raw[..11]);
+		assert!(string.parse("this is too long for it").is_err());
+
+		assert!("i128".parse::<ValueFormatter>().is_err());
+		assert!("bytes[0]".parse::<ValueFormatter>().is_err());
+	}
+}
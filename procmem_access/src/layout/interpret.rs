@@ -0,0 +1,189 @@
+//! Decoding unknown memory as every supported type at once.
+
+use crate::{
+	common::{Endianness, OffsetType},
+	memory::{
+		access::{MemoryAccess, ReadError},
+		map::{MemoryMap, MemoryPage},
+	},
+};
+
+use super::{PrimitiveType, Value};
+
+/// Number of bytes read by [`interpret`].
+pub const INTERPRET_WINDOW: usize = 16;
+
+/// Maximum number of characters of the string interpretation.
+const MAX_STRING_LENGTH: usize = INTERPRET_WINDOW;
+
+const INTERPRETED_TYPES: [PrimitiveType; 12] = [
+	PrimitiveType::U8,
+	PrimitiveType::I8,
+	PrimitiveType::U16,
+	PrimitiveType::I16,
+	PrimitiveType::U32,
+	PrimitiveType::I32,
+	PrimitiveType::U64,
+	PrimitiveType::I64,
+	PrimitiveType::F16,
+	PrimitiveType::F32,
+	PrimitiveType::F64,
+	PrimitiveType::Pointer,
+];
+
+/// Report of [`interpret`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Interpretation {
+	pub offset: OffsetType,
+	/// Bytes which could be read, at most [`INTERPRET_WINDOW`].
+	pub bytes: Vec<u8>,
+	/// Value of each primitive type which fits into the read bytes.
+	pub values: Vec<(PrimitiveType, Value)>,
+	/// Page the pointer interpretation points into, if any.
+	pub pointer_target: Option<MemoryPage>,
+	/// Printable ASCII prefix of the bytes, if there is one.
+	pub string: Option<String>,
+}
+impl Interpretation {
+	pub fn value(&self, primitive: PrimitiveType) -> Option<&Value> {
+		self.values
+			.iter()
+			.find(|(value_type, _)| *value_type == primitive)
+			.map(|(_, value)| value)
+	}
+}
+impl std::fmt::Display for Interpretation {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		writeln!(f, "{}: {}", self.offset, Value::Bytes(self.bytes.clone()))?;
+
+		for (value_type, value) in self.values.iter() {
+			write!(f, "  {:>4}: {}", value_type.to_string(), value)?;
+			if let (PrimitiveType::Pointer, Some(page)) = (value_type, &self.pointer_target) {
+				write!(f, " -> {}", page)?;
+			}
+			writeln!(f)?;
+		}
+
+		if let Some(string) = &self.string {
+			writeln!(f, "   str: {:?}", string)?;
+		}
+
+		Ok(())
+	}
+}
+
+/// Reads up to [`INTERPRET_WINDOW`] bytes at `offset` and decodes them as every primitive type in native byte order.
+///
+/// The pointer interpretation is looked up in `map`, and a printable prefix is reported as a string.
+/// Fails only if not even one byte can be read.
+///
+/// ## Safety
+/// * See [`MemoryAccess::read`].
+pub unsafe fn interpret<A: MemoryAccess + ?Sized, M: MemoryMap + ?Sized>(
+	access: &mut A,
+	map: &M,
+	offset: OffsetType,
+) -> Result<Interpretation, ReadError> {
+	let mut bytes = vec![0u8; INTERPRET_WINDOW];
+	if let Err(err) = access.read_partial(offset, &mut bytes) {
+		if err.read == 0 {
+			return Err(err.source);
+		}
+		bytes.truncate(err.read);
+	}
+
+	let values = INTERPRETED_TYPES
+		.iter()
+		.filter(|value_type| value_type.size() <= bytes.len())
+		.map(|value_type| {
+			(
+				*value_type,
+				value_type.decode_as(&bytes, Endianness::NATIVE),
+			)
+		})
+		.collect::<Vec<_>>();
+
+	let pointer_target = values
+		.iter()
+		.find_map(|(_, value)| match value {
+			Value::Pointer(pointer) => OffsetType::new(*pointer),
+			_ => None,
+		})
+		.and_then(|pointer| {
+			map.pages()
+				.iter()
+				.find(|page| pointer >= page.start() && pointer < page.end())
+		})
+		.cloned();
+
+	let printable = bytes
+		.iter()
+		.take(MAX_STRING_LENGTH)
+		.take_while(|byte| byte.is_ascii_graphic() || **byte == b' ')
+		.count();
+	let string = match printable {
+		0 => None,
+		len => Some(String::from_utf8_lossy(&bytes[..len]).into_owned()),
+	};
+
+	Ok(Interpretation {
+		offset,
+		bytes,
+		values,
+		pointer_target,
+		string,
+	})
+}
+
+#[cfg(test)]
+mod test {
+	use super::{interpret, INTERPRET_WINDOW};
+	use crate::{
+		common::OffsetType,
+		layout::{PrimitiveType, Value},
+		memory::map::{MemoryPage, MemoryPagePermissions, MemoryPageType},
+		platform::mock::MockMemoryAccess,
+	};
+
+	#[test]
+	fn test_interpret() {
+		let pages = [MemoryPage {
+			address_range: [
+				OffsetType::new_unwrap(0x1000),
+				OffsetType::new_unwrap(0x2000),
+			],
+			permissions: MemoryPagePermissions::new(true, true, false, false),
+			offset: 0,
+			page_type: MemoryPageType::Heap,
+		}];
+
+		let mut memory = vec![0u8; 0x1000];
+		memory[0x10..0x18].copy_from_slice(&0x1100u64.to_ne_bytes());
+		memory[0x20..0x26].copy_from_slice(b"hello\0");
+		let mut access =
+			MockMemoryAccess::new().with_region(OffsetType::new_unwrap(0x1000), memory);
+
+		let report =
+			unsafe { interpret(&mut access, &pages[..], OffsetType::new_unwrap(0x1010)).unwrap() };
+		assert_eq!(report.bytes.len(), INTERPRET_WINDOW);
+		assert_eq!(report.value(PrimitiveType::U16), Some(&Value::U16(0x1100)));
+		assert_eq!(
+			report.value(PrimitiveType::Pointer),
+			Some(&Value::Pointer(0x1100))
+		);
+		assert_eq!(report.pointer_target.as_ref(), Some(&pages[0]));
+		assert_eq!(report.string, None);
+
+		let report =
+			unsafe { interpret(&mut access, &pages[..], OffsetType::new_unwrap(0x1020)).unwrap() };
+		assert_eq!(report.string.as_deref(), Some("hello"));
+		assert_eq!(report.pointer_target, None);
+
+		// only the last 4 bytes are readable
+		let report =
+			unsafe { interpret(&mut access, &pages[..], OffsetType::new_unwrap(0x1FFC)).unwrap() };
+		assert_eq!(report.bytes.len(), 4);
+		assert_eq!(report.value(PrimitiveType::U64), None);
+		assert!(report.value(PrimitiveType::U32).is_some());
+	}
+}
@@ -0,0 +1,219 @@
+//! Structures which are re-read and compared to their previous value.
+
+use std::time::Duration;
+
+use crate::{
+	common::OffsetType,
+	memory::access::{MemoryAccess, ReadError},
+};
+
+use super::{read_struct, StructDef, Value};
+
+/// Change of a single leaf field between two reads.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldChange {
+	/// Path of the field in the format accepted by [`Value::get`].
+	pub path: String,
+	pub old: Value,
+	pub new: Value,
+}
+impl std::fmt::Display for FieldChange {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		write!(f, "{}: {} -> {}", self.path, self.old, self.new)
+	}
+}
+
+/// Appends changes of leaves between `old` and `new` to `changes`.
+///
+/// Both values are expected to be decoded using the same definition.
+fn diff_values(path: &mut String, old: &Value, new: &Value, changes: &mut Vec<FieldChange>) {
+	fn push_segment(path: &mut String, segment: &str) -> usize {
+		let len = path.len();
+		if !path.is_empty() {
+			path.push('.');
+		}
+		path.push_str(segment);
+
+		len
+	}
+
+	match (old, new) {
+		(Value::Struct(old_fields), Value::Struct(new_fields)) => {
+			for ((name, old), (_, new)) in old_fields.iter().zip(new_fields) {
+				let len = push_segment(path, name);
+				diff_values(path, old, new, changes);
+				path.truncate(len);
+			}
+		}
+		(Value::Array(old_elements), Value::Array(new_elements)) => {
+			for (index, (old, new)) in old_elements.iter().zip(new_elements).enumerate() {
+				let len = push_segment(path, &index.to_string());
+				diff_values(path, old, new, changes);
+				path.truncate(len);
+			}
+		}
+		(old, new) => {
+			// compare floats bitwise so that NaN fields are not reported as changed on every refresh
+			let same = match (old, new) {
+				(Value::F32(old), Value::F32(new)) => old.to_bits() == new.to_bits(),
+				(Value::F64(old), Value::F64(new)) => old.to_bits() == new.to_bits(),
+				_ => old == new,
+			};
+			if !same {
+				changes.push(FieldChange {
+					path: path.clone(),
+					old: old.clone(),
+					new: new.clone(),
+				});
+			}
+		}
+	}
+}
+
+/// Structure at an address which is refreshed on demand and reports which fields changed.
+///
+/// This is the model behind object inspectors: keep the view around and call [`refresh`](LiveView::refresh) whenever the display should update.
+pub struct LiveView {
+	offset: OffsetType,
+	def: StructDef,
+	value: Option<Value>,
+}
+impl LiveView {
+	/// Creates a new view, the structure is read on the first refresh.
+	pub const fn new(offset: OffsetType, def: StructDef) -> Self {
+		LiveView {
+			offset,
+			def,
+			value: None,
+		}
+	}
+
+	pub const fn offset(&self) -> OffsetType {
+		self.offset
+	}
+
+	pub const fn def(&self) -> &StructDef {
+		&self.def
+	}
+
+	/// Returns the value from the last successful refresh.
+	pub const fn value(&self) -> Option<&Value> {
+		self.value.as_ref()
+	}
+
+	/// Points the view at a different address, the next refresh reports no changes.
+	pub fn set_offset(&mut self, offset: OffsetType) {
+		self.offset = offset;
+		self.value = None;
+	}
+
+	/// Changes the definition, the next refresh reports no changes.
+	pub fn set_def(&mut self, def: StructDef) {
+		self.def = def;
+		self.value = None;
+	}
+
+	/// Re-reads the structure and returns the fields which changed since the previous refresh.
+	///
+	/// The first refresh after creation or retargeting returns no changes.
+	///
+	/// ## Safety
+	/// * See [`MemoryAccess::read`].
+	pub unsafe fn refresh<A: MemoryAccess + ?Sized>(
+		&mut self,
+		access: &mut A,
+	) -> Result<Vec<FieldChange>, ReadError> {
+		let new = read_struct(access, self.offset, &self.def)?;
+
+		let mut changes = Vec::new();
+		if let Some(old) = self.value.as_ref() {
+			diff_values(&mut String::new(), old, &new, &mut changes);
+		}
+		self.value = Some(new);
+
+		Ok(changes)
+	}
+
+	/// Refreshes every `interval` and calls `on_changes` with non-empty changes until it returns `false`.
+	///
+	/// ## Safety
+	/// * See [`MemoryAccess::read`].
+	pub unsafe fn run<A: MemoryAccess + ?Sized>(
+		&mut self,
+		access: &mut A,
+		interval: Duration,
+		mut on_changes: impl FnMut(&Value, Vec<FieldChange>) -> bool,
+	) -> Result<(), ReadError> {
+		if self.value.is_none() {
+			self.refresh(access)?;
+		}
+
+		loop {
+			std::thread::sleep(interval);
+
+			let changes = self.refresh(access)?;
+			if !changes.is_empty() && !on_changes(self.value.as_ref().unwrap(), changes) {
+				return Ok(());
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::{FieldChange, LiveView};
+	use crate::{
+		common::OffsetType,
+		layout::{FieldDef, FieldType, PrimitiveType, StructDef, Value},
+		memory::access::MemoryAccess,
+		platform::mock::MockMemoryAccess,
+	};
+
+	#[test]
+	fn test_live_view_changes() {
+		let def = StructDef::new(
+			"Counters",
+			vec![
+				FieldDef::new("total", 0, PrimitiveType::U16),
+				FieldDef::new(
+					"slots",
+					2,
+					FieldType::Array {
+						element: Box::new(PrimitiveType::U8.into()),
+						count: 3,
+					},
+				),
+			],
+		);
+		let mut access = MockMemoryAccess::new().with_region(OffsetType::new_unwrap(1), vec![0; 7]);
+		let mut view = LiveView::new(OffsetType::new_unwrap(1), def);
+
+		unsafe {
+			assert!(view.refresh(&mut access).unwrap().is_empty());
+			assert!(view.refresh(&mut access).unwrap().is_empty());
+
+			access
+				.write(OffsetType::new_unwrap(1), &[5, 0, 0, 7])
+				.unwrap();
+			assert_eq!(
+				view.refresh(&mut access).unwrap(),
+				[
+					FieldChange {
+						path: "total".to_string(),
+						old: Value::U16(0),
+						new: Value::U16(5)
+					},
+					FieldChange {
+						path: "slots.1".to_string(),
+						old: Value::U8(0),
+						new: Value::U8(7)
+					}
+				]
+			);
+			assert_eq!(view.value().unwrap().get("slots.1"), Some(&Value::U8(7)));
+
+			view.set_offset(OffsetType::new_unwrap(2));
+			assert!(view.refresh(&mut access).unwrap().is_empty());
+		}
+	}
+}
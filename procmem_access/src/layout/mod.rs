@@ -0,0 +1,473 @@
+//! Runtime description of data layouts.
+//!
+//! A [`StructDef`] describes the fields of a structure in the target process, which can then be read with [`read_struct`]
+//! into a tree of [`Value`]s. Values are decoded in the [byte order](StructDef::endianness) of the structure, native by default.
+//! A [`LiveView`](live::LiveView) keeps re-reading a structure and reports which fields changed.
+//! A [`ValueFormatter`](format::ValueFormatter) renders single values as text and parses user input back into bytes.
+
+pub mod format;
+pub mod interpret;
+pub mod live;
+
+use crate::{
+	common::{Endianness, OffsetType, F16},
+	memory::access::{MemoryAccess, ReadError},
+};
+
+/// Scalar types with a fixed size.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PrimitiveType {
+	U8,
+	I8,
+	U16,
+	I16,
+	U32,
+	I32,
+	U64,
+	I64,
+	/// Half-precision float, decoded as [`Value::F32`].
+	F16,
+	F32,
+	F64,
+	/// One byte, any non-zero value is `true`.
+	Bool,
+	/// Native pointer of the target platform.
+	Pointer,
+}
+impl PrimitiveType {
+	pub const fn size(&self) -> usize {
+		match self {
+			PrimitiveType::U8 | PrimitiveType::I8 | PrimitiveType::Bool => 1,
+			PrimitiveType::U16 | PrimitiveType::I16 | PrimitiveType::F16 => 2,
+			PrimitiveType::U32 | PrimitiveType::I32 | PrimitiveType::F32 => 4,
+			PrimitiveType::U64 | PrimitiveType::I64 | PrimitiveType::F64 => 8,
+			PrimitiveType::Pointer => std::mem::size_of::<usize>(),
+		}
+	}
+
+	/// Decodes the value in native byte order from the start of `bytes`.
+	///
+	/// Panics if `bytes` is shorter than [`size`](PrimitiveType::size).
+	pub fn decode(&self, bytes: &[u8]) -> Value {
+		self.decode_as(bytes, Endianness::NATIVE)
+	}
+
+	/// Decodes the value in `endianness` byte order from the start of `bytes`.
+	///
+	/// Panics if `bytes` is shorter than [`size`](PrimitiveType::size).
+	pub fn decode_as(&self, bytes: &[u8], endianness: Endianness) -> Value {
+		let mut scalar = [0u8; 8];
+		let scalar = &mut scalar[..self.size()];
+		scalar.copy_from_slice(&bytes[..self.size()]);
+		endianness.convert_scalar(scalar);
+		let bytes = &*scalar;
+
+		macro_rules! decode {
+			($variant: ident, $ty: ty) => {
+				Value::$variant(<$ty>::from_ne_bytes(bytes.try_into().unwrap()))
+			};
+		}
+
+		match self {
+			PrimitiveType::U8 => Value::U8(bytes[0]),
+			PrimitiveType::I8 => Value::I8(bytes[0] as i8),
+			PrimitiveType::U16 => decode!(U16, u16),
+			PrimitiveType::I16 => decode!(I16, i16),
+			PrimitiveType::U32 => decode!(U32, u32),
+			PrimitiveType::I32 => decode!(I32, i32),
+			PrimitiveType::U64 => decode!(U64, u64),
+			PrimitiveType::I64 => decode!(I64, i64),
+			PrimitiveType::F16 => {
+				Value::F32(F16::from_ne_bytes(bytes.try_into().unwrap()).to_f32())
+			}
+			PrimitiveType::F32 => decode!(F32, f32),
+			PrimitiveType::F64 => decode!(F64, f64),
+			PrimitiveType::Bool => Value::Bool(bytes[0] != 0),
+			PrimitiveType::Pointer => {
+				Value::Pointer(usize::from_ne_bytes(bytes.try_into().unwrap()) as u64)
+			}
+		}
+	}
+}
+impl std::fmt::Display for PrimitiveType {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		let name = match self {
+			PrimitiveType::U8 => "u8",
+			PrimitiveType::I8 => "i8",
+			PrimitiveType::U16 => "u16",
+			PrimitiveType::I16 => "i16",
+			PrimitiveType::U32 => "u32",
+			PrimitiveType::I32 => "i32",
+			PrimitiveType::U64 => "u64",
+			PrimitiveType::I64 => "i64",
+			PrimitiveType::F16 => "f16",
+			PrimitiveType::F32 => "f32",
+			PrimitiveType::F64 => "f64",
+			PrimitiveType::Bool => "bool",
+			PrimitiveType::Pointer => "ptr",
+		};
+
+		write!(f, "{}", name)
+	}
+}
+
+/// Type of a field.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FieldType {
+	Primitive(PrimitiveType),
+	/// Raw bytes of the given length.
+	Bytes(usize),
+	/// Inline nul-terminated string of at most the given length, including the terminator.
+	CString(usize),
+	/// Inline structure.
+	Struct(StructDef),
+	/// Inline array of `count` elements laid out back to back.
+	Array {
+		element: Box<FieldType>,
+		count: usize,
+	},
+}
+impl FieldType {
+	pub fn size(&self) -> usize {
+		match self {
+			FieldType::Primitive(primitive) => primitive.size(),
+			FieldType::Bytes(len) | FieldType::CString(len) => *len,
+			FieldType::Struct(def) => def.size,
+			FieldType::Array { element, count } => element.size() * count,
+		}
+	}
+
+	/// Decodes the value in native byte order from the start of `bytes`.
+	///
+	/// Panics if `bytes` is shorter than [`size`](FieldType::size).
+	pub fn decode(&self, bytes: &[u8]) -> Value {
+		self.decode_as(bytes, Endianness::NATIVE)
+	}
+
+	/// Decodes the value from the start of `bytes`, using `endianness` for primitives outside of nested structures.
+	///
+	/// Nested structures use their own [`endianness`](StructDef::endianness).
+	/// Panics if `bytes` is shorter than [`size`](FieldType::size).
+	pub fn decode_as(&self, bytes: &[u8], endianness: Endianness) -> Value {
+		match self {
+			FieldType::Primitive(primitive) => primitive.decode_as(bytes, endianness),
+			FieldType::Bytes(len) => Value::Bytes(bytes[..*len].to_vec()),
+			FieldType::CString(len) => {
+				let bytes = &bytes[..*len];
+				let end = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+
+				Value::String(String::from_utf8_lossy(&bytes[..end]).into_owned())
+			}
+			FieldType::Struct(def) => def.decode(bytes),
+			FieldType::Array { element, count } => {
+				let size = element.size();
+				Value::Array(
+					(0..*count)
+						.map(|index| element.decode_as(&bytes[index * size..], endianness))
+						.collect(),
+				)
+			}
+		}
+	}
+}
+impl From<PrimitiveType> for FieldType {
+	fn from(primitive: PrimitiveType) -> Self {
+		FieldType::Primitive(primitive)
+	}
+}
+impl From<StructDef> for FieldType {
+	fn from(def: StructDef) -> Self {
+		FieldType::Struct(def)
+	}
+}
+
+/// Named field of a [`StructDef`] at an offset from the start of the structure.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldDef {
+	pub name: String,
+	pub offset: usize,
+	pub ty: FieldType,
+}
+impl FieldDef {
+	pub fn new(name: impl Into<String>, offset: usize, ty: impl Into<FieldType>) -> Self {
+		FieldDef {
+			name: name.into(),
+			offset,
+			ty: ty.into(),
+		}
+	}
+
+	/// Offset one past the last byte of this field.
+	pub fn end(&self) -> usize {
+		self.offset + self.ty.size()
+	}
+}
+
+/// Description of a structure in memory.
+///
+/// Fields may be listed in any order, may overlap (like unions) and may leave gaps (like padding or unknown fields).
+#[derive(Debug, Clone, PartialEq)]
+pub struct StructDef {
+	pub name: String,
+	pub fields: Vec<FieldDef>,
+	/// Size of the structure, at least the end of the last field.
+	pub size: usize,
+	/// Byte order of the primitive fields.
+	pub endianness: Endianness,
+}
+impl StructDef {
+	/// Creates a new definition in native byte order with the size set to the end of the last field.
+	pub fn new(name: impl Into<String>, fields: Vec<FieldDef>) -> Self {
+		let size = fields.iter().map(FieldDef::end).max().unwrap_or(0);
+
+		StructDef {
+			name: name.into(),
+			fields,
+			size,
+			endianness: Endianness::NATIVE,
+		}
+	}
+
+	/// Sets the byte order, for example when reading a core dump of a big-endian machine.
+	pub fn with_endianness(mut self, endianness: Endianness) -> Self {
+		self.endianness = endianness;
+
+		self
+	}
+
+	/// Sets the size, for example to include trailing padding when the structure is used as an array element.
+	///
+	/// Panics if `size` is smaller than the end of the last field.
+	pub fn with_size(mut self, size: usize) -> Self {
+		assert!(self.fields.iter().all(|field| field.end() <= size));
+		self.size = size;
+
+		self
+	}
+
+	pub fn field(&self, name: &str) -> Option<&FieldDef> {
+		self.fields.iter().find(|field| field.name == name)
+	}
+
+	/// Decodes the structure from the start of `bytes`.
+	///
+	/// Panics if `bytes` is shorter than [`size`](StructDef::size).
+	pub fn decode(&self, bytes: &[u8]) -> Value {
+		assert!(bytes.len() >= self.size);
+
+		Value::Struct(
+			self.fields
+				.iter()
+				.map(|field| {
+					(
+						field.name.clone(),
+						field.ty.decode_as(&bytes[field.offset..], self.endianness),
+					)
+				})
+				.collect(),
+		)
+	}
+}
+
+/// Value decoded according to a [`FieldType`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+	U8(u8),
+	I8(i8),
+	U16(u16),
+	I16(i16),
+	U32(u32),
+	I32(i32),
+	U64(u64),
+	I64(i64),
+	F32(f32),
+	F64(f64),
+	Bool(bool),
+	/// Pointer values may be null, so they are not represented as [`OffsetType`].
+	Pointer(u64),
+	Bytes(Vec<u8>),
+	String(String),
+	/// Fields in the order of the definition.
+	Struct(Vec<(String, Value)>),
+	Array(Vec<Value>),
+}
+impl Value {
+	/// Returns the field `name` if this is a struct.
+	pub fn field(&self, name: &str) -> Option<&Value> {
+		match self {
+			Value::Struct(fields) => fields
+				.iter()
+				.find(|(field, _)| field == name)
+				.map(|(_, value)| value),
+			_ => None,
+		}
+	}
+
+	/// Returns the element at `index` if this is an array.
+	pub fn index(&self, index: usize) -> Option<&Value> {
+		match self {
+			Value::Array(elements) => elements.get(index),
+			_ => None,
+		}
+	}
+
+	/// Returns the value at `path`, a sequence of field names and array indices separated by dots, such as `player.items.3.count`.
+	pub fn get(&self, path: &str) -> Option<&Value> {
+		path.split('.')
+			.filter(|segment| !segment.is_empty())
+			.try_fold(self, |value, segment| match value {
+				Value::Array(_) => value.index(segment.parse().ok()?),
+				_ => value.field(segment),
+			})
+	}
+
+	fn fmt_indented(&self, f: &mut std::fmt::Formatter, depth: usize) -> std::fmt::Result {
+		match self {
+			Value::Struct(fields) => {
+				writeln!(f, "{{")?;
+				for (name, value) in fields {
+					write!(f, "{:indent$}{}: ", "", name, indent = (depth + 1) * 2)?;
+					value.fmt_indented(f, depth + 1)?;
+					writeln!(f)?;
+				}
+				write!(f, "{:indent$}}}", "", indent = depth * 2)
+			}
+			Value::Array(elements) => {
+				writeln!(f, "[")?;
+				for value in elements {
+					write!(f, "{:indent$}", "", indent = (depth + 1) * 2)?;
+					value.fmt_indented(f, depth + 1)?;
+					writeln!(f)?;
+				}
+				write!(f, "{:indent$}]", "", indent = depth * 2)
+			}
+			Value::U8(v) => write!(f, "{}", v),
+			Value::I8(v) => write!(f, "{}", v),
+			Value::U16(v) => write!(f, "{}", v),
+			Value::I16(v) => write!(f, "{}", v),
+			Value::U32(v) => write!(f, "{}", v),
+			Value::I32(v) => write!(f, "{}", v),
+			Value::U64(v) => write!(f, "{}", v),
+			Value::I64(v) => write!(f, "{}", v),
+			Value::F32(v) => write!(f, "{}", v),
+			Value::F64(v) => write!(f, "{}", v),
+			Value::Bool(v) => write!(f, "{}", v),
+			Value::Pointer(v) => write!(f, "0x{:x}", v),
+			Value::Bytes(bytes) => {
+				for (i, byte) in bytes.iter().enumerate() {
+					if i > 0 {
+						write!(f, " ")?;
+					}
+					write!(f, "{:02X}", byte)?;
+				}
+
+				Ok(())
+			}
+			Value::String(v) => write!(f, "{:?}", v),
+		}
+	}
+}
+impl std::fmt::Display for Value {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		self.fmt_indented(f, 0)
+	}
+}
+
+/// Reads the structure described by `def` at `offset` with a single read.
+///
+/// ## Safety
+/// * See [`MemoryAccess::read`].
+pub unsafe fn read_struct<A: MemoryAccess + ?Sized>(
+	access: &mut A,
+	offset: OffsetType,
+	def: &StructDef,
+) -> Result<Value, ReadError> {
+	let mut buffer = vec![0u8; def.size];
+	access.read(offset, &mut buffer)?;
+
+	Ok(def.decode(&buffer))
+}
+
+#[cfg(test)]
+mod test {
+	use super::{read_struct, FieldDef, FieldType, PrimitiveType, StructDef, Value};
+	use crate::{
+		common::{Endianness, OffsetType},
+		platform::mock::MockMemoryAccess,
+	};
+
+	#[test]
+	fn test_read_struct() {
+		let vec2 = StructDef::new(
+			"Vec2",
+			vec![
+				FieldDef::new("x", 0, PrimitiveType::F32),
+				FieldDef::new("y", 4, PrimitiveType::F32),
+			],
+		);
+		let player = StructDef::new(
+			"Player",
+			vec![
+				FieldDef::new("health", 0, PrimitiveType::I32),
+				FieldDef::new("alive", 4, PrimitiveType::Bool),
+				FieldDef::new("position", 8, vec2.clone()),
+				FieldDef::new(
+					"path",
+					16,
+					FieldType::Array {
+						element: Box::new(vec2.into()),
+						count: 2,
+					},
+				),
+				FieldDef::new("name", 32, FieldType::CString(8)),
+			],
+		);
+		assert_eq!(player.size, 40);
+
+		let mut memory = Vec::new();
+		memory.extend_from_slice(&100i32.to_ne_bytes());
+		memory.extend_from_slice(&[1, 0, 0, 0]);
+		for value in [1.0f32, 2.0, 3.0, 4.0, 5.0, 6.0] {
+			memory.extend_from_slice(&value.to_ne_bytes());
+		}
+		memory.extend_from_slice(b"bob\0junk");
+
+		let mut access = MockMemoryAccess::new().with_region(OffsetType::new_unwrap(1), memory);
+		let value =
+			unsafe { read_struct(&mut access, OffsetType::new_unwrap(1), &player).unwrap() };
+
+		assert_eq!(value.get("health"), Some(&Value::I32(100)));
+		assert_eq!(value.get("alive"), Some(&Value::Bool(true)));
+		assert_eq!(value.get("position.y"), Some(&Value::F32(2.0)));
+		assert_eq!(value.get("path.1.x"), Some(&Value::F32(5.0)));
+		assert_eq!(value.get("path.2"), None);
+		assert_eq!(value.get("name"), Some(&Value::String("bob".to_string())));
+
+		let header = StructDef::new(
+			"Header",
+			vec![
+				FieldDef::new("magic", 0, PrimitiveType::U32),
+				FieldDef::new(
+					"counts",
+					4,
+					FieldType::Array {
+						element: Box::new(PrimitiveType::U16.into()),
+						count: 2,
+					},
+				),
+			],
+		)
+		.with_endianness(Endianness::Big);
+		let value = header.decode(&[0xCA, 0xFE, 0xBA, 0xBE, 0x00, 0x01, 0x01, 0x00]);
+		assert_eq!(value.get("magic"), Some(&Value::U32(0xCAFEBABE)));
+		assert_eq!(value.get("counts.0"), Some(&Value::U16(1)));
+		assert_eq!(value.get("counts.1"), Some(&Value::U16(0x100)));
+
+		let value =
+			unsafe { read_struct(&mut access, OffsetType::new_unwrap(1), &player).unwrap() };
+		assert_eq!(
+			value.get("position").unwrap().to_string(),
+			"{\n  x: 1\n  y: 2\n}"
+		);
+	}
+}
@@ -3,9 +3,14 @@
 //! This library provides abstraction and implementation of multi-platform process memory reading and writing, as well as scanning bytes for values.
 
 pub mod common;
+pub mod dump;
+pub mod error;
+pub mod layout;
 pub mod memory;
 
 pub mod platform;
+pub mod symbols;
 pub mod util;
+pub mod wrapper;
 
 pub mod prelude;
@@ -1,22 +1,66 @@
 use thiserror::Error;
 
-use crate::common::OffsetType;
+use crate::{common::OffsetType, error::ErrorKind};
 
 #[derive(Debug, Error)]
 pub enum ReadError {
 	#[error("not permitted to read from this range")]
 	NotPermitted,
+	#[error("range is not mapped")]
+	NotMapped,
 	#[error("could not perform memory read")]
 	Io(#[from] std::io::Error),
 }
+impl ReadError {
+	pub fn kind(&self) -> ErrorKind {
+		match self {
+			ReadError::NotPermitted => ErrorKind::PermissionDenied,
+			ReadError::NotMapped => ErrorKind::NotMapped,
+			ReadError::Io(err) => ErrorKind::from_io(err),
+		}
+	}
+}
 
 #[derive(Debug, Error)]
 pub enum WriteError {
 	#[error("not permitted to write to this range")]
 	NotPermitted,
+	#[error("range is not mapped")]
+	NotMapped,
 	#[error("could not perform memory write")]
 	Io(#[from] std::io::Error),
 }
+impl WriteError {
+	pub fn kind(&self) -> ErrorKind {
+		match self {
+			WriteError::NotPermitted => ErrorKind::PermissionDenied,
+			WriteError::NotMapped => ErrorKind::NotMapped,
+			WriteError::Io(err) => ErrorKind::from_io(err),
+		}
+	}
+}
+
+/// Error returned by [`read_partial`](MemoryAccess::read_partial) describing the first unreadable range.
+#[derive(Debug, Error)]
+#[error("could not read range {}-{} (read {read} bytes before it)", unreadable[0], unreadable[1])]
+pub struct PartialReadError {
+	/// Number of bytes successfully read from the start of the buffer.
+	pub read: usize,
+	/// The first range which could not be read.
+	pub unreadable: [OffsetType; 2],
+	#[source]
+	pub source: ReadError,
+}
+impl PartialReadError {
+	pub fn kind(&self) -> ErrorKind {
+		self.source.kind()
+	}
+}
+
+/// Granularity in which [`read_partial`](MemoryAccess::read_partial) looks for unreadable ranges.
+///
+/// This is the smallest page size of the supported platforms.
+pub const PARTIAL_READ_GRANULARITY: u64 = 4096;
 
 /// Trait implemented on abstractions over reading and writing from memory.
 pub trait MemoryAccess {
@@ -27,10 +71,170 @@ pub trait MemoryAccess {
 	/// * Offset must be mapped in the process memory mappings.
 	unsafe fn read(&mut self, offset: OffsetType, buffer: &mut [u8]) -> Result<(), ReadError>;
 
+	/// Reads as many bytes as possible to fill the `buffer` from `offset`.
+	///
+	/// Unlike [`read`](MemoryAccess::read) this does not fail the whole read when part of the range cannot be read.
+	/// Instead the error reports how many bytes were read from the start of the buffer and which range could not be read.
+	/// The contents of the buffer past the successfully read bytes are unspecified.
+	///
+	/// The default implementation falls back to reading in [`PARTIAL_READ_GRANULARITY`] aligned blocks when reading the whole range fails.
+	///
+	/// ## Safety
+	/// * The process must be locked and or otherwise protected against data races.
+	unsafe fn read_partial(
+		&mut self,
+		offset: OffsetType,
+		buffer: &mut [u8],
+	) -> Result<(), PartialReadError> {
+		let first_error = match self.read(offset, buffer) {
+			Ok(()) => return Ok(()),
+			Err(err) => err,
+		};
+
+		let start = offset.get();
+		let end = start + buffer.len() as u64;
+		let block_end = |position: u64| {
+			((position / PARTIAL_READ_GRANULARITY + 1) * PARTIAL_READ_GRANULARITY).min(end)
+		};
+		let mut read_block = |position: u64, next: u64| {
+			let block = &mut buffer[(position - start) as usize..(next - start) as usize];
+
+			self.read(OffsetType::new_unwrap(position), block)
+		};
+
+		// read block by block until the first unreadable one
+		let mut position = start;
+		let mut source = None;
+		while position < end {
+			let next = block_end(position);
+			if let Err(err) = read_block(position, next) {
+				source = Some(err);
+				break;
+			}
+			position = next;
+		}
+		let source = match source {
+			Some(err) => err,
+			None => {
+				// the whole read failed but no block did, report the original error over the whole range
+				return Err(PartialReadError {
+					read: 0,
+					unreadable: [offset, OffsetType::new_unwrap(end)],
+					source: first_error,
+				});
+			}
+		};
+
+		// find where the unreadable range ends
+		let read = (position - start) as usize;
+		let unreadable_start = position;
+		position = block_end(position);
+		while position < end {
+			let next = block_end(position);
+			if read_block(position, next).is_ok() {
+				break;
+			}
+			position = next;
+		}
+
+		Err(PartialReadError {
+			read,
+			unreadable: [
+				OffsetType::new_unwrap(unreadable_start),
+				OffsetType::new_unwrap(position),
+			],
+			source,
+		})
+	}
+
+	/// Performs multiple reads, filling each buffer from its respective offset.
+	///
+	/// This has the same effect as calling [`read`](MemoryAccess::read) for each request in order and stops at the first error,
+	/// but implementations may batch the requests into fewer system calls.
+	///
+	/// ## Safety
+	/// Same as [`read`](MemoryAccess::read), for each of the requests.
+	unsafe fn read_v(&mut self, requests: &mut [(OffsetType, &mut [u8])]) -> Result<(), ReadError> {
+		for (offset, buffer) in requests.iter_mut() {
+			self.read(*offset, buffer)?;
+		}
+
+		Ok(())
+	}
+
 	/// Write exact amount of bytes from `data` into the process memory starting at `offset`.
 	///
 	/// ## Safety
 	/// * The process must be exclusively locked or otherwise protected against data races.
 	/// * Offset must be mapped in the process memory mappings.
 	unsafe fn write(&mut self, offset: OffsetType, data: &[u8]) -> Result<(), WriteError>;
+
+	/// Performs multiple writes, writing each `data` at its respective offset.
+	///
+	/// This has the same effect as calling [`write`](MemoryAccess::write) for each write in order and stops at the first error,
+	/// but implementations may batch the writes into fewer system calls. The process only needs to be locked once for the whole batch.
+	///
+	/// ## Safety
+	/// Same as [`write`](MemoryAccess::write), for each of the writes.
+	unsafe fn write_many(&mut self, writes: &[(OffsetType, &[u8])]) -> Result<(), WriteError> {
+		for (offset, data) in writes.iter() {
+			self.write(*offset, data)?;
+		}
+
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::{MemoryAccess, PARTIAL_READ_GRANULARITY};
+	use crate::{common::OffsetType, platform::mock::MockMemoryAccess};
+
+	#[test]
+	fn test_read_partial_hole() {
+		const BLOCK: u64 = PARTIAL_READ_GRANULARITY;
+
+		let base = OffsetType::new_unwrap(BLOCK);
+		let mut access = MockMemoryAccess::new()
+			.with_region(base, (0..BLOCK * 4).map(|i| i as u8).collect())
+			.with_fault([
+				OffsetType::new_unwrap(BLOCK * 2),
+				OffsetType::new_unwrap(BLOCK * 3),
+			]);
+
+		// start in the middle of the first block
+		let mut buffer = vec![0u8; (BLOCK * 3) as usize];
+		let err = unsafe {
+			access
+				.read_partial(OffsetType::new_unwrap(BLOCK + 16), &mut buffer)
+				.unwrap_err()
+		};
+
+		assert_eq!(err.read, (BLOCK - 16) as usize);
+		assert_eq!(
+			err.unreadable,
+			[
+				OffsetType::new_unwrap(BLOCK * 2),
+				OffsetType::new_unwrap(BLOCK * 3)
+			]
+		);
+		assert_eq!(
+			&buffer[..err.read],
+			&access.region(base).unwrap()[16..BLOCK as usize]
+		);
+	}
+
+	#[test]
+	fn test_read_partial_complete() {
+		let mut access =
+			MockMemoryAccess::new().with_region(OffsetType::new_unwrap(1), vec![1, 2, 3, 4]);
+
+		let mut buffer = [0u8; 4];
+		unsafe {
+			access
+				.read_partial(OffsetType::new_unwrap(1), &mut buffer)
+				.unwrap()
+		};
+		assert_eq!(buffer, [1, 2, 3, 4]);
+	}
 }
@@ -0,0 +1,112 @@
+//! Allocation of memory inside the target process.
+//!
+//! Code injection and trampolines need space in the target process which is not used by anything else.
+//! [`MemoryAllocate`] maps fresh pages with the requested permissions and unmaps them again.
+
+use std::collections::BTreeMap;
+
+use thiserror::Error;
+
+use crate::{
+	common::OffsetType,
+	error::{ErrorKind, ProcmemError},
+	memory::map::MemoryPagePermissions,
+};
+
+use super::lock::{LockError, UnlockError};
+
+#[derive(Debug, Error)]
+pub enum AllocateError {
+	#[error("allocation size must not be zero")]
+	ZeroSize,
+	#[error("{0} was not allocated by this allocator")]
+	NotAllocated(OffsetType),
+	#[error("could not lock the process")]
+	Lock(#[from] LockError),
+	#[error("could not unlock the process")]
+	Unlock(#[from] UnlockError),
+	#[error("could not perform allocation")]
+	Io(#[from] std::io::Error),
+	#[error("platform specific error: {0}")]
+	PlatformError(Box<dyn std::error::Error + Send + Sync>),
+}
+impl AllocateError {
+	pub fn kind(&self) -> ErrorKind {
+		match self {
+			AllocateError::ZeroSize => ErrorKind::Platform,
+			AllocateError::NotAllocated(_) => ErrorKind::NotMapped,
+			AllocateError::Lock(err) => err.kind(),
+			AllocateError::Unlock(err) => err.kind(),
+			AllocateError::Io(err) => ErrorKind::from_io(err),
+			AllocateError::PlatformError(err) => ErrorKind::from_error(err.as_ref()),
+		}
+	}
+}
+impl From<AllocateError> for ProcmemError {
+	fn from(err: AllocateError) -> Self {
+		ProcmemError::new(err.kind(), err)
+	}
+}
+
+/// Trait implemented on abstractions which can allocate memory in the target process.
+pub trait MemoryAllocate {
+	/// Maps new private pages of at least `size` bytes with `permissions` and returns the start of the allocation.
+	///
+	/// The size is rounded up to whole pages and the memory is zeroed. The `shared` permission is ignored.
+	///
+	/// ## Safety
+	/// * The process may be stopped or have code run in it for the duration of the call, depending on the platform.
+	unsafe fn allocate(
+		&mut self,
+		size: u64,
+		permissions: MemoryPagePermissions,
+	) -> Result<OffsetType, AllocateError>;
+
+	/// Unmaps an allocation returned by [`allocate`](MemoryAllocate::allocate) of the same allocator.
+	///
+	/// ## Safety
+	/// * The process must not use the memory anymore.
+	/// * See [`allocate`](MemoryAllocate::allocate).
+	unsafe fn free(&mut self, offset: OffsetType) -> Result<(), AllocateError>;
+}
+
+/// Sizes of live allocations, for implementations which need the size to unmap them.
+#[derive(Debug, Clone, Default)]
+pub struct Allocations {
+	sizes: BTreeMap<OffsetType, u64>,
+}
+impl Allocations {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Rounds `size` up to whole pages of `page_size`, which must be a power of two.
+	pub fn round_to_pages(size: u64, page_size: u64) -> Result<u64, AllocateError> {
+		if size == 0 {
+			return Err(AllocateError::ZeroSize);
+		}
+
+		Ok((size + page_size - 1) & !(page_size - 1))
+	}
+
+	pub fn insert(&mut self, offset: OffsetType, size: u64) {
+		self.sizes.insert(offset, size);
+	}
+
+	/// Returns the size of the allocation at `offset` without forgetting it.
+	pub fn get(&self, offset: OffsetType) -> Result<u64, AllocateError> {
+		self.sizes
+			.get(&offset)
+			.copied()
+			.ok_or(AllocateError::NotAllocated(offset))
+	}
+
+	pub fn remove(&mut self, offset: OffsetType) {
+		self.sizes.remove(&offset);
+	}
+
+	/// Returns the live allocations and their sizes ordered by offset.
+	pub fn iter(&self) -> impl Iterator<Item = (OffsetType, u64)> + '_ {
+		self.sizes.iter().map(|(offset, size)| (*offset, *size))
+	}
+}
@@ -0,0 +1,162 @@
+//! Reading of large ranges in bounded chunks.
+//!
+//! Reading a whole mapping into one buffer can take hundreds of megabytes for large anonymous mappings.
+//! The [`ChunkedReader`] reads a range through one reusable buffer instead. Consecutive chunks can overlap,
+//! so that consumers without state carried between chunks still see every match shorter than the overlap in one piece.
+
+use crate::common::OffsetType;
+
+use super::access::{MemoryAccess, ReadError};
+
+/// Chunk of memory passed to the callback of [`ChunkedReader::read`].
+#[derive(Debug, Clone, Copy)]
+pub struct Chunk<'a> {
+	/// Offset of the first byte of the chunk.
+	pub offset: OffsetType,
+	pub bytes: &'a [u8],
+	/// Number of leading bytes which were already part of the previous chunk.
+	pub overlap: usize,
+}
+impl<'a> Chunk<'a> {
+	/// Returns whether a match at `index` of `length` bytes was already contained in the previous chunk.
+	///
+	/// Skipping these avoids reporting matches inside the overlap twice.
+	pub const fn is_repeated(&self, index: usize, length: usize) -> bool {
+		index + length <= self.overlap
+	}
+}
+
+/// Reads ranges of memory in chunks of at most `chunk_size` bytes through a reused buffer.
+#[derive(Debug, Clone)]
+pub struct ChunkedReader {
+	chunk_size: usize,
+	overlap: usize,
+	buffer: Vec<u8>,
+}
+impl ChunkedReader {
+	/// ## Panics
+	/// * If `chunk_size` is zero.
+	pub fn new(chunk_size: usize) -> Self {
+		assert!(chunk_size > 0);
+
+		ChunkedReader {
+			chunk_size,
+			overlap: 0,
+			buffer: Vec::new(),
+		}
+	}
+
+	/// Sets the number of bytes each chunk repeats from the end of the previous one.
+	///
+	/// Every match of at most `overlap + 1` bytes is then fully contained in at least one chunk.
+	/// The repeated bytes are copied, not read again.
+	///
+	/// ## Panics
+	/// * If `overlap` is not less than the chunk size.
+	pub fn with_overlap(mut self, overlap: usize) -> Self {
+		assert!(overlap < self.chunk_size);
+
+		self.overlap = overlap;
+		self
+	}
+
+	pub const fn chunk_size(&self) -> usize {
+		self.chunk_size
+	}
+
+	pub const fn overlap(&self) -> usize {
+		self.overlap
+	}
+
+	/// Reads `range` chunk by chunk in ascending order, calling `on_chunk` for each.
+	///
+	/// Returns the error of the first failed read, chunks before it have already been passed to `on_chunk`.
+	///
+	/// ## Safety
+	/// * See [`MemoryAccess::read`].
+	pub unsafe fn read<A: MemoryAccess + ?Sized>(
+		&mut self,
+		access: &mut A,
+		range: [OffsetType; 2],
+		mut on_chunk: impl FnMut(Chunk),
+	) -> Result<(), ReadError> {
+		let [start, end] = range;
+
+		let mut position = start;
+		let mut repeated = 0;
+		while position < end {
+			let new_bytes = ((end.get() - position.get()) as usize).min(self.chunk_size - repeated);
+
+			// keep the tail of the previous chunk at the start of the buffer
+			let previous_len = self.buffer.len();
+			self.buffer
+				.copy_within(previous_len - repeated..previous_len, 0);
+			self.buffer.resize(repeated + new_bytes, 0);
+			access.read(position, &mut self.buffer[repeated..])?;
+
+			on_chunk(Chunk {
+				offset: OffsetType::new_unwrap(position.get() - repeated as u64),
+				bytes: &self.buffer,
+				overlap: repeated,
+			});
+
+			position = position.saturating_add(new_bytes as u64);
+			repeated = self.overlap.min(self.buffer.len());
+		}
+		self.buffer.clear();
+
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::ChunkedReader;
+	use crate::{common::OffsetType, platform::mock::MockMemoryAccess};
+
+	#[test]
+	fn test_chunked_reader_overlap() {
+		let data: Vec<u8> = (0..100).collect();
+		let mut access = MockMemoryAccess::new().with_region(OffsetType::new_unwrap(0x1000), data);
+		let range = [
+			OffsetType::new_unwrap(0x1000),
+			OffsetType::new_unwrap(0x1000 + 100),
+		];
+
+		let mut reader = ChunkedReader::new(32).with_overlap(3);
+		let mut chunks = Vec::new();
+		unsafe {
+			reader
+				.read(&mut access, range, |chunk| {
+					chunks.push((
+						chunk.offset.get() - 0x1000,
+						chunk.overlap,
+						chunk.bytes.to_vec(),
+					))
+				})
+				.unwrap();
+		}
+
+		let layout: Vec<_> = chunks
+			.iter()
+			.map(|(offset, overlap, bytes)| (*offset, *overlap, bytes.len()))
+			.collect();
+		assert_eq!(layout, [(0, 0, 32), (29, 3, 32), (58, 3, 32), (87, 3, 13)]);
+		for (offset, _, bytes) in chunks {
+			assert_eq!(bytes[0] as u64, offset);
+			assert!(bytes.windows(2).all(|w| w[1] == w[0] + 1));
+		}
+
+		// reads beyond the region fail after the readable chunks
+		let mut count = 0;
+		let result = unsafe {
+			reader.read(
+				&mut access,
+				[range[0], OffsetType::new_unwrap(0x1000 + 200)],
+				|_| count += 1,
+			)
+		};
+		assert!(result.is_err());
+		assert_eq!(count, 3);
+	}
+}
@@ -0,0 +1,198 @@
+//! Heuristic classification of memory regions.
+//!
+//! Platforms report most of the memory of modern runtimes as plain anonymous mappings. The [`classify_pages`] heuristics
+//! look at the shape of the memory map (sizes, alignment, guard pages, permissions) to guess what such mappings hold.
+//! They work on the map alone and can be wrong, so use them to prioritize scans rather than to exclude memory.
+
+use super::map::{MemoryPage, MemoryPageType};
+
+/// Size of the heaps glibc allocates for non-main malloc arenas on 64-bit platforms.
+const MALLOC_HEAP_SIZE: u64 = 64 * 1024 * 1024;
+/// Smallest mapping considered a thread stack.
+const MIN_THREAD_STACK_SIZE: u64 = 16 * 1024;
+/// Largest mapping considered a thread stack.
+const MAX_THREAD_STACK_SIZE: u64 = 64 * 1024 * 1024;
+/// Smallest contiguous anonymous reservation considered a garbage collected heap.
+const MIN_GC_RESERVATION_SIZE: u64 = 128 * 1024 * 1024;
+
+/// Page type extended with guesses for anonymous memory.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum RegionClass {
+	/// The platform provided a type which is more specific than anonymous memory.
+	Typed(MemoryPageType),
+	/// Inaccessible page guarding a stack or a heap, or reserved address space.
+	Guard,
+	/// Stack of a thread other than the main one.
+	ThreadStack,
+	/// Heap of a non-main malloc arena.
+	MallocArena,
+	/// Executable anonymous memory, typically produced by a JIT compiler.
+	JitCode,
+	/// Committed part of a large reservation, typical for garbage collected runtimes (JVM, V8, .NET).
+	GcHeap,
+	/// Anonymous memory no heuristic matched.
+	Anon,
+}
+impl std::fmt::Display for RegionClass {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		match self {
+			RegionClass::Typed(page_type) => write!(f, "{}", page_type),
+			RegionClass::Guard => write!(f, "[guard]"),
+			RegionClass::ThreadStack => write!(f, "[thread stack]"),
+			RegionClass::MallocArena => write!(f, "[malloc arena]"),
+			RegionClass::JitCode => write!(f, "[jit]"),
+			RegionClass::GcHeap => write!(f, "[gc heap]"),
+			RegionClass::Anon => write!(f, ""),
+		}
+	}
+}
+
+fn is_anon(page: &MemoryPage) -> bool {
+	matches!(
+		page.page_type,
+		MemoryPageType::Anon | MemoryPageType::Unknown
+	)
+}
+
+fn is_inaccessible(page: &MemoryPage) -> bool {
+	!page.permissions.read() && !page.permissions.write() && !page.permissions.exec()
+}
+
+/// Returns whether the pages are directly adjacent.
+fn adjacent(left: &MemoryPage, right: &MemoryPage) -> bool {
+	left.end() == right.start()
+}
+
+/// Classifies each page of a sorted memory map, returning the classes in the same order.
+pub fn classify_pages(pages: &[MemoryPage]) -> Vec<RegionClass> {
+	// sizes of runs of adjacent anonymous pages, used to recognize large reservations
+	let mut run_sizes = vec![0u64; pages.len()];
+	let mut run_start = 0;
+	for index in 0..=pages.len() {
+		let continues = index < pages.len()
+			&& is_anon(&pages[index])
+			&& index > run_start
+			&& adjacent(&pages[index - 1], &pages[index]);
+		if continues {
+			continue;
+		}
+
+		if run_start < index && is_anon(&pages[run_start]) {
+			let size = pages[index - 1].end().get() - pages[run_start].start().get();
+			run_sizes[run_start..index].fill(size);
+		}
+		run_start = index;
+	}
+
+	pages
+		.iter()
+		.enumerate()
+		.map(|(index, page)| {
+			if !is_anon(page) {
+				return RegionClass::Typed(page.page_type.clone());
+			}
+			if is_inaccessible(page) {
+				return RegionClass::Guard;
+			}
+			if page.permissions.exec() {
+				return RegionClass::JitCode;
+			}
+
+			let previous = index.checked_sub(1).map(|i| &pages[i]);
+			let next = pages.get(index + 1);
+			let guarded_below = previous
+				.map(|p| is_anon(p) && is_inaccessible(p) && adjacent(p, page))
+				.unwrap_or(false);
+			let reserved_above = next
+				.map(|n| is_anon(n) && is_inaccessible(n) && adjacent(page, n))
+				.unwrap_or(false);
+
+			// glibc arena heaps are aligned to their maximum size and grow into the reservation above them
+			if page.start().get() % MALLOC_HEAP_SIZE == 0
+				&& page.size() <= MALLOC_HEAP_SIZE
+				&& (reserved_above || page.size() == MALLOC_HEAP_SIZE)
+			{
+				return RegionClass::MallocArena;
+			}
+
+			if run_sizes[index] >= MIN_GC_RESERVATION_SIZE && (guarded_below || reserved_above) {
+				return RegionClass::GcHeap;
+			}
+
+			// pthread stacks grow down towards a guard page placed right below them
+			if guarded_below
+				&& page.permissions.write()
+				&& (MIN_THREAD_STACK_SIZE..=MAX_THREAD_STACK_SIZE).contains(&page.size())
+			{
+				return RegionClass::ThreadStack;
+			}
+
+			RegionClass::Anon
+		})
+		.collect()
+}
+
+#[cfg(test)]
+mod test {
+	use std::path::PathBuf;
+
+	use super::{classify_pages, RegionClass};
+	use crate::{
+		common::OffsetType,
+		memory::map::{MemoryPage, MemoryPagePermissions, MemoryPageType},
+	};
+
+	const NONE: MemoryPagePermissions = MemoryPagePermissions::new(false, false, false, false);
+	const RW: MemoryPagePermissions = MemoryPagePermissions::new(true, true, false, false);
+	const RWX: MemoryPagePermissions = MemoryPagePermissions::new(true, true, true, false);
+
+	const MIB: u64 = 1024 * 1024;
+
+	fn anon(start: u64, end: u64, permissions: MemoryPagePermissions) -> MemoryPage {
+		MemoryPage {
+			address_range: [OffsetType::new_unwrap(start), OffsetType::new_unwrap(end)],
+			permissions,
+			offset: 0,
+			page_type: MemoryPageType::Anon,
+		}
+	}
+
+	#[test]
+	fn test_classify_pages() {
+		let base = 0x7f00_0000_0000u64;
+		let pages = [
+			MemoryPage {
+				page_type: MemoryPageType::File(PathBuf::from("/usr/lib/libc.so.6")),
+				..anon(0x1000, 0x2000, RW)
+			},
+			// thread stack below its guard page
+			anon(0x10_0000, 0x10_1000, NONE),
+			anon(0x10_1000, 0x10_1000 + 8 * MIB, RW),
+			// arena heap at 64 MiB alignment with the rest reserved
+			anon(base, base + MIB, RW),
+			anon(base + MIB, base + 64 * MIB, NONE),
+			// jit code
+			anon(base + 128 * MIB, base + 128 * MIB + 0x1000, RWX),
+			// gc heap reservation
+			anon(base + 256 * MIB + 0x1000, base + 260 * MIB, RW),
+			anon(base + 260 * MIB, base + 512 * MIB, NONE),
+			// small anonymous mapping
+			anon(base + 1024 * MIB, base + 1024 * MIB + 0x2000, RW),
+		];
+
+		assert_eq!(
+			classify_pages(&pages),
+			[
+				RegionClass::Typed(MemoryPageType::File(PathBuf::from("/usr/lib/libc.so.6"))),
+				RegionClass::Guard,
+				RegionClass::ThreadStack,
+				RegionClass::MallocArena,
+				RegionClass::Guard,
+				RegionClass::JitCode,
+				RegionClass::GcHeap,
+				RegionClass::Guard,
+				RegionClass::Anon,
+			]
+		);
+	}
+}
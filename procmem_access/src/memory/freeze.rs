@@ -0,0 +1,212 @@
+//! Freezing of values.
+//!
+//! The [`ValueFreezer`] keeps rewriting a set of values from a background thread, so that the target process cannot change them
+//! for longer than one interval. The process is not locked for the writes, stopping it on every tick would slow it down
+//! considerably and a frozen value is rewritten shortly after any change anyway.
+
+use std::{
+	collections::BTreeMap,
+	sync::{Arc, Condvar, Mutex, MutexGuard},
+	thread::JoinHandle,
+	time::Duration,
+};
+
+use crate::common::OffsetType;
+
+use super::access::{MemoryAccess, WriteError};
+
+#[derive(Debug, Default)]
+struct FreezerState {
+	values: BTreeMap<OffsetType, Vec<u8>>,
+	paused: bool,
+	stopped: bool,
+	/// First error since the last [`ValueFreezer::take_error`].
+	error: Option<WriteError>,
+}
+
+#[derive(Debug, Default)]
+struct Shared {
+	state: Mutex<FreezerState>,
+	wake: Condvar,
+}
+
+/// Rewrites frozen values every interval from a background thread.
+///
+/// The thread is stopped and joined on drop.
+pub struct ValueFreezer {
+	shared: Arc<Shared>,
+	interval: Duration,
+	handle: Option<JoinHandle<()>>,
+}
+impl ValueFreezer {
+	/// Starts a freezer writing through `access` every `interval`, initially without any frozen values.
+	///
+	/// ## Safety
+	/// * See [`MemoryAccess::write`], all values frozen later are written without locking the process.
+	pub unsafe fn new<A: MemoryAccess + Send + 'static>(mut access: A, interval: Duration) -> Self {
+		let shared = Arc::new(Shared::default());
+
+		let thread_shared = shared.clone();
+		let handle = std::thread::spawn(move || {
+			let mut writes: Vec<(OffsetType, Vec<u8>)> = Vec::new();
+			loop {
+				{
+					let state = thread_shared.state.lock().unwrap();
+					let (state, _) = thread_shared
+						.wake
+						.wait_timeout_while(state, interval, |state| !state.stopped)
+						.unwrap();
+					if state.stopped {
+						break;
+					}
+					if state.paused {
+						continue;
+					}
+
+					writes.clear();
+					writes.extend(
+						state
+							.values
+							.iter()
+							.map(|(offset, value)| (*offset, value.clone())),
+					);
+				}
+
+				let batch: Vec<(OffsetType, &[u8])> = writes
+					.iter()
+					.map(|(offset, value)| (*offset, value.as_slice()))
+					.collect();
+				if let Err(err) = unsafe { access.write_many(&batch) } {
+					thread_shared.state.lock().unwrap().error.get_or_insert(err);
+				}
+			}
+		});
+
+		ValueFreezer {
+			shared,
+			interval,
+			handle: Some(handle),
+		}
+	}
+
+	pub const fn interval(&self) -> Duration {
+		self.interval
+	}
+
+	fn state(&self) -> MutexGuard<'_, FreezerState> {
+		self.shared.state.lock().unwrap()
+	}
+
+	/// Freezes `value` at `offset`, replacing a value already frozen there.
+	pub fn freeze(&self, offset: OffsetType, value: impl Into<Vec<u8>>) {
+		self.state().values.insert(offset, value.into());
+	}
+
+	/// Stops freezing the value at `offset` and returns it, if it was frozen.
+	pub fn unfreeze(&self, offset: OffsetType) -> Option<Vec<u8>> {
+		self.state().values.remove(&offset)
+	}
+
+	/// Stops freezing all values.
+	pub fn clear(&self) {
+		self.state().values.clear();
+	}
+
+	/// Returns the frozen values ordered by offset.
+	pub fn frozen(&self) -> Vec<(OffsetType, Vec<u8>)> {
+		self.state()
+			.values
+			.iter()
+			.map(|(offset, value)| (*offset, value.clone()))
+			.collect()
+	}
+
+	/// Pauses writing without forgetting the frozen values.
+	pub fn pause(&self) {
+		self.state().paused = true;
+	}
+
+	pub fn resume(&self) {
+		self.state().paused = false;
+	}
+
+	pub fn is_paused(&self) -> bool {
+		self.state().paused
+	}
+
+	/// Returns the first write error since the last call.
+	///
+	/// Failed writes do not stop the freezer, the values are written again in the next interval.
+	pub fn take_error(&self) -> Option<WriteError> {
+		self.state().error.take()
+	}
+}
+impl Drop for ValueFreezer {
+	fn drop(&mut self) {
+		self.state().stopped = true;
+		self.shared.wake.notify_all();
+
+		if let Some(handle) = self.handle.take() {
+			let _ = handle.join();
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use std::{
+		sync::{Arc, Mutex},
+		time::Duration,
+	};
+
+	use super::ValueFreezer;
+	use crate::{
+		common::OffsetType, memory::access::MemoryAccess, platform::mock::MockMemoryAccess,
+	};
+
+	fn wait_for(mut condition: impl FnMut() -> bool) {
+		for _ in 0..1000 {
+			if condition() {
+				return;
+			}
+			std::thread::sleep(Duration::from_millis(1));
+		}
+		panic!("condition not met in time");
+	}
+
+	#[test]
+	fn test_value_freezer() {
+		let base = OffsetType::new_unwrap(0x1000);
+		// shared with the freezer thread, so that its writes can be observed
+		let mut access = Arc::new(Mutex::new(
+			MockMemoryAccess::new().with_region(base, vec![0; 16]),
+		));
+		let read = |access: &mut Arc<Mutex<MockMemoryAccess>>| {
+			let mut buffer = [0u8; 2];
+			unsafe { access.read(base, &mut buffer).unwrap() };
+			buffer
+		};
+
+		let freezer = unsafe { ValueFreezer::new(access.clone(), Duration::from_millis(1)) };
+		freezer.freeze(base, [1, 2]);
+		wait_for(|| read(&mut access) == [1, 2]);
+
+		// changes by the process are overwritten
+		unsafe { access.write(base, &[5, 5]).unwrap() };
+		wait_for(|| read(&mut access) == [1, 2]);
+
+		freezer.pause();
+		std::thread::sleep(Duration::from_millis(5));
+		unsafe { access.write(base, &[5, 5]).unwrap() };
+		std::thread::sleep(Duration::from_millis(5));
+		assert_eq!(read(&mut access), [5, 5]);
+		freezer.resume();
+		wait_for(|| read(&mut access) == [1, 2]);
+
+		assert_eq!(freezer.unfreeze(base), Some(vec![1, 2]));
+		freezer.freeze(OffsetType::new_unwrap(0x8000), [1]);
+		wait_for(|| freezer.take_error().is_some());
+
+		drop(freezer);
+	}
+}
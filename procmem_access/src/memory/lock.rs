@@ -1,5 +1,7 @@
 use thiserror::Error;
 
+use crate::error::ErrorKind;
+
 #[derive(Debug, Error)]
 pub enum LockError {
 	#[error("process is already locked exclusively")]
@@ -7,6 +9,14 @@ pub enum LockError {
 	#[error("platform specific error: {0}")]
 	PlatformError(Box<dyn std::error::Error + Send + Sync>),
 }
+impl LockError {
+	pub fn kind(&self) -> ErrorKind {
+		match self {
+			LockError::AlreadyLocked => ErrorKind::Platform,
+			LockError::PlatformError(err) => ErrorKind::from_error(err.as_ref()),
+		}
+	}
+}
 
 #[derive(Debug, Error)]
 pub enum UnlockError {
@@ -15,6 +25,14 @@ pub enum UnlockError {
 	#[error("platform specific error: {0}")]
 	PlatformError(Box<dyn std::error::Error + Send + Sync>),
 }
+impl UnlockError {
+	pub fn kind(&self) -> ErrorKind {
+		match self {
+			UnlockError::NotLocked => ErrorKind::Platform,
+			UnlockError::PlatformError(err) => ErrorKind::from_error(err.as_ref()),
+		}
+	}
+}
 
 /// Trait implemented on abstractions over locking and unlocking process memory.
 pub trait MemoryLock {
@@ -2,7 +2,7 @@ use std::path::PathBuf;
 
 use crate::{common::OffsetType, util::AccFilter};
 
-#[derive(Debug, Copy, Clone, PartialEq)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub struct MemoryPagePermissions {
 	bits: u8,
 }
@@ -59,7 +59,8 @@ impl std::fmt::Display for MemoryPagePermissions {
 	}
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum MemoryPageType {
 	/// The API does not provide additional information.
 	Unknown,
@@ -78,6 +79,17 @@ pub enum MemoryPageType {
 	               // Vvar,
 	               // Vdso,
 }
+impl MemoryPageType {
+	/// Returns the file name of the backing file, which identifies the module (executable or library) of file-backed pages.
+	pub fn module_name(&self) -> Option<&str> {
+		match self {
+			MemoryPageType::ProcessExecutable(path) | MemoryPageType::File(path) => {
+				path.file_name()?.to_str()
+			}
+			_ => None,
+		}
+	}
+}
 impl std::fmt::Display for MemoryPageType {
 	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
 		match self {
@@ -92,6 +104,7 @@ impl std::fmt::Display for MemoryPageType {
 }
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct MemoryPage {
 	pub address_range: [OffsetType; 2],
 	pub permissions: MemoryPagePermissions,
@@ -156,6 +169,33 @@ impl std::fmt::Display for MemoryPage {
 	}
 }
 
+/// Memory usage of one page, in bytes.
+///
+/// Reported by platforms which track it, such as `/proc/<pid>/smaps` on Linux.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MemoryPageStats {
+	/// Bytes in physical memory.
+	pub resident: u64,
+	/// Bytes in swap.
+	pub swap: u64,
+	pub shared_clean: u64,
+	pub shared_dirty: u64,
+	pub private_clean: u64,
+	pub private_dirty: u64,
+}
+impl MemoryPageStats {
+	/// Bytes which were modified since they were loaded.
+	pub const fn dirty(&self) -> u64 {
+		self.shared_dirty + self.private_dirty
+	}
+
+	/// Bytes which are identical to their backing file, or are zero pages.
+	pub const fn clean(&self) -> u64 {
+		self.shared_clean + self.private_clean
+	}
+}
+
 /// Trait for objects that serve as memory map storages.
 ///
 /// The `containing_page` should only be implemented if the implementation can provide a more efficient search behavior.
@@ -169,6 +209,68 @@ pub trait MemoryMap {
 			.iter()
 			.find(|&p| offset >= p.address_range[0] && offset <= p.address_range[1])
 	}
+
+	/// Returns the lowest address at which `module` is mapped, see [`MemoryPageType::module_name`].
+	fn module_base(&self, module: &str) -> Option<OffsetType> {
+		self.pages()
+			.iter()
+			.filter(|page| page.page_type.module_name() == Some(module))
+			.map(MemoryPage::start)
+			.min()
+	}
+
+	/// Returns the module containing `offset` and the offset relative to its base.
+	fn module_offset(&self, offset: OffsetType) -> Option<(&str, u64)> {
+		let module = self
+			.pages()
+			.iter()
+			.find(|p| offset >= p.start() && offset < p.end())?
+			.page_type
+			.module_name()?;
+		let base = self.module_base(module)?;
+
+		Some((module, offset.get() - base.get()))
+	}
+
+	/// Returns the statistics of each page of [`pages`](MemoryMap::pages) in the same order, if they were loaded.
+	fn page_stats(&self) -> Option<&[MemoryPageStats]> {
+		None
+	}
+
+	/// Writes the pages in the `/proc/<pid>/maps` text format, see [`maps_format`](super::maps_format).
+	///
+	/// The text can be parsed back with [`maps_format::parse`](super::maps_format::parse).
+	fn write_maps(&self, writer: &mut dyn std::io::Write) -> std::io::Result<()> {
+		super::maps_format::write(writer, self.pages())
+	}
+}
+impl MemoryMap for [MemoryPage] {
+	fn pages(&self) -> &[MemoryPage] {
+		self
+	}
+}
+
+/// Permissions are written in the `rwxp` format of [`parse_permissions`](super::maps_format::parse_permissions).
+#[cfg(feature = "serde")]
+mod serialization {
+	use serde::{de::Error, Deserialize, Deserializer, Serialize, Serializer};
+
+	use super::MemoryPagePermissions;
+	use crate::memory::maps_format::parse_permissions;
+
+	impl Serialize for MemoryPagePermissions {
+		fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+			serializer.collect_str(self)
+		}
+	}
+
+	impl<'de> Deserialize<'de> for MemoryPagePermissions {
+		fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+			let string = String::deserialize(deserializer)?;
+
+			parse_permissions(&string).map_err(D::Error::custom)
+		}
+	}
 }
 
 #[cfg(test)]
@@ -0,0 +1,467 @@
+//! Parsing and writing of memory maps in the `/proc/<pid>/maps` text format.
+//!
+//! This does not depend on a live process, so maps collected from other machines can be analyzed on any platform.
+//!
+//! Each line has the format `start-end perms offset dev inode path`, for example:
+//! ```text
+//! 55d0c2a4e000-55d0c2a6f000 rw-p 00000000 00:00 0                          [heap]
+//! ```
+
+use std::{
+	io::{Read, Write},
+	path::{Path, PathBuf},
+};
+
+use thiserror::Error;
+
+use crate::{
+	common::OffsetType,
+	error::{impl_from_kinded_error, ErrorKind},
+	memory::map::{MemoryPage, MemoryPagePermissions, MemoryPageStats, MemoryPageType},
+};
+
+/// Column at which the kernel starts the path, padding shorter lines with spaces.
+const PATH_COLUMN: usize = 73;
+
+#[derive(Debug, Error)]
+pub enum MemoryPagePermissionsParseError {
+	#[error("invalid read permission: {0:?}")]
+	InvalidRead(Option<char>),
+	#[error("invalid write permission: {0:?}")]
+	InvalidWrite(Option<char>),
+	#[error("invalid exec permission: {0:?}")]
+	InvalidExec(Option<char>),
+	#[error("invalid share permission: {0:?}")]
+	InvalidShare(Option<char>),
+}
+#[derive(Debug, Error)]
+pub enum MemoryPageParseError {
+	#[error("mapped range has invalid format")]
+	InvalidRange,
+	#[error("permissions have invalid format")]
+	InvalidPerms,
+	#[error("offset has invalid format")]
+	InvalidOffset,
+	#[error("devnode has invalid format")]
+	InvalidDevnode,
+	#[error("inode has invalid format")]
+	InvalidInode,
+	#[error("entry type has invalid format")]
+	InvalidEntry,
+	#[error("smaps statistic has invalid format")]
+	InvalidStat,
+
+	#[error("could not parse range bounds")]
+	ParseUsize(#[from] std::num::ParseIntError),
+	#[error("could not parse map permissions")]
+	ParseMapPerms(#[from] MemoryPagePermissionsParseError),
+}
+impl MemoryPageParseError {
+	pub fn kind(&self) -> ErrorKind {
+		ErrorKind::Parse
+	}
+}
+impl_from_kinded_error!(MemoryPageParseError);
+
+#[derive(Debug, Error)]
+pub enum MapsReadError {
+	#[error("could not read maps")]
+	Io(#[from] std::io::Error),
+	#[error(transparent)]
+	MemoryPageParseError(#[from] MemoryPageParseError),
+}
+impl MapsReadError {
+	pub fn kind(&self) -> ErrorKind {
+		match self {
+			MapsReadError::Io(err) => ErrorKind::from_io(err),
+			MapsReadError::MemoryPageParseError(err) => err.kind(),
+		}
+	}
+}
+impl_from_kinded_error!(MapsReadError);
+
+/// Parses permissions in the `rwxp` format.
+pub fn parse_permissions(
+	string: &str,
+) -> Result<MemoryPagePermissions, MemoryPagePermissionsParseError> {
+	let mut chars = string.trim().chars();
+
+	let read = match chars.next() {
+		Some('r') => true,
+		Some('-') => false,
+		ch => return Err(MemoryPagePermissionsParseError::InvalidRead(ch)),
+	};
+
+	let write = match chars.next() {
+		Some('w') => true,
+		Some('-') => false,
+		ch => return Err(MemoryPagePermissionsParseError::InvalidWrite(ch)),
+	};
+
+	let exec = match chars.next() {
+		Some('x') => true,
+		Some('-') => false,
+		ch => return Err(MemoryPagePermissionsParseError::InvalidExec(ch)),
+	};
+
+	let share = match chars.next() {
+		Some('s') => true,
+		Some('p') => false,
+		ch => return Err(MemoryPagePermissionsParseError::InvalidShare(ch)),
+	};
+
+	Ok(MemoryPagePermissions::new(read, write, exec, share))
+}
+
+/// Parses the path column into a page type.
+///
+/// Pages backed by `exe_path` are reported as [`MemoryPageType::ProcessExecutable`].
+pub fn parse_page_type(string: &str, exe_path: Option<&Path>) -> MemoryPageType {
+	match string.trim() {
+		"[stack]" => MemoryPageType::Stack,
+		"[heap]" => MemoryPageType::Heap,
+		"" => MemoryPageType::Anon,
+
+		// named anonymous memory, used heavily by Android for allocator and runtime regions
+		s if s.starts_with("[anon:") && s.ends_with(']') => {
+			parse_anon_name(&s["[anon:".len()..s.len() - 1])
+		}
+		// ashmem regions of older Android versions, reported as deleted files
+		s if s.starts_with("/dev/ashmem/") => {
+			parse_anon_name(s["/dev/ashmem/".len()..].trim_end_matches(" (deleted)"))
+		}
+		// thread stacks on older kernels
+		s if s.starts_with("[stack:") => MemoryPageType::Stack,
+
+		// [vvar] [vdso]
+		s if s.starts_with('[') && s.ends_with(']') => MemoryPageType::Unknown,
+		s if s.ends_with("(deleted)") => MemoryPageType::Unknown,
+
+		path => match exe_path {
+			Some(exe) if Path::new(path) == exe => {
+				MemoryPageType::ProcessExecutable(PathBuf::from(path))
+			}
+			_ => MemoryPageType::File(PathBuf::from(path)),
+		},
+	}
+}
+
+/// Classifies named anonymous memory by the name its owner assigned to it.
+fn parse_anon_name(name: &str) -> MemoryPageType {
+	if name.starts_with("libc_malloc")
+		|| name.starts_with("scudo:")
+		|| name.starts_with("dalvik-main space")
+		|| name.starts_with("dalvik-large object space")
+	{
+		MemoryPageType::Heap
+	} else if name.starts_with("stack_and_tls:") || name.starts_with("thread stack") {
+		MemoryPageType::Stack
+	} else {
+		MemoryPageType::Anon
+	}
+}
+
+/// Parses one line of maps text.
+pub fn parse_line(line: &str, exe_path: Option<&Path>) -> Result<MemoryPage, MemoryPageParseError> {
+	let mut rest = line;
+	let mut next_field = || {
+		let field;
+		(field, rest) = rest
+			.trim_start()
+			.split_once(char::is_whitespace)
+			.unwrap_or((rest.trim_start(), ""));
+
+		Some(field).filter(|field| !field.is_empty())
+	};
+
+	let (from, to) = next_field()
+		.and_then(|range| range.split_once('-'))
+		.ok_or(MemoryPageParseError::InvalidRange)?;
+	let from = OffsetType::new(u64::from_str_radix(from, 16)?)
+		.ok_or(MemoryPageParseError::InvalidRange)?;
+	let to =
+		OffsetType::new(u64::from_str_radix(to, 16)?).ok_or(MemoryPageParseError::InvalidRange)?;
+
+	let permissions = parse_permissions(next_field().ok_or(MemoryPageParseError::InvalidPerms)?)?;
+
+	let offset = next_field()
+		.and_then(|offset| u64::from_str_radix(offset, 16).ok())
+		.ok_or(MemoryPageParseError::InvalidOffset)?;
+
+	next_field()
+		.filter(|devnode| devnode.contains(':'))
+		.ok_or(MemoryPageParseError::InvalidDevnode)?;
+	next_field()
+		.and_then(|inode| inode.parse::<u64>().ok())
+		.ok_or(MemoryPageParseError::InvalidInode)?;
+
+	let page_type = parse_page_type(rest, exe_path);
+
+	Ok(MemoryPage {
+		address_range: [from, to],
+		permissions,
+		offset,
+		page_type,
+	})
+}
+
+/// Parses maps text, skipping empty lines.
+pub fn parse(text: &str, exe_path: Option<&Path>) -> Result<Vec<MemoryPage>, MemoryPageParseError> {
+	text.lines()
+		.filter(|line| !line.trim().is_empty())
+		.map(|line| parse_line(line, exe_path))
+		.collect()
+}
+
+/// Parses maps text, skipping lines which cannot be parsed.
+///
+/// Returns the pages and the number of skipped lines. Android hides parts of some entries from processes
+/// confined by SELinux, which leaves lines that the strict [`parse`] rejects.
+pub fn parse_lossy(text: &str, exe_path: Option<&Path>) -> (Vec<MemoryPage>, usize) {
+	let mut skipped = 0;
+	let pages = text
+		.lines()
+		.filter(|line| !line.trim().is_empty())
+		.filter_map(|line| {
+			let page = parse_line(line, exe_path).ok();
+			if page.is_none() {
+				skipped += 1;
+			}
+
+			page
+		})
+		.collect();
+
+	(pages, skipped)
+}
+
+/// Parses the `/proc/<pid>/smaps` text, which follows each maps line with lines of statistics such as `Rss:  12 kB`.
+///
+/// Returns the pages and their statistics in the same order, unknown statistics are ignored.
+pub fn parse_smaps(
+	text: &str,
+	exe_path: Option<&Path>,
+) -> Result<(Vec<MemoryPage>, Vec<MemoryPageStats>), MemoryPageParseError> {
+	let mut pages = Vec::new();
+	let mut stats: Vec<MemoryPageStats> = Vec::new();
+
+	for line in text.lines().filter(|line| !line.trim().is_empty()) {
+		// maps lines start with the range, statistic lines with the key
+		let (key, value) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+		let Some(key) = key.strip_suffix(':') else {
+			pages.push(parse_line(line, exe_path)?);
+			stats.push(MemoryPageStats::default());
+			continue;
+		};
+
+		let page_stats = stats.last_mut().ok_or(MemoryPageParseError::InvalidStat)?;
+		let field = match key {
+			"Rss" => &mut page_stats.resident,
+			"Swap" => &mut page_stats.swap,
+			"Shared_Clean" => &mut page_stats.shared_clean,
+			"Shared_Dirty" => &mut page_stats.shared_dirty,
+			"Private_Clean" => &mut page_stats.private_clean,
+			"Private_Dirty" => &mut page_stats.private_dirty,
+			_ => continue,
+		};
+		*field = value
+			.trim()
+			.strip_suffix("kB")
+			.and_then(|kib| kib.trim_end().parse::<u64>().ok())
+			.ok_or(MemoryPageParseError::InvalidStat)?
+			* 1024;
+	}
+
+	Ok((pages, stats))
+}
+
+/// Reads and parses maps text from `reader`.
+pub fn read(
+	mut reader: impl Read,
+	exe_path: Option<&Path>,
+) -> Result<Vec<MemoryPage>, MapsReadError> {
+	let mut buffer = String::new();
+	// TODO: Lets hope there not invalid unicode in the file paths
+	reader.read_to_string(&mut buffer)?;
+
+	Ok(parse(&buffer, exe_path)?)
+}
+
+/// Formats `page` as one line of maps text, without the line terminator.
+///
+/// Device and inode are not tracked by [`MemoryPage`] and are written as zero.
+/// Pages of [`MemoryPageType::Unknown`] are written with the `[unknown]` path, which is parsed back as unknown.
+pub fn format_line(page: &MemoryPage) -> String {
+	let mut line = format!(
+		"{:08x}-{:08x} {} {:08x} 00:00 0",
+		page.start().get(),
+		page.end().get(),
+		page.permissions,
+		page.offset
+	);
+
+	let path = match &page.page_type {
+		MemoryPageType::Anon => return line,
+		MemoryPageType::Unknown => "[unknown]".into(),
+		MemoryPageType::Stack => "[stack]".into(),
+		MemoryPageType::Heap => "[heap]".into(),
+		MemoryPageType::ProcessExecutable(path) | MemoryPageType::File(path) => {
+			path.to_string_lossy()
+		}
+	};
+	let padding = PATH_COLUMN.saturating_sub(line.len()).max(1);
+	line.extend(std::iter::repeat_n(' ', padding));
+	line.push_str(&path);
+
+	line
+}
+
+/// Writes `pages` as maps text, one line per page.
+pub fn write<'a>(
+	mut writer: impl Write,
+	pages: impl IntoIterator<Item = &'a MemoryPage>,
+) -> std::io::Result<()> {
+	for page in pages {
+		writeln!(writer, "{}", format_line(page))?;
+	}
+
+	Ok(())
+}
+
+#[cfg(test)]
+mod test {
+	use std::path::{Path, PathBuf};
+
+	use super::{format_line, parse, parse_line, parse_lossy, parse_smaps, read, write};
+	use crate::{
+		memory::map::{MemoryPage, MemoryPagePermissions, MemoryPageStats, MemoryPageType},
+		prelude::OffsetType,
+	};
+
+	const MAPS: &str = "\
+55d0c2a3c000-55d0c2a3e000 r--p 00000000 fd:01 1311032                    /usr/bin/cat
+55d0c2a3e000-55d0c2a43000 r-xp 00002000 fd:01 1311032                    /usr/bin/cat
+55d0c2a4e000-55d0c2a6f000 rw-p 00000000 00:00 0                          [heap]
+7f1e5c000000-7f1e5c021000 rw-p 00000000 00:00 0
+7f1e5c200000-7f1e5c222000 r--p 00000000 fd:01 1315005                    /usr/lib/my lib.so
+7ffd4b9e9000-7ffd4ba0a000 rw-p 00000000 00:00 0                          [stack]
+7ffd4ba2d000-7ffd4ba31000 r--p 00000000 00:00 0                          [vvar]
+";
+
+	#[test]
+	fn test_maps_parse() {
+		let line = "1f0-20f rw-p 0 00:00 0 [heap]";
+		assert_eq!(
+			parse_line(line, None).unwrap(),
+			MemoryPage {
+				address_range: [OffsetType::new_unwrap(496), OffsetType::new_unwrap(527)],
+				permissions: MemoryPagePermissions::new(true, true, false, false),
+				offset: 0,
+				page_type: MemoryPageType::Heap
+			}
+		);
+
+		let pages = read(MAPS.as_bytes(), Some(Path::new("/usr/bin/cat"))).unwrap();
+		assert_eq!(pages.len(), 7);
+		assert_eq!(pages[1].offset, 0x2000);
+		assert_eq!(
+			pages[1].page_type,
+			MemoryPageType::ProcessExecutable(PathBuf::from("/usr/bin/cat"))
+		);
+		assert_eq!(pages[3].page_type, MemoryPageType::Anon);
+		assert_eq!(
+			pages[4].page_type,
+			MemoryPageType::File(PathBuf::from("/usr/lib/my lib.so"))
+		);
+		assert_eq!(pages[6].page_type, MemoryPageType::Unknown);
+
+		assert_eq!(format_line(&pages[2]), MAPS.lines().nth(2).unwrap());
+
+		let mut text = Vec::new();
+		write(&mut text, &pages).unwrap();
+		let text = String::from_utf8(text).unwrap();
+		assert_eq!(
+			parse(&text, Some(Path::new("/usr/bin/cat"))).unwrap(),
+			pages
+		);
+
+		parse_line("1000-2000 rw-p zz 00:00 0", None).unwrap_err();
+		parse_line("1000-2000 rw-p 0 00:00", None).unwrap_err();
+	}
+
+	#[test]
+	fn test_maps_parse_android() {
+		const ANDROID_MAPS: &str = "\
+12c00000-32c00000 rw-p 00000000 00:00 0                                  [anon:dalvik-main space (region space)]
+6f5a1000-6f5a2000 rw-p 00000000 00:05 10243                              /dev/ashmem/dalvik-main space (deleted)
+7b4b200000-7b4b400000 rw-p 00000000 00:00 0                              [anon:libc_malloc]
+7b4c000000-7b4c0fe000 rw-p 00000000 00:00 0                              [anon:stack_and_tls:2045]
+7b4d000000-7b4d001000 r--p 00000000 00:00 0                              [anon:linker_alloc]
+7b4e000000-7b4e001000 r--p
+";
+
+		let (pages, skipped) = parse_lossy(ANDROID_MAPS, None);
+		assert_eq!(skipped, 1);
+		assert_eq!(
+			pages
+				.iter()
+				.map(|page| page.page_type.clone())
+				.collect::<Vec<_>>(),
+			[
+				MemoryPageType::Heap,
+				MemoryPageType::Heap,
+				MemoryPageType::Heap,
+				MemoryPageType::Stack,
+				MemoryPageType::Anon
+			]
+		);
+
+		parse(ANDROID_MAPS, None).unwrap_err();
+	}
+
+	#[test]
+	fn test_smaps_parse() {
+		const SMAPS: &str = "\
+55d0c2a4e000-55d0c2a6f000 rw-p 00000000 00:00 0                          [heap]
+Size:                132 kB
+KernelPageSize:        4 kB
+Rss:                  12 kB
+Pss:                  12 kB
+Shared_Clean:          0 kB
+Shared_Dirty:          0 kB
+Private_Clean:         4 kB
+Private_Dirty:         8 kB
+Swap:                 16 kB
+VmFlags: rd wr mr mw me ac sd
+7f1e5c200000-7f1e5c222000 r--p 00000000 fd:01 1315005                    /usr/lib/libc.so
+Rss:                 136 kB
+Shared_Clean:        136 kB
+";
+
+		let (pages, stats) = parse_smaps(SMAPS, None).unwrap();
+		assert_eq!(pages.len(), 2);
+		assert_eq!(pages[0].page_type, MemoryPageType::Heap);
+		assert_eq!(
+			stats,
+			[
+				MemoryPageStats {
+					resident: 12 * 1024,
+					swap: 16 * 1024,
+					shared_clean: 0,
+					shared_dirty: 0,
+					private_clean: 4 * 1024,
+					private_dirty: 8 * 1024,
+				},
+				MemoryPageStats {
+					resident: 136 * 1024,
+					shared_clean: 136 * 1024,
+					..Default::default()
+				}
+			]
+		);
+		assert_eq!(stats[0].dirty(), 8 * 1024);
+
+		parse_smaps("Rss: 4 kB", None).unwrap_err();
+		parse_smaps("1000-2000 rw-p 0 00:00 0\nRss: many kB", None).unwrap_err();
+	}
+}
@@ -1,5 +1,13 @@
 //! Abstractions around different platforms/memory access interfaces.
 
 pub mod access;
+pub mod allocate;
+pub mod chunked;
+pub mod classify;
+pub mod freeze;
 pub mod lock;
 pub mod map;
+pub mod maps_format;
+pub mod monitor;
+pub mod protect;
+pub mod watcher;
@@ -0,0 +1,598 @@
+//! Monitoring of memory map and memory content changes.
+//!
+//! The [`MapMonitor`] periodically reloads the memory map and diffs it against the previous one.
+//! Permission changes such as writable memory becoming executable are a good trigger to rescan code,
+//! since that is what JIT compilers and unpackers do.
+//!
+//! The [`RegionMonitor`] periodically re-reads a set of small regions and reports which bytes changed.
+//! Watching several candidate structures while performing an action in the target reveals which of them actually updates.
+
+use std::{
+	sync::mpsc::{self, Receiver},
+	thread::JoinHandle,
+	time::Duration,
+};
+
+use crate::common::OffsetType;
+
+use super::{
+	access::{MemoryAccess, ReadError},
+	map::{MemoryMap, MemoryPage, MemoryPagePermissions, MemoryPageType},
+};
+
+/// Change between two memory maps.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MapEvent {
+	/// Range was mapped.
+	Mapped {
+		range: [OffsetType; 2],
+		permissions: MemoryPagePermissions,
+		page_type: MemoryPageType,
+	},
+	/// Range was unmapped.
+	Unmapped {
+		range: [OffsetType; 2],
+		permissions: MemoryPagePermissions,
+		page_type: MemoryPageType,
+	},
+	/// Permissions of a mapped range changed.
+	PermissionsChanged {
+		range: [OffsetType; 2],
+		old: MemoryPagePermissions,
+		new: MemoryPagePermissions,
+		page_type: MemoryPageType,
+	},
+}
+impl MapEvent {
+	pub const fn range(&self) -> [OffsetType; 2] {
+		match self {
+			MapEvent::Mapped { range, .. } => *range,
+			MapEvent::Unmapped { range, .. } => *range,
+			MapEvent::PermissionsChanged { range, .. } => *range,
+		}
+	}
+
+	fn range_mut(&mut self) -> &mut [OffsetType; 2] {
+		match self {
+			MapEvent::Mapped { range, .. } => range,
+			MapEvent::Unmapped { range, .. } => range,
+			MapEvent::PermissionsChanged { range, .. } => range,
+		}
+	}
+
+	/// Returns whether the range is executable after this change and was not executable before.
+	///
+	/// This covers both newly mapped executable ranges and ranges made executable.
+	pub const fn became_executable(&self) -> bool {
+		match self {
+			MapEvent::Mapped { permissions, .. } => permissions.exec(),
+			MapEvent::Unmapped { .. } => false,
+			MapEvent::PermissionsChanged { old, new, .. } => !old.exec() && new.exec(),
+		}
+	}
+}
+impl std::fmt::Display for MapEvent {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		match self {
+			MapEvent::Mapped {
+				range,
+				permissions,
+				page_type,
+			} => write!(
+				f,
+				"mapped {}-{} {} {}",
+				range[0], range[1], permissions, page_type
+			),
+			MapEvent::Unmapped {
+				range,
+				permissions,
+				page_type,
+			} => write!(
+				f,
+				"unmapped {}-{} {} {}",
+				range[0], range[1], permissions, page_type
+			),
+			MapEvent::PermissionsChanged {
+				range,
+				old,
+				new,
+				page_type,
+			} => write!(
+				f,
+				"changed {}-{} {} -> {} {}",
+				range[0], range[1], old, new, page_type
+			),
+		}
+	}
+}
+
+/// Returns the page from `pages` containing `offset`, advancing `index` past pages which end before it.
+fn page_at<'p>(
+	pages: &'p [MemoryPage],
+	index: &mut usize,
+	offset: OffsetType,
+) -> Option<&'p MemoryPage> {
+	while *index < pages.len() && pages[*index].end() <= offset {
+		*index += 1;
+	}
+
+	pages.get(*index).filter(|page| page.start() <= offset)
+}
+
+/// Diffs two sorted lists of pages and returns the changes from `old` to `new`.
+///
+/// Adjacent ranges with the same change are reported as one event.
+pub fn diff_maps(old: &[MemoryPage], new: &[MemoryPage]) -> Vec<MapEvent> {
+	// every page boundary in either map starts an interval which is entirely inside or outside of each page
+	let mut boundaries: Vec<OffsetType> = old
+		.iter()
+		.chain(new.iter())
+		.flat_map(|page| page.address_range)
+		.collect();
+	boundaries.sort_unstable();
+	boundaries.dedup();
+
+	let mut events: Vec<MapEvent> = Vec::new();
+	let (mut old_index, mut new_index) = (0, 0);
+	for interval in boundaries.windows(2) {
+		let range = [interval[0], interval[1]];
+		let old_page = page_at(old, &mut old_index, range[0]);
+		let new_page = page_at(new, &mut new_index, range[0]);
+
+		let event = match (old_page, new_page) {
+			(None, None) => continue,
+			(Some(old_page), Some(new_page)) => {
+				if old_page.permissions == new_page.permissions {
+					continue;
+				}
+
+				MapEvent::PermissionsChanged {
+					range,
+					old: old_page.permissions,
+					new: new_page.permissions,
+					page_type: new_page.page_type.clone(),
+				}
+			}
+			(None, Some(page)) => MapEvent::Mapped {
+				range,
+				permissions: page.permissions,
+				page_type: page.page_type.clone(),
+			},
+			(Some(page), None) => MapEvent::Unmapped {
+				range,
+				permissions: page.permissions,
+				page_type: page.page_type.clone(),
+			},
+		};
+
+		// merge with the previous event if it is the same change of an adjacent range
+		if let Some(last) = events.last_mut() {
+			let mut same = event.clone();
+			*same.range_mut() = last.range();
+			if last.range()[1] == range[0] && *last == same {
+				last.range_mut()[1] = range[1];
+				continue;
+			}
+		}
+
+		events.push(event);
+	}
+
+	events
+}
+
+/// Polls a memory map and reports changes to it.
+///
+/// The map is loaded by a user provided function, so the monitor works with any [`MemoryMap`] implementation.
+pub struct MapMonitor<F> {
+	load: F,
+	previous: Vec<MemoryPage>,
+	interval: Duration,
+}
+impl<M: MemoryMap, E, F: FnMut() -> Result<M, E>> MapMonitor<F> {
+	/// Creates a new monitor which polls every `interval`, loading the initial map right away.
+	pub fn new(mut load: F, interval: Duration) -> Result<Self, E> {
+		let previous = load()?.pages().to_vec();
+
+		Ok(MapMonitor {
+			load,
+			previous,
+			interval,
+		})
+	}
+
+	pub const fn interval(&self) -> Duration {
+		self.interval
+	}
+
+	/// Returns the pages of the last loaded map.
+	pub fn pages(&self) -> &[MemoryPage] {
+		&self.previous
+	}
+
+	/// Reloads the map and returns the changes since the last poll.
+	pub fn poll(&mut self) -> Result<Vec<MapEvent>, E> {
+		let current = (self.load)()?.pages().to_vec();
+		let events = diff_maps(&self.previous, &current);
+		self.previous = current;
+
+		Ok(events)
+	}
+
+	/// Polls every [`interval`](MapMonitor::interval) and calls `on_event` for each change until it returns `false`.
+	pub fn run(&mut self, mut on_event: impl FnMut(MapEvent) -> bool) -> Result<(), E> {
+		loop {
+			std::thread::sleep(self.interval);
+
+			for event in self.poll()? {
+				if !on_event(event) {
+					return Ok(());
+				}
+			}
+		}
+	}
+}
+impl<M: MemoryMap, E: Send + 'static, F: FnMut() -> Result<M, E> + Send + 'static> MapMonitor<F> {
+	/// Runs the monitor on a new thread and returns a channel of the changes.
+	///
+	/// The thread stops after the first error, which is sent through the channel,
+	/// or once the receiver is dropped.
+	pub fn spawn(mut self) -> (Receiver<Result<MapEvent, E>>, JoinHandle<()>) {
+		let (sender, receiver) = mpsc::channel();
+
+		let handle = std::thread::spawn(move || {
+			let result = self.run(|event| sender.send(Ok(event)).is_ok());
+			if let Err(err) = result {
+				let _ = sender.send(Err(err));
+			}
+		});
+
+		(receiver, handle)
+	}
+}
+
+/// Returns the ranges of bytes which differ between `old` and `new`, both read at `base`.
+///
+/// Only the common prefix of the two slices is compared.
+pub fn diff_bytes(base: OffsetType, old: &[u8], new: &[u8]) -> Vec<[OffsetType; 2]> {
+	let mut ranges: Vec<[OffsetType; 2]> = Vec::new();
+
+	for (index, _) in old
+		.iter()
+		.zip(new.iter())
+		.enumerate()
+		.filter(|(_, (old, new))| old != new)
+	{
+		let offset = base.saturating_add(index as u64);
+		match ranges.last_mut() {
+			Some(last) if last[1] == offset => last[1] = offset.saturating_add(1),
+			_ => ranges.push([offset, offset.saturating_add(1)]),
+		}
+	}
+
+	ranges
+}
+
+/// Change of the contents of a region watched by [`RegionMonitor`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RegionChange {
+	pub label: String,
+	/// Range of the whole region.
+	pub region: [OffsetType; 2],
+	/// Ranges of the bytes which changed, adjacent bytes are merged.
+	pub ranges: Vec<[OffsetType; 2]>,
+}
+impl std::fmt::Display for RegionChange {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		write!(f, "{} {}-{}:", self.label, self.region[0], self.region[1])?;
+		for range in self.ranges.iter() {
+			write!(
+				f,
+				" +{:x}..{:x}",
+				range[0].get() - self.region[0].get(),
+				range[1].get() - self.region[0].get()
+			)?;
+		}
+
+		Ok(())
+	}
+}
+
+/// Region watched by [`RegionMonitor`].
+#[derive(Debug, Clone)]
+pub struct MonitoredRegion {
+	label: String,
+	offset: OffsetType,
+	size: usize,
+	contents: Option<Vec<u8>>,
+	change_count: usize,
+}
+impl MonitoredRegion {
+	pub fn label(&self) -> &str {
+		&self.label
+	}
+
+	pub fn range(&self) -> [OffsetType; 2] {
+		[self.offset, self.offset.saturating_add(self.size as u64)]
+	}
+
+	pub const fn size(&self) -> usize {
+		self.size
+	}
+
+	/// Returns the contents from the last poll, if the region was polled already.
+	pub fn contents(&self) -> Option<&[u8]> {
+		self.contents.as_deref()
+	}
+
+	/// Returns the number of polls which saw this region change.
+	pub const fn change_count(&self) -> usize {
+		self.change_count
+	}
+}
+
+/// Polls the contents of a set of regions and reports changes to them.
+///
+/// This works on raw bytes and is independent of scanning, the regions are compared against their contents from the previous poll.
+pub struct RegionMonitor {
+	regions: Vec<MonitoredRegion>,
+	interval: Duration,
+}
+impl RegionMonitor {
+	/// Creates a new monitor without regions which polls every `interval`.
+	pub const fn new(interval: Duration) -> Self {
+		RegionMonitor {
+			regions: Vec::new(),
+			interval,
+		}
+	}
+
+	pub const fn interval(&self) -> Duration {
+		self.interval
+	}
+
+	pub fn regions(&self) -> &[MonitoredRegion] {
+		&self.regions
+	}
+
+	pub fn region(&self, label: &str) -> Option<&MonitoredRegion> {
+		self.regions.iter().find(|region| region.label == label)
+	}
+
+	/// Adds a region of `size` bytes at `offset`, its contents are read on the next poll.
+	pub fn add(&mut self, label: impl Into<String>, offset: OffsetType, size: usize) {
+		self.regions.push(MonitoredRegion {
+			label: label.into(),
+			offset,
+			size,
+			contents: None,
+			change_count: 0,
+		});
+	}
+
+	/// Removes all regions with `label` and returns whether any was removed.
+	pub fn remove(&mut self, label: &str) -> bool {
+		let len = self.regions.len();
+		self.regions.retain(|region| region.label != label);
+
+		self.regions.len() != len
+	}
+
+	/// Re-reads all regions and returns the changes since the last poll.
+	///
+	/// Regions added since the last poll are read but never reported as changed. All regions are read with one
+	/// [`read_v`](MemoryAccess::read_v) call, if it fails no region is updated.
+	///
+	/// ## Safety
+	/// * See [`MemoryAccess::read`].
+	pub unsafe fn poll<A: MemoryAccess + ?Sized>(
+		&mut self,
+		access: &mut A,
+	) -> Result<Vec<RegionChange>, ReadError> {
+		let mut changes = Vec::new();
+		self.poll_with(access, |region, previous| {
+			changes.push(RegionChange {
+				label: region.label.clone(),
+				region: region.range(),
+				ranges: diff_bytes(
+					region.offset,
+					&previous,
+					region.contents().unwrap_or_default(),
+				),
+			})
+		})?;
+
+		Ok(changes)
+	}
+
+	/// Re-reads all regions like [`poll`](RegionMonitor::poll) and calls `on_change` with each changed region and its previous contents.
+	pub(crate) unsafe fn poll_with<A: MemoryAccess + ?Sized>(
+		&mut self,
+		access: &mut A,
+		mut on_change: impl FnMut(&MonitoredRegion, Vec<u8>),
+	) -> Result<(), ReadError> {
+		let mut buffers: Vec<Vec<u8>> = self
+			.regions
+			.iter()
+			.map(|region| vec![0u8; region.size])
+			.collect();
+		let mut requests: Vec<(OffsetType, &mut [u8])> = self
+			.regions
+			.iter()
+			.zip(buffers.iter_mut())
+			.map(|(region, buffer)| (region.offset, buffer.as_mut_slice()))
+			.collect();
+		access.read_v(&mut requests)?;
+
+		for (region, current) in self.regions.iter_mut().zip(buffers) {
+			match region.contents.replace(current) {
+				Some(previous) if region.contents.as_ref() != Some(&previous) => {
+					region.change_count += 1;
+					on_change(region, previous);
+				}
+				_ => (),
+			}
+		}
+
+		Ok(())
+	}
+
+	/// Polls every [`interval`](RegionMonitor::interval) and calls `on_change` for each change until it returns `false`.
+	///
+	/// ## Safety
+	/// * See [`MemoryAccess::read`].
+	pub unsafe fn run<A: MemoryAccess + ?Sized>(
+		&mut self,
+		access: &mut A,
+		mut on_change: impl FnMut(RegionChange) -> bool,
+	) -> Result<(), ReadError> {
+		self.poll(access)?;
+
+		loop {
+			std::thread::sleep(self.interval);
+
+			for change in self.poll(access)? {
+				if !on_change(change) {
+					return Ok(());
+				}
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use std::{
+		sync::{Arc, Mutex},
+		time::Duration,
+	};
+
+	use crate::{
+		common::OffsetType,
+		memory::{
+			access::MemoryAccess,
+			map::{MemoryMap, MemoryPage, MemoryPagePermissions, MemoryPageType},
+		},
+		platform::mock::MockMemoryAccess,
+	};
+
+	use super::{diff_maps, MapEvent, MapMonitor, RegionChange, RegionMonitor};
+
+	const RW: MemoryPagePermissions = MemoryPagePermissions::new(true, true, false, false);
+	const RX: MemoryPagePermissions = MemoryPagePermissions::new(true, false, true, false);
+
+	fn page(start: u64, end: u64, permissions: MemoryPagePermissions) -> MemoryPage {
+		MemoryPage {
+			address_range: [OffsetType::new_unwrap(start), OffsetType::new_unwrap(end)],
+			permissions,
+			offset: 0,
+			page_type: MemoryPageType::Anon,
+		}
+	}
+
+	fn range(start: u64, end: u64) -> [OffsetType; 2] {
+		[OffsetType::new_unwrap(start), OffsetType::new_unwrap(end)]
+	}
+
+	struct PagesMap(Vec<MemoryPage>);
+	impl MemoryMap for PagesMap {
+		fn pages(&self) -> &[MemoryPage] {
+			&self.0
+		}
+	}
+
+	#[test]
+	fn test_diff_maps() {
+		let old = [page(0x1000, 0x3000, RW), page(0x5000, 0x6000, RW)];
+		let new = [
+			page(0x1000, 0x2000, RW),
+			page(0x2000, 0x2800, RX),
+			page(0x2800, 0x3000, RX),
+			page(0x8000, 0x9000, RX),
+		];
+
+		let events = diff_maps(&old, &new);
+		assert_eq!(
+			events,
+			[
+				MapEvent::PermissionsChanged {
+					range: range(0x2000, 0x3000),
+					old: RW,
+					new: RX,
+					page_type: MemoryPageType::Anon
+				},
+				MapEvent::Unmapped {
+					range: range(0x5000, 0x6000),
+					permissions: RW,
+					page_type: MemoryPageType::Anon
+				},
+				MapEvent::Mapped {
+					range: range(0x8000, 0x9000),
+					permissions: RX,
+					page_type: MemoryPageType::Anon
+				}
+			]
+		);
+		assert!(events[0].became_executable());
+		assert!(!events[1].became_executable());
+		assert!(diff_maps(&new, &new).is_empty());
+	}
+
+	#[test]
+	fn test_map_monitor_spawn() {
+		let maps = Arc::new(Mutex::new(vec![page(0x1000, 0x2000, RW)]));
+
+		let load_maps = maps.clone();
+		let monitor = MapMonitor::new(
+			move || Ok::<_, ()>(PagesMap(load_maps.lock().unwrap().clone())),
+			Duration::from_millis(1),
+		)
+		.unwrap();
+		assert_eq!(monitor.pages().len(), 1);
+
+		let (receiver, _handle) = monitor.spawn();
+		maps.lock().unwrap()[0].permissions = RX;
+
+		let event = receiver.recv().unwrap().unwrap();
+		assert_eq!(
+			event,
+			MapEvent::PermissionsChanged {
+				range: range(0x1000, 0x2000),
+				old: RW,
+				new: RX,
+				page_type: MemoryPageType::Anon
+			}
+		);
+	}
+
+	#[test]
+	fn test_region_monitor_changes() {
+		let mut access =
+			MockMemoryAccess::new().with_region(OffsetType::new_unwrap(1), vec![0; 63]);
+		let mut monitor = RegionMonitor::new(Duration::from_millis(1));
+		monitor.add("first", OffsetType::new_unwrap(8), 8);
+		monitor.add("second", OffsetType::new_unwrap(32), 16);
+
+		unsafe {
+			assert!(monitor.poll(&mut access).unwrap().is_empty());
+
+			access
+				.write(OffsetType::new_unwrap(34), &[1, 2, 0, 0, 5])
+				.unwrap();
+			assert_eq!(
+				monitor.poll(&mut access).unwrap(),
+				[RegionChange {
+					label: "second".to_string(),
+					region: range(32, 48),
+					ranges: vec![range(34, 36), range(38, 39)]
+				}]
+			);
+			assert!(monitor.poll(&mut access).unwrap().is_empty());
+		}
+
+		assert_eq!(monitor.region("first").unwrap().change_count(), 0);
+		assert_eq!(monitor.region("second").unwrap().change_count(), 1);
+		assert!(monitor.remove("first"));
+		assert_eq!(monitor.regions().len(), 1);
+	}
+}
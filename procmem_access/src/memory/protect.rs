@@ -0,0 +1,83 @@
+//! Changing page permissions of the target process.
+//!
+//! Patching code requires writing to pages which are mapped read-only and executable. [`MemoryProtect`] changes
+//! the permissions of a range, so that the patch can be written and the original permissions restored afterwards.
+
+use thiserror::Error;
+
+use crate::{
+	common::OffsetType,
+	error::{ErrorKind, ProcmemError},
+	memory::map::MemoryPagePermissions,
+};
+
+use super::lock::{LockError, UnlockError};
+
+#[derive(Debug, Error)]
+pub enum ProtectError {
+	#[error("range must not be empty")]
+	EmptyRange,
+	#[error("could not lock the process")]
+	Lock(#[from] LockError),
+	#[error("could not unlock the process")]
+	Unlock(#[from] UnlockError),
+	#[error("could not change page protection")]
+	Io(#[from] std::io::Error),
+	#[error("platform specific error: {0}")]
+	PlatformError(Box<dyn std::error::Error + Send + Sync>),
+}
+impl ProtectError {
+	pub fn kind(&self) -> ErrorKind {
+		match self {
+			ProtectError::EmptyRange => ErrorKind::Platform,
+			ProtectError::Lock(err) => err.kind(),
+			ProtectError::Unlock(err) => err.kind(),
+			ProtectError::Io(err) => ErrorKind::from_io(err),
+			ProtectError::PlatformError(err) => ErrorKind::from_error(err.as_ref()),
+		}
+	}
+}
+impl From<ProtectError> for ProcmemError {
+	fn from(err: ProtectError) -> Self {
+		ProcmemError::new(err.kind(), err)
+	}
+}
+
+/// Trait implemented on abstractions which can change page permissions of the target process.
+pub trait MemoryProtect {
+	/// Sets the permissions of all pages overlapping `range` to `permissions`.
+	///
+	/// The range is extended to whole pages. The `shared` permission is ignored.
+	///
+	/// ## Safety
+	/// * The process faults if it accesses the pages in a way the new permissions do not allow.
+	/// * The process may be stopped or have code run in it for the duration of the call, depending on the platform.
+	unsafe fn protect(
+		&mut self,
+		range: [OffsetType; 2],
+		permissions: MemoryPagePermissions,
+	) -> Result<(), ProtectError>;
+}
+
+/// Extends `range` to whole pages of `page_size`, which must be a power of two, and returns its start and size.
+#[cfg(any(
+	all(
+		any(target_os = "linux", target_os = "android"),
+		target_arch = "x86_64"
+	),
+	target_os = "macos"
+))]
+pub(crate) fn page_range(
+	range: [OffsetType; 2],
+	page_size: u64,
+) -> Result<(u64, u64), ProtectError> {
+	let [start, end] = range;
+	if end <= start {
+		return Err(ProtectError::EmptyRange);
+	}
+
+	let start = start.get() & !(page_size - 1);
+	let end = (end.get() + page_size - 1) & !(page_size - 1);
+
+	Ok((start, end - start))
+}
@@ -0,0 +1,244 @@
+//! Software watchpoints.
+//!
+//! The [`MemoryWatcher`] polls a set of values and reports each change together with the old and new bytes.
+//! It keeps the values in a [`RegionMonitor`], but also owns the access and a lock, taking the lock
+//! for each poll so that all values of one poll are read from the same stopped state of the process.
+
+use std::{
+	sync::mpsc::{self, Receiver},
+	thread::JoinHandle,
+	time::Duration,
+};
+
+use thiserror::Error;
+
+use crate::{
+	common::OffsetType,
+	error::{ErrorKind, ProcmemError},
+};
+
+use super::{
+	access::{MemoryAccess, ReadError},
+	lock::{LockError, MemoryLock, UnlockError},
+	monitor::{diff_bytes, RegionMonitor},
+};
+
+#[derive(Debug, Error)]
+pub enum WatchError {
+	#[error("could not read watched values")]
+	Read(#[from] ReadError),
+	#[error("could not lock the process")]
+	Lock(#[from] LockError),
+	#[error("could not unlock the process")]
+	Unlock(#[from] UnlockError),
+}
+impl WatchError {
+	pub fn kind(&self) -> ErrorKind {
+		match self {
+			WatchError::Read(err) => err.kind(),
+			WatchError::Lock(err) => err.kind(),
+			WatchError::Unlock(err) => err.kind(),
+		}
+	}
+}
+impl From<WatchError> for ProcmemError {
+	fn from(err: WatchError) -> Self {
+		ProcmemError::new(err.kind(), err)
+	}
+}
+
+/// Change of a value watched by [`MemoryWatcher`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WatchEvent {
+	pub offset: OffsetType,
+	pub old: Vec<u8>,
+	pub new: Vec<u8>,
+}
+impl WatchEvent {
+	/// Returns the ranges of the bytes which changed, adjacent bytes are merged.
+	pub fn ranges(&self) -> Vec<[OffsetType; 2]> {
+		diff_bytes(self.offset, &self.old, &self.new)
+	}
+}
+impl std::fmt::Display for WatchEvent {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		write!(f, "{}:", self.offset)?;
+		for byte in self.old.iter() {
+			write!(f, " {:02x}", byte)?;
+		}
+		write!(f, " ->")?;
+		for byte in self.new.iter() {
+			write!(f, " {:02x}", byte)?;
+		}
+
+		Ok(())
+	}
+}
+
+/// Polls watched values every interval and reports their changes.
+///
+/// Each value is a region of the inner [`RegionMonitor`] labelled with its offset.
+pub struct MemoryWatcher<A: MemoryAccess, L: MemoryLock> {
+	access: A,
+	lock: L,
+	monitor: RegionMonitor,
+}
+impl<A: MemoryAccess, L: MemoryLock> MemoryWatcher<A, L> {
+	/// Creates a new watcher without watched values which polls every `interval`.
+	pub fn new(access: A, lock: L, interval: Duration) -> Self {
+		MemoryWatcher {
+			access,
+			lock,
+			monitor: RegionMonitor::new(interval),
+		}
+	}
+
+	pub const fn interval(&self) -> Duration {
+		self.monitor.interval()
+	}
+
+	/// Returns the watched offsets and lengths.
+	pub fn watched(&self) -> impl Iterator<Item = (OffsetType, usize)> + '_ {
+		self.monitor
+			.regions()
+			.iter()
+			.map(|region| (region.range()[0], region.size()))
+	}
+
+	/// Watches `length` bytes at `offset`, replacing an existing watch at the same offset.
+	///
+	/// The value is first read by the next poll, which does not report it as changed.
+	pub fn watch(&mut self, offset: OffsetType, length: usize) {
+		self.unwatch(offset);
+		self.monitor.add(offset.to_string(), offset, length);
+	}
+
+	/// Stops watching the value at `offset` and returns whether it was watched.
+	pub fn unwatch(&mut self, offset: OffsetType) -> bool {
+		self.monitor.remove(&offset.to_string())
+	}
+
+	/// Returns the access and the lock.
+	pub fn into_inner(self) -> (A, L) {
+		(self.access, self.lock)
+	}
+
+	/// Locks the process, re-reads all watched values with one vectored read and returns the changes since the last poll.
+	///
+	/// The process is unlocked even if the read fails.
+	///
+	/// ## Safety
+	/// * See [`MemoryAccess::read`].
+	pub unsafe fn poll(&mut self) -> Result<Vec<WatchEvent>, WatchError> {
+		let mut events = Vec::new();
+
+		self.lock.lock()?;
+		let result = self.monitor.poll_with(&mut self.access, |region, old| {
+			events.push(WatchEvent {
+				offset: region.range()[0],
+				old,
+				new: region.contents().unwrap_or_default().to_vec(),
+			})
+		});
+		self.lock.unlock()?;
+		result?;
+
+		Ok(events)
+	}
+
+	/// Polls every [`interval`](MemoryWatcher::interval) and calls `on_event` for each change until it returns `false`.
+	///
+	/// ## Safety
+	/// * See [`MemoryAccess::read`].
+	pub unsafe fn run(
+		&mut self,
+		mut on_event: impl FnMut(WatchEvent) -> bool,
+	) -> Result<(), WatchError> {
+		self.poll()?;
+
+		loop {
+			std::thread::sleep(self.interval());
+
+			for event in self.poll()? {
+				if !on_event(event) {
+					return Ok(());
+				}
+			}
+		}
+	}
+}
+impl<A: MemoryAccess + Send + 'static, L: MemoryLock + Send + 'static> MemoryWatcher<A, L> {
+	/// Runs the watcher on a new thread and returns a channel of the changes.
+	///
+	/// The thread stops after the first error, which is sent through the channel,
+	/// or once the receiver is dropped.
+	///
+	/// ## Safety
+	/// * See [`MemoryAccess::read`].
+	pub unsafe fn spawn(mut self) -> (Receiver<Result<WatchEvent, WatchError>>, JoinHandle<()>) {
+		let (sender, receiver) = mpsc::channel();
+
+		let handle = std::thread::spawn(move || {
+			let result = unsafe { self.run(|event| sender.send(Ok(event)).is_ok()) };
+			if let Err(err) = result {
+				let _ = sender.send(Err(err));
+			}
+		});
+
+		(receiver, handle)
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use std::time::Duration;
+
+	use super::{MemoryWatcher, WatchError, WatchEvent};
+	use crate::{
+		common::OffsetType,
+		memory::access::MemoryAccess,
+		platform::mock::{MockMemoryAccess, MockMemoryLock},
+	};
+
+	#[test]
+	fn test_memory_watcher_poll() {
+		let base = OffsetType::new_unwrap(0x1000);
+		let access = MockMemoryAccess::new().with_region(base, vec![0; 64]);
+		let mut watcher =
+			MemoryWatcher::new(access, MockMemoryLock::new(), Duration::from_millis(1));
+		watcher.watch(base.saturating_add(8), 4);
+		watcher.watch(base.saturating_add(16), 2);
+
+		unsafe {
+			assert!(watcher.poll().unwrap().is_empty());
+
+			watcher.access.write(base.saturating_add(9), &[7]).unwrap();
+			let events = watcher.poll().unwrap();
+			assert_eq!(
+				events,
+				[WatchEvent {
+					offset: base.saturating_add(8),
+					old: vec![0, 0, 0, 0],
+					new: vec![0, 7, 0, 0]
+				}]
+			);
+			assert_eq!(
+				events[0].ranges(),
+				[[base.saturating_add(9), base.saturating_add(10)]]
+			);
+			assert!(watcher.poll().unwrap().is_empty());
+		}
+		assert_eq!(watcher.lock.acquired(), 3);
+
+		// failed reads still unlock the process
+		watcher.watch(base.saturating_add(100), 4);
+		assert!(matches!(
+			unsafe { watcher.poll() },
+			Err(WatchError::Read(_))
+		));
+		assert!(!watcher.lock.is_locked());
+
+		assert!(watcher.unwatch(base.saturating_add(100)));
+		assert_eq!(watcher.watched().count(), 2);
+	}
+}
@@ -0,0 +1,97 @@
+use thiserror::Error;
+
+use crate::{
+	common::OffsetType,
+	error::{impl_from_kinded_error, ErrorKind},
+	memory::access::{MemoryAccess, ReadError, WriteError},
+};
+
+#[derive(Debug, Error)]
+pub enum BsdAccessError {
+	#[error("process could not be found")]
+	ProcessNotFound(#[source] std::io::Error),
+}
+impl BsdAccessError {
+	pub fn kind(&self) -> ErrorKind {
+		match self {
+			BsdAccessError::ProcessNotFound(err) => ErrorKind::from_io(err),
+		}
+	}
+}
+impl_from_kinded_error!(BsdAccessError);
+
+/// Access through `ptrace(PT_IO)`.
+///
+/// The process must be locked by a [`BsdLock`](super::BsdLock) of this process while accessing memory.
+pub struct BsdAccess {
+	pid: libc::pid_t,
+}
+impl BsdAccess {
+	pub fn new(pid: libc::pid_t) -> Result<Self, BsdAccessError> {
+		if unsafe { libc::kill(pid, 0) } != 0 {
+			return Err(BsdAccessError::ProcessNotFound(
+				std::io::Error::last_os_error(),
+			));
+		}
+
+		Ok(BsdAccess { pid })
+	}
+
+	pub const fn pid(&self) -> libc::pid_t {
+		self.pid
+	}
+
+	/// Transfers `length` bytes between `offset` in the process and `local`, returning the number of bytes transferred.
+	unsafe fn transfer(
+		&mut self,
+		operation: libc::c_int,
+		offset: OffsetType,
+		local: *mut u8,
+		length: usize,
+	) -> std::io::Result<usize> {
+		let mut desc = libc::ptrace_io_desc {
+			piod_op: operation,
+			piod_offs: offset.get() as *mut libc::c_void,
+			piod_addr: local as *mut libc::c_void,
+			piod_len: length,
+		};
+
+		let result = libc::ptrace(
+			libc::PT_IO,
+			self.pid,
+			&mut desc as *mut libc::ptrace_io_desc as _,
+			0,
+		);
+		if result != 0 {
+			return Err(std::io::Error::last_os_error());
+		}
+
+		Ok(desc.piod_len)
+	}
+}
+impl MemoryAccess for BsdAccess {
+	unsafe fn read(&mut self, offset: OffsetType, buffer: &mut [u8]) -> Result<(), ReadError> {
+		let count = self.transfer(libc::PIOD_READ_D, offset, buffer.as_mut_ptr(), buffer.len())?;
+		// the transfer stops at the first unmapped page
+		if count != buffer.len() {
+			return Err(ReadError::NotMapped);
+		}
+
+		Ok(())
+	}
+
+	unsafe fn write(&mut self, offset: OffsetType, data: &[u8]) -> Result<(), WriteError> {
+		// the buffer is only read by the kernel for writes
+		let count = self.transfer(
+			libc::PIOD_WRITE_D,
+			offset,
+			data.as_ptr() as *mut u8,
+			data.len(),
+		)?;
+		if count != data.len() {
+			return Err(WriteError::NotMapped);
+		}
+
+		Ok(())
+	}
+}
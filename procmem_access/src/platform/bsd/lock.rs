@@ -0,0 +1,157 @@
+use thiserror::Error;
+
+use crate::{
+	error::{impl_from_kinded_error, ErrorKind},
+	memory::lock::{LockError, MemoryLock, UnlockError},
+};
+
+#[derive(Debug, Error)]
+pub enum BsdLockError {
+	#[error("ptrace attach failed")]
+	PtraceAttach(#[source] std::io::Error),
+	#[error("stopping failed")]
+	StopError(#[source] std::io::Error),
+	#[error("waitpid failed")]
+	WaitpidError(#[source] std::io::Error),
+	#[error("ptrace continue failed")]
+	PtraceCont(#[source] std::io::Error),
+	#[error("ptrace detach failed")]
+	PtraceDetach(#[source] std::io::Error),
+}
+impl BsdLockError {
+	pub fn kind(&self) -> ErrorKind {
+		ErrorKind::from_error(self)
+	}
+}
+impl_from_kinded_error!(BsdLockError);
+impl From<BsdLockError> for LockError {
+	fn from(err: BsdLockError) -> Self {
+		LockError::PlatformError(Box::new(err))
+	}
+}
+impl From<BsdLockError> for UnlockError {
+	fn from(err: BsdLockError) -> Self {
+		UnlockError::PlatformError(Box::new(err))
+	}
+}
+
+/// Lock which traces the process with ptrace and stops it with `SIGSTOP`.
+///
+/// Unlike Linux, the tracer is the whole process and not the attaching thread.
+pub struct BsdLock {
+	pid: libc::pid_t,
+	lock_counter: usize,
+}
+impl BsdLock {
+	pub fn new(pid: libc::pid_t) -> Result<Self, BsdLockError> {
+		let mut me = BsdLock {
+			pid,
+			lock_counter: 0,
+		};
+
+		unsafe {
+			// attaching stops the process
+			if libc::ptrace(libc::PT_ATTACH, pid, std::ptr::null_mut(), 0) != 0 {
+				return Err(BsdLockError::PtraceAttach(std::io::Error::last_os_error()));
+			}
+			me.wait_for_stop()?;
+			me.ptrace_cont()?;
+		}
+
+		Ok(me)
+	}
+
+	pub const fn pid(&self) -> libc::pid_t {
+		self.pid
+	}
+
+	unsafe fn wait_for_stop(&mut self) -> Result<(), BsdLockError> {
+		let waitpid_res = libc::waitpid(self.pid, std::ptr::null_mut(), 0);
+		if waitpid_res == -1 {
+			return Err(BsdLockError::WaitpidError(std::io::Error::last_os_error()));
+		}
+
+		Ok(())
+	}
+
+	unsafe fn ptrace_stop(&mut self) -> Result<(), BsdLockError> {
+		if libc::kill(self.pid, libc::SIGSTOP) != 0 {
+			return Err(BsdLockError::StopError(std::io::Error::last_os_error()));
+		}
+		self.wait_for_stop()?;
+
+		Ok(())
+	}
+
+	unsafe fn ptrace_cont(&mut self) -> Result<(), BsdLockError> {
+		// address 1 continues where the process stopped, the intercepted stop signal is not delivered
+		let ptrace_res = libc::ptrace(libc::PT_CONTINUE, self.pid, 1 as _, 0);
+		if ptrace_res != 0 {
+			return Err(BsdLockError::PtraceCont(std::io::Error::last_os_error()));
+		}
+
+		Ok(())
+	}
+
+	unsafe fn ptrace_detach(&mut self) -> Result<(), BsdLockError> {
+		let ptrace_res = libc::ptrace(libc::PT_DETACH, self.pid, 1 as _, 0);
+		if ptrace_res != 0 {
+			return Err(BsdLockError::PtraceDetach(std::io::Error::last_os_error()));
+		}
+
+		Ok(())
+	}
+}
+impl MemoryLock for BsdLock {
+	fn lock(&mut self) -> Result<bool, LockError> {
+		if self.lock_counter == 0 {
+			unsafe { self.ptrace_stop()? };
+			self.lock_counter = 1;
+
+			Ok(true)
+		} else if self.lock_counter == usize::MAX {
+			Err(LockError::AlreadyLocked)
+		} else {
+			self.lock_counter += 1;
+
+			Ok(false)
+		}
+	}
+
+	fn lock_exlusive(&mut self) -> Result<(), LockError> {
+		if self.lock_counter == 0 {
+			self.lock()?;
+			self.lock_counter = usize::MAX;
+
+			Ok(())
+		} else {
+			Err(LockError::AlreadyLocked)
+		}
+	}
+
+	fn unlock(&mut self) -> Result<bool, UnlockError> {
+		if self.lock_counter == 0 {
+			return Err(UnlockError::NotLocked);
+		}
+
+		if self.lock_counter == 1 || self.lock_counter == usize::MAX {
+			unsafe { self.ptrace_cont()? };
+			self.lock_counter = 0;
+
+			Ok(true)
+		} else {
+			self.lock_counter -= 1;
+
+			Ok(false)
+		}
+	}
+}
+impl Drop for BsdLock {
+	fn drop(&mut self) {
+		// detaching requires the process to be stopped and resumes it
+		if self.lock_counter == 0 && unsafe { self.ptrace_stop() }.is_err() {
+			return;
+		}
+		let _ = unsafe { self.ptrace_detach() };
+	}
+}
@@ -0,0 +1,120 @@
+use std::{ffi::CStr, os::unix::ffi::OsStrExt, path::PathBuf};
+
+use thiserror::Error;
+
+use crate::{
+	common::OffsetType,
+	error::{impl_from_kinded_error, ErrorKind},
+	memory::map::{MemoryMap, MemoryPage, MemoryPagePermissions, MemoryPageType},
+};
+
+// `KVME_TYPE_VNODE` has the same value on both systems, but libc only defines it for FreeBSD
+const KVME_TYPE_VNODE: u32 = 2;
+
+#[derive(Debug, Error)]
+pub enum BsdMemoryMapError {
+	#[error("could not read the memory map")]
+	VmMap(#[source] std::io::Error),
+}
+impl BsdMemoryMapError {
+	pub fn kind(&self) -> ErrorKind {
+		match self {
+			BsdMemoryMapError::VmMap(err) => ErrorKind::from_io(err),
+		}
+	}
+}
+impl_from_kinded_error!(BsdMemoryMapError);
+
+/// Array of entries allocated by `kinfo_getvmmap`, freed on drop.
+struct VmMap {
+	entries: *mut libc::kinfo_vmentry,
+	count: usize,
+}
+impl VmMap {
+	fn new(pid: libc::pid_t) -> std::io::Result<Self> {
+		let mut count = 0;
+		let entries = unsafe { libc::kinfo_getvmmap(pid, &mut count) };
+		if entries.is_null() {
+			return Err(std::io::Error::last_os_error());
+		}
+
+		// the count is a signed integer on FreeBSD and `size_t` on NetBSD
+		#[allow(clippy::unnecessary_cast)]
+		let count = count as usize;
+
+		Ok(VmMap { entries, count })
+	}
+
+	fn entries(&self) -> &[libc::kinfo_vmentry] {
+		unsafe { std::slice::from_raw_parts(self.entries, self.count) }
+	}
+}
+impl Drop for VmMap {
+	fn drop(&mut self) {
+		unsafe { libc::free(self.entries as *mut libc::c_void) };
+	}
+}
+
+pub struct BsdMemoryMap {
+	pages: Vec<MemoryPage>,
+}
+impl BsdMemoryMap {
+	pub fn new(pid: libc::pid_t) -> Result<Self, BsdMemoryMapError> {
+		let map = VmMap::new(pid).map_err(BsdMemoryMapError::VmMap)?;
+		let executable = super::executable_path(pid).ok();
+
+		let pages = map
+			.entries()
+			.iter()
+			.filter_map(|entry| Self::page(entry, executable.as_ref()))
+			.collect();
+
+		Ok(BsdMemoryMap { pages })
+	}
+
+	fn page(entry: &libc::kinfo_vmentry, executable: Option<&PathBuf>) -> Option<MemoryPage> {
+		let protection = entry.kve_protection as libc::c_int;
+		let flags = entry.kve_flags as libc::c_int;
+
+		// the systems do not report whether a mapping is shared, only whether it is copy-on-write
+		let permissions = MemoryPagePermissions::new(
+			protection & libc::KVME_PROT_READ != 0,
+			protection & libc::KVME_PROT_WRITE != 0,
+			protection & libc::KVME_PROT_EXEC != 0,
+			false,
+		);
+
+		// FreeBSD declares the path as nested arrays for historical reasons
+		let path = unsafe { CStr::from_ptr(entry.kve_path.as_ptr() as *const libc::c_char) };
+		// the type is signed on FreeBSD and unsigned on NetBSD
+		#[allow(clippy::unnecessary_cast)]
+		let entry_type = entry.kve_type as u32;
+		let page_type = if entry_type == KVME_TYPE_VNODE && !path.is_empty() {
+			let path = PathBuf::from(std::ffi::OsStr::from_bytes(path.to_bytes()));
+			if Some(&path) == executable {
+				MemoryPageType::ProcessExecutable(path)
+			} else {
+				MemoryPageType::File(path)
+			}
+		} else if flags & libc::KVME_FLAG_GROWS_DOWN != 0 {
+			MemoryPageType::Stack
+		} else {
+			MemoryPageType::Anon
+		};
+
+		Some(MemoryPage {
+			address_range: [
+				OffsetType::new(entry.kve_start)?,
+				OffsetType::new(entry.kve_end)?,
+			],
+			permissions,
+			offset: entry.kve_offset,
+			page_type,
+		})
+	}
+}
+impl MemoryMap for BsdMemoryMap {
+	fn pages(&self) -> &[MemoryPage] {
+		&self.pages
+	}
+}
@@ -0,0 +1,200 @@
+//! FreeBSD and NetBSD backend using sysctl, `kinfo_getvmmap` from libutil and ptrace.
+//!
+//! Memory is accessed with `ptrace(PT_IO)`, which requires the process to be traced and stopped,
+//! so reads and writes only succeed while the process is locked by a [`BsdLock`].
+
+use std::ffi::CStr;
+
+use crate::common::{select_one, FindError, NamePattern};
+
+pub mod access;
+pub mod lock;
+pub mod map;
+
+pub use access::BsdAccess;
+pub use lock::BsdLock;
+pub use map::BsdMemoryMap;
+
+/// Reads sysctl `mib` into a vector of `T`, growing the buffer while the result does not fit.
+///
+/// `fix_mib` is called with the capacity in elements before each read, for mibs which encode the element count.
+///
+/// ## Safety
+/// * `T` must be the element type returned by the sysctl and valid when zeroed.
+unsafe fn sysctl_array<T>(
+	mib: &mut [libc::c_int],
+	mut fix_mib: impl FnMut(&mut [libc::c_int], usize),
+) -> std::io::Result<Vec<T>> {
+	loop {
+		fix_mib(mib, 0);
+		let mut size: libc::size_t = 0;
+		if libc::sysctl(
+			mib.as_ptr(),
+			mib.len() as libc::c_uint,
+			std::ptr::null_mut(),
+			&mut size,
+			std::ptr::null(),
+			0,
+		) != 0
+		{
+			return Err(std::io::Error::last_os_error());
+		}
+
+		// leave room for processes created between the two calls
+		let capacity = size / std::mem::size_of::<T>() + 16;
+		let mut buffer: Vec<T> = Vec::with_capacity(capacity);
+		std::ptr::write_bytes(buffer.as_mut_ptr(), 0, capacity);
+		fix_mib(mib, capacity);
+
+		let mut size = capacity * std::mem::size_of::<T>();
+		if libc::sysctl(
+			mib.as_ptr(),
+			mib.len() as libc::c_uint,
+			buffer.as_mut_ptr() as *mut libc::c_void,
+			&mut size,
+			std::ptr::null(),
+			0,
+		) != 0
+		{
+			let err = std::io::Error::last_os_error();
+			if err.raw_os_error() == Some(libc::ENOMEM) {
+				continue;
+			}
+
+			return Err(err);
+		}
+		buffer.set_len(size / std::mem::size_of::<T>());
+
+		return Ok(buffer);
+	}
+}
+
+/// Returns the path of the executable of process `pid`.
+fn executable_path(pid: libc::pid_t) -> std::io::Result<std::path::PathBuf> {
+	use std::os::unix::ffi::OsStrExt;
+
+	#[cfg(target_os = "freebsd")]
+	let mib = [
+		libc::CTL_KERN,
+		libc::KERN_PROC,
+		libc::KERN_PROC_PATHNAME,
+		pid,
+	];
+	#[cfg(target_os = "netbsd")]
+	let mib = [
+		libc::CTL_KERN,
+		libc::KERN_PROC_ARGS,
+		pid,
+		libc::KERN_PROC_PATHNAME,
+	];
+
+	let mut buffer = [0u8; libc::PATH_MAX as usize];
+	let mut size = buffer.len();
+	let result = unsafe {
+		libc::sysctl(
+			mib.as_ptr(),
+			mib.len() as libc::c_uint,
+			buffer.as_mut_ptr() as *mut libc::c_void,
+			&mut size,
+			std::ptr::null(),
+			0,
+		)
+	};
+	if result != 0 {
+		return Err(std::io::Error::last_os_error());
+	}
+
+	let path = CStr::from_bytes_until_nul(&buffer[..size])
+		.map(CStr::to_bytes)
+		.unwrap_or(&buffer[..size]);
+
+	Ok(std::ffi::OsStr::from_bytes(path).into())
+}
+
+#[derive(Debug, Clone)]
+pub struct ProcessInfo {
+	pub pid: libc::pid_t,
+	pub name: String,
+}
+impl std::fmt::Display for ProcessInfo {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		write!(f, "{} ({})", self.pid, self.name)
+	}
+}
+impl ProcessInfo {
+	#[cfg(target_os = "freebsd")]
+	fn list(filter: libc::c_int, argument: libc::c_int) -> std::io::Result<Vec<Self>> {
+		let mut mib = [libc::CTL_KERN, libc::KERN_PROC, filter, argument];
+		let processes: Vec<libc::kinfo_proc> = unsafe { sysctl_array(&mut mib, |_, _| ())? };
+
+		Ok(processes
+			.iter()
+			.map(|process| ProcessInfo {
+				pid: process.ki_pid,
+				name: unsafe { CStr::from_ptr(process.ki_comm.as_ptr()) }
+					.to_string_lossy()
+					.into_owned(),
+			})
+			.collect())
+	}
+
+	#[cfg(target_os = "netbsd")]
+	fn list(filter: libc::c_int, argument: libc::c_int) -> std::io::Result<Vec<Self>> {
+		let mut mib = [
+			libc::CTL_KERN,
+			libc::KERN_PROC2,
+			filter,
+			argument,
+			std::mem::size_of::<libc::kinfo_proc2>() as libc::c_int,
+			0,
+		];
+		let processes: Vec<libc::kinfo_proc2> = unsafe {
+			sysctl_array(&mut mib, |mib, capacity| {
+				mib[5] = capacity as libc::c_int;
+			})?
+		};
+
+		Ok(processes
+			.iter()
+			.map(|process| ProcessInfo {
+				pid: process.p_pid,
+				name: unsafe { CStr::from_ptr(process.p_comm.as_ptr()) }
+					.to_string_lossy()
+					.into_owned(),
+			})
+			.collect())
+	}
+
+	pub fn list_all() -> std::io::Result<Vec<Self>> {
+		#[cfg(target_os = "freebsd")]
+		let filter = libc::KERN_PROC_PROC;
+		#[cfg(target_os = "netbsd")]
+		let filter = libc::KERN_PROC_ALL;
+
+		Self::list(filter, 0)
+	}
+
+	/// Lists all processes accepted by `predicate`.
+	pub fn list_filtered(predicate: impl FnMut(&Self) -> bool) -> std::io::Result<Vec<Self>> {
+		Ok(Self::list_all()?.into_iter().filter(predicate).collect())
+	}
+
+	/// Lists all processes with names matching `pattern`.
+	pub fn find_by_name(pattern: NamePattern) -> std::io::Result<Vec<Self>> {
+		Self::list_filtered(|process| pattern.matches(&process.name))
+	}
+
+	/// Finds the one process with name matching `pattern`, preferring an exact name match when more processes match.
+	pub fn find_one_by_name(pattern: NamePattern) -> Result<Self, FindError<Self>> {
+		select_one(pattern, Self::find_by_name(pattern)?, |process| {
+			&process.name
+		})
+	}
+
+	pub fn for_pid(pid: libc::pid_t) -> std::io::Result<Self> {
+		Self::list(libc::KERN_PROC_PID, pid)?
+			.into_iter()
+			.next()
+			.ok_or_else(|| std::io::Error::from_raw_os_error(libc::ESRCH))
+	}
+}
@@ -0,0 +1,393 @@
+//! Detection of the reasons why attaching to a process is denied.
+//!
+//! The operating system reports all of them as a plain permission error. [`diagnose`] inspects the system
+//! configuration, this process and the target process to find the actual obstacles and
+//! [`AttachObstacle::suggest_fix`] describes how to resolve each of them.
+
+use thiserror::Error;
+
+use crate::error::{impl_from_kinded_error, ErrorKind};
+
+/// Reason why attaching to a process is denied.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AttachObstacle {
+	/// Yama restricts ptrace to descendants of the tracer (`ptrace_scope` 1).
+	#[cfg(any(target_os = "linux", target_os = "android"))]
+	YamaRestricted,
+	/// Yama restricts ptrace to tracers with `CAP_SYS_PTRACE` (`ptrace_scope` 2).
+	#[cfg(any(target_os = "linux", target_os = "android"))]
+	YamaAdminOnly,
+	/// Yama disables ptrace until reboot (`ptrace_scope` 3).
+	#[cfg(any(target_os = "linux", target_os = "android"))]
+	YamaDisabled,
+	/// The target runs as a different user and this process is not privileged.
+	DifferentUser { uid: u32, target_uid: u32 },
+	/// This process is not code signed, so it cannot carry the debugger entitlement.
+	#[cfg(target_os = "macos")]
+	UnsignedBinary,
+	/// This process is signed without the `com.apple.security.cs.debugger` entitlement.
+	#[cfg(target_os = "macos")]
+	MissingEntitlement,
+	/// The target is a platform binary protected by System Integrity Protection.
+	#[cfg(target_os = "macos")]
+	SystemIntegrityProtection,
+	/// The target uses the hardened runtime without the `get-task-allow` entitlement.
+	#[cfg(target_os = "macos")]
+	HardenedRuntime,
+}
+impl AttachObstacle {
+	/// Returns a short instruction for the user on how to remove this obstacle.
+	pub fn suggest_fix(&self) -> &'static str {
+		match self {
+			#[cfg(any(target_os = "linux", target_os = "android"))]
+			AttachObstacle::YamaRestricted => "run as root, grant CAP_SYS_PTRACE (`setcap cap_sys_ptrace=eip <binary>`) or set `kernel.yama.ptrace_scope` to 0",
+			#[cfg(any(target_os = "linux", target_os = "android"))]
+			AttachObstacle::YamaAdminOnly => "run as root or grant CAP_SYS_PTRACE (`setcap cap_sys_ptrace=eip <binary>`)",
+			#[cfg(any(target_os = "linux", target_os = "android"))]
+			AttachObstacle::YamaDisabled => "`kernel.yama.ptrace_scope` 3 cannot be lowered without a reboot",
+			#[cfg(any(target_os = "linux", target_os = "android"))]
+			AttachObstacle::DifferentUser { .. } => "run as the user of the target process, as root or with CAP_SYS_PTRACE",
+			#[cfg(not(any(target_os = "linux", target_os = "android")))]
+			AttachObstacle::DifferentUser { .. } => "run as root",
+			#[cfg(target_os = "macos")]
+			AttachObstacle::UnsignedBinary => "run as root or sign the binary with the `com.apple.security.cs.debugger` entitlement",
+			#[cfg(target_os = "macos")]
+			AttachObstacle::MissingEntitlement => "run as root or add the `com.apple.security.cs.debugger` entitlement to the signature",
+			#[cfg(target_os = "macos")]
+			AttachObstacle::SystemIntegrityProtection => "disable debugging restrictions of System Integrity Protection (`csrutil enable --without debug`)",
+			#[cfg(target_os = "macos")]
+			AttachObstacle::HardenedRuntime => "re-sign the target with the `com.apple.security.get-task-allow` entitlement or disable System Integrity Protection",
+		}
+	}
+}
+impl std::fmt::Display for AttachObstacle {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		match self {
+			#[cfg(any(target_os = "linux", target_os = "android"))]
+			AttachObstacle::YamaRestricted => {
+				write!(f, "yama ptrace_scope only allows tracing descendants")
+			}
+			#[cfg(any(target_os = "linux", target_os = "android"))]
+			AttachObstacle::YamaAdminOnly => {
+				write!(f, "yama ptrace_scope requires CAP_SYS_PTRACE")
+			}
+			#[cfg(any(target_os = "linux", target_os = "android"))]
+			AttachObstacle::YamaDisabled => write!(f, "yama ptrace_scope disables ptrace"),
+			AttachObstacle::DifferentUser { uid, target_uid } => write!(
+				f,
+				"target runs as user {} instead of user {}",
+				target_uid, uid
+			),
+			#[cfg(target_os = "macos")]
+			AttachObstacle::UnsignedBinary => write!(f, "this binary is not code signed"),
+			#[cfg(target_os = "macos")]
+			AttachObstacle::MissingEntitlement => {
+				write!(f, "this binary lacks the debugger entitlement")
+			}
+			#[cfg(target_os = "macos")]
+			AttachObstacle::SystemIntegrityProtection => {
+				write!(f, "target is protected by System Integrity Protection")
+			}
+			#[cfg(target_os = "macos")]
+			AttachObstacle::HardenedRuntime => {
+				write!(f, "target uses the hardened runtime")
+			}
+		}
+	}
+}
+
+/// Attaching was denied, together with the obstacles found by [`diagnose`].
+#[derive(Debug, Error)]
+#[error("attaching to process {pid} was denied")]
+pub struct AttachDeniedError {
+	pub pid: libc::pid_t,
+	/// May be empty if no known obstacle was found.
+	pub obstacles: Vec<AttachObstacle>,
+	#[source]
+	pub source: std::io::Error,
+}
+impl AttachDeniedError {
+	/// Diagnoses why attaching to `pid` failed with `source`.
+	pub fn diagnose(pid: libc::pid_t, source: std::io::Error) -> Self {
+		AttachDeniedError {
+			pid,
+			obstacles: diagnose(pid).unwrap_or_default(),
+			source,
+		}
+	}
+
+	/// Returns the fixes suggested by the obstacles.
+	pub fn suggest_fix(&self) -> impl Iterator<Item = &'static str> + '_ {
+		self.obstacles.iter().map(AttachObstacle::suggest_fix)
+	}
+
+	pub fn kind(&self) -> ErrorKind {
+		ErrorKind::PermissionDenied
+	}
+}
+impl_from_kinded_error!(AttachDeniedError);
+
+/// Returns whether `err` is a permission error worth diagnosing.
+pub(crate) fn is_denied(err: &std::io::Error) -> bool {
+	err.kind() == std::io::ErrorKind::PermissionDenied
+}
+
+/// Finds the obstacles which prevent this process from attaching to process `pid`.
+///
+/// An empty list does not guarantee that attaching succeeds, not all restrictions can be detected.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub fn diagnose(pid: libc::pid_t) -> std::io::Result<Vec<AttachObstacle>> {
+	// <https://www.kernel.org/doc/html/latest/admin-guide/LSM/Yama.html>
+	const CAP_SYS_PTRACE: u32 = 19;
+
+	let own_status = std::fs::read_to_string("/proc/self/status")?;
+	let target_status = std::fs::read_to_string(format!("/proc/{}/status", pid))?;
+
+	let privileged = status_field(&own_status, "CapEff")
+		.and_then(|caps| u64::from_str_radix(caps, 16).ok())
+		.map(|caps| caps & (1 << CAP_SYS_PTRACE) != 0)
+		.unwrap_or(false);
+
+	let mut obstacles = Vec::new();
+	match ptrace_scope()? {
+		Some(1) if !privileged && !is_descendant(pid)? => {
+			obstacles.push(AttachObstacle::YamaRestricted)
+		}
+		Some(2) if !privileged => obstacles.push(AttachObstacle::YamaAdminOnly),
+		Some(3) => obstacles.push(AttachObstacle::YamaDisabled),
+		_ => (),
+	}
+
+	// the real, effective and saved uids of the target must all match our real uid
+	let uid = unsafe { libc::getuid() };
+	let target_uids = status_uids(&target_status);
+	if !privileged && target_uids.iter().any(|&target_uid| target_uid != uid) {
+		obstacles.push(AttachObstacle::DifferentUser {
+			uid,
+			target_uid: target_uids.get(1).copied().unwrap_or(uid),
+		});
+	}
+
+	Ok(obstacles)
+}
+
+/// Returns the yama `ptrace_scope` or `None` if yama is not enabled.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn ptrace_scope() -> std::io::Result<Option<u8>> {
+	match std::fs::read_to_string("/proc/sys/kernel/yama/ptrace_scope") {
+		Ok(scope) => scope
+			.trim()
+			.parse()
+			.map(Some)
+			.map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err)),
+		Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+		Err(err) => Err(err),
+	}
+}
+
+/// Returns whether process `pid` is a descendant of this process.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn is_descendant(mut pid: libc::pid_t) -> std::io::Result<bool> {
+	let own_pid = std::process::id() as libc::pid_t;
+
+	while pid > 1 {
+		let status = std::fs::read_to_string(format!("/proc/{}/status", pid))?;
+		pid = status_field(&status, "PPid")
+			.and_then(|ppid| ppid.parse().ok())
+			.unwrap_or(0);
+
+		if pid == own_pid {
+			return Ok(true);
+		}
+	}
+
+	Ok(false)
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn status_field<'a>(status: &'a str, name: &str) -> Option<&'a str> {
+	status.lines().find_map(|line| {
+		let (key, value) = line.split_once(':')?;
+		(key == name).then(|| value.trim())
+	})
+}
+
+/// Returns the real, effective, saved and filesystem uids from a status file.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn status_uids(status: &str) -> Vec<u32> {
+	status_field(status, "Uid")
+		.map(|uids| {
+			uids.split_whitespace()
+				.take(3)
+				.filter_map(|uid| uid.parse().ok())
+				.collect()
+		})
+		.unwrap_or_default()
+}
+
+#[cfg(target_os = "macos")]
+mod codesign {
+	// <https://opensource.apple.com/source/xnu/xnu-7195.81.3/osfmk/kern/cs_blobs.h.auto.html>
+	pub const CS_OPS_STATUS: u32 = 0;
+	pub const CS_OPS_ENTITLEMENTS_BLOB: u32 = 7;
+
+	pub const CS_VALID: u32 = 0x0000_0001;
+	pub const CS_GET_TASK_ALLOW: u32 = 0x0000_0004;
+	pub const CS_RUNTIME: u32 = 0x0001_0000;
+	pub const CS_PLATFORM_BINARY: u32 = 0x0400_0000;
+
+	// <https://opensource.apple.com/source/xnu/xnu-7195.81.3/bsd/sys/csr.h.auto.html>
+	pub const CSR_ALLOW_TASK_FOR_PID: u32 = 1 << 2;
+
+	extern "C" {
+		pub fn csops(
+			pid: libc::pid_t,
+			ops: u32,
+			useraddr: *mut libc::c_void,
+			usersize: libc::size_t,
+		) -> libc::c_int;
+
+		pub fn csr_check(mask: u32) -> libc::c_int;
+	}
+
+	/// Returns the code signing status flags of process `pid`.
+	pub fn status(pid: libc::pid_t) -> std::io::Result<u32> {
+		let mut flags: u32 = 0;
+		let result = unsafe {
+			csops(
+				pid,
+				CS_OPS_STATUS,
+				&mut flags as *mut u32 as *mut libc::c_void,
+				std::mem::size_of::<u32>(),
+			)
+		};
+		if result != 0 {
+			return Err(std::io::Error::last_os_error());
+		}
+
+		Ok(flags)
+	}
+
+	/// Returns the entitlements blob of process `pid`, which is empty if it has no entitlements.
+	pub fn entitlements(pid: libc::pid_t) -> std::io::Result<Vec<u8>> {
+		// the blob starts with a big endian magic and length
+		let mut header = [0u8; 8];
+		let result = unsafe {
+			csops(
+				pid,
+				CS_OPS_ENTITLEMENTS_BLOB,
+				header.as_mut_ptr() as *mut libc::c_void,
+				header.len(),
+			)
+		};
+		if result == 0 {
+			return Ok(Vec::new());
+		}
+		let err = std::io::Error::last_os_error();
+		if err.raw_os_error() != Some(libc::ERANGE) {
+			return Err(err);
+		}
+
+		let length = u32::from_be_bytes([header[4], header[5], header[6], header[7]]) as usize;
+		let mut blob = vec![0u8; length];
+		let result = unsafe {
+			csops(
+				pid,
+				CS_OPS_ENTITLEMENTS_BLOB,
+				blob.as_mut_ptr() as *mut libc::c_void,
+				blob.len(),
+			)
+		};
+		if result != 0 {
+			return Err(std::io::Error::last_os_error());
+		}
+
+		Ok(blob)
+	}
+}
+
+/// Finds the obstacles which prevent this process from attaching to process `pid`.
+///
+/// An empty list does not guarantee that attaching succeeds, not all restrictions can be detected.
+#[cfg(target_os = "macos")]
+pub fn diagnose(pid: libc::pid_t) -> std::io::Result<Vec<AttachObstacle>> {
+	const DEBUGGER_ENTITLEMENT: &[u8] = b"com.apple.security.cs.debugger";
+
+	let mut obstacles = Vec::new();
+
+	let uid = unsafe { libc::geteuid() };
+	if uid != 0 {
+		let own_pid = std::process::id() as libc::pid_t;
+		if codesign::status(own_pid)? & codesign::CS_VALID == 0 {
+			obstacles.push(AttachObstacle::UnsignedBinary);
+		} else if !codesign::entitlements(own_pid)?
+			.windows(DEBUGGER_ENTITLEMENT.len())
+			.any(|window| window == DEBUGGER_ENTITLEMENT)
+		{
+			obstacles.push(AttachObstacle::MissingEntitlement);
+		}
+
+		let mut info: libc::proc_bsdinfo = unsafe { std::mem::zeroed() };
+		let size = std::mem::size_of::<libc::proc_bsdinfo>() as libc::c_int;
+		let result = unsafe {
+			libc::proc_pidinfo(
+				pid,
+				libc::PROC_PIDTBSDINFO,
+				0,
+				&mut info as *mut libc::proc_bsdinfo as *mut libc::c_void,
+				size,
+			)
+		};
+		if result != size {
+			return Err(std::io::Error::last_os_error());
+		}
+		if info.pbi_uid != uid {
+			obstacles.push(AttachObstacle::DifferentUser {
+				uid,
+				target_uid: info.pbi_uid,
+			});
+		}
+	}
+
+	let sip_enabled = unsafe { codesign::csr_check(codesign::CSR_ALLOW_TASK_FOR_PID) } != 0;
+	if sip_enabled {
+		let flags = codesign::status(pid)?;
+		if flags & codesign::CS_PLATFORM_BINARY != 0 {
+			obstacles.push(AttachObstacle::SystemIntegrityProtection);
+		} else if flags & codesign::CS_RUNTIME != 0 && flags & codesign::CS_GET_TASK_ALLOW == 0 {
+			obstacles.push(AttachObstacle::HardenedRuntime);
+		}
+	}
+
+	Ok(obstacles)
+}
+
+#[cfg(all(test, any(target_os = "linux", target_os = "android")))]
+mod test {
+	use std::process::Command;
+
+	use super::{diagnose, is_descendant, status_uids, AttachObstacle};
+
+	#[test]
+	fn test_status_uids() {
+		let status = "Name:\tsleep\nUid:\t1000\t1000\t0\t1000\nGid:\t1000\t1000\t1000\t1000\n";
+		assert_eq!(status_uids(status), [1000, 1000, 0]);
+	}
+
+	#[test]
+	fn test_diagnose_child() {
+		let mut child = Command::new("sleep").arg("30").spawn().unwrap();
+		let pid = child.id() as libc::pid_t;
+
+		assert!(is_descendant(pid).unwrap());
+		// a child of the same user is traceable unless yama forbids tracing completely
+		let obstacles = diagnose(pid).unwrap();
+		assert!(obstacles
+			.iter()
+			.all(|obstacle| *obstacle == AttachObstacle::YamaDisabled
+				|| *obstacle == AttachObstacle::YamaAdminOnly));
+
+		child.kill().unwrap();
+		child.wait().unwrap();
+	}
+}
@@ -0,0 +1,86 @@
+use std::{
+	fs::File,
+	io::{BufReader, Read, Seek, SeekFrom},
+	path::Path,
+};
+
+use crate::{
+	common::{Endianness, OffsetType},
+	memory::access::{MemoryAccess, ReadError, WriteError},
+};
+
+use super::{CoreDumpError, CoreFile, Segment};
+
+/// Read-only access to the memory stored in an ELF core dump.
+///
+/// Reads are served from the `PT_LOAD` segments and may span adjacent segments. All writes fail with [`WriteError::NotPermitted`].
+#[derive(Debug)]
+pub struct CoreDumpAccess<R: Read + Seek = BufReader<File>> {
+	reader: R,
+	endianness: Endianness,
+	/// Segments sorted by address.
+	segments: Vec<Segment>,
+}
+impl CoreDumpAccess {
+	pub fn open(path: impl AsRef<Path>) -> Result<Self, CoreDumpError> {
+		Self::new(BufReader::new(File::open(path)?))
+	}
+}
+impl<R: Read + Seek> CoreDumpAccess<R> {
+	/// Parses the headers of the core dump in `reader`, the segment data is read on demand.
+	pub fn new(mut reader: R) -> Result<Self, CoreDumpError> {
+		let core = CoreFile::parse(&mut reader)?;
+
+		Ok(CoreDumpAccess {
+			reader,
+			endianness: core.endianness,
+			segments: core.segments,
+		})
+	}
+
+	/// Returns the byte order of the dumped process.
+	pub const fn endianness(&self) -> Endianness {
+		self.endianness
+	}
+
+	pub fn into_inner(self) -> R {
+		self.reader
+	}
+}
+impl<R: Read + Seek> MemoryAccess for CoreDumpAccess<R> {
+	unsafe fn read(&mut self, offset: OffsetType, buffer: &mut [u8]) -> Result<(), ReadError> {
+		let mut index = self
+			.segments
+			.partition_point(|segment| segment.address_range[1] <= offset);
+
+		let mut offset = offset.get();
+		let mut buffer = buffer;
+		while !buffer.is_empty() {
+			let segment = match self.segments.get(index) {
+				Some(segment) if segment.address_range[0].get() <= offset => *segment,
+				_ => return Err(ReadError::NotMapped),
+			};
+
+			// only the first `file_size` bytes of the segment were dumped
+			let skip = offset - segment.address_range[0].get();
+			if skip >= segment.file_size {
+				return Err(ReadError::NotMapped);
+			}
+			let length = ((segment.file_size - skip) as usize).min(buffer.len());
+			let (part, rest) = buffer.split_at_mut(length);
+
+			self.reader.seek(SeekFrom::Start(segment.position + skip))?;
+			self.reader.read_exact(part)?;
+
+			buffer = rest;
+			offset += length as u64;
+			index += 1;
+		}
+
+		Ok(())
+	}
+
+	unsafe fn write(&mut self, _offset: OffsetType, _data: &[u8]) -> Result<(), WriteError> {
+		Err(WriteError::NotPermitted)
+	}
+}
@@ -0,0 +1,36 @@
+use std::{
+	fs::File,
+	io::{BufReader, Read, Seek},
+	path::Path,
+};
+
+use crate::memory::map::{MemoryMap, MemoryPage};
+
+use super::{CoreDumpError, CoreFile};
+
+/// Memory map of the process stored in an ELF core dump, one page per `PT_LOAD` segment.
+///
+/// Pages are typed by the `NT_FILE` note, the executable is recognized through the auxiliary vector.
+/// The shared permission is not stored in core dumps, so all pages are reported as private.
+pub struct CoreDumpMemoryMap {
+	pages: Vec<MemoryPage>,
+}
+impl CoreDumpMemoryMap {
+	/// Parses the headers and notes of the core dump in `reader`.
+	pub fn new(reader: &mut (impl Read + Seek)) -> Result<Self, CoreDumpError> {
+		let core = CoreFile::parse(reader)?;
+
+		Ok(CoreDumpMemoryMap {
+			pages: core.pages(),
+		})
+	}
+
+	pub fn open(path: impl AsRef<Path>) -> Result<Self, CoreDumpError> {
+		Self::new(&mut BufReader::new(File::open(path)?))
+	}
+}
+impl MemoryMap for CoreDumpMemoryMap {
+	fn pages(&self) -> &[MemoryPage] {
+		&self.pages
+	}
+}
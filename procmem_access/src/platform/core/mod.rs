@@ -0,0 +1,492 @@
+//! Offline access to ELF core dumps.
+//!
+//! The memory of the crashed process is stored in `PT_LOAD` segments of the core file, the files mapped by the process
+//! are listed in its `NT_FILE` note. Both 32 and 64-bit core files of either byte order are supported.
+//!
+//! Segments which the kernel did not dump (for example read-only file mappings) are still listed by the memory map,
+//! but reading them fails with [`ReadError::NotMapped`](crate::memory::access::ReadError::NotMapped).
+
+use std::{
+	io::{Read, Seek, SeekFrom},
+	path::PathBuf,
+};
+
+use thiserror::Error;
+
+use crate::{
+	common::{Endianness, OffsetType},
+	error::{impl_from_kinded_error, ErrorKind},
+	memory::map::{MemoryPage, MemoryPagePermissions, MemoryPageType},
+};
+
+pub mod access;
+pub mod map;
+
+pub use access::CoreDumpAccess;
+pub use map::CoreDumpMemoryMap;
+
+const ET_CORE: u16 = 4;
+const PT_LOAD: u32 = 1;
+const PT_NOTE: u32 = 4;
+/// Marks that the number of program headers is stored in the first section header.
+const PN_XNUM: u16 = 0xffff;
+const PF_X: u32 = 1;
+const PF_W: u32 = 2;
+const PF_R: u32 = 4;
+const NT_AUXV: u32 = 6;
+const NT_FILE: u32 = 0x46494c45;
+const AT_PHDR: u64 = 3;
+
+#[derive(Debug, Error)]
+pub enum CoreDumpError {
+	#[error("file is not an ELF file")]
+	InvalidMagic,
+	#[error("ELF file is not a core dump")]
+	NotCore,
+	#[error("core dump is corrupted: {0}")]
+	Corrupted(&'static str),
+	#[error("could not read core dump")]
+	Io(#[from] std::io::Error),
+}
+impl CoreDumpError {
+	pub fn kind(&self) -> ErrorKind {
+		match self {
+			CoreDumpError::Io(err) => ErrorKind::from_io(err),
+			_ => ErrorKind::Parse,
+		}
+	}
+}
+impl_from_kinded_error!(CoreDumpError);
+
+/// Decodes fields of the ELF class and byte order of the core file.
+#[derive(Debug, Clone, Copy)]
+struct ElfLayout {
+	is_64: bool,
+	endianness: Endianness,
+}
+impl ElfLayout {
+	fn field<const N: usize>(&self, bytes: &[u8], at: usize) -> Result<[u8; N], CoreDumpError> {
+		let mut field: [u8; N] = bytes
+			.get(at..at + N)
+			.and_then(|field| field.try_into().ok())
+			.ok_or(CoreDumpError::Corrupted("structure is truncated"))?;
+		self.endianness.convert_scalar(&mut field);
+
+		Ok(field)
+	}
+
+	fn u16(&self, bytes: &[u8], at: usize) -> Result<u16, CoreDumpError> {
+		self.field(bytes, at).map(u16::from_ne_bytes)
+	}
+
+	fn u32(&self, bytes: &[u8], at: usize) -> Result<u32, CoreDumpError> {
+		self.field(bytes, at).map(u32::from_ne_bytes)
+	}
+
+	/// Reads an address sized field at `at` in 64-bit files or at `at_32` in 32-bit files.
+	fn word(&self, bytes: &[u8], at: usize, at_32: usize) -> Result<u64, CoreDumpError> {
+		if self.is_64 {
+			self.field(bytes, at).map(u64::from_ne_bytes)
+		} else {
+			self.u32(bytes, at_32).map(u64::from)
+		}
+	}
+
+	const fn word_size(&self) -> usize {
+		if self.is_64 {
+			8
+		} else {
+			4
+		}
+	}
+}
+
+/// `PT_LOAD` segment of a core file.
+#[derive(Debug, Clone, Copy)]
+struct Segment {
+	address_range: [OffsetType; 2],
+	permissions: MemoryPagePermissions,
+	/// Position of the segment data in the core file.
+	position: u64,
+	/// Number of bytes of the segment stored in the core file.
+	file_size: u64,
+}
+
+/// File mapping listed in the `NT_FILE` note.
+#[derive(Debug, Clone)]
+struct FileMapping {
+	address_range: [u64; 2],
+	offset: u64,
+	path: PathBuf,
+}
+
+/// Headers and notes of a core file, the data of the segments is not read.
+#[derive(Debug, Clone)]
+struct CoreFile {
+	endianness: Endianness,
+	/// Segments sorted by address.
+	segments: Vec<Segment>,
+	files: Vec<FileMapping>,
+	/// Address of the program headers of the executable, from the auxiliary vector.
+	executable_phdr: Option<u64>,
+}
+impl CoreFile {
+	fn parse(reader: &mut (impl Read + Seek)) -> Result<Self, CoreDumpError> {
+		let mut header = [0u8; 64];
+		reader.seek(SeekFrom::Start(0))?;
+		let read = reader.read(&mut header)?;
+		let header = &header[..read];
+
+		if !header.starts_with(b"\x7fELF") {
+			return Err(CoreDumpError::InvalidMagic);
+		}
+		let layout = ElfLayout {
+			is_64: match header.get(4) {
+				Some(1) => false,
+				Some(2) => true,
+				_ => return Err(CoreDumpError::Corrupted("invalid ELF class")),
+			},
+			endianness: match header.get(5) {
+				Some(1) => Endianness::Little,
+				Some(2) => Endianness::Big,
+				_ => return Err(CoreDumpError::Corrupted("invalid ELF data encoding")),
+			},
+		};
+		if layout.u16(header, 16)? != ET_CORE {
+			return Err(CoreDumpError::NotCore);
+		}
+
+		let (phoff, phentsize, mut phnum) = (
+			layout.word(header, 32, 28)?,
+			layout.u16(header, if layout.is_64 { 54 } else { 42 })? as usize,
+			layout.u16(header, if layout.is_64 { 56 } else { 44 })? as usize,
+		);
+		if phnum == PN_XNUM as usize {
+			// sh_info of the first section header
+			let shoff = layout.word(header, 40, 32)?;
+			let mut section = [0u8; 4];
+			reader.seek(SeekFrom::Start(shoff + if layout.is_64 { 44 } else { 28 }))?;
+			reader.read_exact(&mut section)?;
+			phnum = layout.u32(&section, 0)? as usize;
+		}
+		if phentsize < if layout.is_64 { 56 } else { 32 } {
+			return Err(CoreDumpError::Corrupted("program headers are too small"));
+		}
+
+		let mut headers = vec![0u8; phentsize * phnum];
+		reader.seek(SeekFrom::Start(phoff))?;
+		reader.read_exact(&mut headers)?;
+
+		let mut core = CoreFile {
+			endianness: layout.endianness,
+			segments: Vec::new(),
+			files: Vec::new(),
+			executable_phdr: None,
+		};
+		for header in headers.chunks_exact(phentsize) {
+			let p_type = layout.u32(header, 0)?;
+			let p_flags = layout.u32(header, if layout.is_64 { 4 } else { 24 })?;
+			let p_offset = layout.word(header, 8, 4)?;
+			let p_vaddr = layout.word(header, 16, 8)?;
+			let p_filesz = layout.word(header, 32, 16)?;
+			let p_memsz = layout.word(header, 40, 20)?;
+
+			match p_type {
+				PT_LOAD => {
+					let (start, end) = match (
+						OffsetType::new(p_vaddr),
+						p_vaddr.checked_add(p_memsz).and_then(OffsetType::new),
+					) {
+						(Some(start), Some(end)) if p_memsz > 0 => (start, end),
+						_ => continue,
+					};
+
+					core.segments.push(Segment {
+						address_range: [start, end],
+						permissions: MemoryPagePermissions::new(
+							p_flags & PF_R != 0,
+							p_flags & PF_W != 0,
+							p_flags & PF_X != 0,
+							false,
+						),
+						position: p_offset,
+						file_size: p_filesz.min(p_memsz),
+					});
+				}
+				PT_NOTE => {
+					let mut notes = vec![0u8; p_filesz as usize];
+					reader.seek(SeekFrom::Start(p_offset))?;
+					reader.read_exact(&mut notes)?;
+					core.parse_notes(layout, &notes)?;
+				}
+				_ => (),
+			}
+		}
+		core.segments
+			.sort_by_key(|segment| segment.address_range[0]);
+
+		Ok(core)
+	}
+
+	fn parse_notes(&mut self, layout: ElfLayout, notes: &[u8]) -> Result<(), CoreDumpError> {
+		let align = |size: usize| (size + 3) & !3;
+
+		let mut position = 0;
+		while position + 12 <= notes.len() {
+			let name_size = layout.u32(notes, position)? as usize;
+			let desc_size = layout.u32(notes, position + 4)? as usize;
+			let note_type = layout.u32(notes, position + 8)?;
+
+			let desc_start = position + 12 + align(name_size);
+			let desc = notes
+				.get(desc_start..desc_start + desc_size)
+				.ok_or(CoreDumpError::Corrupted("note is truncated"))?;
+			position = desc_start + align(desc_size);
+
+			match note_type {
+				NT_FILE => self.parse_files(layout, desc)?,
+				NT_AUXV => {
+					let entry_size = 2 * layout.word_size();
+					for entry in desc.chunks_exact(entry_size) {
+						if layout.word(entry, 0, 0)? == AT_PHDR {
+							self.executable_phdr = Some(layout.word(entry, 8, 4)?);
+						}
+					}
+				}
+				_ => (),
+			}
+		}
+
+		Ok(())
+	}
+
+	/// Parses `count, page_size, count * (start, end, page offset), count * path` of the `NT_FILE` note.
+	fn parse_files(&mut self, layout: ElfLayout, desc: &[u8]) -> Result<(), CoreDumpError> {
+		let word = layout.word_size();
+		let count = layout.word(desc, 0, 0)? as usize;
+		let page_size = layout.word(desc, 8, 4)?;
+
+		let paths_start = count
+			.checked_mul(3 * word)
+			.and_then(|size| size.checked_add(2 * word))
+			.ok_or(CoreDumpError::Corrupted("file note is truncated"))?;
+		let mut paths = desc
+			.get(paths_start..)
+			.ok_or(CoreDumpError::Corrupted("file note is truncated"))?
+			.split(|byte| *byte == 0);
+
+		for index in 0..count {
+			let entry = &desc[2 * word + index * 3 * word..];
+			let path = paths
+				.next()
+				.ok_or(CoreDumpError::Corrupted("file note is truncated"))?;
+
+			self.files.push(FileMapping {
+				address_range: [layout.word(entry, 0, 0)?, layout.word(entry, 8, 4)?],
+				offset: layout.word(entry, 16, 8)? * page_size,
+				path: PathBuf::from(String::from_utf8_lossy(path).into_owned()),
+			});
+		}
+
+		Ok(())
+	}
+
+	/// Returns a page for each segment, typed by the file mapping containing it.
+	fn pages(&self) -> Vec<MemoryPage> {
+		let executable = self.executable_phdr.and_then(|phdr| {
+			self.files
+				.iter()
+				.find(|file| phdr >= file.address_range[0] && phdr < file.address_range[1])
+				.map(|file| file.path.clone())
+		});
+
+		self.segments
+			.iter()
+			.map(|segment| {
+				let start = segment.address_range[0].get();
+				let file = self
+					.files
+					.iter()
+					.find(|file| start >= file.address_range[0] && start < file.address_range[1]);
+
+				let (offset, page_type) = match file {
+					None => (0, MemoryPageType::Anon),
+					Some(file) => (
+						file.offset + (start - file.address_range[0]),
+						if executable.as_ref() == Some(&file.path) {
+							MemoryPageType::ProcessExecutable(file.path.clone())
+						} else {
+							MemoryPageType::File(file.path.clone())
+						},
+					),
+				};
+
+				MemoryPage {
+					address_range: segment.address_range,
+					permissions: segment.permissions,
+					offset,
+					page_type,
+				}
+			})
+			.collect()
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use std::io::Cursor;
+
+	use super::{CoreDumpAccess, CoreDumpError, CoreDumpMemoryMap};
+	use crate::{
+		common::{Endianness, OffsetType},
+		memory::{
+			access::{MemoryAccess, ReadError},
+			map::{MemoryMap, MemoryPagePermissions, MemoryPageType},
+		},
+	};
+
+	/// Builds a 64-bit little endian core file with segments of `(address, size, flags, data)`.
+	fn build_core(segments: &[(u64, u64, u32, &[u8])], notes: &[(u32, Vec<u8>)]) -> Vec<u8> {
+		let mut note_bytes = Vec::new();
+		for (note_type, desc) in notes {
+			note_bytes.extend_from_slice(&5u32.to_le_bytes());
+			note_bytes.extend_from_slice(&(desc.len() as u32).to_le_bytes());
+			note_bytes.extend_from_slice(&note_type.to_le_bytes());
+			note_bytes.extend_from_slice(b"CORE\0\0\0\0");
+			note_bytes.extend_from_slice(desc);
+			note_bytes.resize((note_bytes.len() + 3) & !3, 0);
+		}
+
+		let phnum = segments.len() + 1;
+		let mut data_position = 64 + 56 * phnum as u64;
+		let mut file = Vec::new();
+		file.extend_from_slice(b"\x7fELF\x02\x01\x01\0\0\0\0\0\0\0\0\0");
+		file.extend_from_slice(&4u16.to_le_bytes());
+		file.extend_from_slice(&62u16.to_le_bytes());
+		file.extend_from_slice(&1u32.to_le_bytes());
+		file.extend_from_slice(&0u64.to_le_bytes());
+		file.extend_from_slice(&64u64.to_le_bytes());
+		file.extend_from_slice(&0u64.to_le_bytes());
+		file.extend_from_slice(&0u32.to_le_bytes());
+		for field in [64u16, 56, phnum as u16, 64, 0, 0] {
+			file.extend_from_slice(&field.to_le_bytes());
+		}
+
+		let mut program_header = |p_type: u32,
+		                          flags: u32,
+		                          position: u64,
+		                          address: u64,
+		                          file_size: u64,
+		                          memory_size: u64| {
+			file.extend_from_slice(&p_type.to_le_bytes());
+			file.extend_from_slice(&flags.to_le_bytes());
+			for field in [position, address, 0, file_size, memory_size, 1] {
+				file.extend_from_slice(&field.to_le_bytes());
+			}
+		};
+		program_header(4, 0, data_position, 0, note_bytes.len() as u64, 0);
+		data_position += note_bytes.len() as u64;
+		for (address, size, flags, data) in segments {
+			program_header(1, *flags, data_position, *address, data.len() as u64, *size);
+			data_position += data.len() as u64;
+		}
+
+		file.extend_from_slice(&note_bytes);
+		for (_, _, _, data) in segments {
+			file.extend_from_slice(data);
+		}
+
+		file
+	}
+
+	fn words(words: &[u64]) -> Vec<u8> {
+		words.iter().flat_map(|word| word.to_le_bytes()).collect()
+	}
+
+	#[test]
+	fn test_core_dump_parse() {
+		let mut files = words(&[2, 0x1000, 0x10000, 0x12000, 0, 0x20000, 0x21000, 3]);
+		files.extend_from_slice(b"/usr/bin/game\0/usr/lib/libc.so.6\0");
+		let auxv = words(&[6, 0x1000, 3, 0x10040, 0, 0]);
+
+		let executable: Vec<u8> = (0..=255).cycle().take(0x2000).collect();
+		let heap = vec![7u8; 0x1000];
+		let core = build_core(
+			&[
+				(0x30000, 0x1000, 6, &heap),
+				(0x10000, 0x2000, 5, &executable),
+				(0x20000, 0x1000, 5, &[]),
+			],
+			&[(0x46494c45, files), (6, auxv)],
+		);
+
+		let map = CoreDumpMemoryMap::new(&mut Cursor::new(&core)).unwrap();
+		let pages: Vec<_> = map
+			.pages()
+			.iter()
+			.map(|page| {
+				(
+					page.start().get(),
+					page.size(),
+					page.permissions,
+					page.offset,
+					page.page_type.clone(),
+				)
+			})
+			.collect();
+		assert_eq!(
+			pages,
+			[
+				(
+					0x10000,
+					0x2000,
+					MemoryPagePermissions::new(true, false, true, false),
+					0,
+					MemoryPageType::ProcessExecutable("/usr/bin/game".into())
+				),
+				(
+					0x20000,
+					0x1000,
+					MemoryPagePermissions::new(true, false, true, false),
+					0x3000,
+					MemoryPageType::File("/usr/lib/libc.so.6".into())
+				),
+				(
+					0x30000,
+					0x1000,
+					MemoryPagePermissions::new(true, true, false, false),
+					0,
+					MemoryPageType::Anon
+				),
+			]
+		);
+
+		let mut access = CoreDumpAccess::new(Cursor::new(core)).unwrap();
+		assert_eq!(access.endianness(), Endianness::Little);
+		let mut buffer = [0u8; 4];
+		unsafe {
+			access
+				.read(OffsetType::new_unwrap(0x11ffe), &mut buffer[..2])
+				.unwrap();
+			assert_eq!(buffer[..2], [0xfe, 0xff]);
+			access
+				.read(OffsetType::new_unwrap(0x30010), &mut buffer)
+				.unwrap();
+			assert_eq!(buffer, [7; 4]);
+
+			// the library segment was not dumped
+			assert!(matches!(
+				access.read(OffsetType::new_unwrap(0x20000), &mut buffer),
+				Err(ReadError::NotMapped)
+			));
+			assert!(access.write(OffsetType::new_unwrap(0x30000), &[1]).is_err());
+		}
+
+		assert!(matches!(
+			CoreDumpMemoryMap::new(&mut Cursor::new(
+				b"\x7fELF\x02\x01\x01\0\0\0\0\0\0\0\0\0\x02\0"
+			)),
+			Err(CoreDumpError::NotCore)
+		));
+	}
+}
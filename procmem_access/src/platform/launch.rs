@@ -0,0 +1,399 @@
+//! Spawning processes which are stopped before running any of their code.
+//!
+//! Attaching to an already running process misses everything the process does during startup.
+//! [`ProcessLauncher`] spawns the process stopped at its entry and returns the simple platform handles
+//! with the process locked, so the caller can prepare scans, freezes or breakpoints before unlocking it.
+//!
+//! On Linux the child stops itself before `exec` and is seized with `PTRACE_O_TRACEEXEC`, so it stops again
+//! right after the new image is loaded. On macOS the process is spawned with `POSIX_SPAWN_START_SUSPENDED`.
+//!
+//! Like [`PtraceLock::new`], the calling thread becomes the tracer of the process.
+
+use std::{
+	ffi::{CString, NulError, OsStr, OsString},
+	os::unix::ffi::{OsStrExt, OsStringExt},
+};
+
+use thiserror::Error;
+
+use crate::{
+	error::{impl_from_kinded_error, ErrorKind, ProcmemError},
+	platform::{
+		ptrace::PtraceLock,
+		simple::{SimpleMemoryAccess, SimpleMemoryMap},
+	},
+};
+
+#[derive(Debug, Error)]
+pub enum LaunchError {
+	#[error("arguments must not contain nul bytes")]
+	InvalidArgument(#[from] NulError),
+	#[error("could not spawn process")]
+	Spawn(#[source] std::io::Error),
+	#[error("could not stop the process at its entry")]
+	Stop(#[source] std::io::Error),
+	#[error("could not open the process")]
+	Open(#[source] ProcmemError),
+}
+impl LaunchError {
+	pub fn kind(&self) -> ErrorKind {
+		match self {
+			LaunchError::InvalidArgument(_) => ErrorKind::Platform,
+			LaunchError::Spawn(err) | LaunchError::Stop(err) => ErrorKind::from_io(err),
+			LaunchError::Open(err) => err.kind(),
+		}
+	}
+}
+impl_from_kinded_error!(LaunchError);
+
+/// Process spawned by [`ProcessLauncher::launch`].
+///
+/// The `lock` is locked once and the process is stopped at its entry, unlock it to let the process run.
+pub struct LaunchedProcess {
+	pub pid: libc::pid_t,
+	pub lock: PtraceLock,
+	pub map: SimpleMemoryMap,
+	pub access: SimpleMemoryAccess,
+}
+
+/// Builder of a process spawned stopped at its entry.
+///
+/// The program is looked up in `PATH` and inherits the environment of the current process, extended by [`with_env`](ProcessLauncher::with_env).
+#[derive(Debug, Clone)]
+pub struct ProcessLauncher {
+	program: OsString,
+	args: Vec<OsString>,
+	env: Vec<(OsString, OsString)>,
+}
+impl ProcessLauncher {
+	pub fn new(program: impl AsRef<OsStr>) -> Self {
+		ProcessLauncher {
+			program: program.as_ref().to_owned(),
+			args: Vec::new(),
+			env: Vec::new(),
+		}
+	}
+
+	pub fn with_arg(mut self, arg: impl AsRef<OsStr>) -> Self {
+		self.args.push(arg.as_ref().to_owned());
+		self
+	}
+
+	pub fn with_args(mut self, args: impl IntoIterator<Item = impl AsRef<OsStr>>) -> Self {
+		self.args
+			.extend(args.into_iter().map(|arg| arg.as_ref().to_owned()));
+		self
+	}
+
+	pub fn with_env(mut self, key: impl AsRef<OsStr>, value: impl AsRef<OsStr>) -> Self {
+		self.env
+			.push((key.as_ref().to_owned(), value.as_ref().to_owned()));
+		self
+	}
+
+	/// Spawns the process and opens it while it is stopped at its entry.
+	///
+	/// The returned process is a child of the current process, the caller is responsible for reaping it.
+	pub fn launch(&self) -> Result<LaunchedProcess, LaunchError> {
+		let program = CString::new(self.program.as_bytes())?;
+		let argv = std::iter::once(Ok(program.clone()))
+			.chain(self.args.iter().map(|arg| CString::new(arg.as_bytes())))
+			.collect::<Result<Vec<_>, _>>()?;
+		let envp = self.environment()?;
+
+		let pid = unsafe { spawn_stopped(&program, &argv, &envp)? };
+
+		match unsafe { open_stopped(pid) } {
+			Ok(launched) => Ok(launched),
+			Err(err) => {
+				unsafe {
+					libc::kill(pid, libc::SIGKILL);
+					libc::waitpid(pid, std::ptr::null_mut(), 0);
+				}
+				Err(err)
+			}
+		}
+	}
+
+	/// Returns the environment of the current process with the configured variables overridden.
+	fn environment(&self) -> Result<Vec<CString>, NulError> {
+		let mut env: Vec<(OsString, OsString)> = std::env::vars_os()
+			.filter(|(key, _)| !self.env.iter().any(|(set, _)| set == key))
+			.collect();
+		env.extend(self.env.iter().cloned());
+
+		env.into_iter()
+			.map(|(key, value)| {
+				let mut entry = key.into_vec();
+				entry.push(b'=');
+				entry.extend(value.into_vec());
+
+				CString::new(entry)
+			})
+			.collect()
+	}
+}
+
+/// Builds a null-terminated array of pointers into `strings`.
+fn c_array(strings: &[CString]) -> Vec<*mut libc::c_char> {
+	strings
+		.iter()
+		.map(|string| string.as_ptr() as *mut libc::c_char)
+		.chain(std::iter::once(std::ptr::null_mut()))
+		.collect()
+}
+
+/// Forks and execs `program`, returning once the child is seized and stopped right after `exec`.
+///
+/// The child stops itself with `SIGSTOP` before `exec` so that it can be seized without racing it.
+/// If `exec` fails, the child reports `errno` through a close-on-exec pipe.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+unsafe fn spawn_stopped(
+	program: &CString,
+	argv: &[CString],
+	envp: &[CString],
+) -> Result<libc::pid_t, LaunchError> {
+	let argv = c_array(argv);
+	let envp = c_array(envp);
+
+	let mut pipe = [0 as libc::c_int; 2];
+	if libc::pipe2(pipe.as_mut_ptr(), libc::O_CLOEXEC) != 0 {
+		return Err(LaunchError::Spawn(std::io::Error::last_os_error()));
+	}
+	let [read_end, write_end] = pipe;
+
+	let pid = libc::fork();
+	if pid == -1 {
+		let err = std::io::Error::last_os_error();
+		libc::close(read_end);
+		libc::close(write_end);
+		return Err(LaunchError::Spawn(err));
+	}
+	if pid == 0 {
+		// only async-signal-safe calls from here on
+		libc::raise(libc::SIGSTOP);
+		libc::execvpe(program.as_ptr(), argv.as_ptr() as _, envp.as_ptr() as _);
+
+		let errno = *crate::platform::ptrace::errno_location();
+		libc::write(
+			write_end,
+			&errno as *const libc::c_int as _,
+			std::mem::size_of::<libc::c_int>(),
+		);
+		libc::_exit(127);
+	}
+	libc::close(write_end);
+
+	let result = wait_for_exec(pid, read_end);
+	libc::close(read_end);
+	if result.is_err() {
+		libc::kill(pid, libc::SIGKILL);
+		libc::waitpid(pid, std::ptr::null_mut(), 0);
+	}
+
+	result.map(|_| pid)
+}
+
+/// Seizes the self-stopped child `pid` and waits until it stops after `exec`.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+unsafe fn wait_for_exec(pid: libc::pid_t, errno_pipe: libc::c_int) -> Result<(), LaunchError> {
+	let mut status = 0;
+	if libc::waitpid(pid, &mut status, libc::WUNTRACED) == -1 {
+		return Err(LaunchError::Stop(std::io::Error::last_os_error()));
+	}
+	if !libc::WIFSTOPPED(status) {
+		return Err(LaunchError::Spawn(std::io::Error::other(
+			"process terminated before exec",
+		)));
+	}
+
+	if libc::ptrace(
+		crate::platform::ptrace::requests::PTRACE_SEIZE,
+		pid,
+		0,
+		libc::PTRACE_O_TRACEEXEC as libc::c_long,
+	) != 0
+	{
+		return Err(LaunchError::Stop(std::io::Error::last_os_error()));
+	}
+	if libc::kill(pid, libc::SIGCONT) != 0 {
+		return Err(LaunchError::Stop(std::io::Error::last_os_error()));
+	}
+
+	loop {
+		if libc::waitpid(pid, &mut status, 0) == -1 {
+			return Err(LaunchError::Stop(std::io::Error::last_os_error()));
+		}
+
+		if libc::WIFEXITED(status) || libc::WIFSIGNALED(status) {
+			let mut errno: libc::c_int = 0;
+			let read = libc::read(
+				errno_pipe,
+				&mut errno as *mut libc::c_int as _,
+				std::mem::size_of::<libc::c_int>(),
+			);
+
+			return Err(LaunchError::Spawn(
+				if read == std::mem::size_of::<libc::c_int>() as isize {
+					std::io::Error::from_raw_os_error(errno)
+				} else {
+					std::io::Error::other("process terminated before exec")
+				},
+			));
+		}
+
+		if status >> 8 == libc::SIGTRAP | (libc::PTRACE_EVENT_EXEC << 8) {
+			return Ok(());
+		}
+
+		// the stops caused by our own SIGSTOP and SIGCONT are suppressed, other signals are delivered
+		let signal = match libc::WSTOPSIG(status) {
+			_ if status >> 16 == libc::PTRACE_EVENT_STOP => 0,
+			libc::SIGSTOP | libc::SIGCONT => 0,
+			signal => signal,
+		};
+		if libc::ptrace(libc::PTRACE_CONT, pid, 0, signal as libc::c_long) != 0 {
+			return Err(LaunchError::Stop(std::io::Error::last_os_error()));
+		}
+	}
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+unsafe fn open_stopped(pid: libc::pid_t) -> Result<LaunchedProcess, LaunchError> {
+	let lock = PtraceLock::from_stopped(pid);
+	let map = SimpleMemoryMap::new(pid).map_err(|err| LaunchError::Open(err.into()))?;
+	let access = SimpleMemoryAccess::new(pid).map_err(|err| LaunchError::Open(err.into()))?;
+
+	Ok(LaunchedProcess {
+		pid,
+		lock,
+		map,
+		access,
+	})
+}
+
+/// Spawns `program` with its task suspended before the first instruction.
+#[cfg(target_os = "macos")]
+unsafe fn spawn_stopped(
+	program: &CString,
+	argv: &[CString],
+	envp: &[CString],
+) -> Result<libc::pid_t, LaunchError> {
+	let argv = c_array(argv);
+	let envp = c_array(envp);
+
+	let mut attributes: libc::posix_spawnattr_t = std::ptr::null_mut();
+	let result = libc::posix_spawnattr_init(&mut attributes);
+	if result != 0 {
+		return Err(LaunchError::Spawn(std::io::Error::from_raw_os_error(
+			result,
+		)));
+	}
+	libc::posix_spawnattr_setflags(
+		&mut attributes,
+		libc::POSIX_SPAWN_START_SUSPENDED as libc::c_short,
+	);
+
+	let mut pid = 0;
+	let result = libc::posix_spawnp(
+		&mut pid,
+		program.as_ptr(),
+		std::ptr::null(),
+		&attributes,
+		argv.as_ptr(),
+		envp.as_ptr(),
+	);
+	libc::posix_spawnattr_destroy(&mut attributes);
+	if result != 0 {
+		return Err(LaunchError::Spawn(std::io::Error::from_raw_os_error(
+			result,
+		)));
+	}
+
+	Ok(pid)
+}
+
+/// Attaches to the suspended process and replaces the task suspension by the lock.
+#[cfg(target_os = "macos")]
+unsafe fn open_stopped(pid: libc::pid_t) -> Result<LaunchedProcess, LaunchError> {
+	use crate::{memory::lock::MemoryLock, platform::mach::TaskPort};
+
+	let mut lock = PtraceLock::new(pid).map_err(|err| LaunchError::Open(err.into()))?;
+	lock.lock()
+		.map_err(|err| LaunchError::Open(ProcmemError::new(ErrorKind::Platform, err)))?;
+
+	let task = TaskPort::new(pid).map_err(LaunchError::Stop)?;
+	if mach::task::task_resume(task.get()) != mach::kern_return::KERN_SUCCESS {
+		return Err(LaunchError::Stop(std::io::Error::other(
+			"could not resume suspended task",
+		)));
+	}
+
+	let map = SimpleMemoryMap::new(pid).map_err(|err| LaunchError::Open(err.into()))?;
+	let access = SimpleMemoryAccess::new(pid).map_err(|err| LaunchError::Open(err.into()))?;
+
+	Ok(LaunchedProcess {
+		pid,
+		lock,
+		map,
+		access,
+	})
+}
+
+#[cfg(all(test, any(target_os = "linux", target_os = "android")))]
+mod test {
+	use super::{LaunchError, ProcessLauncher};
+	use crate::{
+		memory::{access::MemoryAccess, lock::MemoryLock, map::MemoryMap},
+		prelude::MemoryPageType,
+	};
+
+	#[test]
+	fn test_launch_stopped() {
+		let launched = match ProcessLauncher::new("sleep").with_arg("30").launch() {
+			Ok(launched) => launched,
+			Err(LaunchError::Stop(err)) => {
+				eprintln!("skipping, could not trace: {}", err);
+				return;
+			}
+			Err(err) => panic!("{}", err),
+		};
+		let super::LaunchedProcess {
+			pid,
+			mut lock,
+			map,
+			mut access,
+		} = launched;
+		assert!(lock.is_locked());
+
+		// the executable is mapped, the ELF header is at its first page
+		let exe_page = map
+			.pages()
+			.iter()
+			.find(|page| matches!(page.page_type, MemoryPageType::ProcessExecutable(_)))
+			.unwrap();
+		let mut magic = [0u8; 4];
+		unsafe { access.read(exe_page.address_range[0], &mut magic) }.unwrap();
+		assert_eq!(&magic, b"\x7fELF");
+
+		lock.unlock().unwrap();
+		drop(lock);
+
+		unsafe {
+			libc::kill(pid, libc::SIGKILL);
+			libc::waitpid(pid, std::ptr::null_mut(), 0);
+		}
+	}
+
+	#[test]
+	fn test_launch_missing_program() {
+		match ProcessLauncher::new("procmem-missing-program").launch() {
+			Err(LaunchError::Spawn(err)) => {
+				assert_eq!(err.kind(), std::io::ErrorKind::NotFound)
+			}
+			Err(LaunchError::Stop(err)) => eprintln!("skipping, could not trace: {}", err),
+			Err(err) => panic!("{}", err),
+			Ok(_) => panic!("missing program launched"),
+		}
+	}
+}
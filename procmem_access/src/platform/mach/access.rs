@@ -1,28 +1,69 @@
 use thiserror::Error;
 
-use mach::kern_return::KERN_SUCCESS;
+use mach::{
+	kern_return::KERN_SUCCESS,
+	vm_prot::{
+		vm_prot_t, VM_PROT_COPY, VM_PROT_EXECUTE, VM_PROT_NONE, VM_PROT_READ, VM_PROT_WRITE,
+	},
+	vm_statistics::VM_FLAGS_ANYWHERE,
+	vm_types::mach_vm_address_t,
+};
 
 use crate::{
 	common::OffsetType,
-	memory::access::{MemoryAccess, ReadError, WriteError},
+	error::{impl_from_kinded_error, ErrorKind},
+	memory::{
+		access::{MemoryAccess, ReadError, WriteError},
+		allocate::{AllocateError, Allocations, MemoryAllocate},
+		map::MemoryPagePermissions,
+		protect::{page_range, MemoryProtect, ProtectError},
+	},
 };
 
 #[derive(Debug, Error)]
 pub enum MachAccessError {
 	#[error("could not retrieve port handle")]
-	PortError(std::io::Error),
+	PortError(#[source] std::io::Error),
 }
+impl MachAccessError {
+	pub fn kind(&self) -> ErrorKind {
+		match self {
+			MachAccessError::PortError(err) => ErrorKind::from_io(err),
+		}
+	}
+}
+impl_from_kinded_error!(MachAccessError);
 
 pub struct MachAccess {
 	#[allow(dead_code)]
 	pid: libc::pid_t,
 	port: super::TaskPort,
+	allocations: Allocations,
 }
 impl MachAccess {
 	pub fn new(pid: libc::pid_t) -> Result<Self, MachAccessError> {
 		let port = super::TaskPort::new(pid).map_err(MachAccessError::PortError)?;
 
-		Ok(MachAccess { pid, port })
+		Ok(MachAccess {
+			pid,
+			port,
+			allocations: Allocations::new(),
+		})
+	}
+
+	pub(super) fn protection(permissions: MemoryPagePermissions) -> vm_prot_t {
+		let mut protection = VM_PROT_NONE;
+		if permissions.read() {
+			protection |= VM_PROT_READ;
+		}
+		if permissions.write() {
+			protection |= VM_PROT_WRITE;
+		}
+		if permissions.exec() {
+			protection |= VM_PROT_EXECUTE;
+		}
+
+		protection
 	}
 }
 impl MemoryAccess for MachAccess {
@@ -61,3 +102,73 @@ impl MemoryAccess for MachAccess {
 		Ok(())
 	}
 }
+impl MemoryAllocate for MachAccess {
+	unsafe fn allocate(
+		&mut self,
+		size: u64,
+		permissions: MemoryPagePermissions,
+	) -> Result<OffsetType, AllocateError> {
+		let size = Allocations::round_to_pages(size, libc::sysconf(libc::_SC_PAGESIZE) as u64)?;
+
+		let mut address: mach_vm_address_t = 0;
+		let res =
+			mach::vm::mach_vm_allocate(self.port.get(), &mut address, size, VM_FLAGS_ANYWHERE);
+		if res != KERN_SUCCESS {
+			return Err(AllocateError::Io(std::io::Error::last_os_error()));
+		}
+
+		// new memory is read-write, executable memory may be refused by the hardened runtime
+		let res = mach::vm::mach_vm_protect(
+			self.port.get(),
+			address,
+			size,
+			0,
+			Self::protection(permissions),
+		);
+		if res != KERN_SUCCESS {
+			let error = std::io::Error::last_os_error();
+			mach::vm::mach_vm_deallocate(self.port.get(), address, size);
+
+			return Err(AllocateError::Io(error));
+		}
+
+		let offset = OffsetType::new_unwrap(address);
+		self.allocations.insert(offset, size);
+
+		Ok(offset)
+	}
+
+	unsafe fn free(&mut self, offset: OffsetType) -> Result<(), AllocateError> {
+		let size = self.allocations.get(offset)?;
+
+		let res = mach::vm::mach_vm_deallocate(self.port.get(), offset.get(), size);
+		if res != KERN_SUCCESS {
+			return Err(AllocateError::Io(std::io::Error::last_os_error()));
+		}
+		self.allocations.remove(offset);
+
+		Ok(())
+	}
+}
+impl MemoryProtect for MachAccess {
+	unsafe fn protect(
+		&mut self,
+		range: [OffsetType; 2],
+		permissions: MemoryPagePermissions,
+	) -> Result<(), ProtectError> {
+		let (start, size) = page_range(range, libc::sysconf(libc::_SC_PAGESIZE) as u64)?;
+
+		// shared mappings such as code pages have to be copied before they can be made writable
+		let mut protection = Self::protection(permissions);
+		if permissions.write() {
+			protection |= VM_PROT_COPY;
+		}
+
+		let res = mach::vm::mach_vm_protect(self.port.get(), start, size, 0, protection);
+		if res != KERN_SUCCESS {
+			return Err(ProtectError::Io(std::io::Error::last_os_error()));
+		}
+
+		Ok(())
+	}
+}
@@ -76,11 +76,11 @@ From https://github.com/llvm/llvm-project/blob/62ec4ac90738a5f2d209ed28c822223e5
 #[derive(Debug, Error)]
 pub enum MachExceptionHandlerError {
 	#[error("could not get task port from pid")]
-	TaskPortError(std::io::Error),
+	TaskPortError(#[source] std::io::Error),
 	#[error("could not create exception port")]
-	CreatePortError(std::io::Error),
+	CreatePortError(#[source] std::io::Error),
 	#[error("could not swap new and old exception handler configuration")]
-	SwapExceptionError(std::io::Error),
+	SwapExceptionError(#[source] std::io::Error),
 }
 
 // This is not defined in the mach crate either.
@@ -113,7 +113,7 @@ impl MessageBuffer {
 	);
 
 	const fn natural_count(byte_count: usize) -> usize {
-		byte_count / Self::ELEMENT_SIZE + (byte_count % Self::ELEMENT_SIZE != 0) as usize
+		byte_count.div_ceil(Self::ELEMENT_SIZE)
 	}
 
 	pub fn new() -> Self {
@@ -139,6 +139,7 @@ impl MessageBuffer {
 
 	/// ## Safety
 	/// * The data loaded into this buffer must be a valid header
+	///
 	/// TODO: Can zeroed header be "valid" header? What even is documentation
 	pub fn header(&self) -> &mach_msg_header_t {
 		debug_assert!(self.buffer.len() >= Self::MINIMUM_SIZE);
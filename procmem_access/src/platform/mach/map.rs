@@ -1,35 +1,68 @@
+use std::{ffi::OsStr, os::unix::ffi::OsStrExt, path::PathBuf};
+
 use thiserror::Error;
 
 use mach::{
 	kern_return::KERN_SUCCESS,
 	mach_port::mach_port_deallocate,
+	message::mach_msg_type_number_t,
 	port::{mach_port_t, MACH_PORT_NULL},
 	vm_prot::{VM_PROT_EXECUTE, VM_PROT_READ, VM_PROT_WRITE},
-	vm_region::{vm_region_basic_info_64, vm_region_info_t, VM_REGION_BASIC_INFO_64},
+	vm_region::{
+		vm_region_basic_info_64, vm_region_extended_info, vm_region_flavor_t, vm_region_info_t,
+		VM_REGION_BASIC_INFO_64, VM_REGION_EXTENDED_INFO,
+	},
+	vm_statistics::{
+		VM_MEMORY_MALLOC, VM_MEMORY_MALLOC_HUGE, VM_MEMORY_MALLOC_LARGE, VM_MEMORY_MALLOC_SMALL,
+		VM_MEMORY_SBRK, VM_MEMORY_STACK,
+	},
 	vm_types::{mach_vm_address_t, mach_vm_size_t},
 };
 
 use crate::{
 	common::OffsetType,
+	error::{impl_from_kinded_error, ErrorKind},
 	memory::map::{MemoryMap, MemoryPage, MemoryPagePermissions, MemoryPageType},
 };
 
 #[derive(Debug, Error)]
 pub enum MachMemoryMapError {
 	#[error("could not retrieve port handle")]
-	PortError(std::io::Error),
+	PortError(#[source] std::io::Error),
+}
+impl MachMemoryMapError {
+	pub fn kind(&self) -> ErrorKind {
+		match self {
+			MachMemoryMapError::PortError(err) => ErrorKind::from_io(err),
+		}
+	}
 }
+impl_from_kinded_error!(MachMemoryMapError);
+
+// malloc zone tags missing from the `mach` crate, see `mach/vm_statistics.h`
+const VM_MEMORY_REALLOC: libc::c_uint = 6;
+const VM_MEMORY_MALLOC_TINY: libc::c_uint = 7;
+const VM_MEMORY_MALLOC_LARGE_REUSABLE: libc::c_uint = 8;
+const VM_MEMORY_MALLOC_LARGE_REUSED: libc::c_uint = 9;
+const VM_MEMORY_MALLOC_NANO: libc::c_uint = 11;
+const VM_MEMORY_MALLOC_MEDIUM: libc::c_uint = 12;
 
+/// Memory map of a task read with `mach_vm_region`.
+///
+/// Untested, see the [module documentation](super).
 pub struct MachMemoryMap {
 	pages: Vec<MemoryPage>,
 }
 impl MachMemoryMap {
 	pub fn new(pid: libc::pid_t) -> Result<Self, MachMemoryMapError> {
 		let port = super::TaskPort::new(pid).map_err(MachMemoryMapError::PortError)?;
+		let executable = Self::executable_path(pid);
 		let mut pages = Vec::new();
 
 		let mut previous_address = 0;
-		while let Some(page) = Self::enumerate_next_page(port.get(), previous_address) {
+		while let Some(page) =
+			Self::enumerate_next_page(pid, port.get(), previous_address, executable.as_ref())
+		{
 			previous_address = page.address_range[1].get();
 			pages.push(page);
 		}
@@ -37,42 +70,136 @@ impl MachMemoryMap {
 		Ok(MachMemoryMap { pages })
 	}
 
+	fn executable_path(pid: libc::pid_t) -> Option<PathBuf> {
+		let mut buffer = [0u8; libc::PROC_PIDPATHINFO_MAXSIZE as usize];
+
+		let count = unsafe { libc::proc_pidpath(pid, buffer.as_mut_ptr() as _, buffer.len() as _) };
+		if count <= 0 {
+			return None;
+		}
+
+		Some(OsStr::from_bytes(&buffer[..count as usize]).into())
+	}
+
+	/// Returns the path of the file mapped at `address`, if any.
+	fn region_file_name(pid: libc::pid_t, address: mach_vm_address_t) -> Option<PathBuf> {
+		let mut buffer = [0u8; libc::PROC_PIDPATHINFO_MAXSIZE as usize];
+
+		let count = unsafe {
+			libc::proc_regionfilename(pid, address, buffer.as_mut_ptr() as _, buffer.len() as _)
+		};
+		if count <= 0 {
+			return None;
+		}
+
+		Some(OsStr::from_bytes(&buffer[..count as usize]).into())
+	}
+
+	/// Classifies anonymous regions by the tag the allocator assigned to them.
+	fn page_type_of_tag(user_tag: libc::c_uint) -> MemoryPageType {
+		match user_tag {
+			VM_MEMORY_STACK => MemoryPageType::Stack,
+			VM_MEMORY_MALLOC
+			| VM_MEMORY_MALLOC_SMALL
+			| VM_MEMORY_MALLOC_LARGE
+			| VM_MEMORY_MALLOC_HUGE
+			| VM_MEMORY_SBRK
+			| VM_MEMORY_REALLOC
+			| VM_MEMORY_MALLOC_TINY
+			| VM_MEMORY_MALLOC_LARGE_REUSABLE
+			| VM_MEMORY_MALLOC_LARGE_REUSED
+			| VM_MEMORY_MALLOC_NANO
+			| VM_MEMORY_MALLOC_MEDIUM => MemoryPageType::Heap,
+			_ => MemoryPageType::Anon,
+		}
+	}
+
+	/// Queries the region at or after `address` with info of `flavor`.
+	///
+	/// ## Safety
+	/// * `T` must be the info structure of `flavor` and `info_count` its size in `int`s.
+	unsafe fn query_region<T>(
+		port: mach_port_t,
+		address: &mut mach_vm_address_t,
+		size: &mut mach_vm_size_t,
+		flavor: vm_region_flavor_t,
+		info: &mut T,
+		mut info_count: mach_msg_type_number_t,
+	) -> bool {
+		let mut object_name: mach_port_t = Default::default();
+
+		// TODO: I could not find any documentation, so this code is
+		// just a best-effort guess, I don't really know how unsafe it is
+		let res = mach::vm::mach_vm_region(
+			port,
+			address as *mut mach_vm_address_t,
+			size as *mut mach_vm_size_t,
+			flavor,
+			info as *mut T as vm_region_info_t,
+			&mut info_count,
+			&mut object_name,
+		);
+
+		if object_name != MACH_PORT_NULL {
+			// TODO: Documentation would probably reveal what this is and why it is necessary
+			// until then, I'll just believe the internet
+			let res = mach_port_deallocate(port, object_name);
+			debug_assert_eq!(res, KERN_SUCCESS);
+		}
+
+		res == KERN_SUCCESS
+	}
+
 	fn enumerate_next_page(
+		pid: libc::pid_t,
 		port: mach_port_t,
 		previous_address: mach_vm_address_t,
+		executable: Option<&PathBuf>,
 	) -> Option<MemoryPage> {
 		let mut address = previous_address;
 		let mut size: mach_vm_size_t = 0;
 		let mut info: vm_region_basic_info_64 = Default::default();
-		let mut info_count = vm_region_basic_info_64::count();
-		let mut object_name: mach_port_t = Default::default();
-
-		// TODO: I could not find any documentation, so this code is
-		// just a best-effort guess, I don't really know how unsafe it is
-		let res = unsafe {
-			mach::vm::mach_vm_region(
+		let found = unsafe {
+			Self::query_region(
 				port,
-				&mut address as *mut mach_vm_address_t,
-				&mut size as *mut mach_vm_size_t,
+				&mut address,
+				&mut size,
 				VM_REGION_BASIC_INFO_64,
-				&mut info as *mut vm_region_basic_info_64 as vm_region_info_t,
-				&mut info_count,
-				&mut object_name,
+				&mut info,
+				vm_region_basic_info_64::count(),
 			)
 		};
-
-		if object_name != MACH_PORT_NULL {
-			// TODO: Documentation would probably reveal what this is and why it is necessary
-			// until then, I'll just believe the internet
-			unsafe {
-				let res = mach_port_deallocate(port, object_name);
-				debug_assert_eq!(res, KERN_SUCCESS);
-			}
-		}
-		if res != KERN_SUCCESS {
+		if !found {
 			return None;
 		}
 
+		let page_type = match Self::region_file_name(pid, address) {
+			Some(path) if Some(&path) == executable => MemoryPageType::ProcessExecutable(path),
+			Some(path) => MemoryPageType::File(path),
+			None => {
+				// query the same region again, the address is already at its start
+				let mut extended_address = address;
+				let mut extended_size: mach_vm_size_t = 0;
+				let mut extended: vm_region_extended_info = Default::default();
+				let found = unsafe {
+					Self::query_region(
+						port,
+						&mut extended_address,
+						&mut extended_size,
+						VM_REGION_EXTENDED_INFO,
+						&mut extended,
+						vm_region_extended_info::count(),
+					)
+				};
+
+				if found && extended_address == address {
+					Self::page_type_of_tag(extended.user_tag)
+				} else {
+					MemoryPageType::Unknown
+				}
+			}
+		};
+
 		let page = MemoryPage {
 			address_range: [
 				OffsetType::new(address).unwrap(),
@@ -85,8 +212,7 @@ impl MachMemoryMap {
 				info.shared != 0,
 			),
 			offset: info.offset,
-			// TODO: This info can probably be retrieved from somewhere, maybe `object_name`?
-			page_type: MemoryPageType::Unknown,
+			page_type,
 		};
 
 		Some(page)
@@ -1,9 +1,19 @@
+//! Backend using the mach task and virtual memory APIs on macOS.
+//!
+//! **Untested:** this backend is only compiled and linted for macOS targets by `build.sh check` and has not been run
+//! on a macOS machine, including the classification of regions into page types in [`MachMemoryMap`].
+//! Expect it to need fixes before use.
+
+use crate::common::{select_one, FindError, NamePattern};
+
 pub mod access;
 pub mod exception;
 pub mod map;
+pub mod thread;
 
 pub use access::MachAccess;
 pub use map::MachMemoryMap;
+pub use thread::{MachThreadLock, ThreadInfo};
 
 #[derive(Debug, Default)]
 pub struct TaskPort(mach::port::mach_port_name_t);
@@ -47,10 +57,16 @@ impl Drop for TaskPort {
 }
 
 // <https://opensource.apple.com/source/xnu/xnu-2422.1.72/libsyscall/wrappers/libproc/libproc.h.auto.html>
+#[derive(Debug, Clone)]
 pub struct ProcessInfo {
 	pub pid: libc::pid_t,
 	pub name: String,
 }
+impl std::fmt::Display for ProcessInfo {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		write!(f, "{} ({})", self.pid, self.name)
+	}
+}
 impl ProcessInfo {
 	pub fn list_all() -> std::io::Result<Vec<Self>> {
 		let pids = {
@@ -64,8 +80,7 @@ impl ProcessInfo {
 			}
 
 			// prepare destination buffer and read the actual pids
-			let mut pids: Vec<libc::pid_t> = Vec::new();
-			pids.resize(count as usize, 0);
+			let mut pids: Vec<libc::pid_t> = vec![0; count as usize];
 			let count = unsafe {
 				libc::proc_listallpids(
 					pids.as_mut_ptr() as _,
@@ -92,6 +107,23 @@ impl ProcessInfo {
 		Ok(processes)
 	}
 
+	/// Lists all processes accepted by `predicate`.
+	pub fn list_filtered(predicate: impl FnMut(&Self) -> bool) -> std::io::Result<Vec<Self>> {
+		Ok(Self::list_all()?.into_iter().filter(predicate).collect())
+	}
+
+	/// Lists all processes with names matching `pattern`.
+	pub fn find_by_name(pattern: NamePattern) -> std::io::Result<Vec<Self>> {
+		Self::list_filtered(|process| pattern.matches(&process.name))
+	}
+
+	/// Finds the one process with name matching `pattern`, preferring an exact name match when more processes match.
+	pub fn find_one_by_name(pattern: NamePattern) -> Result<Self, FindError<Self>> {
+		select_one(pattern, Self::find_by_name(pattern)?, |process| {
+			&process.name
+		})
+	}
+
 	pub fn for_pid(pid: libc::pid_t) -> std::io::Result<Self> {
 		let name = Self::process_name(pid)?;
 		Ok(Self { pid, name })
@@ -0,0 +1,250 @@
+//! Threads of a task and a lock which suspends only selected threads.
+
+use thiserror::Error;
+
+use mach::{
+	kern_return::{kern_return_t, KERN_SUCCESS},
+	mach_types::thread_act_t,
+	message::mach_msg_type_number_t,
+	vm_types::{integer_t, natural_t},
+};
+
+use crate::{
+	error::{impl_from_kinded_error, ErrorKind},
+	memory::lock::{LockError, MemoryLock, UnlockError},
+};
+
+use super::TaskPort;
+
+extern "C" {
+	fn thread_info(
+		target_act: thread_act_t,
+		flavor: natural_t,
+		thread_info_out: *mut integer_t,
+		thread_info_out_cnt: *mut mach_msg_type_number_t,
+	) -> kern_return_t;
+}
+
+// <https://opensource.apple.com/source/xnu/xnu-7195.81.3/osfmk/mach/thread_info.h.auto.html>
+const THREAD_IDENTIFIER_INFO: natural_t = 4;
+
+#[repr(C)]
+#[derive(Default)]
+struct ThreadIdentifierInfo {
+	thread_id: u64,
+	thread_handle: u64,
+	dispatch_qaddr: u64,
+}
+
+/// Send right to a thread which is deallocated on drop.
+struct ThreadPort(thread_act_t);
+impl ThreadPort {
+	/// Returns the system-wide unique id of the thread.
+	fn thread_id(&self) -> std::io::Result<u64> {
+		let mut info = ThreadIdentifierInfo::default();
+		let mut count = (std::mem::size_of::<ThreadIdentifierInfo>()
+			/ std::mem::size_of::<natural_t>()) as mach_msg_type_number_t;
+
+		let result = unsafe {
+			thread_info(
+				self.0,
+				THREAD_IDENTIFIER_INFO,
+				&mut info as *mut ThreadIdentifierInfo as *mut integer_t,
+				&mut count,
+			)
+		};
+		if result != KERN_SUCCESS {
+			return Err(std::io::Error::last_os_error());
+		}
+
+		Ok(info.thread_id)
+	}
+}
+impl Drop for ThreadPort {
+	fn drop(&mut self) {
+		let result =
+			unsafe { mach::mach_port::mach_port_deallocate(mach::traps::mach_task_self(), self.0) };
+
+		debug_assert_eq!(result, 0);
+	}
+}
+
+/// Returns ports of all threads of process `pid`.
+fn task_threads(pid: libc::pid_t) -> std::io::Result<Vec<ThreadPort>> {
+	let task = TaskPort::new(pid)?;
+
+	let mut list: *mut thread_act_t = std::ptr::null_mut();
+	let mut count: mach_msg_type_number_t = 0;
+	let result = unsafe { mach::task::task_threads(task.get(), &mut list, &mut count) };
+	if result != KERN_SUCCESS {
+		return Err(std::io::Error::last_os_error());
+	}
+
+	let threads = unsafe { std::slice::from_raw_parts(list, count as usize) }
+		.iter()
+		.map(|&port| ThreadPort(port))
+		.collect();
+
+	// the list itself is allocated in our address space
+	unsafe {
+		mach::vm::mach_vm_deallocate(
+			mach::traps::mach_task_self(),
+			list as u64,
+			(count as usize * std::mem::size_of::<thread_act_t>()) as u64,
+		)
+	};
+
+	Ok(threads)
+}
+
+/// Thread of a task, listed with `task_threads`.
+pub struct ThreadInfo {
+	pub tid: u64,
+}
+impl ThreadInfo {
+	/// Lists all threads of process `pid`.
+	pub fn list(pid: libc::pid_t) -> std::io::Result<Vec<Self>> {
+		task_threads(pid)?
+			.iter()
+			.map(|port| port.thread_id().map(|tid| ThreadInfo { tid }))
+			.collect()
+	}
+}
+
+#[derive(Debug, Error)]
+pub enum MachThreadLockError {
+	#[error("could not list threads")]
+	Threads(#[source] std::io::Error),
+	#[error("suspending thread failed")]
+	Suspend(#[source] std::io::Error),
+	#[error("resuming thread failed")]
+	Resume(#[source] std::io::Error),
+}
+impl MachThreadLockError {
+	pub fn kind(&self) -> ErrorKind {
+		ErrorKind::from_error(self)
+	}
+}
+impl_from_kinded_error!(MachThreadLockError);
+impl From<MachThreadLockError> for LockError {
+	fn from(err: MachThreadLockError) -> Self {
+		LockError::PlatformError(Box::new(err))
+	}
+}
+impl From<MachThreadLockError> for UnlockError {
+	fn from(err: MachThreadLockError) -> Self {
+		UnlockError::PlatformError(Box::new(err))
+	}
+}
+
+/// Lock which suspends only the selected threads of a process with `thread_suspend`.
+///
+/// Memory can still change while locked, because the remaining threads keep running.
+pub struct MachThreadLock {
+	threads: Vec<ThreadPort>,
+	lock_counter: usize,
+}
+impl MachThreadLock {
+	/// Opens threads `tids` of process `pid`, see [`ThreadInfo::list`].
+	pub fn new(pid: libc::pid_t, tids: &[u64]) -> Result<Self, MachThreadLockError> {
+		let mut ports = task_threads(pid).map_err(MachThreadLockError::Threads)?;
+
+		let mut threads = Vec::with_capacity(tids.len());
+		for &tid in tids {
+			let index = ports
+				.iter()
+				.position(|port| port.thread_id().ok() == Some(tid))
+				.ok_or_else(|| {
+					MachThreadLockError::Threads(std::io::Error::new(
+						std::io::ErrorKind::NotFound,
+						"thread does not belong to the process",
+					))
+				})?;
+
+			threads.push(ports.swap_remove(index));
+		}
+
+		Ok(MachThreadLock {
+			threads,
+			lock_counter: 0,
+		})
+	}
+
+	fn suspend(&mut self) -> Result<(), MachThreadLockError> {
+		for (index, thread) in self.threads.iter().enumerate() {
+			if unsafe { mach::thread_act::thread_suspend(thread.0) } != KERN_SUCCESS {
+				let err = std::io::Error::last_os_error();
+				// resume the threads suspended so far
+				for thread in self.threads[..index].iter() {
+					unsafe { mach::thread_act::thread_resume(thread.0) };
+				}
+
+				return Err(MachThreadLockError::Suspend(err));
+			}
+		}
+
+		Ok(())
+	}
+
+	fn resume(&mut self) -> Result<(), MachThreadLockError> {
+		let mut result = Ok(());
+		for thread in self.threads.iter() {
+			if unsafe { mach::thread_act::thread_resume(thread.0) } != KERN_SUCCESS {
+				result = Err(MachThreadLockError::Resume(std::io::Error::last_os_error()));
+			}
+		}
+
+		result
+	}
+}
+impl MemoryLock for MachThreadLock {
+	fn lock(&mut self) -> Result<bool, LockError> {
+		if self.lock_counter == 0 {
+			self.suspend()?;
+			self.lock_counter = 1;
+
+			Ok(true)
+		} else if self.lock_counter == usize::MAX {
+			Err(LockError::AlreadyLocked)
+		} else {
+			self.lock_counter += 1;
+
+			Ok(false)
+		}
+	}
+
+	fn lock_exlusive(&mut self) -> Result<(), LockError> {
+		if self.lock_counter == 0 {
+			self.lock()?;
+			self.lock_counter = usize::MAX;
+
+			Ok(())
+		} else {
+			Err(LockError::AlreadyLocked)
+		}
+	}
+
+	fn unlock(&mut self) -> Result<bool, UnlockError> {
+		if self.lock_counter == 0 {
+			return Err(UnlockError::NotLocked);
+		}
+
+		if self.lock_counter == 1 || self.lock_counter == usize::MAX {
+			self.resume()?;
+			self.lock_counter = 0;
+
+			Ok(true)
+		} else {
+			self.lock_counter -= 1;
+
+			Ok(false)
+		}
+	}
+}
+impl Drop for MachThreadLock {
+	fn drop(&mut self) {
+		if self.lock_counter != 0 {
+			let _ = self.resume();
+		}
+	}
+}
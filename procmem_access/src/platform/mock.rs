@@ -0,0 +1,435 @@
+//! In-memory backends for deterministic tests.
+//!
+//! [`MockMemoryAccess`] serves reads and writes from byte buffers placed at chosen offsets and allocates new ones, [`MockMemoryMap`] holds a synthetic page layout
+//! and [`MockMemoryLock`] counts locking without stopping any process. All of them can be told to fail to test error handling.
+
+use std::sync::{Arc, Mutex};
+
+use crate::{
+	common::OffsetType,
+	memory::{
+		access::{MemoryAccess, ReadError, WriteError},
+		allocate::{AllocateError, Allocations, MemoryAllocate},
+		lock::{LockError, MemoryLock, UnlockError},
+		map::{MemoryMap, MemoryPage, MemoryPagePermissions, MemoryPageType},
+	},
+};
+
+/// Memory access backed by byte buffers.
+///
+/// Offsets outside of all regions fail with [`ReadError::NotMapped`], offsets inside an injected fault fail with an I/O error
+/// like an unreadable mapping would. Accesses may span adjacent regions.
+#[derive(Debug, Clone, Default)]
+pub struct MockMemoryAccess {
+	/// Regions sorted by start offset, not overlapping.
+	regions: Vec<(OffsetType, Vec<u8>)>,
+	faults: Vec<[OffsetType; 2]>,
+	allocations: Allocations,
+}
+impl MockMemoryAccess {
+	/// Page size used to round and place allocations.
+	pub const PAGE_SIZE: u64 = 4096;
+
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Adds a region containing `data` starting at `start`.
+	///
+	/// Panics if the region overlaps an existing one.
+	pub fn with_region(mut self, start: OffsetType, data: Vec<u8>) -> Self {
+		let end = start.get() + data.len() as u64;
+		let index = self
+			.regions
+			.partition_point(|(region_start, _)| *region_start < start);
+		assert!(
+			index == 0 || Self::region_end(&self.regions[index - 1]) <= start.get(),
+			"region overlaps the previous region"
+		);
+		assert!(
+			index == self.regions.len() || end <= self.regions[index].0.get(),
+			"region overlaps the next region"
+		);
+
+		self.regions.insert(index, (start, data));
+		self
+	}
+
+	/// Makes every access touching `range` fail.
+	pub fn with_fault(mut self, range: [OffsetType; 2]) -> Self {
+		self.faults.push(range);
+		self
+	}
+
+	/// Returns the regions sorted by their start offset.
+	pub fn regions(&self) -> impl Iterator<Item = (OffsetType, &[u8])> {
+		self.regions
+			.iter()
+			.map(|(start, data)| (*start, data.as_slice()))
+	}
+
+	/// Returns the data of the region starting at `start`.
+	pub fn region(&self, start: OffsetType) -> Option<&[u8]> {
+		self.regions
+			.iter()
+			.find(|(region_start, _)| *region_start == start)
+			.map(|(_, data)| data.as_slice())
+	}
+
+	/// Returns a memory map with one private read-write anonymous page for each region.
+	pub fn memory_map(&self) -> MockMemoryMap {
+		self.regions
+			.iter()
+			.fold(MockMemoryMap::new(), |map, region| {
+				map.with_page(
+					[region.0, OffsetType::new_unwrap(Self::region_end(region))],
+					MemoryPagePermissions::new(true, true, false, false),
+					MemoryPageType::Anon,
+				)
+			})
+	}
+
+	fn region_end((start, data): &(OffsetType, Vec<u8>)) -> u64 {
+		start.get() + data.len() as u64
+	}
+
+	fn faulted(&self, start: u64, end: u64) -> bool {
+		self.faults
+			.iter()
+			.any(|fault| start < fault[1].get() && end > fault[0].get())
+	}
+
+	/// Calls `on_part` with each region and the part of `offset..offset + length` it covers, in order.
+	///
+	/// Returns `false` if part of the range is not covered by any region.
+	fn for_each_part(
+		&mut self,
+		offset: OffsetType,
+		length: usize,
+		mut on_part: impl FnMut(&mut [u8], usize),
+	) -> bool {
+		let mut position = offset.get();
+		let end = position + length as u64;
+
+		let first = self
+			.regions
+			.partition_point(|region| Self::region_end(region) <= position);
+		for (start, data) in self.regions[first..].iter_mut() {
+			if position == end || start.get() > position {
+				break;
+			}
+
+			let from = (position - start.get()) as usize;
+			let to = (end - start.get()).min(data.len() as u64) as usize;
+			on_part(&mut data[from..to], (position - offset.get()) as usize);
+
+			position += (to - from) as u64;
+		}
+
+		position == end
+	}
+}
+impl MemoryAccess for MockMemoryAccess {
+	unsafe fn read(&mut self, offset: OffsetType, buffer: &mut [u8]) -> Result<(), ReadError> {
+		if self.faulted(offset.get(), offset.get() + buffer.len() as u64) {
+			return Err(ReadError::Io(std::io::Error::from_raw_os_error(libc::EIO)));
+		}
+
+		let length = buffer.len();
+		let mapped = self.for_each_part(offset, length, |part, at| {
+			buffer[at..at + part.len()].copy_from_slice(part)
+		});
+
+		if mapped {
+			Ok(())
+		} else {
+			Err(ReadError::NotMapped)
+		}
+	}
+
+	unsafe fn write(&mut self, offset: OffsetType, data: &[u8]) -> Result<(), WriteError> {
+		if self.faulted(offset.get(), offset.get() + data.len() as u64) {
+			return Err(WriteError::Io(std::io::Error::from_raw_os_error(libc::EIO)));
+		}
+
+		// check first so that failed writes do not apply partially
+		if !self.for_each_part(offset, data.len(), |_, _| ()) {
+			return Err(WriteError::NotMapped);
+		}
+		self.for_each_part(offset, data.len(), |part, at| {
+			part.copy_from_slice(&data[at..at + part.len()])
+		});
+
+		Ok(())
+	}
+}
+
+/// Shared mock, so that a test can observe accesses made from another thread.
+impl MemoryAccess for Arc<Mutex<MockMemoryAccess>> {
+	unsafe fn read(&mut self, offset: OffsetType, buffer: &mut [u8]) -> Result<(), ReadError> {
+		self.lock().unwrap().read(offset, buffer)
+	}
+
+	unsafe fn write(&mut self, offset: OffsetType, data: &[u8]) -> Result<(), WriteError> {
+		self.lock().unwrap().write(offset, data)
+	}
+}
+
+/// Allocations are placed one page after the last region and ignore the permissions.
+impl MemoryAllocate for MockMemoryAccess {
+	unsafe fn allocate(
+		&mut self,
+		size: u64,
+		_permissions: MemoryPagePermissions,
+	) -> Result<OffsetType, AllocateError> {
+		let size = Allocations::round_to_pages(size, Self::PAGE_SIZE)?;
+
+		let last_end = self.regions.last().map(Self::region_end).unwrap_or(0);
+		let start = OffsetType::new_unwrap(
+			Allocations::round_to_pages(last_end + 1, Self::PAGE_SIZE)? + Self::PAGE_SIZE,
+		);
+
+		self.regions.push((start, vec![0; size as usize]));
+		self.allocations.insert(start, size);
+
+		Ok(start)
+	}
+
+	unsafe fn free(&mut self, offset: OffsetType) -> Result<(), AllocateError> {
+		self.allocations.get(offset)?;
+
+		self.regions.retain(|(start, _)| *start != offset);
+		self.allocations.remove(offset);
+
+		Ok(())
+	}
+}
+
+/// Memory map holding a synthetic page layout.
+#[derive(Debug, Clone, Default)]
+pub struct MockMemoryMap {
+	pages: Vec<MemoryPage>,
+}
+impl MockMemoryMap {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Creates a map from `pages` in any order.
+	pub fn from_pages(mut pages: Vec<MemoryPage>) -> Self {
+		pages.sort_by_key(MemoryPage::start);
+
+		MockMemoryMap { pages }
+	}
+
+	/// Adds a page spanning `address_range` with zero file offset.
+	pub fn with_page(
+		self,
+		address_range: [OffsetType; 2],
+		permissions: MemoryPagePermissions,
+		page_type: MemoryPageType,
+	) -> Self {
+		let mut pages = self.pages;
+		pages.push(MemoryPage {
+			address_range,
+			permissions,
+			offset: 0,
+			page_type,
+		});
+
+		Self::from_pages(pages)
+	}
+}
+impl MemoryMap for MockMemoryMap {
+	fn pages(&self) -> &[MemoryPage] {
+		&self.pages
+	}
+}
+
+/// Lock which only counts locking, with the same recursion rules as the platform locks.
+#[derive(Debug, Clone, Default)]
+pub struct MockMemoryLock {
+	lock_counter: usize,
+	acquired: usize,
+	failing: bool,
+}
+impl MockMemoryLock {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Makes subsequent `lock` and `unlock` calls fail with a platform error while `failing` is true.
+	pub fn set_failing(&mut self, failing: bool) {
+		self.failing = failing;
+	}
+
+	pub fn is_locked(&self) -> bool {
+		self.lock_counter != 0
+	}
+
+	pub fn is_locked_exclusive(&self) -> bool {
+		self.lock_counter == usize::MAX
+	}
+
+	/// Returns how many times the lock was acquired from the unlocked state.
+	pub fn acquired(&self) -> usize {
+		self.acquired
+	}
+
+	fn injected_error() -> Box<dyn std::error::Error + Send + Sync> {
+		"injected lock failure".into()
+	}
+}
+impl MemoryLock for MockMemoryLock {
+	fn lock(&mut self) -> Result<bool, LockError> {
+		if self.failing {
+			return Err(LockError::PlatformError(Self::injected_error()));
+		}
+
+		match self.lock_counter {
+			0 => {
+				self.lock_counter = 1;
+				self.acquired += 1;
+
+				Ok(true)
+			}
+			usize::MAX => Err(LockError::AlreadyLocked),
+			_ => {
+				self.lock_counter += 1;
+
+				Ok(false)
+			}
+		}
+	}
+
+	fn lock_exlusive(&mut self) -> Result<(), LockError> {
+		if self.lock_counter != 0 {
+			return Err(LockError::AlreadyLocked);
+		}
+
+		self.lock()?;
+		self.lock_counter = usize::MAX;
+
+		Ok(())
+	}
+
+	fn unlock(&mut self) -> Result<bool, UnlockError> {
+		if self.failing {
+			return Err(UnlockError::PlatformError(Self::injected_error()));
+		}
+
+		match self.lock_counter {
+			0 => Err(UnlockError::NotLocked),
+			1 | usize::MAX => {
+				self.lock_counter = 0;
+
+				Ok(true)
+			}
+			_ => {
+				self.lock_counter -= 1;
+
+				Ok(false)
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::{MockMemoryAccess, MockMemoryLock};
+	use crate::{
+		memory::{
+			access::ReadError,
+			allocate::{AllocateError, MemoryAllocate},
+		},
+		prelude::{MemoryAccess, MemoryLock, MemoryMap, MemoryPagePermissions, OffsetType},
+	};
+
+	#[test]
+	fn test_mock_access() {
+		let mut access = MockMemoryAccess::new()
+			.with_region(OffsetType::new_unwrap(0x104), vec![5, 6, 7, 8])
+			.with_region(OffsetType::new_unwrap(0x100), vec![1, 2, 3, 4])
+			.with_region(OffsetType::new_unwrap(0x200), vec![0; 16])
+			.with_fault([OffsetType::new_unwrap(0x208), OffsetType::new_unwrap(0x20C)]);
+
+		let mut buffer = [0u8; 6];
+		unsafe {
+			access
+				.read(OffsetType::new_unwrap(0x101), &mut buffer)
+				.unwrap();
+			assert_eq!(buffer, [2, 3, 4, 5, 6, 7]);
+
+			access
+				.write(OffsetType::new_unwrap(0x202), &[9, 9])
+				.unwrap();
+			access
+				.read(OffsetType::new_unwrap(0x200), &mut buffer[..4])
+				.unwrap();
+			assert_eq!(buffer[..4], [0, 0, 9, 9]);
+
+			assert!(matches!(
+				access.read(OffsetType::new_unwrap(0x106), &mut buffer),
+				Err(ReadError::NotMapped)
+			));
+			assert!(matches!(
+				access.read(OffsetType::new_unwrap(0x206), &mut buffer),
+				Err(ReadError::Io(_))
+			));
+			access
+				.write(OffsetType::new_unwrap(0x106), &[1; 4])
+				.unwrap_err();
+		}
+
+		let map = access.memory_map();
+		assert_eq!(map.pages().len(), 3);
+		assert_eq!(map.pages()[1].start(), OffsetType::new_unwrap(0x104));
+	}
+
+	#[test]
+	fn test_mock_lock() {
+		let mut lock = MockMemoryLock::new();
+		assert!(lock.lock().unwrap());
+		assert!(!lock.lock().unwrap());
+		lock.lock_exlusive().unwrap_err();
+		assert!(!lock.unlock().unwrap());
+		assert!(lock.unlock().unwrap());
+		lock.unlock().unwrap_err();
+
+		lock.lock_exlusive().unwrap();
+		assert!(lock.is_locked_exclusive());
+		lock.lock().unwrap_err();
+		assert!(lock.unlock().unwrap());
+
+		lock.set_failing(true);
+		lock.lock().unwrap_err();
+		assert!(!lock.is_locked());
+		assert_eq!(lock.acquired(), 2);
+	}
+
+	#[test]
+	fn test_mock_allocate() {
+		let mut access =
+			MockMemoryAccess::new().with_region(OffsetType::new_unwrap(0x1000), vec![0; 16]);
+		let permissions = MemoryPagePermissions::new(true, true, false, false);
+
+		unsafe {
+			let offset = access.allocate(10, permissions).unwrap();
+			assert_eq!(offset.get(), 0x3000);
+			assert_eq!(access.regions().last().unwrap().1.len(), 4096);
+			access.write(offset, &[1, 2, 3]).unwrap();
+
+			assert!(matches!(
+				access.allocate(0, permissions),
+				Err(AllocateError::ZeroSize)
+			));
+			assert!(matches!(
+				access.free(OffsetType::new_unwrap(0x1000)),
+				Err(AllocateError::NotAllocated(_))
+			));
+
+			access.free(offset).unwrap();
+			assert!(access.write(offset, &[1]).is_err());
+		}
+	}
+}
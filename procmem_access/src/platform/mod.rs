@@ -1,15 +1,33 @@
-#[cfg(any(target_os = "linux", target_os = "macos"))]
+#[cfg(any(target_os = "linux", target_os = "android", target_os = "macos"))]
 pub mod ptrace;
 
-#[cfg(target_os = "linux")]
+#[cfg(any(target_os = "linux", target_os = "android", target_os = "macos"))]
+pub mod capabilities;
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
 pub mod procfs;
 
+pub mod core;
+
 #[cfg(target_os = "macos")]
 pub mod mach;
 
+#[cfg(target_os = "windows")]
+pub mod windows;
+
+#[cfg(any(target_os = "freebsd", target_os = "netbsd"))]
+pub mod bsd;
+
+#[cfg(any(test, feature = "test-util"))]
+pub mod mock;
+
 #[cfg(feature = "platform_simple")]
 pub mod simple;
 
-// TODO: mach virtual memory api
+#[cfg(all(
+	feature = "platform_simple",
+	any(target_os = "linux", target_os = "android", target_os = "macos")
+))]
+pub mod launch;
 
-// TODO: windows virtual memory api
+// TODO: mach virtual memory api
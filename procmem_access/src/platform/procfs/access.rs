@@ -7,14 +7,25 @@ use thiserror::Error;
 
 use crate::{
 	common::OffsetType,
+	error::{impl_from_kinded_error, ErrorKind},
 	memory::access::{MemoryAccess, ReadError, WriteError},
 };
 
+use super::vm::{process_vm_readv, process_vm_writev, IOV_MAX};
+
 #[derive(Debug, Error)]
 pub enum ProcfsAccessError {
 	#[error("could not open memory file")]
-	MemoryIo(std::io::Error),
+	MemoryIo(#[source] std::io::Error),
+}
+impl ProcfsAccessError {
+	pub fn kind(&self) -> ErrorKind {
+		match self {
+			ProcfsAccessError::MemoryIo(err) => ErrorKind::from_io(err),
+		}
+	}
 }
+impl_from_kinded_error!(ProcfsAccessError);
 
 /// Procfs implementation of memory access.
 ///
@@ -22,9 +33,8 @@ pub enum ProcfsAccessError {
 ///
 /// Ptrace is detached on drop.
 pub struct ProcfsAccess {
-	#[allow(dead_code)]
 	pid: libc::pid_t,
-	mem: File,
+	pub(super) mem: File,
 }
 impl ProcfsAccess {
 	pub fn mem_path(pid: libc::pid_t) -> std::path::PathBuf {
@@ -41,25 +51,81 @@ impl ProcfsAccess {
 			.read(true)
 			.write(true)
 			.open(path)
-			.map_err(|err| ProcfsAccessError::MemoryIo(err))?;
+			.map_err(ProcfsAccessError::MemoryIo)?;
 
 		Ok(ProcfsAccess { pid, mem })
 	}
 }
 impl MemoryAccess for ProcfsAccess {
 	unsafe fn read(&mut self, offset: OffsetType, buffer: &mut [u8]) -> Result<(), ReadError> {
-		self.mem.seek(SeekFrom::Start(offset.get() as u64))?;
+		self.mem.seek(SeekFrom::Start(offset.get()))?;
 
 		self.mem.read_exact(buffer)?;
 
 		Ok(())
 	}
 
+	unsafe fn read_v(&mut self, requests: &mut [(OffsetType, &mut [u8])]) -> Result<(), ReadError> {
+		for batch in requests.chunks_mut(IOV_MAX) {
+			// the syscall might not be available or permitted, then we fall back to the memory file
+			let complete = process_vm_readv(self.pid, batch).unwrap_or(0);
+
+			// read the rest one by one so that the error is reported for the right request
+			for (offset, buffer) in batch[complete..].iter_mut() {
+				self.read(*offset, buffer)?;
+			}
+		}
+
+		Ok(())
+	}
+
 	unsafe fn write(&mut self, offset: OffsetType, data: &[u8]) -> Result<(), WriteError> {
-		self.mem.seek(SeekFrom::Start(offset.get() as u64))?;
+		self.mem.seek(SeekFrom::Start(offset.get()))?;
 
 		self.mem.write_all(data)?;
 
 		Ok(())
 	}
+
+	unsafe fn write_many(&mut self, writes: &[(OffsetType, &[u8])]) -> Result<(), WriteError> {
+		for batch in writes.chunks(IOV_MAX) {
+			// the syscall respects page permissions, writes to read-only pages fall back to the memory file
+			let complete = process_vm_writev(self.pid, batch).unwrap_or(0);
+
+			for (offset, data) in batch[complete..].iter() {
+				self.write(*offset, data)?;
+			}
+		}
+
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::ProcfsAccess;
+	use crate::{common::OffsetType, memory::access::MemoryAccess};
+
+	fn offset_of(value: &[u8]) -> OffsetType {
+		OffsetType::new_unwrap(value.as_ptr() as u64)
+	}
+
+	#[test]
+	fn test_procfs_read_v_self() {
+		let mut access = ProcfsAccess::new(std::process::id() as libc::pid_t).unwrap();
+
+		let first = [1u8, 2, 3, 4];
+		let second = *b"Hello There";
+
+		let mut first_buffer = [0u8; 4];
+		let mut second_buffer = [0u8; 5];
+		let mut requests = [
+			(offset_of(&first), &mut first_buffer[..]),
+			(offset_of(&second[6..]), &mut second_buffer[..]),
+		];
+		unsafe { access.read_v(&mut requests).unwrap() };
+
+		assert_eq!(first_buffer, first);
+		assert_eq!(&second_buffer, b"There");
+	}
 }
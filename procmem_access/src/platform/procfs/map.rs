@@ -1,15 +1,17 @@
-use std::{
-	fs::{self, OpenOptions},
-	io::Read,
-};
+use std::fs::{self, File};
 
 use thiserror::Error;
 
 use crate::{
-	common::OffsetType,
-	memory::map::{MemoryMap, MemoryPage, MemoryPagePermissions, MemoryPageType},
+	error::{impl_from_kinded_error, ErrorKind},
+	memory::{
+		map::{MemoryMap, MemoryPage, MemoryPageStats},
+		maps_format::{self, MapsReadError},
+	},
 };
 
+pub use crate::memory::maps_format::{MemoryPageParseError, MemoryPagePermissionsParseError};
+
 #[derive(Debug, Error)]
 pub enum ProcfsMemoryMapLoadError {
 	#[error("could not read map file")]
@@ -17,196 +19,150 @@ pub enum ProcfsMemoryMapLoadError {
 	#[error(transparent)]
 	MemoryPageParseError(#[from] MemoryPageParseError),
 }
+impl ProcfsMemoryMapLoadError {
+	pub fn kind(&self) -> ErrorKind {
+		match self {
+			ProcfsMemoryMapLoadError::Io(err) => ErrorKind::from_io(err),
+			ProcfsMemoryMapLoadError::MemoryPageParseError(_) => ErrorKind::Parse,
+		}
+	}
+}
+impl_from_kinded_error!(ProcfsMemoryMapLoadError);
 
 pub struct ProcfsMemoryMap {
 	#[allow(dead_code)]
 	pid: libc::pid_t,
 	pages: Vec<MemoryPage>,
+	skipped_lines: usize,
+	/// Loaded from `smaps` by [`new_with_stats`](Self::new_with_stats).
+	stats: Option<Vec<MemoryPageStats>>,
 }
 impl ProcfsMemoryMap {
 	fn map_path(pid: libc::pid_t) -> std::path::PathBuf {
 		format!("/proc/{}/maps", pid).into()
 	}
 
+	/// Loads the map, failing on lines which cannot be parsed.
+	///
+	/// On Android this behaves like [`new_lossy`](Self::new_lossy), because SELinux may hide parts of the entries.
 	pub fn new(pid: libc::pid_t) -> Result<Self, ProcfsMemoryMapLoadError> {
+		if cfg!(target_os = "android") {
+			return Self::new_lossy(pid);
+		}
+
 		let path = Self::map_path(pid);
 
-		let mut pages = Vec::new();
+		let file = File::open(path)?;
+		// reading the link may be denied even when the maps are readable
+		let exe_path = fs::read_link(format!("/proc/{}/exe", pid)).ok();
 
-		let mut file = OpenOptions::new().read(true).open(path)?;
-		let mut buffer = String::new();
-		// TODO: Lets hope there not invalid unicode in the file paths
-		file.read_to_string(&mut buffer)?;
+		let pages = maps_format::read(file, exe_path.as_deref()).map_err(|err| match err {
+			MapsReadError::Io(err) => ProcfsMemoryMapLoadError::Io(err),
+			MapsReadError::MemoryPageParseError(err) => err.into(),
+		})?;
 
-		let exe_path = fs::read_link(format!("/proc/{}/exe", pid))
-			.ok()
-			.and_then(|p| p.into_os_string().into_string().ok());
+		Ok(ProcfsMemoryMap {
+			pid,
+			pages,
+			skipped_lines: 0,
+			stats: None,
+		})
+	}
 
-		for line in buffer.lines() {
-			let page = Self::parse_map_line(line, exe_path.as_deref())?;
+	/// Loads the map, skipping lines which cannot be parsed, see [`skipped_lines`](Self::skipped_lines).
+	pub fn new_lossy(pid: libc::pid_t) -> Result<Self, ProcfsMemoryMapLoadError> {
+		let text = fs::read_to_string(Self::map_path(pid))?;
+		let exe_path = fs::read_link(format!("/proc/{}/exe", pid)).ok();
 
-			pages.push(page);
-		}
+		let (pages, skipped_lines) = maps_format::parse_lossy(&text, exe_path.as_deref());
 
-		Ok(ProcfsMemoryMap { pid, pages })
+		Ok(ProcfsMemoryMap {
+			pid,
+			pages,
+			skipped_lines,
+			stats: None,
+		})
 	}
 
-	fn parse_page_permissions(
-		string: &str,
-	) -> Result<MemoryPagePermissions, MemoryPagePermissionsParseError> {
-		let mut chars = string.trim().chars();
-
-		let read = match chars.next() {
-			Some('r') => true,
-			Some('-') => false,
-			ch => return Err(MemoryPagePermissionsParseError::InvalidRead(ch)),
-		};
-
-		let write = match chars.next() {
-			Some('w') => true,
-			Some('-') => false,
-			ch => return Err(MemoryPagePermissionsParseError::InvalidWrite(ch)),
-		};
-
-		let exec = match chars.next() {
-			Some('x') => true,
-			Some('-') => false,
-			ch => return Err(MemoryPagePermissionsParseError::InvalidExec(ch)),
-		};
-
-		let share = match chars.next() {
-			Some('s') => true,
-			Some('p') => false,
-			ch => return Err(MemoryPagePermissionsParseError::InvalidShare(ch)),
-		};
-
-		Ok(MemoryPagePermissions::new(read, write, exec, share))
-	}
+	/// Loads the map together with the [`page_stats`](MemoryMap::page_stats) from `/proc/[pid]/smaps`.
+	///
+	/// The kernel walks the page tables of every mapping to produce the statistics, so this is much slower than [`new`](Self::new).
+	pub fn new_with_stats(pid: libc::pid_t) -> Result<Self, ProcfsMemoryMapLoadError> {
+		let text = fs::read_to_string(format!("/proc/{}/smaps", pid))?;
+		let exe_path = fs::read_link(format!("/proc/{}/exe", pid)).ok();
 
-	fn parse_page_type(string: &str, exe_path: Option<&str>) -> MemoryPageType {
-		match string.trim() {
-			"[stack]" => MemoryPageType::Stack,
-			"[heap]" => MemoryPageType::Heap,
-			"" => MemoryPageType::Anon,
-
-			// [vvar] [vdso]
-			s if s.starts_with('[') && s.ends_with(']') => MemoryPageType::Unknown,
-			s if s.ends_with("(deleted)") => MemoryPageType::Unknown,
-
-			path => match exe_path {
-				Some(exe) if path == exe => {
-					MemoryPageType::ProcessExecutable(std::path::PathBuf::from(path))
-				}
-				_ => MemoryPageType::File(std::path::PathBuf::from(path)),
-			},
-		}
-	}
+		let (pages, stats) = maps_format::parse_smaps(&text, exe_path.as_deref())?;
 
-	fn parse_map_line(
-		line: &str,
-		exe_path: Option<&str>,
-	) -> Result<MemoryPage, MemoryPageParseError> {
-		let mut split = line.splitn(6, " ");
-
-		let mut range_split = split
-			.next()
-			.ok_or(MemoryPageParseError::InvalidRange)?
-			.split('-');
-		let from = u64::from_str_radix(
-			range_split
-				.next()
-				.ok_or(MemoryPageParseError::InvalidRange)?,
-			16,
-		)?;
-		let to = u64::from_str_radix(
-			range_split
-				.next()
-				.ok_or(MemoryPageParseError::InvalidRange)?,
-			16,
-		)?;
-
-		let permissions =
-			Self::parse_page_permissions(split.next().ok_or(MemoryPageParseError::InvalidPerms)?)?;
-
-		split.next().ok_or(MemoryPageParseError::InvalidDevnode)?;
-		split.next().ok_or(MemoryPageParseError::InvalidInode)?;
-		let offset = split
-			.next()
-			.ok_or(MemoryPageParseError::InvalidOffset)?
-			.parse::<u64>()?;
-
-		let page_type = Self::parse_page_type(
-			split.next().ok_or(MemoryPageParseError::InvalidEntry)?,
-			exe_path,
-		);
-
-		Ok(MemoryPage {
-			address_range: [OffsetType::new_unwrap(from), OffsetType::new_unwrap(to)],
-			permissions,
-			offset,
-			page_type,
+		Ok(ProcfsMemoryMap {
+			pid,
+			pages,
+			skipped_lines: 0,
+			stats: Some(stats),
 		})
 	}
+
+	/// Returns the number of lines skipped by [`new_lossy`](Self::new_lossy).
+	pub const fn skipped_lines(&self) -> usize {
+		self.skipped_lines
+	}
 }
 impl MemoryMap for ProcfsMemoryMap {
 	fn pages(&self) -> &[MemoryPage] {
 		&self.pages
 	}
-}
 
-#[derive(Debug, Error)]
-pub enum MemoryPagePermissionsParseError {
-	#[error("invalid read permission: {0:?}")]
-	InvalidRead(Option<char>),
-	#[error("invalid write permission: {0:?}")]
-	InvalidWrite(Option<char>),
-	#[error("invalid exec permission: {0:?}")]
-	InvalidExec(Option<char>),
-	#[error("invalid share permission: {0:?}")]
-	InvalidShare(Option<char>),
-}
-#[derive(Debug, Error)]
-pub enum MemoryPageParseError {
-	#[error("mapped range has invalid format")]
-	InvalidRange,
-	#[error("permissions have invalid format")]
-	InvalidPerms,
-	#[error("offset has invalid format")]
-	InvalidOffset,
-	#[error("devnode has invalid format")]
-	InvalidDevnode,
-	#[error("inode has invalid format")]
-	InvalidInode,
-	#[error("entry type has invalid format")]
-	InvalidEntry,
-
-	#[error("could not parse range bounds")]
-	ParseUsize(#[from] std::num::ParseIntError),
-	#[error("could not parse map permissions")]
-	ParseMapPerms(#[from] MemoryPagePermissionsParseError),
+	fn page_stats(&self) -> Option<&[MemoryPageStats]> {
+		self.stats.as_deref()
+	}
 }
 
 #[cfg(test)]
 mod test {
 	use super::ProcfsMemoryMap;
-	use crate::{
-		memory::map::{MemoryPage, MemoryPagePermissions, MemoryPageType},
-		prelude::OffsetType,
-	};
+	use crate::memory::{map::MemoryMap, maps_format};
+
+	#[test]
+	fn test_procfs_maps_self() {
+		let map = ProcfsMemoryMap::new(std::process::id() as libc::pid_t).unwrap();
+		let exe = std::env::current_exe().unwrap();
+
+		assert!(map
+			.pages
+			.iter()
+			.any(|page| page.page_type.module_name()
+				== exe.file_name().and_then(|name| name.to_str())));
+	}
+
+	#[test]
+	fn test_procfs_maps_write_round_trip() {
+		let map = ProcfsMemoryMap::new(std::process::id() as libc::pid_t).unwrap();
+		let exe = std::fs::read_link("/proc/self/exe").unwrap();
+
+		let mut text = Vec::new();
+		map.write_maps(&mut text).unwrap();
+		let pages = maps_format::read(text.as_slice(), Some(&exe)).unwrap();
+
+		assert_eq!(pages, map.pages());
+	}
 
 	#[test]
-	fn test_procfs_maps_parse() {
-		let line = "1f0-20f rw-p 0 00:00 0 [heap]";
-
-		let value = ProcfsMemoryMap::parse_map_line(line, None).unwrap();
-		assert_eq!(
-			value,
-			MemoryPage {
-				address_range: [OffsetType::new_unwrap(496), OffsetType::new_unwrap(527)],
-				permissions: MemoryPagePermissions::new(true, true, false, false),
-				offset: 0,
-				page_type: MemoryPageType::Heap
-			}
-		);
+	fn test_procfs_page_stats() {
+		let pid = std::process::id() as libc::pid_t;
+		assert!(ProcfsMemoryMap::new(pid).unwrap().page_stats().is_none());
+
+		let map = ProcfsMemoryMap::new_with_stats(pid).unwrap();
+		let stats = map.page_stats().unwrap();
+		assert_eq!(stats.len(), map.pages().len());
+
+		// the stack of this thread is resident and written to
+		let local = 0u8;
+		let offset = crate::common::OffsetType::new_unwrap(&local as *const u8 as u64);
+		let index = map
+			.pages()
+			.iter()
+			.position(|page| page.start() <= offset && offset < page.end())
+			.unwrap();
+		assert!(stats[index].resident > 0);
+		assert!(stats[index].dirty() > 0);
 	}
 }
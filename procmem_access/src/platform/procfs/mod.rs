@@ -1,13 +1,30 @@
+use crate::common::{select_one, FindError, NamePattern};
+
 pub mod access;
 pub mod map;
+pub mod pagemap;
+pub mod shm;
+#[cfg(feature = "iouring")]
+pub mod uring;
+pub mod vm;
 
 pub use access::ProcfsAccess;
 pub use map::ProcfsMemoryMap;
+pub use pagemap::PagePresence;
+#[cfg(feature = "iouring")]
+pub use uring::ProcfsUringAccess;
+pub use vm::ProcessVmAccess;
 
+#[derive(Debug, Clone)]
 pub struct ProcessInfo {
 	pub pid: libc::pid_t,
 	pub name: String,
 }
+impl std::fmt::Display for ProcessInfo {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		write!(f, "{} ({})", self.pid, self.name)
+	}
+}
 impl ProcessInfo {
 	pub fn list_all() -> std::io::Result<Vec<Self>> {
 		let mut processes = Vec::new();
@@ -39,6 +56,23 @@ impl ProcessInfo {
 		Ok(processes)
 	}
 
+	/// Lists all processes accepted by `predicate`.
+	pub fn list_filtered(predicate: impl FnMut(&Self) -> bool) -> std::io::Result<Vec<Self>> {
+		Ok(Self::list_all()?.into_iter().filter(predicate).collect())
+	}
+
+	/// Lists all processes with names matching `pattern`.
+	pub fn find_by_name(pattern: NamePattern) -> std::io::Result<Vec<Self>> {
+		Self::list_filtered(|process| pattern.matches(&process.name))
+	}
+
+	/// Finds the one process with name matching `pattern`, preferring an exact name match when more processes match.
+	pub fn find_one_by_name(pattern: NamePattern) -> Result<Self, FindError<Self>> {
+		select_one(pattern, Self::find_by_name(pattern)?, |process| {
+			&process.name
+		})
+	}
+
 	pub fn for_pid(pid: libc::pid_t) -> std::io::Result<Self> {
 		let name = Self::process_name(pid)?;
 		Ok(Self { pid, name })
@@ -47,4 +81,119 @@ impl ProcessInfo {
 	fn process_name(pid: libc::pid_t) -> std::io::Result<String> {
 		std::fs::read_to_string(format!("/proc/{}/comm", pid)).map(|s| s.trim().into())
 	}
+
+	/// Returns the Android package name of the process or `None` if it is not an app.
+	///
+	/// Apps are forked from the zygote (`app_process`) and rename themselves to their package in `cmdline`,
+	/// while `comm` is truncated to 15 bytes. Secondary processes of an app share its package name.
+	pub fn package_name(&self) -> std::io::Result<Option<String>> {
+		let cmdline = std::fs::read(format!("/proc/{}/cmdline", self.pid))?;
+
+		Ok(Self::parse_package_name(&cmdline))
+	}
+
+	fn parse_package_name(cmdline: &[u8]) -> Option<String> {
+		let name = cmdline.split(|&b| b == 0).next()?;
+		let name = std::str::from_utf8(name).ok()?;
+		// `com.example.app:service` is a secondary process of `com.example.app`
+		let package = name.split(':').next()?;
+
+		let is_package = package.contains('.')
+			&& package.split('.').all(|part| {
+				part.starts_with(|ch: char| ch.is_ascii_alphabetic())
+					&& part
+						.chars()
+						.all(|ch| ch.is_ascii_alphanumeric() || ch == '_')
+			});
+
+		is_package.then(|| package.to_string())
+	}
+}
+
+/// Thread of a process, listed from `/proc/<pid>/task`.
+pub struct ThreadInfo {
+	pub tid: libc::pid_t,
+	pub name: String,
+}
+impl ThreadInfo {
+	/// Lists all threads of process `pid`, the main thread has `tid == pid`.
+	pub fn list(pid: libc::pid_t) -> std::io::Result<Vec<Self>> {
+		let mut threads = Vec::new();
+
+		for entry in std::fs::read_dir(format!("/proc/{}/task", pid))? {
+			let entry = entry?;
+
+			let tid = match entry
+				.file_name()
+				.to_str()
+				.and_then(|e| e.parse::<libc::pid_t>().ok())
+			{
+				None => continue,
+				Some(t) => t,
+			};
+
+			// the thread may have exited in the meantime
+			let name = match std::fs::read_to_string(entry.path().join("comm")) {
+				Err(_) => continue,
+				Ok(name) => name.trim().into(),
+			};
+
+			threads.push(ThreadInfo { tid, name });
+		}
+		threads.sort_by_key(|thread| thread.tid);
+
+		Ok(threads)
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::{ProcessInfo, ThreadInfo};
+	use crate::common::NamePattern;
+
+	#[test]
+	fn test_find_by_name() {
+		let pid = std::process::id() as libc::pid_t;
+		let me = ProcessInfo::for_pid(pid).unwrap();
+
+		let found = ProcessInfo::find_by_name(NamePattern::Exact(&me.name)).unwrap();
+		assert!(found.iter().any(|process| process.pid == pid));
+
+		let found = ProcessInfo::list_filtered(|process| process.pid == pid).unwrap();
+		assert_eq!(found.len(), 1);
+	}
+
+	#[test]
+	fn test_list_threads() {
+		let pid = std::process::id() as libc::pid_t;
+		let (sender, receiver) = std::sync::mpsc::channel::<()>();
+		let thread = std::thread::spawn(move || receiver.recv().unwrap());
+
+		let threads = ThreadInfo::list(pid).unwrap();
+		assert!(threads.len() >= 2);
+		assert!(threads.iter().any(|thread| thread.tid == pid));
+		assert!(threads
+			.iter()
+			.any(|thread| thread.tid == unsafe { libc::gettid() }));
+
+		sender.send(()).unwrap();
+		thread.join().unwrap();
+	}
+
+	#[test]
+	fn test_package_name() {
+		assert_eq!(
+			ProcessInfo::parse_package_name(b"com.example.app:remote\0").as_deref(),
+			Some("com.example.app")
+		);
+		assert_eq!(ProcessInfo::parse_package_name(b"system_server\0"), None);
+		assert_eq!(
+			ProcessInfo::parse_package_name(b"/system/bin/surfaceflinger\0"),
+			None
+		);
+
+		// test binaries are started by path
+		let me = ProcessInfo::for_pid(std::process::id() as libc::pid_t).unwrap();
+		assert_eq!(me.package_name().unwrap(), None);
+	}
 }
@@ -0,0 +1,191 @@
+//! Residency of virtual pages from `/proc/[pid]/pagemap`.
+//!
+//! Processes such as the JVM or browsers reserve huge anonymous mappings and only touch small parts of them.
+//! Reading the untouched parts returns zeroes, but it still costs a page fault for each page, so scans can use
+//! [`PagePresence`] to only read the parts of anonymous mappings which were ever written.
+
+use std::{fs::File, os::unix::fs::FileExt, path::PathBuf};
+
+use thiserror::Error;
+
+use crate::{
+	common::OffsetType,
+	error::{impl_from_kinded_error, ErrorKind},
+	memory::map::{MemoryPage, MemoryPageType},
+};
+
+/// The page is in memory.
+const PAGEMAP_PRESENT: u64 = 1 << 63;
+/// The page is in swap.
+const PAGEMAP_SWAPPED: u64 = 1 << 62;
+/// Number of entries read at once, the file has one `u64` entry per page.
+const PAGEMAP_CHUNK: usize = 4096;
+
+#[derive(Debug, Error)]
+pub enum PagePresenceError {
+	#[error("could not open pagemap file")]
+	Open(#[source] std::io::Error),
+	#[error("could not read pagemap file")]
+	Read(#[source] std::io::Error),
+}
+impl PagePresenceError {
+	pub fn kind(&self) -> ErrorKind {
+		match self {
+			PagePresenceError::Open(err) => ErrorKind::from_io(err),
+			PagePresenceError::Read(err) => ErrorKind::from_io(err),
+		}
+	}
+}
+impl_from_kinded_error!(PagePresenceError);
+
+/// Reports which pages of a process are backed by memory or swap.
+///
+/// Pages of file mappings which are not resident still have the content of the file, so only anonymous pages should be skipped,
+/// see [`filter_pages`](PagePresence::filter_pages).
+pub struct PagePresence {
+	pagemap: File,
+	page_size: u64,
+}
+impl PagePresence {
+	pub fn pagemap_path(pid: libc::pid_t) -> PathBuf {
+		format!("/proc/{}/pagemap", pid).into()
+	}
+
+	/// Opens the pagemap of the process with given `pid`.
+	///
+	/// Reading the flags of the entries does not require privileges since Linux 4.2.
+	pub fn new(pid: libc::pid_t) -> Result<Self, PagePresenceError> {
+		let pagemap = File::open(Self::pagemap_path(pid)).map_err(PagePresenceError::Open)?;
+		let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) } as u64;
+
+		Ok(PagePresence { pagemap, page_size })
+	}
+
+	pub const fn page_size(&self) -> u64 {
+		self.page_size
+	}
+
+	/// Returns whether the page containing `offset` is in memory or swap.
+	pub fn is_backed(&mut self, offset: OffsetType) -> Result<bool, PagePresenceError> {
+		let mut entry = [0u8; 8];
+		self.pagemap
+			.read_exact_at(&mut entry, offset.get() / self.page_size * 8)
+			.map_err(PagePresenceError::Read)?;
+
+		Ok(Self::entry_backed(u64::from_ne_bytes(entry)))
+	}
+
+	/// Splits `range` into the sorted subranges which are in memory or swap.
+	pub fn backed_ranges(
+		&mut self,
+		range: [OffsetType; 2],
+	) -> Result<Vec<[OffsetType; 2]>, PagePresenceError> {
+		let [start, end] = range;
+		let first_page = start.get() / self.page_size;
+		let end_page = end.get().div_ceil(self.page_size);
+
+		let mut ranges: Vec<[OffsetType; 2]> = Vec::new();
+		let mut buffer = vec![0u8; PAGEMAP_CHUNK * 8];
+		let mut page = first_page;
+		while page < end_page {
+			let count = ((end_page - page) as usize).min(PAGEMAP_CHUNK);
+			let entries = &mut buffer[..count * 8];
+			self.pagemap
+				.read_exact_at(entries, page * 8)
+				.map_err(PagePresenceError::Read)?;
+
+			for (index, entry) in entries.chunks_exact(8).enumerate() {
+				if !Self::entry_backed(u64::from_ne_bytes(entry.try_into().unwrap())) {
+					continue;
+				}
+
+				// the first and last page may be only partially in the range
+				let page_base = (page + index as u64) * self.page_size;
+				let page_start = OffsetType::new_unwrap(page_base.max(start.get()));
+				let page_end = OffsetType::new_unwrap(page_base + self.page_size).min(end);
+
+				// merge with the previous page if they are adjacent
+				match ranges.last_mut() {
+					Some(last) if last[1] == page_start => last[1] = page_end,
+					_ => ranges.push([page_start, page_end]),
+				}
+			}
+
+			page += count as u64;
+		}
+
+		Ok(ranges)
+	}
+
+	/// Returns the ranges of `pages` worth scanning.
+	///
+	/// File-backed pages are kept whole, anonymous pages are reduced to their [`backed_ranges`](PagePresence::backed_ranges).
+	pub fn filter_pages<'a>(
+		&mut self,
+		pages: impl IntoIterator<Item = &'a MemoryPage>,
+	) -> Result<Vec<[OffsetType; 2]>, PagePresenceError> {
+		let mut ranges = Vec::new();
+		for page in pages {
+			match page.page_type {
+				MemoryPageType::File(_) | MemoryPageType::ProcessExecutable(_) => {
+					ranges.push(page.address_range)
+				}
+				_ => ranges.extend(self.backed_ranges(page.address_range)?),
+			}
+		}
+
+		Ok(ranges)
+	}
+
+	const fn entry_backed(entry: u64) -> bool {
+		entry & (PAGEMAP_PRESENT | PAGEMAP_SWAPPED) != 0
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::PagePresence;
+	use crate::common::OffsetType;
+
+	#[test]
+	fn test_backed_ranges() {
+		let mut presence = PagePresence::new(std::process::id() as libc::pid_t).unwrap();
+		let page_size = presence.page_size() as usize;
+
+		let length = page_size * 16;
+		let mapping = unsafe {
+			libc::mmap(
+				std::ptr::null_mut(),
+				length,
+				libc::PROT_READ | libc::PROT_WRITE,
+				libc::MAP_PRIVATE | libc::MAP_ANONYMOUS,
+				-1,
+				0,
+			)
+		};
+		assert_ne!(mapping, libc::MAP_FAILED);
+		let base = mapping as usize;
+
+		// touch pages 3, 4 and 10
+		for page in [3, 4, 10] {
+			unsafe { std::ptr::write_volatile((base + page * page_size) as *mut u8, 1) };
+		}
+
+		let offset = |page: usize| OffsetType::new((base + page * page_size) as u64).unwrap();
+		assert_eq!(
+			presence.backed_ranges([offset(0), offset(16)]).unwrap(),
+			[[offset(3), offset(5)], [offset(10), offset(11)]]
+		);
+		assert!(presence.is_backed(offset(4)).unwrap());
+		assert!(!presence.is_backed(offset(5)).unwrap());
+
+		// ranges are clipped to the requested range
+		let inner_start = offset(4).saturating_add(8);
+		assert_eq!(
+			presence.backed_ranges([inner_start, offset(8)]).unwrap(),
+			[[inner_start, offset(5)]]
+		);
+
+		unsafe { libc::munmap(mapping, length) };
+	}
+}
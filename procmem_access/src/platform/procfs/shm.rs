@@ -0,0 +1,467 @@
+//! System V and POSIX shared memory segments.
+//!
+//! Shared memory is mapped into each process using it, so it can be read through any of them.
+//! Reading it directly through [`SharedMemoryAccess`] avoids locking the processes and scanning
+//! the same segment once per process.
+
+use std::{
+	fs::{File, OpenOptions},
+	os::unix::io::AsRawFd,
+	path::PathBuf,
+};
+
+use thiserror::Error;
+
+use crate::{
+	common::OffsetType,
+	error::{impl_from_kinded_error, ErrorKind},
+	memory::{
+		access::{MemoryAccess, ReadError, WriteError},
+		map::MemoryPagePermissions,
+		maps_format,
+	},
+};
+
+use super::map::MemoryPageParseError;
+
+#[derive(Debug, Error)]
+pub enum SharedMemoryError {
+	#[error("could not read shared memory information")]
+	Io(#[from] std::io::Error),
+	#[error("could not parse line {0:?}")]
+	Parse(String),
+	#[error(transparent)]
+	MemoryPageParseError(#[from] MemoryPageParseError),
+	#[error("could not attach shared memory segment")]
+	Attach(#[source] std::io::Error),
+}
+impl SharedMemoryError {
+	pub fn kind(&self) -> ErrorKind {
+		match self {
+			SharedMemoryError::Io(err) => ErrorKind::from_io(err),
+			SharedMemoryError::Parse(_) => ErrorKind::Parse,
+			SharedMemoryError::MemoryPageParseError(_) => ErrorKind::Parse,
+			SharedMemoryError::Attach(err) => ErrorKind::from_io(err),
+		}
+	}
+}
+impl_from_kinded_error!(SharedMemoryError);
+
+/// Identity of a shared memory segment, the same in all processes which map it.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum SharedMemoryId {
+	SysV { shmid: i32 },
+	Posix { device: String, inode: u64 },
+}
+
+/// Part of a shared memory segment mapped into a process.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SharedMemoryMapping {
+	pub id: SharedMemoryId,
+	/// Path of the segment, `/SYSV<key>` for System V segments.
+	pub path: PathBuf,
+	/// Mapped range in the process.
+	pub address_range: [OffsetType; 2],
+	/// Offset of the mapped range within the segment.
+	pub segment_offset: u64,
+	pub permissions: MemoryPagePermissions,
+}
+impl SharedMemoryMapping {
+	pub const fn size(&self) -> u64 {
+		self.address_range[1].get() - self.address_range[0].get()
+	}
+
+	/// Lists shared memory mappings of process `pid`.
+	pub fn list(pid: libc::pid_t) -> Result<Vec<Self>, SharedMemoryError> {
+		let maps = std::fs::read_to_string(format!("/proc/{}/maps", pid))?;
+
+		let mut mappings = Vec::new();
+		for line in maps.lines() {
+			if let Some(mapping) = Self::parse_map_line(line)? {
+				mappings.push(mapping);
+			}
+		}
+
+		Ok(mappings)
+	}
+
+	/// Parses a line of `/proc/[pid]/maps`, returning `None` if it is not shared memory.
+	fn parse_map_line(line: &str) -> Result<Option<Self>, SharedMemoryError> {
+		let parse_error = || SharedMemoryError::Parse(line.to_string());
+
+		let mut split = line.splitn(6, ' ');
+		let mut next = || split.next().ok_or_else(parse_error);
+
+		let (range, permissions, offset, device, inode) =
+			(next()?, next()?, next()?, next()?, next()?);
+		let path = split.next().unwrap_or_default().trim();
+		let path = path.strip_suffix(" (deleted)").unwrap_or(path);
+
+		let is_sysv = path.starts_with("/SYSV");
+		if !is_sysv && !path.starts_with("/dev/shm/") {
+			return Ok(None);
+		}
+
+		let (from, to) = range.split_once('-').ok_or_else(parse_error)?;
+		let parse_hex = |s| u64::from_str_radix(s, 16).map_err(|_| parse_error());
+		let address_range = [
+			OffsetType::new(parse_hex(from)?).ok_or_else(parse_error)?,
+			OffsetType::new(parse_hex(to)?).ok_or_else(parse_error)?,
+		];
+		let inode: u64 = inode.parse().map_err(|_| parse_error())?;
+
+		let id = if is_sysv {
+			// the inode of a System V segment is its id
+			SharedMemoryId::SysV {
+				shmid: inode as i32,
+			}
+		} else {
+			SharedMemoryId::Posix {
+				device: device.to_string(),
+				inode,
+			}
+		};
+
+		Ok(Some(SharedMemoryMapping {
+			id,
+			path: PathBuf::from(path),
+			address_range,
+			segment_offset: parse_hex(offset)?,
+			permissions: maps_format::parse_permissions(permissions)
+				.map_err(MemoryPageParseError::from)?,
+		}))
+	}
+
+	/// Removes mappings of the same part of the same segment, keeping the first one.
+	///
+	/// Use this to scan each segment once when collecting mappings from multiple processes.
+	pub fn dedup<T>(mappings: impl IntoIterator<Item = (T, Self)>) -> Vec<(T, Self)> {
+		let mut seen = std::collections::HashSet::new();
+
+		mappings
+			.into_iter()
+			.filter(|(_, mapping)| {
+				seen.insert((mapping.id.clone(), mapping.segment_offset, mapping.size()))
+			})
+			.collect()
+	}
+}
+
+/// System V shared memory segment as listed in `/proc/sysvipc/shm`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SysVSegmentInfo {
+	pub key: i32,
+	pub shmid: i32,
+	pub size: u64,
+	/// Pid of the creator.
+	pub creator_pid: libc::pid_t,
+	/// Pid of the last process to attach or detach.
+	pub last_pid: libc::pid_t,
+	/// Number of current attaches.
+	pub attach_count: u64,
+}
+impl SysVSegmentInfo {
+	/// Lists all System V shared memory segments in the system.
+	pub fn list_all() -> Result<Vec<Self>, SharedMemoryError> {
+		let table = std::fs::read_to_string("/proc/sysvipc/shm")?;
+
+		table
+			.lines()
+			.skip(1)
+			.map(|line| {
+				let parse_error = || SharedMemoryError::Parse(line.to_string());
+
+				let fields: Vec<&str> = line.split_whitespace().collect();
+				if fields.len() < 7 {
+					return Err(parse_error());
+				}
+
+				Ok(SysVSegmentInfo {
+					key: fields[0].parse().map_err(|_| parse_error())?,
+					shmid: fields[1].parse().map_err(|_| parse_error())?,
+					size: fields[3].parse().map_err(|_| parse_error())?,
+					creator_pid: fields[4].parse().map_err(|_| parse_error())?,
+					last_pid: fields[5].parse().map_err(|_| parse_error())?,
+					attach_count: fields[6].parse().map_err(|_| parse_error())?,
+				})
+			})
+			.collect()
+	}
+}
+
+enum Attachment {
+	#[cfg(target_os = "linux")]
+	SysV(*mut libc::c_void),
+	Mmap(*mut libc::c_void, usize),
+}
+
+/// Direct access to a shared memory mapping of a process.
+///
+/// The segment is attached into this process, so reads and writes do not go through the target process at all.
+/// Offsets are addresses in the target process, as in the [`SharedMemoryMapping::address_range`].
+pub struct SharedMemoryAccess {
+	attachment: Attachment,
+	data: *mut u8,
+	address_range: [OffsetType; 2],
+	writable: bool,
+}
+impl SharedMemoryAccess {
+	/// Attaches the shared memory of `mapping` from process `pid`.
+	///
+	/// The segment is attached writable if permitted, read-only otherwise.
+	pub fn open(
+		pid: libc::pid_t,
+		mapping: &SharedMemoryMapping,
+	) -> Result<Self, SharedMemoryError> {
+		let (attachment, data, writable) = match mapping.id {
+			SharedMemoryId::SysV { shmid } => Self::attach_sysv(shmid, mapping.segment_offset)?,
+			SharedMemoryId::Posix { .. } => Self::attach_posix(pid, mapping)?,
+		};
+
+		Ok(SharedMemoryAccess {
+			attachment,
+			data,
+			address_range: mapping.address_range,
+			writable,
+		})
+	}
+
+	#[cfg(target_os = "linux")]
+	fn attach_sysv(
+		shmid: i32,
+		segment_offset: u64,
+	) -> Result<(Attachment, *mut u8, bool), SharedMemoryError> {
+		let attach = |flags| {
+			let ptr = unsafe { libc::shmat(shmid, std::ptr::null(), flags) };
+			if ptr as isize == -1 {
+				Err(std::io::Error::last_os_error())
+			} else {
+				Ok(ptr)
+			}
+		};
+
+		let (ptr, writable) = match attach(0) {
+			Ok(ptr) => (ptr, true),
+			Err(_) => (
+				attach(libc::SHM_RDONLY).map_err(SharedMemoryError::Attach)?,
+				false,
+			),
+		};
+
+		let data = unsafe { (ptr as *mut u8).add(segment_offset as usize) };
+
+		Ok((Attachment::SysV(ptr), data, writable))
+	}
+
+	/// Android has no System V shared memory.
+	#[cfg(target_os = "android")]
+	fn attach_sysv(
+		_shmid: i32,
+		_segment_offset: u64,
+	) -> Result<(Attachment, *mut u8, bool), SharedMemoryError> {
+		Err(SharedMemoryError::Attach(
+			std::io::Error::from_raw_os_error(libc::ENOSYS),
+		))
+	}
+
+	fn attach_posix(
+		pid: libc::pid_t,
+		mapping: &SharedMemoryMapping,
+	) -> Result<(Attachment, *mut u8, bool), SharedMemoryError> {
+		// the segment might have been unlinked, the map file refers to it regardless
+		let map_file = format!(
+			"/proc/{}/map_files/{:x}-{:x}",
+			pid,
+			mapping.address_range[0].get(),
+			mapping.address_range[1].get()
+		);
+		let open = |write| -> std::io::Result<File> {
+			OpenOptions::new()
+				.read(true)
+				.write(write)
+				.open(&mapping.path)
+				.or_else(|_| OpenOptions::new().read(true).write(write).open(&map_file))
+		};
+		let (file, writable) = match open(true) {
+			Ok(file) => (file, true),
+			Err(_) => (open(false).map_err(SharedMemoryError::Attach)?, false),
+		};
+
+		let size = mapping.size() as usize;
+		let protection = if writable {
+			libc::PROT_READ | libc::PROT_WRITE
+		} else {
+			libc::PROT_READ
+		};
+		let ptr = unsafe {
+			libc::mmap(
+				std::ptr::null_mut(),
+				size,
+				protection,
+				libc::MAP_SHARED,
+				file.as_raw_fd(),
+				mapping.segment_offset as libc::off_t,
+			)
+		};
+		if ptr == libc::MAP_FAILED {
+			return Err(SharedMemoryError::Attach(std::io::Error::last_os_error()));
+		}
+
+		Ok((Attachment::Mmap(ptr, size), ptr as *mut u8, writable))
+	}
+
+	pub const fn address_range(&self) -> [OffsetType; 2] {
+		self.address_range
+	}
+
+	pub const fn is_writable(&self) -> bool {
+		self.writable
+	}
+
+	/// Returns the position of `offset..offset + len` in the attached data.
+	fn position(&self, offset: OffsetType, len: usize) -> Option<usize> {
+		let [start, end] = self.address_range;
+		if offset < start || offset.get() + len as u64 > end.get() {
+			return None;
+		}
+
+		Some((offset.get() - start.get()) as usize)
+	}
+}
+impl MemoryAccess for SharedMemoryAccess {
+	unsafe fn read(&mut self, offset: OffsetType, buffer: &mut [u8]) -> Result<(), ReadError> {
+		let position = self
+			.position(offset, buffer.len())
+			.ok_or(ReadError::NotMapped)?;
+
+		std::ptr::copy_nonoverlapping(self.data.add(position), buffer.as_mut_ptr(), buffer.len());
+
+		Ok(())
+	}
+
+	unsafe fn write(&mut self, offset: OffsetType, data: &[u8]) -> Result<(), WriteError> {
+		let position = self
+			.position(offset, data.len())
+			.ok_or(WriteError::NotMapped)?;
+		if !self.writable {
+			return Err(WriteError::NotPermitted);
+		}
+
+		std::ptr::copy_nonoverlapping(data.as_ptr(), self.data.add(position), data.len());
+
+		Ok(())
+	}
+}
+impl Drop for SharedMemoryAccess {
+	fn drop(&mut self) {
+		unsafe {
+			match self.attachment {
+				#[cfg(target_os = "linux")]
+				Attachment::SysV(ptr) => {
+					libc::shmdt(ptr);
+				}
+				Attachment::Mmap(ptr, size) => {
+					libc::munmap(ptr, size);
+				}
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	#[cfg(target_os = "linux")]
+	use super::{SharedMemoryAccess, SysVSegmentInfo};
+	use super::{SharedMemoryId, SharedMemoryMapping};
+	#[cfg(target_os = "linux")]
+	use crate::{common::OffsetType, memory::access::MemoryAccess};
+
+	#[test]
+	fn test_parse_shm_map_line() {
+		let mapping = SharedMemoryMapping::parse_map_line(
+			"7f0000000000-7f0000002000 rw-s 00001000 00:01 42                         /SYSV0000162e (deleted)",
+		)
+		.unwrap()
+		.unwrap();
+		assert_eq!(mapping.id, SharedMemoryId::SysV { shmid: 42 });
+		assert_eq!(mapping.path.to_str(), Some("/SYSV0000162e"));
+		assert_eq!(mapping.segment_offset, 0x1000);
+		assert_eq!(mapping.size(), 0x2000);
+
+		let mapping = SharedMemoryMapping::parse_map_line(
+			"7f0000000000-7f0000001000 r--s 00000000 00:1a 7                          /dev/shm/state",
+		)
+		.unwrap()
+		.unwrap();
+		assert_eq!(
+			mapping.id,
+			SharedMemoryId::Posix {
+				device: "00:1a".to_string(),
+				inode: 7
+			}
+		);
+		assert!(!mapping.permissions.write());
+
+		assert!(SharedMemoryMapping::parse_map_line(
+			"7f0000000000-7f0000001000 r-xp 00000000 08:01 1234                       /usr/lib/libc.so.6",
+		)
+		.unwrap()
+		.is_none());
+
+		let mappings = SharedMemoryMapping::dedup([(1, mapping.clone()), (2, mapping)]);
+		assert_eq!(mappings.len(), 1);
+		assert_eq!(mappings[0].0, 1);
+	}
+
+	#[cfg(target_os = "linux")]
+	#[test]
+	fn test_sysv_segment_access() {
+		let shmid = unsafe { libc::shmget(libc::IPC_PRIVATE, 4096, libc::IPC_CREAT | 0o600) };
+		if shmid == -1 {
+			eprintln!(
+				"skipping, could not create segment: {}",
+				std::io::Error::last_os_error()
+			);
+			return;
+		}
+		let ptr = unsafe { libc::shmat(shmid, std::ptr::null(), 0) } as *mut u8;
+		assert_ne!(ptr as isize, -1);
+		// the segment is destroyed once the last attachment is gone
+		unsafe { libc::shmctl(shmid, libc::IPC_RMID, std::ptr::null_mut()) };
+		unsafe { ptr.add(16).copy_from([1u8, 2, 3, 4].as_ptr(), 4) };
+
+		assert!(SysVSegmentInfo::list_all()
+			.unwrap()
+			.iter()
+			.any(|segment| segment.shmid == shmid && segment.size == 4096));
+
+		let pid = std::process::id() as libc::pid_t;
+		let mapping = SharedMemoryMapping::list(pid)
+			.unwrap()
+			.into_iter()
+			.find(|mapping| mapping.id == SharedMemoryId::SysV { shmid })
+			.unwrap();
+		assert_eq!(mapping.address_range[0].get(), ptr as u64);
+
+		let mut access = SharedMemoryAccess::open(pid, &mapping).unwrap();
+		let mut buffer = [0u8; 4];
+		unsafe {
+			access
+				.read(OffsetType::new_unwrap(ptr as u64 + 16), &mut buffer)
+				.unwrap();
+			access
+				.write(OffsetType::new_unwrap(ptr as u64), &[9])
+				.unwrap();
+		}
+		assert_eq!(buffer, [1, 2, 3, 4]);
+		assert_eq!(unsafe { ptr.read_volatile() }, 9);
+		assert!(unsafe {
+			access
+				.read(OffsetType::new_unwrap(ptr as u64 + 4094), &mut buffer)
+				.is_err()
+		});
+
+		drop(access);
+		unsafe { libc::shmdt(ptr as *const libc::c_void) };
+	}
+}
@@ -0,0 +1,271 @@
+use std::os::unix::io::AsRawFd;
+
+use io_uring::{opcode, types, IoUring};
+use thiserror::Error;
+
+use crate::{
+	common::OffsetType,
+	error::{impl_from_kinded_error, ErrorKind},
+	memory::access::{MemoryAccess, ReadError, WriteError},
+};
+
+use super::{access::ProcfsAccessError, ProcfsAccess};
+
+#[derive(Debug, Error)]
+pub enum ProcfsUringAccessError {
+	#[error(transparent)]
+	Access(#[from] ProcfsAccessError),
+	#[error("could not create io_uring instance")]
+	UringSetup(#[source] std::io::Error),
+}
+impl ProcfsUringAccessError {
+	pub fn kind(&self) -> ErrorKind {
+		match self {
+			ProcfsUringAccessError::Access(err) => err.kind(),
+			ProcfsUringAccessError::UringSetup(err) => ErrorKind::from_io(err),
+		}
+	}
+}
+impl_from_kinded_error!(ProcfsUringAccessError);
+
+/// Procfs memory access which submits batches of reads through io_uring.
+///
+/// Single reads and writes go through [`ProcfsAccess`], only [`read_v`](MemoryAccess::read_v) and [`read_v_with`](ProcfsUringAccess::read_v_with) use the ring.
+/// This removes most of the per-read syscall overhead when scanning large processes page by page.
+pub struct ProcfsUringAccess {
+	access: ProcfsAccess,
+	ring: IoUring,
+}
+impl ProcfsUringAccess {
+	/// Default number of submission queue entries.
+	pub const DEFAULT_ENTRIES: u32 = 256;
+
+	/// Opens a process with given `pid` and creates a ring with [`DEFAULT_ENTRIES`](Self::DEFAULT_ENTRIES) entries.
+	pub fn new(pid: libc::pid_t) -> Result<Self, ProcfsUringAccessError> {
+		Self::with_entries(pid, Self::DEFAULT_ENTRIES)
+	}
+
+	/// Opens a process with given `pid` and creates a ring with `entries` submission queue entries.
+	///
+	/// This is the maximum number of reads in flight at once.
+	pub fn with_entries(pid: libc::pid_t, entries: u32) -> Result<Self, ProcfsUringAccessError> {
+		let access = ProcfsAccess::new(pid)?;
+		let ring = IoUring::new(entries).map_err(ProcfsUringAccessError::UringSetup)?;
+
+		Ok(ProcfsUringAccess { access, ring })
+	}
+
+	pub fn access(&mut self) -> &mut ProcfsAccess {
+		&mut self.access
+	}
+
+	pub fn into_inner(self) -> ProcfsAccess {
+		self.access
+	}
+
+	/// Reads all `requests`, calling `on_complete` with the index of each request as soon as it is done.
+	///
+	/// Requests complete in arbitrary order. Short reads are finished synchronously, so the error passed to `on_complete` always belongs to the right request.
+	///
+	/// Returns an error only if the ring itself fails, in which case some requests might not have been reported.
+	///
+	/// ## Safety
+	/// * See [`MemoryAccess::read`].
+	pub unsafe fn read_v_with(
+		&mut self,
+		requests: &mut [(OffsetType, &mut [u8])],
+		mut on_complete: impl FnMut(usize, Result<(), ReadError>),
+	) -> std::io::Result<()> {
+		let fd = types::Fd(self.access.mem.as_raw_fd());
+
+		let mut next = 0;
+		let mut in_flight = 0;
+		while next < requests.len() || in_flight > 0 {
+			// fill the submission queue
+			{
+				let mut submission = self.ring.submission();
+				while next < requests.len() && !submission.is_full() {
+					let (offset, buffer) = &mut requests[next];
+					if buffer.len() > u32::MAX as usize {
+						// too big for one entry, this is rare enough to just read it directly
+						let result = self.access.read(*offset, buffer);
+						on_complete(next, result);
+						next += 1;
+						continue;
+					}
+
+					let entry = opcode::Read::new(fd, buffer.as_mut_ptr(), buffer.len() as u32)
+						.offset(offset.get())
+						.build()
+						.user_data(next as u64);
+					// cannot fail because we checked that the queue is not full
+					submission.push(&entry).unwrap();
+
+					next += 1;
+					in_flight += 1;
+				}
+			}
+
+			if in_flight == 0 {
+				continue;
+			}
+
+			match self.ring.submit_and_wait(1) {
+				Ok(_) => (),
+				Err(err)
+					if matches!(
+						err.raw_os_error(),
+						Some(libc::EINTR) | Some(libc::EAGAIN) | Some(libc::EBUSY)
+					) => {}
+				Err(err) => return Err(err),
+			}
+
+			// collect completions first so that the ring is not borrowed during the synchronous reads
+			let completed: Vec<(usize, i32)> = self
+				.ring
+				.completion()
+				.map(|entry| (entry.user_data() as usize, entry.result()))
+				.collect();
+			in_flight -= completed.len();
+
+			for (index, result) in completed {
+				let (offset, buffer) = &mut requests[index];
+
+				// errors are converted the same way as those of `ProcfsAccess::read`
+				let result = if result < 0 {
+					Err(ReadError::from(std::io::Error::from_raw_os_error(-result)))
+				} else if (result as usize) < buffer.len() {
+					let read = result as usize;
+
+					self.access.read(
+						OffsetType::new_unwrap(offset.get() + read as u64),
+						&mut buffer[read..],
+					)
+				} else {
+					Ok(())
+				};
+
+				on_complete(index, result);
+			}
+		}
+
+		Ok(())
+	}
+}
+impl MemoryAccess for ProcfsUringAccess {
+	unsafe fn read(&mut self, offset: OffsetType, buffer: &mut [u8]) -> Result<(), ReadError> {
+		self.access.read(offset, buffer)
+	}
+
+	unsafe fn read_v(&mut self, requests: &mut [(OffsetType, &mut [u8])]) -> Result<(), ReadError> {
+		// requests complete out of order, report the error of the first failed request like the synchronous path does
+		let mut first_error: Option<(usize, ReadError)> = None;
+		self.read_v_with(requests, |index, result| {
+			if let Err(err) = result {
+				if first_error.as_ref().is_none_or(|(first, _)| index < *first) {
+					first_error = Some((index, err));
+				}
+			}
+		})?;
+
+		match first_error {
+			None => Ok(()),
+			Some((_, err)) => Err(err),
+		}
+	}
+
+	unsafe fn write(&mut self, offset: OffsetType, data: &[u8]) -> Result<(), WriteError> {
+		self.access.write(offset, data)
+	}
+
+	unsafe fn write_many(&mut self, writes: &[(OffsetType, &[u8])]) -> Result<(), WriteError> {
+		self.access.write_many(writes)
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::{ProcfsUringAccess, ProcfsUringAccessError};
+	use crate::{
+		common::OffsetType,
+		error::ErrorKind,
+		memory::access::{MemoryAccess, ReadError},
+		platform::procfs::ProcfsAccess,
+	};
+
+	#[test]
+	fn test_procfs_uring_read_v_self() {
+		let mut access = match ProcfsUringAccess::with_entries(std::process::id() as libc::pid_t, 2)
+		{
+			// io_uring may be disabled by the kernel or a seccomp filter
+			Err(ProcfsUringAccessError::UringSetup(_)) => return,
+			result => result.unwrap(),
+		};
+
+		let data: Vec<[u8; 16]> = (0..5u8).map(|i| [i; 16]).collect();
+
+		let mut buffers = vec![[0u8; 16]; data.len()];
+		let mut requests: Vec<_> = data
+			.iter()
+			.zip(buffers.iter_mut())
+			.map(|(value, buffer)| {
+				(
+					OffsetType::new_unwrap(value.as_ptr() as u64),
+					&mut buffer[..],
+				)
+			})
+			.collect();
+		unsafe { access.read_v(&mut requests).unwrap() };
+
+		assert_eq!(buffers, data);
+	}
+
+	#[test]
+	fn test_procfs_uring_read_v_unmapped() {
+		let pid = std::process::id() as libc::pid_t;
+		let mut access = match ProcfsUringAccess::with_entries(pid, 4) {
+			Err(ProcfsUringAccessError::UringSetup(_)) => return,
+			result => result.unwrap(),
+		};
+
+		let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) } as usize;
+		let mapping = unsafe {
+			libc::mmap(
+				std::ptr::null_mut(),
+				page_size * 2,
+				libc::PROT_READ | libc::PROT_WRITE,
+				libc::MAP_PRIVATE | libc::MAP_ANONYMOUS,
+				-1,
+				0,
+			)
+		};
+		assert_ne!(mapping, libc::MAP_FAILED);
+		// leave the second page unmapped
+		unsafe { libc::munmap((mapping as usize + page_size) as *mut _, page_size) };
+
+		let mapped = OffsetType::new_unwrap(mapping as u64);
+		let unmapped = OffsetType::new_unwrap((mapping as usize + page_size) as u64);
+		let mut buffers = [[0u8; 16]; 4];
+		let [first, second, third, fourth] = &mut buffers;
+		let mut requests = [
+			(mapped, &mut first[..]),
+			(unmapped, &mut second[..]),
+			(mapped, &mut third[..]),
+			(unmapped, &mut fourth[..]),
+		];
+
+		let uring_err = unsafe { access.read_v(&mut requests).unwrap_err() };
+		let sync_err = unsafe {
+			ProcfsAccess::new(pid)
+				.unwrap()
+				.read_v(&mut requests)
+				.unwrap_err()
+		};
+		for err in [&uring_err, &sync_err] {
+			assert_eq!(err.kind(), ErrorKind::NotMapped);
+			assert!(matches!(err, ReadError::Io(err) if err.raw_os_error() == Some(libc::EIO)));
+		}
+
+		unsafe { libc::munmap(mapping, page_size) };
+	}
+}
@@ -0,0 +1,244 @@
+use crate::{
+	common::OffsetType,
+	memory::access::{MemoryAccess, ReadError, WriteError},
+};
+
+/// Maximum number of iovecs passed to one `process_vm_readv` or `process_vm_writev` call.
+///
+/// This is the value of `IOV_MAX` on Linux.
+pub(super) const IOV_MAX: usize = 1024;
+
+/// Builds the local and remote iovecs of transfers given as remote offset, local pointer and length.
+fn to_iovecs(
+	transfers: impl ExactSizeIterator<Item = (OffsetType, *mut u8, usize)>,
+) -> (Vec<libc::iovec>, Vec<libc::iovec>) {
+	let mut local = Vec::with_capacity(transfers.len());
+	let mut remote = Vec::with_capacity(transfers.len());
+	for (offset, pointer, len) in transfers {
+		local.push(libc::iovec {
+			iov_base: pointer as *mut libc::c_void,
+			iov_len: len,
+		});
+		remote.push(libc::iovec {
+			iov_base: offset.get() as *mut libc::c_void,
+			iov_len: len,
+		});
+	}
+
+	(local, remote)
+}
+
+/// Reads a batch of at most [`IOV_MAX`] requests using `process_vm_readv`.
+///
+/// Returns the number of requests that were read completely.
+pub(super) unsafe fn process_vm_readv(
+	pid: libc::pid_t,
+	requests: &mut [(OffsetType, &mut [u8])],
+) -> std::io::Result<usize> {
+	debug_assert!(requests.len() <= IOV_MAX);
+
+	let (local, remote) = to_iovecs(
+		requests
+			.iter_mut()
+			.map(|(offset, buffer)| (*offset, buffer.as_mut_ptr(), buffer.len())),
+	);
+	let read = libc::process_vm_readv(
+		pid,
+		local.as_ptr(),
+		local.len() as libc::c_ulong,
+		remote.as_ptr(),
+		remote.len() as libc::c_ulong,
+		0,
+	);
+	if read < 0 {
+		return Err(std::io::Error::last_os_error());
+	}
+
+	Ok(complete_requests(
+		read as usize,
+		requests.iter().map(|(_, buffer)| buffer.len()),
+	))
+}
+
+/// Writes a batch of at most [`IOV_MAX`] writes using `process_vm_writev`.
+///
+/// Returns the number of writes that were written completely.
+pub(super) unsafe fn process_vm_writev(
+	pid: libc::pid_t,
+	writes: &[(OffsetType, &[u8])],
+) -> std::io::Result<usize> {
+	debug_assert!(writes.len() <= IOV_MAX);
+
+	// the local iovecs are only read from
+	let (local, remote) = to_iovecs(
+		writes
+			.iter()
+			.map(|(offset, data)| (*offset, data.as_ptr() as *mut u8, data.len())),
+	);
+
+	let written = libc::process_vm_writev(
+		pid,
+		local.as_ptr(),
+		local.len() as libc::c_ulong,
+		remote.as_ptr(),
+		remote.len() as libc::c_ulong,
+		0,
+	);
+	if written < 0 {
+		return Err(std::io::Error::last_os_error());
+	}
+
+	Ok(complete_requests(
+		written as usize,
+		writes.iter().map(|(_, data)| data.len()),
+	))
+}
+
+/// Counts how many requests of `lengths` were transferred fully by a transfer of `transferred` bytes.
+///
+/// Partial transfers never split an iovec.
+fn complete_requests(mut transferred: usize, lengths: impl Iterator<Item = usize>) -> usize {
+	let mut complete = 0;
+	for length in lengths {
+		if transferred < length {
+			break;
+		}
+		transferred -= length;
+		complete += 1;
+	}
+
+	complete
+}
+
+/// Memory access using only the `process_vm_readv` and `process_vm_writev` syscalls.
+///
+/// Unlike [`ProcfsAccess`](super::ProcfsAccess) this does not keep `/proc/[pid]/mem` open, so it holds no file descriptors
+/// into the process and works in environments where procfs is not mounted.
+///
+/// Writes respect page permissions, so read-only pages (such as code) cannot be written through this access.
+pub struct ProcessVmAccess {
+	pid: libc::pid_t,
+}
+impl ProcessVmAccess {
+	pub const fn new(pid: libc::pid_t) -> Self {
+		ProcessVmAccess { pid }
+	}
+
+	pub const fn pid(&self) -> libc::pid_t {
+		self.pid
+	}
+}
+impl MemoryAccess for ProcessVmAccess {
+	unsafe fn read(&mut self, offset: OffsetType, buffer: &mut [u8]) -> Result<(), ReadError> {
+		let len = buffer.len();
+		match process_vm_readv(self.pid, &mut [(offset, buffer)]) {
+			Ok(1) => Ok(()),
+			Ok(_) if len == 0 => Ok(()),
+			Ok(_) => Err(ReadError::NotMapped),
+			Err(err) => match err.raw_os_error() {
+				Some(libc::EFAULT) => Err(ReadError::NotMapped),
+				Some(libc::EPERM) => Err(ReadError::NotPermitted),
+				_ => Err(ReadError::Io(err)),
+			},
+		}
+	}
+
+	unsafe fn read_v(&mut self, requests: &mut [(OffsetType, &mut [u8])]) -> Result<(), ReadError> {
+		for batch in requests.chunks_mut(IOV_MAX) {
+			let complete = process_vm_readv(self.pid, batch).unwrap_or(0);
+
+			// read the rest one by one so that the error is reported for the right request
+			for (offset, buffer) in batch[complete..].iter_mut() {
+				self.read(*offset, buffer)?;
+			}
+		}
+
+		Ok(())
+	}
+
+	unsafe fn write(&mut self, offset: OffsetType, data: &[u8]) -> Result<(), WriteError> {
+		match process_vm_writev(self.pid, &[(offset, data)]) {
+			Ok(1) => Ok(()),
+			Ok(_) if data.is_empty() => Ok(()),
+			Ok(_) => Err(WriteError::NotMapped),
+			Err(err) => match err.raw_os_error() {
+				Some(libc::EFAULT) => Err(WriteError::NotMapped),
+				Some(libc::EPERM) => Err(WriteError::NotPermitted),
+				_ => Err(WriteError::Io(err)),
+			},
+		}
+	}
+
+	unsafe fn write_many(&mut self, writes: &[(OffsetType, &[u8])]) -> Result<(), WriteError> {
+		for batch in writes.chunks(IOV_MAX) {
+			let complete = process_vm_writev(self.pid, batch).unwrap_or(0);
+
+			// write the rest one by one so that the error is reported for the right write
+			for (offset, data) in batch[complete..].iter() {
+				self.write(*offset, data)?;
+			}
+		}
+
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::ProcessVmAccess;
+	use crate::{
+		common::OffsetType,
+		memory::access::{MemoryAccess, ReadError, WriteError},
+	};
+
+	#[test]
+	fn test_process_vm_access_self() {
+		let mut access = ProcessVmAccess::new(std::process::id() as libc::pid_t);
+
+		let value = [1u8, 2, 3, 4];
+		let mut target = [0u8; 4];
+		let target_offset = OffsetType::new_unwrap(target.as_ptr() as u64);
+		unsafe {
+			access.write(target_offset, &value).unwrap();
+		}
+
+		let mut buffer = [0u8; 4];
+		unsafe {
+			access.read(target_offset, &mut buffer).unwrap();
+		}
+		assert_eq!(buffer, value);
+		assert_eq!(std::hint::black_box(&mut target), &value);
+
+		let err = unsafe {
+			access
+				.read(OffsetType::new_unwrap(8), &mut buffer)
+				.unwrap_err()
+		};
+		assert!(matches!(err, ReadError::NotMapped));
+	}
+
+	#[test]
+	fn test_process_vm_write_many_self() {
+		let mut access = ProcessVmAccess::new(std::process::id() as libc::pid_t);
+
+		let mut first = [0u8; 4];
+		let mut second = [0u8; 2];
+		let writes = [
+			(
+				OffsetType::new_unwrap(first.as_ptr() as u64),
+				&[1u8, 2, 3, 4][..],
+			),
+			(
+				OffsetType::new_unwrap(second.as_ptr() as u64),
+				&[5u8, 6][..],
+			),
+			(OffsetType::new_unwrap(8), &[7u8][..]),
+		];
+		let err = unsafe { access.write_many(&writes).unwrap_err() };
+		assert!(matches!(err, WriteError::NotMapped));
+
+		// writes before the failed one are done
+		assert_eq!(std::hint::black_box(&mut first), &[1, 2, 3, 4]);
+		assert_eq!(std::hint::black_box(&mut second), &[5, 6]);
+	}
+}
@@ -0,0 +1,336 @@
+//! Reporting of forks and execs of a process traced by a [`PtraceLock`].
+//!
+//! With events enabled through [`PtraceLock::set_events`], the kernel stops the process on each selected event.
+//! The lock handles these stops whenever it waits for the process, that is while locking and in [`PtraceLock::poll_events`],
+//! passes the event to the handler and resumes the process.
+//!
+//! Children of the process are traced by the kernel from their first instruction. Unless they are followed, the lock detaches from them
+//! right away. Followed children are passed to the handler with their own lock, stopped and locked once.
+
+use super::lock::{PtraceLock, PtraceLockError};
+
+/// Event of the process traced by a [`PtraceLock`].
+pub enum PtraceEvent {
+	/// The process created a child process with `fork` or `vfork`.
+	///
+	/// `lock` is present if children are followed. A `vfork` parent stays blocked until the child is unlocked and execs or exits.
+	Fork {
+		child: libc::pid_t,
+		vfork: bool,
+		lock: Option<PtraceLock>,
+	},
+	/// The process executed a new program, any memory map of the process is outdated.
+	Exec,
+}
+impl std::fmt::Debug for PtraceEvent {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		match self {
+			PtraceEvent::Fork { child, vfork, lock } => f
+				.debug_struct("Fork")
+				.field("child", child)
+				.field("vfork", vfork)
+				.field("followed", &lock.is_some())
+				.finish(),
+			PtraceEvent::Exec => write!(f, "Exec"),
+		}
+	}
+}
+
+/// Selection of events reported by a [`PtraceLock`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PtraceEvents {
+	/// Report children created with `fork` (and `clone` without `CLONE_VM`).
+	pub fork: bool,
+	/// Report children created with `vfork`.
+	pub vfork: bool,
+	/// Report `exec` of the process.
+	pub exec: bool,
+	/// Keep tracing reported children and pass their locks to the handler instead of detaching from them.
+	pub follow_children: bool,
+}
+impl PtraceEvents {
+	pub(super) fn options(&self) -> libc::c_int {
+		(self.fork as libc::c_int * libc::PTRACE_O_TRACEFORK)
+			| (self.vfork as libc::c_int * libc::PTRACE_O_TRACEVFORK)
+			| (self.exec as libc::c_int * libc::PTRACE_O_TRACEEXEC)
+	}
+}
+
+pub(super) struct EventState {
+	pub(super) events: PtraceEvents,
+	handler: Box<dyn FnMut(PtraceEvent) + Send>,
+}
+
+/// How a stop of the process was handled.
+enum Stop {
+	/// Stopped by `PTRACE_INTERRUPT` or a stop signal.
+	Interrupt(libc::c_int),
+	/// Stopped on an event which was passed to the handler.
+	Event,
+	/// Stopped before delivering a signal.
+	Signal(libc::c_int),
+}
+
+impl PtraceLock {
+	/// Enables reporting of `events` to `handler`, replacing previous events.
+	///
+	/// The process is briefly stopped to set the ptrace options if it is running. Transient locks set the options on each attach,
+	/// but do not see events while unlocked.
+	pub fn set_events(
+		&mut self,
+		events: PtraceEvents,
+		handler: impl FnMut(PtraceEvent) + Send + 'static,
+	) -> Result<(), PtraceLockError> {
+		self.events = Some(EventState {
+			events,
+			handler: Box::new(handler),
+		});
+
+		if !self.is_attached() {
+			return Ok(());
+		}
+
+		unsafe {
+			let stop_here = !self.is_locked();
+			if stop_here {
+				self.ptrace_stop()?;
+			}
+
+			let ptrace_res = libc::ptrace(
+				libc::PTRACE_SETOPTIONS,
+				self.pid(),
+				0,
+				events.options() as libc::c_long,
+			);
+			if ptrace_res != 0 {
+				return Err(PtraceLockError::PtraceSetOptions(
+					std::io::Error::last_os_error(),
+				));
+			}
+
+			if stop_here {
+				self.ptrace_cont()?;
+			}
+		}
+
+		Ok(())
+	}
+
+	/// Handles events and signals of the running process without blocking, returns the number of events passed to the handler.
+	///
+	/// While events are enabled, the process stops on each event and on each signal until the tracer handles it,
+	/// so this should be called regularly while unlocked. Signals are delivered to the process.
+	pub fn poll_events(&mut self) -> Result<usize, PtraceLockError> {
+		if self.events.is_none() || !self.is_attached() || self.is_locked() {
+			return Ok(0);
+		}
+
+		let mut count = 0;
+		loop {
+			let mut status = 0;
+			let waitpid_res =
+				unsafe { libc::waitpid(self.pid(), &mut status, libc::WNOHANG | libc::__WALL) };
+			match waitpid_res {
+				-1 => {
+					return Err(PtraceLockError::WaitpidError(
+						std::io::Error::last_os_error(),
+					))
+				}
+				0 => return Ok(count),
+				_ => (),
+			}
+
+			unsafe {
+				match self.handle_stop(status)? {
+					Stop::Event => {
+						count += 1;
+						self.ptrace_cont_signal(0)?;
+					}
+					Stop::Signal(signal) => self.ptrace_cont_signal(signal)?,
+					// leftover interrupt
+					Stop::Interrupt(libc::SIGTRAP) => self.ptrace_cont_signal(0)?,
+					// group-stop, keep the process stopped while still receiving events
+					Stop::Interrupt(_) => {
+						if libc::ptrace(super::requests::PTRACE_LISTEN, self.pid(), 0, 0) != 0 {
+							return Err(PtraceLockError::PtraceCont(
+								std::io::Error::last_os_error(),
+							));
+						}
+					}
+				}
+			}
+		}
+	}
+
+	/// Waits for the stop requested by `PTRACE_INTERRUPT`, handling events and signals which arrive first.
+	pub(super) unsafe fn wait_for_interrupt(&mut self) -> Result<(), PtraceLockError> {
+		loop {
+			let mut status = 0;
+			if libc::waitpid(self.pid(), &mut status, libc::__WALL) == -1 {
+				return Err(PtraceLockError::WaitpidError(
+					std::io::Error::last_os_error(),
+				));
+			}
+
+			match self.handle_stop(status)? {
+				Stop::Interrupt(_) => return Ok(()),
+				Stop::Event => self.ptrace_cont_signal(0)?,
+				Stop::Signal(signal) => self.ptrace_cont_signal(signal)?,
+			}
+		}
+	}
+
+	/// Classifies the stop described by the waitpid `status` and passes events to the handler.
+	unsafe fn handle_stop(&mut self, status: libc::c_int) -> Result<Stop, PtraceLockError> {
+		if libc::WIFEXITED(status) || libc::WIFSIGNALED(status) {
+			self.mark_detached();
+			return Err(PtraceLockError::ProcessExited);
+		}
+
+		let event = status >> 16;
+		let event = match event {
+			0 => return Ok(Stop::Signal(libc::WSTOPSIG(status))),
+			libc::PTRACE_EVENT_STOP => return Ok(Stop::Interrupt(libc::WSTOPSIG(status))),
+			libc::PTRACE_EVENT_FORK | libc::PTRACE_EVENT_VFORK => {
+				let mut child: libc::c_ulong = 0;
+				if libc::ptrace(libc::PTRACE_GETEVENTMSG, self.pid(), 0, &mut child) != 0 {
+					return Err(PtraceLockError::WaitpidError(
+						std::io::Error::last_os_error(),
+					));
+				}
+				let child = child as libc::pid_t;
+
+				let follow = self
+					.events
+					.as_ref()
+					.map(|state| state.events.follow_children)
+					.unwrap_or(false);
+
+				PtraceEvent::Fork {
+					child,
+					vfork: event == libc::PTRACE_EVENT_VFORK,
+					lock: Self::adopt_child(child, follow)?,
+				}
+			}
+			libc::PTRACE_EVENT_EXEC => PtraceEvent::Exec,
+			// events which were not requested
+			_ => return Ok(Stop::Signal(0)),
+		};
+
+		if let Some(state) = self.events.as_mut() {
+			(state.handler)(event);
+		}
+
+		Ok(Stop::Event)
+	}
+
+	/// Waits for the initial stop of an automatically traced child and either detaches from it or returns its lock.
+	unsafe fn adopt_child(
+		child: libc::pid_t,
+		follow: bool,
+	) -> Result<Option<PtraceLock>, PtraceLockError> {
+		if libc::waitpid(child, std::ptr::null_mut(), libc::__WALL) == -1 {
+			return Err(PtraceLockError::WaitpidError(
+				std::io::Error::last_os_error(),
+			));
+		}
+
+		if !follow {
+			if libc::ptrace(libc::PTRACE_DETACH, child, 0, 0) != 0 {
+				return Err(PtraceLockError::PtraceDetach(
+					std::io::Error::last_os_error(),
+				));
+			}
+
+			return Ok(None);
+		}
+
+		// the child inherits the options, but nobody handles its events
+		if libc::ptrace(libc::PTRACE_SETOPTIONS, child, 0, 0) != 0 {
+			return Err(PtraceLockError::PtraceSetOptions(
+				std::io::Error::last_os_error(),
+			));
+		}
+
+		Ok(Some(PtraceLock::from_stopped(child)))
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use std::{
+		process::Command,
+		sync::{Arc, Mutex},
+		time::{Duration, Instant},
+	};
+
+	use super::{PtraceEvent, PtraceEvents};
+	use crate::{memory::lock::MemoryLock, platform::ptrace::PtraceLock};
+
+	#[test]
+	fn test_follow_fork() {
+		// the shell forks for the first command and execs the last one
+		let mut child = Command::new("sh")
+			.args(["-c", "sleep 0.2; sleep 30"])
+			.spawn()
+			.unwrap();
+		let pid = child.id() as libc::pid_t;
+
+		let mut lock = match PtraceLock::new(pid) {
+			Ok(lock) => lock,
+			Err(err) => {
+				eprintln!("skipping, could not attach: {}", err);
+				child.kill().unwrap();
+				child.wait().unwrap();
+				return;
+			}
+		};
+
+		let events = Arc::new(Mutex::new(Vec::new()));
+		let handler_events = events.clone();
+		lock.set_events(
+			PtraceEvents {
+				fork: true,
+				vfork: true,
+				exec: true,
+				follow_children: true,
+			},
+			move |event| handler_events.lock().unwrap().push(event),
+		)
+		.unwrap();
+
+		let start = Instant::now();
+		while !events
+			.lock()
+			.unwrap()
+			.iter()
+			.any(|event| matches!(event, PtraceEvent::Fork { .. }))
+		{
+			assert!(start.elapsed() < Duration::from_secs(5));
+			lock.poll_events().unwrap();
+			std::thread::sleep(Duration::from_millis(10));
+		}
+
+		let (forked, mut child_lock) = events
+			.lock()
+			.unwrap()
+			.iter_mut()
+			.find_map(|event| match event {
+				PtraceEvent::Fork { child, lock, .. } => Some((*child, lock.take().unwrap())),
+				_ => None,
+			})
+			.unwrap();
+		assert!(child_lock.is_locked());
+		assert_eq!(child_lock.pid(), forked);
+
+		// the followed child runs once unlocked
+		child_lock.unlock().unwrap();
+		child_lock.lock().unwrap();
+		drop(child_lock);
+		drop(lock);
+
+		child.kill().unwrap();
+		child.wait().unwrap();
+		unsafe { libc::kill(forked, libc::SIGKILL) };
+	}
+}
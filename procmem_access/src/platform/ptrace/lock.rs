@@ -1,6 +1,10 @@
 use thiserror::Error;
 
-use crate::memory::lock::{LockError, MemoryLock, UnlockError};
+use crate::{
+	error::{impl_from_kinded_error, ErrorKind},
+	memory::lock::{LockError, MemoryLock, UnlockError},
+	platform::capabilities::{self, AttachDeniedError},
+};
 
 #[cfg(target_os = "macos")]
 use crate::platform::mach::exception::{MachExceptionHandler, MachExceptionHandlerError};
@@ -8,28 +12,46 @@ use crate::platform::mach::exception::{MachExceptionHandler, MachExceptionHandle
 #[derive(Debug, Error)]
 pub enum PtraceLockError {
 	#[error("ptrace attach failed")]
-	PtraceAttach(std::io::Error),
+	PtraceAttach(#[source] std::io::Error),
+	#[error(transparent)]
+	AttachDenied(#[from] AttachDeniedError),
 	#[error("stopping failed")]
-	StopError(std::io::Error),
+	StopError(#[source] std::io::Error),
 	#[error("ptrace continue failed")]
-	PtraceCont(std::io::Error),
+	PtraceCont(#[source] std::io::Error),
 	#[error("ptrace detach failed")]
-	PtraceDetach(std::io::Error),
+	PtraceDetach(#[source] std::io::Error),
 
-	#[cfg(target_os = "linux")]
+	#[cfg(any(target_os = "linux", target_os = "android"))]
 	#[error("waitpid failed")]
-	WaitpidError(std::io::Error),
+	WaitpidError(#[source] std::io::Error),
+	#[cfg(any(target_os = "linux", target_os = "android"))]
+	#[error("setting ptrace options failed")]
+	PtraceSetOptions(#[source] std::io::Error),
+	#[cfg(any(target_os = "linux", target_os = "android"))]
+	#[error("process exited")]
+	ProcessExited,
 
 	#[cfg(target_os = "macos")]
 	#[error(transparent)]
 	ExceptionHandlerError(#[from] MachExceptionHandlerError),
 	#[cfg(target_os = "macos")]
 	#[error("failed to initialize mach exception port")]
-	ExceptionPortError(std::io::Error),
+	ExceptionPortError(#[source] std::io::Error),
 	#[cfg(target_os = "macos")]
 	#[error("failed to receive mach exceptions")]
-	ExceptionRecvError(std::io::Error),
+	ExceptionRecvError(#[source] std::io::Error),
+}
+impl PtraceLockError {
+	pub fn kind(&self) -> ErrorKind {
+		match self {
+			#[cfg(any(target_os = "linux", target_os = "android"))]
+			PtraceLockError::ProcessExited => ErrorKind::ProcessExited,
+			_ => ErrorKind::from_error(self),
+		}
+	}
 }
+impl_from_kinded_error!(PtraceLockError);
 impl From<PtraceLockError> for LockError {
 	fn from(err: PtraceLockError) -> Self {
 		LockError::PlatformError(Box::new(err))
@@ -44,16 +66,34 @@ impl From<PtraceLockError> for UnlockError {
 pub struct PtraceLock {
 	pid: libc::pid_t,
 	lock_counter: usize,
+	/// Whether ptrace is currently attached.
+	attached: bool,
+	/// Whether to attach only while locked.
+	transient: bool,
+	/// Memory allocated in the process through remote calls.
+	#[cfg(all(
+		any(target_os = "linux", target_os = "android"),
+		target_arch = "x86_64"
+	))]
+	pub(super) allocations: crate::memory::allocate::Allocations,
+	/// Event options and handler set by [`set_events`](PtraceLock::set_events).
+	#[cfg(any(target_os = "linux", target_os = "android"))]
+	pub(super) events: Option<super::events::EventState>,
 
 	#[cfg(target_os = "macos")]
 	exception_handler: MachExceptionHandler,
 }
-#[cfg(target_os = "linux")]
+#[cfg(any(target_os = "linux", target_os = "android"))]
 impl PtraceLock {
 	pub fn new(pid: libc::pid_t) -> Result<Self, PtraceLockError> {
 		let mut me = PtraceLock {
 			pid,
 			lock_counter: 0,
+			attached: false,
+			transient: false,
+			#[cfg(target_arch = "x86_64")]
+			allocations: Default::default(),
+			events: None,
 		};
 
 		unsafe { me.ptrace_attach()? };
@@ -61,10 +101,47 @@ impl PtraceLock {
 		Ok(me)
 	}
 
+	/// Creates a lock which attaches to the process when locked and detaches again when unlocked.
+	///
+	/// The process is not traced while unlocked, so other debuggers can attach in the meantime
+	/// and the process does not observe a tracer. Each lock pays the cost of attaching.
+	pub fn new_transient(pid: libc::pid_t) -> Self {
+		PtraceLock {
+			pid,
+			lock_counter: 0,
+			attached: false,
+			transient: true,
+			#[cfg(target_arch = "x86_64")]
+			allocations: Default::default(),
+			events: None,
+		}
+	}
+
+	/// Creates a locked lock for a process which this thread already seized and which is in a ptrace-stop.
+	///
+	/// ## Safety
+	/// * `pid` must be seized by the calling thread with `PTRACE_SEIZE` and currently stopped.
+	pub(crate) unsafe fn from_stopped(pid: libc::pid_t) -> Self {
+		PtraceLock {
+			pid,
+			lock_counter: 1,
+			attached: true,
+			transient: false,
+			#[cfg(target_arch = "x86_64")]
+			allocations: Default::default(),
+			events: None,
+		}
+	}
+
 	unsafe fn wait_for_stop(&mut self) -> Result<(), PtraceLockError> {
+		if self.events.is_some() {
+			return self.wait_for_interrupt();
+		}
+
 		// wait until the stop signal is delivered
 		// TODO: read the manpage and check how to properly use this
-		let waitpid_res = libc::waitpid(self.pid, std::ptr::null_mut(), 0);
+		// `__WALL` is needed to wait for threads other than the main thread
+		let waitpid_res = libc::waitpid(self.pid, std::ptr::null_mut(), libc::__WALL);
 		if waitpid_res == -1 {
 			return Err(PtraceLockError::WaitpidError(
 				std::io::Error::last_os_error(),
@@ -76,18 +153,27 @@ impl PtraceLock {
 	}
 
 	unsafe fn ptrace_attach(&mut self) -> Result<(), PtraceLockError> {
-		let ptrace_res = libc::ptrace(libc::PTRACE_SEIZE, self.pid, 0, 0);
+		let options = self
+			.events
+			.as_ref()
+			.map(|state| state.events.options())
+			.unwrap_or(0);
+		let ptrace_res = libc::ptrace(super::requests::PTRACE_SEIZE, self.pid, 0, options);
 		if ptrace_res != 0 {
-			return Err(PtraceLockError::PtraceAttach(
-				std::io::Error::last_os_error(),
-			));
+			let err = std::io::Error::last_os_error();
+			if capabilities::is_denied(&err) {
+				return Err(AttachDeniedError::diagnose(self.pid, err).into());
+			}
+
+			return Err(PtraceLockError::PtraceAttach(err));
 		}
+		self.attached = true;
 
 		Ok(())
 	}
 
-	unsafe fn ptrace_stop(&mut self) -> Result<(), PtraceLockError> {
-		let ptrace_res = libc::ptrace(libc::PTRACE_INTERRUPT, self.pid, 0, 0);
+	pub(super) unsafe fn ptrace_stop(&mut self) -> Result<(), PtraceLockError> {
+		let ptrace_res = libc::ptrace(super::requests::PTRACE_INTERRUPT, self.pid, 0, 0);
 		if ptrace_res != 0 {
 			return Err(PtraceLockError::StopError(std::io::Error::last_os_error()));
 		}
@@ -96,8 +182,16 @@ impl PtraceLock {
 		Ok(())
 	}
 
-	unsafe fn ptrace_cont(&mut self) -> Result<(), PtraceLockError> {
-		let ptrace_res = libc::ptrace(libc::PTRACE_CONT, self.pid, 0, 0);
+	pub(super) unsafe fn ptrace_cont(&mut self) -> Result<(), PtraceLockError> {
+		self.ptrace_cont_signal(0)
+	}
+
+	/// Resumes the process and delivers `signal` to it, unless it is zero.
+	pub(super) unsafe fn ptrace_cont_signal(
+		&mut self,
+		signal: libc::c_int,
+	) -> Result<(), PtraceLockError> {
+		let ptrace_res = libc::ptrace(libc::PTRACE_CONT, self.pid, 0, signal as libc::c_long);
 		if ptrace_res != 0 {
 			return Err(PtraceLockError::PtraceCont(std::io::Error::last_os_error()));
 		}
@@ -112,6 +206,7 @@ impl PtraceLock {
 				std::io::Error::last_os_error(),
 			));
 		}
+		self.attached = false;
 
 		Ok(())
 	}
@@ -122,6 +217,8 @@ impl PtraceLock {
 		let mut me = PtraceLock {
 			pid,
 			lock_counter: 0,
+			attached: false,
+			transient: false,
 			exception_handler: MachExceptionHandler::new(pid)?,
 		};
 
@@ -141,10 +238,14 @@ impl PtraceLock {
 	unsafe fn ptrace_attach(&mut self) -> Result<(), PtraceLockError> {
 		let ptrace_res = libc::ptrace(libc::PT_ATTACHEXC, self.pid, std::ptr::null_mut(), 0);
 		if ptrace_res != 0 {
-			return Err(PtraceLockError::PtraceAttach(
-				std::io::Error::last_os_error(),
-			));
+			let err = std::io::Error::last_os_error();
+			if capabilities::is_denied(&err) {
+				return Err(AttachDeniedError::diagnose(self.pid, err).into());
+			}
+
+			return Err(PtraceLockError::PtraceAttach(err));
 		}
+		self.attached = true;
 		self.wait_for_stop()?;
 		self.ptrace_cont()?;
 
@@ -176,14 +277,40 @@ impl PtraceLock {
 				std::io::Error::last_os_error(),
 			));
 		}
+		self.attached = false;
 
 		Ok(())
 	}
 }
+impl PtraceLock {
+	pub const fn pid(&self) -> libc::pid_t {
+		self.pid
+	}
+
+	/// Returns whether the process is currently stopped by this lock.
+	pub const fn is_locked(&self) -> bool {
+		self.lock_counter != 0
+	}
+
+	#[cfg(any(target_os = "linux", target_os = "android"))]
+	pub(super) const fn is_attached(&self) -> bool {
+		self.attached
+	}
+
+	/// Forgets the process after it exited, so that dropping the lock does not try to detach.
+	#[cfg(any(target_os = "linux", target_os = "android"))]
+	pub(super) fn mark_detached(&mut self) {
+		self.attached = false;
+		self.lock_counter = 0;
+	}
+}
 impl MemoryLock for PtraceLock {
 	fn lock(&mut self) -> Result<bool, LockError> {
 		if self.lock_counter == 0 {
 			unsafe {
+				if !self.attached {
+					self.ptrace_attach()?;
+				}
 				self.ptrace_stop()?;
 			}
 			self.lock_counter = 1;
@@ -216,7 +343,12 @@ impl MemoryLock for PtraceLock {
 
 		if self.lock_counter == 1 || self.lock_counter == usize::MAX {
 			unsafe {
-				self.ptrace_cont()?;
+				// detaching resumes the process
+				if self.transient {
+					self.ptrace_detach()?;
+				} else {
+					self.ptrace_cont()?;
+				}
 			}
 			self.lock_counter = 0;
 
@@ -230,8 +362,56 @@ impl MemoryLock for PtraceLock {
 }
 impl Drop for PtraceLock {
 	fn drop(&mut self) {
+		if !self.attached {
+			return;
+		}
 		let _ = self.lock();
 
-		unsafe { self.ptrace_detach().unwrap() }
+		// fails if the process exited in the meantime, there is nothing left to release then
+		let _ = unsafe { self.ptrace_detach() };
+	}
+}
+
+#[cfg(all(test, any(target_os = "linux", target_os = "android")))]
+mod test {
+	use std::process::Command;
+
+	use super::PtraceLock;
+	use crate::memory::lock::MemoryLock;
+
+	fn tracer_pid(pid: u32) -> u32 {
+		std::fs::read_to_string(format!("/proc/{}/status", pid))
+			.unwrap()
+			.lines()
+			.find_map(|line| line.strip_prefix("TracerPid:"))
+			.unwrap()
+			.trim()
+			.parse()
+			.unwrap()
+	}
+
+	#[test]
+	fn test_transient_lock() {
+		let mut child = Command::new("sleep").arg("30").spawn().unwrap();
+		let pid = child.id();
+
+		let mut lock = PtraceLock::new_transient(pid as libc::pid_t);
+		assert_eq!(tracer_pid(pid), 0);
+
+		match lock.lock() {
+			Ok(acquired) => {
+				assert!(acquired);
+				// the tracer is the attaching thread
+				assert_eq!(tracer_pid(pid), unsafe { libc::gettid() } as u32);
+
+				lock.unlock().unwrap();
+				assert_eq!(tracer_pid(pid), 0);
+			}
+			Err(err) => eprintln!("skipping, could not attach: {}", err),
+		}
+		drop(lock);
+
+		child.kill().unwrap();
+		child.wait().unwrap();
 	}
 }
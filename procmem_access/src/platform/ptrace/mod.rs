@@ -1,3 +1,49 @@
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub mod events;
 pub mod lock;
+#[cfg(all(
+	any(target_os = "linux", target_os = "android"),
+	target_arch = "x86_64"
+))]
+pub mod remote;
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub mod thread;
+#[cfg(all(
+	any(target_os = "linux", target_os = "android"),
+	target_arch = "x86_64"
+))]
+pub mod watch;
 
-pub use lock::PtraceLock;
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub use events::{PtraceEvent, PtraceEvents};
+pub use lock::{PtraceLock, PtraceLockError};
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub use thread::PtraceThreadLock;
+#[cfg(all(
+	any(target_os = "linux", target_os = "android"),
+	target_arch = "x86_64"
+))]
+pub use watch::{PtraceWatcher, WatchHit, WatchKind, Watchpoint};
+
+/// Returns the location of `errno` of the calling thread.
+#[cfg(target_os = "linux")]
+pub(crate) unsafe fn errno_location() -> *mut libc::c_int {
+	libc::__errno_location()
+}
+#[cfg(target_os = "android")]
+pub(crate) unsafe fn errno_location() -> *mut libc::c_int {
+	libc::__errno()
+}
+
+/// Ptrace requests used by this crate which libc only defines for Linux.
+#[cfg(target_os = "linux")]
+pub(crate) mod requests {
+	pub use libc::{PTRACE_INTERRUPT, PTRACE_LISTEN, PTRACE_SEIZE};
+}
+/// Ptrace requests used by this crate which libc only defines for Linux, with the values of the kernel headers.
+#[cfg(target_os = "android")]
+pub(crate) mod requests {
+	pub const PTRACE_SEIZE: libc::c_int = 0x4206;
+	pub const PTRACE_INTERRUPT: libc::c_int = 0x4207;
+	pub const PTRACE_LISTEN: libc::c_int = 0x4208;
+}
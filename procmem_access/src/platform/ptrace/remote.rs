@@ -0,0 +1,750 @@
+//! Calling functions inside a traced process and loading libraries into it.
+//!
+//! Remote calls hijack the stopped main thread: its registers are saved, the arguments are placed according to
+//! the System V calling convention and the function returns to address `0`. The resulting fault is caught,
+//! the return value read and the original registers restored.
+//!
+//! Only Linux and Android on x86_64 are supported. Libraries are loaded into Windows processes by
+//! `WindowsAccess::inject_library` instead, which runs `LoadLibraryW` in a remote thread.
+
+use std::{
+	ffi::{CStr, CString},
+	os::unix::ffi::OsStrExt,
+	path::{Path, PathBuf},
+};
+
+use thiserror::Error;
+
+use crate::{
+	common::OffsetType,
+	error::{impl_from_kinded_error, ErrorKind},
+	memory::{
+		allocate::{AllocateError, Allocations, MemoryAllocate},
+		lock::{LockError, MemoryLock, UnlockError},
+		map::{MemoryMap, MemoryPagePermissions, MemoryPageType},
+		protect::{page_range, MemoryProtect, ProtectError},
+	},
+	platform::procfs::{map::ProcfsMemoryMapLoadError, ProcfsMemoryMap},
+};
+
+use super::PtraceLock;
+
+/// General purpose registers of a traced thread.
+pub type Registers = libc::user_regs_struct;
+
+/// Size of the area below the stack pointer which the called code may use without adjusting it.
+const RED_ZONE_SIZE: u64 = 128;
+const ARGUMENT_REGISTERS: usize = 6;
+
+/// Libc function returning the location of `errno` of the calling thread.
+#[cfg(target_os = "linux")]
+const ERRNO_LOCATION_SYMBOL: &CStr = c"__errno_location";
+#[cfg(target_os = "android")]
+const ERRNO_LOCATION_SYMBOL: &CStr = c"__errno";
+
+#[derive(Debug, Error)]
+pub enum RemoteCallError {
+	#[error("process must be locked to call remote functions")]
+	NotLocked,
+	#[error("at most {} arguments are supported", ARGUMENT_REGISTERS)]
+	TooManyArguments,
+	#[error("could not get registers")]
+	GetRegisters(#[source] std::io::Error),
+	#[error("could not set registers")]
+	SetRegisters(#[source] std::io::Error),
+	#[error("could not read remote memory at {0:#x}")]
+	Peek(u64, #[source] std::io::Error),
+	#[error("could not write remote memory at {0:#x}")]
+	Poke(u64, #[source] std::io::Error),
+	#[error("ptrace continue failed")]
+	PtraceCont(#[source] std::io::Error),
+	#[error("waitpid failed")]
+	WaitpidError(#[source] std::io::Error),
+	#[error("remote call stopped by signal {signal} at {rip:#x}")]
+	UnexpectedStop { signal: libc::c_int, rip: u64 },
+	#[error("process exited during remote call")]
+	ProcessExited,
+}
+impl RemoteCallError {
+	pub fn kind(&self) -> ErrorKind {
+		match self {
+			RemoteCallError::ProcessExited => ErrorKind::ProcessExited,
+			_ => ErrorKind::from_error(self),
+		}
+	}
+}
+impl_from_kinded_error!(RemoteCallError);
+
+#[derive(Debug, Error)]
+pub enum InjectError {
+	#[error(transparent)]
+	Lock(#[from] LockError),
+	#[error(transparent)]
+	Unlock(#[from] UnlockError),
+	#[error(transparent)]
+	MemoryMap(#[from] ProcfsMemoryMapLoadError),
+	#[error(transparent)]
+	Call(#[from] RemoteCallError),
+	#[error("library path contains a nul byte")]
+	InvalidPath,
+	#[error("symbol {0} not found in this process")]
+	SymbolNotFound(String),
+	#[error("library {} is not loaded in the target process", .0.display())]
+	LibraryNotLoaded(PathBuf),
+	#[error("dlopen failed: {0}")]
+	Dlopen(String),
+}
+impl InjectError {
+	pub fn kind(&self) -> ErrorKind {
+		match self {
+			InjectError::Lock(err) => err.kind(),
+			InjectError::Unlock(err) => err.kind(),
+			InjectError::MemoryMap(err) => err.kind(),
+			InjectError::Call(err) => err.kind(),
+			_ => ErrorKind::Platform,
+		}
+	}
+}
+impl_from_kinded_error!(InjectError);
+impl From<InjectError> for AllocateError {
+	fn from(err: InjectError) -> Self {
+		AllocateError::PlatformError(Box::new(err))
+	}
+}
+impl From<InjectError> for ProtectError {
+	fn from(err: InjectError) -> Self {
+		ProtectError::PlatformError(Box::new(err))
+	}
+}
+
+/// Argument of a remote call.
+#[derive(Debug, Clone, Copy)]
+pub enum RemoteArgument<'a> {
+	/// Value passed in a register as is.
+	Value(u64),
+	/// Bytes copied onto the remote stack, the argument is a pointer to them.
+	///
+	/// The bytes are only valid for the duration of the call.
+	Bytes(&'a [u8]),
+}
+
+impl PtraceLock {
+	fn ensure_locked(&self) -> Result<(), RemoteCallError> {
+		if self.is_locked() {
+			Ok(())
+		} else {
+			Err(RemoteCallError::NotLocked)
+		}
+	}
+
+	/// Returns the registers of the stopped main thread.
+	pub fn registers(&self) -> Result<Registers, RemoteCallError> {
+		self.ensure_locked()?;
+
+		let mut registers = std::mem::MaybeUninit::<Registers>::uninit();
+		let res =
+			unsafe { libc::ptrace(libc::PTRACE_GETREGS, self.pid(), 0, registers.as_mut_ptr()) };
+		if res != 0 {
+			return Err(RemoteCallError::GetRegisters(
+				std::io::Error::last_os_error(),
+			));
+		}
+
+		Ok(unsafe { registers.assume_init() })
+	}
+
+	/// Sets the registers of the stopped main thread.
+	///
+	/// ## Safety
+	/// * The process continues with the new registers once unlocked.
+	pub unsafe fn set_registers(&mut self, registers: &Registers) -> Result<(), RemoteCallError> {
+		self.ensure_locked()?;
+
+		let res = libc::ptrace(
+			libc::PTRACE_SETREGS,
+			self.pid(),
+			0,
+			registers as *const Registers,
+		);
+		if res != 0 {
+			return Err(RemoteCallError::SetRegisters(
+				std::io::Error::last_os_error(),
+			));
+		}
+
+		Ok(())
+	}
+
+	unsafe fn peek_word(&self, address: u64) -> Result<u64, RemoteCallError> {
+		// -1 is a valid word, errors can only be told apart by errno
+		*super::errno_location() = 0;
+		let word = libc::ptrace(libc::PTRACE_PEEKDATA, self.pid(), address, 0);
+		if word == -1 && *super::errno_location() != 0 {
+			return Err(RemoteCallError::Peek(
+				address,
+				std::io::Error::last_os_error(),
+			));
+		}
+
+		Ok(word as u64)
+	}
+
+	unsafe fn poke_word(&mut self, address: u64, word: u64) -> Result<(), RemoteCallError> {
+		let res = libc::ptrace(libc::PTRACE_POKEDATA, self.pid(), address, word);
+		if res != 0 {
+			return Err(RemoteCallError::Poke(
+				address,
+				std::io::Error::last_os_error(),
+			));
+		}
+
+		Ok(())
+	}
+
+	/// Writes `data` at `address` word by word, preserving the surrounding bytes of partially written words.
+	unsafe fn poke_bytes(&mut self, address: u64, data: &[u8]) -> Result<(), RemoteCallError> {
+		const WORD: u64 = std::mem::size_of::<u64>() as u64;
+
+		let end = address + data.len() as u64;
+		let mut word_address = address & !(WORD - 1);
+		while word_address < end {
+			let mut word = if word_address < address || word_address + WORD > end {
+				self.peek_word(word_address)?.to_ne_bytes()
+			} else {
+				[0; WORD as usize]
+			};
+
+			for (i, byte) in word.iter_mut().enumerate() {
+				let byte_address = word_address + i as u64;
+				if byte_address >= address && byte_address < end {
+					*byte = data[(byte_address - address) as usize];
+				}
+			}
+			self.poke_word(word_address, u64::from_ne_bytes(word))?;
+
+			word_address += WORD;
+		}
+
+		Ok(())
+	}
+
+	/// Reads a nul terminated string of at most `max_length` bytes at `address`.
+	unsafe fn peek_c_string(
+		&self,
+		address: u64,
+		max_length: usize,
+	) -> Result<String, RemoteCallError> {
+		let mut bytes = Vec::new();
+		'words: for word_address in (address..).step_by(8) {
+			for byte in self.peek_word(word_address)?.to_ne_bytes() {
+				if byte == 0 || bytes.len() == max_length {
+					break 'words;
+				}
+				bytes.push(byte);
+			}
+		}
+
+		Ok(String::from_utf8_lossy(&bytes).into_owned())
+	}
+
+	/// Calls `function` in the process and returns the value of `rax` after it returns.
+	///
+	/// The process must be locked. The main thread executes the call and its registers are restored afterwards,
+	/// the process stays locked.
+	///
+	/// ## Safety
+	/// * `function` must be the address of a function in the process taking `arguments`.
+	/// * The function runs with the process in whatever state it was stopped in, calling a function that takes
+	///   a lock already held by the stopped thread (e.g. `malloc` or the dynamic loader lock) deadlocks.
+	pub unsafe fn call_function(
+		&mut self,
+		function: u64,
+		arguments: &[RemoteArgument],
+	) -> Result<u64, RemoteCallError> {
+		if arguments.len() > ARGUMENT_REGISTERS {
+			return Err(RemoteCallError::TooManyArguments);
+		}
+
+		let saved = self.registers()?;
+
+		let mut stack = saved.rsp - RED_ZONE_SIZE;
+		let mut values = [0u64; ARGUMENT_REGISTERS];
+		for (value, argument) in values.iter_mut().zip(arguments) {
+			*value = match argument {
+				RemoteArgument::Value(value) => *value,
+				RemoteArgument::Bytes(bytes) => {
+					stack = (stack - bytes.len() as u64) & !0xF;
+					self.poke_bytes(stack, bytes)?;
+
+					stack
+				}
+			};
+		}
+		// the stack is aligned to 16 bytes before the return address is pushed
+		stack = (stack & !0xF) - 8;
+		self.poke_bytes(stack, &0u64.to_ne_bytes())?;
+
+		let mut registers = saved;
+		registers.rdi = values[0];
+		registers.rsi = values[1];
+		registers.rdx = values[2];
+		registers.rcx = values[3];
+		registers.r8 = values[4];
+		registers.r9 = values[5];
+		// number of vector registers used by variadic functions
+		registers.rax = 0;
+		registers.rsp = stack;
+		registers.rip = function;
+		// prevent the kernel from restarting an interrupted syscall at the new instruction pointer
+		registers.orig_rax = u64::MAX;
+		self.set_registers(&registers)?;
+
+		let result = self.wait_for_return();
+		match result {
+			Err(RemoteCallError::ProcessExited) => (),
+			_ => self.set_registers(&saved)?,
+		}
+
+		result
+	}
+
+	unsafe fn wait_for_return(&mut self) -> Result<u64, RemoteCallError> {
+		let mut signal = 0;
+		loop {
+			if libc::ptrace(libc::PTRACE_CONT, self.pid(), 0, signal) != 0 {
+				return Err(RemoteCallError::PtraceCont(std::io::Error::last_os_error()));
+			}
+
+			let mut status = 0;
+			if libc::waitpid(self.pid(), &mut status, 0) == -1 {
+				return Err(RemoteCallError::WaitpidError(
+					std::io::Error::last_os_error(),
+				));
+			}
+
+			if libc::WIFEXITED(status) || libc::WIFSIGNALED(status) {
+				return Err(RemoteCallError::ProcessExited);
+			}
+			debug_assert!(libc::WIFSTOPPED(status));
+
+			// group stops and ptrace events are not signals to deliver
+			if status >> 16 != 0 {
+				signal = 0;
+				continue;
+			}
+
+			let stop_signal = libc::WSTOPSIG(status);
+			let registers = self.registers()?;
+			if stop_signal == libc::SIGSEGV && registers.rip == 0 {
+				return Ok(registers.rax);
+			}
+
+			match stop_signal {
+				libc::SIGSEGV | libc::SIGBUS | libc::SIGILL | libc::SIGFPE => {
+					return Err(RemoteCallError::UnexpectedStop {
+						signal: stop_signal,
+						rip: registers.rip,
+					})
+				}
+				// signals which arrive during the call are delivered to the process as usual
+				_ => {
+					signal = stop_signal;
+				}
+			}
+		}
+	}
+
+	/// Calls the libc function `symbol`, locking the process for the duration of the call if it is not locked already.
+	///
+	/// If `failed` returns true for the result then the error is read from `errno` of the process.
+	///
+	/// ## Safety
+	/// * See [`call_function`](PtraceLock::call_function).
+	pub(crate) unsafe fn call_libc(
+		&mut self,
+		symbol: &CStr,
+		arguments: &[RemoteArgument],
+		failed: impl FnOnce(u64) -> bool,
+	) -> Result<Result<u64, std::io::Error>, InjectError> {
+		let function = remote_symbol_address(self.pid(), symbol)?;
+		let errno_location = remote_symbol_address(self.pid(), ERRNO_LOCATION_SYMBOL)?;
+
+		self.lock()?;
+		let result = (|| {
+			let result = self.call_function(function, arguments)?;
+			if !failed(result) {
+				return Ok(Ok(result));
+			}
+
+			let errno = self.call_function(errno_location, &[])?;
+			let errno = self.peek_word(errno)? as u32 as i32;
+
+			Ok(Err(std::io::Error::from_raw_os_error(errno)))
+		})();
+		self.unlock()?;
+
+		result
+	}
+
+	/// Loads the shared library at `path` into the process using `dlopen` and returns the library handle.
+	///
+	/// The process is locked for the duration of the call if it is not locked already.
+	/// The `path` is resolved by the process, so it should be absolute.
+	///
+	/// ## Safety
+	/// * See [`call_function`](PtraceLock::call_function). Initializers of the library run inside the process.
+	pub unsafe fn inject_library(&mut self, path: &Path) -> Result<u64, InjectError> {
+		let path =
+			CString::new(path.as_os_str().as_bytes()).map_err(|_| InjectError::InvalidPath)?;
+
+		let dlopen = remote_symbol_address(self.pid(), c"dlopen")?;
+		let dlerror = remote_symbol_address(self.pid(), c"dlerror")?;
+
+		self.lock()?;
+		let result = (|| {
+			let handle = self.call_function(
+				dlopen,
+				&[
+					RemoteArgument::Bytes(path.as_bytes_with_nul()),
+					RemoteArgument::Value(libc::RTLD_NOW as u64),
+				],
+			)?;
+			if handle != 0 {
+				return Ok(handle);
+			}
+
+			let message = match self.call_function(dlerror, &[])? {
+				0 => String::new(),
+				message => self.peek_c_string(message, 4096)?,
+			};
+
+			Err(InjectError::Dlopen(message))
+		})();
+		self.unlock()?;
+
+		result
+	}
+}
+
+/// Converts permissions to `PROT_*` flags.
+fn protection_flags(permissions: MemoryPagePermissions) -> u64 {
+	let mut flags = libc::PROT_NONE;
+	if permissions.read() {
+		flags |= libc::PROT_READ;
+	}
+	if permissions.write() {
+		flags |= libc::PROT_WRITE;
+	}
+	if permissions.exec() {
+		flags |= libc::PROT_EXEC;
+	}
+
+	flags as u64
+}
+
+/// Allocates memory by calling `mmap` and `munmap` inside the process.
+///
+/// The process is locked for the duration of each call if it is not locked already.
+impl MemoryAllocate for PtraceLock {
+	unsafe fn allocate(
+		&mut self,
+		size: u64,
+		permissions: MemoryPagePermissions,
+	) -> Result<OffsetType, AllocateError> {
+		let size = Allocations::round_to_pages(size, libc::sysconf(libc::_SC_PAGESIZE) as u64)?;
+
+		let address = self.call_libc(
+			c"mmap",
+			&[
+				RemoteArgument::Value(0),
+				RemoteArgument::Value(size),
+				RemoteArgument::Value(protection_flags(permissions)),
+				RemoteArgument::Value((libc::MAP_PRIVATE | libc::MAP_ANONYMOUS) as u64),
+				RemoteArgument::Value(-1i64 as u64),
+				RemoteArgument::Value(0),
+			],
+			|result| result == libc::MAP_FAILED as u64,
+		)??;
+
+		let offset = OffsetType::new_unwrap(address);
+		self.allocations.insert(offset, size);
+
+		Ok(offset)
+	}
+
+	unsafe fn free(&mut self, offset: OffsetType) -> Result<(), AllocateError> {
+		let size = self.allocations.get(offset)?;
+
+		self.call_libc(
+			c"munmap",
+			&[
+				RemoteArgument::Value(offset.get()),
+				RemoteArgument::Value(size),
+			],
+			|result| result as i32 == -1,
+		)??;
+		self.allocations.remove(offset);
+
+		Ok(())
+	}
+}
+
+/// Changes permissions by calling `mprotect` inside the process.
+///
+/// The process is locked for the duration of the call if it is not locked already.
+impl MemoryProtect for PtraceLock {
+	unsafe fn protect(
+		&mut self,
+		range: [OffsetType; 2],
+		permissions: MemoryPagePermissions,
+	) -> Result<(), ProtectError> {
+		let (start, size) = page_range(range, libc::sysconf(libc::_SC_PAGESIZE) as u64)?;
+
+		self.call_libc(
+			c"mprotect",
+			&[
+				RemoteArgument::Value(start),
+				RemoteArgument::Value(size),
+				RemoteArgument::Value(protection_flags(permissions)),
+			],
+			|result| result as i32 == -1,
+		)??;
+
+		Ok(())
+	}
+}
+
+/// Returns the path and the start of the executable segment of the module containing `address`.
+///
+/// The executable segment is used as the reference point because other mappings of the same file
+/// (e.g. mapped to read debug info) are not executable.
+fn module_code_base(map: &impl MemoryMap, address: u64) -> Option<(PathBuf, u64)> {
+	let path = map
+		.pages()
+		.iter()
+		.find(|page| address >= page.start().get() && address < page.end().get())
+		.and_then(|page| module_path(&page.page_type))?;
+	let base = code_base(map, &path)?;
+
+	Some((path, base))
+}
+
+fn code_base(map: &impl MemoryMap, path: &Path) -> Option<u64> {
+	map.pages()
+		.iter()
+		.filter(|page| {
+			page.permissions.exec() && module_path(&page.page_type).as_deref() == Some(path)
+		})
+		.map(|page| page.start().get())
+		.min()
+}
+
+fn module_path(page_type: &MemoryPageType) -> Option<PathBuf> {
+	match page_type {
+		MemoryPageType::File(path) | MemoryPageType::ProcessExecutable(path) => Some(path.clone()),
+		_ => None,
+	}
+}
+
+/// Finds the address of `symbol` in process `pid`.
+///
+/// The symbol is resolved in this process and relocated to the same library in the target process,
+/// so both processes must map the same library file.
+pub fn remote_symbol_address(pid: libc::pid_t, symbol: &CStr) -> Result<u64, InjectError> {
+	let symbol_name = || symbol.to_string_lossy().into_owned();
+
+	let local = unsafe { libc::dlsym(libc::RTLD_DEFAULT, symbol.as_ptr()) } as u64;
+	if local == 0 {
+		return Err(InjectError::SymbolNotFound(symbol_name()));
+	}
+
+	let local_map = ProcfsMemoryMap::new(unsafe { libc::getpid() })?;
+	let (path, local_base) = module_code_base(&local_map, local)
+		.ok_or_else(|| InjectError::SymbolNotFound(symbol_name()))?;
+
+	let remote_map = ProcfsMemoryMap::new(pid)?;
+	let remote_base = code_base(&remote_map, &path).ok_or(InjectError::LibraryNotLoaded(path))?;
+
+	Ok(remote_base + (local - local_base))
+}
+
+#[cfg(test)]
+mod test {
+	use std::process::{Child, Command};
+
+	use crate::{
+		memory::{
+			allocate::{AllocateError, MemoryAllocate},
+			lock::MemoryLock,
+			map::{MemoryMap, MemoryPagePermissions, MemoryPageType},
+			protect::{MemoryProtect, ProtectError},
+		},
+		platform::{procfs::ProcfsMemoryMap, ptrace::PtraceLock},
+	};
+
+	use super::{remote_symbol_address, InjectError, RemoteArgument};
+
+	struct KillOnDrop(Child);
+	impl Drop for KillOnDrop {
+		fn drop(&mut self) {
+			let _ = self.0.kill();
+			let _ = self.0.wait();
+		}
+	}
+
+	fn spawn_target() -> Option<(KillOnDrop, PtraceLock)> {
+		let child = KillOnDrop(Command::new("sleep").arg("30").spawn().ok()?);
+		// give the loader time to map libc
+		std::thread::sleep(std::time::Duration::from_millis(100));
+
+		match PtraceLock::new(child.0.id() as libc::pid_t) {
+			Ok(lock) => Some((child, lock)),
+			Err(err) => {
+				eprintln!("skipping, could not attach: {}", err);
+				None
+			}
+		}
+	}
+
+	#[test]
+	fn test_call_function() {
+		let (child, mut lock) = match spawn_target() {
+			None => return,
+			Some(target) => target,
+		};
+		let pid = child.0.id() as libc::pid_t;
+
+		let getpid = remote_symbol_address(pid, c"getpid").unwrap();
+		let strlen = remote_symbol_address(pid, c"strlen").unwrap();
+
+		lock.lock().unwrap();
+		let registers = lock.registers().unwrap();
+		unsafe {
+			assert_eq!(lock.call_function(getpid, &[]).unwrap(), pid as u64);
+			assert_eq!(
+				lock.call_function(strlen, &[RemoteArgument::Bytes(b"procmem\0")])
+					.unwrap(),
+				7
+			);
+		}
+		assert_eq!(lock.registers().unwrap().rip, registers.rip);
+		lock.unlock().unwrap();
+	}
+
+	#[test]
+	fn test_call_function_signal() {
+		let (child, mut lock) = match spawn_target() {
+			None => return,
+			Some(target) => target,
+		};
+		let pid = child.0.id() as libc::pid_t;
+
+		let usleep = remote_symbol_address(pid, c"usleep").unwrap();
+
+		lock.lock().unwrap();
+		// SIGWINCH is ignored by default, so the interrupted sleep is restarted
+		let signaller = std::thread::spawn(move || {
+			std::thread::sleep(std::time::Duration::from_millis(50));
+			unsafe { libc::kill(pid, libc::SIGWINCH) };
+		});
+		let result = unsafe { lock.call_function(usleep, &[RemoteArgument::Value(200_000)]) };
+		signaller.join().unwrap();
+		assert_eq!(result.unwrap(), 0);
+		lock.unlock().unwrap();
+	}
+
+	#[test]
+	fn test_inject_library() {
+		let (child, mut lock) = match spawn_target() {
+			None => return,
+			Some(target) => target,
+		};
+
+		let handle = unsafe {
+			lock.inject_library("/lib/x86_64-linux-gnu/libm.so.6".as_ref())
+				.unwrap()
+		};
+		assert_ne!(handle, 0);
+		let map = ProcfsMemoryMap::new(child.0.id() as libc::pid_t).unwrap();
+		assert!(map.pages().iter().any(|page| matches!(
+			&page.page_type,
+			MemoryPageType::File(path) if path.ends_with("libm.so.6")
+		)));
+
+		let err = unsafe {
+			lock.inject_library("/nonexistent/libprocmem.so".as_ref())
+				.unwrap_err()
+		};
+		match err {
+			InjectError::Dlopen(message) => assert!(message.contains("libprocmem.so")),
+			err => panic!("unexpected error: {}", err),
+		}
+		assert!(!lock.is_locked());
+	}
+
+	#[test]
+	fn test_remote_allocate() {
+		let (child, mut lock) = match spawn_target() {
+			None => return,
+			Some(target) => target,
+		};
+		let pid = child.0.id() as libc::pid_t;
+
+		let offset = unsafe {
+			lock.allocate(100, MemoryPagePermissions::new(true, false, true, false))
+				.unwrap()
+		};
+		let map = ProcfsMemoryMap::new(pid).unwrap();
+		let page = map.containing_page(offset).unwrap();
+		assert_eq!(page.start(), offset);
+		assert!(page.permissions.read() && page.permissions.exec() && !page.permissions.write());
+		assert!(!lock.is_locked());
+
+		unsafe { lock.free(offset).unwrap() };
+		let map = ProcfsMemoryMap::new(pid).unwrap();
+		assert!(map.containing_page(offset).is_none());
+		assert!(matches!(
+			unsafe { lock.free(offset) },
+			Err(AllocateError::NotAllocated(_))
+		));
+	}
+
+	#[test]
+	fn test_remote_protect() {
+		let (child, mut lock) = match spawn_target() {
+			None => return,
+			Some(target) => target,
+		};
+		let pid = child.0.id() as libc::pid_t;
+
+		let offset = unsafe {
+			lock.allocate(100, MemoryPagePermissions::new(true, true, false, false))
+				.unwrap()
+		};
+		// the range is extended to the whole page
+		unsafe {
+			lock.protect(
+				[offset.saturating_add(10), offset.saturating_add(20)],
+				MemoryPagePermissions::new(true, false, true, false),
+			)
+			.unwrap()
+		};
+		let map = ProcfsMemoryMap::new(pid).unwrap();
+		let page = map.containing_page(offset).unwrap();
+		assert_eq!(page.start(), offset);
+		assert!(page.permissions.read() && page.permissions.exec() && !page.permissions.write());
+		assert!(!lock.is_locked());
+
+		assert!(matches!(
+			unsafe {
+				lock.protect(
+					[offset, offset],
+					MemoryPagePermissions::new(true, false, false, false),
+				)
+			},
+			Err(ProtectError::EmptyRange)
+		));
+
+		unsafe { lock.free(offset).unwrap() };
+	}
+}
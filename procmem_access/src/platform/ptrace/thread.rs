@@ -0,0 +1,162 @@
+//! Lock which stops only selected threads of a process.
+//!
+//! On Linux each thread is traced separately, so seizing and interrupting a thread id leaves the other threads running.
+//! This is useful for targets with watchdog threads which detect that the whole process stopped.
+
+use crate::memory::lock::{LockError, MemoryLock, UnlockError};
+
+use super::lock::{PtraceLock, PtraceLockError};
+
+/// Lock which stops only the selected threads of a process, see [`ThreadInfo::list`](crate::platform::procfs::ThreadInfo::list).
+///
+/// Memory can still change while locked, because the remaining threads keep running.
+pub struct PtraceThreadLock {
+	pid: libc::pid_t,
+	threads: Vec<PtraceLock>,
+	lock_counter: usize,
+}
+impl PtraceThreadLock {
+	/// Seizes threads `tids` of process `pid`.
+	pub fn new(pid: libc::pid_t, tids: &[libc::pid_t]) -> Result<Self, PtraceLockError> {
+		let threads = tids
+			.iter()
+			.map(|&tid| {
+				// the thread must belong to the process, `/proc/<tid>` would also find threads of other processes
+				if std::fs::metadata(format!("/proc/{}/task/{}", pid, tid)).is_err() {
+					return Err(PtraceLockError::PtraceAttach(std::io::Error::new(
+						std::io::ErrorKind::NotFound,
+						"thread does not belong to the process",
+					)));
+				}
+
+				PtraceLock::new(tid)
+			})
+			.collect::<Result<Vec<_>, _>>()?;
+
+		Ok(PtraceThreadLock {
+			pid,
+			threads,
+			lock_counter: 0,
+		})
+	}
+
+	pub const fn pid(&self) -> libc::pid_t {
+		self.pid
+	}
+
+	/// Returns the ids of the threads stopped by this lock.
+	pub fn tids(&self) -> impl Iterator<Item = libc::pid_t> + '_ {
+		self.threads.iter().map(PtraceLock::pid)
+	}
+
+	fn stop(&mut self) -> Result<(), LockError> {
+		for index in 0..self.threads.len() {
+			if let Err(err) = self.threads[index].lock() {
+				// resume the threads stopped so far
+				for thread in self.threads[..index].iter_mut() {
+					let _ = thread.unlock();
+				}
+
+				return Err(err);
+			}
+		}
+
+		Ok(())
+	}
+
+	fn resume(&mut self) -> Result<(), UnlockError> {
+		let mut result = Ok(());
+		for thread in self.threads.iter_mut() {
+			if let Err(err) = thread.unlock() {
+				result = Err(err);
+			}
+		}
+
+		result
+	}
+}
+impl MemoryLock for PtraceThreadLock {
+	fn lock(&mut self) -> Result<bool, LockError> {
+		if self.lock_counter == 0 {
+			self.stop()?;
+			self.lock_counter = 1;
+
+			Ok(true)
+		} else if self.lock_counter == usize::MAX {
+			Err(LockError::AlreadyLocked)
+		} else {
+			self.lock_counter += 1;
+
+			Ok(false)
+		}
+	}
+
+	fn lock_exlusive(&mut self) -> Result<(), LockError> {
+		if self.lock_counter == 0 {
+			self.lock()?;
+			self.lock_counter = usize::MAX;
+
+			Ok(())
+		} else {
+			Err(LockError::AlreadyLocked)
+		}
+	}
+
+	fn unlock(&mut self) -> Result<bool, UnlockError> {
+		if self.lock_counter == 0 {
+			return Err(UnlockError::NotLocked);
+		}
+
+		if self.lock_counter == 1 || self.lock_counter == usize::MAX {
+			self.resume()?;
+			self.lock_counter = 0;
+
+			Ok(true)
+		} else {
+			self.lock_counter -= 1;
+
+			Ok(false)
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use std::process::Command;
+
+	use super::PtraceThreadLock;
+	use crate::{memory::lock::MemoryLock, platform::procfs::ThreadInfo};
+
+	fn thread_state(pid: u32, tid: libc::pid_t) -> char {
+		let stat = std::fs::read_to_string(format!("/proc/{}/task/{}/stat", pid, tid)).unwrap();
+		// the name in parentheses may contain spaces
+		stat[stat.rfind(')').unwrap() + 2..].chars().next().unwrap()
+	}
+
+	#[test]
+	fn test_thread_lock() {
+		let mut child = Command::new("sleep").arg("30").spawn().unwrap();
+		let pid = child.id();
+
+		let threads = ThreadInfo::list(pid as libc::pid_t).unwrap();
+		assert_eq!(threads.len(), 1);
+		let tid = threads[0].tid;
+
+		// threads of other processes are rejected
+		assert!(PtraceThreadLock::new(pid as libc::pid_t, &[unsafe { libc::gettid() }]).is_err());
+
+		match PtraceThreadLock::new(pid as libc::pid_t, &[tid]) {
+			Ok(mut lock) => {
+				assert!(lock.lock().unwrap());
+				assert_eq!(thread_state(pid, tid), 't');
+
+				lock.unlock().unwrap();
+				assert_ne!(thread_state(pid, tid), 't');
+			}
+			Err(err) => eprintln!("skipping, could not attach: {}", err),
+		}
+
+		child.kill().unwrap();
+		child.wait().unwrap();
+	}
+}
@@ -0,0 +1,521 @@
+//! Hardware watchpoints using the x86 debug registers.
+//!
+//! A [`PtraceWatcher`] traces all threads of a process and programs the same watchpoints into the debug registers
+//! of each of them, including threads created later. The CPU traps when a watched address is accessed and the watcher
+//! reports the thread and its instruction pointer, which answers the question "what writes this address".
+//!
+//! The watcher is a separate tracer, it cannot be used together with a [`PtraceLock`](super::PtraceLock) of the same process.
+
+use std::time::{Duration, Instant};
+
+use thiserror::Error;
+
+use crate::{
+	common::OffsetType,
+	error::{impl_from_kinded_error, ErrorKind},
+};
+
+/// Number of debug address registers, `DR0` to `DR3`.
+pub const WATCHPOINT_SLOTS: usize = 4;
+
+/// `si_code` of traps caused by hardware breakpoints.
+const TRAP_HWBKPT: libc::c_int = 4;
+
+#[derive(Debug, Error)]
+pub enum WatchError {
+	#[error("all {WATCHPOINT_SLOTS} watchpoint slots are in use")]
+	NoFreeSlot,
+	#[error("invalid watchpoint: {0}")]
+	InvalidWatchpoint(&'static str),
+	#[error("could not list threads")]
+	Threads(#[source] std::io::Error),
+	#[error("ptrace failed")]
+	Ptrace(#[source] std::io::Error),
+	#[error("waitpid failed")]
+	Waitpid(#[source] std::io::Error),
+	#[error("process exited")]
+	ProcessExited,
+}
+impl WatchError {
+	pub fn kind(&self) -> ErrorKind {
+		match self {
+			WatchError::NoFreeSlot | WatchError::InvalidWatchpoint(_) => ErrorKind::Platform,
+			WatchError::Threads(err) | WatchError::Ptrace(err) | WatchError::Waitpid(err) => {
+				ErrorKind::from_io(err)
+			}
+			WatchError::ProcessExited => ErrorKind::ProcessExited,
+		}
+	}
+}
+impl_from_kinded_error!(WatchError);
+
+/// Access which triggers a watchpoint.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum WatchKind {
+	/// Instruction fetch, the size must be 1.
+	Execute,
+	Write,
+	/// Reads and writes, x86 cannot watch only reads.
+	ReadWrite,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Watchpoint {
+	pub address: OffsetType,
+	/// Size of the watched range, one of 1, 2, 4 or 8 bytes. The address must be aligned to it.
+	pub size: u8,
+	pub kind: WatchKind,
+}
+impl Watchpoint {
+	pub fn new(address: OffsetType, size: u8, kind: WatchKind) -> Self {
+		Watchpoint {
+			address,
+			size,
+			kind,
+		}
+	}
+
+	fn validate(&self) -> Result<(), WatchError> {
+		if !matches!(self.size, 1 | 2 | 4 | 8) {
+			return Err(WatchError::InvalidWatchpoint("size must be 1, 2, 4 or 8"));
+		}
+		if !self.address.get().is_multiple_of(self.size as u64) {
+			return Err(WatchError::InvalidWatchpoint(
+				"address must be aligned to size",
+			));
+		}
+		if self.kind == WatchKind::Execute && self.size != 1 {
+			return Err(WatchError::InvalidWatchpoint(
+				"execute watchpoints must have size 1",
+			));
+		}
+
+		Ok(())
+	}
+
+	/// Returns the `DR7` control bits of this watchpoint in `slot`.
+	fn control_bits(&self, slot: usize) -> u64 {
+		let access = match self.kind {
+			WatchKind::Execute => 0b00,
+			WatchKind::Write => 0b01,
+			WatchKind::ReadWrite => 0b11,
+		};
+		let length = match self.size {
+			1 => 0b00,
+			2 => 0b01,
+			8 => 0b10,
+			_ => 0b11,
+		};
+
+		// local enable bit and the access and length fields
+		(1 << (slot * 2)) | ((access | (length << 2)) << (16 + slot * 4))
+	}
+}
+
+/// Watchpoint trap reported by [`PtraceWatcher::wait`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct WatchHit {
+	/// Thread which accessed the watched address.
+	pub tid: libc::pid_t,
+	/// Slot of the triggered watchpoint.
+	pub slot: usize,
+	/// Instruction pointer of the thread at the trap.
+	///
+	/// Data watchpoints trap after the access, so this is the instruction following the one which accessed the address.
+	/// Execute watchpoints trap before the instruction, so this is the watched address.
+	pub instruction_pointer: u64,
+}
+
+/// How a stop of a thread was handled.
+enum Stop {
+	/// Stopped by `PTRACE_INTERRUPT`, a stop signal or a new thread starting.
+	Interrupt,
+	/// Trapped by a watchpoint.
+	Hit(WatchHit),
+	/// A new thread was created, both threads were resumed.
+	Clone,
+	/// Stopped before delivering a signal.
+	Signal(libc::c_int),
+	/// The thread exited.
+	Exited,
+}
+
+/// Tracer which reports accesses to watched addresses by any thread of a process.
+pub struct PtraceWatcher {
+	pid: libc::pid_t,
+	threads: Vec<libc::pid_t>,
+	slots: [Option<Watchpoint>; WATCHPOINT_SLOTS],
+}
+impl PtraceWatcher {
+	/// Seizes all threads of process `pid`, new threads are traced automatically.
+	pub fn new(pid: libc::pid_t) -> Result<Self, WatchError> {
+		let mut me = PtraceWatcher {
+			pid,
+			threads: Vec::new(),
+			slots: [None; WATCHPOINT_SLOTS],
+		};
+
+		for entry in
+			std::fs::read_dir(format!("/proc/{}/task", pid)).map_err(WatchError::Threads)?
+		{
+			let tid = match entry
+				.map_err(WatchError::Threads)?
+				.file_name()
+				.to_str()
+				.and_then(|name| name.parse::<libc::pid_t>().ok())
+			{
+				None => continue,
+				Some(tid) => tid,
+			};
+
+			let ptrace_res = unsafe {
+				libc::ptrace(
+					super::requests::PTRACE_SEIZE,
+					tid,
+					0,
+					libc::PTRACE_O_TRACECLONE as libc::c_long,
+				)
+			};
+			match ptrace_res {
+				0 => me.threads.push(tid),
+				// the thread exited in the meantime
+				_ if std::io::Error::last_os_error().raw_os_error() == Some(libc::ESRCH) => (),
+				_ => return Err(WatchError::Ptrace(std::io::Error::last_os_error())),
+			}
+		}
+
+		Ok(me)
+	}
+
+	pub const fn pid(&self) -> libc::pid_t {
+		self.pid
+	}
+
+	/// Returns the traced threads.
+	pub fn threads(&self) -> &[libc::pid_t] {
+		&self.threads
+	}
+
+	pub fn watchpoints(&self) -> &[Option<Watchpoint>; WATCHPOINT_SLOTS] {
+		&self.slots
+	}
+
+	/// Adds `watchpoint` to a free slot in all threads and returns the slot.
+	pub fn watch(&mut self, watchpoint: Watchpoint) -> Result<usize, WatchError> {
+		watchpoint.validate()?;
+		let slot = self
+			.slots
+			.iter()
+			.position(Option::is_none)
+			.ok_or(WatchError::NoFreeSlot)?;
+
+		self.slots[slot] = Some(watchpoint);
+		if let Err(err) = self.apply_all() {
+			self.slots[slot] = None;
+			return Err(err);
+		}
+
+		Ok(slot)
+	}
+
+	/// Removes the watchpoint in `slot` from all threads.
+	pub fn unwatch(&mut self, slot: usize) -> Result<Option<Watchpoint>, WatchError> {
+		let watchpoint = match self.slots.get_mut(slot) {
+			None => return Ok(None),
+			Some(watchpoint) => watchpoint.take(),
+		};
+		self.apply_all()?;
+
+		Ok(watchpoint)
+	}
+
+	/// Waits up to `timeout` for a watchpoint trap, signals are delivered to the process in the meantime.
+	///
+	/// The thread continues right after the hit is recorded.
+	pub fn wait(&mut self, timeout: Duration) -> Result<Option<WatchHit>, WatchError> {
+		let start = Instant::now();
+		loop {
+			// waiting for each thread separately keeps other children of this process untouched
+			for index in 0..self.threads.len() {
+				let tid = self.threads[index];
+
+				let mut status = 0;
+				let waitpid_res =
+					unsafe { libc::waitpid(tid, &mut status, libc::WNOHANG | libc::__WALL) };
+				match waitpid_res {
+					0 => continue,
+					-1 => return Err(WatchError::Waitpid(std::io::Error::last_os_error())),
+					_ => (),
+				}
+
+				match unsafe { self.handle_stop(tid, status)? } {
+					Stop::Hit(hit) => {
+						unsafe { Self::cont(tid, 0)? };
+						return Ok(Some(hit));
+					}
+					// threads were added or removed, start over
+					Stop::Clone | Stop::Exited => break,
+					Stop::Interrupt => unsafe { Self::cont(tid, 0)? },
+					Stop::Signal(signal) => unsafe { Self::cont(tid, signal)? },
+				}
+			}
+
+			if start.elapsed() >= timeout {
+				return Ok(None);
+			}
+			std::thread::sleep(Duration::from_millis(1));
+		}
+	}
+
+	/// Stops every thread, writes the debug registers and resumes it.
+	fn apply_all(&mut self) -> Result<(), WatchError> {
+		let mut index = 0;
+		while index < self.threads.len() {
+			let tid = self.threads[index];
+
+			unsafe {
+				if libc::ptrace(super::requests::PTRACE_INTERRUPT, tid, 0, 0) != 0 {
+					return Err(WatchError::Ptrace(std::io::Error::last_os_error()));
+				}
+				if self.wait_for_interrupt(tid)? {
+					self.apply(tid)?;
+					Self::cont(tid, 0)?;
+					index += 1;
+				}
+			}
+		}
+
+		Ok(())
+	}
+
+	/// Waits until thread `tid` stops after `PTRACE_INTERRUPT`, returns false if it exited.
+	///
+	/// Signals are delivered, but hits are dropped because the watchpoints are being changed.
+	unsafe fn wait_for_interrupt(&mut self, tid: libc::pid_t) -> Result<bool, WatchError> {
+		loop {
+			let mut status = 0;
+			if libc::waitpid(tid, &mut status, libc::__WALL) == -1 {
+				return Err(WatchError::Waitpid(std::io::Error::last_os_error()));
+			}
+
+			match self.handle_stop(tid, status)? {
+				Stop::Interrupt => return Ok(true),
+				Stop::Exited => return Ok(false),
+				Stop::Clone => (),
+				Stop::Hit(_) => Self::cont(tid, 0)?,
+				Stop::Signal(signal) => Self::cont(tid, signal)?,
+			}
+		}
+	}
+
+	/// Classifies the stop of thread `tid` described by the waitpid `status`.
+	unsafe fn handle_stop(
+		&mut self,
+		tid: libc::pid_t,
+		status: libc::c_int,
+	) -> Result<Stop, WatchError> {
+		if libc::WIFEXITED(status) || libc::WIFSIGNALED(status) {
+			self.threads.retain(|&thread| thread != tid);
+			if self.threads.is_empty() {
+				return Err(WatchError::ProcessExited);
+			}
+
+			return Ok(Stop::Exited);
+		}
+
+		match status >> 16 {
+			libc::PTRACE_EVENT_STOP => Ok(Stop::Interrupt),
+			libc::PTRACE_EVENT_CLONE => {
+				let mut new_tid: libc::c_ulong = 0;
+				if libc::ptrace(libc::PTRACE_GETEVENTMSG, tid, 0, &mut new_tid) != 0 {
+					return Err(WatchError::Ptrace(std::io::Error::last_os_error()));
+				}
+				let new_tid = new_tid as libc::pid_t;
+
+				// the new thread starts traced and stopped, but without our debug registers
+				if libc::waitpid(new_tid, std::ptr::null_mut(), libc::__WALL) == -1 {
+					return Err(WatchError::Waitpid(std::io::Error::last_os_error()));
+				}
+				self.threads.push(new_tid);
+				self.apply(new_tid)?;
+				Self::cont(new_tid, 0)?;
+				Self::cont(tid, 0)?;
+
+				Ok(Stop::Clone)
+			}
+			0 if libc::WSTOPSIG(status) == libc::SIGTRAP => {
+				let mut info = std::mem::MaybeUninit::<libc::siginfo_t>::uninit();
+				if libc::ptrace(libc::PTRACE_GETSIGINFO, tid, 0, info.as_mut_ptr()) != 0 {
+					return Err(WatchError::Ptrace(std::io::Error::last_os_error()));
+				}
+				if info.assume_init().si_code != TRAP_HWBKPT {
+					return Ok(Stop::Signal(libc::SIGTRAP));
+				}
+
+				// DR6 has a bit for each triggered slot and must be cleared by the handler
+				let status_register = Self::peek_debug_register(tid, 6)?;
+				Self::poke_debug_register(tid, 6, 0)?;
+				let slot =
+					match (0..WATCHPOINT_SLOTS).find(|slot| status_register & (1 << slot) != 0) {
+						None => return Ok(Stop::Signal(0)),
+						Some(slot) => slot,
+					};
+
+				let mut registers = std::mem::MaybeUninit::<libc::user_regs_struct>::uninit();
+				if libc::ptrace(libc::PTRACE_GETREGS, tid, 0, registers.as_mut_ptr()) != 0 {
+					return Err(WatchError::Ptrace(std::io::Error::last_os_error()));
+				}
+
+				Ok(Stop::Hit(WatchHit {
+					tid,
+					slot,
+					instruction_pointer: registers.assume_init().rip,
+				}))
+			}
+			0 => Ok(Stop::Signal(libc::WSTOPSIG(status))),
+			// events which were not requested
+			_ => Ok(Stop::Signal(0)),
+		}
+	}
+
+	/// Writes the debug registers of the stopped thread `tid`.
+	unsafe fn apply(&self, tid: libc::pid_t) -> Result<(), WatchError> {
+		// disable everything first, the addresses cannot be changed while enabled
+		Self::poke_debug_register(tid, 7, 0)?;
+
+		let mut control = 0;
+		for (slot, watchpoint) in self.slots.iter().enumerate() {
+			if let Some(watchpoint) = watchpoint {
+				Self::poke_debug_register(tid, slot, watchpoint.address.get())?;
+				control |= watchpoint.control_bits(slot);
+			}
+		}
+
+		Self::poke_debug_register(tid, 7, control)
+	}
+
+	fn debug_register_offset(register: usize) -> usize {
+		std::mem::offset_of!(libc::user, u_debugreg) + register * std::mem::size_of::<u64>()
+	}
+
+	unsafe fn peek_debug_register(tid: libc::pid_t, register: usize) -> Result<u64, WatchError> {
+		*super::errno_location() = 0;
+		let value = libc::ptrace(
+			libc::PTRACE_PEEKUSER,
+			tid,
+			Self::debug_register_offset(register),
+			0,
+		);
+		if value == -1 && *super::errno_location() != 0 {
+			return Err(WatchError::Ptrace(std::io::Error::last_os_error()));
+		}
+
+		Ok(value as u64)
+	}
+
+	unsafe fn poke_debug_register(
+		tid: libc::pid_t,
+		register: usize,
+		value: u64,
+	) -> Result<(), WatchError> {
+		let ptrace_res = libc::ptrace(
+			libc::PTRACE_POKEUSER,
+			tid,
+			Self::debug_register_offset(register),
+			value,
+		);
+		if ptrace_res != 0 {
+			return Err(WatchError::Ptrace(std::io::Error::last_os_error()));
+		}
+
+		Ok(())
+	}
+
+	unsafe fn cont(tid: libc::pid_t, signal: libc::c_int) -> Result<(), WatchError> {
+		if libc::ptrace(libc::PTRACE_CONT, tid, 0, signal as libc::c_long) != 0 {
+			return Err(WatchError::Ptrace(std::io::Error::last_os_error()));
+		}
+
+		Ok(())
+	}
+}
+impl Drop for PtraceWatcher {
+	fn drop(&mut self) {
+		self.slots = [None; WATCHPOINT_SLOTS];
+
+		// waiting may remove threads which exit and add new ones from clones, which are detached as well
+		while let Some(tid) = self.threads.pop() {
+			unsafe {
+				if libc::ptrace(super::requests::PTRACE_INTERRUPT, tid, 0, 0) != 0 {
+					continue;
+				}
+				if let Ok(true) = self.wait_for_interrupt(tid) {
+					let _ = Self::poke_debug_register(tid, 7, 0);
+					libc::ptrace(libc::PTRACE_DETACH, tid, 0, 0);
+				}
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use std::{process::Command, time::Duration};
+
+	use super::{PtraceWatcher, WatchKind, Watchpoint};
+	use crate::{common::OffsetType, memory::lock::MemoryLock, platform::ptrace::PtraceLock};
+
+	#[test]
+	fn test_watch_execute() {
+		let mut child = Command::new("sh")
+			.args(["-c", "while :; do :; done"])
+			.spawn()
+			.unwrap();
+		let pid = child.id() as libc::pid_t;
+		std::thread::sleep(Duration::from_millis(50));
+
+		// sample an instruction of the busy loop, it is executed again soon
+		let instruction = match PtraceLock::new(pid) {
+			Ok(mut lock) => {
+				lock.lock().unwrap();
+				let rip = lock.registers().unwrap().rip;
+				lock.unlock().unwrap();
+
+				rip
+			}
+			Err(err) => {
+				eprintln!("skipping, could not attach: {}", err);
+				child.kill().unwrap();
+				child.wait().unwrap();
+				return;
+			}
+		};
+
+		let mut watcher = PtraceWatcher::new(pid).unwrap();
+		assert!(watcher
+			.watch(Watchpoint::new(
+				OffsetType::new_unwrap(instruction),
+				4,
+				WatchKind::Execute
+			))
+			.is_err());
+		let slot = watcher
+			.watch(Watchpoint::new(
+				OffsetType::new_unwrap(instruction),
+				1,
+				WatchKind::Execute,
+			))
+			.unwrap();
+
+		let hit = watcher.wait(Duration::from_secs(5)).unwrap().unwrap();
+		assert_eq!(hit.slot, slot);
+		assert_eq!(hit.tid, pid);
+		assert_eq!(hit.instruction_pointer, instruction);
+
+		watcher.unwatch(slot).unwrap();
+		drop(watcher);
+
+		child.kill().unwrap();
+		child.wait().unwrap();
+	}
+}
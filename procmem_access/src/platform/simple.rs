@@ -4,31 +4,62 @@
 //! For each supported platform, this module exports uniformly named types and functions
 //! for simple common functionality.
 
-#[cfg(target_os = "linux")]
+#[cfg(any(target_os = "linux", target_os = "android"))]
 mod inner {
 	use super::super::{procfs, ptrace};
 
+	pub type SimplePid = libc::pid_t;
+	pub type SimpleTid = libc::pid_t;
 	pub type SimpleMemoryLock = ptrace::PtraceLock;
+	pub type SimpleThreadLock = ptrace::PtraceThreadLock;
 	pub type SimpleMemoryAccess = procfs::ProcfsAccess;
 	pub type SimpleMemoryMap = procfs::ProcfsMemoryMap;
 
-	pub use procfs::ProcessInfo;
+	pub use procfs::{ProcessInfo, ThreadInfo};
 }
 
 #[cfg(target_os = "macos")]
 mod inner {
 	use super::super::{mach as mch, ptrace};
 
+	pub type SimplePid = libc::pid_t;
+	pub type SimpleTid = u64;
 	pub type SimpleMemoryLock = ptrace::PtraceLock;
+	pub type SimpleThreadLock = mch::MachThreadLock;
 	pub type SimpleMemoryAccess = mch::MachAccess;
 	pub type SimpleMemoryMap = mch::MachMemoryMap;
 
-	pub use mch::ProcessInfo;
+	pub use mch::{ProcessInfo, ThreadInfo};
 }
 
 #[cfg(target_os = "windows")]
 mod inner {
-	// TODO
+	use super::super::windows;
+
+	pub type SimplePid = u32;
+	pub type SimpleTid = u32;
+	pub type SimpleMemoryLock = windows::WindowsLock;
+	pub type SimpleThreadLock = windows::WindowsThreadLock;
+	pub type SimpleMemoryAccess = windows::WindowsAccess;
+	pub type SimpleMemoryMap = windows::WindowsMemoryMap;
+
+	pub use windows::{ProcessInfo, ThreadInfo};
+}
+
+#[cfg(any(target_os = "freebsd", target_os = "netbsd"))]
+mod inner {
+	use super::super::bsd;
+
+	pub type SimplePid = libc::pid_t;
+	pub type SimpleMemoryLock = bsd::BsdLock;
+	pub type SimpleMemoryAccess = bsd::BsdAccess;
+	pub type SimpleMemoryMap = bsd::BsdMemoryMap;
+
+	pub use bsd::ProcessInfo;
 }
 
-pub use inner::{ProcessInfo, SimpleMemoryAccess, SimpleMemoryLock, SimpleMemoryMap};
+pub use inner::{ProcessInfo, SimpleMemoryAccess, SimpleMemoryLock, SimpleMemoryMap, SimplePid};
+
+// thread locks are not implemented for the BSDs yet
+#[cfg(not(any(target_os = "freebsd", target_os = "netbsd")))]
+pub use inner::{SimpleThreadLock, SimpleTid, ThreadInfo};
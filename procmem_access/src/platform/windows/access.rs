@@ -0,0 +1,335 @@
+use std::{os::windows::ffi::OsStrExt, path::Path};
+
+use thiserror::Error;
+
+use windows_sys::Win32::{
+	Foundation::WAIT_FAILED,
+	System::{
+		Diagnostics::Debug::{ReadProcessMemory, WriteProcessMemory},
+		LibraryLoader::{GetModuleHandleW, GetProcAddress},
+		Memory::{
+			VirtualAllocEx, VirtualFreeEx, VirtualProtectEx, MEM_COMMIT, MEM_RELEASE, MEM_RESERVE,
+			PAGE_EXECUTE, PAGE_EXECUTE_READ, PAGE_EXECUTE_READWRITE, PAGE_NOACCESS,
+			PAGE_PROTECTION_FLAGS, PAGE_READONLY, PAGE_READWRITE,
+		},
+		Threading::{
+			CreateRemoteThread, GetExitCodeThread, WaitForSingleObject, INFINITE,
+			LPTHREAD_START_ROUTINE, PROCESS_CREATE_THREAD, PROCESS_QUERY_INFORMATION,
+			PROCESS_VM_OPERATION, PROCESS_VM_READ, PROCESS_VM_WRITE,
+		},
+	},
+};
+
+use crate::{
+	common::OffsetType,
+	error::{impl_from_kinded_error, ErrorKind},
+	memory::{
+		access::{MemoryAccess, ReadError, WriteError},
+		allocate::{AllocateError, Allocations, MemoryAllocate},
+		map::MemoryPagePermissions,
+		protect::{MemoryProtect, ProtectError},
+	},
+};
+
+use super::{ModuleInfo, OwnedHandle};
+
+#[derive(Debug, Error)]
+pub enum WindowsAccessError {
+	#[error("could not open process")]
+	OpenProcess(#[source] std::io::Error),
+}
+impl WindowsAccessError {
+	pub fn kind(&self) -> ErrorKind {
+		match self {
+			WindowsAccessError::OpenProcess(err) => ErrorKind::from_io(err),
+		}
+	}
+}
+impl_from_kinded_error!(WindowsAccessError);
+
+#[derive(Debug, Error)]
+pub enum WindowsInjectError {
+	#[error("library path contains a nul character")]
+	InvalidPath,
+	#[error("could not open process")]
+	OpenProcess(#[source] std::io::Error),
+	#[error("LoadLibraryW not found in this process")]
+	SymbolNotFound(#[source] std::io::Error),
+	#[error(transparent)]
+	Allocate(#[from] AllocateError),
+	#[error(transparent)]
+	Write(#[from] WriteError),
+	#[error("could not create remote thread")]
+	CreateThread(#[source] std::io::Error),
+	#[error("waiting for remote thread failed")]
+	Wait(#[source] std::io::Error),
+	#[error("could not list modules of the target process")]
+	Modules(#[source] std::io::Error),
+	#[error("LoadLibraryW failed in the target process")]
+	LoadLibrary,
+	#[error("library {} is not loaded in the target process", .0.display())]
+	LibraryNotLoaded(std::path::PathBuf),
+}
+impl WindowsInjectError {
+	pub fn kind(&self) -> ErrorKind {
+		match self {
+			WindowsInjectError::Allocate(err) => err.kind(),
+			WindowsInjectError::Write(err) => err.kind(),
+			WindowsInjectError::InvalidPath
+			| WindowsInjectError::LoadLibrary
+			| WindowsInjectError::LibraryNotLoaded(_) => ErrorKind::Platform,
+			_ => ErrorKind::from_error(self),
+		}
+	}
+}
+impl_from_kinded_error!(WindowsInjectError);
+
+/// Memory access through `ReadProcessMemory` and `WriteProcessMemory`.
+pub struct WindowsAccess {
+	pid: u32,
+	process: OwnedHandle,
+	allocations: Allocations,
+}
+impl WindowsAccess {
+	/// Granularity of allocations made by `VirtualAllocEx`.
+	const ALLOCATION_GRANULARITY: u64 = 64 * 1024;
+
+	pub fn new(pid: u32) -> Result<Self, WindowsAccessError> {
+		let process = OwnedHandle::open_process(
+			pid,
+			PROCESS_VM_READ | PROCESS_VM_WRITE | PROCESS_VM_OPERATION | PROCESS_QUERY_INFORMATION,
+		)
+		.map_err(WindowsAccessError::OpenProcess)?;
+
+		Ok(WindowsAccess {
+			pid,
+			process,
+			allocations: Allocations::new(),
+		})
+	}
+
+	/// Converts permissions to `PAGE_*` protection, write-only pages are also readable.
+	pub(super) fn protection(permissions: MemoryPagePermissions) -> PAGE_PROTECTION_FLAGS {
+		match (permissions.read(), permissions.write(), permissions.exec()) {
+			(false, false, false) => PAGE_NOACCESS,
+			(true, false, false) => PAGE_READONLY,
+			(_, true, false) => PAGE_READWRITE,
+			(false, false, true) => PAGE_EXECUTE,
+			(true, false, true) => PAGE_EXECUTE_READ,
+			(_, true, true) => PAGE_EXECUTE_READWRITE,
+		}
+	}
+
+	/// Loads the library at `path` into the process by running `LoadLibraryW` in a remote thread and returns the module handle.
+	///
+	/// The address of `LoadLibraryW` is taken from this process, which relies on `kernel32.dll` being mapped
+	/// at the same address in every process. The target process must therefore have the same architecture.
+	/// The `path` is resolved by the process, so it should be absolute.
+	///
+	/// ## Safety
+	/// * Initializers of the library run inside the process.
+	pub unsafe fn inject_library(&mut self, path: &Path) -> Result<u64, WindowsInjectError> {
+		let mut path_wide: Vec<u16> = path.as_os_str().encode_wide().collect();
+		if path_wide.contains(&0) {
+			return Err(WindowsInjectError::InvalidPath);
+		}
+		path_wide.push(0);
+
+		let kernel32: Vec<u16> = "kernel32.dll\0".encode_utf16().collect();
+		let kernel32 = GetModuleHandleW(kernel32.as_ptr());
+		if kernel32.is_null() {
+			return Err(WindowsInjectError::SymbolNotFound(
+				std::io::Error::last_os_error(),
+			));
+		}
+		let load_library = GetProcAddress(kernel32, c"LoadLibraryW".as_ptr().cast());
+		let load_library = match load_library {
+			None => {
+				return Err(WindowsInjectError::SymbolNotFound(
+					std::io::Error::last_os_error(),
+				))
+			}
+			Some(function) => std::mem::transmute::<
+				unsafe extern "system" fn() -> isize,
+				LPTHREAD_START_ROUTINE,
+			>(function),
+		};
+
+		// the access handle is not opened with the right to create threads
+		let process = OwnedHandle::open_process(
+			self.pid,
+			PROCESS_CREATE_THREAD
+				| PROCESS_QUERY_INFORMATION
+				| PROCESS_VM_OPERATION
+				| PROCESS_VM_READ
+				| PROCESS_VM_WRITE,
+		)
+		.map_err(WindowsInjectError::OpenProcess)?;
+
+		let path_bytes: Vec<u8> = path_wide.iter().flat_map(|ch| ch.to_ne_bytes()).collect();
+		let argument = self.allocate(
+			path_bytes.len() as u64,
+			MemoryPagePermissions::new(true, true, false, false),
+		)?;
+		let result = (|| {
+			self.write(argument, &path_bytes)?;
+
+			let thread = CreateRemoteThread(
+				process.get(),
+				std::ptr::null(),
+				0,
+				load_library,
+				argument.get() as usize as *const _,
+				0,
+				std::ptr::null_mut(),
+			);
+			if thread.is_null() {
+				return Err(WindowsInjectError::CreateThread(
+					std::io::Error::last_os_error(),
+				));
+			}
+			let thread = OwnedHandle::from_raw(thread);
+
+			if WaitForSingleObject(thread.get(), INFINITE) == WAIT_FAILED {
+				return Err(WindowsInjectError::Wait(std::io::Error::last_os_error()));
+			}
+			let mut exit_code: u32 = 0;
+			if GetExitCodeThread(thread.get(), &mut exit_code) == 0 {
+				return Err(WindowsInjectError::Wait(std::io::Error::last_os_error()));
+			}
+
+			Ok(exit_code)
+		})();
+		// the path is no longer needed once the thread has finished
+		let free_result = self.free(argument);
+		let exit_code = result?;
+		free_result?;
+
+		if exit_code == 0 {
+			return Err(WindowsInjectError::LoadLibrary);
+		}
+
+		// the exit code only holds the lower half of the module handle
+		let file_name = path
+			.file_name()
+			.map(|name| name.to_string_lossy().to_lowercase());
+		ModuleInfo::list(self.pid)
+			.map_err(WindowsInjectError::Modules)?
+			.into_iter()
+			.find(|module| {
+				module.base as u32 == exit_code
+					&& file_name
+						.as_deref()
+						.is_none_or(|name| module.name.to_lowercase() == name)
+			})
+			.map(|module| module.base)
+			.ok_or_else(|| WindowsInjectError::LibraryNotLoaded(path.to_path_buf()))
+	}
+}
+impl MemoryAccess for WindowsAccess {
+	unsafe fn read(&mut self, offset: OffsetType, buffer: &mut [u8]) -> Result<(), ReadError> {
+		let mut read_len: usize = 0;
+		let res = ReadProcessMemory(
+			self.process.get(),
+			offset.get() as usize as *const _,
+			buffer.as_mut_ptr().cast(),
+			buffer.len(),
+			&mut read_len,
+		);
+
+		// reads crossing into an inaccessible page fail with ERROR_PARTIAL_COPY
+		if res == 0 {
+			return Err(ReadError::Io(std::io::Error::last_os_error()));
+		}
+		debug_assert_eq!(read_len, buffer.len());
+
+		Ok(())
+	}
+
+	unsafe fn write(&mut self, offset: OffsetType, data: &[u8]) -> Result<(), WriteError> {
+		let mut written_len: usize = 0;
+		let res = WriteProcessMemory(
+			self.process.get(),
+			offset.get() as usize as *const _,
+			data.as_ptr().cast(),
+			data.len(),
+			&mut written_len,
+		);
+
+		if res == 0 {
+			return Err(WriteError::Io(std::io::Error::last_os_error()));
+		}
+		debug_assert_eq!(written_len, data.len());
+
+		Ok(())
+	}
+}
+impl MemoryAllocate for WindowsAccess {
+	unsafe fn allocate(
+		&mut self,
+		size: u64,
+		permissions: MemoryPagePermissions,
+	) -> Result<OffsetType, AllocateError> {
+		let size = Allocations::round_to_pages(size, Self::ALLOCATION_GRANULARITY)?;
+
+		let address = VirtualAllocEx(
+			self.process.get(),
+			std::ptr::null(),
+			size as usize,
+			MEM_COMMIT | MEM_RESERVE,
+			Self::protection(permissions),
+		);
+		if address.is_null() {
+			return Err(AllocateError::Io(std::io::Error::last_os_error()));
+		}
+
+		let offset = OffsetType::new_unwrap(address as u64);
+		self.allocations.insert(offset, size);
+
+		Ok(offset)
+	}
+
+	unsafe fn free(&mut self, offset: OffsetType) -> Result<(), AllocateError> {
+		self.allocations.get(offset)?;
+
+		let res = VirtualFreeEx(
+			self.process.get(),
+			offset.get() as usize as *mut _,
+			0,
+			MEM_RELEASE,
+		);
+		if res == 0 {
+			return Err(AllocateError::Io(std::io::Error::last_os_error()));
+		}
+		self.allocations.remove(offset);
+
+		Ok(())
+	}
+}
+impl MemoryProtect for WindowsAccess {
+	unsafe fn protect(
+		&mut self,
+		range: [OffsetType; 2],
+		permissions: MemoryPagePermissions,
+	) -> Result<(), ProtectError> {
+		let [start, end] = range;
+		if end <= start {
+			return Err(ProtectError::EmptyRange);
+		}
+
+		// the range is extended to whole pages by the system
+		let mut old_protection: PAGE_PROTECTION_FLAGS = 0;
+		let res = VirtualProtectEx(
+			self.process.get(),
+			start.get() as usize as *const _,
+			(end.get() - start.get()) as usize,
+			Self::protection(permissions),
+			&mut old_protection,
+		);
+		if res == 0 {
+			return Err(ProtectError::Io(std::io::Error::last_os_error()));
+		}
+
+		Ok(())
+	}
+}
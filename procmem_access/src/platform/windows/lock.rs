@@ -0,0 +1,257 @@
+use thiserror::Error;
+
+use windows_sys::Win32::{
+	Foundation::HANDLE,
+	System::Threading::{
+		ResumeThread, SuspendThread, PROCESS_SUSPEND_RESUME, THREAD_SUSPEND_RESUME,
+	},
+};
+
+use crate::{
+	error::{impl_from_kinded_error, ErrorKind},
+	memory::lock::{LockError, MemoryLock, UnlockError},
+};
+
+use super::{OwnedHandle, ThreadInfo};
+
+#[link(name = "ntdll")]
+extern "system" {
+	fn NtSuspendProcess(process: HANDLE) -> i32;
+	fn NtResumeProcess(process: HANDLE) -> i32;
+	fn RtlNtStatusToDosError(status: i32) -> u32;
+}
+
+/// Converts a failed `NTSTATUS` to the corresponding Win32 error.
+fn status_error(status: i32) -> std::io::Error {
+	let code = unsafe { RtlNtStatusToDosError(status) };
+
+	std::io::Error::from_raw_os_error(code as i32)
+}
+
+#[derive(Debug, Error)]
+pub enum WindowsLockError {
+	#[error("could not open process")]
+	OpenProcess(#[source] std::io::Error),
+	#[error("could not open thread")]
+	OpenThread(#[source] std::io::Error),
+	#[error("suspending process failed")]
+	Suspend(#[source] std::io::Error),
+	#[error("resuming process failed")]
+	Resume(#[source] std::io::Error),
+}
+impl WindowsLockError {
+	pub fn kind(&self) -> ErrorKind {
+		ErrorKind::from_error(self)
+	}
+}
+impl_from_kinded_error!(WindowsLockError);
+impl From<WindowsLockError> for LockError {
+	fn from(err: WindowsLockError) -> Self {
+		LockError::PlatformError(Box::new(err))
+	}
+}
+impl From<WindowsLockError> for UnlockError {
+	fn from(err: WindowsLockError) -> Self {
+		UnlockError::PlatformError(Box::new(err))
+	}
+}
+
+/// Lock which suspends all threads of the process with `NtSuspendProcess`.
+///
+/// Unlike ptrace this does not attach a debugger, so it does not conflict with one attached to the process.
+pub struct WindowsLock {
+	process: OwnedHandle,
+	lock_counter: usize,
+}
+impl WindowsLock {
+	pub fn new(pid: u32) -> Result<Self, WindowsLockError> {
+		let process = OwnedHandle::open_process(pid, PROCESS_SUSPEND_RESUME)
+			.map_err(WindowsLockError::OpenProcess)?;
+
+		Ok(WindowsLock {
+			process,
+			lock_counter: 0,
+		})
+	}
+
+	fn suspend(&mut self) -> Result<(), WindowsLockError> {
+		let status = unsafe { NtSuspendProcess(self.process.get()) };
+		if status < 0 {
+			return Err(WindowsLockError::Suspend(status_error(status)));
+		}
+
+		Ok(())
+	}
+
+	fn resume(&mut self) -> Result<(), WindowsLockError> {
+		let status = unsafe { NtResumeProcess(self.process.get()) };
+		if status < 0 {
+			return Err(WindowsLockError::Resume(status_error(status)));
+		}
+
+		Ok(())
+	}
+}
+impl MemoryLock for WindowsLock {
+	fn lock(&mut self) -> Result<bool, LockError> {
+		if self.lock_counter == 0 {
+			self.suspend()?;
+			self.lock_counter = 1;
+
+			Ok(true)
+		} else if self.lock_counter == usize::MAX {
+			Err(LockError::AlreadyLocked)
+		} else {
+			self.lock_counter += 1;
+
+			Ok(false)
+		}
+	}
+
+	fn lock_exlusive(&mut self) -> Result<(), LockError> {
+		if self.lock_counter == 0 {
+			self.lock()?;
+			self.lock_counter = usize::MAX;
+
+			Ok(())
+		} else {
+			Err(LockError::AlreadyLocked)
+		}
+	}
+
+	fn unlock(&mut self) -> Result<bool, UnlockError> {
+		if self.lock_counter == 0 {
+			return Err(UnlockError::NotLocked);
+		}
+
+		if self.lock_counter == 1 || self.lock_counter == usize::MAX {
+			self.resume()?;
+			self.lock_counter = 0;
+
+			Ok(true)
+		} else {
+			self.lock_counter -= 1;
+
+			Ok(false)
+		}
+	}
+}
+impl Drop for WindowsLock {
+	fn drop(&mut self) {
+		if self.lock_counter != 0 {
+			let _ = self.resume();
+		}
+	}
+}
+
+/// Lock which suspends only the selected threads of a process with `SuspendThread`.
+///
+/// Memory can still change while locked, because the remaining threads keep running.
+pub struct WindowsThreadLock {
+	threads: Vec<OwnedHandle>,
+	lock_counter: usize,
+}
+impl WindowsThreadLock {
+	/// Opens threads `tids` of process `pid`, see [`ThreadInfo::list`].
+	pub fn new(pid: u32, tids: &[u32]) -> Result<Self, WindowsLockError> {
+		let owned = ThreadInfo::list(pid).map_err(WindowsLockError::OpenThread)?;
+
+		let threads = tids
+			.iter()
+			.map(|&tid| {
+				if !owned.iter().any(|thread| thread.tid == tid) {
+					return Err(WindowsLockError::OpenThread(std::io::Error::new(
+						std::io::ErrorKind::NotFound,
+						"thread does not belong to the process",
+					)));
+				}
+
+				OwnedHandle::open_thread(tid, THREAD_SUSPEND_RESUME)
+					.map_err(WindowsLockError::OpenThread)
+			})
+			.collect::<Result<Vec<_>, _>>()?;
+
+		Ok(WindowsThreadLock {
+			threads,
+			lock_counter: 0,
+		})
+	}
+
+	fn suspend(&mut self) -> Result<(), WindowsLockError> {
+		for (index, thread) in self.threads.iter().enumerate() {
+			if unsafe { SuspendThread(thread.get()) } == u32::MAX {
+				let err = std::io::Error::last_os_error();
+				// resume the threads suspended so far
+				for thread in self.threads[..index].iter() {
+					unsafe { ResumeThread(thread.get()) };
+				}
+
+				return Err(WindowsLockError::Suspend(err));
+			}
+		}
+
+		Ok(())
+	}
+
+	fn resume(&mut self) -> Result<(), WindowsLockError> {
+		let mut result = Ok(());
+		for thread in self.threads.iter() {
+			if unsafe { ResumeThread(thread.get()) } == u32::MAX {
+				result = Err(WindowsLockError::Resume(std::io::Error::last_os_error()));
+			}
+		}
+
+		result
+	}
+}
+impl MemoryLock for WindowsThreadLock {
+	fn lock(&mut self) -> Result<bool, LockError> {
+		if self.lock_counter == 0 {
+			self.suspend()?;
+			self.lock_counter = 1;
+
+			Ok(true)
+		} else if self.lock_counter == usize::MAX {
+			Err(LockError::AlreadyLocked)
+		} else {
+			self.lock_counter += 1;
+
+			Ok(false)
+		}
+	}
+
+	fn lock_exlusive(&mut self) -> Result<(), LockError> {
+		if self.lock_counter == 0 {
+			self.lock()?;
+			self.lock_counter = usize::MAX;
+
+			Ok(())
+		} else {
+			Err(LockError::AlreadyLocked)
+		}
+	}
+
+	fn unlock(&mut self) -> Result<bool, UnlockError> {
+		if self.lock_counter == 0 {
+			return Err(UnlockError::NotLocked);
+		}
+
+		if self.lock_counter == 1 || self.lock_counter == usize::MAX {
+			self.resume()?;
+			self.lock_counter = 0;
+
+			Ok(true)
+		} else {
+			self.lock_counter -= 1;
+
+			Ok(false)
+		}
+	}
+}
+impl Drop for WindowsThreadLock {
+	fn drop(&mut self) {
+		if self.lock_counter != 0 {
+			let _ = self.resume();
+		}
+	}
+}
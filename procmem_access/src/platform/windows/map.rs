@@ -0,0 +1,182 @@
+use std::path::PathBuf;
+
+use thiserror::Error;
+
+use windows_sys::Win32::System::{
+	Memory::{
+		VirtualQueryEx, MEMORY_BASIC_INFORMATION, MEM_COMMIT, MEM_IMAGE, MEM_MAPPED, PAGE_EXECUTE,
+		PAGE_EXECUTE_READ, PAGE_EXECUTE_READWRITE, PAGE_EXECUTE_WRITECOPY, PAGE_GUARD,
+		PAGE_NOACCESS, PAGE_READONLY, PAGE_READWRITE, PAGE_WRITECOPY,
+	},
+	ProcessStatus::GetMappedFileNameW,
+	Threading::{
+		QueryFullProcessImageNameW, PROCESS_NAME_NATIVE, PROCESS_QUERY_INFORMATION, PROCESS_VM_READ,
+	},
+};
+
+use crate::{
+	common::OffsetType,
+	error::{impl_from_kinded_error, ErrorKind},
+	memory::map::{MemoryMap, MemoryPage, MemoryPagePermissions, MemoryPageType},
+};
+
+use super::OwnedHandle;
+
+/// Maximum length of the paths of mapped files, in UTF-16 units.
+const PATH_BUFFER_LEN: usize = 1024;
+
+#[derive(Debug, Error)]
+pub enum WindowsMemoryMapError {
+	#[error("could not open process")]
+	OpenProcess(#[source] std::io::Error),
+}
+impl WindowsMemoryMapError {
+	pub fn kind(&self) -> ErrorKind {
+		match self {
+			WindowsMemoryMapError::OpenProcess(err) => ErrorKind::from_io(err),
+		}
+	}
+}
+impl_from_kinded_error!(WindowsMemoryMapError);
+
+/// Memory map of committed regions enumerated with `VirtualQueryEx`.
+///
+/// Windows does not label stack and heap regions, so private regions are reported as [`MemoryPageType::Anon`].
+/// Paths of mapped files and images are native NT paths such as `\Device\HarddiskVolume3\Windows\System32\ntdll.dll`.
+pub struct WindowsMemoryMap {
+	pages: Vec<MemoryPage>,
+}
+impl WindowsMemoryMap {
+	pub fn new(pid: u32) -> Result<Self, WindowsMemoryMapError> {
+		let process = OwnedHandle::open_process(pid, PROCESS_QUERY_INFORMATION | PROCESS_VM_READ)
+			.map_err(WindowsMemoryMapError::OpenProcess)?;
+		let exe_path = Self::executable_path(&process);
+
+		let mut pages = Vec::new();
+
+		let mut address: usize = 0;
+		loop {
+			let mut info: MEMORY_BASIC_INFORMATION = Default::default();
+			let written = unsafe {
+				VirtualQueryEx(
+					process.get(),
+					address as *const _,
+					&mut info,
+					std::mem::size_of::<MEMORY_BASIC_INFORMATION>(),
+				)
+			};
+			if written == 0 {
+				break;
+			}
+
+			let base = info.BaseAddress as usize;
+			if info.State == MEM_COMMIT {
+				if let Some(page) = Self::page_from_info(&process, &info, exe_path.as_ref()) {
+					pages.push(page);
+				}
+			}
+
+			address = match base.checked_add(info.RegionSize) {
+				Some(next) if next > address => next,
+				_ => break,
+			};
+		}
+
+		Ok(WindowsMemoryMap { pages })
+	}
+
+	fn page_from_info(
+		process: &OwnedHandle,
+		info: &MEMORY_BASIC_INFORMATION,
+		exe_path: Option<&PathBuf>,
+	) -> Option<MemoryPage> {
+		let start = OffsetType::new(info.BaseAddress as u64)?;
+		let end = OffsetType::new(start.get() + info.RegionSize as u64)?;
+
+		let is_file = info.Type == MEM_IMAGE || info.Type == MEM_MAPPED;
+		let page_type = match Self::mapped_file_name(process, info.BaseAddress) {
+			Some(path) if is_file && Some(&path) == exe_path => {
+				MemoryPageType::ProcessExecutable(path)
+			}
+			Some(path) if is_file => MemoryPageType::File(path),
+			_ => MemoryPageType::Anon,
+		};
+		let offset = if is_file {
+			(info.BaseAddress as u64).saturating_sub(info.AllocationBase as u64)
+		} else {
+			0
+		};
+
+		Some(MemoryPage {
+			address_range: [start, end],
+			permissions: Self::permissions(info.Protect, info.Type == MEM_MAPPED),
+			offset,
+			page_type,
+		})
+	}
+
+	fn permissions(protect: u32, mapped: bool) -> MemoryPagePermissions {
+		if protect & PAGE_GUARD != 0 || protect & PAGE_NOACCESS != 0 {
+			return MemoryPagePermissions::new(false, false, false, false);
+		}
+
+		// the low byte holds the access, the rest are modifiers such as caching
+		let (read, write, exec, copy_on_write) = match protect & 0xFF {
+			PAGE_READONLY => (true, false, false, false),
+			PAGE_READWRITE => (true, true, false, false),
+			PAGE_WRITECOPY => (true, true, false, true),
+			PAGE_EXECUTE => (false, false, true, false),
+			PAGE_EXECUTE_READ => (true, false, true, false),
+			PAGE_EXECUTE_READWRITE => (true, true, true, false),
+			PAGE_EXECUTE_WRITECOPY => (true, true, true, true),
+			_ => (false, false, false, false),
+		};
+
+		MemoryPagePermissions::new(read, write, exec, mapped && !copy_on_write)
+	}
+
+	fn mapped_file_name(process: &OwnedHandle, address: *mut core::ffi::c_void) -> Option<PathBuf> {
+		let mut buffer = [0u16; PATH_BUFFER_LEN];
+		let len = unsafe {
+			GetMappedFileNameW(
+				process.get(),
+				address,
+				buffer.as_mut_ptr(),
+				buffer.len() as u32,
+			)
+		};
+		if len == 0 {
+			return None;
+		}
+
+		Some(PathBuf::from(String::from_utf16_lossy(
+			&buffer[..len as usize],
+		)))
+	}
+
+	/// Returns the native path of the process executable, in the same format as [`mapped_file_name`](Self::mapped_file_name).
+	fn executable_path(process: &OwnedHandle) -> Option<PathBuf> {
+		let mut buffer = [0u16; PATH_BUFFER_LEN];
+		let mut len = buffer.len() as u32;
+		let res = unsafe {
+			QueryFullProcessImageNameW(
+				process.get(),
+				PROCESS_NAME_NATIVE,
+				buffer.as_mut_ptr(),
+				&mut len,
+			)
+		};
+		if res == 0 {
+			return None;
+		}
+
+		Some(PathBuf::from(String::from_utf16_lossy(
+			&buffer[..len as usize],
+		)))
+	}
+}
+impl MemoryMap for WindowsMemoryMap {
+	fn pages(&self) -> &[MemoryPage] {
+		&self.pages
+	}
+}
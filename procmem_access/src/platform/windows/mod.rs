@@ -0,0 +1,212 @@
+//! Windows backend using the process and virtual memory APIs of kernel32.
+
+use windows_sys::Win32::{
+	Foundation::{CloseHandle, HANDLE, INVALID_HANDLE_VALUE},
+	System::{
+		Diagnostics::ToolHelp::{
+			CreateToolhelp32Snapshot, Module32FirstW, Module32NextW, Process32FirstW,
+			Process32NextW, Thread32First, Thread32Next, MODULEENTRY32W, PROCESSENTRY32W,
+			TH32CS_SNAPMODULE, TH32CS_SNAPMODULE32, TH32CS_SNAPPROCESS, TH32CS_SNAPTHREAD,
+			THREADENTRY32,
+		},
+		Threading::{OpenProcess, OpenThread, PROCESS_ACCESS_RIGHTS, THREAD_ACCESS_RIGHTS},
+	},
+};
+
+use crate::common::{select_one, FindError, NamePattern};
+
+pub mod access;
+pub mod lock;
+pub mod map;
+
+pub use access::WindowsAccess;
+pub use lock::{WindowsLock, WindowsThreadLock};
+pub use map::WindowsMemoryMap;
+
+/// Owned handle which is closed on drop.
+#[derive(Debug)]
+pub struct OwnedHandle(HANDLE);
+impl OwnedHandle {
+	/// Opens process `pid` with `access` rights.
+	pub fn open_process(pid: u32, access: PROCESS_ACCESS_RIGHTS) -> std::io::Result<Self> {
+		let handle = unsafe { OpenProcess(access, 0, pid) };
+		if handle.is_null() {
+			return Err(std::io::Error::last_os_error());
+		}
+
+		Ok(OwnedHandle(handle))
+	}
+
+	/// Opens thread `tid` with `access` rights.
+	pub fn open_thread(tid: u32, access: THREAD_ACCESS_RIGHTS) -> std::io::Result<Self> {
+		let handle = unsafe { OpenThread(access, 0, tid) };
+		if handle.is_null() {
+			return Err(std::io::Error::last_os_error());
+		}
+
+		Ok(OwnedHandle(handle))
+	}
+
+	/// ## Safety
+	/// * `handle` must be a valid handle that needs to be closed on drop.
+	unsafe fn from_raw(handle: HANDLE) -> Self {
+		OwnedHandle(handle)
+	}
+
+	pub const fn get(&self) -> HANDLE {
+		self.0
+	}
+}
+impl Drop for OwnedHandle {
+	fn drop(&mut self) {
+		let result = unsafe { CloseHandle(self.0) };
+
+		debug_assert_ne!(result, 0);
+	}
+}
+// handles are process-wide and can be used from any thread
+unsafe impl Send for OwnedHandle {}
+
+#[derive(Debug, Clone)]
+pub struct ProcessInfo {
+	pub pid: u32,
+	pub name: String,
+}
+impl std::fmt::Display for ProcessInfo {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		write!(f, "{} ({})", self.pid, self.name)
+	}
+}
+impl ProcessInfo {
+	pub fn list_all() -> std::io::Result<Vec<Self>> {
+		let snapshot = unsafe { CreateToolhelp32Snapshot(TH32CS_SNAPPROCESS, 0) };
+		if snapshot == INVALID_HANDLE_VALUE {
+			return Err(std::io::Error::last_os_error());
+		}
+		let snapshot = unsafe { OwnedHandle::from_raw(snapshot) };
+
+		let mut processes = Vec::new();
+
+		let mut entry = PROCESSENTRY32W {
+			dwSize: std::mem::size_of::<PROCESSENTRY32W>() as u32,
+			..Default::default()
+		};
+		let mut found = unsafe { Process32FirstW(snapshot.get(), &mut entry) };
+		while found != 0 {
+			let name_len = entry
+				.szExeFile
+				.iter()
+				.position(|&ch| ch == 0)
+				.unwrap_or(entry.szExeFile.len());
+
+			processes.push(ProcessInfo {
+				pid: entry.th32ProcessID,
+				name: String::from_utf16_lossy(&entry.szExeFile[..name_len]),
+			});
+
+			found = unsafe { Process32NextW(snapshot.get(), &mut entry) };
+		}
+
+		Ok(processes)
+	}
+
+	/// Lists all processes accepted by `predicate`.
+	pub fn list_filtered(predicate: impl FnMut(&Self) -> bool) -> std::io::Result<Vec<Self>> {
+		Ok(Self::list_all()?.into_iter().filter(predicate).collect())
+	}
+
+	/// Lists all processes with names matching `pattern`.
+	pub fn find_by_name(pattern: NamePattern) -> std::io::Result<Vec<Self>> {
+		Self::list_filtered(|process| pattern.matches(&process.name))
+	}
+
+	/// Finds the one process with name matching `pattern`, preferring an exact name match when more processes match.
+	pub fn find_one_by_name(pattern: NamePattern) -> Result<Self, FindError<Self>> {
+		select_one(pattern, Self::find_by_name(pattern)?, |process| {
+			&process.name
+		})
+	}
+
+	pub fn for_pid(pid: u32) -> std::io::Result<Self> {
+		Self::list_filtered(|process| process.pid == pid)?
+			.pop()
+			.ok_or_else(|| std::io::Error::new(std::io::ErrorKind::NotFound, "no such process"))
+	}
+}
+
+/// Thread of a process, listed from a Toolhelp32 snapshot.
+pub struct ThreadInfo {
+	pub tid: u32,
+}
+impl ThreadInfo {
+	/// Lists all threads of process `pid`.
+	pub fn list(pid: u32) -> std::io::Result<Vec<Self>> {
+		// the snapshot always contains threads of all processes
+		let snapshot = unsafe { CreateToolhelp32Snapshot(TH32CS_SNAPTHREAD, 0) };
+		if snapshot == INVALID_HANDLE_VALUE {
+			return Err(std::io::Error::last_os_error());
+		}
+		let snapshot = unsafe { OwnedHandle::from_raw(snapshot) };
+
+		let mut threads = Vec::new();
+
+		let mut entry = THREADENTRY32 {
+			dwSize: std::mem::size_of::<THREADENTRY32>() as u32,
+			..Default::default()
+		};
+		let mut found = unsafe { Thread32First(snapshot.get(), &mut entry) };
+		while found != 0 {
+			if entry.th32OwnerProcessID == pid {
+				threads.push(ThreadInfo {
+					tid: entry.th32ThreadID,
+				});
+			}
+
+			found = unsafe { Thread32Next(snapshot.get(), &mut entry) };
+		}
+
+		Ok(threads)
+	}
+}
+
+/// Module loaded in a process, listed from a Toolhelp32 snapshot.
+pub struct ModuleInfo {
+	/// Base address of the module, which is also its `HMODULE` in the process.
+	pub base: u64,
+	pub name: String,
+}
+impl ModuleInfo {
+	/// Lists all modules of process `pid`.
+	pub fn list(pid: u32) -> std::io::Result<Vec<Self>> {
+		let snapshot =
+			unsafe { CreateToolhelp32Snapshot(TH32CS_SNAPMODULE | TH32CS_SNAPMODULE32, pid) };
+		if snapshot == INVALID_HANDLE_VALUE {
+			return Err(std::io::Error::last_os_error());
+		}
+		let snapshot = unsafe { OwnedHandle::from_raw(snapshot) };
+
+		let mut modules = Vec::new();
+
+		let mut entry = MODULEENTRY32W {
+			dwSize: std::mem::size_of::<MODULEENTRY32W>() as u32,
+			..Default::default()
+		};
+		let mut found = unsafe { Module32FirstW(snapshot.get(), &mut entry) };
+		while found != 0 {
+			let name_len = entry
+				.szModule
+				.iter()
+				.position(|&ch| ch == 0)
+				.unwrap_or(entry.szModule.len());
+
+			modules.push(ModuleInfo {
+				base: entry.modBaseAddr as u64,
+				name: String::from_utf16_lossy(&entry.szModule[..name_len]),
+			});
+
+			found = unsafe { Module32NextW(snapshot.get(), &mut entry) };
+		}
+
+		Ok(modules)
+	}
+}
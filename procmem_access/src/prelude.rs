@@ -1,8 +1,11 @@
 pub use crate::{
-	common::OffsetType,
+	common::{Endianness, OffsetType},
+	error::{ErrorKind, ProcmemError},
 	memory::{
 		access::MemoryAccess,
+		allocate::MemoryAllocate,
 		lock::MemoryLock,
-		map::{MemoryMap, MemoryPage, MemoryPagePermissions, MemoryPageType},
+		map::{MemoryMap, MemoryPage, MemoryPagePermissions, MemoryPageStats, MemoryPageType},
+		protect::MemoryProtect,
 	},
 };
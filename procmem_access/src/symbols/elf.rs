@@ -0,0 +1,117 @@
+//! Symbols from the `SHT_SYMTAB` and `SHT_DYNSYM` sections of ELF files.
+
+use crate::common::Endianness;
+
+use super::{BinaryReader, Symbol, SymbolError};
+
+pub const ELF_MAGIC: &[u8] = b"\x7fELF";
+
+const ELFCLASS64: u8 = 2;
+const ELFDATA2MSB: u8 = 2;
+const PT_LOAD: u32 = 1;
+const SHT_SYMTAB: u32 = 2;
+const SHT_DYNSYM: u32 = 11;
+const SHN_UNDEF: u16 = 0;
+const STT_OBJECT: u8 = 1;
+const STT_FUNC: u8 = 2;
+/// Mappings start at page boundaries, the smallest page size is used so that the base is never above the mapping.
+const PAGE_SIZE: u64 = 0x1000;
+
+/// Parses the defined function and object symbols, relative to the lowest loaded address.
+pub fn parse_symbols(bytes: &[u8]) -> Result<Vec<Symbol>, SymbolError> {
+	let is_64 = bytes.get(4) == Some(&ELFCLASS64);
+	let reader = BinaryReader {
+		bytes,
+		endianness: if bytes.get(5) == Some(&ELFDATA2MSB) {
+			Endianness::Big
+		} else {
+			Endianness::Little
+		},
+	};
+	let word = |at: u64, at_32: u64| -> Result<u64, SymbolError> {
+		if is_64 {
+			reader.u64(at)
+		} else {
+			reader.u32(at_32).map(u64::from)
+		}
+	};
+
+	let (phoff, shoff) = (word(32, 28)?, word(40, 32)?);
+	let [phentsize, phnum, shentsize, shnum] = if is_64 {
+		[54, 56, 58, 60]
+	} else {
+		[42, 44, 46, 48]
+	}
+	.map(|at| reader.u16(at).map(u64::from));
+	let (phentsize, phnum, shentsize, shnum) = (phentsize?, phnum?, shentsize?, shnum?);
+
+	// non-PIE executables are linked at a fixed address, which is the base of the module
+	let mut base = None::<u64>;
+	for index in 0..phnum {
+		let header = phoff + index * phentsize;
+		if reader.u32(header)? == PT_LOAD {
+			let vaddr = word(header + 16, header + 8)? & !(PAGE_SIZE - 1);
+			base = Some(base.map_or(vaddr, |base| base.min(vaddr)));
+		}
+	}
+	let base = base.unwrap_or(0);
+
+	let mut symbols = Vec::new();
+	for index in 0..shnum {
+		let header = shoff + index * shentsize;
+		let section_type = reader.u32(header + 4)?;
+		if section_type != SHT_SYMTAB && section_type != SHT_DYNSYM {
+			continue;
+		}
+
+		let offset = word(header + 24, header + 16)?;
+		let size = word(header + 32, header + 20)?;
+		let link = reader.u32(header + if is_64 { 40 } else { 24 })? as u64;
+		let entsize = word(header + 56, header + 36)?;
+		if entsize == 0 {
+			return Err(SymbolError::Corrupted("symbol entry size is zero"));
+		}
+
+		let strings_header = shoff + link * shentsize;
+		let strings = word(strings_header + 24, strings_header + 16)?;
+
+		for entry in (0..size / entsize).map(|index| offset + index * entsize) {
+			let (info, shndx, value, size) = if is_64 {
+				(
+					reader.u8(entry + 4)?,
+					reader.u16(entry + 6)?,
+					reader.u64(entry + 8)?,
+					reader.u64(entry + 16)?,
+				)
+			} else {
+				(
+					reader.u8(entry + 12)?,
+					reader.u16(entry + 14)?,
+					reader.u32(entry + 4)? as u64,
+					reader.u32(entry + 8)? as u64,
+				)
+			};
+
+			let symbol_type = info & 0xf;
+			if (symbol_type != STT_FUNC && symbol_type != STT_OBJECT)
+				|| shndx == SHN_UNDEF
+				|| value < base
+			{
+				continue;
+			}
+
+			let name = reader.string(strings + reader.u32(entry)? as u64)?;
+			if name.is_empty() {
+				continue;
+			}
+
+			symbols.push(Symbol {
+				name,
+				rva: value - base,
+				size,
+			});
+		}
+	}
+
+	Ok(symbols)
+}
@@ -0,0 +1,144 @@
+//! Symbols from the `LC_SYMTAB` command of 64-bit Mach-O files.
+
+use crate::common::Endianness;
+
+use super::{BinaryReader, Symbol, SymbolError};
+
+const MH_MAGIC_64: u32 = 0xfeedfacf;
+const LC_SYMTAB: u32 = 0x2;
+const LC_SEGMENT_64: u32 = 0x19;
+const N_STAB: u8 = 0xe0;
+const N_TYPE: u8 = 0x0e;
+const N_SECT: u8 = 0x0e;
+const HEADER_SIZE: u64 = 32;
+const NLIST_SIZE: u64 = 16;
+
+pub fn is_macho(magic: &[u8]) -> bool {
+	magic == MH_MAGIC_64.to_le_bytes() || magic == MH_MAGIC_64.to_be_bytes()
+}
+
+/// Parses the symbols defined in sections, relative to the `__TEXT` segment.
+///
+/// Mach-O does not record symbol sizes, each symbol is assumed to extend to the next one.
+/// The leading underscore which the compilers add to C names is removed.
+pub fn parse_symbols(bytes: &[u8]) -> Result<Vec<Symbol>, SymbolError> {
+	let reader = BinaryReader {
+		bytes,
+		endianness: if bytes.get(..4) == Some(&MH_MAGIC_64.to_le_bytes()) {
+			Endianness::Little
+		} else {
+			Endianness::Big
+		},
+	};
+
+	let command_count = reader.u32(16)?;
+	let mut base = 0;
+	let mut symtab = None;
+
+	let mut command = HEADER_SIZE;
+	for _ in 0..command_count {
+		let command_size = reader.u32(command + 4)? as u64;
+		match reader.u32(command)? {
+			// the segment mapped from the start of the file is the base of the module
+			LC_SEGMENT_64 if reader.u64(command + 40)? == 0 && reader.u64(command + 48)? != 0 => {
+				base = reader.u64(command + 24)?;
+			}
+			LC_SYMTAB => {
+				symtab = Some([
+					reader.u32(command + 8)? as u64,
+					reader.u32(command + 12)? as u64,
+					reader.u32(command + 16)? as u64,
+				]);
+			}
+			_ => (),
+		}
+
+		if command_size == 0 {
+			return Err(SymbolError::Corrupted("load command size is zero"));
+		}
+		command += command_size;
+	}
+
+	let Some([symbol_offset, symbol_count, strings]) = symtab else {
+		return Ok(Vec::new());
+	};
+
+	let mut symbols = Vec::new();
+	for entry in (0..symbol_count).map(|index| symbol_offset + index * NLIST_SIZE) {
+		let symbol_type = reader.u8(entry + 4)?;
+		let value = reader.u64(entry + 8)?;
+		if symbol_type & N_STAB != 0 || symbol_type & N_TYPE != N_SECT || value < base {
+			continue;
+		}
+
+		let name = reader.string(strings + reader.u32(entry)? as u64)?;
+		let name = name.strip_prefix('_').unwrap_or(&name);
+		if name.is_empty() {
+			continue;
+		}
+
+		symbols.push(Symbol {
+			name: name.to_string(),
+			rva: value - base,
+			size: 0,
+		});
+	}
+
+	// symbols extend to the next one
+	symbols.sort_by_key(|symbol| symbol.rva);
+	let next_rvas: Vec<u64> = symbols.iter().skip(1).map(|symbol| symbol.rva).collect();
+	for (symbol, next) in symbols.iter_mut().zip(next_rvas) {
+		symbol.size = next - symbol.rva;
+	}
+
+	Ok(symbols)
+}
+
+#[cfg(test)]
+mod test {
+	use super::super::SymbolTable;
+
+	#[test]
+	fn test_macho_symbols() {
+		let mut file = Vec::new();
+		// header: magic, cpu type, subtype, file type, command count, commands size, flags, reserved
+		for word in [0xfeedfacfu32, 0x0100000c, 0, 2, 2, 72 + 24, 0, 0] {
+			file.extend(word.to_le_bytes());
+		}
+
+		// LC_SEGMENT_64 __TEXT at 0x100000000 mapped from the start of the file
+		file.extend(0x19u32.to_le_bytes());
+		file.extend(72u32.to_le_bytes());
+		file.extend(*b"__TEXT\0\0\0\0\0\0\0\0\0\0");
+		for word in [0x1_0000_0000u64, 0x4000, 0, 0x4000] {
+			file.extend(word.to_le_bytes());
+		}
+		file.extend([0u8; 16]);
+
+		// LC_SYMTAB with three symbols after the commands, strings after them
+		let symbols_offset = file.len() as u32 + 24;
+		let strings_offset = symbols_offset + 3 * 16;
+		for word in [2u32, 24, symbols_offset, 3, strings_offset, 32] {
+			file.extend(word.to_le_bytes());
+		}
+
+		// string index, type, section, description, value
+		for (name, symbol_type, value) in [
+			(1u32, 0x0fu8, 0x1_0000_1a2bu64),
+			(16, 0x0f, 0x1_0000_1a30),
+			(22, 0x01, 0),
+		] {
+			file.extend(name.to_le_bytes());
+			file.extend([symbol_type, 1]);
+			file.extend(0u16.to_le_bytes());
+			file.extend(value.to_le_bytes());
+		}
+		file.extend(*b"\0_player_health\0_ammo\0_printf\0");
+
+		let table = SymbolTable::parse(&file).unwrap();
+		assert_eq!(table.symbols().len(), 2);
+		let (symbol, offset) = table.lookup(0x1a2d).unwrap();
+		assert_eq!((symbol.name.as_str(), offset), ("player_health", 2));
+		assert_eq!(table.lookup(0x1a30).unwrap().0.name, "ammo");
+	}
+}
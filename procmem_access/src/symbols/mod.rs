@@ -0,0 +1,282 @@
+//! Resolution of offsets to modules and symbols.
+//!
+//! A [`SymbolResolver`] turns an offset into the module containing it and the offset relative to the module base,
+//! using the file-backed pages of a [`MemoryMap`]. When the backing file can be read, the exported and local symbols of it
+//! are loaded from its ELF or Mach-O symbol tables and the nearest symbol is reported as well:
+//! ```text
+//! libgame.so+0x1a2b (player_health)
+//! ```
+//!
+//! Only files which contain symbol tables can be resolved to symbols, stripped binaries still have their dynamic symbols on ELF.
+//!
+//! A [`StableAddress`] keeps the module-relative form of an address so that it can be resolved again after the process restarts.
+
+use std::{collections::HashMap, fmt, path::Path};
+
+use thiserror::Error;
+
+use crate::{
+	common::{Endianness, OffsetType},
+	error::{impl_from_kinded_error, ErrorKind},
+	memory::map::{MemoryMap, MemoryPageType},
+};
+
+mod elf;
+mod macho;
+mod stable;
+
+pub use stable::{StableAddress, StableAddressError};
+
+#[derive(Debug, Error)]
+pub enum SymbolError {
+	#[error("file is neither ELF nor Mach-O")]
+	UnknownFormat,
+	#[error("file is corrupted: {0}")]
+	Corrupted(&'static str),
+	#[error("could not read file")]
+	Io(#[from] std::io::Error),
+}
+impl SymbolError {
+	pub fn kind(&self) -> ErrorKind {
+		match self {
+			SymbolError::Io(err) => ErrorKind::from_io(err),
+			_ => ErrorKind::Parse,
+		}
+	}
+}
+impl_from_kinded_error!(SymbolError);
+
+/// Named function or object of a module.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Symbol {
+	pub name: String,
+	/// Offset of the symbol relative to the module base.
+	pub rva: u64,
+	/// Size in bytes, zero if the file does not record it.
+	pub size: u64,
+}
+
+/// Symbols of one module sorted by their offset.
+#[derive(Debug, Clone, Default)]
+pub struct SymbolTable {
+	symbols: Vec<Symbol>,
+}
+impl SymbolTable {
+	/// Creates a table from symbols in any order.
+	pub fn new(mut symbols: Vec<Symbol>) -> Self {
+		symbols.sort_by(|a, b| a.rva.cmp(&b.rva).then_with(|| a.name.cmp(&b.name)));
+		symbols.dedup_by(|a, b| a.rva == b.rva && a.name == b.name);
+
+		SymbolTable { symbols }
+	}
+
+	/// Parses the symbol tables of an ELF or Mach-O file.
+	pub fn parse(bytes: &[u8]) -> Result<Self, SymbolError> {
+		let symbols = match bytes.get(..4) {
+			Some(elf::ELF_MAGIC) => elf::parse_symbols(bytes)?,
+			Some(magic) if macho::is_macho(magic) => macho::parse_symbols(bytes)?,
+			_ => return Err(SymbolError::UnknownFormat),
+		};
+
+		Ok(Self::new(symbols))
+	}
+
+	pub fn load(path: impl AsRef<Path>) -> Result<Self, SymbolError> {
+		Self::parse(&std::fs::read(path)?)
+	}
+
+	pub fn symbols(&self) -> &[Symbol] {
+		&self.symbols
+	}
+
+	/// Returns the symbol containing `rva` together with the offset into it.
+	///
+	/// Symbols of unknown size only contain their first byte.
+	pub fn lookup(&self, rva: u64) -> Option<(&Symbol, u64)> {
+		let index = self.symbols.partition_point(|symbol| symbol.rva <= rva);
+		let symbol = &self.symbols[index.checked_sub(1)?];
+
+		let offset = rva - symbol.rva;
+		if offset < symbol.size.max(1) {
+			Some((symbol, offset))
+		} else {
+			None
+		}
+	}
+}
+
+/// Offset resolved by a [`SymbolResolver`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResolvedOffset {
+	/// File name of the module.
+	pub module: String,
+	/// Offset relative to the module base.
+	pub rva: u64,
+	/// Name of the symbol containing the offset and the offset into the symbol.
+	pub symbol: Option<(String, u64)>,
+}
+impl fmt::Display for ResolvedOffset {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "{}+0x{:x}", self.module, self.rva)?;
+
+		match &self.symbol {
+			None => Ok(()),
+			Some((name, 0)) => write!(f, " ({})", name),
+			Some((name, offset)) => write!(f, " ({}+0x{:x})", name, offset),
+		}
+	}
+}
+
+/// Resolves offsets to modules and symbols, caching the symbol tables of the modules.
+#[derive(Debug, Default)]
+pub struct SymbolResolver {
+	/// Tables by module path, `None` if the file could not be loaded.
+	tables: HashMap<std::path::PathBuf, Option<SymbolTable>>,
+}
+impl SymbolResolver {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Resolves `offset` to the module mapped at it, see [`MemoryMap::module_offset`].
+	///
+	/// Returns `None` if the offset is not inside a file-backed page. Failing to load the symbols of the module is not an error,
+	/// the symbol is left out instead.
+	pub fn resolve<M: MemoryMap + ?Sized>(
+		&mut self,
+		map: &M,
+		offset: OffsetType,
+	) -> Option<ResolvedOffset> {
+		let page = map
+			.pages()
+			.iter()
+			.find(|page| page.start() <= offset && offset < page.end())?;
+		let path = match &page.page_type {
+			MemoryPageType::ProcessExecutable(path) | MemoryPageType::File(path) => path,
+			_ => return None,
+		};
+		let (module, rva) = map.module_offset(offset)?;
+
+		let table = self
+			.tables
+			.entry(path.clone())
+			.or_insert_with(|| SymbolTable::load(path).ok());
+		let symbol = table
+			.as_ref()
+			.and_then(|table| table.lookup(rva))
+			.map(|(symbol, offset)| (symbol.name.clone(), offset));
+
+		Some(ResolvedOffset {
+			module: module.to_string(),
+			rva,
+			symbol,
+		})
+	}
+
+	/// Formats `offset` as `module+rva (symbol)`, or as a plain hex offset if it is not inside a module.
+	pub fn describe<M: MemoryMap + ?Sized>(&mut self, map: &M, offset: OffsetType) -> String {
+		match self.resolve(map, offset) {
+			Some(resolved) => resolved.to_string(),
+			None => format!("0x{:x}", offset.get()),
+		}
+	}
+}
+
+/// Reads fields of a binary file in its byte order.
+#[derive(Debug, Clone, Copy)]
+struct BinaryReader<'a> {
+	bytes: &'a [u8],
+	endianness: Endianness,
+}
+impl<'a> BinaryReader<'a> {
+	fn field<const N: usize>(&self, at: u64) -> Result<[u8; N], SymbolError> {
+		let at = usize::try_from(at).map_err(|_| SymbolError::Corrupted("offset is too large"))?;
+		let mut field: [u8; N] = self
+			.bytes
+			.get(at..at.saturating_add(N))
+			.and_then(|field| field.try_into().ok())
+			.ok_or(SymbolError::Corrupted("structure is truncated"))?;
+		self.endianness.convert_scalar(&mut field);
+
+		Ok(field)
+	}
+
+	fn u8(&self, at: u64) -> Result<u8, SymbolError> {
+		self.field::<1>(at).map(|[byte]| byte)
+	}
+
+	fn u16(&self, at: u64) -> Result<u16, SymbolError> {
+		self.field(at).map(u16::from_ne_bytes)
+	}
+
+	fn u32(&self, at: u64) -> Result<u32, SymbolError> {
+		self.field(at).map(u32::from_ne_bytes)
+	}
+
+	fn u64(&self, at: u64) -> Result<u64, SymbolError> {
+		self.field(at).map(u64::from_ne_bytes)
+	}
+
+	/// Reads a nul-terminated string at `at`, lossily converting it to UTF-8.
+	fn string(&self, at: u64) -> Result<String, SymbolError> {
+		let rest = usize::try_from(at)
+			.ok()
+			.and_then(|at| self.bytes.get(at..))
+			.ok_or(SymbolError::Corrupted("string is out of bounds"))?;
+		let end = rest
+			.iter()
+			.position(|&byte| byte == 0)
+			.ok_or(SymbolError::Corrupted("string is not terminated"))?;
+
+		Ok(String::from_utf8_lossy(&rest[..end]).into_owned())
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::{ResolvedOffset, Symbol, SymbolTable};
+
+	#[test]
+	fn test_symbol_lookup() {
+		let symbol = |name: &str, rva: u64, size: u64| Symbol {
+			name: name.to_string(),
+			rva,
+			size,
+		};
+		let table = SymbolTable::new(vec![
+			symbol("update", 0x2000, 0x100),
+			symbol("player_health", 0x1a2b, 4),
+			symbol("marker", 0x3000, 0),
+		]);
+
+		assert_eq!(table.lookup(0x1a2b).unwrap().0.name, "player_health");
+		assert_eq!(table.lookup(0x2010).unwrap().1, 0x10);
+		assert!(table.lookup(0x1a2f).is_none());
+		assert!(table.lookup(0x1000).is_none());
+		assert_eq!(table.lookup(0x3000).unwrap().0.name, "marker");
+		assert!(table.lookup(0x3001).is_none());
+
+		let resolved = ResolvedOffset {
+			module: "libgame.so".to_string(),
+			rva: 0x1a2b,
+			symbol: Some(("player_health".to_string(), 0)),
+		};
+		assert_eq!(resolved.to_string(), "libgame.so+0x1a2b (player_health)");
+	}
+
+	#[cfg(target_os = "linux")]
+	#[test]
+	fn test_resolve_libc() {
+		use super::SymbolResolver;
+		use crate::{common::OffsetType, platform::procfs::ProcfsMemoryMap};
+
+		let map = ProcfsMemoryMap::new(std::process::id() as libc::pid_t).unwrap();
+		let offset = OffsetType::new_unwrap(libc::getpid as *const () as u64);
+
+		let resolved = SymbolResolver::new().resolve(&map, offset).unwrap();
+		assert!(resolved.module.starts_with("libc"));
+		let (name, offset) = resolved.symbol.unwrap();
+		assert!(name.contains("getpid"));
+		assert_eq!(offset, 0);
+	}
+}
@@ -0,0 +1,302 @@
+//! Addresses which survive restarts of the process.
+//!
+//! Modules are loaded at a different base on each run because of ASLR, so an absolute address is only valid until the process exits.
+//! A [`StableAddress`] stores the module name and the offset relative to its base instead, optionally followed by a pointer path
+//! for values which live on the heap, and is resolved again against the freshly attached process.
+//!
+//! The textual form follows the usual notation of pointer paths:
+//! ```text
+//! libgame.so+0x1a2b             // value inside the module
+//! [[libgame.so+0x10]+0x8]-0x4   // value reached by reading two pointers
+//! ```
+
+use std::{fmt, str::FromStr};
+
+use thiserror::Error;
+
+use crate::{
+	common::OffsetType,
+	error::{impl_from_kinded_error, ErrorKind},
+	memory::{
+		access::{MemoryAccess, ReadError},
+		map::MemoryMap,
+	},
+};
+
+#[derive(Debug, Error)]
+pub enum StableAddressError {
+	#[error("invalid stable address \"{0}\"")]
+	Invalid(String),
+	#[error("module {0} is not mapped")]
+	ModuleNotFound(String),
+	#[error("could not read pointer at {offset}")]
+	Read {
+		offset: OffsetType,
+		#[source]
+		source: ReadError,
+	},
+	#[error("pointer path resolves to null")]
+	NullPointer,
+}
+impl StableAddressError {
+	pub fn kind(&self) -> ErrorKind {
+		match self {
+			StableAddressError::Invalid(_) => ErrorKind::Parse,
+			StableAddressError::ModuleNotFound(_) => ErrorKind::NotMapped,
+			StableAddressError::Read { source, .. } => source.kind(),
+			StableAddressError::NullPointer => ErrorKind::NotMapped,
+		}
+	}
+}
+impl_from_kinded_error!(StableAddressError);
+
+/// Address relative to the base of a module, optionally followed by a pointer path.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct StableAddress {
+	/// File name of the module, see [`MemoryPageType::module_name`](crate::memory::map::MemoryPageType::module_name).
+	pub module: String,
+	/// Offset relative to the module base.
+	pub rva: u64,
+	/// Offsets added to the pointers read along the path, so `[[module + rva] + a] + b` is `offsets: [a, b]`.
+	pub offsets: Vec<i64>,
+}
+impl StableAddress {
+	pub fn new(module: impl Into<String>, rva: u64) -> Self {
+		StableAddress {
+			module: module.into(),
+			rva,
+			offsets: Vec::new(),
+		}
+	}
+
+	pub fn with_offsets(mut self, offsets: Vec<i64>) -> Self {
+		self.offsets = offsets;
+
+		self
+	}
+
+	/// Creates the address of `offset` relative to the module containing it, see [`MemoryMap::module_offset`].
+	///
+	/// Returns `None` if `offset` is not inside a module, values on the heap need a pointer path instead.
+	pub fn from_offset<M: MemoryMap + ?Sized>(map: &M, offset: OffsetType) -> Option<Self> {
+		map.module_offset(offset)
+			.map(|(module, rva)| Self::new(module, rva))
+	}
+
+	/// Resolves the address against a process with memory map `map`.
+	///
+	/// The module base is looked up in `map` and the pointers of the path are read through `access` with native width and byte order.
+	///
+	/// ## Safety
+	/// * See [`MemoryAccess::read`].
+	pub unsafe fn resolve<A: MemoryAccess + ?Sized, M: MemoryMap + ?Sized>(
+		&self,
+		access: &mut A,
+		map: &M,
+	) -> Result<OffsetType, StableAddressError> {
+		let base = map
+			.module_base(&self.module)
+			.ok_or_else(|| StableAddressError::ModuleNotFound(self.module.clone()))?;
+		let mut address = base
+			.get()
+			.checked_add(self.rva)
+			.and_then(OffsetType::new)
+			.ok_or(StableAddressError::NullPointer)?;
+
+		for &offset in self.offsets.iter() {
+			let mut pointer = [0u8; std::mem::size_of::<usize>()];
+			access
+				.read(address, &mut pointer)
+				.map_err(|source| StableAddressError::Read {
+					offset: address,
+					source,
+				})?;
+
+			address = (usize::from_ne_bytes(pointer) as u64)
+				.checked_add_signed(offset)
+				.and_then(OffsetType::new)
+				.ok_or(StableAddressError::NullPointer)?;
+		}
+
+		Ok(address)
+	}
+}
+impl fmt::Display for StableAddress {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		for _ in 0..self.offsets.len() {
+			write!(f, "[")?;
+		}
+		write!(f, "{}+0x{:x}", self.module, self.rva)?;
+
+		for &offset in self.offsets.iter() {
+			write!(f, "]")?;
+			match offset {
+				0 => (),
+				offset if offset < 0 => write!(f, "-0x{:x}", offset.unsigned_abs())?,
+				offset => write!(f, "+0x{:x}", offset)?,
+			}
+		}
+
+		Ok(())
+	}
+}
+impl FromStr for StableAddress {
+	type Err = StableAddressError;
+
+	/// Parses the form written by [`Display`](fmt::Display). Offsets are hex, the `0x` prefix is optional.
+	fn from_str(text: &str) -> Result<Self, Self::Err> {
+		let invalid = || StableAddressError::Invalid(text.to_string());
+
+		let unbracketed = text.trim().trim_start_matches('[');
+		let depth = text.trim().len() - unbracketed.len();
+
+		// module names such as libstdc++.so contain `+` themselves
+		let (base, mut rest) = split_at_bracket(unbracketed);
+		let (module, rva) = base.rsplit_once('+').ok_or_else(invalid)?;
+		if module.is_empty() {
+			return Err(invalid());
+		}
+		let rva = parse_hex(rva).ok_or_else(invalid)?;
+
+		let mut offsets = Vec::with_capacity(depth);
+		for _ in 0..depth {
+			let (offset, remaining) = split_at_bracket(rest.strip_prefix(']').ok_or_else(invalid)?);
+			offsets.push(match offset {
+				"" => 0,
+				offset => parse_offset(offset).ok_or_else(invalid)?,
+			});
+			rest = remaining;
+		}
+		if !rest.is_empty() {
+			return Err(invalid());
+		}
+
+		Ok(StableAddress::new(module, rva).with_offsets(offsets))
+	}
+}
+
+/// Splits `text` before the first closing bracket.
+fn split_at_bracket(text: &str) -> (&str, &str) {
+	text.split_at(text.find(']').unwrap_or(text.len()))
+}
+
+fn parse_hex(text: &str) -> Option<u64> {
+	u64::from_str_radix(text.strip_prefix("0x").unwrap_or(text), 16).ok()
+}
+
+/// Parses `+0x8` or `-0x8`.
+fn parse_offset(text: &str) -> Option<i64> {
+	match text.split_at_checked(1)? {
+		("+", magnitude) => i64::try_from(parse_hex(magnitude)?).ok(),
+		("-", magnitude) => 0i64.checked_sub_unsigned(parse_hex(magnitude)?),
+		_ => None,
+	}
+}
+
+#[cfg(feature = "serde")]
+mod serialization {
+	use serde::{de::Error, Deserialize, Deserializer, Serialize, Serializer};
+
+	use super::StableAddress;
+
+	impl Serialize for StableAddress {
+		fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+			serializer.collect_str(self)
+		}
+	}
+
+	impl<'de> Deserialize<'de> for StableAddress {
+		fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+			let string = String::deserialize(deserializer)?;
+
+			string.parse().map_err(D::Error::custom)
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use std::path::PathBuf;
+
+	use super::StableAddress;
+	use crate::{
+		common::OffsetType,
+		memory::map::{MemoryPage, MemoryPagePermissions, MemoryPageType},
+		platform::mock::MockMemoryAccess,
+	};
+
+	/// Process with `libgame.so` mapped at `base`, holding a pointer at +0x10 to a structure on the heap whose field +0x8 is the value.
+	fn process(base: u64, heap: u64) -> (MockMemoryAccess, Vec<MemoryPage>) {
+		let base = OffsetType::new_unwrap(base);
+		let heap = OffsetType::new_unwrap(heap);
+
+		let mut module = vec![0u8; 0x20];
+		module[0x10..0x10 + std::mem::size_of::<usize>()]
+			.copy_from_slice(&(heap.get() as usize).to_ne_bytes());
+		let access = MockMemoryAccess::new()
+			.with_region(base, module)
+			.with_region(heap, vec![0u8; 0x10]);
+
+		let page = |start: OffsetType, page_type: MemoryPageType| MemoryPage {
+			address_range: [start, start.saturating_add(0x20)],
+			permissions: MemoryPagePermissions::new(true, true, false, true),
+			offset: 0,
+			page_type,
+		};
+		let pages = vec![
+			page(
+				base,
+				MemoryPageType::File(PathBuf::from("/usr/lib/libgame.so")),
+			),
+			page(heap, MemoryPageType::Heap),
+		];
+
+		(access, pages)
+	}
+
+	#[test]
+	fn test_stable_address() {
+		let path: StableAddress = "[[libgame.so+0x10]+0x8]-0x4".parse().unwrap();
+		assert_eq!(
+			path,
+			StableAddress::new("libgame.so", 0x10).with_offsets(vec![8, -4])
+		);
+		assert_eq!(path.to_string(), "[[libgame.so+0x10]+0x8]-0x4");
+		assert_eq!(
+			"[libstdc++.so.6+1a2b]".parse::<StableAddress>().unwrap(),
+			StableAddress::new("libstdc++.so.6", 0x1a2b).with_offsets(vec![0])
+		);
+		for invalid in [
+			"libgame.so",
+			"+0x10",
+			"[libgame.so+0x10",
+			"libgame.so+0x10]",
+			"[libgame.so+0x10]*8",
+		] {
+			assert!(invalid.parse::<StableAddress>().is_err(), "{}", invalid);
+		}
+
+		// the same path finds the value after the module and the heap move
+		let path = StableAddress::new("libgame.so", 0x10).with_offsets(vec![8]);
+		for (base, heap) in [(0x10000, 0x40000), (0x70000, 0x20000)] {
+			let (mut access, pages) = process(base, heap);
+			assert_eq!(
+				unsafe { path.resolve(&mut access, pages.as_slice()) }.unwrap(),
+				OffsetType::new_unwrap(heap + 8)
+			);
+
+			let static_address =
+				StableAddress::from_offset(pages.as_slice(), OffsetType::new_unwrap(base + 0x18))
+					.unwrap();
+			assert_eq!(static_address, StableAddress::new("libgame.so", 0x18));
+			assert!(
+				StableAddress::from_offset(pages.as_slice(), OffsetType::new_unwrap(heap))
+					.is_none()
+			);
+		}
+
+		let (mut access, pages) = process(0x10000, 0x40000);
+		let missing = StableAddress::new("libother.so", 0);
+		assert!(unsafe { missing.resolve(&mut access, pages.as_slice()) }.is_err());
+	}
+}
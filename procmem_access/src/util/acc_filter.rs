@@ -12,17 +12,17 @@
 /// ```
 /// # use procmem_access::util::AccFilter;
 /// let dedup = AccFilter::new(
-/// 	[1, 1, 1, 2, 3, 3, 4, 4, 4].iter().copied(),
-/// 	|acc, curr| match acc {
-/// 		Some(acc) if *acc == curr => None,
-/// 		_ => acc.replace(curr)
-/// 	}
+///     [1, 1, 1, 2, 3, 3, 4, 4, 4].iter().copied(),
+///     |acc, curr| match acc {
+///         Some(acc) if *acc == curr => None,
+///         _ => acc.replace(curr)
+///     }
 /// );
 ///
 /// let deduped = dedup.collect::<Vec<_>>();
 /// assert_eq!(
-/// 	deduped,
-/// 	&[1, 2, 3, 4]
+///     deduped,
+///     &[1, 2, 3, 4]
 /// );
 /// ```
 pub struct AccFilter<T, I: Iterator<Item = T>, F: FnMut(&mut Option<T>, T) -> Option<T>> {
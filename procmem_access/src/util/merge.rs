@@ -0,0 +1,189 @@
+use std::{cmp::Ordering, collections::BinaryHeap, iter::Peekable};
+
+/// Merge-sort like merge iterator.
+///
+/// On equal items, items from `a` are yielded first. See [`KMergeIter`] for merging more than two iterators.
+pub struct MergeIter<T: PartialOrd, A: Iterator<Item = T>, B: Iterator<Item = T>> {
+	a: Peekable<A>,
+	b: Peekable<B>,
+}
+impl<T: PartialOrd, A: Iterator<Item = T>, B: Iterator<Item = T>> MergeIter<T, A, B> {
+	/// Creates a new merge iterator.
+	///
+	/// This will only function correctly both `a` and `b` are sorted.
+	pub fn new(a: A, b: B) -> Self {
+		MergeIter {
+			a: a.peekable(),
+			b: b.peekable(),
+		}
+	}
+}
+impl<T: PartialOrd, A: Iterator<Item = T>, B: Iterator<Item = T>> Iterator for MergeIter<T, A, B> {
+	type Item = T;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		match (self.a.peek(), self.b.peek()) {
+			(None, None) => None,
+			(_, None) => self.a.next(),
+			(None, _) => self.b.next(),
+			(Some(left), Some(right)) => {
+				if left
+					.partial_cmp(right)
+					.map(|o| o != std::cmp::Ordering::Greater)
+					.unwrap_or(false)
+				{
+					self.a.next()
+				} else {
+					self.b.next()
+				}
+			}
+		}
+	}
+
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		let a_hint = self.a.size_hint();
+		let b_hint = self.b.size_hint();
+
+		(
+			a_hint.0 + b_hint.0,
+			a_hint
+				.1
+				.and_then(|a| b_hint.1.and_then(|b| a.checked_add(b))),
+		)
+	}
+}
+
+/// Heap entry which orders by item first and by source index second, reversed so that [`BinaryHeap`] pops the minimum.
+struct KMergeEntry<T: PartialOrd> {
+	item: T,
+	source: usize,
+}
+impl<T: PartialOrd> PartialEq for KMergeEntry<T> {
+	fn eq(&self, other: &Self) -> bool {
+		self.cmp(other) == Ordering::Equal
+	}
+}
+impl<T: PartialOrd> Eq for KMergeEntry<T> {}
+impl<T: PartialOrd> PartialOrd for KMergeEntry<T> {
+	fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+		Some(self.cmp(other))
+	}
+}
+impl<T: PartialOrd> Ord for KMergeEntry<T> {
+	fn cmp(&self, other: &Self) -> Ordering {
+		other
+			.item
+			.partial_cmp(&self.item)
+			.unwrap_or(Ordering::Equal)
+			.then_with(|| other.source.cmp(&self.source))
+	}
+}
+
+/// Merge-sort like merge iterator over any number of iterators.
+///
+/// On equal items, items from iterators given earlier are yielded first.
+pub struct KMergeIter<T: PartialOrd, I: Iterator<Item = T>> {
+	iters: Vec<I>,
+	heap: BinaryHeap<KMergeEntry<T>>,
+}
+impl<T: PartialOrd, I: Iterator<Item = T>> KMergeIter<T, I> {
+	/// Creates a new merge iterator.
+	///
+	/// This will only function correctly if all `iters` are sorted.
+	pub fn new(iters: impl IntoIterator<Item = I>) -> Self {
+		let mut iters: Vec<I> = iters.into_iter().collect();
+
+		let mut heap = BinaryHeap::with_capacity(iters.len());
+		for (source, iter) in iters.iter_mut().enumerate() {
+			if let Some(item) = iter.next() {
+				heap.push(KMergeEntry { item, source });
+			}
+		}
+
+		KMergeIter { iters, heap }
+	}
+}
+impl<T: PartialOrd, I: Iterator<Item = T>> Iterator for KMergeIter<T, I> {
+	type Item = T;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		let KMergeEntry { item, source } = self.heap.pop()?;
+
+		if let Some(next) = self.iters[source].next() {
+			self.heap.push(KMergeEntry { item: next, source });
+		}
+
+		Some(item)
+	}
+
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		self.iters
+			.iter()
+			.fold((self.heap.len(), Some(self.heap.len())), |acc, iter| {
+				let hint = iter.size_hint();
+
+				(
+					acc.0.saturating_add(hint.0),
+					acc.1.and_then(|a| hint.1.and_then(|b| a.checked_add(b))),
+				)
+			})
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::{KMergeIter, MergeIter};
+
+	#[test]
+	fn test_merge_iter() {
+		let seq_a = [1, 2, 3, 4, 5, 17, 18, 19, 20];
+		let seq_b = [4, 5, 6, 7, 11, 31];
+
+		let mut iter = MergeIter::new(seq_a.iter(), seq_b.iter());
+
+		assert_eq!(iter.next(), Some(&1));
+		assert_eq!(iter.next(), Some(&2));
+		assert_eq!(iter.next(), Some(&3));
+		assert_eq!(iter.next(), Some(&4));
+		assert_eq!(iter.next(), Some(&4));
+		assert_eq!(iter.next(), Some(&5));
+		assert_eq!(iter.next(), Some(&5));
+		assert_eq!(iter.next(), Some(&6));
+		assert_eq!(iter.next(), Some(&7));
+		assert_eq!(iter.next(), Some(&11));
+		assert_eq!(iter.next(), Some(&17));
+		assert_eq!(iter.next(), Some(&18));
+		assert_eq!(iter.next(), Some(&19));
+		assert_eq!(iter.next(), Some(&20));
+		assert_eq!(iter.next(), Some(&31));
+	}
+
+	#[test]
+	fn test_kmerge_iter() {
+		let seqs = [
+			vec![(1, 'a'), (4, 'a'), (9, 'a')],
+			vec![],
+			vec![(2, 'c'), (4, 'c'), (5, 'c')],
+			vec![(0, 'd'), (4, 'd'), (10, 'd'), (11, 'd')],
+		];
+
+		let iter = KMergeIter::new(seqs.iter().map(|s| s.iter().copied()));
+		assert_eq!(iter.size_hint(), (10, Some(10)));
+
+		assert_eq!(
+			iter.collect::<Vec<_>>(),
+			[
+				(0, 'd'),
+				(1, 'a'),
+				(2, 'c'),
+				(4, 'a'),
+				(4, 'c'),
+				(4, 'd'),
+				(5, 'c'),
+				(9, 'a'),
+				(10, 'd'),
+				(11, 'd')
+			]
+		);
+	}
+}
@@ -1,3 +1,5 @@
 pub mod acc_filter;
+pub mod merge;
 
 pub use acc_filter::AccFilter;
+pub use merge::{KMergeIter, MergeIter};
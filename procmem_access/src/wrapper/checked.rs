@@ -0,0 +1,187 @@
+use crate::{
+	common::OffsetType,
+	memory::{
+		access::{MemoryAccess, ReadError, WriteError},
+		map::{MemoryMap, MemoryPagePermissions},
+	},
+};
+
+/// Reason why a range failed the check against the memory map.
+enum RangeCheckError {
+	NotMapped,
+	NotPermitted,
+}
+
+/// Memory access wrapper which checks each operation against a memory map.
+///
+/// Reads are rejected if any part of the range is not mapped or not readable.
+/// Writes are rejected if any part of the range is not mapped or not writable, unless
+/// writes to protected pages are allowed using [`allow_protected_writes`](CheckedAccess::allow_protected_writes).
+///
+/// The memory map is not refreshed automatically, use [`map_mut`](CheckedAccess::map_mut) to replace it when the mappings change.
+pub struct CheckedAccess<A: MemoryAccess, M: MemoryMap> {
+	inner: A,
+	map: M,
+	allow_protected_writes: bool,
+}
+impl<A: MemoryAccess, M: MemoryMap> CheckedAccess<A, M> {
+	pub fn new(inner: A, map: M) -> Self {
+		CheckedAccess {
+			inner,
+			map,
+			allow_protected_writes: false,
+		}
+	}
+
+	/// Sets whether writes to mapped pages without the write permission are allowed.
+	///
+	/// Some platforms are able to write into read-only pages (for example to patch code), so this can be explicitly allowed.
+	/// Writes to unmapped ranges are rejected regardless.
+	pub fn allow_protected_writes(&mut self, allow: bool) {
+		self.allow_protected_writes = allow;
+	}
+
+	pub fn inner(&self) -> &A {
+		&self.inner
+	}
+
+	pub fn inner_mut(&mut self) -> &mut A {
+		&mut self.inner
+	}
+
+	pub fn map(&self) -> &M {
+		&self.map
+	}
+
+	pub fn map_mut(&mut self) -> &mut M {
+		&mut self.map
+	}
+
+	pub fn into_inner(self) -> (A, M) {
+		(self.inner, self.map)
+	}
+
+	/// Checks that the range `[offset, offset + len)` is fully covered by consecutive pages and that each of them has `required` permissions.
+	fn check_range(
+		&self,
+		offset: OffsetType,
+		len: usize,
+		required: MemoryPagePermissions,
+	) -> Result<(), RangeCheckError> {
+		let mut position = offset.get();
+		let end = position.saturating_add(len as u64);
+
+		// pages are ordered, so we can walk them and advance the position through consecutive pages
+		for page in self.map.pages() {
+			if position >= end {
+				break;
+			}
+			if page.end().get() <= position {
+				continue;
+			}
+			if page.start().get() > position {
+				return Err(RangeCheckError::NotMapped);
+			}
+
+			if page.permissions & required != required {
+				return Err(RangeCheckError::NotPermitted);
+			}
+			position = page.end().get();
+		}
+
+		if position < end {
+			return Err(RangeCheckError::NotMapped);
+		}
+
+		Ok(())
+	}
+}
+impl<A: MemoryAccess, M: MemoryMap> MemoryAccess for CheckedAccess<A, M> {
+	unsafe fn read(&mut self, offset: OffsetType, buffer: &mut [u8]) -> Result<(), ReadError> {
+		match self.check_range(
+			offset,
+			buffer.len(),
+			MemoryPagePermissions::new(true, false, false, false),
+		) {
+			Ok(()) => self.inner.read(offset, buffer),
+			Err(RangeCheckError::NotMapped) => Err(ReadError::NotMapped),
+			Err(RangeCheckError::NotPermitted) => Err(ReadError::NotPermitted),
+		}
+	}
+
+	unsafe fn write(&mut self, offset: OffsetType, data: &[u8]) -> Result<(), WriteError> {
+		let required =
+			MemoryPagePermissions::new(false, !self.allow_protected_writes, false, false);
+
+		match self.check_range(offset, data.len(), required) {
+			Ok(()) => self.inner.write(offset, data),
+			Err(RangeCheckError::NotMapped) => Err(WriteError::NotMapped),
+			Err(RangeCheckError::NotPermitted) => Err(WriteError::NotPermitted),
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::CheckedAccess;
+	use crate::{
+		common::OffsetType,
+		memory::{
+			access::{MemoryAccess, ReadError, WriteError},
+			map::{MemoryPage, MemoryPagePermissions, MemoryPageType},
+		},
+		platform::mock::{MockMemoryAccess, MockMemoryMap},
+	};
+
+	fn page(start: u64, end: u64, write: bool) -> MemoryPage {
+		MemoryPage {
+			address_range: [OffsetType::new_unwrap(start), OffsetType::new_unwrap(end)],
+			permissions: MemoryPagePermissions::new(true, write, false, false),
+			offset: 0,
+			page_type: MemoryPageType::Anon,
+		}
+	}
+
+	#[test]
+	fn test_checked_access() {
+		let map = MockMemoryMap::from_pages(vec![
+			page(100, 200, true),
+			page(200, 300, false),
+			page(400, 500, true),
+		]);
+		// the inner access allows everything, only the map restricts it
+		let inner = MockMemoryAccess::new().with_region(OffsetType::new_unwrap(1), vec![0; 600]);
+		let mut access = CheckedAccess::new(inner, map);
+
+		let mut buffer = [0u8; 16];
+		unsafe {
+			// across consecutive pages
+			access
+				.read(OffsetType::new_unwrap(190), &mut buffer)
+				.unwrap();
+			// across a hole
+			assert!(matches!(
+				access.read(OffsetType::new_unwrap(290), &mut buffer),
+				Err(ReadError::NotMapped)
+			));
+			// before the first page
+			assert!(matches!(
+				access.read(OffsetType::new_unwrap(90), &mut buffer),
+				Err(ReadError::NotMapped)
+			));
+
+			access.write(OffsetType::new_unwrap(100), &buffer).unwrap();
+			assert!(matches!(
+				access.write(OffsetType::new_unwrap(190), &buffer),
+				Err(WriteError::NotPermitted)
+			));
+
+			access.allow_protected_writes(true);
+			access.write(OffsetType::new_unwrap(190), &buffer).unwrap();
+			assert!(matches!(
+				access.write(OffsetType::new_unwrap(490), &buffer),
+				Err(WriteError::NotMapped)
+			));
+		}
+	}
+}
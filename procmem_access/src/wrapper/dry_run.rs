@@ -0,0 +1,181 @@
+use crate::{
+	common::OffsetType,
+	memory::access::{MemoryAccess, ReadError, WriteError},
+};
+
+/// Write recorded by [`DryRunAccess`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordedWrite {
+	pub offset: OffsetType,
+	pub data: Vec<u8>,
+}
+impl RecordedWrite {
+	pub fn end(&self) -> OffsetType {
+		self.offset.saturating_add(self.data.len() as u64)
+	}
+}
+impl std::fmt::Display for RecordedWrite {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		write!(f, "{}:", self.offset)?;
+		for byte in self.data.iter() {
+			write!(f, " {:02x}", byte)?;
+		}
+
+		Ok(())
+	}
+}
+
+/// Memory access wrapper which records writes instead of applying them.
+///
+/// Reads go through to the inner access but reflect the recorded writes, so the wrapped process appears
+/// as if the writes were applied. Recorded writes can be inspected with [`writes`](DryRunAccess::writes)
+/// and later applied with [`commit`](DryRunAccess::commit) or thrown away with [`discard`](DryRunAccess::discard).
+pub struct DryRunAccess<A: MemoryAccess> {
+	inner: A,
+	writes: Vec<RecordedWrite>,
+}
+impl<A: MemoryAccess> DryRunAccess<A> {
+	pub fn new(inner: A) -> Self {
+		DryRunAccess {
+			inner,
+			writes: Vec::new(),
+		}
+	}
+
+	/// Returns the recorded writes in the order they were performed.
+	pub fn writes(&self) -> &[RecordedWrite] {
+		&self.writes
+	}
+
+	/// Throws away all recorded writes.
+	pub fn discard(&mut self) {
+		self.writes.clear()
+	}
+
+	/// Applies the recorded writes to the inner access in the order they were performed.
+	///
+	/// Writes are removed as they are applied, so on error the failed write and the writes after it remain recorded.
+	///
+	/// ## Safety
+	/// Same as [`MemoryAccess::write`] for each of the recorded writes.
+	pub unsafe fn commit(&mut self) -> Result<(), WriteError> {
+		let mut applied = 0;
+		let mut result = Ok(());
+		for write in self.writes.iter() {
+			if let Err(err) = self.inner.write(write.offset, &write.data) {
+				result = Err(err);
+				break;
+			}
+			applied += 1;
+		}
+		self.writes.drain(..applied);
+
+		result
+	}
+
+	pub fn inner(&self) -> &A {
+		&self.inner
+	}
+
+	pub fn inner_mut(&mut self) -> &mut A {
+		&mut self.inner
+	}
+
+	/// Returns the inner access, dropping any recorded writes.
+	pub fn into_inner(self) -> A {
+		self.inner
+	}
+}
+impl<A: MemoryAccess> MemoryAccess for DryRunAccess<A> {
+	unsafe fn read(&mut self, offset: OffsetType, buffer: &mut [u8]) -> Result<(), ReadError> {
+		self.inner.read(offset, buffer)?;
+
+		// overlay recorded writes in order so that later writes win
+		let start = offset.get();
+		let end = start + buffer.len() as u64;
+		for write in self.writes.iter() {
+			let write_start = write.offset.get();
+			let write_end = write.end().get();
+			if write_end <= start || write_start >= end {
+				continue;
+			}
+
+			let from = write_start.max(start);
+			let to = write_end.min(end);
+			buffer[(from - start) as usize..(to - start) as usize].copy_from_slice(
+				&write.data[(from - write_start) as usize..(to - write_start) as usize],
+			);
+		}
+
+		Ok(())
+	}
+
+	unsafe fn write(&mut self, offset: OffsetType, data: &[u8]) -> Result<(), WriteError> {
+		self.writes.push(RecordedWrite {
+			offset,
+			data: data.to_vec(),
+		});
+
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::{DryRunAccess, RecordedWrite};
+	use crate::{
+		common::OffsetType, memory::access::MemoryAccess, platform::mock::MockMemoryAccess,
+	};
+
+	fn buffer_access(len: usize) -> MockMemoryAccess {
+		MockMemoryAccess::new().with_region(OffsetType::new_unwrap(1), vec![0; len])
+	}
+
+	#[test]
+	fn test_dry_run_records_and_overlays() {
+		let mut access = DryRunAccess::new(buffer_access(16));
+
+		unsafe {
+			access.write(OffsetType::new_unwrap(2), &[1, 2, 3]).unwrap();
+			access.write(OffsetType::new_unwrap(4), &[9, 9]).unwrap();
+		}
+		assert_eq!(
+			access.inner().region(OffsetType::new_unwrap(1)),
+			Some(&[0; 16][..])
+		);
+		assert_eq!(
+			access.writes(),
+			&[
+				RecordedWrite {
+					offset: OffsetType::new_unwrap(2),
+					data: vec![1, 2, 3]
+				},
+				RecordedWrite {
+					offset: OffsetType::new_unwrap(4),
+					data: vec![9, 9]
+				}
+			]
+		);
+
+		let mut buffer = [0u8; 5];
+		unsafe { access.read(OffsetType::new_unwrap(1), &mut buffer).unwrap() };
+		assert_eq!(buffer, [0, 1, 2, 9, 9]);
+	}
+
+	#[test]
+	fn test_dry_run_commit() {
+		let mut access = DryRunAccess::new(buffer_access(8));
+
+		unsafe {
+			access.write(OffsetType::new_unwrap(1), &[1, 2]).unwrap();
+			access.write(OffsetType::new_unwrap(2), &[3]).unwrap();
+			access.commit().unwrap();
+		}
+
+		assert!(access.writes().is_empty());
+		assert_eq!(
+			access.into_inner().region(OffsetType::new_unwrap(1)),
+			Some(&[1, 3, 0, 0, 0, 0, 0, 0][..])
+		);
+	}
+}
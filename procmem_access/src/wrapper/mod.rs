@@ -0,0 +1,7 @@
+//! Wrappers over [`MemoryAccess`](crate::memory::access::MemoryAccess) implementations that alter their behavior.
+
+pub mod checked;
+pub mod dry_run;
+
+pub use checked::CheckedAccess;
+pub use dry_run::{DryRunAccess, RecordedWrite};
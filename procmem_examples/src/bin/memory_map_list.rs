@@ -1,14 +1,17 @@
-use procmem_access::{platform::simple::SimpleMemoryMap, prelude::MemoryMap};
+use procmem_access::{
+	platform::simple::{SimpleMemoryMap, SimplePid},
+	prelude::MemoryMap,
+};
 
 fn main() {
 	// simple cli parse
 	let pid = {
 		let mut it = std::env::args().skip(1);
 
-		let pid: i32 = it
+		let pid: SimplePid = it
 			.next()
 			.and_then(|s| s.parse().ok())
-			.unwrap_or_else(|| std::process::id() as i32);
+			.unwrap_or_else(|| std::process::id() as SimplePid);
 
 		pid
 	};
@@ -1,4 +1,5 @@
 use anyhow::Context;
+use procmem_access::layout::format::ValueFormatter;
 use rustyline::{
 	completion::Pair as CompletionPair, config::Config, error::ReadlineError, history::MemHistory,
 	Editor,
@@ -31,6 +32,7 @@ impl ReplHelper {
 			"reset",
 			"detach",
 			"attach ",
+			"attach name:",
 			"scan i16 ",
 			"scan i32 ",
 			"scan i64 ",
@@ -42,10 +44,29 @@ impl ReplHelper {
 			"write i64 ",
 			"write f32 ",
 			"write f64 ",
+			"write ptr ",
+			"write bytes[",
+			"write string[",
+			"inspect ",
+			"dump ",
+			"disasm ",
+			"freeze ",
+			"unfreeze ",
+			"frozen",
 			"stop",
 			"continue",
 			"info",
 			"info pages",
+			"filter",
+			"filter perm ",
+			"filter type ",
+			"filter path ",
+			"filter range ",
+			"filter reset",
+			"save ",
+			"load ",
+			"list",
+			"bookmark ",
 			"exit"
 		}
 
@@ -58,16 +79,15 @@ impl rustyline::hint::Hinter for ReplHelper {
 	type Hint = String;
 
 	fn hint(&self, line: &str, pos: usize, _ctx: &rustyline::Context<'_>) -> Option<Self::Hint> {
-		if line.len() == 0 {
+		if line.is_empty() {
 			return None;
 		}
 
 		let completions = Self::try_complete(line);
 
-		match completions.get(0) {
-			None => None,
-			Some(completion) => Some(completion.replacement[pos..].to_string()),
-		}
+		completions
+			.first()
+			.map(|completion| completion.replacement[pos..].to_string())
 	}
 }
 impl rustyline::completion::Completer for ReplHelper {
@@ -126,12 +146,13 @@ fn main() -> anyhow::Result<()> {
 	)?;
 	rl.set_helper(Some(ReplHelper::new()));
 
+	let mut session = Session::load(Session::path())?;
 	let mut app: Option<App> = None;
 	loop {
 		macro_rules! on_attached {
 			($app: ident => $($code: tt)+) => {
 				match app {
-					None => println!("Not attached, use `attach PID` first"),
+					None => println!("Not attached, use `attach PID` or `attach name:PATTERN` first"),
 					Some(ref mut $app) => {
 						$($code)+
 					}
@@ -147,17 +168,19 @@ fn main() -> anyhow::Result<()> {
 			// commands
 			Ok(line) if line.starts_with("attach ") => match app {
 				Some(_) => println!("Already attached, use `detach` first"),
-				None => match line.split_whitespace().nth(1).unwrap_or("").parse() {
-					Err(_) => println!("Invalid PID"),
-					Ok(pid) => {
-						app = Some(App::attach(pid)?);
-					}
+				None => match resolve_pid(line.split_whitespace().nth(1).unwrap_or("")) {
+					Err(err) => println!("{:#}", err),
+					Ok(pid) => match App::attach(pid) {
+						Err(err) => print_attach_error(&err),
+						Ok(attached) => app = Some(attached),
+					},
 				},
 			},
-			Ok(line) if line == "detach" => match app.take() {
-				None => println!("Not attached, cannot detach"),
-				Some(_) => (),
-			},
+			Ok(line) if line == "detach" => {
+				if app.take().is_none() {
+					println!("Not attached, cannot detach")
+				}
+			}
 			Ok(line) if line == "stop" => on_attached! { app => app.lock(); },
 			Ok(line) if line == "continue" => on_attached! { app => app.unlock(); },
 			Ok(line) if line == "reset" => on_attached! { app => app.reset(); },
@@ -171,9 +194,31 @@ fn main() -> anyhow::Result<()> {
 				println!("Locked: {}", app.is_locked());
 			},
 			Ok(line) if line == "info pages" => on_attached! { app =>
+				println!("Filter: {}", app.filter());
 				println!("Pages:");
+				let stats = app.page_stats();
 				for (selected, page) in app.pages() {
-					println!("\t[{}] {}", selected.then_some("x").unwrap_or(" "), page);
+					let selected = if selected { "x" } else { " " };
+					match stats.get(&page.start()) {
+						None => println!("\t[{}] {}", selected, page),
+						Some(stats) => println!(
+							"\t[{}] {} (rss {} KiB, swap {} KiB, dirty {} KiB)",
+							selected,
+							page,
+							stats.resident / 1024,
+							stats.swap / 1024,
+							stats.dirty() / 1024
+						),
+					}
+				}
+			},
+			Ok(line) if line == "filter" => on_attached! { app =>
+				println!("Filter: {}", app.filter());
+			},
+			Ok(line) if line.starts_with("filter ") => on_attached! { app =>
+				match app.apply_filter(&line["filter ".len()..]) {
+					Err(err) => println!("Could not set filter: {:#}", err),
+					Ok(()) => println!("Filter: {}", app.filter()),
 				}
 			},
 			// scans
@@ -184,11 +229,18 @@ fn main() -> anyhow::Result<()> {
 				let value_str = arguments.next().context("scan value is required")?;
 
 				let mut aligned = true;
-				let mut swapped_bytes = false;
+				let mut endianness = Endianness::NATIVE;
 				for argument in arguments {
 					match argument {
 						"unalign" => { aligned = false; }
-						"swap" => { swapped_bytes = true; }
+						"big" => { endianness = Endianness::Big; }
+						"little" => { endianness = Endianness::Little; }
+						"swap" => {
+							endianness = match Endianness::NATIVE {
+								Endianness::Little => Endianness::Big,
+								Endianness::Big => Endianness::Little,
+							};
+						}
 						flag => anyhow::bail!("Invalid scan flag \"{}\"", flag)
 					}
 				}
@@ -196,24 +248,23 @@ fn main() -> anyhow::Result<()> {
 				macro_rules! do_scan {
 					($scan_type: ty) => {
 						{
-							println!("Scanning as {} (align: {}, swap: {})...", stringify!($scan_type), aligned, swapped_bytes);
+							println!("Scanning as {} (align: {}, endianness: {})...", stringify!($scan_type), aligned, endianness);
 							match value_str.parse::<$scan_type>() {
 								Err(err) => println!("Skipping scan: {}", err),
 								Ok(value) => {
-									let value = if swapped_bytes {
-										#[cfg(target_endian = "little")]
-										{ value.to_be_bytes() }
-										#[cfg(target_endian = "big")]
-										{ value.to_le_bytes() }
-									} else {
-										value.to_ne_bytes()
-									};
-
-									match app.scan_exact(value, aligned)? {
+									let value = value.to_target_bytes(endianness);
+
+									match app.scan_exact(value, stringify!($scan_type), aligned)? {
 										ScanResult::Zero => { println!("No matches"); },
-										ScanResult::One(offset) => println!("One match: 0x{}", offset),
-										ScanResult::Few(offsets) => println!("{} matches: {:X?}", offsets.len(), offsets),
-										ScanResult::Many(n) => println!("{} matches", n)
+										ScanResult::One(offset) => println!("One match: {}", app.describe(offset)),
+										ScanResult::Few(offsets) => {
+											println!("{} matches:", offsets.len());
+											for offset in offsets {
+												println!("\t{}", app.describe(offset));
+											}
+										},
+										ScanResult::Many(n) => println!("{} matches", n),
+										ScanResult::Cancelled => println!("Scan cancelled, keeping the previous matches")
 									}
 								}
 							}
@@ -247,28 +298,149 @@ fn main() -> anyhow::Result<()> {
 
 				let value_type = arguments.next().context("write type is required")?;
 				let offset = arguments.next().and_then(|v| u64::from_str_radix(v, 16).ok()).context("write offset is required")?;
-				let value_str = arguments.next().context("write value is required")?;
+				// strings may contain spaces
+				let value_str = arguments.collect::<Vec<_>>().join(" ");
+				anyhow::ensure!(!value_str.is_empty(), "write value is required");
 
-				macro_rules! do_write {
-					($write_type: ty) => {
-						{
-							match value_str.parse::<$write_type>() {
-								Err(err) => println!("Skipping write: {}", err),
-								Ok(value) => unsafe { app.write(offset, value)? }
+				let formatter = value_type.parse::<ValueFormatter>()?;
+				match formatter.parse(&value_str) {
+					Err(err) => println!("Skipping write: {}", err),
+					Ok(value) => unsafe { app.write(offset, value)? }
+				}
+			},
+			Ok(line) if line.starts_with("freeze ") => on_attached! { app =>
+				let mut arguments = line.split_whitespace().skip(1);
+
+				let offset = arguments.next().and_then(|v| u64::from_str_radix(v, 16).ok()).context("freeze offset is required")?;
+				let value_type = arguments.next().context("freeze type is required")?;
+				let value_str = arguments.collect::<Vec<_>>().join(" ");
+				anyhow::ensure!(!value_str.is_empty(), "freeze value is required");
+
+				let formatter = value_type.parse::<ValueFormatter>()?;
+				match formatter.parse(&value_str) {
+					Err(err) => println!("Skipping freeze: {}", err),
+					Ok(value) => unsafe { app.freeze(offset, value)? }
+				}
+			},
+			Ok(line) if line.starts_with("unfreeze ") => on_attached! { app =>
+				let offset = line.split_whitespace().nth(1).and_then(|v| u64::from_str_radix(v, 16).ok()).context("unfreeze offset is required")?;
+
+				if !app.unfreeze(offset) {
+					println!("Value at {:x} is not frozen", offset);
+				}
+			},
+			Ok(line) if line == "frozen" => on_attached! { app =>
+				for (offset, value) in app.frozen() {
+					println!("0x{}: {:02X?}", offset, value);
+				}
+			},
+			Ok(line) if line.starts_with("inspect ") => on_attached! { app =>
+				let offset = line.split_whitespace().nth(1).and_then(|v| u64::from_str_radix(v, 16).ok()).context("inspect offset is required")?;
+
+				match unsafe { app.inspect(offset) } {
+					Err(err) => println!("Could not inspect: {:#}", err),
+					Ok(report) => print!("{}", report)
+				}
+			},
+			Ok(line) if line.starts_with("dump ") => on_attached! { app =>
+				let mut arguments = line.split_whitespace().skip(1);
+				let offset = arguments.next().and_then(|v| u64::from_str_radix(v, 16).ok()).context("dump offset is required")?;
+				let length = arguments.next().map(str::parse).transpose().context("dump length must be a number")?.unwrap_or(256);
+
+				let bytes = unsafe { app.read_bytes(offset, length)? };
+				print!("{}", view::hexdump(offset, &bytes));
+				if bytes.len() < length {
+					println!("Memory at 0x{:x} is not readable", offset + bytes.len() as u64);
+				}
+			},
+			Ok(line) if line.starts_with("disasm ") => on_attached! { app =>
+				let mut arguments = line.split_whitespace().skip(1);
+				let offset = arguments.next().and_then(|v| u64::from_str_radix(v, 16).ok()).context("disasm offset is required")?;
+				let length = arguments.next().map(str::parse).transpose().context("disasm length must be a number")?.unwrap_or(64);
+
+				let bytes = unsafe { app.read_bytes(offset, length)? };
+				match view::disassemble(offset, &bytes) {
+					None => println!("Disassembly requires building with the `disasm` feature"),
+					Some(listing) => print!("{}", listing),
+				}
+			},
+			// session
+			Ok(line) if line.starts_with("save ") => on_attached! { app =>
+				let name = line.split_whitespace().nth(1).context("save name is required")?;
+
+				match app.matches() {
+					(None, _) => println!("No matches to save, scan first"),
+					(Some(value_type), matches) => {
+						session.save_matches(name, value_type, matches);
+						session.store()?;
+						println!("Saved {} {} matches as \"{}\"", matches.len(), value_type, name);
+					}
+				}
+			},
+			Ok(line) if line.starts_with("load ") => on_attached! { app =>
+				let name = line.split_whitespace().nth(1).context("load name is required")?;
+
+				match session.match_list(name) {
+					None => println!("No saved matches named \"{}\"", name),
+					Some(list) => {
+						app.set_matches(&list.value_type, list.results.clone());
+						println!("Loaded {} {} matches", list.results.len(), list.value_type);
+					}
+				}
+			},
+			Ok(line) if line == "list" => {
+				println!("Saved matches:");
+				for (name, list) in session.match_lists() {
+					println!(
+						"\t{}: {} {} matches",
+						name,
+						list.results.len(),
+						list.value_type
+					);
+				}
+				println!("Bookmarks:");
+				for bookmark in session.bookmarks() {
+					// stable addresses are shown with their address in the attached process
+					let resolved = match (&bookmark.address, app.as_mut()) {
+						(BookmarkAddress::Stable(address), Some(app)) => {
+							match unsafe { app.resolve_stable(address) } {
+								Ok(offset) => format!(" = 0x{}", offset),
+								Err(err) => format!(" = {}", err),
 							}
 						}
+						_ => String::new(),
 					};
+					println!(
+						"\t{}{} {} {}",
+						bookmark.address, resolved, bookmark.value_type, bookmark.label
+					);
 				}
+			}
+			Ok(line) if line.starts_with("bookmark ") => {
+				let mut arguments = line.splitn(4, char::is_whitespace).skip(1);
 
-				match value_type {
-					"i16" => do_write!(i16),
-					"i32" => do_write!(i32),
-					"i64" => do_write!(i64),
-					"f32" => do_write!(f32),
-					"f64" => do_write!(f64),
-					value_type => anyhow::bail!("Unknown value type \"{}\"", value_type)
+				let mut address = arguments
+					.next()
+					.and_then(BookmarkAddress::parse)
+					.context("bookmark offset or module+offset is required")?;
+				let value_type = arguments.next().context("bookmark type is required")?;
+				let label = arguments.next().unwrap_or("").trim();
+
+				if !VALUE_TYPES.contains(&value_type) {
+					anyhow::bail!("Unknown value type \"{}\"", value_type)
 				}
-			},
+				// offsets inside modules are kept relative to the module so that they survive restarts
+				if let (BookmarkAddress::Absolute(offset), Some(app)) = (&address, app.as_ref()) {
+					if let Some(stable) =
+						OffsetType::new(*offset).and_then(|offset| app.stable_address(offset))
+					{
+						println!("Bookmarked as {}", stable);
+						address = BookmarkAddress::Stable(stable);
+					}
+				}
+				session.bookmark(address, value_type, label);
+				session.store()?;
+			}
 			// rest
 			Ok(line) => println!("Unknown command \"{}\"", line),
 		}
@@ -278,69 +450,154 @@ fn main() -> anyhow::Result<()> {
 }
 
 mod app {
-	use std::collections::BTreeSet;
+	use std::{
+		collections::HashMap,
+		sync::atomic::{AtomicBool, Ordering},
+		time::Duration,
+	};
 
 	use anyhow::Context;
 
 	pub use procmem_access::platform::simple::ProcessInfo;
 	use procmem_access::{
-		platform::simple::{SimpleMemoryAccess, SimpleMemoryLock, SimpleMemoryMap},
+		layout::interpret::{interpret, Interpretation},
+		memory::{freeze::ValueFreezer, map::MemoryPageStats},
+		platform::simple::{SimpleMemoryAccess, SimpleMemoryLock, SimpleMemoryMap, SimplePid},
 		prelude::{MemoryAccess, MemoryLock, MemoryMap, MemoryPage, OffsetType},
+		symbols::{StableAddress, StableAddressError, SymbolResolver},
+	};
+
+	use crate::filter::PageFilter;
+	use procmem_scan::prelude::{
+		ByteComparable, ScanConfig, ScanDriver, ScanProgress, ScanResultSet, ValuePredicate,
 	};
-	use procmem_scan::prelude::{ByteComparable, StreamScanner, ValuePredicate};
 
 	pub enum ScanResult {
 		Many(usize),
 		Few(Vec<OffsetType>),
 		One(OffsetType),
 		Zero,
+		Cancelled,
+	}
+
+	/// Set by the `SIGINT` handler while a scan is running.
+	static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+	#[cfg(unix)]
+	extern "C" fn on_interrupt(_signal: libc::c_int) {
+		INTERRUPTED.store(true, Ordering::Relaxed);
+	}
+
+	/// Runs `scan` while printing its progress, Ctrl-C cancels the scan instead of exiting.
+	fn with_progress<R>(scan: impl FnOnce(&ScanProgress) -> R) -> R {
+		let progress = ScanProgress::new();
+		let done = AtomicBool::new(false);
+
+		INTERRUPTED.store(false, Ordering::Relaxed);
+		#[cfg(unix)]
+		let previous_handler = unsafe {
+			libc::signal(
+				libc::SIGINT,
+				on_interrupt as extern "C" fn(libc::c_int) as libc::sighandler_t,
+			)
+		};
+
+		let result = std::thread::scope(|scope| {
+			scope.spawn(|| {
+				while !done.load(Ordering::Relaxed) {
+					if INTERRUPTED.load(Ordering::Relaxed) {
+						progress.cancel();
+					}
+
+					eprint!(
+						"\r{:5.1}% ({} / {} KiB, {} matches)",
+						progress.fraction() * 100.0,
+						progress.bytes_scanned() / 1024,
+						progress.total_bytes() / 1024,
+						progress.matches()
+					);
+					std::thread::sleep(Duration::from_millis(100));
+				}
+				eprintln!();
+			});
+
+			let result = scan(&progress);
+			done.store(true, Ordering::Relaxed);
+
+			result
+		});
+
+		#[cfg(unix)]
+		unsafe {
+			libc::signal(libc::SIGINT, previous_handler);
+		}
+
+		result
 	}
 
 	pub struct App {
-		pid: i32,
+		pid: SimplePid,
 		lock: SimpleMemoryLock,
-		#[allow(dead_code)]
 		map: SimpleMemoryMap,
 		access: SimpleMemoryAccess,
+		filter: PageFilter,
+		/// Merged pages selected by `filter`, clipped to its range.
 		pages: Vec<MemoryPage>,
-		current_matches: BTreeSet<OffsetType>,
+		driver: ScanDriver,
+		current_matches: ScanResultSet,
+		/// Value type of the current matches.
+		current_type: Option<String>,
 		user_locked: bool,
+		freezer: Option<ValueFreezer>,
+		symbols: SymbolResolver,
 	}
 	impl App {
-		fn filter_page_predicate(page: &MemoryPage) -> bool {
-			page.permissions.read()
-				&& page.permissions.write()
-				&& !page.permissions.shared()
-				&& page.offset == 0
+		/// Merges the pages selected by `filter` into the scanned ranges.
+		fn select_pages(map: &SimpleMemoryMap, filter: &PageFilter) -> Vec<MemoryPage> {
+			MemoryPage::merge_sorted(
+				map.pages()
+					.iter()
+					.filter(|page| filter.matches(page))
+					.cloned(),
+			)
+			.filter_map(|mut page| {
+				let [start, end] =
+					filter.clip([page.address_range[0].get(), page.address_range[1].get()]);
+				if start >= end {
+					return None;
+				}
+				page.address_range = [OffsetType::new(start)?, OffsetType::new(end)?];
 
-			// && matches!(page.page_type, MemoryPageType::Stack | MemoryPageType::Heap)
+				Some(page)
+			})
+			.collect()
 		}
 
-		pub fn attach(pid: i32) -> anyhow::Result<Self> {
+		pub fn attach(pid: SimplePid) -> anyhow::Result<Self> {
 			let mut lock = SimpleMemoryLock::new(pid)?;
 			lock.lock()?;
 
 			let map = SimpleMemoryMap::new(pid)?;
 			let access = SimpleMemoryAccess::new(pid)?;
 
-			let pages: Vec<MemoryPage> = MemoryPage::merge_sorted(
-				map.pages()
-					.into_iter()
-					.filter(|page| Self::filter_page_predicate(page))
-					.cloned(),
-			)
-			.collect();
-
 			lock.unlock()?;
 
+			let filter = PageFilter::default();
+			let pages = Self::select_pages(&map, &filter);
+
 			Ok(Self {
 				pid,
 				lock,
 				map,
 				access,
+				filter,
 				pages,
-				current_matches: Default::default(),
+				driver: ScanDriver::new(ScanConfig::default()),
+				current_matches: ScanResultSet::new(),
+				current_type: None,
 				user_locked: false,
+				freezer: None,
+				symbols: SymbolResolver::new(),
 			})
 		}
 
@@ -349,10 +606,55 @@ mod app {
 		}
 
 		pub fn pages(&self) -> impl Iterator<Item = (bool, &'_ MemoryPage)> {
-			self.map
-				.pages()
-				.into_iter()
-				.map(|p| (Self::filter_page_predicate(p), p))
+			self.map.pages().iter().map(|p| (self.filter.matches(p), p))
+		}
+
+		/// Formats `offset` as `module+rva (symbol)` when it is inside a mapped file.
+		pub fn describe(&mut self, offset: OffsetType) -> String {
+			self.symbols.describe(&self.map, offset)
+		}
+
+		/// Returns `offset` relative to the module containing it, which stays valid after the process restarts.
+		pub fn stable_address(&self, offset: OffsetType) -> Option<StableAddress> {
+			StableAddress::from_offset(&self.map, offset)
+		}
+
+		pub unsafe fn resolve_stable(
+			&mut self,
+			address: &StableAddress,
+		) -> Result<OffsetType, StableAddressError> {
+			unsafe { address.resolve(&mut self.access, &self.map) }
+		}
+
+		/// Loads the memory usage of the pages by their start, empty where the platform does not report it.
+		pub fn page_stats(&self) -> HashMap<OffsetType, MemoryPageStats> {
+			#[cfg(any(target_os = "linux", target_os = "android"))]
+			if let Ok(map) =
+				procmem_access::platform::procfs::ProcfsMemoryMap::new_with_stats(self.pid)
+			{
+				if let Some(stats) = map.page_stats() {
+					return map
+						.pages()
+						.iter()
+						.map(MemoryPage::start)
+						.zip(stats.iter().copied())
+						.collect();
+				}
+			}
+
+			HashMap::new()
+		}
+
+		pub fn filter(&self) -> &PageFilter {
+			&self.filter
+		}
+
+		/// Applies a `filter` command and reselects the scanned pages, the current matches are kept.
+		pub fn apply_filter(&mut self, command: &str) -> anyhow::Result<()> {
+			self.filter.apply(command)?;
+			self.pages = Self::select_pages(&self.map, &self.filter);
+
+			Ok(())
 		}
 
 		pub fn is_locked(&self) -> bool {
@@ -378,42 +680,60 @@ mod app {
 		}
 
 		pub fn reset(&mut self) {
-			self.current_matches.clear()
+			self.current_matches.clear();
+			self.current_type = None;
+		}
+
+		pub fn matches(&self) -> (Option<&str>, &ScanResultSet) {
+			(self.current_type.as_deref(), &self.current_matches)
+		}
+
+		/// Replaces the current matches, next scans filter them.
+		pub fn set_matches(&mut self, value_type: &str, matches: ScanResultSet) {
+			self.current_matches = matches;
+			self.current_type = Some(value_type.to_string());
 		}
 
 		pub fn scan_exact<T: ByteComparable>(
 			&mut self,
 			value: T,
+			value_type: &str,
 			aligned: bool,
 		) -> anyhow::Result<ScanResult> {
 			self.lock.lock()?;
 
 			let predicate = ValuePredicate::new(value, aligned);
-			let mut scanner = StreamScanner::new(predicate);
-
-			let mut new_matches = BTreeSet::default();
-			let mut chunk_buffer = Vec::new();
-			for page in self.pages.iter() {
-				chunk_buffer.resize(page.size() as usize, 0);
 
-				unsafe {
-					self.access
-						.read(page.start(), chunk_buffer.as_mut())
-						.context("Could not read memory page")?;
-				}
+			// later scans only re-read the current matches
+			if self.current_matches.is_empty() {
+				let mut new_matches = Vec::new();
+				let finished = with_progress(|progress| unsafe {
+					self.driver.scan_with_progress(
+						&mut self.access,
+						self.pages.iter().map(|page| page.address_range),
+						predicate,
+						progress,
+						|result| new_matches.push(result),
+					)
+				})
+				.context("Could not read memory page")?;
 
-				for (offset, _) in scanner.scan_once(page.start(), chunk_buffer.iter().copied()) {
-					if self.current_matches.len() == 0 || self.current_matches.contains(&offset) {
-						new_matches.insert(offset);
-					}
+				if !finished {
+					self.lock.unlock()?;
+					return Ok(ScanResult::Cancelled);
 				}
+				self.current_matches = new_matches.into_iter().collect();
+			} else {
+				self.current_matches =
+					unsafe { self.current_matches.filter(&mut self.access, predicate) };
 			}
-			self.current_matches = new_matches;
+
+			self.current_type = Some(value_type.to_string());
 
 			let result = match self.current_matches.len() {
 				0 => ScanResult::Zero,
-				1 => ScanResult::One(self.current_matches.iter().next().unwrap().clone()),
-				2..=5 => ScanResult::Few(self.current_matches.iter().cloned().collect()),
+				1 => ScanResult::One(self.current_matches.offsets().next().unwrap()),
+				2..=5 => ScanResult::Few(self.current_matches.offsets().collect()),
 				n => ScanResult::Many(n),
 			};
 
@@ -422,6 +742,33 @@ mod app {
 			Ok(result)
 		}
 
+		pub unsafe fn inspect(&mut self, offset: u64) -> anyhow::Result<Interpretation> {
+			let offset = OffsetType::new(offset).context("Offset must not be zero")?;
+
+			self.lock.lock()?;
+			let report = unsafe { interpret(&mut self.access, &self.map, offset) };
+			self.lock.unlock()?;
+
+			report.context("Could not read memory")
+		}
+
+		/// Maximum number of bytes read by [`read_bytes`](App::read_bytes).
+		pub const MAX_READ: usize = 64 * 1024;
+
+		/// Reads up to `length` bytes at `offset`, stopping at the first unreadable byte.
+		pub unsafe fn read_bytes(&mut self, offset: u64, length: usize) -> anyhow::Result<Vec<u8>> {
+			let offset = OffsetType::new(offset).context("Offset must not be zero")?;
+			let mut bytes = vec![0u8; length.min(Self::MAX_READ)];
+
+			self.lock.lock()?;
+			if let Err(err) = unsafe { self.access.read_partial(offset, &mut bytes) } {
+				bytes.truncate(err.read);
+			}
+			self.lock.unlock()?;
+
+			Ok(bytes)
+		}
+
 		pub unsafe fn write<T: ByteComparable>(
 			&mut self,
 			offset: u64,
@@ -440,6 +787,514 @@ mod app {
 			self.lock.unlock()?;
 			Ok(())
 		}
+
+		/// Keeps writing `value` at `offset` until unfrozen or detached.
+		pub unsafe fn freeze<T: ByteComparable>(
+			&mut self,
+			offset: u64,
+			value: T,
+		) -> anyhow::Result<()> {
+			let offset = OffsetType::new(offset).context("Offset must not be zero")?;
+
+			let freezer = match self.freezer.as_mut() {
+				Some(freezer) => freezer,
+				None => {
+					let access = SimpleMemoryAccess::new(self.pid)?;
+					self.freezer
+						.insert(unsafe { ValueFreezer::new(access, Duration::from_millis(50)) })
+				}
+			};
+			freezer.freeze(offset, value.as_bytes());
+
+			Ok(())
+		}
+
+		pub fn unfreeze(&mut self, offset: u64) -> bool {
+			match (self.freezer.as_ref(), OffsetType::new(offset)) {
+				(Some(freezer), Some(offset)) => freezer.unfreeze(offset).is_some(),
+				_ => false,
+			}
+		}
+
+		pub fn frozen(&self) -> Vec<(OffsetType, Vec<u8>)> {
+			self.freezer
+				.as_ref()
+				.map(ValueFreezer::frozen)
+				.unwrap_or_default()
+		}
+	}
+}
+
+/// Text views of raw memory.
+mod view {
+	use std::fmt::Write;
+
+	const ROW: usize = 16;
+
+	/// Formats `bytes` starting at `offset` as rows of hex bytes followed by their ASCII characters.
+	pub fn hexdump(offset: u64, bytes: &[u8]) -> String {
+		let mut text = String::new();
+
+		for (index, row) in bytes.chunks(ROW).enumerate() {
+			write!(text, "{:016x} ", offset + (index * ROW) as u64).unwrap();
+			for column in 0..ROW {
+				if column == ROW / 2 {
+					text.push(' ');
+				}
+				match row.get(column) {
+					Some(byte) => write!(text, " {:02x}", byte).unwrap(),
+					None => text.push_str("   "),
+				}
+			}
+
+			text.push_str("  |");
+			text.extend(row.iter().map(|&byte| {
+				if byte.is_ascii_graphic() || byte == b' ' {
+					byte as char
+				} else {
+					'.'
+				}
+			}));
+			text.push_str("|\n");
+		}
+
+		text
+	}
+
+	/// Disassembles `bytes` starting at `offset` as x86 code of the host bitness.
+	#[cfg(feature = "disasm")]
+	pub fn disassemble(offset: u64, bytes: &[u8]) -> Option<String> {
+		use iced_x86::{Decoder, DecoderOptions, Formatter, IntelFormatter};
+
+		let mut decoder = Decoder::with_ip(usize::BITS, bytes, offset, DecoderOptions::NONE);
+		let mut formatter = IntelFormatter::new();
+
+		let mut text = String::new();
+		let mut instruction_text = String::new();
+		for instruction in &mut decoder {
+			let start = (instruction.ip() - offset) as usize;
+			let encoded = &bytes[start..start + instruction.len()];
+
+			instruction_text.clear();
+			formatter.format(&instruction, &mut instruction_text);
+			writeln!(
+				text,
+				"{:016x}  {:<24} {}",
+				instruction.ip(),
+				encoded
+					.iter()
+					.map(|byte| format!("{:02x}", byte))
+					.collect::<Vec<_>>()
+					.join(" "),
+				instruction_text
+			)
+			.unwrap();
+		}
+
+		Some(text)
+	}
+
+	/// Disassembly is not available without the `disasm` feature.
+	#[cfg(not(feature = "disasm"))]
+	pub fn disassemble(_offset: u64, _bytes: &[u8]) -> Option<String> {
+		None
+	}
+}
+
+/// Named match lists and bookmarks kept between runs of the REPL.
+mod session {
+	use std::{
+		cmp::Ordering,
+		collections::BTreeMap,
+		fmt::{self, Write as _},
+		path::{Path, PathBuf},
+	};
+
+	use anyhow::Context;
+
+	use procmem_access::{prelude::OffsetType, symbols::StableAddress};
+	use procmem_scan::prelude::ScanResultSet;
+
+	pub struct MatchList {
+		pub value_type: String,
+		pub results: ScanResultSet,
+	}
+
+	/// Address of a bookmark, absolute or relative to a module.
+	#[derive(PartialEq, Eq)]
+	pub enum BookmarkAddress {
+		Absolute(u64),
+		Stable(StableAddress),
+	}
+	impl BookmarkAddress {
+		/// Parses a hex offset or a stable address such as `libgame.so+0x1a2b`.
+		pub fn parse(text: &str) -> Option<Self> {
+			match u64::from_str_radix(text.trim_start_matches("0x"), 16) {
+				Ok(offset) => Some(BookmarkAddress::Absolute(offset)),
+				Err(_) => text.parse().ok().map(BookmarkAddress::Stable),
+			}
+		}
+	}
+	impl fmt::Display for BookmarkAddress {
+		fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+			match self {
+				BookmarkAddress::Absolute(offset) => write!(f, "0x{:x}", offset),
+				BookmarkAddress::Stable(address) => write!(f, "{}", address),
+			}
+		}
+	}
+
+	pub struct Bookmark {
+		pub address: BookmarkAddress,
+		pub value_type: String,
+		pub label: String,
+	}
+
+	/// Session stored as text, one `matches <name> <type> <offset>:<length>...` or `bookmark <address> <type> <label>` per line.
+	pub struct Session {
+		path: PathBuf,
+		match_lists: BTreeMap<String, MatchList>,
+		bookmarks: Vec<Bookmark>,
+	}
+	impl Session {
+		/// Returns the path of the session file, `$PROCMEM_SESSION` or `procmem_session.txt` in the working directory.
+		pub fn path() -> PathBuf {
+			std::env::var_os("PROCMEM_SESSION")
+				.map(PathBuf::from)
+				.unwrap_or_else(|| PathBuf::from("procmem_session.txt"))
+		}
+
+		/// Loads the session at `path`, a missing file is an empty session.
+		pub fn load(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+			let mut session = Session {
+				path: path.as_ref().to_path_buf(),
+				match_lists: BTreeMap::new(),
+				bookmarks: Vec::new(),
+			};
+
+			let text = match std::fs::read_to_string(&session.path) {
+				Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(session),
+				result => result.context("Could not read session file")?,
+			};
+			for line in text.lines().filter(|line| !line.trim().is_empty()) {
+				session
+					.parse_line(line)
+					.with_context(|| format!("Invalid session line \"{}\"", line))?;
+			}
+
+			Ok(session)
+		}
+
+		fn parse_line(&mut self, line: &str) -> Option<()> {
+			let mut fields = line.splitn(4, ' ');
+
+			match fields.next()? {
+				"matches" => {
+					let name = fields.next()?;
+					let value_type = fields.next()?;
+					let results = fields
+						.next()
+						.unwrap_or("")
+						.split_whitespace()
+						.map(|result| {
+							let (offset, length) = result.split_once(':')?;
+
+							Some((
+								OffsetType::new(u64::from_str_radix(offset, 16).ok()?)?,
+								length.parse().ok()?,
+							))
+						})
+						.collect::<Option<_>>()?;
+
+					self.save_matches(name, value_type, &results);
+				}
+				"bookmark" => {
+					let address = BookmarkAddress::parse(fields.next()?)?;
+					let value_type = fields.next()?;
+
+					self.bookmark(address, value_type, fields.next().unwrap_or(""));
+				}
+				_ => return None,
+			}
+
+			Some(())
+		}
+
+		/// Writes the session into its file.
+		pub fn store(&self) -> anyhow::Result<()> {
+			let mut text = String::new();
+			for (name, list) in self.match_lists.iter() {
+				write!(text, "matches {} {}", name, list.value_type).unwrap();
+				for (offset, length) in list.results.iter() {
+					write!(text, " {:x}:{}", offset.get(), length).unwrap();
+				}
+				text.push('\n');
+			}
+			for bookmark in self.bookmarks.iter() {
+				writeln!(
+					text,
+					"bookmark {} {} {}",
+					bookmark.address, bookmark.value_type, bookmark.label
+				)
+				.unwrap();
+			}
+
+			std::fs::write(&self.path, text).context("Could not write session file")
+		}
+
+		pub fn match_lists(&self) -> impl Iterator<Item = (&str, &MatchList)> {
+			self.match_lists
+				.iter()
+				.map(|(name, list)| (name.as_str(), list))
+		}
+
+		pub fn match_list(&self, name: &str) -> Option<&MatchList> {
+			self.match_lists.get(name)
+		}
+
+		/// Saves `results` under `name`, replacing a previous list of the same name.
+		pub fn save_matches(&mut self, name: &str, value_type: &str, results: &ScanResultSet) {
+			self.match_lists.insert(
+				name.to_string(),
+				MatchList {
+					value_type: value_type.to_string(),
+					results: results.clone(),
+				},
+			);
+		}
+
+		pub fn bookmarks(&self) -> &[Bookmark] {
+			&self.bookmarks
+		}
+
+		/// Bookmarks `address`, replacing a previous bookmark at the same address.
+		///
+		/// Absolute addresses are sorted by offset and come before stable addresses sorted by module and offset.
+		pub fn bookmark(&mut self, address: BookmarkAddress, value_type: &str, label: &str) {
+			self.bookmarks
+				.retain(|bookmark| bookmark.address != address);
+			self.bookmarks.push(Bookmark {
+				address,
+				value_type: value_type.to_string(),
+				label: label.to_string(),
+			});
+			self.bookmarks
+				.sort_by(|a, b| match (&a.address, &b.address) {
+					(BookmarkAddress::Absolute(a), BookmarkAddress::Absolute(b)) => a.cmp(b),
+					(BookmarkAddress::Absolute(_), BookmarkAddress::Stable(_)) => Ordering::Less,
+					(BookmarkAddress::Stable(_), BookmarkAddress::Absolute(_)) => Ordering::Greater,
+					(BookmarkAddress::Stable(a), BookmarkAddress::Stable(b)) => {
+						(&a.module, a.rva, &a.offsets).cmp(&(&b.module, b.rva, &b.offsets))
+					}
+				});
+		}
+	}
+}
+
+/// Selection of the memory pages which are scanned.
+mod filter {
+	use std::fmt;
+
+	use anyhow::Context;
+
+	use procmem_access::prelude::{MemoryPage, MemoryPageType};
+
+	/// Page types accepted by `filter type`.
+	pub const PAGE_TYPES: [&str; 6] = ["heap", "stack", "anon", "exe", "file", "unknown"];
+
+	fn type_name(page_type: &MemoryPageType) -> &'static str {
+		match page_type {
+			MemoryPageType::Heap => "heap",
+			MemoryPageType::Stack => "stack",
+			MemoryPageType::Anon => "anon",
+			MemoryPageType::ProcessExecutable(_) => "exe",
+			MemoryPageType::File(_) => "file",
+			MemoryPageType::Unknown => "unknown",
+		}
+	}
+
+	fn parse_hex(value: &str) -> anyhow::Result<u64> {
+		u64::from_str_radix(value.trim_start_matches("0x"), 16)
+			.with_context(|| format!("Invalid address \"{}\"", value))
+	}
+
+	pub struct PageFilter {
+		/// Permission letters (`r`, `w`, `x`, `s` or `p`) the page must have.
+		permissions: String,
+		/// Accepted page types, all types if empty.
+		types: Vec<&'static str>,
+		/// Substring of the backing file path.
+		path: Option<String>,
+		/// Only the part of pages in this address range is scanned.
+		range: Option<[u64; 2]>,
+		/// Skips pages mapped at a non-zero file offset.
+		zero_offset: bool,
+	}
+	impl PageFilter {
+		/// Applies one `filter` command, e.g. `perm rw` or `type heap,stack`.
+		pub fn apply(&mut self, command: &str) -> anyhow::Result<()> {
+			let mut arguments = command.split_whitespace();
+
+			match arguments.next() {
+				Some("reset") => *self = Self::default(),
+				Some("perm") => {
+					let permissions = arguments
+						.next()
+						.context("filter permissions are required")?;
+					if let Some(invalid) = permissions.chars().find(|c| !"rwxsp".contains(*c)) {
+						anyhow::bail!(
+							"Invalid permission \"{}\", expected r, w, x, s or p",
+							invalid
+						)
+					}
+					self.permissions = permissions.to_string();
+				}
+				Some("type") => {
+					let types = arguments.next().context("filter types are required")?;
+					self.types = types
+						.split(',')
+						.map(|name| {
+							PAGE_TYPES
+								.into_iter()
+								.find(|known| *known == name)
+								.with_context(|| format!("Unknown page type \"{}\"", name))
+						})
+						.collect::<anyhow::Result<_>>()?;
+					// file-backed pages are rarely mapped at offset zero
+					self.zero_offset = false;
+				}
+				Some("path") => {
+					let path = arguments.next().context("filter path is required")?;
+					self.path = Some(path.to_string());
+					self.zero_offset = false;
+				}
+				Some("range") => {
+					let from =
+						parse_hex(arguments.next().context("filter range start is required")?)?;
+					let to = parse_hex(arguments.next().context("filter range end is required")?)?;
+					if from >= to {
+						anyhow::bail!("Filter range start must be below its end")
+					}
+					self.range = Some([from, to]);
+				}
+				Some(other) => anyhow::bail!("Unknown filter \"{}\"", other),
+				None => anyhow::bail!("filter kind is required"),
+			}
+
+			Ok(())
+		}
+
+		pub fn matches(&self, page: &MemoryPage) -> bool {
+			let permissions = page.permissions;
+			let permissions_match = self.permissions.chars().all(|c| match c {
+				'r' => permissions.read(),
+				'w' => permissions.write(),
+				'x' => permissions.exec(),
+				's' => permissions.shared(),
+				'p' => !permissions.shared(),
+				_ => unreachable!(),
+			});
+
+			let path_matches = match (&self.path, &page.page_type) {
+				(None, _) => true,
+				(
+					Some(pattern),
+					MemoryPageType::ProcessExecutable(path) | MemoryPageType::File(path),
+				) => path.to_string_lossy().contains(pattern.as_str()),
+				(Some(_), _) => false,
+			};
+
+			let range_matches = match self.range {
+				None => true,
+				Some([from, to]) => {
+					page.address_range[0].get() < to && page.address_range[1].get() > from
+				}
+			};
+
+			permissions_match
+				&& (self.types.is_empty() || self.types.contains(&type_name(&page.page_type)))
+				&& path_matches
+				&& range_matches
+				&& (!self.zero_offset || page.offset == 0)
+		}
+
+		/// Clips `range` to the filter address range.
+		pub fn clip(&self, range: [u64; 2]) -> [u64; 2] {
+			match self.range {
+				None => range,
+				Some([from, to]) => [range[0].max(from), range[1].min(to)],
+			}
+		}
+	}
+	impl Default for PageFilter {
+		/// Private writable pages which are not file-backed data, where most values live.
+		fn default() -> Self {
+			Self {
+				permissions: "rwp".to_string(),
+				types: Vec::new(),
+				path: None,
+				range: None,
+				zero_offset: true,
+			}
+		}
+	}
+	impl fmt::Display for PageFilter {
+		fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+			write!(f, "perm {}", self.permissions)?;
+			if !self.types.is_empty() {
+				write!(f, ", type {}", self.types.join(","))?;
+			}
+			if let Some(path) = &self.path {
+				write!(f, ", path {}", path)?;
+			}
+			if let Some([from, to]) = self.range {
+				write!(f, ", range {:x}-{:x}", from, to)?;
+			}
+			if self.zero_offset {
+				write!(f, ", offset 0")?;
+			}
+
+			Ok(())
+		}
+	}
+}
+/// Prints why attaching failed, including the detected obstacles and how to fix them.
+fn print_attach_error(err: &anyhow::Error) {
+	println!("{:#}", err);
+
+	#[cfg(any(target_os = "linux", target_os = "android", target_os = "macos"))]
+	if let Some(PtraceLockError::AttachDenied(denied)) = err.downcast_ref::<PtraceLockError>() {
+		for obstacle in denied.obstacles.iter() {
+			println!("  {}, {}", obstacle, obstacle.suggest_fix());
+		}
 	}
 }
-use app::{App, ScanResult};
+
+/// Parses the `attach` target, which is either a PID or `name:` followed by a process name pattern.
+///
+/// The pattern is a glob if it contains `*` or `?` and a substring otherwise, ambiguous patterns are resolved by an exact name match.
+fn resolve_pid(target: &str) -> anyhow::Result<SimplePid> {
+	let pattern = match target.strip_prefix("name:") {
+		None => return target.parse().context("Invalid PID"),
+		Some(pattern) => pattern,
+	};
+
+	let process = ProcessInfo::find_one_by_name(NamePattern::fuzzy(pattern))
+		.with_context(|| format!("Pattern \"{}\"", pattern))?;
+
+	Ok(process.pid)
+}
+
+use app::{App, ProcessInfo, ScanResult};
+#[cfg(any(target_os = "linux", target_os = "android", target_os = "macos"))]
+use procmem_access::platform::ptrace::PtraceLockError;
+use procmem_access::prelude::OffsetType;
+use procmem_access::{
+	common::{Endianness, NamePattern},
+	platform::simple::SimplePid,
+};
+use procmem_scan::prelude::EndianScalar;
+use session::{BookmarkAddress, Session};
+
+/// Value types accepted by the typed commands.
+const VALUE_TYPES: [&str; 5] = ["i16", "i32", "i64", "f32", "f64"];
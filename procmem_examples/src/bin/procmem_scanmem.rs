@@ -0,0 +1,525 @@
+//! Backend speaking the scanmem command protocol on stdin and stdout, so that frontends written for scanmem,
+//! such as GameConqueror, can drive procmem instead.
+//!
+//! Usage: `procmem_scanmem [PID]`, then one command per line:
+//! * `pid [PID]` prints or changes the target process
+//! * `option scan_data_type int8|int16|int32|int64|float32|float64`, `int` and `number` scan as int32, `float` as float32
+//! * `option region_scan_level 1|2|3`, heap, stack and executable / all writable / all readable pages
+//! * `reset` forgets the matches and reloads the memory map
+//! * `list` prints the matches as `[id] address, region + offset, region type, value, [flags]`
+//! * `set [ID[,ID|FIRST..LAST...]=]VALUE` writes the value to the given or all matches
+//! * `delete ID` forgets one match
+//! * `write TYPE ADDRESS VALUE` writes one value at a hex address
+//! * match commands: `N` or `= N`, `!= N`, `< N`, `> N`, `<= N`, `>= N` compare against a value,
+//!   `=`, `!=`, `<`, `>`, `+`, `-` compare against the previous scan and `+ N`, `- N` match values changed by exactly `N`
+//! * `exit` or `quit`
+//!
+//! Only numeric scans are supported. Informational messages and errors are written to stderr like scanmem does.
+
+use std::{
+	io::{BufRead, Write},
+	str::FromStr,
+};
+
+use anyhow::Context;
+
+use procmem_access::{
+	layout::{format::ValueFormatter, PrimitiveType},
+	platform::simple::{SimpleMemoryAccess, SimpleMemoryLock, SimpleMemoryMap, SimplePid},
+	prelude::{MemoryAccess, MemoryLock, MemoryMap, MemoryPage, MemoryPageType, OffsetType},
+};
+use procmem_scan::{
+	predicate::value::NumericScalar,
+	prelude::{Comparison, ComparisonPredicate, ScanConfig, ScanDriver, ScanResultSet},
+};
+
+/// Value types of `option scan_data_type`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ScanType {
+	I8,
+	I16,
+	I32,
+	I64,
+	F32,
+	F64,
+}
+impl ScanType {
+	/// Parses the names of `option scan_data_type` and the short names of `write`.
+	fn parse(name: &str) -> Option<Self> {
+		let scan_type = match name {
+			"int8" | "i8" => ScanType::I8,
+			"int16" | "i16" => ScanType::I16,
+			"int32" | "i32" | "int" | "number" => ScanType::I32,
+			"int64" | "i64" => ScanType::I64,
+			"float32" | "f32" | "float" => ScanType::F32,
+			"float64" | "f64" => ScanType::F64,
+			_ => return None,
+		};
+
+		Some(scan_type)
+	}
+
+	fn primitive(self) -> PrimitiveType {
+		match self {
+			ScanType::I8 => PrimitiveType::I8,
+			ScanType::I16 => PrimitiveType::I16,
+			ScanType::I32 => PrimitiveType::I32,
+			ScanType::I64 => PrimitiveType::I64,
+			ScanType::F32 => PrimitiveType::F32,
+			ScanType::F64 => PrimitiveType::F64,
+		}
+	}
+
+	/// Flag shown by `list`.
+	fn flag(self) -> &'static str {
+		match self {
+			ScanType::I8 => "I8",
+			ScanType::I16 => "I16",
+			ScanType::I32 => "I32",
+			ScanType::I64 => "I64",
+			ScanType::F32 => "F32",
+			ScanType::F64 => "F64",
+		}
+	}
+}
+
+/// Runs `$body` with `$scalar` aliased to the Rust type of `$scan_type`.
+macro_rules! with_scalar {
+	($scan_type: expr, $scalar: ident => $body: expr) => {
+		match $scan_type {
+			ScanType::I8 => {
+				type $scalar = i8;
+				$body
+			}
+			ScanType::I16 => {
+				type $scalar = i16;
+				$body
+			}
+			ScanType::I32 => {
+				type $scalar = i32;
+				$body
+			}
+			ScanType::I64 => {
+				type $scalar = i64;
+				$body
+			}
+			ScanType::F32 => {
+				type $scalar = f32;
+				$body
+			}
+			ScanType::F64 => {
+				type $scalar = f64;
+				$body
+			}
+		}
+	};
+}
+
+/// Numeric match command, operands are kept as text until the scan type is known.
+#[derive(Debug, Clone, PartialEq)]
+enum MatchCommand {
+	Compare(Comparison, String),
+	Changed,
+	Unchanged,
+	Increased,
+	Decreased,
+	IncreasedBy(String),
+	DecreasedBy(String),
+}
+impl MatchCommand {
+	fn parse(line: &str) -> Option<Self> {
+		// a bare number, including negative ones, is an exact match
+		if line.parse::<f64>().is_ok() {
+			return Some(MatchCommand::Compare(Comparison::Eq, line.to_string()));
+		}
+
+		let operator_length = line
+			.find(|c: char| !"=!<>+-".contains(c))
+			.unwrap_or(line.len());
+		let (operator, operand) = line.split_at(operator_length);
+		let operand = Some(operand.trim())
+			.filter(|operand| !operand.is_empty())
+			.map(str::to_string);
+
+		let command = match (operator, operand) {
+			("+", None) | (">", None) => MatchCommand::Increased,
+			("-", None) | ("<", None) => MatchCommand::Decreased,
+			("=" | "==", None) => MatchCommand::Unchanged,
+			("!=", None) => MatchCommand::Changed,
+			("+", Some(delta)) => MatchCommand::IncreasedBy(delta),
+			("-", Some(delta)) => MatchCommand::DecreasedBy(delta),
+			(operator, Some(value)) => MatchCommand::Compare(operator.parse().ok()?, value),
+			_ => return None,
+		};
+
+		Some(command)
+	}
+}
+
+/// Match with the value read by the scan which found or last filtered it.
+struct Match {
+	offset: OffsetType,
+	value: Vec<u8>,
+}
+
+struct Target {
+	pid: SimplePid,
+	lock: SimpleMemoryLock,
+	map: SimpleMemoryMap,
+	access: SimpleMemoryAccess,
+}
+impl Target {
+	fn attach(pid: SimplePid) -> anyhow::Result<Self> {
+		Ok(Target {
+			pid,
+			lock: SimpleMemoryLock::new(pid)?,
+			map: SimpleMemoryMap::new(pid)?,
+			access: SimpleMemoryAccess::new(pid)?,
+		})
+	}
+}
+
+struct Backend {
+	target: Option<Target>,
+	scan_type: ScanType,
+	region_level: u8,
+	driver: ScanDriver,
+	/// Matches sorted by offset.
+	matches: Vec<Match>,
+}
+impl Backend {
+	fn new() -> Self {
+		Backend {
+			target: None,
+			scan_type: ScanType::I32,
+			region_level: 2,
+			driver: ScanDriver::new(ScanConfig::default()),
+			matches: Vec::new(),
+		}
+	}
+
+	fn command(&mut self, line: &str) -> anyhow::Result<()> {
+		let (name, arguments) = line
+			.split_once(char::is_whitespace)
+			.map(|(name, arguments)| (name, arguments.trim()))
+			.unwrap_or((line, ""));
+
+		match name {
+			"" => (),
+			"pid" if arguments.is_empty() => match self.target.as_ref() {
+				None => println!("no target process"),
+				Some(target) => println!("{}", target.pid),
+			},
+			"pid" => {
+				let pid = arguments.parse().context("invalid pid")?;
+				self.matches.clear();
+				self.target = Some(Target::attach(pid)?);
+			}
+			"option" => self.option(arguments)?,
+			"reset" => {
+				self.matches.clear();
+				if let Some(target) = self.target.as_mut() {
+					target.map = SimpleMemoryMap::new(target.pid)?;
+				}
+			}
+			"list" => self.list(),
+			"set" => self.set(arguments)?,
+			"delete" => {
+				let id: usize = arguments.parse().context("invalid match id")?;
+				anyhow::ensure!(id < self.matches.len(), "no match with id {}", id);
+				self.matches.remove(id);
+			}
+			"write" => self.write(arguments)?,
+			_ => {
+				let command = MatchCommand::parse(line)
+					.with_context(|| format!("unknown command \"{}\"", line))?;
+				with_scalar!(self.scan_type, T => self.scan::<T>(&command)?);
+				eprintln!("info: we currently have {} matches.", self.matches.len());
+			}
+		}
+
+		Ok(())
+	}
+
+	fn option(&mut self, arguments: &str) -> anyhow::Result<()> {
+		let (name, value) = arguments
+			.split_once(char::is_whitespace)
+			.context("option name and value are required")?;
+
+		match name {
+			"scan_data_type" => {
+				self.scan_type = ScanType::parse(value.trim())
+					.with_context(|| format!("unsupported scan data type \"{}\"", value))?;
+				// the values of the matches have the size of the previous type
+				self.matches.clear();
+			}
+			"region_scan_level" => {
+				self.region_level = value
+					.trim()
+					.parse()
+					.ok()
+					.filter(|level| (1..=3).contains(level))
+					.context("region scan level must be 1, 2 or 3")?;
+			}
+			_ => anyhow::bail!("unsupported option \"{}\"", name),
+		}
+
+		Ok(())
+	}
+
+	/// Returns whether `page` is scanned on the current region scan level.
+	fn is_scanned(&self, page: &MemoryPage) -> bool {
+		let permissions = &page.permissions;
+
+		match self.region_level {
+			1 => {
+				permissions.read()
+					&& permissions.write()
+					&& matches!(
+						page.page_type,
+						MemoryPageType::Heap
+							| MemoryPageType::Stack
+							| MemoryPageType::ProcessExecutable(_)
+					)
+			}
+			2 => permissions.read() && permissions.write(),
+			_ => permissions.read(),
+		}
+	}
+
+	fn scan<T: NumericScalar + FromStr>(&mut self, command: &MatchCommand) -> anyhow::Result<()> {
+		let flag = self.scan_type.flag();
+		let parse = |text: &str| {
+			text.parse::<T>()
+				.ok()
+				.with_context(|| format!("invalid {} value \"{}\"", flag, text))
+		};
+
+		// the filter receives the current and the previous value
+		let keep: Box<dyn Fn(T, T) -> bool> = match command {
+			MatchCommand::Compare(comparison, value) => {
+				let (comparison, value) = (*comparison, parse(value)?);
+				Box::new(move |current, _| comparison.compare(&current, &value))
+			}
+			MatchCommand::Changed => Box::new(|current, previous| current != previous),
+			MatchCommand::Unchanged => Box::new(|current, previous| current == previous),
+			MatchCommand::Increased => Box::new(|current, previous| current > previous),
+			MatchCommand::Decreased => Box::new(|current, previous| current < previous),
+			MatchCommand::IncreasedBy(delta) => {
+				let delta = parse(delta)?;
+				Box::new(move |current, previous| current == previous.offset_by(delta))
+			}
+			MatchCommand::DecreasedBy(delta) => {
+				let delta = parse(delta)?;
+				Box::new(move |current, previous| previous == current.offset_by(delta))
+			}
+		};
+
+		let ranges: Vec<[OffsetType; 2]> = {
+			let target = self
+				.target
+				.as_ref()
+				.context("no target process, use `pid PID` first")?;
+
+			MemoryPage::merge_sorted(
+				target
+					.map
+					.pages()
+					.iter()
+					.filter(|page| self.is_scanned(page))
+					.cloned(),
+			)
+			.map(|page| page.address_range)
+			.collect()
+		};
+		let target = self.target.as_mut().unwrap();
+		let size = std::num::NonZeroUsize::new(std::mem::size_of::<T>()).unwrap();
+
+		target.lock.lock()?;
+
+		// without matches every offset is a candidate, so only comparisons against a value can start a scan
+		let results: ScanResultSet = if self.matches.is_empty() {
+			let MatchCommand::Compare(comparison, value) = command else {
+				target.lock.unlock()?;
+				anyhow::bail!("there are no matches to compare against, scan for a value first");
+			};
+
+			let mut found = Vec::new();
+			let predicate = ComparisonPredicate::new(*comparison, parse(value)?, true);
+			let scanned = unsafe {
+				self.driver
+					.scan(&mut target.access, ranges, predicate, |result| {
+						found.push(result)
+					})
+			};
+			if let Err(err) = scanned {
+				target.lock.unlock()?;
+				return Err(err).context("could not read memory");
+			}
+
+			found.into_iter().collect()
+		} else {
+			self.matches
+				.iter()
+				.map(|matched| (matched.offset, size))
+				.collect()
+		};
+
+		// new matches are compared against themselves, which keeps all of them
+		let mut previous = self.matches.iter();
+		let mut kept = Vec::new();
+		unsafe {
+			results.read_values(&mut target.access, |(offset, _), bytes| {
+				let previous = previous.next();
+				if let Ok(bytes) = bytes {
+					let current = T::from_ne_slice(bytes);
+					let previous =
+						previous.map_or(current, |previous| T::from_ne_slice(&previous.value));
+
+					if keep(current, previous) {
+						kept.push(Match {
+							offset,
+							value: bytes.to_vec(),
+						});
+					}
+				}
+			})
+		};
+		self.matches = kept;
+
+		target.lock.unlock()?;
+
+		Ok(())
+	}
+
+	fn list(&self) {
+		let formatter = ValueFormatter::new(self.scan_type.primitive());
+		let pages = self
+			.target
+			.as_ref()
+			.map(|target| target.map.pages())
+			.unwrap_or(&[]);
+
+		for (id, matched) in self.matches.iter().enumerate() {
+			let region = pages
+				.iter()
+				.enumerate()
+				.find(|(_, page)| page.start() <= matched.offset && matched.offset < page.end());
+			let (region_id, region_offset, region_type) = match region {
+				None => (0, matched.offset.get(), "misc"),
+				Some((region_id, page)) => (
+					region_id,
+					matched.offset.get() - page.start().get(),
+					match page.page_type {
+						MemoryPageType::Heap => "heap",
+						MemoryPageType::Stack => "stack",
+						MemoryPageType::ProcessExecutable(_) => "exe",
+						MemoryPageType::File(_) => "code",
+						_ => "misc",
+					},
+				),
+			};
+
+			println!(
+				"[{:2}] {:12x}, {:2} + {:12x}, {:>5}, {}, [{} ]",
+				id,
+				matched.offset.get(),
+				region_id,
+				region_offset,
+				region_type,
+				formatter.format(&matched.value).unwrap_or_default(),
+				self.scan_type.flag()
+			);
+		}
+	}
+
+	/// Parses match ids such as `1,3,5..7`.
+	fn parse_ids(&self, text: &str) -> anyhow::Result<Vec<usize>> {
+		let mut ids = Vec::new();
+		for part in text.split(',').map(str::trim) {
+			let parse = |id: &str| id.trim().parse::<usize>().context("invalid match id");
+			match part.split_once("..") {
+				None => ids.push(parse(part)?),
+				Some((first, last)) => ids.extend(parse(first)?..=parse(last)?),
+			}
+		}
+
+		if let Some(id) = ids.iter().find(|&&id| id >= self.matches.len()) {
+			anyhow::bail!("no match with id {}", id);
+		}
+
+		Ok(ids)
+	}
+
+	fn set(&mut self, arguments: &str) -> anyhow::Result<()> {
+		let (offsets, value): (Vec<OffsetType>, &str) = match arguments.split_once('=') {
+			None => (
+				self.matches.iter().map(|matched| matched.offset).collect(),
+				arguments,
+			),
+			Some((ids, value)) => (
+				self.parse_ids(ids)?
+					.into_iter()
+					.map(|id| self.matches[id].offset)
+					.collect(),
+				value.trim(),
+			),
+		};
+		anyhow::ensure!(!value.contains('/'), "continuous set is not supported");
+		let value = ValueFormatter::new(self.scan_type.primitive()).parse(value)?;
+
+		let target = self.target.as_mut().context("no target process")?;
+		target.lock.lock()?;
+		let written = offsets
+			.into_iter()
+			.try_for_each(|offset| unsafe { target.access.write(offset, &value) });
+		target.lock.unlock()?;
+
+		written.context("could not write memory")
+	}
+
+	fn write(&mut self, arguments: &str) -> anyhow::Result<()> {
+		let mut arguments = arguments.splitn(3, char::is_whitespace);
+		let scan_type = arguments
+			.next()
+			.and_then(ScanType::parse)
+			.context("write type is required")?;
+		let offset = arguments
+			.next()
+			.and_then(|offset| u64::from_str_radix(offset.trim_start_matches("0x"), 16).ok())
+			.and_then(OffsetType::new)
+			.context("write address is required")?;
+		let value = ValueFormatter::new(scan_type.primitive())
+			.parse(arguments.next().context("write value is required")?.trim())?;
+
+		let target = self.target.as_mut().context("no target process")?;
+		target.lock.lock()?;
+		let written = unsafe { target.access.write(offset, &value) };
+		target.lock.unlock()?;
+
+		written.context("could not write memory")
+	}
+}
+
+fn main() -> anyhow::Result<()> {
+	let mut backend = Backend::new();
+	if let Some(pid) = std::env::args().nth(1) {
+		backend.command(&format!("pid {}", pid))?;
+	}
+
+	for line in std::io::stdin().lock().lines() {
+		let line = line?;
+		let line = line.trim();
+		if line == "exit" || line == "quit" {
+			break;
+		}
+
+		if let Err(err) = backend.command(line) {
+			eprintln!("error: {:#}", err);
+		}
+		// frontends wait for the output of each command
+		std::io::stdout().flush()?;
+	}
+
+	Ok(())
+}
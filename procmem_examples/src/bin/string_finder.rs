@@ -1,6 +1,7 @@
 use procmem_access::{
-	platform::simple::{SimpleMemoryAccess, SimpleMemoryLock, SimpleMemoryMap},
-	prelude::{MemoryAccess, MemoryLock, MemoryMap, MemoryPage, MemoryPageType},
+	memory::chunked::ChunkedReader,
+	platform::simple::{SimpleMemoryAccess, SimpleMemoryLock, SimpleMemoryMap, SimplePid},
+	prelude::{MemoryLock, MemoryMap, MemoryPage, MemoryPageType},
 };
 use procmem_scan::prelude::{StreamScanner, ValuePredicate};
 
@@ -11,10 +12,10 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
 		let needle = it.next().unwrap_or_else(|| "\x7FELF".to_string());
 
-		let pid: i32 = it
+		let pid: SimplePid = it
 			.next()
 			.and_then(|s| s.parse().ok())
-			.unwrap_or_else(|| std::process::id() as i32);
+			.unwrap_or_else(|| std::process::id() as SimplePid);
 
 		(needle, pid)
 	};
@@ -39,13 +40,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 			.iter()
 			.filter(|page| {
 				page.permissions.read()
-					&& match page.page_type {
-						MemoryPageType::ProcessExecutable(_) => true,
-						// TODO: macos memory map detection currently cannot categorize pages
-						#[cfg(target_os = "macos")]
-						MemoryPageType::Unknown => true,
-						_ => false,
-					}
+					&& matches!(page.page_type, MemoryPageType::ProcessExecutable(_))
 			})
 			.cloned(),
 	)
@@ -55,43 +50,39 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 	let predicate = ValuePredicate::new(needle.as_str(), true);
 	let mut scanner = StreamScanner::new(predicate);
 
-	// for each page, read it into the buffer then scan the chunk
-	let mut chunk_buffer = Vec::new();
+	// read each page in chunks so that large pages don't need a buffer of their size,
+	// consecutive chunks overlap so that each match is fully contained in one of them
+	let mut reader = ChunkedReader::new(1024 * 1024).with_overlap(needle.len().saturating_sub(1));
 	for page in pages {
-		chunk_buffer.resize(
-			(page.address_range[1].get() - page.address_range[0].get()) as usize,
-			0,
-		);
 		eprintln!("Reading page {}", page);
 		// Safe becasue the process is locked and thus cannot change until we unlock it
 		// although even if we don't lock it, it should be ok to _read_ the memory
 		// there just migh be a data race
-		unsafe {
-			match memory_access.read(page.address_range[0], chunk_buffer.as_mut()) {
-				Ok(()) => (),
-				Err(err) => {
-					eprintln!("could not read memory page {}", err);
-
-					continue;
-				}
-			}
+		let result = unsafe {
+			reader.read(&mut memory_access, page.address_range, |chunk| {
+				// scan the chunk, skipping matches already found in the previous one
+				scanner
+					.scan_once(chunk.offset, chunk.bytes.iter().copied())
+					.for_each(|(offset, len)| {
+						let relative_offset = (offset.get() - chunk.offset.get()) as usize;
+						if chunk.is_repeated(relative_offset, len.get()) {
+							return;
+						}
+
+						println!(
+							"[0x{}]: {}",
+							offset,
+							std::str::from_utf8(
+								&chunk.bytes[relative_offset..relative_offset + len.get()]
+							)
+							.unwrap()
+						);
+					});
+			})
+		};
+		if let Err(err) = result {
+			eprintln!("could not read memory page {}", err);
 		}
-
-		// scan the chunk (one or more conscutive pages at once)
-		scanner
-			.scan_once(page.address_range[0], chunk_buffer.iter().copied())
-			.for_each(|(offset, len)| {
-				let relative_offset = (offset.get() - page.address_range[0].get()) as usize;
-
-				println!(
-					"[0x{}]: {}",
-					offset,
-					std::str::from_utf8(
-						&chunk_buffer[relative_offset..relative_offset + len.get()]
-					)
-					.unwrap()
-				);
-			});
 	}
 
 	// finally unlock the memory so that the process gets unfrozen
@@ -0,0 +1,87 @@
+#ifndef PROCMEM_H
+#define PROCMEM_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define PROCMEM_PERMISSION_READ 1
+#define PROCMEM_PERMISSION_WRITE 2
+#define PROCMEM_PERMISSION_EXEC 4
+#define PROCMEM_PERMISSION_SHARED 8
+
+typedef enum ProcmemStatus {
+	PROCMEM_OK = 0,
+	PROCMEM_NULL_POINTER = 1,
+	PROCMEM_INVALID_ARGUMENT = 2,
+	PROCMEM_PERMISSION_DENIED = 3,
+	PROCMEM_PROCESS_EXITED = 4,
+	PROCMEM_NOT_MAPPED = 5,
+	PROCMEM_PARSE = 6,
+	PROCMEM_PLATFORM = 7,
+	PROCMEM_PANIC = 8
+} ProcmemStatus;
+
+typedef struct ProcmemPage {
+	uint64_t start;
+	uint64_t end;
+	uint64_t offset;
+	/* combination of PROCMEM_PERMISSION_* */
+	uint32_t permissions;
+} ProcmemPage;
+
+/* Opaque session handle. Must only be used from the thread which created it. */
+typedef struct ProcmemSession ProcmemSession;
+
+/* Message of the last error on this thread or NULL. Valid until the next call on this thread. */
+const char *procmem_last_error_message(void);
+
+ProcmemStatus procmem_attach(int32_t pid, ProcmemSession **out_session);
+void procmem_detach(ProcmemSession *session);
+int32_t procmem_pid(const ProcmemSession *session);
+
+/* Locks are counted, reads, writes and scans lock the process on their own. */
+ProcmemStatus procmem_lock(ProcmemSession *session);
+ProcmemStatus procmem_unlock(ProcmemSession *session);
+
+ProcmemStatus procmem_reload_pages(ProcmemSession *session);
+size_t procmem_page_count(const ProcmemSession *session);
+ProcmemStatus procmem_page_get(const ProcmemSession *session, size_t index, ProcmemPage *out_page);
+
+ProcmemStatus procmem_read(ProcmemSession *session, uint64_t address, uint8_t *buffer, size_t len);
+ProcmemStatus procmem_write(ProcmemSession *session, uint64_t address, const uint8_t *data, size_t len);
+
+/*
+ * Scans pages which have all `permissions` flags, skipping unreadable ones. Up to `capacity` addresses are written to `results`,
+ * the total number of matches is written to `out_count`.
+ */
+ProcmemStatus procmem_scan_value(
+	ProcmemSession *session,
+	const uint8_t *value,
+	size_t len,
+	size_t alignment,
+	uint32_t permissions,
+	uint64_t *results,
+	size_t capacity,
+	size_t *out_count
+);
+/* Bytes whose `mask` byte is zero are wildcards. */
+ProcmemStatus procmem_scan_pattern(
+	ProcmemSession *session,
+	const uint8_t *bytes,
+	const uint8_t *mask,
+	size_t len,
+	uint32_t permissions,
+	uint64_t *results,
+	size_t capacity,
+	size_t *out_count
+);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
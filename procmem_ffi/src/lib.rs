@@ -0,0 +1,719 @@
+//! C ABI for procmem.
+//!
+//! All functions operate on an opaque [`ProcmemSession`] created by [`procmem_attach`] and destroyed by [`procmem_detach`].
+//! Fallible functions return a [`ProcmemStatus`] and store a description of the failure which can be retrieved
+//! with [`procmem_last_error_message`]. The matching C header is `include/procmem.h`.
+//!
+//! Sessions use ptrace based locks on some platforms, so a session must only be used from the thread which created it.
+
+use std::{
+	cell::RefCell,
+	ffi::{c_char, CString},
+	panic::{catch_unwind, AssertUnwindSafe},
+	ptr,
+};
+
+use procmem_access::{
+	platform::simple::{SimpleMemoryAccess, SimpleMemoryLock, SimpleMemoryMap, SimplePid},
+	prelude::{
+		ErrorKind, MemoryAccess, MemoryLock, MemoryMap, MemoryPage, MemoryPagePermissions,
+		OffsetType, ProcmemError,
+	},
+};
+use procmem_scan::prelude::{
+	ByteComparable, PatternPredicate, ScanConfig, ScanDriver, ValuePredicate,
+};
+
+pub const PROCMEM_PERMISSION_READ: u32 = 1;
+pub const PROCMEM_PERMISSION_WRITE: u32 = 2;
+pub const PROCMEM_PERMISSION_EXEC: u32 = 4;
+pub const PROCMEM_PERMISSION_SHARED: u32 = 8;
+
+/// Status code returned by fallible functions.
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ProcmemStatus {
+	Ok = 0,
+	NullPointer = 1,
+	InvalidArgument = 2,
+	PermissionDenied = 3,
+	ProcessExited = 4,
+	NotMapped = 5,
+	Parse = 6,
+	Platform = 7,
+	Panic = 8,
+}
+impl From<ErrorKind> for ProcmemStatus {
+	fn from(kind: ErrorKind) -> Self {
+		match kind {
+			ErrorKind::PermissionDenied => ProcmemStatus::PermissionDenied,
+			ErrorKind::ProcessExited => ProcmemStatus::ProcessExited,
+			ErrorKind::NotMapped => ProcmemStatus::NotMapped,
+			ErrorKind::Parse => ProcmemStatus::Parse,
+			ErrorKind::Platform => ProcmemStatus::Platform,
+		}
+	}
+}
+
+/// Memory page as seen from C.
+///
+/// `permissions` is a combination of the `PROCMEM_PERMISSION_*` flags.
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ProcmemPage {
+	pub start: u64,
+	pub end: u64,
+	pub offset: u64,
+	pub permissions: u32,
+}
+impl From<&MemoryPage> for ProcmemPage {
+	fn from(page: &MemoryPage) -> Self {
+		ProcmemPage {
+			start: page.start().get(),
+			end: page.end().get(),
+			offset: page.offset,
+			permissions: permission_flags(page.permissions),
+		}
+	}
+}
+
+fn permission_flags(permissions: MemoryPagePermissions) -> u32 {
+	let mut flags = 0;
+	if permissions.read() {
+		flags |= PROCMEM_PERMISSION_READ;
+	}
+	if permissions.write() {
+		flags |= PROCMEM_PERMISSION_WRITE;
+	}
+	if permissions.exec() {
+		flags |= PROCMEM_PERMISSION_EXEC;
+	}
+	if permissions.shared() {
+		flags |= PROCMEM_PERMISSION_SHARED;
+	}
+
+	flags
+}
+
+/// Opaque handle to an attached process.
+pub struct ProcmemSession {
+	/// Pid as passed through the C interface, Windows process ids are reinterpreted as unsigned.
+	pid: i32,
+	lock: SimpleMemoryLock,
+	map: SimpleMemoryMap,
+	access: SimpleMemoryAccess,
+	driver: ScanDriver,
+}
+impl ProcmemSession {
+	fn new(pid: i32) -> Result<Self, ProcmemError> {
+		Ok(ProcmemSession {
+			pid,
+			lock: SimpleMemoryLock::new(pid as SimplePid)?,
+			map: SimpleMemoryMap::new(pid as SimplePid)?,
+			access: SimpleMemoryAccess::new(pid as SimplePid)?,
+			driver: ScanDriver::new(ScanConfig {
+				skip_unreadable: true,
+				..Default::default()
+			}),
+		})
+	}
+
+	/// Runs `fun` with the process locked.
+	///
+	/// Locks are counted, so this keeps the process stopped if the caller locked it explicitly.
+	fn locked<T>(
+		&mut self,
+		fun: impl FnOnce(&mut Self) -> Result<T, ProcmemError>,
+	) -> Result<T, ProcmemError> {
+		self.lock.lock()?;
+		let result = fun(self);
+		self.lock.unlock()?;
+
+		result
+	}
+
+	/// Merged address ranges of pages which have all `permissions` flags.
+	fn ranges(&self, permissions: u32) -> Vec<[OffsetType; 2]> {
+		MemoryPage::merge_sorted(
+			self.map
+				.pages()
+				.iter()
+				.filter(|page| permission_flags(page.permissions) & permissions == permissions)
+				.cloned(),
+		)
+		.map(|page| page.address_range)
+		.collect()
+	}
+}
+
+/// Byte value with an explicit alignment.
+struct AlignedBytes<'a> {
+	bytes: &'a [u8],
+	alignment: usize,
+}
+impl ByteComparable for AlignedBytes<'_> {
+	fn as_bytes(&self) -> &[u8] {
+		self.bytes
+	}
+
+	fn align_of(&self) -> usize {
+		self.alignment
+	}
+}
+
+/// Writes found offsets into the caller provided buffer while counting all of them.
+struct ResultSink {
+	results: *mut u64,
+	capacity: usize,
+	count: usize,
+}
+impl ResultSink {
+	fn push(&mut self, offset: OffsetType) {
+		if self.count < self.capacity {
+			unsafe { self.results.add(self.count).write(offset.get()) };
+		}
+		self.count += 1;
+	}
+}
+
+thread_local! {
+	static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn set_last_error(message: impl std::fmt::Display) {
+	// interior nul bytes would truncate the message, so replace them
+	let message = message.to_string().replace('\0', "\\0");
+	LAST_ERROR.with(|last| *last.borrow_mut() = CString::new(message).ok());
+}
+
+fn error_status(err: ProcmemError) -> ProcmemStatus {
+	let status = err.kind().into();
+
+	let mut message = err.to_string();
+	let mut source = std::error::Error::source(&err);
+	while let Some(err) = source {
+		message.push_str(": ");
+		message.push_str(&err.to_string());
+		source = err.source();
+	}
+	set_last_error(message);
+
+	status
+}
+
+fn invalid_argument(message: &str) -> ProcmemStatus {
+	set_last_error(message);
+	ProcmemStatus::InvalidArgument
+}
+
+/// Runs `fun` and converts its result and any panic into a status.
+fn guard(fun: impl FnOnce() -> Result<(), ProcmemStatus>) -> ProcmemStatus {
+	LAST_ERROR.with(|last| *last.borrow_mut() = None);
+
+	match catch_unwind(AssertUnwindSafe(fun)) {
+		Ok(Ok(())) => ProcmemStatus::Ok,
+		Ok(Err(status)) => status,
+		Err(panic) => {
+			let message = panic
+				.downcast_ref::<&str>()
+				.copied()
+				.or_else(|| panic.downcast_ref::<String>().map(String::as_str))
+				.unwrap_or("unknown panic");
+			set_last_error(format_args!("panic: {}", message));
+
+			ProcmemStatus::Panic
+		}
+	}
+}
+
+unsafe fn session_mut<'a>(
+	session: *mut ProcmemSession,
+) -> Result<&'a mut ProcmemSession, ProcmemStatus> {
+	session.as_mut().ok_or_else(|| {
+		set_last_error("session is null");
+		ProcmemStatus::NullPointer
+	})
+}
+
+fn offset(address: u64) -> Result<OffsetType, ProcmemStatus> {
+	OffsetType::new(address).ok_or_else(|| invalid_argument("address must not be zero"))
+}
+
+/// Returns the message describing the last error on this thread, or null if the last call succeeded.
+///
+/// The string is valid until the next call into this library on the same thread.
+#[no_mangle]
+pub extern "C" fn procmem_last_error_message() -> *const c_char {
+	LAST_ERROR.with(|last| {
+		last.borrow()
+			.as_ref()
+			.map(|message| message.as_ptr())
+			.unwrap_or(ptr::null())
+	})
+}
+
+/// Attaches to process `pid` and stores the new session in `out_session`.
+///
+/// ## Safety
+/// * `out_session` must be a valid pointer to writable memory
+#[no_mangle]
+pub unsafe extern "C" fn procmem_attach(
+	pid: i32,
+	out_session: *mut *mut ProcmemSession,
+) -> ProcmemStatus {
+	guard(|| {
+		if out_session.is_null() {
+			set_last_error("out_session is null");
+			return Err(ProcmemStatus::NullPointer);
+		}
+
+		let session = ProcmemSession::new(pid).map_err(error_status)?;
+		out_session.write(Box::into_raw(Box::new(session)));
+
+		Ok(())
+	})
+}
+
+/// Detaches from the process and frees the session. Null is ignored.
+///
+/// ## Safety
+/// * `session` must be null or a session returned by [`procmem_attach`] which was not yet detached
+#[no_mangle]
+pub unsafe extern "C" fn procmem_detach(session: *mut ProcmemSession) {
+	if session.is_null() {
+		return;
+	}
+
+	let _ = catch_unwind(AssertUnwindSafe(|| drop(Box::from_raw(session))));
+}
+
+/// Returns the pid of the session process, or `-1` if `session` is null.
+///
+/// ## Safety
+/// * `session` must be null or a valid session
+#[no_mangle]
+pub unsafe extern "C" fn procmem_pid(session: *const ProcmemSession) -> i32 {
+	session.as_ref().map(|session| session.pid).unwrap_or(-1)
+}
+
+/// Stops the process until the matching [`procmem_unlock`].
+///
+/// Locks are counted, reads, writes and scans lock the process on their own when it is not locked.
+///
+/// ## Safety
+/// * `session` must be a valid session
+#[no_mangle]
+pub unsafe extern "C" fn procmem_lock(session: *mut ProcmemSession) -> ProcmemStatus {
+	guard(|| {
+		let session = session_mut(session)?;
+		session
+			.lock
+			.lock()
+			.map_err(|err| error_status(err.into()))?;
+
+		Ok(())
+	})
+}
+
+/// Resumes the process stopped by [`procmem_lock`].
+///
+/// ## Safety
+/// * `session` must be a valid session
+#[no_mangle]
+pub unsafe extern "C" fn procmem_unlock(session: *mut ProcmemSession) -> ProcmemStatus {
+	guard(|| {
+		let session = session_mut(session)?;
+		session
+			.lock
+			.unlock()
+			.map_err(|err| error_status(err.into()))?;
+
+		Ok(())
+	})
+}
+
+/// Reloads the memory map of the process.
+///
+/// ## Safety
+/// * `session` must be a valid session
+#[no_mangle]
+pub unsafe extern "C" fn procmem_reload_pages(session: *mut ProcmemSession) -> ProcmemStatus {
+	guard(|| {
+		let session = session_mut(session)?;
+		session.map = SimpleMemoryMap::new(session.pid as SimplePid)
+			.map_err(|err| error_status(err.into()))?;
+
+		Ok(())
+	})
+}
+
+/// Returns the number of pages loaded by [`procmem_attach`] or [`procmem_reload_pages`], or `0` if `session` is null.
+///
+/// ## Safety
+/// * `session` must be null or a valid session
+#[no_mangle]
+pub unsafe extern "C" fn procmem_page_count(session: *const ProcmemSession) -> usize {
+	session
+		.as_ref()
+		.map(|session| session.map.pages().len())
+		.unwrap_or(0)
+}
+
+/// Stores the page at `index` in `out_page`.
+///
+/// ## Safety
+/// * `session` must be a valid session
+/// * `out_page` must be a valid pointer to writable memory
+#[no_mangle]
+pub unsafe extern "C" fn procmem_page_get(
+	session: *const ProcmemSession,
+	index: usize,
+	out_page: *mut ProcmemPage,
+) -> ProcmemStatus {
+	guard(|| {
+		let session = session_mut(session as *mut ProcmemSession)?;
+		if out_page.is_null() {
+			set_last_error("out_page is null");
+			return Err(ProcmemStatus::NullPointer);
+		}
+
+		let page = session
+			.map
+			.pages()
+			.get(index)
+			.ok_or_else(|| invalid_argument("page index out of bounds"))?;
+		out_page.write(page.into());
+
+		Ok(())
+	})
+}
+
+/// Reads `len` bytes at `address` into `buffer`.
+///
+/// ## Safety
+/// * `session` must be a valid session
+/// * `buffer` must be valid for writes of `len` bytes
+/// * See [`MemoryAccess::read`]
+#[no_mangle]
+pub unsafe extern "C" fn procmem_read(
+	session: *mut ProcmemSession,
+	address: u64,
+	buffer: *mut u8,
+	len: usize,
+) -> ProcmemStatus {
+	guard(|| {
+		let session = session_mut(session)?;
+		if len == 0 {
+			return Ok(());
+		}
+		if buffer.is_null() {
+			set_last_error("buffer is null");
+			return Err(ProcmemStatus::NullPointer);
+		}
+		let offset = offset(address)?;
+
+		let buffer = std::slice::from_raw_parts_mut(buffer, len);
+		session
+			.locked(|session| Ok(session.access.read(offset, buffer)?))
+			.map_err(error_status)
+	})
+}
+
+/// Writes `len` bytes from `data` to `address`.
+///
+/// ## Safety
+/// * `session` must be a valid session
+/// * `data` must be valid for reads of `len` bytes
+/// * See [`MemoryAccess::write`]
+#[no_mangle]
+pub unsafe extern "C" fn procmem_write(
+	session: *mut ProcmemSession,
+	address: u64,
+	data: *const u8,
+	len: usize,
+) -> ProcmemStatus {
+	guard(|| {
+		let session = session_mut(session)?;
+		if len == 0 {
+			return Ok(());
+		}
+		if data.is_null() {
+			set_last_error("data is null");
+			return Err(ProcmemStatus::NullPointer);
+		}
+		let offset = offset(address)?;
+
+		let data = std::slice::from_raw_parts(data, len);
+		session
+			.locked(|session| Ok(session.access.write(offset, data)?))
+			.map_err(error_status)
+	})
+}
+
+/// Scans pages with all `permissions` flags for the bytes of `value`.
+///
+/// Pages which cannot be read (such as `[vvar]` on Linux) are skipped.
+/// Only offsets divisible by `alignment` are considered, `0` and `1` disable alignment.
+/// Up to `capacity` found addresses are written to `results` in ascending order
+/// and the total number of matches is stored in `out_count`, so the scan can be repeated with a larger buffer.
+///
+/// ## Safety
+/// * `session` must be a valid session
+/// * `value` must be valid for reads of `len` bytes
+/// * `results` must be valid for writes of `capacity` values, it may be null if `capacity` is zero
+/// * `out_count` must be a valid pointer to writable memory
+/// * See [`MemoryAccess::read`]
+#[no_mangle]
+pub unsafe extern "C" fn procmem_scan_value(
+	session: *mut ProcmemSession,
+	value: *const u8,
+	len: usize,
+	alignment: usize,
+	permissions: u32,
+	results: *mut u64,
+	capacity: usize,
+	out_count: *mut usize,
+) -> ProcmemStatus {
+	guard(|| {
+		let session = session_mut(session)?;
+		if value.is_null() || out_count.is_null() || (results.is_null() && capacity > 0) {
+			set_last_error("value, results or out_count is null");
+			return Err(ProcmemStatus::NullPointer);
+		}
+		if len == 0 {
+			return Err(invalid_argument("value must not be empty"));
+		}
+
+		let predicate = ValuePredicate::new(
+			AlignedBytes {
+				bytes: std::slice::from_raw_parts(value, len),
+				alignment: alignment.max(1),
+			},
+			alignment > 1,
+		);
+		let mut sink = ResultSink {
+			results,
+			capacity,
+			count: 0,
+		};
+		session
+			.locked(|session| {
+				let ranges = session.ranges(permissions);
+				session
+					.driver
+					.scan(&mut session.access, ranges, predicate, |(offset, _)| {
+						sink.push(offset)
+					})?;
+
+				Ok(())
+			})
+			.map_err(error_status)?;
+		out_count.write(sink.count);
+
+		Ok(())
+	})
+}
+
+/// Scans pages with all `permissions` flags for a byte pattern with wildcards.
+///
+/// Bytes of `bytes` whose corresponding `mask` byte is zero match any byte.
+/// Unreadable pages are skipped and the results are reported the same way as in [`procmem_scan_value`].
+///
+/// ## Safety
+/// * `session` must be a valid session
+/// * `bytes` and `mask` must be valid for reads of `len` bytes
+/// * `results` must be valid for writes of `capacity` values, it may be null if `capacity` is zero
+/// * `out_count` must be a valid pointer to writable memory
+/// * See [`MemoryAccess::read`]
+#[no_mangle]
+pub unsafe extern "C" fn procmem_scan_pattern(
+	session: *mut ProcmemSession,
+	bytes: *const u8,
+	mask: *const u8,
+	len: usize,
+	permissions: u32,
+	results: *mut u64,
+	capacity: usize,
+	out_count: *mut usize,
+) -> ProcmemStatus {
+	guard(|| {
+		let session = session_mut(session)?;
+		if bytes.is_null()
+			|| mask.is_null()
+			|| out_count.is_null()
+			|| (results.is_null() && capacity > 0)
+		{
+			set_last_error("bytes, mask, results or out_count is null");
+			return Err(ProcmemStatus::NullPointer);
+		}
+		if len == 0 {
+			return Err(invalid_argument("pattern must not be empty"));
+		}
+
+		let masks: Vec<u8> = std::slice::from_raw_parts(mask, len)
+			.iter()
+			.map(|&m| if m != 0 { 0xFF } else { 0 })
+			.collect();
+		let predicate = PatternPredicate::new(std::slice::from_raw_parts(bytes, len), &masks);
+
+		let mut sink = ResultSink {
+			results,
+			capacity,
+			count: 0,
+		};
+		session
+			.locked(|session| {
+				let ranges = session.ranges(permissions);
+				session
+					.driver
+					.scan(&mut session.access, ranges, predicate, |(offset, _)| {
+						sink.push(offset)
+					})?;
+
+				Ok(())
+			})
+			.map_err(error_status)?;
+		out_count.write(sink.count);
+
+		Ok(())
+	})
+}
+
+#[cfg(test)]
+mod test {
+	use std::ptr;
+
+	use super::{
+		procmem_attach, procmem_detach, procmem_last_error_message, procmem_page_count,
+		procmem_page_get, procmem_read, procmem_scan_pattern, procmem_scan_value, ProcmemPage,
+		ProcmemSession, ProcmemStatus, PROCMEM_PERMISSION_EXEC, PROCMEM_PERMISSION_READ,
+	};
+
+	struct KillOnDrop(std::process::Child);
+	impl Drop for KillOnDrop {
+		fn drop(&mut self) {
+			let _ = self.0.kill();
+			let _ = self.0.wait();
+		}
+	}
+
+	#[test]
+	fn test_ffi_session() {
+		let child = KillOnDrop(
+			std::process::Command::new("sleep")
+				.arg("30")
+				.spawn()
+				.unwrap(),
+		);
+		// give the loader time to map the executable
+		std::thread::sleep(std::time::Duration::from_millis(100));
+
+		let mut session: *mut ProcmemSession = ptr::null_mut();
+		unsafe {
+			assert_eq!(
+				procmem_attach(child.0.id() as i32, &mut session),
+				ProcmemStatus::Ok
+			);
+			assert!(procmem_last_error_message().is_null());
+
+			let count = procmem_page_count(session);
+			assert!(count > 0);
+
+			// the executable is mapped first and starts with the ELF header
+			let mut page = ProcmemPage {
+				start: 0,
+				end: 0,
+				offset: 0,
+				permissions: 0,
+			};
+			assert_eq!(procmem_page_get(session, 0, &mut page), ProcmemStatus::Ok);
+			assert_eq!(
+				procmem_page_get(session, count, &mut page),
+				ProcmemStatus::InvalidArgument
+			);
+			assert!(!procmem_last_error_message().is_null());
+			assert_eq!(procmem_page_get(session, 0, &mut page), ProcmemStatus::Ok);
+
+			let mut magic = [0u8; 4];
+			assert_eq!(
+				procmem_read(session, page.start, magic.as_mut_ptr(), magic.len()),
+				ProcmemStatus::Ok
+			);
+			assert_eq!(&magic, b"\x7fELF");
+			assert_eq!(
+				procmem_read(session, 0, magic.as_mut_ptr(), magic.len()),
+				ProcmemStatus::InvalidArgument
+			);
+
+			let mut results = [0u64; 4];
+			let mut found = 0;
+			assert_eq!(
+				procmem_scan_value(
+					session,
+					magic.as_ptr(),
+					magic.len(),
+					0x1000,
+					PROCMEM_PERMISSION_READ,
+					results.as_mut_ptr(),
+					results.len(),
+					&mut found
+				),
+				ProcmemStatus::Ok
+			);
+			assert!(found > 0);
+			assert_eq!(results[0], page.start);
+
+			// "?ELF" must find at least the same headers
+			let mask = [0u8, 1, 1, 1];
+			let mut pattern_found = 0;
+			assert_eq!(
+				procmem_scan_pattern(
+					session,
+					magic.as_ptr(),
+					mask.as_ptr(),
+					magic.len(),
+					PROCMEM_PERMISSION_READ,
+					results.as_mut_ptr(),
+					results.len(),
+					&mut pattern_found
+				),
+				ProcmemStatus::Ok
+			);
+			assert!(pattern_found >= found);
+			assert_eq!(results[0], page.start);
+
+			// counting only
+			assert_eq!(
+				procmem_scan_value(
+					session,
+					magic.as_ptr(),
+					magic.len(),
+					0,
+					PROCMEM_PERMISSION_READ | PROCMEM_PERMISSION_EXEC,
+					ptr::null_mut(),
+					0,
+					&mut found
+				),
+				ProcmemStatus::Ok
+			);
+
+			procmem_detach(session);
+		}
+	}
+
+	#[test]
+	fn test_ffi_null() {
+		unsafe {
+			assert_eq!(
+				procmem_attach(1, ptr::null_mut()),
+				ProcmemStatus::NullPointer
+			);
+			assert_eq!(
+				procmem_read(ptr::null_mut(), 1, ptr::null_mut(), 1),
+				ProcmemStatus::NullPointer
+			);
+			assert_eq!(procmem_page_count(ptr::null()), 0);
+			procmem_detach(ptr::null_mut());
+		}
+	}
+}
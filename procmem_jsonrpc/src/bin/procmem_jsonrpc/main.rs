@@ -1,3 +1,34 @@
+use procmem_jsonrpc::{
+	framing::Framing,
+	server::{Endpoint, Server},
+	simple::{dispatcher, SimpleState},
+};
+
 fn main() {
-	
-}
\ No newline at end of file
+	// simple cli parse: <endpoint> [--content-length]
+	let (endpoint, framing) = {
+		let mut endpoint = None;
+		let mut framing = Framing::NewlineDelimited;
+
+		for arg in std::env::args().skip(1) {
+			match arg.as_str() {
+				"--content-length" => framing = Framing::ContentLength,
+				_ => {
+					endpoint = Some(
+						arg.parse::<Endpoint>()
+							.unwrap_or_else(|err| panic!("{}", err)),
+					)
+				}
+			}
+		}
+
+		(
+			endpoint.unwrap_or_else(|| Endpoint::Tcp("127.0.0.1:8162".into())),
+			framing,
+		)
+	};
+	eprintln!("listening on {:?} with {:?} framing", endpoint, framing);
+
+	let server = Server::new(dispatcher(), SimpleState::new()).with_framing(framing);
+	server.listen(&endpoint).expect("server failed");
+}
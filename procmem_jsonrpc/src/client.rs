@@ -0,0 +1,433 @@
+//! Typed client calling procedures over a [`Transport`].
+//!
+//! The [`RpcClient`] assigns each request a unique id, waits for the response with the same id
+//! and parses it into the result or error type of the procedure. Many calls can be sent in one round trip
+//! as a [`Batch`].
+
+use std::{
+	borrow::Cow,
+	collections::HashMap,
+	io::{BufReader, Read, Write},
+	marker::PhantomData,
+	net::{TcpStream, ToSocketAddrs},
+};
+
+use serde_json::{value::RawValue, Value};
+use thiserror::Error;
+
+use crate::{
+	framing::Framing,
+	procedures::Procedure,
+	rpc::{client, ClientId, FromJson, FromRpcError, IntoJson},
+};
+
+/// Channel used by the client to exchange serialized messages with a server.
+pub trait Transport {
+	fn send(&mut self, message: &str) -> std::io::Result<()>;
+
+	/// Receives the next message, returns `None` if the server closed the channel.
+	fn receive(&mut self) -> std::io::Result<Option<String>>;
+}
+
+/// Transport over a byte stream, such as a TCP or Unix domain socket.
+pub struct StreamTransport<R: Read, W: Write> {
+	reader: BufReader<R>,
+	writer: W,
+	framing: Framing,
+}
+impl<R: Read, W: Write> StreamTransport<R, W> {
+	pub fn new(reader: R, writer: W, framing: Framing) -> Self {
+		StreamTransport {
+			reader: BufReader::new(reader),
+			writer,
+			framing,
+		}
+	}
+}
+impl StreamTransport<TcpStream, TcpStream> {
+	pub fn connect_tcp(address: impl ToSocketAddrs, framing: Framing) -> std::io::Result<Self> {
+		let stream = TcpStream::connect(address)?;
+
+		Ok(Self::new(stream.try_clone()?, stream, framing))
+	}
+}
+#[cfg(unix)]
+impl StreamTransport<std::os::unix::net::UnixStream, std::os::unix::net::UnixStream> {
+	pub fn connect_unix(
+		path: impl AsRef<std::path::Path>,
+		framing: Framing,
+	) -> std::io::Result<Self> {
+		let stream = std::os::unix::net::UnixStream::connect(path)?;
+
+		Ok(Self::new(stream.try_clone()?, stream, framing))
+	}
+}
+impl<R: Read, W: Write> Transport for StreamTransport<R, W> {
+	fn send(&mut self, message: &str) -> std::io::Result<()> {
+		self.framing.write_message(&mut self.writer, message)
+	}
+
+	fn receive(&mut self) -> std::io::Result<Option<String>> {
+		self.framing.read_message(&mut self.reader)
+	}
+}
+
+#[derive(Debug, Error)]
+pub enum ClientError {
+	#[error("transport failed")]
+	Transport(#[from] std::io::Error),
+	#[error("server closed the connection")]
+	Closed,
+	#[error("could not serialize or parse message")]
+	Json(#[from] serde_json::Error),
+	/// Error response which is not an error of the procedure, such as a predefined error.
+	#[error("call failed with code {code}: {message}")]
+	Call {
+		code: isize,
+		message: String,
+		data: Option<Value>,
+	},
+	#[error("server sent no response to request {0}")]
+	MissingResponse(isize),
+}
+impl ClientError {
+	fn from_response_error(error: client::ResponseError) -> Self {
+		ClientError::Call {
+			code: error.code,
+			message: error.message.to_string(),
+			data: error
+				.data
+				.and_then(|data| serde_json::from_str(data.get()).ok()),
+		}
+	}
+}
+
+/// Parses the result or error of procedure `P` from its response.
+fn parse_response<P: Procedure>(
+	response: client::Response,
+) -> Result<Result<P::Result, P::Error>, ClientError> {
+	match response.error {
+		// a null result is parsed as a missing one
+		None => {
+			let result = response.result.map(|result| result.get()).unwrap_or("null");
+			Ok(Ok(serde_json::from_str(result)?))
+		}
+		Some(error) => match P::Error::from_rpc_error(error.code, error.data) {
+			Some(error) => Ok(Err(error)),
+			None => Err(ClientError::from_response_error(error)),
+		},
+	}
+}
+
+/// Client calling typed procedures.
+pub struct RpcClient<T: Transport> {
+	transport: T,
+	next_id: isize,
+}
+impl<T: Transport> RpcClient<T> {
+	pub fn new(transport: T) -> Self {
+		RpcClient {
+			transport,
+			next_id: 1,
+		}
+	}
+
+	pub fn transport(&self) -> &T {
+		&self.transport
+	}
+
+	pub fn into_transport(self) -> T {
+		self.transport
+	}
+
+	/// Calls procedure `P` and waits for its response.
+	///
+	/// Responses with other ids, such as errors of earlier notifications, are skipped.
+	pub fn call<P: Procedure>(
+		&mut self,
+		params: P,
+	) -> Result<Result<P::Result, P::Error>, ClientError> {
+		let id = self.next_id();
+		let request = client::Request::new(P::NAME.into(), Some(params), ClientId::Number(id));
+		self.transport.send(&request.into_json()?)?;
+
+		loop {
+			let message = self.transport.receive()?.ok_or(ClientError::Closed)?;
+			let response = client::Response::from_json_str(&message)?;
+			if response.id != Some(ClientId::Number(id)) {
+				continue;
+			}
+
+			return parse_response::<P>(response);
+		}
+	}
+
+	/// Calls procedure `P` without waiting for a response.
+	pub fn notify<P: Procedure>(&mut self, params: P) -> Result<(), ClientError> {
+		let request = client::Request::new_notification(Cow::Borrowed(P::NAME), Some(params));
+		self.transport.send(&request.into_json()?)?;
+
+		Ok(())
+	}
+
+	/// Starts a batch of calls which are sent together in one message.
+	pub fn batch(&mut self) -> Batch<'_, T> {
+		Batch {
+			client: self,
+			requests: client::Batch::new(),
+			ids: Vec::new(),
+		}
+	}
+
+	fn next_id(&mut self) -> isize {
+		let id = self.next_id;
+		self.next_id = self.next_id.wrapping_add(1);
+
+		id
+	}
+}
+
+/// Calls collected by [`RpcClient::batch`] and sent in one round trip by [`send`](Batch::send).
+pub struct Batch<'c, T: Transport> {
+	client: &'c mut RpcClient<T>,
+	requests: client::Batch,
+	/// Ids of the calls which expect a response.
+	ids: Vec<isize>,
+}
+impl<'c, T: Transport> Batch<'c, T> {
+	/// Adds a call of procedure `P`, its result is taken from the [`BatchResponses`] using the returned handle.
+	pub fn call<P: Procedure>(&mut self, params: P) -> Result<BatchCall<P>, ClientError> {
+		let id = self.client.next_id();
+		self.requests.push(&client::Request::new(
+			P::NAME.into(),
+			Some(params),
+			ClientId::Number(id),
+		))?;
+		self.ids.push(id);
+
+		Ok(BatchCall {
+			id,
+			procedure: PhantomData,
+		})
+	}
+
+	/// Adds a call of procedure `P` without a response.
+	pub fn notify<P: Procedure>(&mut self, params: P) -> Result<(), ClientError> {
+		self.requests.push(&client::Request::new_notification(
+			Cow::Borrowed(P::NAME),
+			Some(params),
+		))?;
+
+		Ok(())
+	}
+
+	pub fn len(&self) -> usize {
+		self.requests.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.requests.is_empty()
+	}
+
+	/// Sends the batch and waits for the responses to its calls.
+	///
+	/// An error response to the whole batch, such as when the server does not support batches, is returned as [`ClientError::Call`].
+	pub fn send(self) -> Result<BatchResponses, ClientError> {
+		let mut responses = HashMap::new();
+		if self.requests.is_empty() {
+			return Ok(BatchResponses { responses });
+		}
+
+		self.client.transport.send(&self.requests.into_json()?)?;
+		// the server does not answer a batch of notifications
+		if self.ids.is_empty() {
+			return Ok(BatchResponses { responses });
+		}
+
+		loop {
+			let message = self
+				.client
+				.transport
+				.receive()?
+				.ok_or(ClientError::Closed)?;
+
+			if !message.trim_start().starts_with('[') {
+				// responses to earlier calls are skipped, an error without an id fails the whole batch
+				let response = client::Response::from_json_str(&message)?;
+				match (response.id, response.error) {
+					(None, Some(error)) => return Err(ClientError::from_response_error(error)),
+					_ => continue,
+				}
+			}
+
+			for response in Vec::<Box<RawValue>>::from_json_str(&message)? {
+				if let Some(ClientId::Number(id)) =
+					client::Response::from_json_str(response.get())?.id
+				{
+					if self.ids.contains(&id) {
+						responses.insert(id, response);
+					}
+				}
+			}
+
+			return Ok(BatchResponses { responses });
+		}
+	}
+}
+
+/// Handle to the result of a call in a [`Batch`].
+#[derive(Debug)]
+pub struct BatchCall<P: Procedure> {
+	id: isize,
+	procedure: PhantomData<fn() -> P>,
+}
+
+/// Responses to the calls of a [`Batch`].
+#[derive(Debug)]
+pub struct BatchResponses {
+	responses: HashMap<isize, Box<RawValue>>,
+}
+impl BatchResponses {
+	/// Takes the result of `call`, parsed like the result of [`RpcClient::call`].
+	pub fn take<P: Procedure>(
+		&mut self,
+		call: BatchCall<P>,
+	) -> Result<Result<P::Result, P::Error>, ClientError> {
+		let response = self
+			.responses
+			.remove(&call.id)
+			.ok_or(ClientError::MissingResponse(call.id))?;
+
+		parse_response::<P>(client::Response::from_json_str(response.get())?)
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use std::collections::VecDeque;
+
+	use super::{ClientError, RpcClient, Transport};
+	use crate::{
+		dispatch::Dispatcher,
+		procedures::{common::Pid, detach, scan_exact},
+	};
+
+	/// Transport handling the requests in place.
+	struct LocalTransport {
+		dispatcher: Dispatcher<Vec<Pid>>,
+		state: Vec<Pid>,
+		responses: VecDeque<String>,
+	}
+	impl Transport for LocalTransport {
+		fn send(&mut self, message: &str) -> std::io::Result<()> {
+			if let Some(response) = self.dispatcher.handle(&mut self.state, message) {
+				self.responses.push_back(response);
+			}
+
+			Ok(())
+		}
+
+		fn receive(&mut self) -> std::io::Result<Option<String>> {
+			Ok(self.responses.pop_front())
+		}
+	}
+
+	#[test]
+	fn test_rpc_client_call() {
+		let mut dispatcher = Dispatcher::<Vec<Pid>>::new();
+		dispatcher.register(|attached: &mut Vec<Pid>, params: detach::detach| {
+			match attached.iter().position(|pid| *pid == params.pid) {
+				None => Err(detach::ProcedureError::NotAttached),
+				Some(index) => {
+					attached.remove(index);
+					Ok(())
+				}
+			}
+		});
+		dispatcher.register(|_: &mut Vec<Pid>, params: scan_exact::scan_exact| {
+			Err(scan_exact::ProcedureError::InvalidRange(
+				params.ranges.unwrap()[0],
+			))
+		});
+
+		let mut client = RpcClient::new(LocalTransport {
+			dispatcher,
+			state: vec![1, 2],
+			responses: VecDeque::new(),
+		});
+
+		assert_eq!(client.call(detach::detach::new(1)).unwrap(), Ok(()));
+		assert_eq!(
+			client.call(detach::detach::new(1)).unwrap(),
+			Err(detach::ProcedureError::NotAttached)
+		);
+		assert_eq!(
+			client
+				.call(scan_exact::scan_exact::new(
+					1,
+					crate::procedures::common::ScanValue::U8(1),
+					true,
+					Some(vec![[2, 1]])
+				))
+				.unwrap(),
+			Err(scan_exact::ProcedureError::InvalidRange([2, 1]))
+		);
+
+		client.notify(detach::detach::new(2)).unwrap();
+		assert!(client.transport().state.is_empty());
+
+		// errors not defined by the procedure are reported by the client
+		assert!(matches!(
+			client.call(crate::procedures::attach::attach::new(1)),
+			Err(ClientError::Call { code: -32601, .. })
+		));
+	}
+
+	#[test]
+	fn test_rpc_client_batch() {
+		let mut dispatcher = Dispatcher::<Vec<Pid>>::new();
+		dispatcher.register(|attached: &mut Vec<Pid>, params: detach::detach| {
+			match attached.iter().position(|pid| *pid == params.pid) {
+				None => Err(detach::ProcedureError::NotAttached),
+				Some(index) => {
+					attached.remove(index);
+					Ok(())
+				}
+			}
+		});
+
+		let mut client = RpcClient::new(LocalTransport {
+			dispatcher,
+			state: vec![1, 2, 3],
+			responses: VecDeque::new(),
+		});
+
+		let mut batch = client.batch();
+		let first = batch.call(detach::detach::new(1)).unwrap();
+		batch.notify(detach::detach::new(2)).unwrap();
+		let again = batch.call(detach::detach::new(1)).unwrap();
+		let unknown = batch
+			.call(crate::procedures::attach::attach::new(3))
+			.unwrap();
+		assert_eq!(batch.len(), 4);
+
+		let mut responses = batch.send().unwrap();
+		assert_eq!(
+			responses.take(again).unwrap(),
+			Err(detach::ProcedureError::NotAttached)
+		);
+		assert_eq!(responses.take(first).unwrap(), Ok(()));
+		assert!(matches!(
+			responses.take(unknown),
+			Err(ClientError::Call { code: -32601, .. })
+		));
+		assert_eq!(client.transport().state, [3]);
+
+		// a batch of notifications waits for nothing
+		let mut batch = client.batch();
+		batch.notify(detach::detach::new(3)).unwrap();
+		batch.send().unwrap();
+		assert!(client.transport().state.is_empty());
+		assert!(client.transport().responses.is_empty());
+	}
+}
@@ -0,0 +1,306 @@
+//! Dispatching of requests to procedure handlers.
+//!
+//! The [`Dispatcher`] parses requests, calls the handler registered for the method with typed params
+//! and serializes the response. Handlers operate on a shared state `S`, such as the attached processes.
+
+use std::{borrow::Cow, collections::HashMap};
+
+use serde_json::{value::RawValue, Value};
+
+use crate::{
+	procedures::Procedure,
+	rpc::{server, FromJson, IntoJson, PredefinedError, RpcError, RPC_VERSION},
+};
+
+/// Error of a call serialized as the error object of a response.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CallError {
+	pub code: isize,
+	pub message: Cow<'static, str>,
+	pub data: Option<Value>,
+}
+impl CallError {
+	pub fn from_rpc_error<E: RpcError<'static>>(error: E) -> Self {
+		CallError {
+			code: error.code(),
+			message: error.message(),
+			data: error
+				.data()
+				.and_then(|data| serde_json::to_value(data).ok()),
+		}
+	}
+
+	/// Creates a predefined error with a human readable description as data.
+	pub fn predefined(error: PredefinedError, description: impl ToString) -> Self {
+		CallError {
+			data: Some(Value::String(description.to_string())),
+			..Self::from_rpc_error(error)
+		}
+	}
+}
+
+type Handler<S> = Box<dyn Fn(&mut S, Option<&RawValue>) -> Result<Value, CallError> + Send + Sync>;
+
+/// Maps method names to handlers operating on state `S`.
+pub struct Dispatcher<S> {
+	handlers: HashMap<&'static str, Handler<S>>,
+}
+impl<S> Dispatcher<S> {
+	pub fn new() -> Self {
+		Dispatcher {
+			handlers: HashMap::new(),
+		}
+	}
+
+	/// Registers `handler` for procedure `P`, replacing any previous handler.
+	pub fn register<P: Procedure>(
+		&mut self,
+		handler: impl Fn(&mut S, P) -> Result<P::Result, P::Error> + Send + Sync + 'static,
+	) {
+		self.handlers.insert(
+			P::NAME,
+			Box::new(move |state, params| {
+				// omitted params are treated as an empty object
+				let params = params.map(RawValue::get).unwrap_or("{}");
+				let params = P::from_json_str(params)
+					.map_err(|err| CallError::predefined(PredefinedError::InvalidParams, err))?;
+
+				let result = handler(state, params).map_err(CallError::from_rpc_error)?;
+				serde_json::to_value(result)
+					.map_err(|err| CallError::predefined(PredefinedError::InternalError, err))
+			}),
+		);
+	}
+
+	/// Returns whether a handler for `method` is registered.
+	pub fn contains(&self, method: &str) -> bool {
+		self.handlers.contains_key(method)
+	}
+
+	/// Calls the handler for `method` with raw `params`.
+	pub fn call(
+		&self,
+		state: &mut S,
+		method: &str,
+		params: Option<&RawValue>,
+	) -> Result<Value, CallError> {
+		match self.handlers.get(method) {
+			None => Err(CallError::predefined(
+				PredefinedError::MethodNotFound,
+				format!("method \"{}\" not found", method),
+			)),
+			Some(handler) => handler(state, params),
+		}
+	}
+
+	/// Handles one serialized request or batch of requests and returns the serialized response.
+	///
+	/// Returns `None` for notifications, which are requests without an id, and for batches of only notifications.
+	/// The requests of a batch are handled in order and their responses are returned as one array.
+	pub fn handle(&self, state: &mut S, message: &str) -> Option<String> {
+		let requests = match server::RequestMessage::parse(message) {
+			Ok(server::RequestMessage::Single(request)) => {
+				return self.handle_request(state, request, PredefinedError::ParseError)
+			}
+			Ok(server::RequestMessage::Batch(requests)) => requests,
+			Err(err) => {
+				let error = CallError::predefined(PredefinedError::ParseError, err);
+				return Some(Self::response(None, Err(error)));
+			}
+		};
+
+		if requests.is_empty() {
+			let error = CallError::predefined(PredefinedError::InvalidRequest, "batch is empty");
+			return Some(Self::response(None, Err(error)));
+		}
+
+		// elements of a batch are valid JSON, so an element which is not a request is invalid instead
+		let responses: Vec<String> = requests
+			.into_iter()
+			.filter_map(|request| {
+				self.handle_request(state, request.get(), PredefinedError::InvalidRequest)
+			})
+			.collect();
+
+		server::batch_response(&responses)
+	}
+
+	/// Handles one request, reporting `malformed` if it cannot be parsed.
+	fn handle_request(
+		&self,
+		state: &mut S,
+		request: &str,
+		malformed: PredefinedError,
+	) -> Option<String> {
+		let request = match server::Request::from_json_str(request) {
+			Ok(request) => request,
+			Err(err) => {
+				let error = CallError::predefined(malformed, err);
+				return Some(Self::response(None, Err(error)));
+			}
+		};
+
+		let result = if request.jsonrpc != RPC_VERSION {
+			Err(CallError::predefined(
+				PredefinedError::InvalidRequest,
+				"jsonrpc must be \"2.0\"",
+			))
+		} else {
+			self.call(state, request.method, request.params)
+		};
+
+		request.id.map(|id| Self::response(Some(id), result))
+	}
+
+	fn response(id: Option<crate::rpc::ClientId>, result: Result<Value, CallError>) -> String {
+		let json = match result {
+			Ok(value) => server::Response::<Value, ()> {
+				jsonrpc: RPC_VERSION.into(),
+				result: server::ResponseResult::Ok(value),
+				id,
+			}
+			.into_json(),
+			Err(error) => {
+				server::Response::error(id, error.code, error.message, error.data).into_json()
+			}
+		};
+
+		// serializing values and strings cannot fail
+		json.unwrap()
+	}
+}
+impl<S> Default for Dispatcher<S> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use serde_json::Value;
+
+	use super::Dispatcher;
+	use crate::procedures::{
+		common::{Pid, NOT_ATTACHED},
+		detach,
+	};
+
+	#[test]
+	fn test_dispatcher_handle() {
+		let mut dispatcher = Dispatcher::<Vec<Pid>>::new();
+		dispatcher.register(|attached: &mut Vec<Pid>, params: detach::detach| {
+			match attached.iter().position(|pid| *pid == params.pid) {
+				None => Err(detach::ProcedureError::NotAttached),
+				Some(index) => {
+					attached.remove(index);
+					Ok(())
+				}
+			}
+		});
+
+		let mut attached = vec![1, 2];
+		let response =
+			|response: Option<String>| serde_json::from_str::<Value>(&response.unwrap()).unwrap();
+
+		assert_eq!(
+			response(dispatcher.handle(
+				&mut attached,
+				r#"{"jsonrpc":"2.0","method":"detach","params":{"pid":1},"id":1}"#
+			)),
+			serde_json::json!({"jsonrpc": "2.0", "result": null, "id": 1})
+		);
+		assert_eq!(attached, [2]);
+
+		assert_eq!(
+			response(dispatcher.handle(
+				&mut attached,
+				r#"{"jsonrpc":"2.0","method":"detach","params":{"pid":1},"id":"a"}"#
+			))["error"]["code"],
+			NOT_ATTACHED
+		);
+		assert_eq!(
+			response(dispatcher.handle(
+				&mut attached,
+				r#"{"jsonrpc":"2.0","method":"detach","params":{"pd":2},"id":2}"#
+			))["error"]["code"],
+			-32602
+		);
+		assert_eq!(
+			response(dispatcher.handle(
+				&mut attached,
+				r#"{"jsonrpc":"2.0","method":"attach","id":3}"#
+			))["error"]["code"],
+			-32601
+		);
+		assert_eq!(
+			response(dispatcher.handle(&mut attached, "{"))["error"]["code"],
+			-32700
+		);
+
+		// notifications have no response
+		assert_eq!(
+			dispatcher.handle(
+				&mut attached,
+				r#"{"jsonrpc":"2.0","method":"detach","params":{"pid":2}}"#
+			),
+			None
+		);
+		assert!(attached.is_empty());
+	}
+
+	#[test]
+	fn test_dispatcher_batch() {
+		let mut dispatcher = Dispatcher::<Vec<Pid>>::new();
+		dispatcher.register(|attached: &mut Vec<Pid>, params: detach::detach| {
+			match attached.iter().position(|pid| *pid == params.pid) {
+				None => Err(detach::ProcedureError::NotAttached),
+				Some(index) => {
+					attached.remove(index);
+					Ok(())
+				}
+			}
+		});
+
+		let mut attached = vec![1, 2, 3];
+		let response =
+			|response: Option<String>| serde_json::from_str::<Value>(&response.unwrap()).unwrap();
+
+		let responses = response(dispatcher.handle(
+			&mut attached,
+			r#"[
+				{"jsonrpc":"2.0","method":"detach","params":{"pid":1},"id":1},
+				{"jsonrpc":"2.0","method":"detach","params":{"pid":2}},
+				{"jsonrpc":"2.0","method":"detach","params":{"pid":1},"id":2},
+				1
+			]"#,
+		));
+		assert_eq!(attached, [3]);
+		assert_eq!(
+			responses[0],
+			serde_json::json!({"jsonrpc": "2.0", "result": null, "id": 1})
+		);
+		assert_eq!(responses[1]["error"]["code"], NOT_ATTACHED);
+		assert_eq!(responses[2]["error"]["code"], -32600);
+		assert_eq!(responses.as_array().unwrap().len(), 3);
+
+		// a batch of notifications has no response
+		assert_eq!(
+			dispatcher.handle(
+				&mut attached,
+				r#"[{"jsonrpc":"2.0","method":"detach","params":{"pid":3}}]"#
+			),
+			None
+		);
+		assert!(attached.is_empty());
+
+		// an empty or malformed batch is answered with a single error
+		assert_eq!(
+			response(dispatcher.handle(&mut attached, "[]"))["error"]["code"],
+			-32600
+		);
+		assert_eq!(
+			response(dispatcher.handle(&mut attached, "[{"))["error"]["code"],
+			-32700
+		);
+	}
+}
@@ -0,0 +1,110 @@
+//! Framing of messages on byte streams.
+
+use std::io::{BufRead, Write};
+
+/// How messages are delimited on a stream.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Framing {
+	/// Each message is one line, the JSON must not contain raw newlines.
+	#[default]
+	NewlineDelimited,
+	/// Each message is preceded by a `Content-Length: <bytes>` header and an empty line, as in the Language Server Protocol.
+	ContentLength,
+}
+impl Framing {
+	/// Reads one message, returns `None` at the end of the stream.
+	///
+	/// Empty lines between newline delimited messages are skipped, unknown headers are ignored.
+	pub fn read_message(&self, reader: &mut impl BufRead) -> std::io::Result<Option<String>> {
+		match self {
+			Framing::NewlineDelimited => loop {
+				let mut line = String::new();
+				if reader.read_line(&mut line)? == 0 {
+					return Ok(None);
+				}
+
+				if !line.trim().is_empty() {
+					return Ok(Some(line));
+				}
+			},
+			Framing::ContentLength => {
+				let mut length = None;
+				loop {
+					let mut line = String::new();
+					if reader.read_line(&mut line)? == 0 {
+						return Ok(None);
+					}
+
+					let line = line.trim();
+					if line.is_empty() {
+						if length.is_some() {
+							break;
+						}
+						continue;
+					}
+
+					if let Some((name, value)) = line.split_once(':') {
+						if name.trim().eq_ignore_ascii_case("content-length") {
+							length = Some(value.trim().parse::<usize>().map_err(|err| {
+								std::io::Error::new(std::io::ErrorKind::InvalidData, err)
+							})?);
+						}
+					}
+				}
+
+				let mut body = vec![0u8; length.unwrap()];
+				reader.read_exact(&mut body)?;
+
+				String::from_utf8(body)
+					.map(Some)
+					.map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err))
+			}
+		}
+	}
+
+	/// Writes one message and flushes the writer.
+	pub fn write_message(&self, writer: &mut impl Write, message: &str) -> std::io::Result<()> {
+		match self {
+			Framing::NewlineDelimited => writeln!(writer, "{}", message)?,
+			Framing::ContentLength => write!(
+				writer,
+				"Content-Length: {}\r\n\r\n{}",
+				message.len(),
+				message
+			)?,
+		}
+
+		writer.flush()
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::Framing;
+
+	#[test]
+	fn test_framing_round_trip() {
+		for framing in [Framing::NewlineDelimited, Framing::ContentLength] {
+			let mut stream = Vec::new();
+			framing.write_message(&mut stream, r#"{"a":1}"#).unwrap();
+			framing.write_message(&mut stream, r#"{"b":"ü"}"#).unwrap();
+
+			let mut reader = stream.as_slice();
+			assert_eq!(
+				framing.read_message(&mut reader).unwrap().unwrap().trim(),
+				r#"{"a":1}"#
+			);
+			assert_eq!(
+				framing.read_message(&mut reader).unwrap().unwrap().trim(),
+				r#"{"b":"ü"}"#
+			);
+			assert_eq!(framing.read_message(&mut reader).unwrap(), None);
+		}
+
+		let mut reader = "Content-Type: application/json\r\ncontent-length: 2\r\n\r\n{}".as_bytes();
+		assert_eq!(
+			Framing::ContentLength.read_message(&mut reader).unwrap(),
+			Some("{}".to_string())
+		);
+	}
+}
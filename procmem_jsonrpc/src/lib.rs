@@ -1,9 +1,20 @@
 //! Provides JSON RPC interface and implementation for procmem access and scan libraries.
 //!
 //! This library can also be used for interface definitions only by disabling
-//! the `implementation` feature (disabling the defaults features). It does not provide
-//! implementation of communiation channels.
+//! the `implementation` feature (disabling the defaults features). The [`server`] module
+//! serves a [`dispatch::Dispatcher`] over TCP or Unix domain sockets and the [`client`] module
+//! calls procedures of such a server. The [`remote`] module implements the procmem_access traits on top of the client,
+//! so that scans can run against a process on another machine.
 
-pub mod rpc;
+#[macro_use]
 pub mod procedures;
+pub mod client;
+pub mod dispatch;
+pub mod framing;
+pub mod rpc;
+pub mod server;
 
+#[cfg(feature = "implementation")]
+pub mod remote;
+#[cfg(feature = "implementation")]
+pub mod simple;
@@ -0,0 +1,14 @@
+//! Attaches to a process, which is required by most other procedures.
+
+use super::common::Pid;
+
+define_procedure! {
+	/// Opens the memory of process `pid`. The process is not locked or traced until `lock` is called.
+	procedure attach;
+	type params = { struct pid: Pid };
+	type result = ();
+	type error = { RpcError
+		AlreadyAttached(-3101, "process is already attached"),
+		Attach(-3102, "could not attach to process", String)
+	};
+}
@@ -0,0 +1,126 @@
+//! Types shared by multiple procedures.
+
+use serde::{Deserialize, Serialize};
+
+/// Process id as sent over the wire, converted to the platform pid by the server.
+pub type Pid = u32;
+
+/// Error code of procedures called on a process which was not attached.
+pub const NOT_ATTACHED: isize = -3100;
+
+/// Memory page as reported by the `pages` procedure.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Page {
+	pub start: u64,
+	pub end: u64,
+	/// Permissions in the `rwxp` format.
+	pub permissions: String,
+	pub offset: u64,
+	#[serde(flatten)]
+	pub page_type: PageType,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", content = "path", rename_all = "snake_case")]
+pub enum PageType {
+	Unknown,
+	Anon,
+	Stack,
+	Heap,
+	ProcessExecutable(String),
+	File(String),
+}
+
+/// Process as reported by the `process_list` procedure.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProcessEntry {
+	pub pid: Pid,
+	pub name: String,
+}
+
+/// Value to scan for, serialized as `{"type": "i32", "value": 5}`.
+///
+/// Numbers are compared in the native endianness of the target.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", content = "value", rename_all = "lowercase")]
+pub enum ScanValue {
+	I8(i8),
+	U8(u8),
+	I16(i16),
+	U16(u16),
+	I32(i32),
+	U32(u32),
+	I64(i64),
+	U64(u64),
+	F32(f32),
+	F64(f64),
+	/// UTF-8 bytes of the string.
+	String(String),
+	Bytes(Vec<u8>),
+}
+
+#[cfg(feature = "implementation")]
+mod implementation {
+	use procmem_access::prelude::{MemoryPage, MemoryPageType};
+	use procmem_scan::prelude::ByteComparable;
+
+	use super::{Page, PageType, ScanValue};
+
+	impl From<&MemoryPage> for Page {
+		fn from(page: &MemoryPage) -> Self {
+			let page_type = match &page.page_type {
+				MemoryPageType::Unknown => PageType::Unknown,
+				MemoryPageType::Anon => PageType::Anon,
+				MemoryPageType::Stack => PageType::Stack,
+				MemoryPageType::Heap => PageType::Heap,
+				MemoryPageType::ProcessExecutable(path) => {
+					PageType::ProcessExecutable(path.to_string_lossy().into_owned())
+				}
+				MemoryPageType::File(path) => PageType::File(path.to_string_lossy().into_owned()),
+			};
+
+			Page {
+				start: page.start().get(),
+				end: page.end().get(),
+				permissions: page.permissions.to_string(),
+				offset: page.offset,
+				page_type,
+			}
+		}
+	}
+
+	impl ByteComparable for ScanValue {
+		fn as_bytes(&self) -> &[u8] {
+			match self {
+				ScanValue::I8(v) => v.as_bytes(),
+				ScanValue::U8(v) => v.as_bytes(),
+				ScanValue::I16(v) => v.as_bytes(),
+				ScanValue::U16(v) => v.as_bytes(),
+				ScanValue::I32(v) => v.as_bytes(),
+				ScanValue::U32(v) => v.as_bytes(),
+				ScanValue::I64(v) => v.as_bytes(),
+				ScanValue::U64(v) => v.as_bytes(),
+				ScanValue::F32(v) => v.as_bytes(),
+				ScanValue::F64(v) => v.as_bytes(),
+				ScanValue::String(v) => v.as_bytes(),
+				ScanValue::Bytes(v) => v.as_slice(),
+			}
+		}
+
+		fn align_of(&self) -> usize {
+			match self {
+				ScanValue::I8(v) => v.align_of(),
+				ScanValue::U8(v) => v.align_of(),
+				ScanValue::I16(v) => v.align_of(),
+				ScanValue::U16(v) => v.align_of(),
+				ScanValue::I32(v) => v.align_of(),
+				ScanValue::U32(v) => v.align_of(),
+				ScanValue::I64(v) => v.align_of(),
+				ScanValue::U64(v) => v.align_of(),
+				ScanValue::F32(v) => v.align_of(),
+				ScanValue::F64(v) => v.align_of(),
+				ScanValue::String(_) | ScanValue::Bytes(_) => 1,
+			}
+		}
+	}
+}
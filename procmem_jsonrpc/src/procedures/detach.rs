@@ -0,0 +1,12 @@
+//! Detaches from a process, releasing its lock and scan results.
+
+use super::common::{Pid, NOT_ATTACHED};
+
+define_procedure! {
+	procedure detach;
+	type params = { struct pid: Pid };
+	type result = ();
+	type error = { RpcError
+		NotAttached(NOT_ATTACHED, "process is not attached")
+	};
+}
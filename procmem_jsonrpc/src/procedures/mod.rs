@@ -1,41 +1,57 @@
 //! Procedure definitions.
+//!
+//! Each procedure is defined in its own module using [`define_procedure!`], which generates
+//! the params struct named after the procedure together with the `ProcedureResult` and `ProcedureError` types.
 
-use serde::{Serialize, Deserialize};
+use serde::{de::DeserializeOwned, Serialize};
 
-use crate::rpc::RpcError;
+use crate::rpc::{FromRpcError, RpcError};
 
-pub trait Procedure<'a> {
+pub trait Procedure: Serialize + DeserializeOwned {
+	/// Method name of the procedure.
 	const NAME: &'static str;
-	type Result: Serialize;
-	type Error: RpcError<'a>;
+	type Result: Serialize + DeserializeOwned;
+	type Error: RpcError<'static> + FromRpcError;
 }
 
+/// Defines a procedure.
+///
+/// ```text
+/// define_procedure! {
+///     /// Documentation of the params struct.
+///     procedure name;
+///     type params = { struct field: Type, ... };
+///     type result = Type;
+///     type error = { RpcError Variant(code, "message"), VariantWithData(code, "message", DataType), ... };
+/// }
+/// ```
+///
+/// The `result` is a type, `params` and `error` are braced struct or `RpcError` definitions.
 macro_rules! define_procedure {
 	(
+		$(#[$procedure_attr: meta])*
 		procedure $procedure_name: ident;
 		type params = $params_tt: tt;
-		type result = $result_tt: tt;
+		type result = $result_ty: ty;
 		type error = $error_tt: tt;
 	) => {
 		define_procedure!(
 			__INNER expand_ty
-			#[derive(Deserialize)]
+			$(#[$procedure_attr])*
 			$procedure_name
 			$params_tt
 		);
 		define_procedure!(
 			__INNER expand_ty
-			#[derive(Serialize)]
 			ProcedureResult
-			$result_tt
+			$result_ty
 		);
 		define_procedure!(
 			__INNER expand_ty
-			#[derive(Deserialize)]
 			ProcedureError
 			$error_tt
 		);
-		impl $crate::procedures::Procedure<'static> for $procedure_name {
+		impl $crate::procedures::Procedure for $procedure_name {
 			const NAME: &'static str = stringify!($procedure_name);
 			type Result = ProcedureResult;
 			type Error = ProcedureError;
@@ -44,30 +60,31 @@ macro_rules! define_procedure {
 
 	(
 		__INNER expand_ty
-		$(#[derive( $($derive_name: ident),+ )])?
+		$(#[$attr: meta])*
 		$name: ident
 		{ struct
 			$(
 				$(#[$field_attr: meta])*
 				$field_name: ident: $field_type: ty
-			),+ $(,)?
+			),* $(,)?
 		}
 	) => {
-		$(
-			#[derive($($derive_name),+)]
-		)?
+		$(#[$attr])*
+		#[allow(non_camel_case_types)]
+		#[derive(Debug, Clone, PartialEq, ::serde::Serialize, ::serde::Deserialize)]
 		pub struct $name {
 			$(
 				$(#[$field_attr])*
 				pub $field_name: $field_type
-			),+
+			),*
 		}
 		impl $name {
+			#[allow(clippy::new_without_default)]
 			pub fn new(
-				$($field_name: $field_type),+
+				$($field_name: $field_type),*
 			) -> Self {
 				$name {
-					$($field_name),+
+					$($field_name),*
 				}
 			}
 		}
@@ -75,7 +92,7 @@ macro_rules! define_procedure {
 
 	(
 		__INNER expand_ty
-		$(#[derive( $($derive_name: ident),+ )])?
+		$(#[$attr: meta])*
 		$name: ident
 		{ RpcError
 			$(
@@ -83,20 +100,97 @@ macro_rules! define_procedure {
 			),+ $(,)?
 		}
 	) => {
+		$(#[$attr])*
+		#[derive(Debug, Clone, PartialEq)]
+		pub enum $name {
+			$(
+				$variant_name $(($data_ty))?
+			),+
+		}
+		impl $crate::rpc::RpcError<'static> for $name {
+			type Data = ::serde_json::Value;
+
+			fn code(&self) -> isize {
+				match self {
+					$(
+						$name::$variant_name { .. } => $code
+					),+
+				}
+			}
+
+			fn message(&self) -> ::std::borrow::Cow<'static, str> {
+				match self {
+					$(
+						$name::$variant_name { .. } => $message.into()
+					),+
+				}
+			}
+
+			#[allow(irrefutable_let_patterns)]
+			fn data(&self) -> Option<Self::Data> {
+				$(
+					define_procedure!(__INNER error_data self, $name::$variant_name $(, $data_ty)?);
+				)+
 
+				None
+			}
+		}
+		impl $crate::rpc::FromRpcError for $name {
+			#[allow(unused_variables)]
+			fn from_rpc_error(
+				code: isize,
+				data: Option<&::serde_json::value::RawValue>,
+			) -> Option<Self> {
+				$(
+					if code == $code {
+						return define_procedure!(__INNER error_from_data data, $name::$variant_name $(, $data_ty)?);
+					}
+				)+
+
+				None
+			}
+		}
 	};
 
 	(
 		__INNER expand_ty
-		$(#[derive( $($derive_name: ident),+ )])?
+		$(#[$attr: meta])*
 		$name: ident
 		$result: ty
 	) => {
+		$(#[$attr])*
 		#[allow(non_camel_case_types)]
 		pub type $name = $result;
 	};
-}
 
+	(__INNER error_data $self: ident, $name: ident :: $variant: ident) => {};
+	(__INNER error_data $self: ident, $name: ident :: $variant: ident, $data_ty: ty) => {
+		if let $name::$variant(data) = $self {
+			return ::serde_json::to_value(data).ok();
+		}
+	};
+
+	(__INNER error_from_data $data: ident, $name: ident :: $variant: ident) => {
+		Some($name::$variant)
+	};
+	(__INNER error_from_data $data: ident, $name: ident :: $variant: ident, $data_ty: ty) => {
+		$data
+			.and_then(|data| ::serde_json::from_str::<$data_ty>(data.get()).ok())
+			.map($name::$variant)
+	};
+}
 
+pub mod common;
 
+pub mod attach;
+pub mod detach;
 pub mod lock;
+pub mod pages;
+pub mod process_list;
+pub mod read;
+pub mod read_value;
+pub mod scan_exact;
+pub mod scan_filter;
+pub mod unlock;
+pub mod write;
+pub mod write_value;
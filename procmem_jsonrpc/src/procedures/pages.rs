@@ -0,0 +1,14 @@
+//! Lists memory pages of an attached process.
+
+use super::common::{Page, Pid, NOT_ATTACHED};
+
+define_procedure! {
+	/// The memory map is loaded again on each call.
+	procedure pages;
+	type params = { struct pid: Pid };
+	type result = Vec<Page>;
+	type error = { RpcError
+		NotAttached(NOT_ATTACHED, "process is not attached"),
+		Pages(-3110, "could not load memory map", String)
+	};
+}
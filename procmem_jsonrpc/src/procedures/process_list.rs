@@ -0,0 +1,12 @@
+//! Lists processes running on the machine of the server.
+
+use super::common::ProcessEntry;
+
+define_procedure! {
+	procedure process_list;
+	type params = { struct };
+	type result = Vec<ProcessEntry>;
+	type error = { RpcError
+		List(-3150, "could not list processes", String)
+	};
+}
@@ -0,0 +1,14 @@
+//! Reads memory of an attached process.
+
+use super::common::{Pid, NOT_ATTACHED};
+
+define_procedure! {
+	/// Reads `length` bytes at `offset`.
+	procedure read;
+	type params = { struct pid: Pid, offset: u64, length: usize };
+	type result = Vec<u8>;
+	type error = { RpcError
+		NotAttached(NOT_ATTACHED, "process is not attached"),
+		Read(-3120, "could not read memory", String)
+	};
+}
@@ -0,0 +1,17 @@
+//! Reads a typed value of an attached process and formats it as text.
+
+use super::common::{Pid, NOT_ATTACHED};
+
+define_procedure! {
+	/// Reads a value of `value_type` at `offset`, such as `i32`, `ptr`, `bytes[16]` or `string[32]`.
+	///
+	/// Numbers are formatted as hex when `hex` is set.
+	procedure read_value;
+	type params = { struct pid: Pid, offset: u64, value_type: String, hex: bool };
+	type result = String;
+	type error = { RpcError
+		NotAttached(NOT_ATTACHED, "process is not attached"),
+		InvalidType(-3170, "value type is invalid", String),
+		Read(-3171, "could not read memory", String)
+	};
+}
@@ -0,0 +1,30 @@
+//! Scans memory of an attached process for a value.
+
+use super::common::{Pid, ScanValue, NOT_ATTACHED};
+
+define_procedure! {
+	/// Scans `ranges` for `value`, replacing the scan results kept for the process.
+	///
+	/// If `ranges` are not given, private writable pages which are not file mappings are scanned.
+	/// The result are the offsets of the matches in ascending order.
+	procedure scan_exact;
+	type params = { struct
+		pid: Pid,
+		value: ScanValue,
+		#[serde(default = "default_aligned")]
+		aligned: bool,
+		#[serde(default)]
+		ranges: Option<Vec<[u64; 2]>>
+	};
+	type result = Vec<u64>;
+	type error = { RpcError
+		NotAttached(NOT_ATTACHED, "process is not attached"),
+		InvalidRange(-3140, "range is invalid", [u64; 2]),
+		Pages(-3141, "could not load memory map", String),
+		Scan(-3142, "scan failed", String)
+	};
+}
+
+fn default_aligned() -> bool {
+	true
+}
@@ -0,0 +1,15 @@
+//! Narrows down the scan results of an attached process.
+
+use super::common::{Pid, ScanValue, NOT_ATTACHED};
+
+define_procedure! {
+	/// Keeps only the scan results which still contain `value`, results which cannot be read are dropped.
+	///
+	/// The result are the remaining offsets in ascending order.
+	procedure scan_filter;
+	type params = { struct pid: Pid, value: ScanValue };
+	type result = Vec<u64>;
+	type error = { RpcError
+		NotAttached(NOT_ATTACHED, "process is not attached")
+	};
+}
@@ -0,0 +1,14 @@
+//! Unlocks a process locked by `lock`.
+
+use super::common::{Pid, NOT_ATTACHED};
+
+define_procedure! {
+	/// The result is whether the lock was released.
+	procedure unlock;
+	type params = { struct pid: Pid };
+	type result = bool;
+	type error = { RpcError
+		NotAttached(NOT_ATTACHED, "process is not attached"),
+		Unlock(-3161, "could not unlock process", String)
+	};
+}
@@ -0,0 +1,14 @@
+//! Writes memory of an attached process.
+
+use super::common::{Pid, NOT_ATTACHED};
+
+define_procedure! {
+	/// Writes `data` at `offset`.
+	procedure write;
+	type params = { struct pid: Pid, offset: u64, data: Vec<u8> };
+	type result = ();
+	type error = { RpcError
+		NotAttached(NOT_ATTACHED, "process is not attached"),
+		Write(-3130, "could not write memory", String)
+	};
+}
@@ -0,0 +1,15 @@
+//! Parses a typed value and writes it to an attached process.
+
+use super::common::{Pid, NOT_ATTACHED};
+
+define_procedure! {
+	/// Parses `value` as `value_type` and writes it at `offset`, the syntax is the one returned by `read_value`.
+	procedure write_value;
+	type params = { struct pid: Pid, offset: u64, value_type: String, value: String };
+	type result = ();
+	type error = { RpcError
+		NotAttached(NOT_ATTACHED, "process is not attached"),
+		InvalidValue(-3180, "value is invalid", String),
+		Write(-3181, "could not write memory", String)
+	};
+}
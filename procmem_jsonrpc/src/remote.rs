@@ -0,0 +1,350 @@
+//! Implementations of the procmem_access traits backed by a JSON RPC server.
+//!
+//! The server runs on the target machine with the privileges needed to access the process, while the scanning
+//! logic of procmem_scan runs on the client against [`RemoteMemoryAccess`] and [`RemoteMemoryMap`].
+//! All types of one process share a single [`RpcClient`] through a [`SharedClient`].
+//! Each read is one round trip, so larger scan chunks pay off more than for local processes.
+//! Vectored reads, such as re-reading scan results, are sent as one batch.
+
+use std::{
+	path::PathBuf,
+	sync::{Arc, Mutex},
+};
+
+use thiserror::Error;
+
+use procmem_access::{
+	memory::{
+		access::{ReadError, WriteError},
+		lock::{LockError, UnlockError},
+		maps_format::parse_permissions,
+	},
+	prelude::{
+		ErrorKind, MemoryAccess, MemoryLock, MemoryMap, MemoryPage, MemoryPageType, OffsetType,
+	},
+};
+
+use crate::{
+	client::{ClientError, RpcClient, Transport},
+	procedures::{
+		attach,
+		common::{Page, PageType, Pid},
+		detach, lock, pages, read, unlock, write, Procedure,
+	},
+	rpc::RpcError,
+};
+
+/// Client shared by the remote types of one connection.
+pub type SharedClient<T> = Arc<Mutex<RpcClient<T>>>;
+
+#[derive(Debug, Error)]
+pub enum RemoteError {
+	#[error("remote call failed")]
+	Client(#[from] ClientError),
+	#[error("remote procedure failed: {0}")]
+	Procedure(String),
+	#[error("remote page {start:#x}-{end:#x} is invalid")]
+	InvalidPage { start: u64, end: u64 },
+}
+impl RemoteError {
+	pub fn kind(&self) -> ErrorKind {
+		match self {
+			RemoteError::Client(ClientError::Transport(err)) => ErrorKind::from_io(err),
+			RemoteError::Client(_) => ErrorKind::Platform,
+			RemoteError::Procedure(_) => ErrorKind::Platform,
+			RemoteError::InvalidPage { .. } => ErrorKind::Parse,
+		}
+	}
+
+	fn into_io(self) -> std::io::Error {
+		match self {
+			RemoteError::Client(ClientError::Transport(err)) => err,
+			err => std::io::Error::other(err),
+		}
+	}
+}
+
+/// Calls procedure `P`, describing procedure errors by their message and string data.
+fn call<T: Transport, P: Procedure>(
+	client: &SharedClient<T>,
+	params: P,
+) -> Result<P::Result, RemoteError> {
+	let result = client.lock().unwrap().call(params)?;
+
+	result.map_err(procedure_error)
+}
+
+fn procedure_error<E: RpcError<'static>>(err: E) -> RemoteError {
+	let detail = err.data().and_then(|data| serde_json::to_value(data).ok());
+	let message = match detail {
+		Some(serde_json::Value::String(detail)) => format!("{}: {}", err.message(), detail),
+		_ => err.message().into_owned(),
+	};
+
+	RemoteError::Procedure(message)
+}
+
+fn copy_read(bytes: Vec<u8>, buffer: &mut [u8]) -> Result<(), ReadError> {
+	if bytes.len() != buffer.len() {
+		return Err(ReadError::Io(std::io::Error::new(
+			std::io::ErrorKind::UnexpectedEof,
+			"server returned a different number of bytes",
+		)));
+	}
+	buffer.copy_from_slice(&bytes);
+
+	Ok(())
+}
+
+/// Attaches to process `pid` on the server, see the `attach` procedure.
+pub fn attach<T: Transport>(client: &SharedClient<T>, pid: Pid) -> Result<(), RemoteError> {
+	call(client, attach::attach::new(pid))
+}
+
+/// Detaches from process `pid` on the server, releasing its lock.
+pub fn detach<T: Transport>(client: &SharedClient<T>, pid: Pid) -> Result<(), RemoteError> {
+	call(client, detach::detach::new(pid))
+}
+
+/// Memory of a process attached on the server, read and written by the `read` and `write` procedures.
+pub struct RemoteMemoryAccess<T: Transport> {
+	client: SharedClient<T>,
+	pid: Pid,
+}
+impl<T: Transport> RemoteMemoryAccess<T> {
+	/// The process must already be attached, see [`attach`].
+	pub fn new(client: SharedClient<T>, pid: Pid) -> Self {
+		RemoteMemoryAccess { client, pid }
+	}
+
+	pub const fn pid(&self) -> Pid {
+		self.pid
+	}
+}
+impl<T: Transport> MemoryAccess for RemoteMemoryAccess<T> {
+	unsafe fn read(&mut self, offset: OffsetType, buffer: &mut [u8]) -> Result<(), ReadError> {
+		let bytes = call(
+			&self.client,
+			read::read::new(self.pid, offset.get(), buffer.len()),
+		)
+		.map_err(|err| ReadError::Io(err.into_io()))?;
+
+		copy_read(bytes, buffer)
+	}
+
+	/// Sends all reads as one batch, the reads are still performed one by one on the server.
+	unsafe fn read_v(&mut self, requests: &mut [(OffsetType, &mut [u8])]) -> Result<(), ReadError> {
+		let io_error = |err: RemoteError| ReadError::Io(err.into_io());
+
+		let mut client = self.client.lock().unwrap();
+		let mut batch = client.batch();
+		let calls = requests
+			.iter()
+			.map(|(offset, buffer)| {
+				batch.call(read::read::new(self.pid, offset.get(), buffer.len()))
+			})
+			.collect::<Result<Vec<_>, _>>()
+			.map_err(|err| io_error(err.into()))?;
+		let mut responses = batch.send().map_err(|err| io_error(err.into()))?;
+		drop(client);
+
+		for ((_, buffer), call) in requests.iter_mut().zip(calls) {
+			let bytes = responses
+				.take(call)
+				.map_err(|err| io_error(err.into()))?
+				.map_err(|err| io_error(procedure_error(err)))?;
+
+			copy_read(bytes, buffer)?;
+		}
+
+		Ok(())
+	}
+
+	unsafe fn write(&mut self, offset: OffsetType, data: &[u8]) -> Result<(), WriteError> {
+		call(
+			&self.client,
+			write::write::new(self.pid, offset.get(), data.to_vec()),
+		)
+		.map_err(|err| WriteError::Io(err.into_io()))
+	}
+}
+
+/// Memory map of a process attached on the server, loaded by the `pages` procedure.
+pub struct RemoteMemoryMap {
+	pages: Vec<MemoryPage>,
+}
+impl RemoteMemoryMap {
+	/// Loads the map of process `pid`, which must already be attached.
+	pub fn new<T: Transport>(client: &SharedClient<T>, pid: Pid) -> Result<Self, RemoteError> {
+		let mut me = RemoteMemoryMap { pages: Vec::new() };
+		me.reload(client, pid)?;
+
+		Ok(me)
+	}
+
+	/// Loads the pages again, the map is not updated otherwise.
+	pub fn reload<T: Transport>(
+		&mut self,
+		client: &SharedClient<T>,
+		pid: Pid,
+	) -> Result<(), RemoteError> {
+		self.pages = call(client, pages::pages::new(pid))?
+			.iter()
+			.map(memory_page)
+			.collect::<Result<_, _>>()?;
+
+		Ok(())
+	}
+}
+impl MemoryMap for RemoteMemoryMap {
+	fn pages(&self) -> &[MemoryPage] {
+		&self.pages
+	}
+}
+
+fn memory_page(page: &Page) -> Result<MemoryPage, RemoteError> {
+	let invalid = || RemoteError::InvalidPage {
+		start: page.start,
+		end: page.end,
+	};
+
+	let start = OffsetType::new(page.start).ok_or_else(invalid)?;
+	let end = OffsetType::new(page.end).ok_or_else(invalid)?;
+	let page_type = match &page.page_type {
+		PageType::Unknown => MemoryPageType::Unknown,
+		PageType::Anon => MemoryPageType::Anon,
+		PageType::Stack => MemoryPageType::Stack,
+		PageType::Heap => MemoryPageType::Heap,
+		PageType::ProcessExecutable(path) => MemoryPageType::ProcessExecutable(PathBuf::from(path)),
+		PageType::File(path) => MemoryPageType::File(PathBuf::from(path)),
+	};
+
+	Ok(MemoryPage {
+		address_range: [start, end],
+		permissions: parse_permissions(&page.permissions).map_err(|_| invalid())?,
+		offset: page.offset,
+		page_type,
+	})
+}
+
+/// Lock of a process attached on the server, using the `lock` and `unlock` procedures.
+///
+/// The server counts the locks itself, so locks of several clients of the same process stack.
+/// Exclusive locks are not supported by the server.
+pub struct RemoteMemoryLock<T: Transport> {
+	client: SharedClient<T>,
+	pid: Pid,
+}
+impl<T: Transport> RemoteMemoryLock<T> {
+	/// The process must already be attached, see [`attach`].
+	pub fn new(client: SharedClient<T>, pid: Pid) -> Self {
+		RemoteMemoryLock { client, pid }
+	}
+}
+impl<T: Transport> MemoryLock for RemoteMemoryLock<T> {
+	fn lock(&mut self) -> Result<bool, LockError> {
+		call(&self.client, lock::lock::new(self.pid))
+			.map_err(|err| LockError::PlatformError(Box::new(err)))
+	}
+
+	fn lock_exlusive(&mut self) -> Result<(), LockError> {
+		Err(LockError::PlatformError(
+			"exclusive locks are not supported by the server".into(),
+		))
+	}
+
+	fn unlock(&mut self) -> Result<bool, UnlockError> {
+		call(&self.client, unlock::unlock::new(self.pid))
+			.map_err(|err| UnlockError::PlatformError(Box::new(err)))
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use std::{
+		collections::VecDeque,
+		sync::{Arc, Mutex},
+	};
+
+	use procmem_access::prelude::{MemoryAccess, MemoryMap, OffsetType};
+	use procmem_scan::prelude::{ScanConfig, ScanDriver, ValuePredicate};
+
+	use super::{attach, RemoteMemoryAccess, RemoteMemoryMap};
+	use crate::{
+		client::{RpcClient, Transport},
+		dispatch::Dispatcher,
+		simple::{dispatcher, SimpleState},
+	};
+
+	/// Transport handling the requests in place.
+	struct LocalTransport {
+		dispatcher: Dispatcher<SimpleState>,
+		state: SimpleState,
+		responses: VecDeque<String>,
+	}
+	impl Transport for LocalTransport {
+		fn send(&mut self, message: &str) -> std::io::Result<()> {
+			if let Some(response) = self.dispatcher.handle(&mut self.state, message) {
+				self.responses.push_back(response);
+			}
+
+			Ok(())
+		}
+
+		fn receive(&mut self) -> std::io::Result<Option<String>> {
+			Ok(self.responses.pop_front())
+		}
+	}
+
+	#[test]
+	fn test_remote_scan() {
+		let client = Arc::new(Mutex::new(RpcClient::new(LocalTransport {
+			dispatcher: dispatcher(),
+			state: SimpleState::new(),
+			responses: VecDeque::new(),
+		})));
+
+		// the server reads the memory of this process
+		let pid = std::process::id();
+		attach(&client, pid).unwrap();
+
+		let data: Box<[u64]> =
+			vec![0x5EC0_7D17_1A7A_0001, 0x5EC0_7D17_1A7A_0002].into_boxed_slice();
+		let data_offset = OffsetType::new_unwrap(data.as_ptr() as u64);
+
+		let map = RemoteMemoryMap::new(&client, pid).unwrap();
+		let page = map.containing_page(data_offset).unwrap().address_range;
+
+		let mut access = RemoteMemoryAccess::new(client.clone(), pid);
+		let mut buffer = [0u8; 8];
+		unsafe { access.read(data_offset, &mut buffer).unwrap() };
+		assert_eq!(u64::from_ne_bytes(buffer), data[0]);
+
+		let (mut first, mut second) = ([0u8; 8], [0u8; 8]);
+		unsafe {
+			access
+				.read_v(&mut [
+					(data_offset.saturating_add(8), &mut second[..]),
+					(data_offset, &mut first[..]),
+				])
+				.unwrap()
+		};
+		assert_eq!(
+			[u64::from_ne_bytes(first), u64::from_ne_bytes(second)],
+			[data[0], data[1]]
+		);
+
+		let mut found = Vec::new();
+		unsafe {
+			ScanDriver::new(ScanConfig::default())
+				.scan(
+					&mut access,
+					[page],
+					ValuePredicate::new(data[1], true),
+					|(offset, _)| found.push(offset),
+				)
+				.unwrap()
+		};
+		assert!(found.contains(&data_offset.saturating_add(8)));
+	}
+}
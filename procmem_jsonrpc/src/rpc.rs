@@ -1,9 +1,9 @@
 //! https://www.jsonrpc.org/specification
 
+use serde::{Deserialize, Serialize};
 use std::borrow::Cow;
-use serde::{Serialize, Deserialize};
 
-pub const RPC_VERSION: &'static str = "2.0";
+pub const RPC_VERSION: &str = "2.0";
 
 /// Like the never type `!` or `std::conver::Infallible` but implements `Serialize`.
 #[derive(Serialize, Copy, Clone)]
@@ -14,7 +14,7 @@ pub enum Null {}
 #[serde(untagged)]
 pub enum ClientId<'a> {
 	String(#[serde(borrow)] Cow<'a, str>),
-	Number(isize)
+	Number(isize),
 }
 
 /// Convenience trait for rpc errors.
@@ -26,6 +26,12 @@ pub trait RpcError<'a> {
 	fn data(&self) -> Option<Self::Data>;
 }
 
+/// Reconstructs a typed error from the parts of an error response.
+pub trait FromRpcError: Sized {
+	/// Returns `None` if the `code` is not known or the `data` do not match it.
+	fn from_rpc_error(code: isize, data: Option<&serde_json::value::RawValue>) -> Option<Self>;
+}
+
 #[derive(Serialize, Deserialize, Clone, Copy)]
 #[repr(isize)]
 pub enum PredefinedError {
@@ -34,7 +40,7 @@ pub enum PredefinedError {
 	MethodNotFound = -32601,
 	InvalidParams = -32602,
 	InternalError = -32603,
-	ServerError = -32000 // to -32099
+	ServerError = -32000, // to -32099
 }
 impl RpcError<'static> for PredefinedError {
 	type Data = ();
@@ -50,8 +56,9 @@ impl RpcError<'static> for PredefinedError {
 			PredefinedError::MethodNotFound => "Method not found",
 			PredefinedError::InvalidParams => "Invalid params",
 			PredefinedError::InternalError => "Internal error",
-			PredefinedError::ServerError => "Server error"
-		}.into()
+			PredefinedError::ServerError => "Server error",
+		}
+		.into()
 	}
 
 	fn data(&self) -> Option<Self::Data> {
@@ -62,6 +69,7 @@ impl RpcError<'static> for PredefinedError {
 /// Convenience trait for simple `.into_json()` function.
 pub trait IntoJson: Serialize {
 	/// Serializes self into json.
+	#[allow(clippy::wrong_self_convention)]
 	fn into_json(&self) -> Result<String, serde_json::Error> {
 		serde_json::to_string(self)
 	}
@@ -90,10 +98,10 @@ pub mod server {
 
 	use std::borrow::Cow;
 
-	use serde::{Serialize, Deserialize};
+	use serde::{Deserialize, Serialize};
 	use serde_json::value::RawValue;
 
-	use super::{ClientId, RPC_VERSION, RpcError};
+	use super::{ClientId, RpcError, RPC_VERSION};
 
 	#[derive(Deserialize, Debug)]
 	pub struct Request<'a> {
@@ -106,15 +114,47 @@ pub mod server {
 		pub params: Option<&'a RawValue>,
 		/// Client identifier that will be included in the response. May be omitted if no response is to be sent.
 		#[serde(default)]
-		pub id: Option<ClientId<'a>>
+		pub id: Option<ClientId<'a>>,
 	}
 	#[cfg(test)]
 	impl<'a> PartialEq for Request<'a> {
 		fn eq(&self, other: &Self) -> bool {
 			self.jsonrpc == other.jsonrpc
-			&& self.method == other.method
-			&& self.params.map(|rw| rw.get()) == other.params.map(|rw| rw.get())
-			&& self.id == other.id
+				&& self.method == other.method
+				&& self.params.map(|rw| rw.get()) == other.params.map(|rw| rw.get())
+				&& self.id == other.id
+		}
+	}
+
+	/// Requests of one message, which is either a single request or a batch array of requests.
+	#[derive(Debug)]
+	pub enum RequestMessage<'a> {
+		/// Single request, still to be parsed into a [`Request`].
+		Single(&'a str),
+		/// Elements of a batch, each still to be parsed into a [`Request`].
+		Batch(Vec<&'a RawValue>),
+	}
+	impl<'a> RequestMessage<'a> {
+		/// Splits `message` into its requests.
+		///
+		/// Only the batch array is parsed, so that each request can fail on its own.
+		pub fn parse(message: &'a str) -> Result<Self, serde_json::Error> {
+			if message.trim_start().starts_with('[') {
+				serde_json::from_str(message).map(RequestMessage::Batch)
+			} else {
+				Ok(RequestMessage::Single(message))
+			}
+		}
+	}
+
+	/// Joins the serialized responses to the requests of a batch into one array.
+	///
+	/// Returns `None` if there are no responses because all requests were notifications, in which case nothing is sent.
+	pub fn batch_response(responses: &[String]) -> Option<String> {
+		if responses.is_empty() {
+			None
+		} else {
+			Some(format!("[{}]", responses.join(",")))
 		}
 	}
 
@@ -131,8 +171,8 @@ pub mod server {
 			message: Cow<'a, str>,
 			/// Optional additional information about the error.
 			#[serde(skip_serializing_if = "Option::is_none")]
-			data: Option<E>
-		}
+			data: Option<E>,
+		},
 	}
 
 	#[derive(Serialize, Debug)]
@@ -144,14 +184,14 @@ pub mod server {
 		#[serde(flatten)]
 		pub result: ResponseResult<'a, T, E>,
 		/// Client identifier included in request, or `None` of it could not be determined.
-		pub id: Option<ClientId<'a>>
+		pub id: Option<ClientId<'a>>,
 	}
 	impl<'a, T: Serialize> Response<'a, T, ()> {
 		pub fn success(id: ClientId<'a>, value: T) -> Self {
 			Response {
 				jsonrpc: RPC_VERSION.into(),
 				result: ResponseResult::Ok(value),
-				id: Some(id)
+				id: Some(id),
 			}
 		}
 	}
@@ -160,31 +200,31 @@ pub mod server {
 			id: Option<ClientId<'a>>,
 			code: isize,
 			message: Cow<'a, str>,
-			data: Option<E>
+			data: Option<E>,
 		) -> Self {
 			Response {
 				jsonrpc: RPC_VERSION.into(),
 				result: ResponseResult::Error {
 					code,
 					message,
-					data
+					data,
 				},
-				id
+				id,
 			}
 		}
 
 		pub fn from_rpc_error<Err: RpcError<'a>>(
 			id: Option<ClientId<'a>>,
-			error: Err
+			error: Err,
 		) -> Response<'a, (), Err::Data> {
 			Response {
 				jsonrpc: RPC_VERSION.into(),
 				result: ResponseResult::Error {
 					code: error.code(),
 					message: error.message(),
-					data: error.data()
+					data: error.data(),
 				},
-				id
+				id,
 			}
 		}
 	}
@@ -198,10 +238,10 @@ pub mod client {
 
 	use std::borrow::Cow;
 
-	use serde::{Serialize, Deserialize};
+	use serde::{Deserialize, Serialize};
 	use serde_json::value::RawValue;
 
-	use super::{RPC_VERSION, ClientId};
+	use super::{ClientId, RPC_VERSION};
 
 	#[derive(Serialize, Debug)]
 	pub struct Request<'a, P: Serialize = ()> {
@@ -214,35 +254,61 @@ pub mod client {
 		pub params: Option<P>,
 		/// Client identifier that will be included in the response. May be omitted if no response is to be sent.
 		#[serde(skip_serializing_if = "Option::is_none")]
-		pub id: Option<ClientId<'a>>
+		pub id: Option<ClientId<'a>>,
 	}
 	impl<'a, P: Serialize> Request<'a, P> {
-		pub fn new(
-			method: Cow<'a, str>,
-			params: Option<P>,
-			id: ClientId<'a>
-		) -> Self {
+		pub fn new(method: Cow<'a, str>, params: Option<P>, id: ClientId<'a>) -> Self {
 			Request {
 				jsonrpc: RPC_VERSION.into(),
 				method,
 				params,
-				id: Some(id)
+				id: Some(id),
 			}
 		}
 
-		pub fn new_notification(
-			method: Cow<'a, str>,
-			params: Option<P>,
-		) -> Self {
+		pub fn new_notification(method: Cow<'a, str>, params: Option<P>) -> Self {
 			Request {
 				jsonrpc: RPC_VERSION.into(),
 				method,
 				params,
-				id: None
+				id: None,
 			}
 		}
 	}
 
+	/// Requests and notifications sent together as one batch array.
+	///
+	/// The server answers with an array of the responses to the requests, in any order, or with nothing if there were only notifications.
+	#[derive(Serialize, Debug, Default)]
+	#[serde(transparent)]
+	pub struct Batch {
+		requests: Vec<Box<RawValue>>,
+	}
+	impl Batch {
+		pub fn new() -> Self {
+			Self::default()
+		}
+
+		/// Appends a request or a notification.
+		pub fn push<P: Serialize>(
+			&mut self,
+			request: &Request<P>,
+		) -> Result<(), serde_json::Error> {
+			self.requests
+				.push(serde_json::value::to_raw_value(request)?);
+
+			Ok(())
+		}
+
+		pub fn len(&self) -> usize {
+			self.requests.len()
+		}
+
+		pub fn is_empty(&self) -> bool {
+			self.requests.is_empty()
+		}
+	}
+
 	#[derive(Deserialize, Debug)]
 	pub enum ResponseResult<'a> {
 		#[serde(rename = "result")]
@@ -255,20 +321,31 @@ pub mod client {
 			message: &'a str,
 			/// Optional additional information about the error.
 			#[serde(borrow)]
-			data: Option<&'a RawValue>
-		}
+			data: Option<&'a RawValue>,
+		},
 	}
 	#[cfg(test)]
 	impl<'a> PartialEq for ResponseResult<'a> {
 		fn eq(&self, other: &Self) -> bool {
 			match (self, other) {
 				(ResponseResult::Result(a), ResponseResult::Result(b)) => a.get() == b.get(),
-				(ResponseResult::Error { code: code_a, message: message_a, data: data_a }, ResponseResult::Error { code: code_b, message: message_b, data: data_b }) => {
+				(
+					ResponseResult::Error {
+						code: code_a,
+						message: message_a,
+						data: data_a,
+					},
+					ResponseResult::Error {
+						code: code_b,
+						message: message_b,
+						data: data_b,
+					},
+				) => {
 					code_a == code_b
-					&& message_a == message_b
-					&& data_a.map(|rw| rw.get()) == data_b.map(|rw| rw.get())
-				},
-				(_, _) => false
+						&& message_a == message_b
+						&& data_a.map(|rw| rw.get()) == data_b.map(|rw| rw.get())
+				}
+				(_, _) => false,
 			}
 		}
 	}
@@ -281,14 +358,14 @@ pub mod client {
 		pub message: &'a str,
 		/// Optional additional information about the error.
 		#[serde(borrow)]
-		pub data: Option<&'a RawValue>
+		pub data: Option<&'a RawValue>,
 	}
 	#[cfg(test)]
 	impl<'a> PartialEq for ResponseError<'a> {
 		fn eq(&self, other: &Self) -> bool {
 			self.code == other.code
-			&& self.message == other.message
-			&& self.data.map(|rw| rw.get()) == other.data.map(|rw| rw.get())
+				&& self.message == other.message
+				&& self.data.map(|rw| rw.get()) == other.data.map(|rw| rw.get())
 		}
 	}
 
@@ -304,37 +381,35 @@ pub mod client {
 
 		/// Client identifier included in request, or `None` of it could not be determined.
 		#[serde(borrow)]
-		pub id: Option<ClientId<'a>>
+		pub id: Option<ClientId<'a>>,
 	}
 	#[cfg(test)]
 	impl<'a> PartialEq for Response<'a> {
 		fn eq(&self, other: &Self) -> bool {
 			self.jsonrpc == other.jsonrpc
-			&& self.result.map(|rw| rw.get()) == other.result.map(|rw| rw.get())
-			&& self.error == other.error
-			&& self.id == other.id
+				&& self.result.map(|rw| rw.get()) == other.result.map(|rw| rw.get())
+				&& self.error == other.error
+				&& self.id == other.id
 		}
 	}
 }
 
 #[cfg(test)]
+#[allow(clippy::missing_transmute_annotations)]
 mod test {
-	use super::{ClientId, IntoJson, FromJson, client, server};
+	use super::{client, server, ClientId, FromJson, IntoJson};
 
 	#[test]
 	fn test_rpc_request() {
-		let client_request = client::Request::new(
-			"foo".into(),
-			Some((1, "hello")),
-			ClientId::Number(1)
-		);
+		let client_request =
+			client::Request::new("foo".into(), Some((1, "hello")), ClientId::Number(1));
 
 		let json = client_request.into_json().unwrap();
 		assert_eq!(
 			json,
 			r#"{"jsonrpc":"2.0","method":"foo","params":[1,"hello"],"id":1}"#
 		);
-		
+
 		let server_request = server::Request::from_json_str(&json).unwrap();
 
 		assert_eq!(
@@ -350,18 +425,11 @@ mod test {
 
 	#[test]
 	fn test_rpc_request_noparams() {
-		let client_request = client::Request::new(
-			"bar".into(),
-			None::<()>,
-			ClientId::Number(2)
-		);
+		let client_request = client::Request::new("bar".into(), None::<()>, ClientId::Number(2));
 
 		let json = client_request.into_json().unwrap();
-		assert_eq!(
-			json,
-			r#"{"jsonrpc":"2.0","method":"bar","id":2}"#
-		);
-		
+		assert_eq!(json, r#"{"jsonrpc":"2.0","method":"bar","id":2}"#);
+
 		let server_request = server::Request::from_json_str(&json).unwrap();
 
 		assert_eq!(
@@ -377,17 +445,11 @@ mod test {
 
 	#[test]
 	fn test_rpc_request_notification() {
-		let client_request = client::Request::new_notification(
-			"baz".into(),
-			Some(true),
-		);
+		let client_request = client::Request::new_notification("baz".into(), Some(true));
 
 		let json = client_request.into_json().unwrap();
-		assert_eq!(
-			json,
-			r#"{"jsonrpc":"2.0","method":"baz","params":true}"#
-		);
-		
+		assert_eq!(json, r#"{"jsonrpc":"2.0","method":"baz","params":true}"#);
+
 		let server_request = server::Request::from_json_str(&json).unwrap();
 
 		assert_eq!(
@@ -403,17 +465,11 @@ mod test {
 
 	#[test]
 	fn test_rpc_request_notification_noparams() {
-		let client_request = client::Request::new_notification(
-			"baz".into(),
-			None::<()>
-		);
+		let client_request = client::Request::new_notification("baz".into(), None::<()>);
 
 		let json = client_request.into_json().unwrap();
-		assert_eq!(
-			json,
-			r#"{"jsonrpc":"2.0","method":"baz"}"#
-		);
-		
+		assert_eq!(json, r#"{"jsonrpc":"2.0","method":"baz"}"#);
+
 		let server_request = server::Request::from_json_str(&json).unwrap();
 
 		assert_eq!(
@@ -429,17 +485,12 @@ mod test {
 
 	#[test]
 	fn test_rpc_response_success() {
-		let server_response = server::Response::success(
-			ClientId::String("salmon".into()),
-			(2, "hi")
-		);
+		let server_response =
+			server::Response::success(ClientId::String("salmon".into()), (2, "hi"));
 
 		let json = server_response.into_json().unwrap();
-		assert_eq!(
-			json,
-			r#"{"jsonrpc":"2.0","result":[2,"hi"],"id":"salmon"}"#
-		);
-		
+		assert_eq!(json, r#"{"jsonrpc":"2.0","result":[2,"hi"],"id":"salmon"}"#);
+
 		let client_response = client::Response::from_json_str(&json).unwrap();
 
 		assert_eq!(
@@ -459,7 +510,7 @@ mod test {
 			Some(ClientId::String("baba".into())),
 			-3600,
 			"my error".into(),
-			Some((1, 2, true))
+			Some((1, 2, true)),
 		);
 
 		let json = server_response.into_json().unwrap();
@@ -467,7 +518,7 @@ mod test {
 			json,
 			r#"{"jsonrpc":"2.0","error":{"code":-3600,"message":"my error","data":[1,2,true]},"id":"baba"}"#
 		);
-		
+
 		let client_response = client::Response::from_json_str(&json).unwrap();
 
 		assert_eq!(
@@ -491,7 +542,7 @@ mod test {
 			Some(ClientId::String("gaga".into())),
 			123,
 			"axf".into(),
-			None::<()>
+			None::<()>,
 		);
 
 		let json = server_response.into_json().unwrap();
@@ -499,7 +550,7 @@ mod test {
 			json,
 			r#"{"jsonrpc":"2.0","error":{"code":123,"message":"axf"},"id":"gaga"}"#
 		);
-		
+
 		let client_response = client::Response::from_json_str(&json).unwrap();
 
 		assert_eq!(
@@ -516,4 +567,52 @@ mod test {
 			}
 		);
 	}
-}
\ No newline at end of file
+
+	#[test]
+	fn test_rpc_batch() {
+		let mut batch = client::Batch::new();
+		batch
+			.push(&client::Request::new(
+				"foo".into(),
+				Some(1),
+				ClientId::Number(1),
+			))
+			.unwrap();
+		batch
+			.push(&client::Request::new_notification("bar".into(), None::<()>))
+			.unwrap();
+		assert_eq!(batch.len(), 2);
+
+		let json = batch.into_json().unwrap();
+		assert_eq!(
+			json,
+			r#"[{"jsonrpc":"2.0","method":"foo","params":1,"id":1},{"jsonrpc":"2.0","method":"bar"}]"#
+		);
+
+		let requests = match server::RequestMessage::parse(&json).unwrap() {
+			server::RequestMessage::Batch(requests) => requests,
+			server::RequestMessage::Single(_) => panic!("batch parsed as a single request"),
+		};
+		let requests: Vec<server::Request> = requests
+			.into_iter()
+			.map(|request| server::Request::from_json_str(request.get()).unwrap())
+			.collect();
+		assert_eq!(requests[0].method, "foo");
+		assert_eq!(requests[1].id, None);
+		assert!(matches!(
+			server::RequestMessage::parse(r#" {"jsonrpc":"2.0","method":"foo"}"#),
+			Ok(server::RequestMessage::Single(_))
+		));
+
+		// only the request has a response
+		let responses = [server::Response::success(ClientId::Number(1), 2)
+			.into_json()
+			.unwrap()];
+		let json = server::batch_response(&responses).unwrap();
+		assert_eq!(json, r#"[{"jsonrpc":"2.0","result":2,"id":1}]"#);
+		let client_responses = Vec::<client::Response>::from_json_str(&json).unwrap();
+		assert_eq!(client_responses[0].result.unwrap().get(), "2");
+
+		assert_eq!(server::batch_response(&[]), None);
+	}
+}
@@ -0,0 +1,229 @@
+//! Server serving a [`Dispatcher`] over TCP or Unix domain sockets.
+//!
+//! Each client is served on its own thread. Requests of all clients are dispatched against
+//! one shared state, so a process attached by one client is visible to all of them.
+
+use std::{
+	io::{BufReader, Read, Write},
+	net::TcpListener,
+	str::FromStr,
+	sync::{Arc, Mutex},
+};
+
+use crate::{dispatch::Dispatcher, framing::Framing};
+
+/// Address the server listens on, parsed from `tcp:<address>` or `unix:<path>`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Endpoint {
+	Tcp(String),
+	#[cfg(unix)]
+	Unix(std::path::PathBuf),
+}
+impl FromStr for Endpoint {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s.split_once(':') {
+			Some(("tcp", address)) => Ok(Endpoint::Tcp(address.to_string())),
+			#[cfg(unix)]
+			Some(("unix", path)) => Ok(Endpoint::Unix(path.into())),
+			_ => Err(format!(
+				"invalid endpoint \"{}\", expected tcp:<address> or unix:<path>",
+				s
+			)),
+		}
+	}
+}
+
+pub struct Server<S> {
+	dispatcher: Arc<Dispatcher<S>>,
+	state: Arc<Mutex<S>>,
+	framing: Framing,
+}
+impl<S> Server<S> {
+	pub fn new(dispatcher: Dispatcher<S>, state: S) -> Self {
+		Server {
+			dispatcher: Arc::new(dispatcher),
+			state: Arc::new(Mutex::new(state)),
+			framing: Framing::default(),
+		}
+	}
+
+	pub fn with_framing(mut self, framing: Framing) -> Self {
+		self.framing = framing;
+		self
+	}
+
+	pub fn framing(&self) -> Framing {
+		self.framing
+	}
+
+	/// State shared by all clients.
+	pub fn state(&self) -> &Arc<Mutex<S>> {
+		&self.state
+	}
+
+	/// Serves one client until the end of the stream.
+	///
+	/// Requests are handled in order and notifications produce no response. A batch of requests is answered with one message.
+	pub fn serve_connection(
+		&self,
+		reader: impl Read,
+		mut writer: impl Write,
+	) -> std::io::Result<()> {
+		let mut reader = BufReader::new(reader);
+
+		while let Some(request) = self.framing.read_message(&mut reader)? {
+			let response = {
+				// a panicking handler must not take down the other clients
+				let mut state = self.state.lock().unwrap_or_else(|err| err.into_inner());
+				self.dispatcher.handle(&mut state, &request)
+			};
+
+			if let Some(response) = response {
+				self.framing.write_message(&mut writer, &response)?;
+			}
+		}
+
+		Ok(())
+	}
+}
+impl<S: Send + 'static> Server<S> {
+	/// Accepts clients on `listener` forever, serving each on a new thread.
+	pub fn serve_tcp(&self, listener: TcpListener) -> std::io::Result<()> {
+		for stream in listener.incoming() {
+			let stream = stream?;
+			let reader = stream.try_clone()?;
+
+			self.spawn_client(reader, stream);
+		}
+
+		Ok(())
+	}
+
+	/// Accepts clients on `listener` forever, serving each on a new thread.
+	#[cfg(unix)]
+	pub fn serve_unix(&self, listener: std::os::unix::net::UnixListener) -> std::io::Result<()> {
+		for stream in listener.incoming() {
+			let stream = stream?;
+			let reader = stream.try_clone()?;
+
+			self.spawn_client(reader, stream);
+		}
+
+		Ok(())
+	}
+
+	/// Binds `endpoint` and serves it.
+	///
+	/// An existing Unix socket file at the path is not removed.
+	pub fn listen(&self, endpoint: &Endpoint) -> std::io::Result<()> {
+		match endpoint {
+			Endpoint::Tcp(address) => self.serve_tcp(TcpListener::bind(address)?),
+			#[cfg(unix)]
+			Endpoint::Unix(path) => self.serve_unix(std::os::unix::net::UnixListener::bind(path)?),
+		}
+	}
+
+	fn spawn_client(
+		&self,
+		reader: impl Read + Send + 'static,
+		writer: impl Write + Send + 'static,
+	) {
+		let server = self.clone();
+		std::thread::spawn(move || {
+			// errors only end the connection of this client
+			let _ = server.serve_connection(reader, writer);
+		});
+	}
+}
+impl<S> Clone for Server<S> {
+	fn clone(&self) -> Self {
+		Server {
+			dispatcher: self.dispatcher.clone(),
+			state: self.state.clone(),
+			framing: self.framing,
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use std::{
+		io::{BufReader, Write},
+		net::{TcpListener, TcpStream},
+	};
+
+	use serde_json::{json, Value};
+
+	use super::{Endpoint, Server};
+	use crate::{
+		dispatch::Dispatcher,
+		framing::Framing,
+		procedures::{common::Pid, detach},
+	};
+
+	#[test]
+	fn test_server_concurrent_clients() {
+		let mut dispatcher = Dispatcher::<Vec<Pid>>::new();
+		dispatcher.register(|attached: &mut Vec<Pid>, params: detach::detach| {
+			match attached.iter().position(|pid| *pid == params.pid) {
+				None => Err(detach::ProcedureError::NotAttached),
+				Some(index) => {
+					attached.remove(index);
+					Ok(())
+				}
+			}
+		});
+
+		let server = Server::new(dispatcher, vec![1, 2]).with_framing(Framing::ContentLength);
+		let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+		let address = listener.local_addr().unwrap();
+		let state = server.state().clone();
+		std::thread::spawn(move || server.serve_tcp(listener));
+
+		let mut clients: Vec<_> = (0..2)
+			.map(|_| {
+				let stream = TcpStream::connect(address).unwrap();
+				(BufReader::new(stream.try_clone().unwrap()), stream)
+			})
+			.collect();
+
+		for (i, (reader, writer)) in clients.iter_mut().enumerate() {
+			let request =
+				json!({"jsonrpc": "2.0", "method": "detach", "params": {"pid": i + 1}, "id": i});
+			Framing::ContentLength
+				.write_message(writer, &request.to_string())
+				.unwrap();
+
+			let response = Framing::ContentLength
+				.read_message(reader)
+				.unwrap()
+				.unwrap();
+			assert_eq!(
+				serde_json::from_str::<Value>(&response).unwrap(),
+				json!({"jsonrpc": "2.0", "result": null, "id": i})
+			);
+		}
+		assert!(state.lock().unwrap().is_empty());
+
+		// the second client still works after the first disconnects
+		drop(clients.remove(0));
+		let (reader, writer) = &mut clients[0];
+		writer.write_all(b"Content-Length: 1\r\n\r\n{").unwrap();
+		let response = Framing::ContentLength
+			.read_message(reader)
+			.unwrap()
+			.unwrap();
+		assert_eq!(
+			serde_json::from_str::<Value>(&response).unwrap()["error"]["code"],
+			-32700
+		);
+
+		assert_eq!(
+			"tcp:127.0.0.1:80".parse::<Endpoint>(),
+			Ok(Endpoint::Tcp("127.0.0.1:80".into()))
+		);
+		assert!("udp:127.0.0.1:80".parse::<Endpoint>().is_err());
+	}
+}
@@ -0,0 +1,443 @@
+//! Implementation of the procedures using the simple platform types of procmem_access.
+
+use std::collections::HashMap;
+
+use procmem_access::{
+	layout::format::ValueFormatter,
+	platform::simple::{
+		ProcessInfo, SimpleMemoryAccess, SimpleMemoryLock, SimpleMemoryMap, SimplePid,
+	},
+	prelude::{MemoryAccess, MemoryLock, MemoryMap, MemoryPage, OffsetType},
+};
+use procmem_scan::prelude::{ByteComparable, ScanConfig, ScanDriver, ValuePredicate};
+
+use crate::{
+	dispatch::Dispatcher,
+	procedures::{
+		attach, common::Pid, detach, lock, pages, process_list, read, read_value, scan_exact,
+		scan_filter, unlock, write, write_value,
+	},
+};
+
+/// Process attached by the `attach` procedure.
+pub struct SimpleProcess {
+	pid: SimplePid,
+	/// Created by the first `lock` call, so that processes which are only read are not traced.
+	lock: Option<SimpleMemoryLock>,
+	access: SimpleMemoryAccess,
+	/// Offsets found by the last scan, sorted.
+	results: Vec<OffsetType>,
+}
+
+/// State of a server serving the simple implementation.
+pub struct SimpleState {
+	processes: HashMap<Pid, SimpleProcess>,
+	driver: ScanDriver,
+}
+impl SimpleState {
+	pub fn new() -> Self {
+		SimpleState {
+			processes: HashMap::new(),
+			driver: ScanDriver::new(ScanConfig::default()),
+		}
+	}
+
+	pub fn is_attached(&self, pid: Pid) -> bool {
+		self.processes.contains_key(&pid)
+	}
+
+	/// Pages scanned by `scan_exact` when no ranges are given.
+	fn default_scan_page(page: &MemoryPage) -> bool {
+		page.permissions.read()
+			&& page.permissions.write()
+			&& !page.permissions.shared()
+			&& page.offset == 0
+	}
+}
+impl Default for SimpleState {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+/// Returns a dispatcher with all procedures registered.
+pub fn dispatcher() -> Dispatcher<SimpleState> {
+	let mut dispatcher = Dispatcher::new();
+
+	dispatcher.register(attach_handler);
+	dispatcher.register(detach_handler);
+	dispatcher.register(lock_handler);
+	dispatcher.register(unlock_handler);
+	dispatcher.register(pages_handler);
+	dispatcher.register(process_list_handler);
+	dispatcher.register(read_handler);
+	dispatcher.register(write_handler);
+	dispatcher.register(read_value_handler);
+	dispatcher.register(write_value_handler);
+	dispatcher.register(scan_exact_handler);
+	dispatcher.register(scan_filter_handler);
+
+	dispatcher
+}
+
+/// Returns the attached process or `not_attached`.
+macro_rules! attached {
+	($state: expr, $pid: expr, $not_attached: expr) => {
+		match $state.processes.get_mut(&$pid) {
+			None => return Err($not_attached),
+			Some(process) => process,
+		}
+	};
+}
+
+fn attach_handler(
+	state: &mut SimpleState,
+	params: attach::attach,
+) -> Result<(), attach::ProcedureError> {
+	if state.is_attached(params.pid) {
+		return Err(attach::ProcedureError::AlreadyAttached);
+	}
+
+	let error = |err: &dyn std::fmt::Display| attach::ProcedureError::Attach(err.to_string());
+	let pid = SimplePid::try_from(params.pid).map_err(|err| error(&err))?;
+	let process = SimpleProcess {
+		pid,
+		lock: None,
+		access: SimpleMemoryAccess::new(pid).map_err(|err| error(&err))?,
+		results: Vec::new(),
+	};
+	state.processes.insert(params.pid, process);
+
+	Ok(())
+}
+
+fn detach_handler(
+	state: &mut SimpleState,
+	params: detach::detach,
+) -> Result<(), detach::ProcedureError> {
+	match state.processes.remove(&params.pid) {
+		None => Err(detach::ProcedureError::NotAttached),
+		Some(_) => Ok(()),
+	}
+}
+
+fn lock_handler(state: &mut SimpleState, params: lock::lock) -> Result<bool, lock::ProcedureError> {
+	let process = attached!(state, params.pid, lock::ProcedureError::NotAttached);
+
+	let error = |err: &dyn std::fmt::Display| lock::ProcedureError::Lock(err.to_string());
+	let lock = match process.lock.as_mut() {
+		Some(lock) => lock,
+		None => process
+			.lock
+			.insert(SimpleMemoryLock::new(process.pid).map_err(|err| error(&err))?),
+	};
+
+	lock.lock().map_err(|err| error(&err))
+}
+
+fn unlock_handler(
+	state: &mut SimpleState,
+	params: unlock::unlock,
+) -> Result<bool, unlock::ProcedureError> {
+	let process = attached!(state, params.pid, unlock::ProcedureError::NotAttached);
+
+	match process.lock.as_mut() {
+		None => Err(unlock::ProcedureError::Unlock(
+			"process was never locked".into(),
+		)),
+		Some(lock) => lock
+			.unlock()
+			.map_err(|err| unlock::ProcedureError::Unlock(err.to_string())),
+	}
+}
+
+fn pages_handler(
+	state: &mut SimpleState,
+	params: pages::pages,
+) -> Result<pages::ProcedureResult, pages::ProcedureError> {
+	let process = attached!(state, params.pid, pages::ProcedureError::NotAttached);
+
+	let map = SimpleMemoryMap::new(process.pid)
+		.map_err(|err| pages::ProcedureError::Pages(err.to_string()))?;
+
+	Ok(map.pages().iter().map(Into::into).collect())
+}
+
+fn process_list_handler(
+	_state: &mut SimpleState,
+	_params: process_list::process_list,
+) -> Result<process_list::ProcedureResult, process_list::ProcedureError> {
+	let processes = ProcessInfo::list_all()
+		.map_err(|err| process_list::ProcedureError::List(err.to_string()))?;
+
+	Ok(processes
+		.into_iter()
+		.filter_map(|info| {
+			Some(crate::procedures::common::ProcessEntry {
+				pid: Pid::try_from(info.pid).ok()?,
+				name: info.name,
+			})
+		})
+		.collect())
+}
+
+fn read_handler(
+	state: &mut SimpleState,
+	params: read::read,
+) -> Result<Vec<u8>, read::ProcedureError> {
+	let process = attached!(state, params.pid, read::ProcedureError::NotAttached);
+
+	let offset = OffsetType::new(params.offset)
+		.ok_or_else(|| read::ProcedureError::Read("offset must not be zero".into()))?;
+	let mut buffer = vec![0u8; params.length];
+	unsafe {
+		process
+			.access
+			.read(offset, &mut buffer)
+			.map_err(|err| read::ProcedureError::Read(err.to_string()))?;
+	}
+
+	Ok(buffer)
+}
+
+fn write_handler(
+	state: &mut SimpleState,
+	params: write::write,
+) -> Result<(), write::ProcedureError> {
+	let process = attached!(state, params.pid, write::ProcedureError::NotAttached);
+
+	let offset = OffsetType::new(params.offset)
+		.ok_or_else(|| write::ProcedureError::Write("offset must not be zero".into()))?;
+	unsafe {
+		process
+			.access
+			.write(offset, &params.data)
+			.map_err(|err| write::ProcedureError::Write(err.to_string()))
+	}
+}
+
+fn read_value_handler(
+	state: &mut SimpleState,
+	params: read_value::read_value,
+) -> Result<String, read_value::ProcedureError> {
+	let process = attached!(state, params.pid, read_value::ProcedureError::NotAttached);
+
+	let formatter = params
+		.value_type
+		.parse::<ValueFormatter>()
+		.map_err(|err| read_value::ProcedureError::InvalidType(err.to_string()))?
+		.with_hex(params.hex);
+	let offset = OffsetType::new(params.offset)
+		.ok_or_else(|| read_value::ProcedureError::Read("offset must not be zero".into()))?;
+	let mut buffer = vec![0u8; formatter.size()];
+	unsafe {
+		process
+			.access
+			.read(offset, &mut buffer)
+			.map_err(|err| read_value::ProcedureError::Read(err.to_string()))?;
+	}
+
+	formatter
+		.format(&buffer)
+		.map_err(|err| read_value::ProcedureError::InvalidType(err.to_string()))
+}
+
+fn write_value_handler(
+	state: &mut SimpleState,
+	params: write_value::write_value,
+) -> Result<(), write_value::ProcedureError> {
+	let process = attached!(state, params.pid, write_value::ProcedureError::NotAttached);
+
+	let data = params
+		.value_type
+		.parse::<ValueFormatter>()
+		.and_then(|formatter| formatter.parse(&params.value))
+		.map_err(|err| write_value::ProcedureError::InvalidValue(err.to_string()))?;
+	let offset = OffsetType::new(params.offset)
+		.ok_or_else(|| write_value::ProcedureError::Write("offset must not be zero".into()))?;
+	unsafe {
+		process
+			.access
+			.write(offset, &data)
+			.map_err(|err| write_value::ProcedureError::Write(err.to_string()))
+	}
+}
+
+fn scan_exact_handler(
+	state: &mut SimpleState,
+	params: scan_exact::scan_exact,
+) -> Result<Vec<u64>, scan_exact::ProcedureError> {
+	let process = attached!(state, params.pid, scan_exact::ProcedureError::NotAttached);
+
+	let mut ranges = match params.ranges {
+		Some(ranges) => ranges
+			.into_iter()
+			.map(
+				|range| match (OffsetType::new(range[0]), OffsetType::new(range[1])) {
+					(Some(start), Some(end)) if start < end => Ok([start, end]),
+					_ => Err(scan_exact::ProcedureError::InvalidRange(range)),
+				},
+			)
+			.collect::<Result<Vec<_>, _>>()?,
+		None => {
+			let map = SimpleMemoryMap::new(process.pid)
+				.map_err(|err| scan_exact::ProcedureError::Pages(err.to_string()))?;
+
+			MemoryPage::merge_sorted(
+				map.pages()
+					.iter()
+					.filter(|page| SimpleState::default_scan_page(page))
+					.cloned(),
+			)
+			.map(|page| page.address_range)
+			.collect()
+		}
+	};
+	ranges.sort_unstable();
+
+	let mut results = Vec::new();
+	unsafe {
+		state
+			.driver
+			.scan(
+				&mut process.access,
+				ranges,
+				ValuePredicate::new(params.value, params.aligned),
+				|(offset, _)| results.push(offset),
+			)
+			.map_err(|err| scan_exact::ProcedureError::Scan(err.to_string()))?;
+	}
+	results.sort_unstable();
+	results.dedup();
+	process.results = results;
+
+	Ok(process.results.iter().map(|offset| offset.get()).collect())
+}
+
+fn scan_filter_handler(
+	state: &mut SimpleState,
+	params: scan_filter::scan_filter,
+) -> Result<Vec<u64>, scan_filter::ProcedureError> {
+	let process = attached!(state, params.pid, scan_filter::ProcedureError::NotAttached);
+
+	let value = params.value.as_bytes();
+	let mut buffer = vec![0u8; value.len()];
+	let access = &mut process.access;
+	process
+		.results
+		.retain(|offset| unsafe { access.read(*offset, &mut buffer).is_ok() && buffer == value });
+
+	Ok(process.results.iter().map(|offset| offset.get()).collect())
+}
+
+#[cfg(test)]
+mod test {
+	use serde_json::{json, Value};
+
+	use super::{dispatcher, SimpleState};
+
+	#[test]
+	fn test_simple_dispatcher_self() {
+		let dispatcher = dispatcher();
+		let mut state = SimpleState::new();
+		let mut call = |method: &str, params: Value| -> Value {
+			let request = json!({"jsonrpc": "2.0", "method": method, "params": params, "id": 1});
+			let response = dispatcher.handle(&mut state, &request.to_string()).unwrap();
+
+			serde_json::from_str(&response).unwrap()
+		};
+
+		let pid = std::process::id();
+		let value = Box::new(0x1234_5678_9ABC_DEF1u64.to_ne_bytes());
+		let offset = value.as_ptr() as u64;
+		let range = [offset & !0xFFF, (offset & !0xFFF) + 0x1000];
+
+		assert_eq!(call("attach", json!({ "pid": pid }))["result"], Value::Null);
+		assert_eq!(
+			call("attach", json!({ "pid": pid }))["error"]["code"],
+			-3101
+		);
+
+		let pages = call("pages", json!({ "pid": pid }));
+		assert!(pages["result"]
+			.as_array()
+			.unwrap()
+			.iter()
+			.any(|page| page["start"].as_u64().unwrap() <= offset
+				&& offset < page["end"].as_u64().unwrap()));
+
+		// scan first, the read result is another copy of the value
+		let found = call(
+			"scan_exact",
+			json!({ "pid": pid, "value": { "type": "u64", "value": 0x1234_5678_9ABC_DEF1u64 }, "ranges": [range] }),
+		);
+		assert_eq!(found["result"], json!([offset]));
+
+		assert_eq!(
+			call("read", json!({ "pid": pid, "offset": offset, "length": 8 }))["result"],
+			json!(0x1234_5678_9ABC_DEF1u64.to_ne_bytes())
+		);
+
+		let new_value = 7u64.to_ne_bytes().to_vec();
+		assert_eq!(
+			call(
+				"write",
+				json!({ "pid": pid, "offset": offset, "data": new_value })
+			)["result"],
+			Value::Null
+		);
+		assert_eq!(
+			unsafe { std::ptr::read_volatile(value.as_ptr() as *const [u8; 8]) },
+			7u64.to_ne_bytes()
+		);
+
+		let filtered = call(
+			"scan_filter",
+			json!({ "pid": pid, "value": { "type": "u64", "value": 7 } }),
+		);
+		assert_eq!(filtered["result"], json!([offset]));
+		let filtered = call(
+			"scan_filter",
+			json!({ "pid": pid, "value": { "type": "u64", "value": 8 } }),
+		);
+		assert_eq!(filtered["result"], json!([]));
+
+		assert_eq!(
+			call(
+				"read_value",
+				json!({ "pid": pid, "offset": offset, "value_type": "u64", "hex": false })
+			)["result"],
+			"7"
+		);
+		assert_eq!(
+			call(
+				"write_value",
+				json!({ "pid": pid, "offset": offset, "value_type": "u64", "value": "0x2A" })
+			)["result"],
+			Value::Null
+		);
+		assert_eq!(
+			unsafe { std::ptr::read_volatile(value.as_ptr() as *const [u8; 8]) },
+			42u64.to_ne_bytes()
+		);
+		assert_eq!(
+			call(
+				"write_value",
+				json!({ "pid": pid, "offset": offset, "value_type": "u128", "value": "1" })
+			)["error"]["code"],
+			-3180
+		);
+
+		assert!(call("process_list", Value::Null)["result"]
+			.as_array()
+			.unwrap()
+			.iter()
+			.any(|process| process["pid"] == pid));
+
+		assert_eq!(call("detach", json!({ "pid": pid }))["result"], Value::Null);
+		assert_eq!(
+			call("read", json!({ "pid": pid, "offset": offset, "length": 8 }))["error"]["code"],
+			-3100
+		);
+	}
+}
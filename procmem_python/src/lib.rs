@@ -1,29 +1,116 @@
-use std::collections::HashSet;
+use std::{
+	collections::{BTreeMap, HashSet},
+	thread::JoinHandle,
+};
 
 use pyo3::{
-	exceptions::PyValueError,
+	exceptions::{PyInterruptedError, PyProcessLookupError, PyValueError},
 	prelude::*,
-	types::{PyAny, PyList},
+	types::{PyAny, PyBytes, PyList, PySlice},
 };
 
 use procmem_access::{
-	platform::simple::{ProcessInfo, SimpleMemoryAccess, SimpleMemoryLock, SimpleMemoryMap},
+	common::{FindError, NamePattern, F16},
+	layout::{format::ValueFormatter, interpret::interpret},
+	memory::freeze::ValueFreezer,
+	platform::simple::{
+		ProcessInfo, SimpleMemoryAccess, SimpleMemoryLock, SimpleMemoryMap, SimplePid,
+	},
 	prelude::{MemoryAccess, MemoryLock, MemoryMap, MemoryPage, MemoryPagePermissions, OffsetType},
 };
-use procmem_scan::prelude::{ByteComparable, StreamScanner, ValuePredicate};
+use procmem_scan::{
+	prelude::{
+		ByteComparable, PatternPredicate, ScanConfig, ScanDriver, ScanProgress, ScannerPredicate,
+		ValuePredicate,
+	},
+	stream::ScanResult,
+};
 
 fn err_to_pyerr<T: std::fmt::Display>(err: T) -> PyErr {
 	PyValueError::new_err(err.to_string())
 }
 
+/// Scans `ranges` using `predicate` with the GIL released, so that other threads can observe and cancel the `progress`.
+fn scan_value(
+	py: Python<'_>,
+	driver: &ScanDriver,
+	access: &mut SimpleMemoryAccess,
+	ranges: Vec<[OffsetType; 2]>,
+	predicate: impl ScannerPredicate + Send,
+	progress: Option<&ScanProgress>,
+	on_result: impl FnMut(ScanResult) + Send,
+) -> PyResult<()> {
+	let finished = py.allow_threads(|| unsafe {
+		match progress {
+			None => driver
+				.scan(access, ranges, predicate, on_result)
+				.map(|()| true),
+			Some(progress) => {
+				driver.scan_with_progress(access, ranges, predicate, progress, on_result)
+			}
+		}
+	});
+
+	match finished.map_err(err_to_pyerr)? {
+		true => Ok(()),
+		false => Err(PyInterruptedError::new_err("scan was cancelled")),
+	}
+}
+
 pub type PyOffsetType = u64;
 
+/// Collects the sorted address ranges of a list of `MemoryPage`s.
+fn page_ranges(pages: &PyList) -> PyResult<Vec<[OffsetType; 2]>> {
+	let mut ranges = Vec::with_capacity(pages.len());
+	for page in pages {
+		let page: &PyCell<PyMemoryPage> = page.downcast()?;
+		ranges.push(page.borrow().0.address_range);
+	}
+	ranges.sort_unstable();
+
+	Ok(ranges)
+}
+
+/// Decodes `bytes` as `encoding`, one of "utf-8", "utf-16" (native endian) or "latin-1".
+fn decode_string(bytes: &[u8], encoding: &str) -> PyResult<String> {
+	match encoding {
+		"utf-8" => String::from_utf8(bytes.to_vec()).map_err(err_to_pyerr),
+		"utf-16" => {
+			if !bytes.len().is_multiple_of(2) {
+				return Err(PyValueError::new_err(
+					"utf-16 string length must be a multiple of 2",
+				));
+			}
+
+			let units: Vec<u16> = bytes
+				.chunks_exact(2)
+				.map(|unit| u16::from_ne_bytes([unit[0], unit[1]]))
+				.collect();
+			String::from_utf16(&units).map_err(err_to_pyerr)
+		}
+		"latin-1" => Ok(bytes.iter().map(|&b| char::from(b)).collect()),
+		unknown => Err(PyValueError::new_err(format!(
+			"Unknown encoding \"{}\"",
+			unknown
+		))),
+	}
+}
+
+/// Size of the terminating NUL in `encoding`.
+fn nul_size(encoding: &str) -> usize {
+	match encoding {
+		"utf-16" => 2,
+		_ => 1,
+	}
+}
+
 #[allow(non_camel_case_types)]
 pub enum MemValue {
 	i8(i8),
 	i16(i16),
 	i32(i32),
 	i64(i64),
+	f16(F16),
 	f32(f32),
 	f64(f64),
 	String(String),
@@ -35,6 +122,7 @@ impl MemValue {
 			"i32" => Self::i32(value.extract::<i32>()?),
 			"i16" => Self::i16(value.extract::<i16>()?),
 			"i8" => Self::i8(value.extract::<i8>()?),
+			"f16" => Self::f16(F16::from_f32(value.extract::<f32>()?)),
 			"f32" => Self::f32(value.extract::<f32>()?),
 			"f64" => Self::f64(value.extract::<f64>()?),
 			"str" => Self::String(value.extract::<&str>()?.to_string()),
@@ -56,6 +144,7 @@ impl ByteComparable for MemValue {
 			Self::i16(v) => v.as_bytes(),
 			Self::i32(v) => v.as_bytes(),
 			Self::i64(v) => v.as_bytes(),
+			Self::f16(v) => v.as_bytes(),
 			Self::f32(v) => v.as_bytes(),
 			Self::f64(v) => v.as_bytes(),
 			Self::String(v) => v.as_str().as_bytes(),
@@ -68,6 +157,7 @@ impl ByteComparable for MemValue {
 			Self::i16(v) => v.align_of(),
 			Self::i32(v) => v.align_of(),
 			Self::i64(v) => v.align_of(),
+			Self::f16(v) => v.align_of(),
 			Self::f32(v) => v.align_of(),
 			Self::f64(v) => v.align_of(),
 			Self::String(v) => v.as_str().align_of(),
@@ -81,6 +171,7 @@ impl IntoPy<PyObject> for MemValue {
 			Self::i16(v) => v.into_py(py),
 			Self::i32(v) => v.into_py(py),
 			Self::i64(v) => v.into_py(py),
+			Self::f16(v) => v.to_f32().into_py(py),
 			Self::f32(v) => v.into_py(py),
 			Self::f64(v) => v.into_py(py),
 			Self::String(v) => v.into_py(py),
@@ -90,29 +181,53 @@ impl IntoPy<PyObject> for MemValue {
 
 #[pyclass(name = "ProcmemSimple")]
 pub struct PyProcmemSimple {
-	pid: i32,
-	lock: SimpleMemoryLock,
+	pid: SimplePid,
+	/// Released by `detach`.
+	lock: Option<SimpleMemoryLock>,
 	map: SimpleMemoryMap,
 	access: SimpleMemoryAccess,
+	driver: ScanDriver,
 	user_locked: bool,
+	/// Started by the first `freeze` call.
+	freezer: Option<ValueFreezer>,
 }
 #[pymethods]
 impl PyProcmemSimple {
 	#[new]
-	pub fn new(pid: i32) -> PyResult<Self> {
+	pub fn new(pid: SimplePid) -> PyResult<Self> {
 		let lock = SimpleMemoryLock::new(pid).map_err(err_to_pyerr)?;
 		let map = SimpleMemoryMap::new(pid).map_err(err_to_pyerr)?;
 		let access = SimpleMemoryAccess::new(pid).map_err(err_to_pyerr)?;
 
 		Ok(Self {
 			pid,
-			lock,
+			lock: Some(lock),
 			map,
 			access,
+			driver: ScanDriver::new(ScanConfig::default()),
 			user_locked: false,
+			freezer: None,
 		})
 	}
 
+	/// Attaches to the process whose name matches `pattern`, see `ProcessInfo.find_by_name`.
+	///
+	/// A pattern containing `*` or `?` is a glob, otherwise a substring. When more processes match, the one with exactly the given name is chosen.
+	#[staticmethod]
+	pub fn from_name(pattern: &str) -> PyResult<Self> {
+		match ProcessInfo::find_one_by_name(NamePattern::fuzzy(pattern)) {
+			Ok(process) => Self::new(process.pid),
+			Err(err @ FindError::NotFound) => Err(PyProcessLookupError::new_err(format!(
+				"Pattern \"{}\": {}",
+				pattern, err
+			))),
+			Err(err) => Err(PyValueError::new_err(format!(
+				"Pattern \"{}\": {}",
+				pattern, err
+			))),
+		}
+	}
+
 	pub fn process_info(&self) -> PyProcessInfo {
 		ProcessInfo::for_pid(self.pid).unwrap().into()
 	}
@@ -120,90 +235,248 @@ impl PyProcmemSimple {
 	pub fn pages(&self) -> Vec<PyMemoryPage> {
 		self.map
 			.pages()
-			.into_iter()
+			.iter()
 			.cloned()
 			.map(PyMemoryPage::from)
 			.collect()
 	}
 
-	pub fn stop(&mut self) {
+	pub fn stop(&mut self) -> PyResult<()> {
 		if self.user_locked {
-			return;
+			return Ok(());
 		}
+
+		self.attached_lock()?.lock().map_err(err_to_pyerr)?;
 		self.user_locked = true;
 
-		self.lock.lock().unwrap();
+		Ok(())
 	}
 
-	pub fn start(&mut self) {
+	pub fn start(&mut self) -> PyResult<()> {
 		if !self.user_locked {
-			return;
+			return Ok(());
 		}
+
+		self.attached_lock()?.unlock().map_err(err_to_pyerr)?;
 		self.user_locked = false;
 
-		self.lock.unlock().unwrap();
+		Ok(())
+	}
+
+	/// Resumes the process and releases the attachment, methods which access memory raise `ValueError` afterwards.
+	///
+	/// Frozen values keep being written until unfrozen. Detaching from a process which already exited succeeds.
+	pub fn detach(&mut self) {
+		// dropping the lock detaches, ignoring errors of a process which is gone
+		self.lock = None;
+		self.user_locked = false;
+	}
+
+	pub fn is_attached(&self) -> bool {
+		self.lock.is_some()
+	}
+
+	pub fn __enter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+		slf
+	}
+
+	/// Stops freezing values and detaches, even when the block raised.
+	pub fn __exit__(&mut self, _exc_type: &PyAny, _exc_value: &PyAny, _traceback: &PyAny) -> bool {
+		self.freezer = None;
+		self.detach();
+
+		false
 	}
 
 	pub fn is_stopped(&self) -> bool {
 		self.user_locked
 	}
 
-	#[pyo3(signature = (pages, value, value_type = "i32", aligned = true))]
+	/// Scans `pages` for `value` and returns the offsets of the matches.
+	///
+	/// Raises `InterruptedError` if the scan is cancelled through `progress`.
+	#[pyo3(signature = (pages, value, value_type = "i32", aligned = true, progress = None))]
 	pub fn scan_exact(
 		&mut self,
+		py: Python<'_>,
 		pages: &PyList,
 		value: &PyAny,
 		value_type: &str,
 		aligned: bool,
+		progress: Option<PyScanProgress>,
 	) -> PyResult<HashSet<PyOffsetType>> {
-		self.lock.lock().map_err(err_to_pyerr)?;
-
 		let value = MemValue::try_from_py(value, value_type)?;
 
 		let predicate = ValuePredicate::new(value, aligned);
-		let mut scanner = StreamScanner::new(predicate);
+		let ranges = page_ranges(pages)?;
+
+		self.attached_lock()?.lock().map_err(err_to_pyerr)?;
 
 		let mut matches = HashSet::new();
-		let mut chunk_buffer = Vec::new();
-		for page in pages {
-			let page: &PyCell<PyMemoryPage> = page.downcast()?;
-			let page = page.borrow();
+		let result = scan_value(
+			py,
+			&self.driver,
+			&mut self.access,
+			ranges,
+			predicate,
+			progress.as_ref().map(|progress| &progress.0),
+			|(offset, _)| {
+				matches.insert(offset.get());
+			},
+		);
 
-			chunk_buffer.resize(page.size() as usize, 0u8);
+		self.attached_lock()?.unlock().map_err(err_to_pyerr)?;
+		result?;
 
-			unsafe {
-				self.access
-					.read(page.0.start(), chunk_buffer.as_mut())
-					.map_err(err_to_pyerr)?;
+		Ok(matches)
+	}
+
+	/// Starts scanning `pages` for `value` in a background thread and returns a `ScanHandle` to poll it.
+	///
+	/// The scan reads memory through its own handle and does not stop the process, call `stop` first to scan a consistent state.
+	#[pyo3(signature = (pages, value, value_type = "i32", aligned = true))]
+	pub fn scan_exact_async(
+		&mut self,
+		pages: &PyList,
+		value: &PyAny,
+		value_type: &str,
+		aligned: bool,
+	) -> PyResult<PyScanHandle> {
+		let value = MemValue::try_from_py(value, value_type)?;
+		let predicate = ValuePredicate::new(value, aligned);
+		let ranges = page_ranges(pages)?;
+		let mut access = SimpleMemoryAccess::new(self.pid).map_err(err_to_pyerr)?;
+
+		let progress = PyScanProgress::default();
+		let scan_progress = progress.0.clone();
+		let thread = std::thread::spawn(move || {
+			let driver = ScanDriver::new(ScanConfig::default());
+
+			let mut matches = HashSet::new();
+			let finished = unsafe {
+				driver.scan_with_progress(
+					&mut access,
+					ranges,
+					predicate,
+					&scan_progress,
+					|(offset, _)| {
+						matches.insert(offset.get());
+					},
+				)
 			}
+			.map_err(|err| err.to_string())?;
 
-			matches.extend(
-				scanner
-					.scan_once(page.0.start(), chunk_buffer.iter().copied())
-					.map(|(offset, _)| offset.get()),
-			);
-		}
+			Ok(finished.then_some(matches))
+		});
 
-		self.lock.unlock().map_err(err_to_pyerr)?;
+		Ok(PyScanHandle {
+			progress,
+			thread: Some(thread),
+			outcome: None,
+		})
+	}
 
+	/// Scans `pages` for a byte pattern such as `"48 8B ?? 05"` and returns the offsets of the matches in ascending order.
+	///
+	/// Raises `InterruptedError` if the scan is cancelled through `progress`.
+	#[pyo3(signature = (pages, pattern, progress = None))]
+	pub fn scan_pattern(
+		&mut self,
+		py: Python<'_>,
+		pages: &PyList,
+		pattern: &str,
+		progress: Option<PyScanProgress>,
+	) -> PyResult<Vec<PyOffsetType>> {
+		let predicate = pattern.parse::<PatternPredicate>().map_err(err_to_pyerr)?;
+		let ranges = page_ranges(pages)?;
+
+		self.attached_lock()?.lock().map_err(err_to_pyerr)?;
+
+		let mut matches = Vec::new();
+		let result = scan_value(
+			py,
+			&self.driver,
+			&mut self.access,
+			ranges,
+			predicate,
+			progress.as_ref().map(|progress| &progress.0),
+			|(offset, _)| matches.push(offset.get()),
+		);
+
+		self.attached_lock()?.unlock().map_err(err_to_pyerr)?;
+		result?;
+
+		matches.sort_unstable();
 		Ok(matches)
 	}
 
-	#[pyo3(signature = (offset, value_type = "i32"))]
-	pub fn read(&mut self, offset: PyOffsetType, value_type: &str) -> PyResult<MemValue> {
-		self.lock.lock().map_err(err_to_pyerr)?;
+	/// Reads `length` raw bytes at `offset`.
+	pub fn read_bytes<'py>(
+		&mut self,
+		py: Python<'py>,
+		offset: PyOffsetType,
+		length: usize,
+	) -> PyResult<&'py PyBytes> {
+		let mut buffer = vec![0u8; length];
+		self.read_raw(offset, &mut buffer)?;
+
+		Ok(PyBytes::new(py, &buffer))
+	}
+
+	/// Reads memory at `offset` into a writable buffer, such as a `bytearray`, `memoryview` or numpy array, and returns the number of bytes read.
+	///
+	/// The buffer is filled completely and must be C-contiguous.
+	pub fn read_into(
+		&mut self,
+		py: Python<'_>,
+		offset: PyOffsetType,
+		buffer: &PyAny,
+	) -> PyResult<usize> {
+		// the buffer protocol is not part of the limited API before Python 3.11, go through a byte view instead
+		let view = py
+			.import("builtins")?
+			.getattr("memoryview")?
+			.call1((buffer,))?
+			.call_method1("cast", ("B",))?;
+		let length = view.len()?;
+
+		let mut bytes = vec![0u8; length];
+		self.read_raw(offset, &mut bytes)?;
+		view.set_item(
+			PySlice::new(py, 0, length as isize, 1),
+			PyBytes::new(py, &bytes),
+		)?;
+
+		Ok(length)
+	}
+
+	/// Reads a value of `value_type` at `offset`.
+	///
+	/// Strings (`"str"`) require the `length` in bytes and are decoded using `encoding`.
+	#[pyo3(signature = (offset, value_type = "i32", length = None, encoding = "utf-8"))]
+	pub fn read(
+		&mut self,
+		offset: PyOffsetType,
+		value_type: &str,
+		length: Option<usize>,
+		encoding: &str,
+	) -> PyResult<MemValue> {
+		self.attached_lock()?.lock().map_err(err_to_pyerr)?;
 
 		let offset = OffsetType::new_unwrap(offset);
 
 		macro_rules! read_fixed_size {
-			($fixed_type: ident) => {{
+			($fixed_type: ident) => {
+				read_fixed_size!($fixed_type, $fixed_type)
+			};
+			($variant: ident, $fixed_type: ty) => {{
 				let mut buffer = [0u8; std::mem::size_of::<$fixed_type>()];
 				unsafe {
 					self.access
 						.read(offset, &mut buffer)
 						.map_err(err_to_pyerr)?
 				};
-				MemValue::$fixed_type(<$fixed_type>::from_ne_bytes(buffer))
+				MemValue::$variant(<$fixed_type>::from_ne_bytes(buffer))
 			}};
 		}
 		let value = match value_type {
@@ -211,9 +484,21 @@ impl PyProcmemSimple {
 			"i32" => read_fixed_size!(i32),
 			"i16" => read_fixed_size!(i16),
 			"i8" => read_fixed_size!(i8),
+			"f16" => read_fixed_size!(f16, F16),
 			"f32" => read_fixed_size!(f32),
 			"f64" => read_fixed_size!(f64),
-			"str" => todo!(),
+			"str" => {
+				let length = length
+					.ok_or_else(|| PyValueError::new_err("length is required to read a string"))?;
+
+				let mut buffer = vec![0u8; length];
+				unsafe {
+					self.access
+						.read(offset, &mut buffer)
+						.map_err(err_to_pyerr)?
+				};
+				MemValue::String(decode_string(&buffer, encoding)?)
+			}
 			unknown => {
 				return Err(PyValueError::new_err(format!(
 					"Unknown type \"{}\"",
@@ -222,13 +507,109 @@ impl PyProcmemSimple {
 			}
 		};
 
-		self.lock.unlock().map_err(err_to_pyerr)?;
+		self.attached_lock()?.unlock().map_err(err_to_pyerr)?;
 		Ok(value)
 	}
 
+	/// Reads a NUL terminated string at `offset` of at most `max_len` bytes, excluding the terminator.
+	///
+	/// The memory is read page by page, so the string may end right before an unreadable page.
+	#[pyo3(signature = (offset, max_len = 4096, encoding = "utf-8"))]
+	pub fn read_cstring(
+		&mut self,
+		offset: PyOffsetType,
+		max_len: usize,
+		encoding: &str,
+	) -> PyResult<String> {
+		const PAGE_SIZE: u64 = 4096;
+
+		self.attached_lock()?.lock().map_err(err_to_pyerr)?;
+
+		let nul_size = nul_size(encoding);
+		let mut bytes = Vec::new();
+		let mut current = OffsetType::new_unwrap(offset);
+		while bytes.len() < max_len {
+			let page_end = (current.get() / PAGE_SIZE + 1) * PAGE_SIZE;
+			let chunk_len = ((page_end - current.get()) as usize).min(max_len - bytes.len());
+
+			let start = bytes.len();
+			bytes.resize(start + chunk_len, 0);
+			unsafe {
+				self.access
+					.read(current, &mut bytes[start..])
+					.map_err(err_to_pyerr)?
+			};
+
+			// terminators are aligned to their size relative to the string start
+			let search_start = start - start % nul_size;
+			if let Some(end) = bytes[search_start..]
+				.chunks_exact(nul_size)
+				.position(|unit| unit.iter().all(|&b| b == 0))
+			{
+				bytes.truncate(search_start + end * nul_size);
+				break;
+			}
+			current = current.saturating_add(chunk_len as u64);
+		}
+		bytes.truncate(max_len - max_len % nul_size);
+
+		self.attached_lock()?.unlock().map_err(err_to_pyerr)?;
+		decode_string(&bytes, encoding)
+	}
+
+	/// Reads a value of `value_type` and returns it as text.
+	///
+	/// The type is written like `i32`, `ptr`, `bytes[16]` or `string[32]`, numbers are written as hex when `hex` is set.
+	#[pyo3(signature = (offset, value_type, hex = false))]
+	pub fn read_formatted(
+		&mut self,
+		offset: PyOffsetType,
+		value_type: &str,
+		hex: bool,
+	) -> PyResult<String> {
+		let formatter = value_type
+			.parse::<ValueFormatter>()
+			.map_err(err_to_pyerr)?
+			.with_hex(hex);
+
+		self.attached_lock()?.lock().map_err(err_to_pyerr)?;
+
+		let mut buffer = vec![0u8; formatter.size()];
+		unsafe {
+			self.access
+				.read(OffsetType::new_unwrap(offset), &mut buffer)
+				.map_err(err_to_pyerr)?
+		};
+
+		self.attached_lock()?.unlock().map_err(err_to_pyerr)?;
+		formatter.format(&buffer).map_err(err_to_pyerr)
+	}
+
+	/// Parses `text` as a value of `value_type` and writes it, the syntax is the one produced by `read_formatted`.
+	pub fn write_formatted(
+		&mut self,
+		offset: PyOffsetType,
+		value_type: &str,
+		text: &str,
+	) -> PyResult<()> {
+		let formatter = value_type.parse::<ValueFormatter>().map_err(err_to_pyerr)?;
+		let bytes = formatter.parse(text).map_err(err_to_pyerr)?;
+
+		self.attached_lock()?.lock().map_err(err_to_pyerr)?;
+
+		unsafe {
+			self.access
+				.write(OffsetType::new_unwrap(offset), &bytes)
+				.map_err(err_to_pyerr)?
+		};
+
+		self.attached_lock()?.unlock().map_err(err_to_pyerr)?;
+		Ok(())
+	}
+
 	#[pyo3(signature = (offset, value, value_type = "i32"))]
 	pub fn write(&mut self, offset: PyOffsetType, value: &PyAny, value_type: &str) -> PyResult<()> {
-		self.lock.lock().map_err(err_to_pyerr)?;
+		self.attached_lock()?.lock().map_err(err_to_pyerr)?;
 
 		let offset = OffsetType::new_unwrap(offset);
 		let value = MemValue::try_from_py(value, value_type)?;
@@ -239,9 +620,344 @@ impl PyProcmemSimple {
 				.map_err(err_to_pyerr)?
 		};
 
-		self.lock.unlock().map_err(err_to_pyerr)?;
+		self.attached_lock()?.unlock().map_err(err_to_pyerr)?;
+		Ok(())
+	}
+
+	/// Keeps writing `value` at `offset` every `interval_ms` milliseconds until unfrozen.
+	///
+	/// The interval is only used when the first value is frozen.
+	#[pyo3(signature = (offset, value, value_type = "i32", interval_ms = 50))]
+	pub fn freeze(
+		&mut self,
+		offset: PyOffsetType,
+		value: &PyAny,
+		value_type: &str,
+		interval_ms: u64,
+	) -> PyResult<()> {
+		let offset = OffsetType::new_unwrap(offset);
+		let value = MemValue::try_from_py(value, value_type)?;
+
+		let freezer = match self.freezer.as_mut() {
+			Some(freezer) => freezer,
+			None => {
+				let access = SimpleMemoryAccess::new(self.pid).map_err(err_to_pyerr)?;
+				self.freezer.insert(unsafe {
+					ValueFreezer::new(access, std::time::Duration::from_millis(interval_ms))
+				})
+			}
+		};
+		freezer.freeze(offset, value.as_bytes());
+
 		Ok(())
 	}
+
+	/// Stops freezing the value at `offset`, returns whether it was frozen.
+	pub fn unfreeze(&mut self, offset: PyOffsetType) -> bool {
+		match self.freezer.as_ref() {
+			None => false,
+			Some(freezer) => freezer.unfreeze(OffsetType::new_unwrap(offset)).is_some(),
+		}
+	}
+
+	/// Returns the offsets of the frozen values.
+	pub fn frozen(&self) -> Vec<PyOffsetType> {
+		match self.freezer.as_ref() {
+			None => Vec::new(),
+			Some(freezer) => freezer
+				.frozen()
+				.into_iter()
+				.map(|(offset, _)| offset.get())
+				.collect(),
+		}
+	}
+
+	/// Returns a report of the memory at `offset` decoded as every supported type.
+	pub fn inspect(&mut self, offset: PyOffsetType) -> PyResult<String> {
+		self.attached_lock()?.lock().map_err(err_to_pyerr)?;
+
+		let offset = OffsetType::new_unwrap(offset);
+		let report =
+			unsafe { interpret(&mut self.access, &self.map, offset).map_err(err_to_pyerr)? };
+
+		self.attached_lock()?.unlock().map_err(err_to_pyerr)?;
+		Ok(report.to_string())
+	}
+}
+
+impl PyProcmemSimple {
+	fn attached_lock(&mut self) -> PyResult<&mut SimpleMemoryLock> {
+		self.lock
+			.as_mut()
+			.ok_or_else(|| PyValueError::new_err("process was detached"))
+	}
+
+	fn read_raw(&mut self, offset: PyOffsetType, buffer: &mut [u8]) -> PyResult<()> {
+		self.attached_lock()?.lock().map_err(err_to_pyerr)?;
+		let result = unsafe { self.access.read(OffsetType::new_unwrap(offset), buffer) };
+		self.attached_lock()?.unlock().map_err(err_to_pyerr)?;
+
+		result.map_err(err_to_pyerr)
+	}
+}
+
+/// Scan keeping its matches between calls, so that they can be narrowed down by next scans.
+#[pyclass(name = "ScanSession")]
+pub struct PyScanSession {
+	lock: SimpleMemoryLock,
+	map: SimpleMemoryMap,
+	access: SimpleMemoryAccess,
+	driver: ScanDriver,
+	value_type: String,
+	aligned: bool,
+	/// Current matches with their bytes as of the last scan.
+	matches: BTreeMap<OffsetType, Vec<u8>>,
+	scanned: bool,
+}
+impl PyScanSession {
+	fn default_scan_page(page: &MemoryPage) -> bool {
+		page.permissions.read()
+			&& page.permissions.write()
+			&& !page.permissions.shared()
+			&& page.offset == 0
+	}
+
+	fn ensure_scanned(&self) -> PyResult<()> {
+		if !self.scanned {
+			return Err(PyValueError::new_err("first_scan must be called first"));
+		}
+
+		Ok(())
+	}
+
+	/// Re-reads the matches with the GIL released and keeps those for which `keep(old, new)` returns true.
+	fn filter(
+		&mut self,
+		py: Python<'_>,
+		mut keep: impl FnMut(&[u8], &[u8]) -> bool + Send,
+	) -> PyResult<usize> {
+		self.lock.lock().map_err(err_to_pyerr)?;
+
+		let access = &mut self.access;
+		let matches = &mut self.matches;
+		py.allow_threads(|| {
+			let mut buffer = Vec::new();
+			matches.retain(|offset, old| {
+				buffer.resize(old.len(), 0);
+				// matches which can no longer be read are dropped
+				if unsafe { access.read(*offset, &mut buffer).is_err() } || !keep(old, &buffer) {
+					return false;
+				}
+
+				old.copy_from_slice(&buffer);
+				true
+			})
+		});
+
+		self.lock.unlock().map_err(err_to_pyerr)?;
+		Ok(self.matches.len())
+	}
+}
+#[pymethods]
+impl PyScanSession {
+	#[new]
+	#[pyo3(signature = (pid, value_type = "i32", aligned = true))]
+	pub fn new(pid: SimplePid, value_type: &str, aligned: bool) -> PyResult<Self> {
+		let lock = SimpleMemoryLock::new(pid).map_err(err_to_pyerr)?;
+		let map = SimpleMemoryMap::new(pid).map_err(err_to_pyerr)?;
+		let access = SimpleMemoryAccess::new(pid).map_err(err_to_pyerr)?;
+
+		Ok(Self {
+			lock,
+			map,
+			access,
+			driver: ScanDriver::new(ScanConfig::default()),
+			value_type: value_type.to_string(),
+			aligned,
+			matches: BTreeMap::new(),
+			scanned: false,
+		})
+	}
+
+	/// Scans `pages` for `value`, replacing the current matches, and returns the number of matches.
+	///
+	/// When `pages` are not given, private writable pages which are not file mappings are scanned.
+	/// Raises `InterruptedError` if the scan is cancelled through `progress`, the current matches are kept in that case.
+	#[pyo3(signature = (value, pages = None, progress = None))]
+	pub fn first_scan(
+		&mut self,
+		py: Python<'_>,
+		value: &PyAny,
+		pages: Option<&PyList>,
+		progress: Option<PyScanProgress>,
+	) -> PyResult<usize> {
+		let value = MemValue::try_from_py(value, &self.value_type)?;
+
+		let mut ranges = match pages {
+			Some(pages) => page_ranges(pages)?,
+			None => MemoryPage::merge_sorted(
+				self.map
+					.pages()
+					.iter()
+					.filter(|page| Self::default_scan_page(page))
+					.cloned(),
+			)
+			.map(|page| page.address_range)
+			.collect(),
+		};
+		ranges.sort_unstable();
+
+		self.lock.lock().map_err(err_to_pyerr)?;
+
+		let bytes = value.as_bytes().to_vec();
+		let mut matches = BTreeMap::new();
+		let result = scan_value(
+			py,
+			&self.driver,
+			&mut self.access,
+			ranges,
+			ValuePredicate::new(value, self.aligned),
+			progress.as_ref().map(|progress| &progress.0),
+			|(offset, _)| {
+				matches.insert(offset, bytes.clone());
+			},
+		);
+
+		self.lock.unlock().map_err(err_to_pyerr)?;
+		result?;
+
+		self.matches = matches;
+		self.scanned = true;
+		Ok(self.matches.len())
+	}
+
+	/// Keeps the matches which now contain `value` and returns the number of matches.
+	pub fn next_scan(&mut self, py: Python<'_>, value: &PyAny) -> PyResult<usize> {
+		self.ensure_scanned()?;
+
+		let value = MemValue::try_from_py(value, &self.value_type)?;
+		self.filter(py, |_, new| new == value.as_bytes())
+	}
+
+	/// Keeps the matches whose value changed since the last scan and returns the number of matches.
+	pub fn next_scan_changed(&mut self, py: Python<'_>) -> PyResult<usize> {
+		self.ensure_scanned()?;
+
+		self.filter(py, |old, new| old != new)
+	}
+
+	/// Forgets the matches, the next scan must be a first scan again.
+	pub fn reset(&mut self) {
+		self.matches.clear();
+		self.scanned = false;
+	}
+
+	/// Returns the offsets of the current matches in ascending order.
+	pub fn matches(&self) -> Vec<PyOffsetType> {
+		self.matches.keys().map(|offset| offset.get()).collect()
+	}
+
+	pub fn __len__(&self) -> usize {
+		self.matches.len()
+	}
+}
+
+/// Outcome of a background scan, `None` if it was cancelled.
+type ScanOutcome = Result<Option<HashSet<PyOffsetType>>, String>;
+
+/// Scan running in a background thread, see `ProcmemSimple.scan_exact_async`.
+#[pyclass(name = "ScanHandle")]
+pub struct PyScanHandle {
+	progress: PyScanProgress,
+	thread: Option<JoinHandle<ScanOutcome>>,
+	outcome: Option<ScanOutcome>,
+}
+#[pymethods]
+impl PyScanHandle {
+	#[getter]
+	pub fn progress(&self) -> PyScanProgress {
+		self.progress.clone()
+	}
+
+	/// Returns whether the scan finished, was cancelled or failed, so that `result` does not block.
+	pub fn done(&self) -> bool {
+		self.thread
+			.as_ref()
+			.map(JoinHandle::is_finished)
+			.unwrap_or(true)
+	}
+
+	pub fn cancel(&self) {
+		self.progress.cancel()
+	}
+
+	/// Waits for the scan with the GIL released and returns the offsets of the matches.
+	///
+	/// Raises `InterruptedError` if the scan was cancelled.
+	pub fn result(&mut self, py: Python<'_>) -> PyResult<HashSet<PyOffsetType>> {
+		if let Some(thread) = self.thread.take() {
+			let outcome = py
+				.allow_threads(|| thread.join())
+				.unwrap_or_else(|_| Err("scan thread panicked".to_string()));
+			self.outcome = Some(outcome);
+		}
+
+		match self.outcome.as_ref().unwrap() {
+			Ok(Some(matches)) => Ok(matches.clone()),
+			Ok(None) => Err(PyInterruptedError::new_err("scan was cancelled")),
+			Err(err) => Err(PyValueError::new_err(err.clone())),
+		}
+	}
+}
+
+/// Progress of a running scan, which can be observed and cancelled from other threads.
+///
+/// Pass it to a scan method running in another thread, scans release the GIL while they run.
+#[pyclass(name = "ScanProgress")]
+#[derive(Clone, Default)]
+pub struct PyScanProgress(ScanProgress);
+#[pymethods]
+impl PyScanProgress {
+	#[new]
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	#[getter]
+	pub fn total_bytes(&self) -> u64 {
+		self.0.total_bytes()
+	}
+
+	#[getter]
+	pub fn bytes_scanned(&self) -> u64 {
+		self.0.bytes_scanned()
+	}
+
+	#[getter]
+	pub fn pages_done(&self) -> u64 {
+		self.0.pages_done()
+	}
+
+	#[getter]
+	pub fn matches(&self) -> u64 {
+		self.0.matches()
+	}
+
+	/// Scanned fraction between 0.0 and 1.0.
+	#[getter]
+	pub fn fraction(&self) -> f64 {
+		self.0.fraction()
+	}
+
+	/// Stops the scan at the next chunk boundary, the handle cannot be reused afterwards.
+	pub fn cancel(&self) {
+		self.0.cancel()
+	}
+
+	#[getter]
+	pub fn cancelled(&self) -> bool {
+		self.0.is_cancelled()
+	}
 }
 
 #[pyclass(name = "MemoryPage")]
@@ -325,7 +1041,7 @@ impl PyMemoryPagePermissions {
 
 #[pyclass(get_all, name = "ProcessInfo")]
 pub struct PyProcessInfo {
-	pub pid: i32,
+	pub pid: SimplePid,
 	pub name: String,
 }
 impl From<ProcessInfo> for PyProcessInfo {
@@ -347,6 +1063,24 @@ impl PyProcessInfo {
 			.collect())
 	}
 
+	/// Finds processes by name, `mode` is one of "exact", "substring" or "glob".
+	#[staticmethod]
+	#[pyo3(signature = (pattern, mode = "exact"))]
+	pub fn find_by_name(pattern: &str, mode: &str) -> PyResult<Vec<Self>> {
+		let pattern = match mode {
+			"exact" => NamePattern::Exact(pattern),
+			"substring" => NamePattern::Substring(pattern),
+			"glob" => NamePattern::Glob(pattern),
+			_ => return Err(PyValueError::new_err(format!("Unknown mode \"{}\"", mode))),
+		};
+
+		Ok(ProcessInfo::find_by_name(pattern)
+			.map_err(err_to_pyerr)?
+			.into_iter()
+			.map(PyProcessInfo::from)
+			.collect())
+	}
+
 	pub fn __str__(&self) -> String {
 		format!("{} ({})", self.pid, self.name)
 	}
@@ -356,6 +1090,9 @@ impl PyProcessInfo {
 #[pymodule]
 fn procmem(_py: Python, m: &PyModule) -> PyResult<()> {
 	m.add_class::<PyProcmemSimple>()?;
+	m.add_class::<PyScanSession>()?;
+	m.add_class::<PyScanProgress>()?;
+	m.add_class::<PyScanHandle>()?;
 	m.add_class::<PyMemoryPage>()?;
 	m.add_class::<PyMemoryPagePermissions>()?;
 	m.add_class::<PyProcessInfo>()?;
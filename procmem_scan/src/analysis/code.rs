@@ -0,0 +1,440 @@
+//! Instruction level scanning of executable memory using capstone.
+//!
+//! Byte signatures break when operands are relocated or encoded relative to the instruction pointer.
+//! Disassembling the code first allows matching on what the instructions actually reference.
+//!
+//! Only x86-64 is supported for now.
+
+use capstone::{
+	arch::{
+		x86::{ArchMode, X86OperandType, X86Reg},
+		ArchOperand, BuildsCapstone,
+	},
+	Capstone, Insn, InsnGroupType, RegId,
+};
+use thiserror::Error;
+
+use procmem_access::{
+	memory::access::ReadError,
+	prelude::{ErrorKind, MemoryAccess, MemoryMap, OffsetType, ProcmemError},
+};
+
+#[derive(Debug, Error)]
+pub enum CodeScanError {
+	#[error("capstone failed: {0}")]
+	Capstone(capstone::Error),
+	#[error("could not read code {}-{}", range[0], range[1])]
+	Read {
+		range: [OffsetType; 2],
+		#[source]
+		source: ReadError,
+	},
+}
+impl CodeScanError {
+	pub fn kind(&self) -> ErrorKind {
+		match self {
+			CodeScanError::Capstone(_) => ErrorKind::Platform,
+			CodeScanError::Read { source, .. } => source.kind(),
+		}
+	}
+}
+impl From<capstone::Error> for CodeScanError {
+	fn from(err: capstone::Error) -> Self {
+		CodeScanError::Capstone(err)
+	}
+}
+impl From<CodeScanError> for ProcmemError {
+	fn from(err: CodeScanError) -> Self {
+		ProcmemError::new(err.kind(), err)
+	}
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Operand {
+	Register(String),
+	/// Immediate value. For relative branches this is already the absolute target address.
+	Immediate(i64),
+	Memory {
+		segment: Option<String>,
+		base: Option<String>,
+		index: Option<String>,
+		scale: i32,
+		displacement: i64,
+		/// Absolute address of the operand if it can be computed statically (rip-relative or absolute addressing).
+		target: Option<u64>,
+	},
+}
+
+/// Disassembled instruction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Instruction {
+	pub address: OffsetType,
+	pub bytes: Vec<u8>,
+	pub mnemonic: String,
+	/// Operands as text, as printed by the disassembler.
+	pub op_str: String,
+	pub operands: Vec<Operand>,
+	/// Byte ranges of operands which change when the code or its data is relocated.
+	///
+	/// These are relative branch targets and rip-relative or absolute displacements.
+	pub relocated_bytes: Vec<std::ops::Range<usize>>,
+	pub is_call: bool,
+	pub is_jump: bool,
+}
+impl Instruction {
+	pub fn end(&self) -> OffsetType {
+		self.address.saturating_add(self.bytes.len() as u64)
+	}
+
+	/// Returns absolute addresses referenced by memory operands and immediates.
+	pub fn referenced_addresses(&self) -> impl Iterator<Item = u64> + '_ {
+		self.operands.iter().filter_map(|operand| match operand {
+			Operand::Immediate(value) => Some(*value as u64),
+			Operand::Memory { target, .. } => *target,
+			Operand::Register(_) => None,
+		})
+	}
+
+	/// Returns whether the instruction references `address`, for example `mov rax, [rip + X]` where `rip + X == address`.
+	pub fn references(&self, address: OffsetType) -> bool {
+		self.referenced_addresses().any(|a| a == address.get())
+	}
+
+	/// Returns whether this is a direct call to `address`.
+	pub fn is_call_to(&self, address: OffsetType) -> bool {
+		self.is_call
+			&& matches!(self.operands.as_slice(), [Operand::Immediate(target)] if *target as u64 == address.get())
+	}
+
+	/// Returns whether this is a direct jump (conditional or not) to `address`.
+	pub fn is_jump_to(&self, address: OffsetType) -> bool {
+		self.is_jump
+			&& matches!(self.operands.as_slice(), [Operand::Immediate(target)] if *target as u64 == address.get())
+	}
+}
+impl std::fmt::Display for Instruction {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		write!(f, "{} {} {}", self.address, self.mnemonic, self.op_str)
+	}
+}
+
+/// Maximum length of an x86 instruction.
+pub(crate) const MAX_INSTRUCTION_LENGTH: usize = 15;
+
+/// Disassembles executable memory and matches instructions using predicates.
+pub struct CodeScanner {
+	capstone: Capstone,
+	chunk_size: usize,
+}
+impl CodeScanner {
+	/// Creates a scanner for x86-64 code which reads memory in chunks of `chunk_size` bytes.
+	pub fn new_x86_64(chunk_size: usize) -> Result<Self, CodeScanError> {
+		let capstone = Capstone::new()
+			.x86()
+			.mode(ArchMode::Mode64)
+			.detail(true)
+			.build()?;
+
+		Ok(CodeScanner {
+			capstone,
+			chunk_size: chunk_size.max(MAX_INSTRUCTION_LENGTH),
+		})
+	}
+
+	fn register_name(&self, register: RegId) -> Option<String> {
+		if register.0 == 0 {
+			return None;
+		}
+
+		self.capstone.reg_name(register)
+	}
+
+	fn convert(&self, insn: &Insn) -> Result<Instruction, CodeScanError> {
+		let detail = self.capstone.insn_detail(insn)?;
+		let end = insn.address() + insn.bytes().len() as u64;
+
+		let len = insn.bytes().len();
+
+		let in_group = |group: InsnGroupType::Type| {
+			detail.groups().any(|g| g.0 as InsnGroupType::Type == group)
+		};
+		let is_call = in_group(InsnGroupType::CS_GRP_CALL);
+		let is_jump = in_group(InsnGroupType::CS_GRP_JUMP);
+
+		let x86_operands: Vec<_> = detail
+			.arch_detail()
+			.operands()
+			.into_iter()
+			.filter_map(|operand| match operand {
+				ArchOperand::X86Operand(operand) => Some(operand),
+				_ => None,
+			})
+			.collect();
+		// immediates are encoded after the displacement
+		let immediate_size: usize = x86_operands
+			.iter()
+			.filter(|operand| matches!(operand.op_type, X86OperandType::Imm(_)))
+			.map(|operand| operand.size as usize)
+			.sum();
+
+		let mut relocated_bytes = Vec::new();
+		let mut operands = Vec::with_capacity(x86_operands.len());
+		for operand in x86_operands {
+			let operand = match operand.op_type {
+				X86OperandType::Reg(register) => match self.register_name(register) {
+					None => continue,
+					Some(name) => Operand::Register(name),
+				},
+				X86OperandType::Imm(value) => {
+					if is_call || is_jump {
+						// relative branch target, rel8 for short jumps and rel32 otherwise
+						let size = if len <= 3 { 1 } else { 4 };
+						relocated_bytes.push(len - size..len);
+					}
+
+					Operand::Immediate(value)
+				}
+				X86OperandType::Mem(memory) => {
+					let target = match (memory.base().0 as u32, memory.index().0) {
+						(X86Reg::X86_REG_RIP, 0) => Some(end.wrapping_add(memory.disp() as u64)),
+						(0, 0) => Some(memory.disp() as u64),
+						_ => None,
+					};
+					if target.is_some() && len >= immediate_size + 4 {
+						let disp_end = len - immediate_size;
+						relocated_bytes.push(disp_end - 4..disp_end);
+					}
+
+					Operand::Memory {
+						segment: self.register_name(memory.segment()),
+						base: self.register_name(memory.base()),
+						index: self.register_name(memory.index()),
+						scale: memory.scale(),
+						displacement: memory.disp(),
+						target,
+					}
+				}
+				X86OperandType::Invalid => continue,
+			};
+
+			operands.push(operand);
+		}
+
+		Ok(Instruction {
+			address: OffsetType::new_unwrap(insn.address()),
+			bytes: insn.bytes().to_vec(),
+			mnemonic: insn.mnemonic().unwrap_or_default().to_string(),
+			op_str: insn.op_str().unwrap_or_default().to_string(),
+			operands,
+			relocated_bytes,
+			is_call,
+			is_jump,
+		})
+	}
+
+	/// Disassembles `code` located at `address` using linear sweep.
+	///
+	/// Bytes which do not decode to a valid instruction are skipped one at a time.
+	pub fn disassemble(
+		&self,
+		address: OffsetType,
+		code: &[u8],
+	) -> Result<Vec<Instruction>, CodeScanError> {
+		let mut instructions = Vec::new();
+
+		let mut position = 0;
+		while position < code.len() {
+			let decoded = self
+				.capstone
+				.disasm_all(&code[position..], address.get() + position as u64)?;
+
+			for insn in decoded.iter() {
+				position += insn.bytes().len();
+				instructions.push(self.convert(&insn)?);
+			}
+
+			// skip the byte that could not be decoded
+			position += 1;
+		}
+
+		Ok(instructions)
+	}
+
+	/// Disassembles readable executable pages of `map` and calls `on_match` for each instruction accepted by `predicate`.
+	///
+	/// ## Safety
+	/// * See [`MemoryAccess::read`].
+	pub unsafe fn scan<A: MemoryAccess, M: MemoryMap>(
+		&self,
+		access: &mut A,
+		map: &M,
+		mut predicate: impl FnMut(&Instruction) -> bool,
+		mut on_match: impl FnMut(Instruction),
+	) -> Result<(), CodeScanError> {
+		let mut buffer = vec![0u8; self.chunk_size + MAX_INSTRUCTION_LENGTH];
+
+		let pages = map
+			.pages()
+			.iter()
+			.filter(|page| page.permissions.read() && page.permissions.exec());
+		for page in pages {
+			let mut chunk_start = page.start();
+			while chunk_start < page.end() {
+				let chunk_end = chunk_start
+					.saturating_add(self.chunk_size as u64)
+					.min(page.end());
+				// read a little past the chunk end so that the last instruction is not cut off
+				let read_end = chunk_end
+					.saturating_add(MAX_INSTRUCTION_LENGTH as u64)
+					.min(page.end());
+
+				let code = &mut buffer[..(read_end.get() - chunk_start.get()) as usize];
+				access
+					.read(chunk_start, code)
+					.map_err(|source| CodeScanError::Read {
+						range: [chunk_start, read_end],
+						source,
+					})?;
+
+				let mut next_start = chunk_end;
+				for instruction in self.disassemble(chunk_start, code)? {
+					if instruction.address >= chunk_end {
+						break;
+					}
+					next_start = next_start.max(instruction.end());
+
+					if predicate(&instruction) {
+						on_match(instruction);
+					}
+				}
+
+				chunk_start = next_start;
+			}
+		}
+
+		Ok(())
+	}
+
+	/// Finds all instructions which reference `address`.
+	///
+	/// ## Safety
+	/// * See [`MemoryAccess::read`].
+	pub unsafe fn find_references<A: MemoryAccess, M: MemoryMap>(
+		&self,
+		access: &mut A,
+		map: &M,
+		address: OffsetType,
+	) -> Result<Vec<Instruction>, CodeScanError> {
+		let mut found = Vec::new();
+		self.scan(
+			access,
+			map,
+			|instruction| instruction.references(address),
+			|instruction| found.push(instruction),
+		)?;
+
+		Ok(found)
+	}
+
+	/// Finds all direct calls to `address`.
+	///
+	/// ## Safety
+	/// * See [`MemoryAccess::read`].
+	pub unsafe fn find_calls<A: MemoryAccess, M: MemoryMap>(
+		&self,
+		access: &mut A,
+		map: &M,
+		address: OffsetType,
+	) -> Result<Vec<Instruction>, CodeScanError> {
+		let mut found = Vec::new();
+		self.scan(
+			access,
+			map,
+			|instruction| instruction.is_call_to(address),
+			|instruction| found.push(instruction),
+		)?;
+
+		Ok(found)
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use procmem_access::{
+		platform::mock::{MockMemoryAccess, MockMemoryMap},
+		prelude::{MemoryPage, MemoryPagePermissions, MemoryPageType, OffsetType},
+	};
+
+	use super::{CodeScanner, Operand};
+
+	const CODE_BASE: u64 = 0x1000;
+	const CODE: &[u8] = &[
+		// mov rax, qword ptr [rip + 0x10]
+		0x48, 0x8b, 0x05, 0x10, 0x00, 0x00, 0x00, // call 0x110c
+		0xe8, 0x00, 0x01, 0x00, 0x00, // invalid in 64-bit mode
+		0x06, // lea rcx, [rip - 0x14]
+		0x48, 0x8d, 0x0d, 0xec, 0xff, 0xff, 0xff, // ret
+		0xc3,
+	];
+
+	#[test]
+	fn test_disassemble() {
+		let scanner = CodeScanner::new_x86_64(4096).unwrap();
+		let instructions = scanner
+			.disassemble(OffsetType::new_unwrap(CODE_BASE), CODE)
+			.unwrap();
+
+		let mnemonics: Vec<_> = instructions.iter().map(|i| i.mnemonic.as_str()).collect();
+		assert_eq!(mnemonics, ["mov", "call", "lea", "ret"]);
+
+		assert_eq!(
+			instructions[0].operands[1],
+			Operand::Memory {
+				segment: None,
+				base: Some("rip".to_string()),
+				index: None,
+				scale: 1,
+				displacement: 0x10,
+				target: Some(0x1017)
+			}
+		);
+		assert_eq!(instructions[0].relocated_bytes, vec![3..7]);
+		assert!(instructions[1].is_call_to(OffsetType::new_unwrap(0x110c)));
+		assert_eq!(instructions[1].relocated_bytes, vec![1..5]);
+		assert!(!instructions[3].is_call && !instructions[3].is_jump);
+	}
+
+	#[test]
+	fn test_find_references() {
+		let mut access =
+			MockMemoryAccess::new().with_region(OffsetType::new_unwrap(CODE_BASE), CODE.to_vec());
+		let map = MockMemoryMap::from_pages(vec![MemoryPage {
+			address_range: [
+				OffsetType::new_unwrap(CODE_BASE),
+				OffsetType::new_unwrap(CODE_BASE + CODE.len() as u64),
+			],
+			permissions: MemoryPagePermissions::new(true, false, true, false),
+			offset: 0,
+			page_type: MemoryPageType::Anon,
+		}]);
+		// small chunks to exercise instructions crossing chunk boundaries
+		let scanner = CodeScanner::new_x86_64(1).unwrap();
+
+		let found = unsafe {
+			scanner
+				.find_references(&mut access, &map, OffsetType::new_unwrap(0x1000))
+				.unwrap()
+		};
+		assert_eq!(found.len(), 1);
+		assert_eq!(found[0].mnemonic, "lea");
+		assert_eq!(found[0].address, OffsetType::new_unwrap(0x100d));
+
+		let found = unsafe {
+			scanner
+				.find_calls(&mut access, &map, OffsetType::new_unwrap(0x110c))
+				.unwrap()
+		};
+		assert_eq!(found.len(), 1);
+		assert_eq!(found[0].address, OffsetType::new_unwrap(0x1007));
+	}
+}
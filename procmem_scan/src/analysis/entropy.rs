@@ -0,0 +1,226 @@
+//! Shannon entropy of memory blocks.
+//!
+//! Packed code, compressed and encrypted data and key material have entropy close to 8 bits per byte,
+//! while code, strings and most heap data stay well below that.
+
+use procmem_access::{
+	memory::access::ReadError,
+	prelude::{MemoryAccess, OffsetType},
+};
+
+/// Histogram of byte values.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ByteHistogram {
+	counts: [u64; 256],
+	total: u64,
+}
+impl ByteHistogram {
+	pub const fn new() -> Self {
+		ByteHistogram {
+			counts: [0; 256],
+			total: 0,
+		}
+	}
+
+	pub fn from_bytes(bytes: &[u8]) -> Self {
+		let mut histogram = Self::new();
+		histogram.add(bytes);
+
+		histogram
+	}
+
+	pub fn add(&mut self, bytes: &[u8]) {
+		for &byte in bytes {
+			self.counts[byte as usize] += 1;
+		}
+		self.total += bytes.len() as u64;
+	}
+
+	/// Adds counts of `other` to self.
+	pub fn merge(&mut self, other: &Self) {
+		for (count, other) in self.counts.iter_mut().zip(other.counts.iter()) {
+			*count += other;
+		}
+		self.total += other.total;
+	}
+
+	pub const fn count(&self, byte: u8) -> u64 {
+		self.counts[byte as usize]
+	}
+
+	pub const fn counts(&self) -> &[u64; 256] {
+		&self.counts
+	}
+
+	/// Total number of bytes counted.
+	pub const fn total(&self) -> u64 {
+		self.total
+	}
+
+	/// Computes the Shannon entropy in bits per byte, between `0.0` and `8.0`.
+	pub fn entropy(&self) -> f64 {
+		if self.total == 0 {
+			return 0.0;
+		}
+
+		let total = self.total as f64;
+		self.counts
+			.iter()
+			.filter(|&&count| count != 0)
+			.map(|&count| {
+				let p = count as f64 / total;
+				-p * p.log2()
+			})
+			.sum()
+	}
+}
+impl Default for ByteHistogram {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+/// Entropy of one block or of a region of merged blocks.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EntropyBlock {
+	pub range: [OffsetType; 2],
+	pub histogram: ByteHistogram,
+}
+impl EntropyBlock {
+	pub fn entropy(&self) -> f64 {
+		self.histogram.entropy()
+	}
+}
+
+/// Computes entropy of memory in blocks of fixed size.
+pub struct EntropyAnalyzer {
+	block_size: usize,
+	threshold: f64,
+}
+impl EntropyAnalyzer {
+	/// Creates a new analyzer.
+	///
+	/// Blocks with entropy greater or equal to `threshold` bits per byte are considered high entropy.
+	/// A page sized `block_size` with threshold around `7.0` works well for finding compressed or encrypted data.
+	pub fn new(block_size: usize, threshold: f64) -> Self {
+		assert!(block_size > 0);
+
+		EntropyAnalyzer {
+			block_size,
+			threshold,
+		}
+	}
+
+	/// Computes entropy of each block in `range`, calling `on_block` for each of them.
+	///
+	/// The last block may be shorter than the block size.
+	///
+	/// ## Safety
+	/// * See [`MemoryAccess::read`].
+	pub unsafe fn blocks<A: MemoryAccess>(
+		&self,
+		access: &mut A,
+		range: [OffsetType; 2],
+		mut on_block: impl FnMut(EntropyBlock),
+	) -> Result<(), ReadError> {
+		let mut buffer = vec![0u8; self.block_size];
+
+		for start in OffsetType::iter_stride(range[0], range[1], self.block_size as u64) {
+			let size = (range[1].get() - start.get()).min(self.block_size as u64) as usize;
+			let block = &mut buffer[..size];
+			access.read(start, block)?;
+
+			on_block(EntropyBlock {
+				range: [start, start.saturating_add(size as u64)],
+				histogram: ByteHistogram::from_bytes(block),
+			});
+		}
+
+		Ok(())
+	}
+
+	/// Returns regions of adjacent blocks in `ranges` whose entropy is above the threshold.
+	///
+	/// The histogram of each region is the sum of the histograms of its blocks.
+	///
+	/// ## Safety
+	/// * See [`MemoryAccess::read`].
+	pub unsafe fn high_entropy_regions<A: MemoryAccess>(
+		&self,
+		access: &mut A,
+		ranges: impl IntoIterator<Item = [OffsetType; 2]>,
+	) -> Result<Vec<EntropyBlock>, ReadError> {
+		let mut regions: Vec<EntropyBlock> = Vec::new();
+
+		for range in ranges {
+			self.blocks(access, range, |block| {
+				if block.entropy() < self.threshold {
+					return;
+				}
+
+				match regions.last_mut() {
+					Some(last) if last.range[1] == block.range[0] => {
+						last.range[1] = block.range[1];
+						last.histogram.merge(&block.histogram);
+					}
+					_ => regions.push(block),
+				}
+			})?;
+		}
+
+		Ok(regions)
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use procmem_access::{platform::mock::MockMemoryAccess, prelude::OffsetType};
+
+	use super::{ByteHistogram, EntropyAnalyzer};
+
+	#[test]
+	fn test_histogram_entropy() {
+		assert_eq!(ByteHistogram::new().entropy(), 0.0);
+		assert_eq!(ByteHistogram::from_bytes(&[7; 100]).entropy(), 0.0);
+		assert_eq!(ByteHistogram::from_bytes(&[0, 1, 0, 1]).entropy(), 1.0);
+
+		let all: Vec<u8> = (0..=255).collect();
+		let histogram = ByteHistogram::from_bytes(&all);
+		assert_eq!(histogram.entropy(), 8.0);
+		assert_eq!(histogram.count(42), 1);
+		assert_eq!(histogram.total(), 256);
+	}
+
+	#[test]
+	fn test_high_entropy_regions() {
+		// blocks starting at offset 1: low, high, high, low, high
+		let mut data = vec![0u8; 5 * 256];
+		for block in [1, 2, 4] {
+			for i in 0..256 {
+				data[block * 256 + i] = i as u8;
+			}
+		}
+		let mut access = MockMemoryAccess::new().with_region(OffsetType::new_unwrap(1), data);
+
+		let analyzer = EntropyAnalyzer::new(256, 7.0);
+		let regions = unsafe {
+			analyzer
+				.high_entropy_regions(
+					&mut access,
+					[[
+						OffsetType::new_unwrap(1),
+						OffsetType::new_unwrap(1 + 5 * 256),
+					]],
+				)
+				.unwrap()
+		};
+
+		let ranges: Vec<_> = regions
+			.iter()
+			.map(|r| [r.range[0].get(), r.range[1].get()])
+			.collect();
+		assert_eq!(ranges, [[257, 769], [1025, 1281]]);
+		assert_eq!(regions[0].histogram.total(), 512);
+		assert_eq!(regions[0].entropy(), 8.0);
+	}
+}
@@ -0,0 +1,8 @@
+//! Analysis passes over process memory which do not fit the predicate scanning model.
+
+#[cfg(feature = "capstone")]
+pub mod code;
+pub mod entropy;
+#[cfg(feature = "capstone")]
+pub mod signature;
+pub mod strings;
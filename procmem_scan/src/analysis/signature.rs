@@ -0,0 +1,340 @@
+//! Generation of unique byte signatures for code locations.
+//!
+//! A signature found once can be used to find the same code again after the module is updated or relocated,
+//! as long as the instructions themselves do not change. Operands which depend on where the code and its data
+//! are placed are replaced with wildcards.
+
+use thiserror::Error;
+
+use procmem_access::{
+	memory::access::ReadError,
+	prelude::{
+		ErrorKind, MemoryAccess, MemoryMap, MemoryPage, MemoryPageType, OffsetType, ProcmemError,
+	},
+};
+
+use super::code::{CodeScanError, CodeScanner, MAX_INSTRUCTION_LENGTH};
+use crate::pattern::BytePattern;
+
+#[derive(Debug, Error)]
+pub enum SignatureError {
+	#[error("address {0} is not in a readable executable page")]
+	NotExecutable(OffsetType),
+	#[error("no unique signature of at most {length} bytes exists")]
+	NotUnique { length: usize },
+	#[error(transparent)]
+	Code(#[from] CodeScanError),
+	#[error("could not read module code {}-{}", range[0], range[1])]
+	Read {
+		range: [OffsetType; 2],
+		#[source]
+		source: ReadError,
+	},
+}
+impl SignatureError {
+	pub fn kind(&self) -> ErrorKind {
+		match self {
+			SignatureError::NotExecutable(_) => ErrorKind::NotMapped,
+			SignatureError::NotUnique { .. } => ErrorKind::Platform,
+			SignatureError::Code(err) => err.kind(),
+			SignatureError::Read { source, .. } => source.kind(),
+		}
+	}
+}
+impl From<SignatureError> for ProcmemError {
+	fn from(err: SignatureError) -> Self {
+		ProcmemError::new(err.kind(), err)
+	}
+}
+
+/// Contiguous code of a module read from the process.
+struct ModuleCode {
+	start: OffsetType,
+	code: Vec<u8>,
+}
+impl ModuleCode {
+	/// Reads readable executable pages of `map` with type `page_type`, merging adjacent pages.
+	unsafe fn read<A: MemoryAccess, M: MemoryMap>(
+		access: &mut A,
+		map: &M,
+		page_type: &MemoryPageType,
+	) -> Result<Vec<Self>, SignatureError> {
+		let pages = map.pages().iter().filter(|page| {
+			page.permissions.read() && page.permissions.exec() && &page.page_type == page_type
+		});
+
+		let mut modules: Vec<ModuleCode> = Vec::new();
+		for page in pages {
+			let mut code = vec![0u8; (page.end().get() - page.start().get()) as usize];
+			access
+				.read(page.start(), &mut code)
+				.map_err(|source| SignatureError::Read {
+					range: page.address_range,
+					source,
+				})?;
+
+			match modules.last_mut() {
+				Some(last) if last.end() == page.start() => last.code.extend_from_slice(&code),
+				_ => modules.push(ModuleCode {
+					start: page.start(),
+					code,
+				}),
+			}
+		}
+
+		Ok(modules)
+	}
+
+	fn end(&self) -> OffsetType {
+		self.start.saturating_add(self.code.len() as u64)
+	}
+}
+
+/// Generates wildcard byte signatures which uniquely identify a location within its module.
+///
+/// A module is the set of readable executable pages with the same [`MemoryPageType`], so for anonymous
+/// code the signature is unique among all anonymous executable pages.
+pub struct SignatureGenerator {
+	scanner: CodeScanner,
+	max_length: usize,
+}
+impl SignatureGenerator {
+	pub const DEFAULT_MAX_LENGTH: usize = 64;
+
+	/// Creates a generator which gives up on signatures longer than `max_length` bytes.
+	pub fn new(scanner: CodeScanner, max_length: usize) -> Self {
+		SignatureGenerator {
+			scanner,
+			max_length: max_length.max(1),
+		}
+	}
+
+	pub fn scanner(&self) -> &CodeScanner {
+		&self.scanner
+	}
+
+	fn executable_page<M: MemoryMap>(
+		map: &M,
+		address: OffsetType,
+	) -> Result<&MemoryPage, SignatureError> {
+		map.containing_page(address)
+			.filter(|page| {
+				address < page.end() && page.permissions.read() && page.permissions.exec()
+			})
+			.ok_or(SignatureError::NotExecutable(address))
+	}
+
+	/// Generates the shortest signature starting at `address` which matches only at `address` within its module.
+	///
+	/// Relative branch targets and rip-relative or absolute displacements are wildcarded,
+	/// see [`Instruction::relocated_bytes`](super::code::Instruction::relocated_bytes).
+	///
+	/// ## Safety
+	/// * See [`MemoryAccess::read`].
+	pub unsafe fn generate<A: MemoryAccess, M: MemoryMap>(
+		&self,
+		access: &mut A,
+		map: &M,
+		address: OffsetType,
+	) -> Result<BytePattern, SignatureError> {
+		let page = Self::executable_page(map, address)?;
+		let modules = ModuleCode::read(access, map, &page.page_type)?;
+
+		let (module_index, module) = modules
+			.iter()
+			.enumerate()
+			.find(|(_, module)| address >= module.start && address < module.end())
+			.ok_or(SignatureError::NotExecutable(address))?;
+		let position = (address.get() - module.start.get()) as usize;
+
+		// decode a little past the maximum length so that the last instruction is not cut off
+		let window = &module.code[position
+			..module
+				.code
+				.len()
+				.min(position + self.max_length + MAX_INSTRUCTION_LENGTH)];
+		let mut mask = vec![true; window.len()];
+		for instruction in self.scanner.disassemble(address, window)? {
+			let start = (instruction.address.get() - address.get()) as usize;
+			for range in instruction.relocated_bytes.iter() {
+				mask[start + range.start..start + range.end]
+					.iter_mut()
+					.for_each(|exact| *exact = false);
+			}
+		}
+
+		// extend the signature one byte at a time and keep only the candidates which still match,
+		// candidates are only collected at the first exact byte to avoid tracking every position of the module
+		let mut candidates: Option<Vec<(usize, usize)>> = None;
+		let length = window.len().min(self.max_length);
+		for i in 0..length {
+			let byte = window[i];
+			let candidates = match (candidates.as_mut(), mask[i]) {
+				(None, false) => continue,
+				(None, true) => candidates.insert(
+					modules
+						.iter()
+						.enumerate()
+						.flat_map(|(index, module)| {
+							module
+								.code
+								.iter()
+								.skip(i)
+								.enumerate()
+								.filter(move |(_, &b)| b == byte)
+								.map(move |(start, _)| (index, start))
+						})
+						.collect(),
+				),
+				(Some(candidates), true) => {
+					candidates.retain(|&(index, start)| modules[index].code[start + i] == byte);
+					candidates
+				}
+				(Some(candidates), false) => candidates,
+			};
+
+			// only the remaining candidates which are long enough match
+			candidates.retain(|&(index, start)| start + i < modules[index].code.len());
+			if candidates.len() == 1 {
+				debug_assert_eq!(candidates[0], (module_index, position));
+
+				return Ok(BytePattern::from_masked(&window[..=i], &mask[..=i]));
+			}
+		}
+
+		Err(SignatureError::NotUnique { length })
+	}
+
+	/// Finds all locations matching `pattern` within the module with type `page_type`.
+	///
+	/// ## Safety
+	/// * See [`MemoryAccess::read`].
+	pub unsafe fn find<A: MemoryAccess, M: MemoryMap>(
+		&self,
+		access: &mut A,
+		map: &M,
+		page_type: &MemoryPageType,
+		pattern: &BytePattern,
+	) -> Result<Vec<OffsetType>, SignatureError> {
+		let modules = ModuleCode::read(access, map, page_type)?;
+
+		Ok(modules
+			.iter()
+			.flat_map(|module| {
+				pattern
+					.find_iter(&module.code)
+					.map(move |i| module.start.saturating_add(i as u64))
+			})
+			.collect())
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use std::path::PathBuf;
+
+	use procmem_access::{
+		platform::mock::{MockMemoryAccess, MockMemoryMap},
+		prelude::{MemoryMap, MemoryPage, MemoryPagePermissions, MemoryPageType, OffsetType},
+	};
+
+	use super::{SignatureError, SignatureGenerator};
+	use crate::analysis::code::CodeScanner;
+
+	fn module(base: u64, len: u64) -> MockMemoryMap {
+		MockMemoryMap::from_pages(vec![MemoryPage {
+			address_range: [
+				OffsetType::new_unwrap(base),
+				OffsetType::new_unwrap(base + len),
+			],
+			permissions: MemoryPagePermissions::new(true, false, true, false),
+			offset: 0,
+			page_type: MemoryPageType::File(PathBuf::from("/usr/lib/libtest.so")),
+		}])
+	}
+
+	fn code(mov_displacement: u8, call_displacement: u8) -> Vec<u8> {
+		vec![
+			// mov rax, qword ptr [rip + ?]
+			0x48,
+			0x8b,
+			0x05,
+			mov_displacement,
+			0x00,
+			0x00,
+			0x00,
+			// ret
+			0xc3,
+			// mov rax, qword ptr [rip + ?]
+			0x48,
+			0x8b,
+			0x05,
+			mov_displacement,
+			0x00,
+			0x00,
+			0x00,
+			// call ?
+			0xe8,
+			call_displacement,
+			0x00,
+			0x00,
+			0x00,
+			// ret
+			0xc3,
+		]
+	}
+
+	#[test]
+	fn test_generate_and_find_signature() {
+		let generator = SignatureGenerator::new(
+			CodeScanner::new_x86_64(4096).unwrap(),
+			SignatureGenerator::DEFAULT_MAX_LENGTH,
+		);
+
+		let data = code(0x10, 0x20);
+		let map = module(0x1000, data.len() as u64);
+		let mut access = MockMemoryAccess::new().with_region(OffsetType::new_unwrap(0x1000), data);
+
+		let signature = unsafe {
+			generator
+				.generate(&mut access, &map, OffsetType::new_unwrap(0x1008))
+				.unwrap()
+		};
+		assert_eq!(signature.to_string(), "48 8B 05 ?? ?? ?? ?? E8");
+
+		// the same code loaded elsewhere with different displacements
+		let data = code(0x30, 0x40);
+		let map = module(0x7000, data.len() as u64);
+		let mut access = MockMemoryAccess::new().with_region(OffsetType::new_unwrap(0x7000), data);
+
+		let found = unsafe {
+			generator
+				.find(&mut access, &map, &map.pages()[0].page_type, &signature)
+				.unwrap()
+		};
+		assert_eq!(found, [OffsetType::new_unwrap(0x7008)]);
+	}
+
+	#[test]
+	fn test_generate_signature_errors() {
+		let generator = SignatureGenerator::new(CodeScanner::new_x86_64(4096).unwrap(), 4);
+
+		let data = code(0x10, 0x20);
+		let map = module(0x1000, data.len() as u64);
+		let mut access = MockMemoryAccess::new().with_region(OffsetType::new_unwrap(0x1000), data);
+
+		let err = unsafe {
+			generator
+				.generate(&mut access, &map, OffsetType::new_unwrap(0x1000))
+				.unwrap_err()
+		};
+		assert!(matches!(err, SignatureError::NotUnique { length: 4 }));
+
+		let err = unsafe {
+			generator
+				.generate(&mut access, &map, OffsetType::new_unwrap(0x2000))
+				.unwrap_err()
+		};
+		assert!(matches!(err, SignatureError::NotExecutable(_)));
+	}
+}
@@ -0,0 +1,504 @@
+//! Extraction of printable strings from process memory.
+//!
+//! Like the `strings` utility, but aware of the memory map and of multiple encodings.
+
+use std::collections::VecDeque;
+
+use thiserror::Error;
+
+use procmem_access::{
+	memory::access::ReadError,
+	prelude::{
+		ErrorKind, MemoryAccess, MemoryMap, MemoryPage, MemoryPageType, OffsetType, ProcmemError,
+	},
+};
+
+#[derive(Debug, Error)]
+pub enum ExtractStringsError {
+	#[error("could not read page {}-{}", range[0], range[1])]
+	Read {
+		range: [OffsetType; 2],
+		#[source]
+		source: ReadError,
+	},
+}
+impl ExtractStringsError {
+	pub fn kind(&self) -> ErrorKind {
+		match self {
+			ExtractStringsError::Read { source, .. } => source.kind(),
+		}
+	}
+}
+impl From<ExtractStringsError> for ProcmemError {
+	fn from(err: ExtractStringsError) -> Self {
+		ProcmemError::new(err.kind(), err)
+	}
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum StringEncoding {
+	/// Printable ASCII characters only.
+	Ascii,
+	/// UTF-8 containing at least one non-ASCII character.
+	Utf8,
+	/// Little endian UTF-16 starting at an even offset.
+	///
+	/// Code units made of two printable ASCII bytes are treated as ASCII text rather than UTF-16.
+	Utf16Le,
+}
+impl std::fmt::Display for StringEncoding {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		match self {
+			StringEncoding::Ascii => write!(f, "ascii"),
+			StringEncoding::Utf8 => write!(f, "utf8"),
+			StringEncoding::Utf16Le => write!(f, "utf16le"),
+		}
+	}
+}
+
+/// String found in memory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FoundString {
+	pub offset: OffsetType,
+	/// Length of the string in memory, in bytes.
+	pub length: usize,
+	pub encoding: StringEncoding,
+	pub value: String,
+	/// Type of the page which contains the string.
+	pub page_type: MemoryPageType,
+}
+
+#[derive(Debug, Clone)]
+pub struct StringsOptions {
+	/// Minimum number of characters of a reported string.
+	pub min_length: usize,
+	/// Maximum number of characters of a reported string, longer strings are split.
+	pub max_length: usize,
+	pub ascii: bool,
+	pub utf8: bool,
+	pub utf16: bool,
+	/// Number of bytes read from the process at once.
+	pub chunk_size: usize,
+}
+impl Default for StringsOptions {
+	fn default() -> Self {
+		StringsOptions {
+			min_length: 4,
+			max_length: 4096,
+			ascii: true,
+			utf8: true,
+			utf16: true,
+			chunk_size: 64 * 1024,
+		}
+	}
+}
+
+fn is_printable(c: char) -> bool {
+	c == '\t' || !c.is_control()
+}
+
+/// String being built by an extractor.
+#[derive(Default)]
+struct Run {
+	start: u64,
+	length: usize,
+	value: String,
+	chars: usize,
+}
+impl Run {
+	fn push(&mut self, offset: u64, c: char, length: usize) {
+		if self.chars == 0 {
+			self.start = offset;
+		}
+		self.value.push(c);
+		self.length += length;
+		self.chars += 1;
+	}
+
+	fn take(&mut self) -> Run {
+		std::mem::take(self)
+	}
+}
+
+/// Extracts strings from a stream of bytes, reporting them into a queue.
+struct StringExtractor {
+	options: StringsOptions,
+	page_type: MemoryPageType,
+
+	bytes: Run,
+	pending_utf8: Vec<u8>,
+	pending_utf8_start: u64,
+
+	utf16: Run,
+	low_byte: Option<u8>,
+	high_surrogate: Option<u16>,
+}
+impl StringExtractor {
+	fn new(options: StringsOptions) -> Self {
+		StringExtractor {
+			options,
+			page_type: MemoryPageType::Unknown,
+			bytes: Run::default(),
+			pending_utf8: Vec::new(),
+			pending_utf8_start: 0,
+			utf16: Run::default(),
+			low_byte: None,
+			high_surrogate: None,
+		}
+	}
+
+	fn emit(&self, run: Run, encoding: StringEncoding, found: &mut VecDeque<FoundString>) {
+		if run.chars < self.options.min_length.max(1) {
+			return;
+		}
+
+		let encoding = match encoding {
+			StringEncoding::Ascii | StringEncoding::Utf8 if run.value.is_ascii() => {
+				if !self.options.ascii {
+					return;
+				}
+				StringEncoding::Ascii
+			}
+			encoding => encoding,
+		};
+
+		found.push_back(FoundString {
+			offset: OffsetType::new_unwrap(run.start),
+			length: run.length,
+			encoding,
+			value: run.value,
+			page_type: self.page_type.clone(),
+		});
+	}
+
+	fn push_byte_char(
+		&mut self,
+		offset: u64,
+		c: char,
+		length: usize,
+		found: &mut VecDeque<FoundString>,
+	) {
+		self.bytes.push(offset, c, length);
+		if self.bytes.chars >= self.options.max_length {
+			let run = self.bytes.take();
+			self.emit(run, StringEncoding::Utf8, found);
+		}
+	}
+
+	fn end_bytes(&mut self, found: &mut VecDeque<FoundString>) {
+		self.pending_utf8.clear();
+
+		let run = self.bytes.take();
+		self.emit(run, StringEncoding::Utf8, found);
+	}
+
+	fn on_byte_bytes(&mut self, offset: u64, byte: u8, found: &mut VecDeque<FoundString>) {
+		if !self.pending_utf8.is_empty() {
+			if byte & 0xC0 == 0x80 {
+				self.pending_utf8.push(byte);
+
+				let expected = match self.pending_utf8[0] {
+					0xC2..=0xDF => 2,
+					0xE0..=0xEF => 3,
+					_ => 4,
+				};
+				if self.pending_utf8.len() < expected {
+					return;
+				}
+
+				let decoded = std::str::from_utf8(&self.pending_utf8)
+					.ok()
+					.and_then(|s| s.chars().next())
+					.filter(|&c| is_printable(c));
+				match decoded {
+					Some(c) => {
+						self.pending_utf8.clear();
+						self.push_byte_char(self.pending_utf8_start, c, expected, found);
+					}
+					None => self.end_bytes(found),
+				}
+
+				return;
+			}
+
+			// invalid sequence, end the run before it and process the byte normally
+			self.end_bytes(found);
+		}
+
+		match byte {
+			0x20..=0x7E | b'\t' => self.push_byte_char(offset, byte as char, 1, found),
+			0xC2..=0xF4 if self.options.utf8 => {
+				self.pending_utf8.push(byte);
+				self.pending_utf8_start = offset;
+			}
+			_ => self.end_bytes(found),
+		}
+	}
+
+	fn end_utf16(&mut self, found: &mut VecDeque<FoundString>) {
+		self.high_surrogate = None;
+
+		let run = self.utf16.take();
+		self.emit(run, StringEncoding::Utf16Le, found);
+	}
+
+	fn on_unit_utf16(&mut self, offset: u64, unit: u16, found: &mut VecDeque<FoundString>) {
+		let (c, start, length) = match (self.high_surrogate.take(), unit) {
+			(Some(high), 0xDC00..=0xDFFF) => {
+				let c = char::decode_utf16([high, unit]).next().unwrap().ok();
+				(c, offset - 2, 4)
+			}
+			(Some(_), _) => {
+				self.end_utf16(found);
+				return self.on_unit_utf16(offset, unit, found);
+			}
+			(None, 0xD800..=0xDBFF) => {
+				self.high_surrogate = Some(unit);
+				return;
+			}
+			// most likely ASCII text, which would otherwise decode as CJK characters
+			(None, unit) if unit.to_le_bytes().iter().all(|b| (0x20..=0x7E).contains(b)) => {
+				(None, offset, 2)
+			}
+			(None, unit) => (char::from_u32(unit as u32), offset, 2),
+		};
+
+		match c.filter(|&c| is_printable(c)) {
+			None => self.end_utf16(found),
+			Some(c) => {
+				self.utf16.push(start, c, length);
+				if self.utf16.chars >= self.options.max_length {
+					self.end_utf16(found);
+				}
+			}
+		}
+	}
+
+	fn on_byte(&mut self, offset: u64, byte: u8, found: &mut VecDeque<FoundString>) {
+		if self.options.ascii || self.options.utf8 {
+			self.on_byte_bytes(offset, byte, found);
+		}
+
+		if self.options.utf16 {
+			match self.low_byte.take() {
+				None if offset.is_multiple_of(2) => self.low_byte = Some(byte),
+				None => (),
+				Some(low) => self.on_unit_utf16(offset - 1, u16::from_le_bytes([low, byte]), found),
+			}
+		}
+	}
+
+	/// Ends all strings in progress.
+	fn flush(&mut self, found: &mut VecDeque<FoundString>) {
+		self.end_bytes(found);
+		self.low_byte = None;
+		self.end_utf16(found);
+	}
+}
+
+/// Extracts strings from pages of `map` accepted by `filter`.
+///
+/// Pages are read in chunks and strings are yielded as they are found. Strings do not span multiple pages.
+/// When a page cannot be read an error is yielded and extraction continues with the next page.
+///
+/// ## Safety
+/// * See [`MemoryAccess::read`].
+pub unsafe fn extract_strings<'a, A: MemoryAccess, M: MemoryMap, F: FnMut(&MemoryPage) -> bool>(
+	access: &'a mut A,
+	map: &'a M,
+	filter: F,
+	options: StringsOptions,
+) -> StringsIter<'a, A, F> {
+	StringsIter {
+		access,
+		pages: map.pages().iter(),
+		filter,
+		buffer: vec![0; options.chunk_size.max(1)],
+		current: None,
+		extractor: StringExtractor::new(options),
+		found: VecDeque::new(),
+		error: None,
+	}
+}
+
+/// Iterator over strings found in memory.
+///
+/// This is constructed by [`extract_strings`].
+pub struct StringsIter<'a, A: MemoryAccess, F: FnMut(&MemoryPage) -> bool> {
+	access: &'a mut A,
+	pages: std::slice::Iter<'a, MemoryPage>,
+	filter: F,
+	buffer: Vec<u8>,
+	/// Current page and the offset of the next chunk.
+	current: Option<(&'a MemoryPage, OffsetType)>,
+	extractor: StringExtractor,
+	found: VecDeque<FoundString>,
+	/// Error to be reported after the strings found before it.
+	error: Option<ExtractStringsError>,
+}
+impl<'a, A: MemoryAccess, F: FnMut(&MemoryPage) -> bool> Iterator for StringsIter<'a, A, F> {
+	type Item = Result<FoundString, ExtractStringsError>;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		loop {
+			if let Some(found) = self.found.pop_front() {
+				return Some(Ok(found));
+			}
+			if let Some(err) = self.error.take() {
+				return Some(Err(err));
+			}
+
+			let (page, chunk_start) = match self.current {
+				Some(current) => current,
+				None => {
+					let page = self.pages.by_ref().find(|page| (self.filter)(page))?;
+					self.extractor.page_type = page.page_type.clone();
+					self.current = Some((page, page.start()));
+
+					continue;
+				}
+			};
+
+			if chunk_start >= page.end() {
+				self.extractor.flush(&mut self.found);
+				self.current = None;
+
+				continue;
+			}
+
+			let size =
+				(page.end().get() - chunk_start.get()).min(self.buffer.len() as u64) as usize;
+			let chunk = &mut self.buffer[..size];
+
+			// Safe because the caller of `extract_strings` guarantees the safety of reads
+			if let Err(source) = unsafe { self.access.read(chunk_start, chunk) } {
+				self.extractor.flush(&mut self.found);
+				self.current = None;
+				self.error = Some(ExtractStringsError::Read {
+					range: [chunk_start, page.end()],
+					source,
+				});
+
+				continue;
+			}
+
+			for (i, byte) in chunk.iter().copied().enumerate() {
+				self.extractor
+					.on_byte(chunk_start.get() + i as u64, byte, &mut self.found);
+			}
+			self.current = Some((page, chunk_start.saturating_add(size as u64)));
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use procmem_access::{
+		platform::mock::{MockMemoryAccess, MockMemoryMap},
+		prelude::{MemoryPage, MemoryPagePermissions, MemoryPageType, OffsetType},
+	};
+
+	use super::{extract_strings, StringEncoding, StringsOptions};
+
+	fn page(start: u64, end: u64, page_type: MemoryPageType) -> MemoryPage {
+		MemoryPage {
+			address_range: [OffsetType::new_unwrap(start), OffsetType::new_unwrap(end)],
+			permissions: MemoryPagePermissions::new(true, true, false, false),
+			offset: 0,
+			page_type,
+		}
+	}
+
+	#[test]
+	fn test_extract_strings() {
+		let mut data = [0u8; 128];
+		data[2..9].copy_from_slice(b"Hello\x01x");
+		data[10..17].copy_from_slice(&"žluťák".as_bytes()[..7]);
+		let utf16: Vec<u8> = "Wide"
+			.encode_utf16()
+			.flat_map(|u| u.to_le_bytes())
+			.collect();
+		data[20..28].copy_from_slice(&utf16);
+		// spans the chunk boundary at 40
+		data[36..44].copy_from_slice(b"chunked!");
+		// separate page
+		data[64..68].copy_from_slice(b"heap");
+
+		let mut access =
+			MockMemoryAccess::new().with_region(OffsetType::new_unwrap(1), data[1..].to_vec());
+		let map = MockMemoryMap::from_pages(vec![
+			page(1, 64, MemoryPageType::Anon),
+			page(64, 100, MemoryPageType::Heap),
+			page(200, 300, MemoryPageType::Stack),
+		]);
+
+		let options = StringsOptions {
+			chunk_size: 39,
+			..Default::default()
+		};
+		let found: Vec<_> = unsafe {
+			extract_strings(
+				&mut access,
+				&map,
+				|page| page.page_type != MemoryPageType::Stack,
+				options,
+			)
+		}
+		.map(|s| s.unwrap())
+		.map(|s| (s.offset.get(), s.encoding, s.value, s.page_type))
+		.collect();
+
+		assert_eq!(
+			found,
+			[
+				(
+					2,
+					StringEncoding::Ascii,
+					"Hello".to_string(),
+					MemoryPageType::Anon
+				),
+				(
+					10,
+					StringEncoding::Utf8,
+					"žluť".to_string(),
+					MemoryPageType::Anon
+				),
+				(
+					20,
+					StringEncoding::Utf16Le,
+					"Wide".to_string(),
+					MemoryPageType::Anon
+				),
+				(
+					36,
+					StringEncoding::Ascii,
+					"chunked!".to_string(),
+					MemoryPageType::Anon
+				),
+				(
+					64,
+					StringEncoding::Ascii,
+					"heap".to_string(),
+					MemoryPageType::Heap
+				),
+			]
+		);
+	}
+
+	#[test]
+	fn test_extract_strings_unreadable() {
+		let mut access =
+			MockMemoryAccess::new().with_region(OffsetType::new_unwrap(1), b"text".to_vec());
+		let map = MockMemoryMap::from_pages(vec![
+			page(1, 5, MemoryPageType::Anon),
+			page(10, 20, MemoryPageType::Anon),
+		]);
+
+		let found: Vec<_> =
+			unsafe { extract_strings(&mut access, &map, |_| true, StringsOptions::default()) }
+				.collect();
+		assert_eq!(found.len(), 2);
+		assert_eq!(found[0].as_ref().unwrap().value, "text");
+		assert!(found[1].is_err());
+	}
+}
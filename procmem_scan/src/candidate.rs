@@ -5,8 +5,12 @@ use std::{
 
 use procmem_access::{prelude::OffsetType, util::AccFilter};
 
+/// Number of leading bytes recorded by a candidate, see [`ScannerCandidate::bytes`].
+pub const CANDIDATE_RECORDED_BYTES: usize = 8;
+
 /// Candidate match for stream scanner.
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ScannerCandidate {
 	/// Offset where the value match starts.
 	offset: OffsetType,
@@ -18,6 +22,8 @@ pub struct ScannerCandidate {
 	///
 	/// This value is always greated than `start_offset`.
 	start_offset: Option<OffsetType>,
+	/// Leading bytes consumed by the candidate, recorded by the scanner.
+	recorded: [u8; CANDIDATE_RECORDED_BYTES],
 }
 impl ScannerCandidate {
 	pub fn normal(offset: OffsetType) -> Self {
@@ -26,6 +32,7 @@ impl ScannerCandidate {
 			length: NonZeroUsize::new(1).unwrap(),
 			resolved: false,
 			start_offset: None,
+			recorded: [0; CANDIDATE_RECORDED_BYTES],
 		}
 	}
 
@@ -40,6 +47,7 @@ impl ScannerCandidate {
 			start_offset: Some(OffsetType::new_unwrap(
 				offset.get() + length.get() as u64 - 1,
 			)),
+			recorded: [0; CANDIDATE_RECORDED_BYTES],
 		}
 	}
 
@@ -50,6 +58,7 @@ impl ScannerCandidate {
 			length,
 			resolved: true,
 			start_offset: None,
+			recorded: [0; CANDIDATE_RECORDED_BYTES],
 		}
 	}
 
@@ -94,6 +103,22 @@ impl ScannerCandidate {
 		self.offset().saturating_add(self.length().get() as u64)
 	}
 
+	/// Returns the bytes consumed by this candidate so far, at most [`CANDIDATE_RECORDED_BYTES`] of them.
+	///
+	/// This lets predicates decide on values which span multiple bytes, such as numeric ranges.
+	/// The bytes are recorded by [`StreamScanner`](crate::stream::StreamScanner) for normal candidates only,
+	/// for partial candidates the leading bytes were never seen and the contents are unspecified.
+	pub fn bytes(&self) -> &[u8] {
+		&self.recorded[..self.length.get().min(CANDIDATE_RECORDED_BYTES)]
+	}
+
+	/// Records the byte at `index` of the candidate, if it is one of the recorded leading bytes.
+	pub fn record_byte(&mut self, index: usize, byte: u8) {
+		if let Some(slot) = self.recorded.get_mut(index) {
+			*slot = byte;
+		}
+	}
+
 	/// Advances the candidate (increases the length).
 	pub fn advance(&mut self) {
 		debug_assert!(!self.resolved);
@@ -150,7 +175,7 @@ impl ScannerCandidate {
 }
 impl PartialOrd for ScannerCandidate {
 	fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-		Some(self.cmp(&other))
+		Some(self.cmp(other))
 	}
 }
 impl Ord for ScannerCandidate {
@@ -168,7 +193,7 @@ mod test {
 
 	use procmem_access::prelude::OffsetType;
 
-	use super::ScannerCandidate;
+	use super::{ScannerCandidate, CANDIDATE_RECORDED_BYTES};
 
 	#[test]
 	fn test_scanner_candidate_construction() {
@@ -179,7 +204,8 @@ mod test {
 				offset: OffsetType::new_unwrap(10),
 				length: NonZeroUsize::new(1).unwrap(),
 				resolved: false,
-				start_offset: None
+				start_offset: None,
+				recorded: [0; CANDIDATE_RECORDED_BYTES]
 			}
 		);
 
@@ -191,7 +217,8 @@ mod test {
 				offset: OffsetType::new_unwrap(20),
 				length: NonZeroUsize::new(12).unwrap(),
 				resolved: true,
-				start_offset: None
+				start_offset: None,
+				recorded: [0; CANDIDATE_RECORDED_BYTES]
 			}
 		);
 
@@ -203,7 +230,8 @@ mod test {
 				offset: OffsetType::new_unwrap(11),
 				length: NonZeroUsize::new(5).unwrap(),
 				resolved: false,
-				start_offset: Some(OffsetType::new_unwrap(15))
+				start_offset: Some(OffsetType::new_unwrap(15)),
+				recorded: [0; CANDIDATE_RECORDED_BYTES],
 			}
 		);
 
@@ -217,7 +245,8 @@ mod test {
 				offset: OffsetType::new_unwrap(10),
 				length: NonZeroUsize::new(2).unwrap(),
 				resolved: true,
-				start_offset: Some(OffsetType::new_unwrap(11))
+				start_offset: Some(OffsetType::new_unwrap(11)),
+				recorded: [0; CANDIDATE_RECORDED_BYTES],
 			}
 		);
 	}
@@ -230,24 +259,28 @@ mod test {
 				length: NonZeroUsize::new(2).unwrap(),
 				resolved: false,
 				start_offset: None,
+				recorded: [0; CANDIDATE_RECORDED_BYTES],
 			},
 			ScannerCandidate {
 				offset: OffsetType::new_unwrap(2),
 				length: NonZeroUsize::new(1).unwrap(),
 				resolved: false,
 				start_offset: None,
+				recorded: [0; CANDIDATE_RECORDED_BYTES],
 			},
 			ScannerCandidate {
 				offset: OffsetType::new_unwrap(1),
 				length: NonZeroUsize::new(3).unwrap(),
 				resolved: false,
 				start_offset: Some(OffsetType::new_unwrap(1)),
+				recorded: [0; CANDIDATE_RECORDED_BYTES],
 			},
 			ScannerCandidate {
 				offset: OffsetType::new_unwrap(1),
 				length: NonZeroUsize::new(2).unwrap(),
 				resolved: false,
 				start_offset: None,
+				recorded: [0; CANDIDATE_RECORDED_BYTES],
 			},
 		];
 
@@ -260,25 +293,29 @@ mod test {
 					offset: OffsetType::new_unwrap(1),
 					length: NonZeroUsize::new(2).unwrap(),
 					resolved: false,
-					start_offset: None
+					start_offset: None,
+					recorded: [0; CANDIDATE_RECORDED_BYTES],
 				},
 				ScannerCandidate {
 					offset: OffsetType::new_unwrap(1),
 					length: NonZeroUsize::new(3).unwrap(),
 					resolved: false,
-					start_offset: Some(OffsetType::new_unwrap(1))
+					start_offset: Some(OffsetType::new_unwrap(1)),
+					recorded: [0; CANDIDATE_RECORDED_BYTES],
 				},
 				ScannerCandidate {
 					offset: OffsetType::new_unwrap(2),
 					length: NonZeroUsize::new(1).unwrap(),
 					resolved: false,
-					start_offset: None
+					start_offset: None,
+					recorded: [0; CANDIDATE_RECORDED_BYTES],
 				},
 				ScannerCandidate {
 					offset: OffsetType::new_unwrap(2),
 					length: NonZeroUsize::new(2).unwrap(),
 					resolved: false,
-					start_offset: None
+					start_offset: None,
+					recorded: [0; CANDIDATE_RECORDED_BYTES],
 				}
 			]
 		);
@@ -292,24 +329,28 @@ mod test {
 				length: NonZeroUsize::new(2).unwrap(),
 				resolved: false,
 				start_offset: None,
+				recorded: [0; CANDIDATE_RECORDED_BYTES],
 			},
 			ScannerCandidate {
 				offset: OffsetType::new_unwrap(1),
 				length: NonZeroUsize::new(3).unwrap(),
 				resolved: false,
 				start_offset: Some(OffsetType::new_unwrap(1)),
+				recorded: [0; CANDIDATE_RECORDED_BYTES],
 			},
 			ScannerCandidate {
 				offset: OffsetType::new_unwrap(2),
 				length: NonZeroUsize::new(1).unwrap(),
 				resolved: false,
 				start_offset: None,
+				recorded: [0; CANDIDATE_RECORDED_BYTES],
 			},
 			ScannerCandidate {
 				offset: OffsetType::new_unwrap(2),
 				length: NonZeroUsize::new(2).unwrap(),
 				resolved: true,
 				start_offset: None,
+				recorded: [0; CANDIDATE_RECORDED_BYTES],
 			},
 		];
 
@@ -322,13 +363,15 @@ mod test {
 					offset: OffsetType::new_unwrap(1),
 					length: NonZeroUsize::new(3).unwrap(),
 					resolved: false,
-					start_offset: None
+					start_offset: None,
+					recorded: [0; CANDIDATE_RECORDED_BYTES],
 				},
 				ScannerCandidate {
 					offset: OffsetType::new_unwrap(2),
 					length: NonZeroUsize::new(2).unwrap(),
 					resolved: true,
-					start_offset: None
+					start_offset: None,
+					recorded: [0; CANDIDATE_RECORDED_BYTES],
 				}
 			]
 		);
@@ -345,12 +388,14 @@ mod test {
 			length: NonZeroUsize::new(3).unwrap(),
 			resolved: false,
 			start_offset: None,
+			recorded: [0; CANDIDATE_RECORDED_BYTES],
 		};
 		let right = ScannerCandidate {
 			offset: OffsetType::new_unwrap(8),
 			length: NonZeroUsize::new(4).unwrap(),
 			resolved: false,
 			start_offset: Some(OffsetType::new_unwrap(10)),
+			recorded: [0; CANDIDATE_RECORDED_BYTES],
 		};
 
 		left.try_merge_mut(right).unwrap();
@@ -361,7 +406,8 @@ mod test {
 				offset: OffsetType::new_unwrap(8),
 				length: NonZeroUsize::new(4).unwrap(),
 				resolved: false,
-				start_offset: None
+				start_offset: None,
+				recorded: [0; CANDIDATE_RECORDED_BYTES]
 			}
 		);
 	}
@@ -377,12 +423,14 @@ mod test {
 			length: NonZeroUsize::new(3).unwrap(),
 			resolved: false,
 			start_offset: Some(OffsetType::new_unwrap(9)),
+			recorded: [0; CANDIDATE_RECORDED_BYTES],
 		};
 		let right = ScannerCandidate {
 			offset: OffsetType::new_unwrap(8),
 			length: NonZeroUsize::new(4).unwrap(),
 			resolved: true,
 			start_offset: Some(OffsetType::new_unwrap(11)),
+			recorded: [0; CANDIDATE_RECORDED_BYTES],
 		};
 
 		left.try_merge_mut(right).unwrap();
@@ -393,7 +441,8 @@ mod test {
 				offset: OffsetType::new_unwrap(8),
 				length: NonZeroUsize::new(4).unwrap(),
 				resolved: true,
-				start_offset: Some(OffsetType::new_unwrap(9))
+				start_offset: Some(OffsetType::new_unwrap(9)),
+				recorded: [0; CANDIDATE_RECORDED_BYTES],
 			}
 		);
 	}
@@ -409,12 +458,14 @@ mod test {
 			length: NonZeroUsize::new(2).unwrap(),
 			resolved: false,
 			start_offset: Some(OffsetType::new_unwrap(9)),
+			recorded: [0; CANDIDATE_RECORDED_BYTES],
 		};
 		let right = ScannerCandidate {
 			offset: OffsetType::new_unwrap(8),
 			length: NonZeroUsize::new(4).unwrap(),
 			resolved: true,
 			start_offset: Some(OffsetType::new_unwrap(10)),
+			recorded: [0; CANDIDATE_RECORDED_BYTES],
 		};
 
 		left.try_merge_mut(right).unwrap();
@@ -425,7 +476,8 @@ mod test {
 				offset: OffsetType::new_unwrap(8),
 				length: NonZeroUsize::new(4).unwrap(),
 				resolved: true,
-				start_offset: Some(OffsetType::new_unwrap(9))
+				start_offset: Some(OffsetType::new_unwrap(9)),
+				recorded: [0; CANDIDATE_RECORDED_BYTES],
 			}
 		);
 	}
@@ -437,12 +489,14 @@ mod test {
 			length: NonZeroUsize::new(2).unwrap(),
 			resolved: false,
 			start_offset: Some(OffsetType::new_unwrap(10)),
+			recorded: [0; CANDIDATE_RECORDED_BYTES],
 		};
 		let right = ScannerCandidate {
 			offset: OffsetType::new_unwrap(8),
 			length: NonZeroUsize::new(4).unwrap(),
 			resolved: true,
 			start_offset: Some(OffsetType::new_unwrap(12)),
+			recorded: [0; CANDIDATE_RECORDED_BYTES],
 		};
 		left.try_merge_mut(right).unwrap_err();
 		assert_eq!(left.length.get(), 2);
@@ -452,14 +506,28 @@ mod test {
 			length: NonZeroUsize::new(2).unwrap(),
 			resolved: false,
 			start_offset: None,
+			recorded: [0; CANDIDATE_RECORDED_BYTES],
 		};
 		let right = ScannerCandidate {
 			offset: OffsetType::new_unwrap(8),
 			length: NonZeroUsize::new(4).unwrap(),
 			resolved: true,
 			start_offset: Some(OffsetType::new_unwrap(12)),
+			recorded: [0; CANDIDATE_RECORDED_BYTES],
 		};
 		left.try_merge_mut(right).unwrap_err();
 		assert_eq!(left.length.get(), 2);
 	}
+	#[cfg(feature = "serde")]
+	#[test]
+	fn test_scanner_candidate_serde() {
+		let candidates = [
+			ScannerCandidate::normal(OffsetType::new_unwrap(10)),
+			ScannerCandidate::resolved(OffsetType::new_unwrap(20), NonZeroUsize::new(12).unwrap()),
+		];
+
+		let json = serde_json::to_string(&candidates).unwrap();
+		let loaded: Vec<ScannerCandidate> = serde_json::from_str(&json).unwrap();
+		assert_eq!(loaded, candidates);
+	}
 }
@@ -0,0 +1,246 @@
+//! Saved progress of long scans.
+//!
+//! A [`ScanCheckpoint`] is filled in by [`ScanDriver::scan_checkpointed`](crate::driver::ScanDriver::scan_checkpointed)
+//! and records where the scan should continue and the matches found so far. With the `serde` feature it can be saved
+//! and loaded again to resume the scan after the tool restarts.
+//!
+//! The checkpoint remembers a fingerprint of the scanned ranges. If the memory map of the target changed in a way
+//! which affects the scanned ranges, the checkpoint is rejected instead of producing inconsistent results.
+
+use std::num::NonZeroUsize;
+
+#[cfg(feature = "serde")]
+use std::{io, path::Path};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "serde")]
+use thiserror::Error;
+
+use procmem_access::prelude::OffsetType;
+#[cfg(feature = "serde")]
+use procmem_access::prelude::{ErrorKind, ProcmemError};
+
+use crate::stream::ScanResult;
+
+/// Version of the document written by this library.
+#[cfg(feature = "serde")]
+pub const SCAN_CHECKPOINT_VERSION: u32 = 1;
+
+#[cfg(feature = "serde")]
+#[derive(Debug, Error)]
+pub enum ScanCheckpointError {
+	#[error("could not access checkpoint file")]
+	Io(#[from] io::Error),
+	#[error("invalid checkpoint document")]
+	Json(#[from] serde_json::Error),
+	#[error("unsupported checkpoint version {0}")]
+	UnsupportedVersion(u32),
+}
+#[cfg(feature = "serde")]
+impl ScanCheckpointError {
+	pub fn kind(&self) -> ErrorKind {
+		match self {
+			ScanCheckpointError::Io(err) => ErrorKind::from_io(err),
+			ScanCheckpointError::Json(_) => ErrorKind::Parse,
+			ScanCheckpointError::UnsupportedVersion(_) => ErrorKind::Parse,
+		}
+	}
+}
+#[cfg(feature = "serde")]
+impl From<ScanCheckpointError> for ProcmemError {
+	fn from(err: ScanCheckpointError) -> Self {
+		ProcmemError::new(err.kind(), err)
+	}
+}
+
+/// Returns a fingerprint of sorted memory ranges.
+///
+/// This is a FNV-1a hash of the range boundaries, so it is stable across runs and builds.
+pub fn ranges_fingerprint<'a>(ranges: impl IntoIterator<Item = &'a [OffsetType; 2]>) -> u64 {
+	const FNV_OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+	const FNV_PRIME: u64 = 0x100000001b3;
+
+	let mut hash = FNV_OFFSET_BASIS;
+	for range in ranges {
+		for byte in range.iter().flat_map(|offset| offset.get().to_le_bytes()) {
+			hash ^= byte as u64;
+			hash = hash.wrapping_mul(FNV_PRIME);
+		}
+	}
+
+	hash
+}
+
+/// Progress of a scan over a fixed set of ranges.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ScanCheckpoint {
+	#[cfg(feature = "serde")]
+	version: u32,
+	fingerprint: u64,
+	/// Offset to continue from, `None` once the scan finished.
+	resume_offset: Option<u64>,
+	results: Vec<(u64, NonZeroUsize)>,
+}
+impl ScanCheckpoint {
+	/// Creates a checkpoint at the start of a scan of `ranges`.
+	pub fn new<'a>(ranges: impl IntoIterator<Item = &'a [OffsetType; 2]>) -> Self {
+		let ranges: Vec<&[OffsetType; 2]> = ranges.into_iter().collect();
+
+		ScanCheckpoint {
+			#[cfg(feature = "serde")]
+			version: SCAN_CHECKPOINT_VERSION,
+			fingerprint: ranges_fingerprint(ranges.iter().copied()),
+			resume_offset: ranges.first().map(|range| range[0].get()),
+			results: Vec::new(),
+		}
+	}
+
+	pub const fn fingerprint(&self) -> u64 {
+		self.fingerprint
+	}
+
+	/// Returns whether this checkpoint was created for `ranges`.
+	pub fn matches<'a>(&self, ranges: impl IntoIterator<Item = &'a [OffsetType; 2]>) -> bool {
+		self.fingerprint == ranges_fingerprint(ranges)
+	}
+
+	/// Returns the offset the scan continues from, or `None` if it finished.
+	pub fn resume_offset(&self) -> Option<OffsetType> {
+		self.resume_offset.and_then(OffsetType::new)
+	}
+
+	pub fn set_resume_offset(&mut self, offset: Option<OffsetType>) {
+		self.resume_offset = offset.map(|offset| offset.get());
+	}
+
+	pub const fn is_finished(&self) -> bool {
+		self.resume_offset.is_none()
+	}
+
+	/// Returns the matches found so far, in the order they were found.
+	pub fn results(&self) -> impl Iterator<Item = ScanResult> + '_ {
+		self.results
+			.iter()
+			.map(|(offset, length)| (OffsetType::new_unwrap(*offset), *length))
+	}
+
+	pub fn record(&mut self, results: impl IntoIterator<Item = ScanResult>) {
+		self.results.extend(
+			results
+				.into_iter()
+				.map(|(offset, length)| (offset.get(), length)),
+		);
+	}
+}
+#[cfg(feature = "serde")]
+impl ScanCheckpoint {
+	/// Reads a checkpoint, rejecting documents written by a newer version of this library.
+	pub fn from_reader(reader: impl io::Read) -> Result<Self, ScanCheckpointError> {
+		let checkpoint: ScanCheckpoint = serde_json::from_reader(reader)?;
+		if checkpoint.version > SCAN_CHECKPOINT_VERSION {
+			return Err(ScanCheckpointError::UnsupportedVersion(checkpoint.version));
+		}
+
+		Ok(checkpoint)
+	}
+
+	pub fn to_writer(&self, writer: impl io::Write) -> Result<(), ScanCheckpointError> {
+		serde_json::to_writer(writer, self)?;
+
+		Ok(())
+	}
+
+	pub fn load(path: impl AsRef<Path>) -> Result<Self, ScanCheckpointError> {
+		Self::from_reader(io::BufReader::new(std::fs::File::open(path)?))
+	}
+
+	pub fn save(&self, path: impl AsRef<Path>) -> Result<(), ScanCheckpointError> {
+		let mut writer = io::BufWriter::new(std::fs::File::create(path)?);
+		self.to_writer(&mut writer)?;
+		io::Write::flush(&mut writer)?;
+
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use procmem_access::{
+		platform::mock::MockMemoryAccess,
+		prelude::{ErrorKind, OffsetType},
+	};
+
+	use super::ScanCheckpoint;
+	use crate::{
+		driver::{ScanConfig, ScanDriver, ScanDriverError},
+		predicate::value::ValuePredicate,
+	};
+
+	fn range(start: u64, end: u64) -> [OffsetType; 2] {
+		[OffsetType::new_unwrap(start), OffsetType::new_unwrap(end)]
+	}
+
+	#[test]
+	fn test_scan_checkpoint_resume() {
+		let mut data = vec![0u8; 64];
+		for start in [2, 14, 40] {
+			data[start..start + 4].copy_from_slice(&[1, 2, 3, 4]);
+		}
+		let mut access = MockMemoryAccess::new().with_region(OffsetType::new_unwrap(1), data);
+		let ranges = [range(1, 33), range(33, 65)];
+
+		let driver = ScanDriver::new(ScanConfig {
+			chunk_size: 8,
+			..Default::default()
+		});
+		let predicate = ValuePredicate::new([1u8, 2, 3, 4], false);
+
+		// pause after the chunk which ends in the middle of the second match
+		let mut checkpoint = ScanCheckpoint::new(&ranges);
+		let finished = unsafe {
+			driver
+				.scan_checkpointed(
+					&mut access,
+					ranges,
+					&predicate,
+					&mut checkpoint,
+					|checkpoint| checkpoint.resume_offset() != Some(OffsetType::new_unwrap(15)),
+				)
+				.unwrap()
+		};
+		assert!(!finished);
+		assert_eq!(checkpoint.results().count(), 1);
+
+		// resuming with different ranges is rejected
+		let err = unsafe {
+			driver
+				.scan_checkpointed(
+					&mut access,
+					[range(1, 65)],
+					&predicate,
+					&mut checkpoint,
+					|_| true,
+				)
+				.unwrap_err()
+		};
+		assert!(matches!(err, ScanDriverError::CheckpointMismatch));
+		assert_eq!(err.kind(), ErrorKind::Platform);
+
+		let finished = unsafe {
+			driver
+				.scan_checkpointed(&mut access, ranges, &predicate, &mut checkpoint, |_| true)
+				.unwrap()
+		};
+		assert!(finished);
+		assert!(checkpoint.is_finished());
+		assert_eq!(
+			checkpoint
+				.results()
+				.map(|(offset, _)| offset.get())
+				.collect::<Vec<_>>(),
+			[3, 15, 41]
+		);
+	}
+}
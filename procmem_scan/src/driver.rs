@@ -0,0 +1,996 @@
+//! Scan orchestration over process memory.
+//!
+//! The [`ScanDriver`] reads memory ranges in bounded chunks into reusable buffers and feeds them to a [`StreamScanner`],
+//! so scanning a huge heap does not require a buffer the size of the heap.
+
+use std::{
+	cell::RefCell,
+	collections::HashSet,
+	sync::{Arc, Mutex},
+	time::{Duration, Instant},
+};
+
+use thiserror::Error;
+
+use procmem_access::{
+	memory::{
+		access::ReadError,
+		lock::{LockError, UnlockError},
+	},
+	prelude::{ErrorKind, MemoryAccess, MemoryLock, OffsetType, ProcmemError},
+};
+
+use crate::{
+	checkpoint::ScanCheckpoint,
+	predicate::ScannerPredicate,
+	progress::ScanProgress,
+	stream::{ScanResult, StreamScanner},
+};
+
+#[derive(Debug, Error)]
+pub enum ScanDriverError {
+	#[error("could not read region {}-{}", range[0], range[1])]
+	Read {
+		range: [OffsetType; 2],
+		#[source]
+		source: ReadError,
+	},
+	#[error("checkpoint was created for different memory ranges")]
+	CheckpointMismatch,
+	#[error("could not lock the process")]
+	Lock(#[from] LockError),
+	#[error("could not unlock the process")]
+	Unlock(#[from] UnlockError),
+}
+impl ScanDriverError {
+	pub fn kind(&self) -> ErrorKind {
+		match self {
+			ScanDriverError::Read { source, .. } => source.kind(),
+			ScanDriverError::CheckpointMismatch => ErrorKind::Platform,
+			ScanDriverError::Lock(err) => err.kind(),
+			ScanDriverError::Unlock(err) => err.kind(),
+		}
+	}
+}
+impl From<ScanDriverError> for ProcmemError {
+	fn from(err: ScanDriverError) -> Self {
+		ProcmemError::new(err.kind(), err)
+	}
+}
+
+/// Limits on how much a scan may slow down the target process.
+///
+/// Without limits the driver reads memory as fast as possible.
+#[derive(Debug, Clone, Default)]
+pub struct ScanThrottle {
+	/// Maximum number of bytes read per second, unlimited if `None`.
+	pub max_bytes_per_second: Option<u64>,
+	/// Time to sleep after each chunk.
+	///
+	/// Together with [`scan_locked`](ScanDriver::scan_locked) this sets the duty cycle of the lock.
+	pub chunk_pause: Duration,
+}
+impl ScanThrottle {
+	pub fn is_unlimited(&self) -> bool {
+		self.max_bytes_per_second.is_none() && self.chunk_pause.is_zero()
+	}
+
+	/// Returns how long to sleep after reading `bytes_read` bytes in total since `start`.
+	fn delay(&self, start: Instant, bytes_read: u64) -> Duration {
+		let rate_delay = match self.max_bytes_per_second {
+			None | Some(0) => Duration::ZERO,
+			Some(rate) => {
+				let expected = Duration::from_secs_f64(bytes_read as f64 / rate as f64);
+				expected.saturating_sub(start.elapsed())
+			}
+		};
+
+		rate_delay.max(self.chunk_pause)
+	}
+}
+
+/// Configuration of a [`ScanDriver`].
+#[derive(Debug, Clone)]
+pub struct ScanConfig {
+	/// Number of bytes read from the process at once.
+	///
+	/// This is also the size of the buffers in the [`BufferPool`].
+	pub chunk_size: usize,
+	/// Maximum size of a region created by merging adjacent ranges.
+	///
+	/// Larger ranges are split.
+	pub max_region_size: u64,
+	/// Whether to skip memory which cannot be read instead of failing the whole scan.
+	///
+	/// Failed chunks are read again through [`MemoryAccess::read_partial`], so only the unreadable part of a region is skipped.
+	pub skip_unreadable: bool,
+	/// Number of chunks read at once through [`MemoryAccess::read_v`].
+	///
+	/// Backends such as `ProcfsUringAccess` submit the whole batch in one system call. Each chunk of a batch needs its own buffer from the pool.
+	pub read_batch: usize,
+	/// Limits applied between chunks.
+	pub throttle: ScanThrottle,
+}
+impl Default for ScanConfig {
+	fn default() -> Self {
+		ScanConfig {
+			chunk_size: 1024 * 1024,
+			max_region_size: 64 * 1024 * 1024,
+			skip_unreadable: false,
+			read_batch: 8,
+			throttle: ScanThrottle::default(),
+		}
+	}
+}
+
+/// Pool of equally sized buffers that can be shared across scans and threads.
+#[derive(Debug)]
+pub struct BufferPool {
+	buffer_size: usize,
+	buffers: Mutex<Vec<Vec<u8>>>,
+}
+impl BufferPool {
+	pub fn new(buffer_size: usize) -> Self {
+		assert!(buffer_size > 0);
+
+		BufferPool {
+			buffer_size,
+			buffers: Mutex::new(Vec::new()),
+		}
+	}
+
+	pub const fn buffer_size(&self) -> usize {
+		self.buffer_size
+	}
+
+	/// Returns the number of buffers currently available in the pool.
+	pub fn available(&self) -> usize {
+		self.buffers.lock().unwrap().len()
+	}
+
+	/// Takes a buffer of [`buffer_size`](BufferPool::buffer_size) bytes from the pool, allocating a new one if the pool is empty.
+	///
+	/// The contents of the buffer are unspecified.
+	pub fn take(&self) -> Vec<u8> {
+		match self.buffers.lock().unwrap().pop() {
+			Some(buffer) => buffer,
+			None => vec![0; self.buffer_size],
+		}
+	}
+
+	/// Returns a buffer to the pool.
+	///
+	/// Buffers of different size than [`buffer_size`](BufferPool::buffer_size) are dropped.
+	pub fn put(&self, buffer: Vec<u8>) {
+		if buffer.len() == self.buffer_size {
+			self.buffers.lock().unwrap().push(buffer);
+		}
+	}
+}
+
+/// Drives a scanner over memory ranges of a process.
+pub struct ScanDriver {
+	config: ScanConfig,
+	pool: Arc<BufferPool>,
+}
+impl ScanDriver {
+	/// Creates a new driver with its own buffer pool.
+	pub fn new(config: ScanConfig) -> Self {
+		let pool = Arc::new(BufferPool::new(config.chunk_size));
+
+		ScanDriver { config, pool }
+	}
+
+	/// Creates a new driver which shares the `pool` with other drivers.
+	///
+	/// The chunk size is taken from the pool buffer size.
+	pub fn with_pool(config: ScanConfig, pool: Arc<BufferPool>) -> Self {
+		ScanDriver {
+			config: ScanConfig {
+				chunk_size: pool.buffer_size(),
+				..config
+			},
+			pool,
+		}
+	}
+
+	pub const fn config(&self) -> &ScanConfig {
+		&self.config
+	}
+
+	pub const fn pool(&self) -> &Arc<BufferPool> {
+		&self.pool
+	}
+
+	/// Merges adjacent `ranges` into regions of at most [`max_region_size`](ScanConfig::max_region_size) bytes.
+	///
+	/// The `ranges` must be sorted and must not overlap.
+	pub fn regions(
+		&self,
+		ranges: impl IntoIterator<Item = [OffsetType; 2]>,
+	) -> impl Iterator<Item = [OffsetType; 2]> {
+		let max_size = self.config.max_region_size.max(1);
+
+		let mut regions: Vec<[OffsetType; 2]> = Vec::new();
+		for [mut start, end] in ranges {
+			while start < end {
+				let region_end = OffsetType::new_unwrap(end.get().min(start.get() + max_size));
+
+				match regions.last_mut() {
+					Some(last)
+						if last[1] == start && region_end.get() - last[0].get() <= max_size =>
+					{
+						last[1] = region_end;
+					}
+					_ => regions.push([start, region_end]),
+				}
+
+				start = region_end;
+			}
+		}
+
+		regions.into_iter()
+	}
+
+	/// Scans `ranges` of memory using `predicate`, calling `on_result` for each match.
+	///
+	/// Matches spanning chunk and region boundaries are found as long as the regions are adjacent.
+	///
+	/// ## Safety
+	/// * See [`MemoryAccess::read`].
+	pub unsafe fn scan<A: MemoryAccess, P: ScannerPredicate>(
+		&self,
+		access: &mut A,
+		ranges: impl IntoIterator<Item = [OffsetType; 2]>,
+		predicate: P,
+		on_result: impl FnMut(ScanResult),
+	) -> Result<(), ScanDriverError> {
+		self.drive(access, None, ranges, predicate, on_result, |_, _| true)
+			.map(|_| ())
+	}
+
+	/// Scans `ranges` like [`scan`](ScanDriver::scan), but only keeps the process locked while reading each batch of chunks.
+	///
+	/// The process runs while the chunks are being scanned and while the driver sleeps because of the [throttle](ScanConfig::throttle),
+	/// so a scan does not freeze a live process for its whole duration. The price is that values may change between chunks.
+	///
+	/// ## Safety
+	/// * See [`MemoryAccess::read`].
+	pub unsafe fn scan_locked<A: MemoryAccess, L: MemoryLock, P: ScannerPredicate>(
+		&self,
+		access: &mut A,
+		lock: &mut L,
+		ranges: impl IntoIterator<Item = [OffsetType; 2]>,
+		predicate: P,
+		on_result: impl FnMut(ScanResult),
+	) -> Result<(), ScanDriverError> {
+		self.drive(access, Some(lock), ranges, predicate, on_result, |_, _| {
+			true
+		})
+		.map(|_| ())
+	}
+
+	/// Scans `ranges` like [`scan`](ScanDriver::scan), reporting into `progress` after each chunk.
+	///
+	/// Returns `false` if the scan was stopped by [`ScanProgress::cancel`], matches found before that were already passed to `on_result`.
+	/// The `ranges` must be sorted and must not overlap.
+	///
+	/// ## Safety
+	/// * See [`MemoryAccess::read`].
+	pub unsafe fn scan_with_progress<A: MemoryAccess, P: ScannerPredicate>(
+		&self,
+		access: &mut A,
+		ranges: impl IntoIterator<Item = [OffsetType; 2]>,
+		predicate: P,
+		progress: &ScanProgress,
+		mut on_result: impl FnMut(ScanResult),
+	) -> Result<bool, ScanDriverError> {
+		let ranges: Vec<[OffsetType; 2]> = ranges.into_iter().collect();
+		progress.start(
+			ranges
+				.iter()
+				.map(|range| range[1].get() - range[0].get())
+				.sum(),
+		);
+		if progress.is_cancelled() {
+			return Ok(false);
+		}
+
+		// ranges before `pages_done` are finished and contain `bytes_done` bytes
+		let (mut pages_done, mut bytes_done) = (0, 0);
+		self.drive(
+			access,
+			None,
+			ranges.iter().copied(),
+			predicate,
+			|result| {
+				progress.add_match();
+				on_result(result)
+			},
+			|_, next_offset| {
+				while let Some(&[start, end]) = ranges.get(pages_done) {
+					if end > next_offset {
+						let partial = next_offset.get().saturating_sub(start.get());
+						progress.set_position(bytes_done + partial, pages_done as u64);
+						break;
+					}
+
+					pages_done += 1;
+					bytes_done += end.get() - start.get();
+					progress.set_position(bytes_done, pages_done as u64);
+				}
+
+				!progress.is_cancelled()
+			},
+		)
+	}
+
+	/// Scans `ranges` like [`scan`](ScanDriver::scan), continuing from and recording progress into `checkpoint`.
+	///
+	/// Matches are appended to the checkpoint results. After each chunk the checkpoint is updated and passed to `on_progress`,
+	/// which can save it and return `false` to pause the scan. Returns whether the scan finished.
+	///
+	/// Candidates which are still in progress when pausing cannot be saved, so the checkpoint rewinds to the start of the lowest one
+	/// and resuming scans those bytes again. Matches already recorded are not recorded twice.
+	///
+	/// Fails with [`ScanDriverError::CheckpointMismatch`] if the checkpoint was created for different `ranges`.
+	///
+	/// ## Safety
+	/// * See [`MemoryAccess::read`].
+	pub unsafe fn scan_checkpointed<A: MemoryAccess, P: ScannerPredicate>(
+		&self,
+		access: &mut A,
+		ranges: impl IntoIterator<Item = [OffsetType; 2]>,
+		predicate: P,
+		checkpoint: &mut ScanCheckpoint,
+		mut on_progress: impl FnMut(&ScanCheckpoint) -> bool,
+	) -> Result<bool, ScanDriverError> {
+		let ranges: Vec<[OffsetType; 2]> = ranges.into_iter().collect();
+		if !checkpoint.matches(&ranges) {
+			return Err(ScanDriverError::CheckpointMismatch);
+		}
+		let resume_offset = match checkpoint.resume_offset() {
+			None => return Ok(true),
+			Some(offset) => offset,
+		};
+
+		// matches at or after the resume offset may have been recorded before the rewind
+		let recorded: HashSet<ScanResult> = checkpoint
+			.results()
+			.filter(|(offset, _)| *offset >= resume_offset)
+			.collect();
+		let remaining = ranges
+			.into_iter()
+			.filter(|range| range[1] > resume_offset)
+			.map(|[start, end]| [start.max(resume_offset), end]);
+
+		let found = RefCell::new(Vec::new());
+		let finished = self.drive(
+			access,
+			None,
+			remaining,
+			predicate,
+			|result| {
+				if !recorded.contains(&result) {
+					found.borrow_mut().push(result);
+				}
+			},
+			|scanner, next_offset| {
+				checkpoint.record(found.borrow_mut().drain(..));
+				checkpoint.set_resume_offset(Some(
+					scanner
+						.pending_offset()
+						.map_or(next_offset, |pending| pending.min(next_offset)),
+				));
+
+				on_progress(checkpoint)
+			},
+		)?;
+
+		if finished {
+			checkpoint.record(found.into_inner());
+			checkpoint.set_resume_offset(None);
+		}
+
+		Ok(finished)
+	}
+
+	/// Runs the scan loop, calling `after_chunk` with the scanner and the offset following each chunk.
+	///
+	/// Chunks are read in batches of [`read_batch`](ScanConfig::read_batch) through [`MemoryAccess::read_v`]. If a batch fails,
+	/// its chunks are read again one by one through [`MemoryAccess::read_partial`] to find the unreadable range.
+	///
+	/// If `lock` is given, it is held only while reading each batch. Returns `false` if `after_chunk` stopped the scan.
+	unsafe fn drive<A: MemoryAccess, P: ScannerPredicate>(
+		&self,
+		access: &mut A,
+		mut lock: Option<&mut dyn MemoryLock>,
+		ranges: impl IntoIterator<Item = [OffsetType; 2]>,
+		predicate: P,
+		mut on_result: impl FnMut(ScanResult),
+		mut after_chunk: impl FnMut(&StreamScanner<P>, OffsetType) -> bool,
+	) -> Result<bool, ScanDriverError> {
+		let mut scanner = StreamScanner::new(predicate);
+		let mut buffers: Vec<Vec<u8>> = Vec::new();
+
+		let regions: Vec<[OffsetType; 2]> = self.regions(ranges).collect();
+		let read_batch = self.config.read_batch.max(1);
+		let throttle = &self.config.throttle;
+		let start = Instant::now();
+		let mut bytes_read = 0u64;
+
+		let result = (|| {
+			let mut previous_end = None;
+			// region index and start of the next chunk to read
+			let mut next = regions.first().map(|region| (0, region[0]));
+
+			while let Some(position) = next {
+				// the chunks of one batch may come from several regions
+				let mut chunks: Vec<(usize, [OffsetType; 2])> = Vec::with_capacity(read_batch);
+				let mut cursor = Some(position);
+				while let Some((index, chunk_start)) = cursor.filter(|_| chunks.len() < read_batch)
+				{
+					let region = regions[index];
+					let chunk_end = OffsetType::new_unwrap(
+						region[1]
+							.get()
+							.min(chunk_start.get() + self.config.chunk_size as u64),
+					);
+					chunks.push((index, [chunk_start, chunk_end]));
+
+					cursor = if chunk_end < region[1] {
+						Some((index, chunk_end))
+					} else {
+						regions.get(index + 1).map(|next| (index + 1, next[0]))
+					};
+				}
+				next = cursor;
+
+				while buffers.len() < chunks.len() {
+					buffers.push(self.pool.take());
+				}
+				let chunk_size = |[chunk_start, chunk_end]: [OffsetType; 2]| {
+					(chunk_end.get() - chunk_start.get()) as usize
+				};
+
+				let mut requests: Vec<(OffsetType, &mut [u8])> = chunks
+					.iter()
+					.zip(buffers.iter_mut())
+					.map(|(&(_, chunk), buffer)| (chunk[0], &mut buffer[..chunk_size(chunk)]))
+					.collect();
+				let batch_result = with_lock(&mut lock, || access.read_v(&mut requests))?;
+				drop(requests);
+				bytes_read += chunks
+					.iter()
+					.map(|&(_, chunk)| chunk_size(chunk) as u64)
+					.sum::<u64>();
+
+				for (&(index, chunk), buffer) in chunks.iter().zip(buffers.iter_mut()) {
+					let [chunk_start, chunk_end] = chunk;
+					let region = regions[index];
+					let data = &mut buffer[..chunk_size(chunk)];
+
+					if previous_end != Some(chunk_start) {
+						scanner.reset();
+					}
+					previous_end = Some(chunk_end);
+
+					if batch_result.is_err() {
+						if let Err(err) =
+							with_lock(&mut lock, || access.read_partial(chunk_start, data))?
+						{
+							if !self.config.skip_unreadable {
+								return Err(ScanDriverError::Read {
+									range: [err.unreadable[0], region[1]],
+									source: err.source,
+								});
+							}
+
+							// scan what could be read and plan a new batch after the unreadable range
+							scanner
+								.scan_slice(chunk_start, &data[..err.read])
+								.into_iter()
+								.for_each(&mut on_result);
+							previous_end = None;
+							scanner.reset();
+
+							let resume = err.unreadable[1];
+							next = if resume < region[1] {
+								Some((index, resume))
+							} else {
+								regions.get(index + 1).map(|next| (index + 1, next[0]))
+							};
+							if !after_chunk(&scanner, resume) {
+								return Ok(false);
+							}
+							break;
+						}
+					}
+
+					scanner
+						.scan_slice(chunk_start, data)
+						.into_iter()
+						.for_each(&mut on_result);
+
+					if !after_chunk(&scanner, chunk_end) {
+						return Ok(false);
+					}
+
+					if !throttle.is_unlimited() {
+						std::thread::sleep(throttle.delay(start, bytes_read));
+					}
+				}
+			}
+
+			Ok(true)
+		})();
+
+		for buffer in buffers {
+			self.pool.put(buffer);
+		}
+
+		result
+	}
+
+	/// Creates a task which scans `ranges` using `predicate` in bounded steps, see [`ScanTask::step`].
+	pub fn task<P: ScannerPredicate>(
+		&self,
+		ranges: impl IntoIterator<Item = [OffsetType; 2]>,
+		predicate: P,
+	) -> ScanTask<P> {
+		let regions: Vec<[OffsetType; 2]> = self.regions(ranges).collect();
+		let total_bytes = regions
+			.iter()
+			.map(|region| region[1].get() - region[0].get())
+			.sum();
+
+		ScanTask {
+			config: self.config.clone(),
+			pool: self.pool.clone(),
+			buffer: Some(self.pool.take()),
+			scanner: StreamScanner::new(predicate),
+			chunk_start: regions.first().map(|region| region[0]),
+			regions,
+			region_index: 0,
+			total_bytes,
+			bytes_scanned: 0,
+		}
+	}
+}
+
+/// Runs `read` while holding `lock`, if any.
+fn with_lock<T>(
+	lock: &mut Option<&mut dyn MemoryLock>,
+	read: impl FnOnce() -> T,
+) -> Result<T, ScanDriverError> {
+	if let Some(lock) = lock.as_deref_mut() {
+		lock.lock()?;
+	}
+	let result = read();
+	if let Some(lock) = lock.as_deref_mut() {
+		lock.unlock()?;
+	}
+
+	Ok(result)
+}
+
+/// Result of one [`ScanTask::step`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StepResult {
+	/// Some memory remains to be scanned.
+	Pending,
+	/// All ranges were scanned.
+	Finished,
+}
+
+/// Scan which is advanced by the caller a bounded amount of work at a time.
+///
+/// This allows driving a scan from a GUI main loop or an async task without a separate thread.
+/// Created by [`ScanDriver::task`], the [throttle](ScanConfig::throttle) is not applied since the caller controls the pace.
+pub struct ScanTask<P: ScannerPredicate> {
+	config: ScanConfig,
+	pool: Arc<BufferPool>,
+	/// Buffer taken from the pool, returned when the task is dropped.
+	buffer: Option<Vec<u8>>,
+	scanner: StreamScanner<P>,
+	regions: Vec<[OffsetType; 2]>,
+	region_index: usize,
+	/// Offset of the next chunk in the current region, `None` when finished.
+	chunk_start: Option<OffsetType>,
+	total_bytes: u64,
+	bytes_scanned: u64,
+}
+impl<P: ScannerPredicate> ScanTask<P> {
+	/// Total number of bytes in the scanned ranges.
+	pub const fn total_bytes(&self) -> u64 {
+		self.total_bytes
+	}
+
+	/// Number of bytes scanned or skipped so far.
+	pub const fn bytes_scanned(&self) -> u64 {
+		self.bytes_scanned
+	}
+
+	pub const fn is_finished(&self) -> bool {
+		self.chunk_start.is_none()
+	}
+
+	/// Scans up to `budget_bytes` bytes, calling `on_result` for each match.
+	///
+	/// At least one byte is scanned by each call, chunks are shortened to fit the budget. When a read fails with
+	/// [`skip_unreadable`](ScanConfig::skip_unreadable) set, the readable part of the chunk is scanned and the unreadable range is skipped.
+	///
+	/// ## Safety
+	/// * See [`MemoryAccess::read`].
+	pub unsafe fn step<A: MemoryAccess + ?Sized>(
+		&mut self,
+		access: &mut A,
+		budget_bytes: u64,
+		mut on_result: impl FnMut(ScanResult),
+	) -> Result<StepResult, ScanDriverError> {
+		let mut budget = budget_bytes.max(1);
+
+		while let Some(chunk_start) = self.chunk_start {
+			if budget == 0 {
+				return Ok(StepResult::Pending);
+			}
+
+			let region = self.regions[self.region_index];
+			let chunk_size = (region[1].get() - chunk_start.get())
+				.min(self.config.chunk_size as u64)
+				.min(budget) as usize;
+			let chunk = &mut self.buffer.as_mut().unwrap()[..chunk_size];
+
+			match access.read_partial(chunk_start, chunk) {
+				Ok(()) => {
+					self.scanner
+						.scan_slice(chunk_start, chunk)
+						.into_iter()
+						.for_each(&mut on_result);
+
+					self.bytes_scanned += chunk_size as u64;
+					budget -= chunk_size as u64;
+					self.advance(chunk_start.saturating_add(chunk_size as u64));
+				}
+				Err(err) => {
+					if !self.config.skip_unreadable {
+						return Err(ScanDriverError::Read {
+							range: [err.unreadable[0], region[1]],
+							source: err.source,
+						});
+					}
+
+					self.scanner
+						.scan_slice(chunk_start, &chunk[..err.read])
+						.into_iter()
+						.for_each(&mut on_result);
+					self.scanner.reset();
+
+					let resume = err.unreadable[1];
+					self.bytes_scanned += resume.get() - chunk_start.get();
+					budget = budget.saturating_sub(chunk_size as u64);
+					self.advance(resume);
+				}
+			}
+		}
+
+		Ok(StepResult::Finished)
+	}
+
+	/// Moves to `next_offset`, continuing with the next region at the end of the current one.
+	fn advance(&mut self, next_offset: OffsetType) {
+		let region = self.regions[self.region_index];
+		if next_offset < region[1] {
+			self.chunk_start = Some(next_offset);
+			return;
+		}
+
+		self.region_index += 1;
+		self.chunk_start = self.regions.get(self.region_index).map(|next| {
+			if next[0] != region[1] {
+				self.scanner.reset();
+			}
+
+			next[0]
+		});
+	}
+}
+impl<P: ScannerPredicate> Drop for ScanTask<P> {
+	fn drop(&mut self) {
+		if let Some(buffer) = self.buffer.take() {
+			self.pool.put(buffer);
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use std::{convert::TryInto, sync::Arc, time::Duration};
+
+	use procmem_access::{
+		memory::access::PARTIAL_READ_GRANULARITY,
+		platform::mock::{MockMemoryAccess, MockMemoryLock},
+		prelude::OffsetType,
+	};
+
+	use super::{BufferPool, ScanConfig, ScanDriver, ScanThrottle, StepResult};
+	use crate::{predicate::value::ValuePredicate, progress::ScanProgress};
+
+	fn range(start: u64, end: u64) -> [OffsetType; 2] {
+		[OffsetType::new_unwrap(start), OffsetType::new_unwrap(end)]
+	}
+
+	#[test]
+	fn test_driver_regions() {
+		let driver = ScanDriver::new(ScanConfig {
+			max_region_size: 100,
+			..Default::default()
+		});
+
+		let regions: Vec<_> = driver
+			.regions([range(1, 50), range(50, 80), range(80, 120), range(200, 450)])
+			.collect();
+		assert_eq!(
+			regions,
+			[
+				range(1, 80),
+				range(80, 120),
+				range(200, 300),
+				range(300, 400),
+				range(400, 450)
+			]
+		);
+	}
+
+	#[test]
+	fn test_driver_scan_across_chunks() {
+		let mut data = vec![0u8; 64];
+		data[6..10].copy_from_slice(&[1, 2, 3, 4]);
+		data[33..37].copy_from_slice(&[1, 2, 3, 4]);
+		let mut access = MockMemoryAccess::new().with_region(OffsetType::new_unwrap(1), data);
+
+		let driver = ScanDriver::new(ScanConfig {
+			chunk_size: 8,
+			max_region_size: 16,
+			read_batch: 3,
+			..Default::default()
+		});
+		let predicate = ValuePredicate::new([1u8, 2, 3, 4], false);
+
+		let mut found = Vec::new();
+		unsafe {
+			driver
+				.scan(
+					&mut access,
+					[range(1, 20), range(20, 65)],
+					&predicate,
+					|result| found.push(result),
+				)
+				.unwrap();
+		}
+		assert_eq!(
+			found,
+			[
+				(OffsetType::new_unwrap(7), 4.try_into().unwrap()),
+				(OffsetType::new_unwrap(34), 4.try_into().unwrap())
+			]
+		);
+		// one buffer for each chunk of a batch
+		assert_eq!(driver.pool().available(), 3);
+	}
+
+	#[test]
+	fn test_driver_skip_unreadable() {
+		let mut data = vec![0u8; 64];
+		data[4..6].copy_from_slice(&[1, 2]);
+		data[40..42].copy_from_slice(&[1, 2]);
+		let mut access = MockMemoryAccess::new()
+			.with_region(OffsetType::new_unwrap(1), data)
+			.with_fault(range(17, 33));
+
+		let pool = Arc::new(BufferPool::new(8));
+		let driver = ScanDriver::with_pool(
+			ScanConfig {
+				skip_unreadable: true,
+				max_region_size: 16,
+				..Default::default()
+			},
+			pool.clone(),
+		);
+		assert_eq!(driver.config().chunk_size, 8);
+
+		let predicate = ValuePredicate::new([1u8, 2], false);
+
+		let mut found = Vec::new();
+		unsafe {
+			driver
+				.scan(&mut access, [range(1, 65)], &predicate, |(offset, _)| {
+					found.push(offset.get())
+				})
+				.unwrap();
+		}
+		assert_eq!(found, [5, 41]);
+
+		let driver = ScanDriver::with_pool(ScanConfig::default(), pool.clone());
+		let err = unsafe {
+			driver
+				.scan(&mut access, [range(1, 65)], &predicate, |_| ())
+				.unwrap_err()
+		};
+		assert_eq!(
+			err.to_string(),
+			format!(
+				"could not read region {}-{}",
+				range(17, 65)[0],
+				range(17, 65)[1]
+			)
+		);
+		assert_eq!(pool.available(), ScanConfig::default().read_batch);
+	}
+
+	#[test]
+	fn test_driver_skip_unreadable_partial() {
+		const PAGE: u64 = PARTIAL_READ_GRANULARITY;
+
+		// the unreadable page is in the middle of one chunk of one region
+		let mut data = vec![0u8; (PAGE * 3) as usize];
+		data[99..101].copy_from_slice(&[1, 2]);
+		data[(PAGE * 2 + 99) as usize..(PAGE * 2 + 101) as usize].copy_from_slice(&[1, 2]);
+		let mut access = MockMemoryAccess::new()
+			.with_region(OffsetType::new_unwrap(1), data)
+			.with_fault(range(PAGE, PAGE * 2));
+
+		let driver = ScanDriver::new(ScanConfig {
+			skip_unreadable: true,
+			..Default::default()
+		});
+		let predicate = ValuePredicate::new([1u8, 2], false);
+
+		let mut found = Vec::new();
+		unsafe {
+			driver
+				.scan(
+					&mut access,
+					[range(1, PAGE * 3 + 1)],
+					&predicate,
+					|(offset, _)| found.push(offset.get()),
+				)
+				.unwrap();
+		}
+		assert_eq!(found, [100, PAGE * 2 + 100]);
+
+		let mut task = driver.task([range(1, PAGE * 3 + 1)], &predicate);
+		let mut found = Vec::new();
+		let step = unsafe {
+			task.step(&mut access, u64::MAX, |(offset, _)| {
+				found.push(offset.get())
+			})
+			.unwrap()
+		};
+		assert_eq!(step, StepResult::Finished);
+		assert_eq!(task.bytes_scanned(), PAGE * 3);
+		assert_eq!(found, [100, PAGE * 2 + 100]);
+	}
+
+	#[test]
+	fn test_driver_scan_locked_throttled() {
+		let mut data = vec![0u8; 32];
+		data[14..18].copy_from_slice(&[1, 2, 3, 4]);
+		let mut access = MockMemoryAccess::new().with_region(OffsetType::new_unwrap(1), data);
+		let mut lock = MockMemoryLock::new();
+
+		let driver = ScanDriver::new(ScanConfig {
+			chunk_size: 8,
+			read_batch: 2,
+			throttle: ScanThrottle {
+				max_bytes_per_second: Some(1000),
+				chunk_pause: Duration::from_millis(1),
+			},
+			..Default::default()
+		});
+		let predicate = ValuePredicate::new([1u8, 2, 3, 4], false);
+
+		let mut found = Vec::new();
+		unsafe {
+			driver
+				.scan_locked(
+					&mut access,
+					&mut lock,
+					[range(1, 33)],
+					&predicate,
+					|(offset, _)| found.push(offset.get()),
+				)
+				.unwrap();
+		}
+		assert_eq!(found, [15]);
+		// locked once for each batch of two chunks
+		assert_eq!(lock.acquired(), 2);
+		assert!(!lock.is_locked());
+	}
+
+	#[test]
+	fn test_driver_scan_progress() {
+		let mut data = vec![0u8; 64];
+		data[6..10].copy_from_slice(&[1, 2, 3, 4]);
+		data[44..48].copy_from_slice(&[1, 2, 3, 4]);
+		let mut access = MockMemoryAccess::new().with_region(OffsetType::new_unwrap(1), data);
+
+		let driver = ScanDriver::new(ScanConfig {
+			chunk_size: 8,
+			..Default::default()
+		});
+		let predicate = ValuePredicate::new([1u8, 2, 3, 4], false);
+		let ranges = [range(1, 20), range(20, 30), range(40, 65)];
+
+		let progress = ScanProgress::new();
+		let mut reported = Vec::new();
+		let finished = unsafe {
+			driver
+				.scan_with_progress(&mut access, ranges, &predicate, &progress, |_| {
+					reported.push((progress.bytes_scanned(), progress.pages_done()))
+				})
+				.unwrap()
+		};
+		assert!(finished);
+		assert_eq!(progress.total_bytes(), 54);
+		assert_eq!(progress.bytes_scanned(), 54);
+		assert_eq!(progress.pages_done(), 3);
+		assert_eq!(progress.matches(), 2);
+		// matches are reported while their chunk is scanned, before the progress is updated
+		assert_eq!(reported, [(8, 0), (37, 2)]);
+
+		// cancelling stops the scan at the next chunk boundary
+		let progress = ScanProgress::new();
+		let finished = unsafe {
+			driver
+				.scan_with_progress(&mut access, ranges, &predicate, &progress, |_| {
+					progress.cancel()
+				})
+				.unwrap()
+		};
+		assert!(!finished);
+		assert_eq!(progress.matches(), 1);
+		assert_eq!(progress.bytes_scanned(), 16);
+		assert_eq!(driver.pool().available(), driver.config().read_batch);
+	}
+
+	#[test]
+	fn test_driver_task_steps() {
+		let mut data = vec![0u8; 64];
+		data[6..10].copy_from_slice(&[1, 2, 3, 4]);
+		data[44..48].copy_from_slice(&[1, 2, 3, 4]);
+		let mut access = MockMemoryAccess::new()
+			.with_region(OffsetType::new_unwrap(1), data)
+			.with_fault(range(17, 33));
+
+		let driver = ScanDriver::new(ScanConfig {
+			chunk_size: 8,
+			max_region_size: 16,
+			skip_unreadable: true,
+			..Default::default()
+		});
+		let mut task = driver.task([range(1, 65)], ValuePredicate::new([1u8, 2, 3, 4], false));
+		assert_eq!(task.total_bytes(), 64);
+		assert_eq!(driver.pool().available(), 0);
+
+		let mut found = Vec::new();
+		let mut steps = 0;
+		loop {
+			steps += 1;
+			let step = unsafe {
+				task.step(&mut access, 5, |(offset, _)| found.push(offset.get()))
+					.unwrap()
+			};
+			assert!(task.bytes_scanned() <= steps * 16);
+			if step == StepResult::Finished {
+				break;
+			}
+		}
+		assert!(task.is_finished());
+		assert_eq!(task.bytes_scanned(), 64);
+		assert_eq!(found, [7, 45]);
+
+		drop(task);
+		assert_eq!(driver.pool().available(), 1);
+	}
+}
@@ -0,0 +1,571 @@
+//! Export and import of scan results for processing in other tools.
+//!
+//! Results are first turned into [`ExportRecord`]s, which resolve the module-relative form of each offset
+//! and optionally read the current value, and then written as CSV or (with the `serde` feature) JSON.
+//!
+//! Imported records can be [rebased](rebase) onto the current module addresses of a restarted process
+//! and [merged](merge) with the current results.
+
+use std::{collections::HashMap, io, num::NonZeroUsize};
+
+use thiserror::Error;
+
+use procmem_access::{
+	layout::{FieldType, Value},
+	prelude::{ErrorKind, MemoryAccess, MemoryMap, OffsetType, ProcmemError},
+};
+
+use crate::stream::ScanResult;
+
+#[derive(Debug, Error)]
+pub enum ImportError {
+	#[error("could not read results")]
+	Io(#[from] io::Error),
+	#[error("invalid CSV on line {line}: {message}")]
+	Csv { line: usize, message: String },
+	#[cfg(feature = "serde")]
+	#[error("invalid JSON results")]
+	Json(#[from] serde_json::Error),
+}
+impl ImportError {
+	pub fn kind(&self) -> ErrorKind {
+		match self {
+			ImportError::Io(err) => ErrorKind::from_io(err),
+			ImportError::Csv { .. } => ErrorKind::Parse,
+			#[cfg(feature = "serde")]
+			ImportError::Json(_) => ErrorKind::Parse,
+		}
+	}
+}
+impl From<ImportError> for ProcmemError {
+	fn from(err: ImportError) -> Self {
+		ProcmemError::new(err.kind(), err)
+	}
+}
+
+/// One exported match.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExportRecord {
+	pub offset: OffsetType,
+	pub length: usize,
+	/// Module containing the offset and the offset relative to the module base.
+	pub module: Option<(String, u64)>,
+	/// Current value, `None` if no type was requested or the value could not be read.
+	pub value: Option<Value>,
+	pub label: Option<String>,
+}
+impl ExportRecord {
+	/// Returns the offset in the `module+0x1a2b` form if it is inside a module.
+	pub fn module_relative(&self) -> Option<String> {
+		self.module
+			.as_ref()
+			.map(|(module, offset)| format!("{}+0x{:x}", module, offset))
+	}
+}
+
+/// Creates records for `results`.
+///
+/// If `value_type` is set, the value at each offset is read as that type. Offsets present in `labels` get the label attached.
+///
+/// ## Safety
+/// * See [`MemoryAccess::read`].
+pub unsafe fn collect_records<A: MemoryAccess + ?Sized, M: MemoryMap + ?Sized>(
+	access: &mut A,
+	map: &M,
+	results: impl IntoIterator<Item = ScanResult>,
+	value_type: Option<&FieldType>,
+	labels: &HashMap<OffsetType, String>,
+) -> Vec<ExportRecord> {
+	let mut buffer = Vec::new();
+
+	results
+		.into_iter()
+		.map(|(offset, length)| {
+			let value = value_type.and_then(|value_type| {
+				buffer.resize(value_type.size(), 0);
+				access
+					.read(offset, &mut buffer)
+					.ok()
+					.map(|_| value_type.decode(&buffer))
+			});
+
+			ExportRecord {
+				offset,
+				length: length.get(),
+				module: map
+					.module_offset(offset)
+					.map(|(module, relative)| (module.to_string(), relative)),
+				value,
+				label: labels.get(&offset).cloned(),
+			}
+		})
+		.collect()
+}
+
+fn csv_field(field: &str) -> std::borrow::Cow<'_, str> {
+	if field.contains([',', '"', '\n', '\r']) {
+		format!("\"{}\"", field.replace('"', "\"\"")).into()
+	} else {
+		field.into()
+	}
+}
+
+/// Writes records as CSV with the columns `offset,length,module,module_offset,value,label`.
+///
+/// Offsets are written in hex with the `0x` prefix, missing values are empty.
+pub fn write_csv(mut writer: impl io::Write, records: &[ExportRecord]) -> io::Result<()> {
+	writeln!(writer, "offset,length,module,module_offset,value,label")?;
+
+	for record in records {
+		let (module, module_offset) = match &record.module {
+			Some((module, offset)) => (module.as_str(), format!("0x{:x}", offset)),
+			None => ("", String::new()),
+		};
+		let value = record
+			.value
+			.as_ref()
+			.map(|value| value.to_string())
+			.unwrap_or_default();
+
+		writeln!(
+			writer,
+			"0x{:x},{},{},{},{},{}",
+			record.offset.get(),
+			record.length,
+			csv_field(module),
+			module_offset,
+			csv_field(&value),
+			csv_field(record.label.as_deref().unwrap_or(""))
+		)?;
+	}
+
+	Ok(())
+}
+
+#[cfg(feature = "serde")]
+fn value_to_json(value: &Value) -> serde_json::Value {
+	use serde_json::json;
+
+	match value {
+		Value::U8(v) => json!(v),
+		Value::I8(v) => json!(v),
+		Value::U16(v) => json!(v),
+		Value::I16(v) => json!(v),
+		Value::U32(v) => json!(v),
+		Value::I32(v) => json!(v),
+		Value::U64(v) => json!(v),
+		Value::I64(v) => json!(v),
+		Value::F32(v) => json!(v),
+		Value::F64(v) => json!(v),
+		Value::Bool(v) => json!(v),
+		Value::Pointer(v) => json!(v),
+		Value::Bytes(v) => json!(v),
+		Value::String(v) => json!(v),
+		Value::Struct(fields) => serde_json::Value::Object(
+			fields
+				.iter()
+				.map(|(name, value)| (name.clone(), value_to_json(value)))
+				.collect(),
+		),
+		Value::Array(elements) => {
+			serde_json::Value::Array(elements.iter().map(value_to_json).collect())
+		}
+	}
+}
+
+/// Writes records as a JSON array of objects.
+///
+/// Offsets are numbers, typed values are JSON numbers, strings, arrays or objects.
+#[cfg(feature = "serde")]
+pub fn write_json(writer: impl io::Write, records: &[ExportRecord]) -> serde_json::Result<()> {
+	use serde_json::json;
+
+	let records: Vec<_> = records
+		.iter()
+		.map(|record| {
+			json!({
+				"offset": record.offset.get(),
+				"length": record.length,
+				"module": record.module.as_ref().map(|(module, _)| module),
+				"module_offset": record.module.as_ref().map(|(_, offset)| offset),
+				"value": record.value.as_ref().map(value_to_json),
+				"label": record.label,
+			})
+		})
+		.collect();
+
+	serde_json::to_writer_pretty(writer, &records)
+}
+
+/// Splits CSV text into rows of fields, handling quoted fields.
+///
+/// Returns the rows together with the line on which they start.
+fn parse_csv(text: &str) -> Result<Vec<(usize, Vec<String>)>, ImportError> {
+	let mut rows = Vec::new();
+	let mut row = Vec::new();
+	let mut field = String::new();
+	let (mut line, mut row_line) = (1, 1);
+	let mut quoted = false;
+
+	let mut chars = text.chars().peekable();
+	while let Some(c) = chars.next() {
+		match (quoted, c) {
+			(true, '"') if chars.peek() == Some(&'"') => {
+				chars.next();
+				field.push('"');
+			}
+			(true, '"') => quoted = false,
+			(false, '"') if field.is_empty() => quoted = true,
+			(false, ',') => row.push(std::mem::take(&mut field)),
+			(false, '\r') if chars.peek() == Some(&'\n') => (),
+			(false, '\n') => {
+				row.push(std::mem::take(&mut field));
+				rows.push((row_line, std::mem::take(&mut row)));
+				line += 1;
+				row_line = line;
+			}
+			(_, c) => {
+				if c == '\n' {
+					line += 1;
+				}
+				field.push(c);
+			}
+		}
+	}
+	if quoted {
+		return Err(ImportError::Csv {
+			line: row_line,
+			message: "unterminated quoted field".to_string(),
+		});
+	}
+	if !field.is_empty() || !row.is_empty() {
+		row.push(field);
+		rows.push((row_line, row));
+	}
+
+	Ok(rows)
+}
+
+fn parse_number(value: &str) -> Option<u64> {
+	match value.strip_prefix("0x") {
+		Some(hex) => u64::from_str_radix(hex, 16).ok(),
+		None => value.parse().ok(),
+	}
+}
+
+/// Reads records written by [`write_csv`].
+///
+/// Values are not imported since their type is not part of the file, the `value` column is ignored.
+pub fn read_csv(mut reader: impl io::Read) -> Result<Vec<ExportRecord>, ImportError> {
+	let mut text = String::new();
+	reader.read_to_string(&mut text)?;
+
+	let mut rows = parse_csv(&text)?.into_iter();
+	let header = match rows.next() {
+		None => return Ok(Vec::new()),
+		Some((_, header)) => header,
+	};
+	let column = |name: &str| header.iter().position(|column| column == name);
+	let (offset_column, length_column) = match (column("offset"), column("length")) {
+		(Some(offset), Some(length)) => (offset, length),
+		_ => {
+			return Err(ImportError::Csv {
+				line: 1,
+				message: "missing offset or length column".to_string(),
+			})
+		}
+	};
+	let (module_column, module_offset_column, label_column) =
+		(column("module"), column("module_offset"), column("label"));
+
+	let mut records = Vec::new();
+	for (line, row) in rows {
+		let field = |column: Option<usize>| {
+			column
+				.and_then(|column| row.get(column))
+				.map(String::as_str)
+				.filter(|field| !field.is_empty())
+		};
+		let invalid = |message: &str| ImportError::Csv {
+			line,
+			message: message.to_string(),
+		};
+
+		let offset = field(Some(offset_column))
+			.and_then(parse_number)
+			.and_then(OffsetType::new)
+			.ok_or_else(|| invalid("invalid offset"))?;
+		let length = field(Some(length_column))
+			.and_then(|length| length.parse().ok())
+			.filter(|&length| length > 0)
+			.ok_or_else(|| invalid("invalid length"))?;
+		let module = match (field(module_column), field(module_offset_column)) {
+			(Some(module), Some(module_offset)) => Some((
+				module.to_string(),
+				parse_number(module_offset).ok_or_else(|| invalid("invalid module offset"))?,
+			)),
+			_ => None,
+		};
+
+		records.push(ExportRecord {
+			offset,
+			length,
+			module,
+			value: None,
+			label: field(label_column).map(str::to_string),
+		});
+	}
+
+	Ok(records)
+}
+
+/// Reads records written by [`write_json`].
+///
+/// Values are not imported since their type is not part of the file.
+#[cfg(feature = "serde")]
+pub fn read_json(reader: impl io::Read) -> Result<Vec<ExportRecord>, ImportError> {
+	#[derive(serde::Deserialize)]
+	struct JsonRecord {
+		offset: u64,
+		length: usize,
+		module: Option<String>,
+		module_offset: Option<u64>,
+		label: Option<String>,
+	}
+
+	let records: Vec<JsonRecord> = serde_json::from_reader(reader)?;
+
+	records
+		.into_iter()
+		.map(|record| {
+			let offset = OffsetType::new(record.offset)
+				.filter(|_| record.length > 0)
+				.ok_or_else(|| {
+					ImportError::Json(serde::de::Error::custom(
+						"offset and length must not be zero",
+					))
+				})?;
+
+			Ok(ExportRecord {
+				offset,
+				length: record.length,
+				module: record.module.zip(record.module_offset),
+				value: None,
+				label: record.label,
+			})
+		})
+		.collect()
+}
+
+/// Moves records inside modules to where the modules are mapped in `map`.
+///
+/// This makes results from a previous run of the process usable after ASLR placed the modules elsewhere.
+/// Records outside of modules are kept as they are. Records in modules which are not mapped are returned separately.
+pub fn rebase<M: MemoryMap + ?Sized>(
+	records: Vec<ExportRecord>,
+	map: &M,
+) -> (Vec<ExportRecord>, Vec<ExportRecord>) {
+	let mut rebased = Vec::with_capacity(records.len());
+	let mut missing = Vec::new();
+	for mut record in records {
+		if let Some((module, module_offset)) = &record.module {
+			match map.module_base(module) {
+				Some(base) => record.offset = base.saturating_add(*module_offset),
+				None => {
+					missing.push(record);
+					continue;
+				}
+			}
+		}
+
+		rebased.push(record);
+	}
+
+	(rebased, missing)
+}
+
+/// How [`merge`] combines two result sets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MergeMode {
+	/// Keep results present in either set.
+	Union,
+	/// Keep results present in both sets.
+	Intersection,
+}
+
+/// Merges `current` results with `imported` records by offset.
+///
+/// The output is sorted by offset. Results present in both sets keep the length from `current`.
+pub fn merge(
+	current: &[ScanResult],
+	imported: &[ExportRecord],
+	mode: MergeMode,
+) -> Vec<ScanResult> {
+	let mut imported: Vec<ScanResult> = imported
+		.iter()
+		.map(|record| {
+			(
+				record.offset,
+				NonZeroUsize::new(record.length).unwrap_or(NonZeroUsize::MIN),
+			)
+		})
+		.collect();
+	imported.sort_unstable();
+	imported.dedup_by_key(|(offset, _)| *offset);
+
+	let mut current = current.to_vec();
+	current.sort_unstable();
+	current.dedup_by_key(|(offset, _)| *offset);
+
+	let mut merged = Vec::new();
+	let (mut left, mut right) = (
+		current.into_iter().peekable(),
+		imported.into_iter().peekable(),
+	);
+	loop {
+		let next = match (left.peek(), right.peek()) {
+			(None, None) => break,
+			(Some(_), None) => left.next().filter(|_| mode == MergeMode::Union),
+			(None, Some(_)) => right.next().filter(|_| mode == MergeMode::Union),
+			(Some(l), Some(r)) => match l.0.cmp(&r.0) {
+				std::cmp::Ordering::Less => left.next().filter(|_| mode == MergeMode::Union),
+				std::cmp::Ordering::Greater => right.next().filter(|_| mode == MergeMode::Union),
+				std::cmp::Ordering::Equal => {
+					right.next();
+					left.next()
+				}
+			},
+		};
+
+		merged.extend(next);
+	}
+
+	merged
+}
+
+#[cfg(test)]
+mod test {
+	use std::{collections::HashMap, num::NonZeroUsize, path::PathBuf};
+
+	use procmem_access::{
+		layout::{FieldType, PrimitiveType, Value},
+		platform::mock::MockMemoryAccess,
+		prelude::{MemoryPage, MemoryPagePermissions, MemoryPageType, OffsetType},
+	};
+
+	use super::{collect_records, merge, read_csv, rebase, write_csv, MergeMode};
+
+	#[test]
+	fn test_export_csv() {
+		let mut memory = vec![0u8; 0x20];
+		memory[0x10..0x14].copy_from_slice(&42i32.to_ne_bytes());
+		let mut access =
+			MockMemoryAccess::new().with_region(OffsetType::new_unwrap(0x1000), memory);
+
+		let pages = [MemoryPage {
+			address_range: [
+				OffsetType::new_unwrap(0x1000),
+				OffsetType::new_unwrap(0x1020),
+			],
+			permissions: MemoryPagePermissions::new(true, true, false, false),
+			offset: 0,
+			page_type: MemoryPageType::File(PathBuf::from("/lib/libgame.so")),
+		}];
+		let length = NonZeroUsize::new(4).unwrap();
+		let results = [
+			(OffsetType::new_unwrap(0x1010), length),
+			(OffsetType::new_unwrap(0x5000), length),
+		];
+		let labels = HashMap::from([(OffsetType::new_unwrap(0x1010), "hp, current".to_string())]);
+
+		let records = unsafe {
+			collect_records(
+				&mut access,
+				&pages[..],
+				results,
+				Some(&FieldType::Primitive(PrimitiveType::I32)),
+				&labels,
+			)
+		};
+		assert_eq!(records[0].value, Some(Value::I32(42)));
+		assert_eq!(
+			records[0].module_relative().as_deref(),
+			Some("libgame.so+0x10")
+		);
+		assert_eq!(records[1].value, None);
+
+		let mut csv = Vec::new();
+		write_csv(&mut csv, &records).unwrap();
+		assert_eq!(
+			String::from_utf8(csv).unwrap(),
+			"offset,length,module,module_offset,value,label\n\
+			0x1010,4,libgame.so,0x10,42,\"hp, current\"\n\
+			0x5000,4,,,,\n"
+		);
+
+		#[cfg(feature = "serde")]
+		{
+			let mut json = Vec::new();
+			super::write_json(&mut json, &records).unwrap();
+			let json: serde_json::Value = serde_json::from_slice(&json).unwrap();
+			assert_eq!(json[0]["value"], 42);
+			assert_eq!(json[0]["module_offset"], 0x10);
+			assert!(json[1]["module"].is_null());
+
+			let imported = super::read_json(json.to_string().as_bytes()).unwrap();
+			assert_eq!(imported[0].module, records[0].module);
+			assert_eq!(imported[1].offset, records[1].offset);
+		}
+	}
+
+	#[test]
+	fn test_import_rebase_merge() {
+		let csv = "offset,length,module,module_offset,value,label\n\
+			0x1010,4,libgame.so,0x10,42,\"hp, \"\"current\"\"\"\n\
+			0x5000,4,,,,\n\
+			0x6000,4,gone.so,0x0,,\n";
+		let records = read_csv(csv.as_bytes()).unwrap();
+		assert_eq!(records.len(), 3);
+		assert_eq!(records[0].label.as_deref(), Some("hp, \"current\""));
+		assert_eq!(records[0].module, Some(("libgame.so".to_string(), 0x10)));
+		assert!(read_csv("offset,length\nzero,4\n".as_bytes()).is_err());
+
+		// the module moved from 0x1000 to 0x3000
+		let pages = [MemoryPage {
+			address_range: [
+				OffsetType::new_unwrap(0x3000),
+				OffsetType::new_unwrap(0x4000),
+			],
+			permissions: MemoryPagePermissions::new(true, true, false, false),
+			offset: 0,
+			page_type: MemoryPageType::File(PathBuf::from("/lib/libgame.so")),
+		}];
+		let (rebased, missing) = rebase(records, &pages[..]);
+		assert_eq!(
+			rebased.iter().map(|r| r.offset.get()).collect::<Vec<_>>(),
+			[0x3010, 0x5000]
+		);
+		assert_eq!(missing.len(), 1);
+		assert_eq!(missing[0].offset.get(), 0x6000);
+
+		let length = NonZeroUsize::new(4).unwrap();
+		let current = [
+			(OffsetType::new_unwrap(0x3010), length),
+			(OffsetType::new_unwrap(0x3020), length),
+		];
+		let offsets = |results: Vec<_>| {
+			results
+				.into_iter()
+				.map(|(offset, _): (OffsetType, _)| offset.get())
+				.collect::<Vec<_>>()
+		};
+		assert_eq!(
+			offsets(merge(&current, &rebased, MergeMode::Intersection)),
+			[0x3010]
+		);
+		assert_eq!(
+			offsets(merge(&current, &rebased, MergeMode::Union)),
+			[0x3010, 0x3020, 0x5000]
+		);
+	}
+}
@@ -0,0 +1,472 @@
+//! Group scans for sequences of typed fields, similar to the group scan of Cheat Engine.
+//!
+//! A group is a list of fields, each either an exact value or a wildcard of a given size. Each field may start up to
+//! its `max_gap` bytes after the end of the previous one, so one start offset can match with several layouts.
+//! Predicates only see the offset and length of their candidates, which cannot express this branching, so the
+//! [`GroupPredicate`] matches whole windows of memory instead. Of all layouts starting at an offset, the one placing
+//! each field as early as possible is reported.
+//!
+//! Groups can be parsed from text, see the [`FromStr`](std::str::FromStr) implementation of [`GroupPredicate`]:
+//! ```text
+//! i32:100, f32:?, i16:5 within 64
+//! u32:7, ..16 f64:1.5
+//! ```
+
+use std::num::NonZeroUsize;
+
+use thiserror::Error;
+
+use procmem_access::{
+	memory::chunked::ChunkedReader,
+	prelude::{ErrorKind, MemoryAccess, OffsetType, ProcmemError},
+};
+
+use crate::{
+	predicate::value::{AsRawBytes, ByteComparable},
+	stream::ScanResult,
+};
+
+/// Size of the chunks read by [`GroupPredicate::scan`].
+const SCAN_CHUNK_SIZE: usize = 1024 * 1024;
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum GroupParseError {
+	#[error("group is empty")]
+	Empty,
+	#[error("invalid field \"{field}\"")]
+	InvalidField { field: String },
+	#[error("invalid type in field \"{field}\"")]
+	InvalidType { field: String },
+	#[error("invalid value in field \"{field}\"")]
+	InvalidValue { field: String },
+	#[error("invalid gap in field \"{field}\"")]
+	InvalidGap { field: String },
+	#[error("invalid group length \"{length}\"")]
+	InvalidLength { length: String },
+}
+impl GroupParseError {
+	pub fn kind(&self) -> ErrorKind {
+		ErrorKind::Parse
+	}
+}
+impl From<GroupParseError> for ProcmemError {
+	fn from(err: GroupParseError) -> Self {
+		ProcmemError::new(err.kind(), err)
+	}
+}
+
+/// One field of a [`GroupPredicate`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GroupField {
+	/// Exact bytes of the field, `None` for a wildcard.
+	bytes: Option<Vec<u8>>,
+	size: usize,
+	align: usize,
+	max_gap: usize,
+}
+impl GroupField {
+	/// Creates a field matching the bytes of `value` exactly.
+	///
+	/// Use [`TargetBytes`](crate::predicate::value::TargetBytes) for values in a non-native byte order.
+	pub fn exact<T: ByteComparable + ?Sized>(value: &T) -> Self {
+		let bytes = value.as_bytes().to_vec();
+		assert!(!bytes.is_empty(), "field must not be empty");
+
+		GroupField {
+			size: bytes.len(),
+			align: value.align_of(),
+			bytes: Some(bytes),
+			max_gap: 0,
+		}
+	}
+
+	/// Creates a field matching any value of type `T`.
+	pub fn any<T: AsRawBytes>() -> Self {
+		assert!(std::mem::size_of::<T>() > 0, "field must not be empty");
+
+		GroupField {
+			bytes: None,
+			size: std::mem::size_of::<T>(),
+			align: std::mem::align_of::<T>(),
+			max_gap: 0,
+		}
+	}
+
+	/// Allows this field to start up to `max_gap` bytes after the end of the previous field.
+	///
+	/// The gap of the first field is ignored.
+	pub fn with_max_gap(mut self, max_gap: usize) -> Self {
+		self.max_gap = max_gap;
+		self
+	}
+
+	pub const fn size(&self) -> usize {
+		self.size
+	}
+
+	pub const fn max_gap(&self) -> usize {
+		self.max_gap
+	}
+
+	pub const fn is_wildcard(&self) -> bool {
+		self.bytes.is_none()
+	}
+
+	fn matches(&self, data: &[u8]) -> bool {
+		match self.bytes {
+			None => true,
+			Some(ref bytes) => bytes.as_slice() == data,
+		}
+	}
+}
+
+/// Predicate matching a sequence of fields with optional gaps between them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GroupPredicate {
+	fields: Vec<GroupField>,
+	aligned: bool,
+	max_length: Option<usize>,
+}
+impl GroupPredicate {
+	/// Creates a new predicate.
+	///
+	/// If `aligned` is true then each field only starts at offsets divisible by the alignment of its type.
+	///
+	/// ## Panics
+	/// * If `fields` is empty.
+	pub fn new(fields: Vec<GroupField>, aligned: bool) -> Self {
+		assert!(!fields.is_empty(), "group must not be empty");
+
+		GroupPredicate {
+			fields,
+			aligned,
+			max_length: None,
+		}
+	}
+
+	/// Only matches groups spanning at most `max_length` bytes, from the start of the first field to the end of the last one.
+	pub fn with_max_length(mut self, max_length: usize) -> Self {
+		self.max_length = Some(max_length);
+		self
+	}
+
+	pub fn with_aligned(mut self, aligned: bool) -> Self {
+		self.aligned = aligned;
+		self
+	}
+
+	pub fn fields(&self) -> &[GroupField] {
+		&self.fields
+	}
+
+	/// Returns the maximum number of bytes a match can span.
+	pub fn max_span(&self) -> usize {
+		let span = self.fields[0].size
+			+ self.fields[1..]
+				.iter()
+				.map(|field| field.max_gap + field.size)
+				.sum::<usize>();
+
+		match self.max_length {
+			None => span,
+			Some(max_length) => span.min(max_length),
+		}
+	}
+
+	/// Returns the length of the match starting at the start of `data`, which is located at `offset`.
+	///
+	/// `data` must contain [`max_span`](GroupPredicate::max_span) bytes unless it ends where the memory ends,
+	/// otherwise a match could be missed or reported with a different layout.
+	pub fn match_at(&self, offset: OffsetType, data: &[u8]) -> Option<NonZeroUsize> {
+		let data = &data[..data.len().min(self.max_span())];
+
+		self.place(offset.get(), data, 0, 0)
+			.map(|end| NonZeroUsize::new(end).unwrap())
+	}
+
+	/// Returns all matches in `data`, which is located at `offset`.
+	pub fn find(&self, offset: OffsetType, data: &[u8]) -> Vec<ScanResult> {
+		(0..data.len())
+			.filter_map(|index| {
+				let start = offset.saturating_add(index as u64);
+				self.match_at(start, &data[index..])
+					.map(|length| (start, length))
+			})
+			.collect()
+	}
+
+	/// Scans `ranges` of memory and calls `on_match` with each match in ascending order.
+	///
+	/// Ranges are read in overlapping chunks, so matches crossing chunk boundaries are found.
+	/// The rest of a range is skipped once a read fails.
+	///
+	/// ## Safety
+	/// * See [`MemoryAccess::read`].
+	pub unsafe fn scan<A: MemoryAccess + ?Sized>(
+		&self,
+		access: &mut A,
+		ranges: impl IntoIterator<Item = [OffsetType; 2]>,
+		mut on_match: impl FnMut(ScanResult),
+	) {
+		let span = self.max_span();
+
+		let mut reader = ChunkedReader::new(SCAN_CHUNK_SIZE + span - 1).with_overlap(span - 1);
+		for range in ranges {
+			let _ = reader.read(access, range, |chunk| {
+				let last = chunk.offset.get() + chunk.bytes.len() as u64 == range[1].get();
+
+				// starts without a whole window are repeated at the start of the next chunk
+				for index in 0..chunk.bytes.len() {
+					if !last && chunk.bytes.len() - index < span {
+						break;
+					}
+
+					let start = chunk.offset.saturating_add(index as u64);
+					if let Some(length) = self.match_at(start, &chunk.bytes[index..]) {
+						on_match((start, length));
+					}
+				}
+			});
+		}
+	}
+
+	/// Places field `index` at or after `position` and the following fields after it, returning the end of the last field.
+	fn place(&self, base: u64, data: &[u8], index: usize, position: usize) -> Option<usize> {
+		let field = &self.fields[index];
+		let max_gap = if index == 0 { 0 } else { field.max_gap };
+
+		for start in position..=position + max_gap {
+			let end = start + field.size;
+			if end > data.len() {
+				break;
+			}
+
+			if self.aligned && !(base + start as u64).is_multiple_of(field.align as u64) {
+				continue;
+			}
+
+			if !field.matches(&data[start..end]) {
+				continue;
+			}
+
+			if index + 1 == self.fields.len() {
+				return Some(end);
+			}
+			if let Some(end) = self.place(base, data, index + 1, end) {
+				return Some(end);
+			}
+		}
+
+		None
+	}
+}
+impl std::str::FromStr for GroupPredicate {
+	type Err = GroupParseError;
+
+	/// Parses comma separated fields, optionally followed by `within <length>`.
+	///
+	/// Each field is `type:value` or `type:?` for a wildcard, where type is one of `u8`, `i8`, `u16`, `i16`, `u32`, `i32`,
+	/// `u64`, `i64`, `f32` and `f64`. Values are in the native byte order. A field prefixed with `..<gap>` may start up to
+	/// `gap` bytes after the previous field, for example `..8 f32:?`.
+	///
+	/// The parsed predicate is unaligned, see [`with_aligned`](GroupPredicate::with_aligned).
+	fn from_str(string: &str) -> Result<Self, Self::Err> {
+		let (fields_str, max_length) = match string.rsplit_once("within") {
+			None => (string, None),
+			Some((fields_str, length)) => {
+				let length = length.trim();
+				let max_length = length
+					.trim_end_matches("bytes")
+					.trim()
+					.parse::<usize>()
+					.map_err(|_| GroupParseError::InvalidLength {
+						length: length.to_string(),
+					})?;
+
+				(fields_str, Some(max_length))
+			}
+		};
+
+		let mut fields = Vec::new();
+		for field_str in fields_str.split(',').map(str::trim) {
+			if field_str.is_empty() {
+				continue;
+			}
+
+			let field = parse_field(field_str)?;
+			if fields.is_empty() && field.max_gap != 0 {
+				return Err(GroupParseError::InvalidGap {
+					field: field_str.to_string(),
+				});
+			}
+			fields.push(field);
+		}
+
+		if fields.is_empty() {
+			return Err(GroupParseError::Empty);
+		}
+
+		let predicate = GroupPredicate::new(fields, false);
+		Ok(match max_length {
+			None => predicate,
+			Some(max_length) => predicate.with_max_length(max_length),
+		})
+	}
+}
+
+fn parse_field(field_str: &str) -> Result<GroupField, GroupParseError> {
+	let invalid_field = || GroupParseError::InvalidField {
+		field: field_str.to_string(),
+	};
+
+	let mut tokens = field_str.split_whitespace();
+	let mut token = tokens.next().ok_or_else(invalid_field)?;
+
+	let mut max_gap = 0;
+	if let Some(gap) = token.strip_prefix("..") {
+		max_gap = gap.parse().map_err(|_| GroupParseError::InvalidGap {
+			field: field_str.to_string(),
+		})?;
+		token = tokens.next().ok_or_else(invalid_field)?;
+	}
+	if tokens.next().is_some() {
+		return Err(invalid_field());
+	}
+
+	let (type_str, value_str) = token.split_once(':').ok_or_else(invalid_field)?;
+
+	macro_rules! parse_typed {
+		(
+			$( $name: literal => $scalar_type: ty ),+
+		) => {
+			match type_str {
+				$(
+					$name => if value_str == "?" {
+						GroupField::any::<$scalar_type>()
+					} else {
+						let value = value_str.parse::<$scalar_type>().map_err(|_| GroupParseError::InvalidValue {
+							field: field_str.to_string(),
+						})?;
+
+						GroupField::exact(&value)
+					},
+				)+
+				_ => return Err(GroupParseError::InvalidType {
+					field: field_str.to_string(),
+				}),
+			}
+		};
+	}
+	let field = parse_typed!(
+		"u8" => u8, "i8" => i8, "u16" => u16, "i16" => i16, "u32" => u32, "i32" => i32,
+		"u64" => u64, "i64" => i64, "f32" => f32, "f64" => f64
+	);
+
+	Ok(field.with_max_gap(max_gap))
+}
+
+#[cfg(test)]
+mod test {
+	use procmem_access::{platform::mock::MockMemoryAccess, prelude::OffsetType};
+
+	use super::{GroupField, GroupParseError, GroupPredicate};
+	use crate::predicate::value::ByteComparable;
+
+	fn group_data() -> Vec<u8> {
+		let mut data = vec![0u8; 64];
+		data[8..12].copy_from_slice(100i32.as_bytes());
+		data[12..16].copy_from_slice(2.5f32.as_bytes());
+		// 4 bytes of padding
+		data[20..22].copy_from_slice(5i16.as_bytes());
+
+		data
+	}
+
+	#[test]
+	fn test_group_predicate_gaps() {
+		let data = group_data();
+		let base = OffsetType::new_unwrap(0x1000);
+
+		let packed = GroupPredicate::new(
+			vec![
+				GroupField::exact(&100i32),
+				GroupField::any::<f32>(),
+				GroupField::exact(&5i16),
+			],
+			true,
+		);
+		assert_eq!(packed.find(base, &data), []);
+
+		let fields = vec![
+			GroupField::exact(&100i32),
+			GroupField::any::<f32>(),
+			GroupField::exact(&5i16).with_max_gap(8),
+		];
+		let predicate = GroupPredicate::new(fields.clone(), true);
+		assert_eq!(predicate.max_span(), 18);
+		let found: Vec<_> = predicate
+			.find(base, &data)
+			.into_iter()
+			.map(|(offset, length)| (offset.get() - 0x1000, length.get()))
+			.collect();
+		assert_eq!(found, [(8, 14)]);
+
+		// the whole group does not fit
+		let predicate = GroupPredicate::new(fields, true).with_max_length(12);
+		assert_eq!(predicate.find(base, &data), []);
+
+		// aligned fields cannot start at odd offsets
+		let predicate = GroupPredicate::new(
+			vec![GroupField::exact(&100i32), GroupField::exact(&5i16)],
+			true,
+		);
+		let mut shifted = vec![0u8];
+		shifted.extend_from_slice(100i32.as_bytes());
+		shifted.extend_from_slice(5i16.as_bytes());
+		assert_eq!(predicate.find(base, &shifted), []);
+		assert_eq!(predicate.with_aligned(false).find(base, &shifted).len(), 1);
+	}
+
+	#[test]
+	fn test_group_predicate_parse_scan() {
+		let predicate: GroupPredicate =
+			"i32:100, f32:?, ..8 i16:5 within 64 bytes".parse().unwrap();
+		assert_eq!(predicate.fields().len(), 3);
+		assert!(predicate.fields()[1].is_wildcard());
+		assert_eq!(predicate.fields()[2].max_gap(), 8);
+
+		assert_eq!("".parse::<GroupPredicate>(), Err(GroupParseError::Empty));
+		assert!(matches!(
+			"..4 i32:1".parse::<GroupPredicate>(),
+			Err(GroupParseError::InvalidGap { .. })
+		));
+		assert!(matches!(
+			"i24:1".parse::<GroupPredicate>(),
+			Err(GroupParseError::InvalidType { .. })
+		));
+		assert!(matches!(
+			"u8:256".parse::<GroupPredicate>(),
+			Err(GroupParseError::InvalidValue { .. })
+		));
+
+		// matches across chunk boundaries are found once
+		let mut data = vec![0u8; super::SCAN_CHUNK_SIZE * 2];
+		let group = group_data();
+		let boundary = super::SCAN_CHUNK_SIZE - 12;
+		data[boundary..boundary + group.len()].copy_from_slice(&group);
+
+		let base = OffsetType::new_unwrap(0x1000);
+		let mut access = MockMemoryAccess::new().with_region(base, data.clone());
+		let mut found = Vec::new();
+		unsafe {
+			predicate.scan(
+				&mut access,
+				[[base, base.saturating_add(data.len() as u64)]],
+				|result| found.push(result),
+			)
+		};
+		assert_eq!(found, predicate.find(base, &data));
+		assert_eq!(found.len(), 1);
+		assert_eq!(found[0].0.get() - 0x1000, boundary as u64 + 8);
+	}
+}
@@ -1,5 +1,25 @@
+// Allows the derive macros to refer to `::procmem_scan` from within this crate.
+extern crate self as procmem_scan;
+
+pub mod analysis;
 pub mod candidate;
+pub mod checkpoint;
+pub mod driver;
+pub mod export;
+pub mod group;
+pub mod parallel;
+pub mod pattern;
 pub mod predicate;
+pub mod progress;
+#[cfg(feature = "regex")]
+pub mod regex;
+pub mod results;
+pub mod session;
+pub mod snapshot;
 pub mod stream;
+#[cfg(feature = "serde")]
+pub mod table;
+pub mod targets;
+pub mod tracker;
 
 pub mod prelude;
@@ -0,0 +1,257 @@
+//! Scanning memory on multiple threads.
+//!
+//! The [`ParallelScanner`] splits memory ranges into chunks which are handed out to worker threads as they become free.
+//! Each worker runs a partial scan on its chunks, so matches crossing chunk boundaries are left as partial candidates
+//! which are merged and resolved once all workers finish.
+
+use std::{
+	sync::{
+		atomic::{AtomicUsize, Ordering},
+		Arc,
+	},
+	thread,
+};
+
+use procmem_access::{
+	prelude::{MemoryAccess, OffsetType},
+	util::KMergeIter,
+};
+
+use crate::{
+	driver::{BufferPool, ScanConfig, ScanDriverError},
+	predicate::PartialScannerPredicate,
+	stream::{ScanResult, StreamScanner},
+};
+
+/// Scans memory ranges on multiple worker threads.
+///
+/// Uses the [`chunk_size`](ScanConfig::chunk_size) and [`skip_unreadable`](ScanConfig::skip_unreadable) options of the config,
+/// the [throttle](ScanConfig::throttle) is not applied.
+pub struct ParallelScanner {
+	config: ScanConfig,
+	pool: Arc<BufferPool>,
+}
+impl ParallelScanner {
+	/// Creates a new scanner with its own buffer pool.
+	pub fn new(config: ScanConfig) -> Self {
+		let pool = Arc::new(BufferPool::new(config.chunk_size));
+
+		ParallelScanner { config, pool }
+	}
+
+	/// Creates a new scanner which shares the `pool` with other scanners or drivers.
+	///
+	/// The chunk size is taken from the pool buffer size.
+	pub fn with_pool(config: ScanConfig, pool: Arc<BufferPool>) -> Self {
+		ParallelScanner {
+			config: ScanConfig {
+				chunk_size: pool.buffer_size(),
+				..config
+			},
+			pool,
+		}
+	}
+
+	pub const fn config(&self) -> &ScanConfig {
+		&self.config
+	}
+
+	pub const fn pool(&self) -> &Arc<BufferPool> {
+		&self.pool
+	}
+
+	/// Scans `ranges` of memory using `predicate`, spawning one worker thread for each of the `accesses`.
+	///
+	/// The accesses should all refer to the same process. Returns the matches sorted by offset.
+	///
+	/// Matches spanning chunk boundaries are found as long as the ranges are adjacent. When a read fails with
+	/// [`skip_unreadable`](ScanConfig::skip_unreadable) set, only the failed chunk is skipped, otherwise the first error is returned.
+	///
+	/// Panics if `accesses` is empty.
+	///
+	/// ## Safety
+	/// * See [`MemoryAccess::read`].
+	pub unsafe fn scan<A: MemoryAccess + Send, P: PartialScannerPredicate + Sync>(
+		&self,
+		accesses: &mut [A],
+		ranges: impl IntoIterator<Item = [OffsetType; 2]>,
+		predicate: &P,
+	) -> Result<impl Iterator<Item = ScanResult>, ScanDriverError> {
+		assert!(!accesses.is_empty(), "at least one access is required");
+
+		let chunks = self.chunks(ranges);
+		let next_chunk = AtomicUsize::new(0);
+
+		let workers: Vec<_> = thread::scope(|scope| {
+			let handles: Vec<_> = accesses
+				.iter_mut()
+				.map(|access| {
+					let (chunks, next_chunk) = (&chunks, &next_chunk);
+
+					scope.spawn(move || {
+						// SAFETY: the caller upholds the safety contract of `read`
+						let result = unsafe { self.worker(access, chunks, next_chunk, predicate) };
+						if result.is_err() {
+							// stop the other workers early
+							next_chunk.store(chunks.len(), Ordering::Relaxed);
+						}
+
+						result
+					})
+				})
+				.collect();
+
+			handles
+				.into_iter()
+				.map(|handle| match handle.join() {
+					Ok(result) => result,
+					Err(panic) => std::panic::resume_unwind(panic),
+				})
+				.collect()
+		});
+
+		let mut merged = StreamScanner::new(predicate);
+		let mut found = Vec::with_capacity(workers.len() + 1);
+		for worker in workers {
+			let (scanner, mut worker_found) = worker?;
+
+			merged.merge_partial_mut(scanner);
+			// workers take chunks in order, but matches may resolve out of order within a chunk
+			worker_found.sort_unstable();
+			found.push(worker_found);
+		}
+		let mut resolved: Vec<ScanResult> = merged.resolve_partial().collect();
+		resolved.sort_unstable();
+		found.push(resolved);
+
+		let mut found: Vec<ScanResult> =
+			KMergeIter::new(found.into_iter().map(Vec::into_iter)).collect();
+		found.dedup();
+
+		Ok(found.into_iter())
+	}
+
+	/// Splits `ranges` into chunks of at most [`chunk_size`](ScanConfig::chunk_size) bytes.
+	fn chunks(&self, ranges: impl IntoIterator<Item = [OffsetType; 2]>) -> Vec<[OffsetType; 2]> {
+		let chunk_size = self.config.chunk_size as u64;
+
+		ranges
+			.into_iter()
+			.flat_map(|[start, end]| {
+				OffsetType::iter_stride(start, end, chunk_size).map(move |chunk_start| {
+					[
+						chunk_start,
+						OffsetType::new_unwrap(end.get().min(chunk_start.get() + chunk_size)),
+					]
+				})
+			})
+			.collect()
+	}
+
+	/// Scans chunks taken from `chunks` until none are left, returning the scanner with partial candidates and the resolved matches.
+	unsafe fn worker<'p, A: MemoryAccess, P: PartialScannerPredicate>(
+		&self,
+		access: &mut A,
+		chunks: &[[OffsetType; 2]],
+		next_chunk: &AtomicUsize,
+		predicate: &'p P,
+	) -> Result<(StreamScanner<&'p P>, Vec<ScanResult>), ScanDriverError> {
+		let mut scanner = StreamScanner::new(predicate);
+		let mut found = Vec::new();
+		let mut buffer = self.pool.take();
+
+		let result = (|| {
+			while let Some(&[start, end]) = chunks.get(next_chunk.fetch_add(1, Ordering::Relaxed)) {
+				let chunk = &mut buffer[..(end.get() - start.get()) as usize];
+
+				if let Err(source) = access.read(start, chunk) {
+					if self.config.skip_unreadable {
+						continue;
+					}
+
+					return Err(ScanDriverError::Read {
+						range: [start, end],
+						source,
+					});
+				}
+
+				found.extend(scanner.scan_partial(start, chunk.iter().copied()));
+			}
+
+			Ok(())
+		})();
+
+		self.pool.put(buffer);
+
+		result.map(|()| (scanner, found))
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use std::convert::TryInto;
+
+	use procmem_access::{platform::mock::MockMemoryAccess, prelude::OffsetType};
+
+	use super::ParallelScanner;
+	use crate::{
+		driver::{ScanConfig, ScanDriver},
+		predicate::value::ValuePredicate,
+	};
+
+	#[test]
+	fn test_parallel_scan() {
+		let base = OffsetType::new_unwrap(0x1000);
+		let mut data = vec![0u8; 256];
+		for at in [6, 30, 61, 128, 250] {
+			data[at..at + 4].copy_from_slice(&[1, 2, 3, 4]);
+		}
+		let access = MockMemoryAccess::new()
+			.with_region(base, data[..128].to_vec())
+			.with_region(OffsetType::new_unwrap(0x1080), data[128..].to_vec());
+		let ranges = [
+			[base, OffsetType::new_unwrap(0x1080)],
+			[
+				OffsetType::new_unwrap(0x1080),
+				OffsetType::new_unwrap(0x1100),
+			],
+		];
+
+		let config = ScanConfig {
+			chunk_size: 8,
+			..Default::default()
+		};
+		let predicate = ValuePredicate::new([1u8, 2, 3, 4], false);
+
+		let mut expected = Vec::new();
+		unsafe {
+			ScanDriver::new(config.clone())
+				.scan(&mut access.clone(), ranges, &predicate, |result| {
+					expected.push(result)
+				})
+				.unwrap();
+		}
+		assert_eq!(expected.len(), 5);
+		assert_eq!(
+			expected[2],
+			(OffsetType::new_unwrap(0x103D), 4.try_into().unwrap())
+		);
+
+		let scanner = ParallelScanner::new(config);
+		let mut accesses = vec![access.clone(); 3];
+		let found: Vec<_> = unsafe { scanner.scan(&mut accesses, ranges, &predicate) }
+			.unwrap()
+			.collect();
+		assert_eq!(found, expected);
+		assert!(scanner.pool().available() >= 1);
+
+		let mut accesses = vec![
+			access.with_fault([
+				OffsetType::new_unwrap(0x1040),
+				OffsetType::new_unwrap(0x1041)
+			]);
+			2
+		];
+		assert!(unsafe { scanner.scan(&mut accesses, ranges, &predicate) }.is_err());
+	}
+}
@@ -0,0 +1,364 @@
+//! Byte patterns with wildcards, also known as array-of-bytes (AOB) signatures.
+//!
+//! Patterns are written as whitespace separated tokens, see the [`FromStr`](std::str::FromStr) implementation of [`BytePattern`]:
+//! ```text
+//! 48 8B ?? C3
+//! 0x12 0x34 .. 0x56
+//! 488B??C3 "key=\x00"
+//! ```
+
+use std::{iter::Peekable, str::CharIndices};
+
+use thiserror::Error;
+
+use procmem_access::{
+	memory::chunked::ChunkedReader,
+	prelude::{ErrorKind, MemoryAccess, OffsetType, ProcmemError},
+};
+
+/// Size of the chunks read by [`BytePattern::scan`].
+const SCAN_CHUNK_SIZE: usize = 1024 * 1024;
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum BytePatternParseError {
+	#[error("pattern is empty")]
+	Empty,
+	#[error("invalid token \"{token}\" at position {position}")]
+	InvalidToken { position: usize, token: String },
+	#[error("unterminated string starting at position {position}")]
+	UnterminatedString { position: usize },
+	#[error("invalid escape \"{escape}\" at position {position}")]
+	InvalidEscape { position: usize, escape: String },
+}
+impl BytePatternParseError {
+	pub fn kind(&self) -> ErrorKind {
+		ErrorKind::Parse
+	}
+}
+impl From<BytePatternParseError> for ProcmemError {
+	fn from(err: BytePatternParseError) -> Self {
+		ProcmemError::new(err.kind(), err)
+	}
+}
+
+/// Sequence of bytes where some positions match any byte.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct BytePattern {
+	bytes: Vec<Option<u8>>,
+}
+impl BytePattern {
+	/// Creates a pattern from bytes, `None` being a wildcard.
+	pub fn new(bytes: Vec<Option<u8>>) -> Self {
+		BytePattern { bytes }
+	}
+
+	/// Creates a pattern from `bytes` and `mask`, where `false` in the mask is a wildcard.
+	pub fn from_masked(bytes: &[u8], mask: &[bool]) -> Self {
+		assert_eq!(bytes.len(), mask.len());
+
+		BytePattern {
+			bytes: bytes
+				.iter()
+				.zip(mask.iter())
+				.map(|(&byte, &exact)| if exact { Some(byte) } else { None })
+				.collect(),
+		}
+	}
+
+	pub fn bytes(&self) -> &[Option<u8>] {
+		&self.bytes
+	}
+
+	pub fn len(&self) -> usize {
+		self.bytes.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.bytes.is_empty()
+	}
+
+	pub fn push(&mut self, byte: Option<u8>) {
+		self.bytes.push(byte);
+	}
+
+	/// Removes trailing wildcards, which never change what the pattern matches (apart from the length).
+	pub fn trim_end(&mut self) {
+		while let Some(None) = self.bytes.last() {
+			self.bytes.pop();
+		}
+	}
+
+	/// Returns whether `data` starts with bytes matching this pattern.
+	pub fn matches(&self, data: &[u8]) -> bool {
+		data.len() >= self.bytes.len()
+			&& self
+				.bytes
+				.iter()
+				.zip(data.iter())
+				.all(|(pattern, byte)| pattern.map(|p| p == *byte).unwrap_or(true))
+	}
+
+	/// Returns an iterator over positions in `data` where this pattern matches.
+	pub fn find_iter<'a>(&'a self, data: &'a [u8]) -> impl Iterator<Item = usize> + 'a {
+		(0..data.len()).filter(move |&i| self.matches(&data[i..]))
+	}
+
+	/// Scans `ranges` of memory and calls `on_match` with the offset of each match in ascending order.
+	///
+	/// Ranges are read in overlapping chunks, so matches crossing chunk boundaries are found.
+	/// The rest of a range is skipped once a read fails.
+	///
+	/// ## Safety
+	/// * See [`MemoryAccess::read`].
+	pub unsafe fn scan<A: MemoryAccess + ?Sized>(
+		&self,
+		access: &mut A,
+		ranges: impl IntoIterator<Item = [OffsetType; 2]>,
+		mut on_match: impl FnMut(OffsetType),
+	) {
+		if self.bytes.is_empty() {
+			return;
+		}
+
+		let mut reader = ChunkedReader::new(SCAN_CHUNK_SIZE + self.bytes.len() - 1)
+			.with_overlap(self.bytes.len() - 1);
+		for range in ranges {
+			let _ = reader.read(access, range, |chunk| {
+				for index in self.find_iter(chunk.bytes) {
+					if !chunk.is_repeated(index, self.bytes.len()) {
+						on_match(chunk.offset.saturating_add(index as u64));
+					}
+				}
+			});
+		}
+	}
+}
+impl std::fmt::Display for BytePattern {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		for (i, byte) in self.bytes.iter().enumerate() {
+			if i != 0 {
+				write!(f, " ")?;
+			}
+
+			match byte {
+				Some(byte) => write!(f, "{:02X}", byte)?,
+				None => write!(f, "??")?,
+			}
+		}
+
+		Ok(())
+	}
+}
+
+impl std::str::FromStr for BytePattern {
+	type Err = BytePatternParseError;
+
+	/// Parses a pattern from whitespace separated tokens.
+	///
+	/// * `48`, `0x48` - one hexadecimal byte
+	/// * `?`, `??`, `..` - wildcard
+	/// * `488B??C3` - packed pairs of hexadecimal bytes and wildcards
+	/// * `"text"` - exact bytes of the UTF-8 text, supporting the escapes `\\`, `\"`, `\n`, `\r`, `\t`, `\0` and `\xHH`
+	///
+	/// Positions in errors are byte offsets into `string`. The output of `Display` is always accepted.
+	fn from_str(string: &str) -> Result<Self, Self::Err> {
+		let mut bytes = Vec::new();
+
+		let mut chars = string.char_indices().peekable();
+		while let Some(&(position, ch)) = chars.peek() {
+			if ch.is_whitespace() {
+				chars.next();
+				continue;
+			}
+
+			if ch == '"' {
+				chars.next();
+				parse_quoted(position, &mut chars, &mut bytes)?;
+				continue;
+			}
+
+			let mut end = string.len();
+			while let Some(&(index, ch)) = chars.peek() {
+				if ch.is_whitespace() || ch == '"' {
+					end = index;
+					break;
+				}
+				chars.next();
+			}
+			parse_token(position, &string[position..end], &mut bytes)?;
+		}
+
+		if bytes.is_empty() {
+			return Err(BytePatternParseError::Empty);
+		}
+
+		Ok(BytePattern::new(bytes))
+	}
+}
+
+fn parse_token(
+	position: usize,
+	token: &str,
+	bytes: &mut Vec<Option<u8>>,
+) -> Result<(), BytePatternParseError> {
+	let invalid = || BytePatternParseError::InvalidToken {
+		position,
+		token: token.to_string(),
+	};
+	let parse_hex = |digits: &str| {
+		if digits.is_empty() || !digits.chars().all(|ch| ch.is_ascii_hexdigit()) {
+			return Err(invalid());
+		}
+
+		u8::from_str_radix(digits, 16).map_err(|_| invalid())
+	};
+
+	match token {
+		"?" | "??" | ".." => bytes.push(None),
+		token if token.starts_with("0x") || token.starts_with("0X") => {
+			bytes.push(Some(parse_hex(&token[2..])?))
+		}
+		token if token.len() <= 2 => bytes.push(Some(parse_hex(token)?)),
+		token => {
+			if !token.is_ascii() || token.len() % 2 != 0 {
+				return Err(invalid());
+			}
+
+			for pair in token.as_bytes().chunks(2) {
+				match pair {
+					b"??" => bytes.push(None),
+					// ascii checked above
+					pair => bytes.push(Some(parse_hex(std::str::from_utf8(pair).unwrap())?)),
+				}
+			}
+		}
+	}
+
+	Ok(())
+}
+
+/// Parses a quoted string whose opening quote at `start` was already consumed.
+fn parse_quoted(
+	start: usize,
+	chars: &mut Peekable<CharIndices>,
+	bytes: &mut Vec<Option<u8>>,
+) -> Result<(), BytePatternParseError> {
+	let mut encoded = [0u8; 4];
+	loop {
+		let (position, ch) = chars
+			.next()
+			.ok_or(BytePatternParseError::UnterminatedString { position: start })?;
+
+		let byte = match ch {
+			'"' => return Ok(()),
+			'\\' => {
+				let invalid = |escape: &str| BytePatternParseError::InvalidEscape {
+					position,
+					escape: escape.to_string(),
+				};
+
+				match chars.next().map(|(_, ch)| ch) {
+					Some('\\') => b'\\',
+					Some('"') => b'"',
+					Some('n') => b'\n',
+					Some('r') => b'\r',
+					Some('t') => b'\t',
+					Some('0') => 0,
+					Some('x') => {
+						let digits: String = (0..2)
+							.filter_map(|_| chars.next())
+							.map(|(_, ch)| ch)
+							.collect();
+						match u8::from_str_radix(&digits, 16) {
+							Ok(byte) if digits.chars().all(|ch| ch.is_ascii_hexdigit()) => byte,
+							_ => return Err(invalid(&format!("\\x{}", digits))),
+						}
+					}
+					Some(ch) => return Err(invalid(&format!("\\{}", ch))),
+					None => {
+						return Err(BytePatternParseError::UnterminatedString { position: start })
+					}
+				}
+			}
+			ch => {
+				bytes.extend(ch.encode_utf8(&mut encoded).bytes().map(Some));
+				continue;
+			}
+		};
+		bytes.push(Some(byte));
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::{BytePattern, BytePatternParseError};
+
+	#[test]
+	fn test_byte_pattern_matches() {
+		let mut pattern =
+			BytePattern::from_masked(&[0x48, 0x8B, 0, 0xC3, 0], &[true, true, false, true, false]);
+		assert_eq!(pattern.to_string(), "48 8B ?? C3 ??");
+
+		pattern.trim_end();
+		assert_eq!(pattern.len(), 4);
+
+		let data = [0x00, 0x48, 0x8B, 0x11, 0xC3, 0x48, 0x8B, 0x22, 0xC3];
+		assert_eq!(pattern.find_iter(&data).collect::<Vec<_>>(), [1, 5]);
+		assert!(!pattern.matches(&data[5..7]));
+	}
+
+	#[test]
+	fn test_byte_pattern_parse() {
+		let pattern: BytePattern = "48 8b ?? C3".parse().unwrap();
+		assert_eq!(pattern.to_string(), "48 8B ?? C3");
+		assert_eq!(pattern.to_string().parse::<BytePattern>().unwrap(), pattern);
+
+		assert_eq!(
+			"0x12 0x34 .. 0x56".parse::<BytePattern>().unwrap().bytes(),
+			[Some(0x12), Some(0x34), None, Some(0x56)]
+		);
+		assert_eq!(
+			"488B??C3".parse::<BytePattern>().unwrap(),
+			"48 8B ? C3".parse().unwrap()
+		);
+		assert_eq!(
+			r#"01"a \"b\"\x00"?"#.parse::<BytePattern>().unwrap(),
+			BytePattern::new(
+				[
+					Some(1),
+					Some(b'a'),
+					Some(b' '),
+					Some(b'"'),
+					Some(b'b'),
+					Some(b'"'),
+					Some(0),
+					None
+				]
+				.to_vec()
+			)
+		);
+
+		assert_eq!(
+			"  ".parse::<BytePattern>(),
+			Err(BytePatternParseError::Empty)
+		);
+		assert_eq!(
+			"48 8G".parse::<BytePattern>(),
+			Err(BytePatternParseError::InvalidToken {
+				position: 3,
+				token: "8G".into()
+			})
+		);
+		assert_eq!(
+			"48 \"abc".parse::<BytePattern>(),
+			Err(BytePatternParseError::UnterminatedString { position: 3 })
+		);
+		assert_eq!(
+			r#""a\q""#.parse::<BytePattern>(),
+			Err(BytePatternParseError::InvalidEscape {
+				position: 2,
+				escape: "\\q".into()
+			})
+		);
+	}
+}
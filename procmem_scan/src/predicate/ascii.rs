@@ -0,0 +1,237 @@
+use std::num::NonZeroUsize;
+
+use procmem_access::prelude::OffsetType;
+
+use crate::{
+	candidate::{ScannerCandidate, CANDIDATE_RECORDED_BYTES},
+	predicate::{ScannerPredicate, UpdateCandidateResult},
+};
+
+/// Radix of numbers stored as text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AsciiRadix {
+	Decimal,
+	/// Hexadecimal digits, matched case-insensitively.
+	Hex,
+}
+
+/// Predicate scanning for a number stored as ASCII text, such as a score kept as `"12500"`.
+///
+/// The predicate matches a set of encodings of the value, see [`encodings`](AsciiNumberPredicate::encodings).
+/// Bytes around the match are not inspected, so `"12500"` is also found inside `"112500"`.
+pub struct AsciiNumberPredicate {
+	radix: AsciiRadix,
+	encodings: Vec<Vec<u8>>,
+}
+impl AsciiNumberPredicate {
+	/// Creates a new predicate matching the plain encoding of `value`, with a leading `-` for negative values.
+	pub fn new(value: i64, radix: AsciiRadix) -> Self {
+		let text = match (radix, value < 0) {
+			(AsciiRadix::Decimal, _) => value.to_string(),
+			(AsciiRadix::Hex, false) => format!("{:x}", value),
+			(AsciiRadix::Hex, true) => format!("-{:x}", value.unsigned_abs()),
+		};
+
+		AsciiNumberPredicate {
+			radix,
+			encodings: vec![text.into_bytes()],
+		}
+	}
+
+	/// Also matches non-negative values written with an explicit `+` sign.
+	pub fn with_plus_sign(mut self) -> Self {
+		let signed: Vec<Vec<u8>> = self
+			.encodings
+			.iter()
+			.filter(|encoding| encoding.first() != Some(&b'-'))
+			.map(|encoding| [b"+".as_slice(), encoding].concat())
+			.collect();
+		self.add_encodings(signed);
+
+		self
+	}
+
+	/// Also matches hexadecimal encodings written with a `0x` prefix, after the sign.
+	///
+	/// Has no effect on decimal predicates.
+	pub fn with_hex_prefix(mut self) -> Self {
+		if self.radix != AsciiRadix::Hex {
+			return self;
+		}
+
+		let prefixed: Vec<Vec<u8>> = self
+			.encodings
+			.iter()
+			.map(|encoding| {
+				let (sign, digits) = split_sign(encoding);
+				[sign, b"0x".as_slice(), digits].concat()
+			})
+			.collect();
+		self.add_encodings(prefixed);
+
+		self
+	}
+
+	/// Also matches the current encodings left-padded with `pad` to exactly `width` bytes.
+	///
+	/// Zero padding is placed after the sign and prefix (`-0012500`), any other padding before them (`  -12500`).
+	pub fn with_padding(mut self, width: usize, pad: u8) -> Self {
+		let padded: Vec<Vec<u8>> = self
+			.encodings
+			.iter()
+			.filter(|encoding| encoding.len() < width)
+			.map(|encoding| {
+				let padding = vec![pad; width - encoding.len()];
+				if pad != b'0' {
+					return [padding.as_slice(), encoding].concat();
+				}
+
+				let (sign, rest) = split_sign(encoding);
+				let (prefix, digits) = match rest.strip_prefix(b"0x") {
+					Some(digits) => (b"0x".as_slice(), digits),
+					None => (b"".as_slice(), rest),
+				};
+				[sign, prefix, &padding, digits].concat()
+			})
+			.collect();
+		self.add_encodings(padded);
+
+		self
+	}
+
+	/// Returns all encodings matched by this predicate.
+	pub fn encodings(&self) -> &[Vec<u8>] {
+		&self.encodings
+	}
+
+	fn add_encodings(&mut self, encodings: Vec<Vec<u8>>) {
+		for encoding in encodings {
+			if !self.encodings.contains(&encoding) {
+				self.encodings.push(encoding);
+			}
+		}
+	}
+
+	fn byte_eq(&self, expected: u8, byte: u8) -> bool {
+		match self.radix {
+			AsciiRadix::Decimal => expected == byte,
+			AsciiRadix::Hex => expected.eq_ignore_ascii_case(&byte),
+		}
+	}
+
+	/// Returns encodings which start with the bytes recorded by `candidate`.
+	///
+	/// Encodings which only differ after the recorded bytes are differing in letter case only, which is ignored anyway.
+	fn consistent_encodings<'a>(
+		&'a self,
+		candidate: &'a ScannerCandidate,
+	) -> impl Iterator<Item = &'a [u8]> + 'a {
+		let recorded = candidate.bytes();
+		debug_assert!(recorded.len() == candidate.length().get().min(CANDIDATE_RECORDED_BYTES));
+
+		self.encodings
+			.iter()
+			.filter(move |encoding| {
+				encoding.len() > candidate.length().get()
+					&& encoding
+						.iter()
+						.zip(recorded)
+						.all(|(expected, byte)| self.byte_eq(*expected, *byte))
+			})
+			.map(|encoding| encoding.as_slice())
+	}
+}
+impl ScannerPredicate for AsciiNumberPredicate {
+	fn try_start_candidate(&self, offset: OffsetType, byte: u8) -> Option<ScannerCandidate> {
+		let mut starting = self
+			.encodings
+			.iter()
+			.filter(|encoding| self.byte_eq(encoding[0], byte));
+
+		let first = starting.next()?;
+		if first.len() == 1 && starting.all(|encoding| encoding.len() == 1) {
+			return Some(ScannerCandidate::resolved(
+				offset,
+				NonZeroUsize::new(1).unwrap(),
+			));
+		}
+
+		Some(ScannerCandidate::normal(offset))
+	}
+
+	fn update_candidate(
+		&self,
+		_offset: OffsetType,
+		byte: u8,
+		candidate: &ScannerCandidate,
+	) -> UpdateCandidateResult {
+		let position = candidate.length().get();
+
+		let mut advance = false;
+		for encoding in self.consistent_encodings(candidate) {
+			if !self.byte_eq(encoding[position], byte) {
+				continue;
+			}
+
+			if encoding.len() == position + 1 {
+				return UpdateCandidateResult::Resolve;
+			}
+			advance = true;
+		}
+
+		if advance {
+			UpdateCandidateResult::Advance
+		} else {
+			UpdateCandidateResult::Remove
+		}
+	}
+}
+
+fn split_sign(encoding: &[u8]) -> (&[u8], &[u8]) {
+	match encoding.first() {
+		Some(b'-' | b'+') => encoding.split_at(1),
+		_ => (b"", encoding),
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use procmem_access::prelude::OffsetType;
+
+	use super::{AsciiNumberPredicate, AsciiRadix};
+	use crate::stream::StreamScanner;
+
+	#[test]
+	fn test_ascii_number_predicate() {
+		let predicate = AsciiNumberPredicate::new(-0x2a, AsciiRadix::Hex)
+			.with_hex_prefix()
+			.with_padding(6, b'0')
+			.with_padding(8, b' ');
+		assert_eq!(
+			predicate.encodings(),
+			[
+				b"-2a".to_vec(),
+				b"-0x2a".to_vec(),
+				b"-0002a".to_vec(),
+				b"-0x02a".to_vec(),
+				b"     -2a".to_vec(),
+				b"   -0x2a".to_vec(),
+				b"  -0002a".to_vec(),
+				b"  -0x02a".to_vec(),
+			]
+		);
+
+		let data = b"score:12500;hp:+012500;x=  12500\x00125";
+		let predicate = AsciiNumberPredicate::new(12500, AsciiRadix::Decimal)
+			.with_plus_sign()
+			.with_padding(7, b'0')
+			.with_padding(7, b' ');
+
+		let mut scanner = StreamScanner::new(predicate);
+		let found: Vec<(u64, usize)> = scanner
+			.scan_once(OffsetType::new_unwrap(1), data.iter().copied())
+			.map(|(offset, length)| (offset.get(), length.get()))
+			.collect();
+		assert_eq!(found, [(7, 5), (16, 7), (18, 5), (26, 7), (28, 5)]);
+	}
+}
@@ -0,0 +1,219 @@
+use std::num::NonZeroUsize;
+
+use procmem_access::{common::Endianness, prelude::OffsetType};
+
+use crate::{
+	candidate::{ScannerCandidate, CANDIDATE_RECORDED_BYTES},
+	predicate::{is_aligned, value::NumericScalar, ScannerPredicate, UpdateCandidateResult},
+};
+
+/// Operator comparing the value in memory (left) against the reference value (right).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Comparison {
+	Eq,
+	Ne,
+	Gt,
+	Ge,
+	Lt,
+	Le,
+}
+impl Comparison {
+	/// Returns whether `value <op> reference` holds.
+	///
+	/// Floats follow IEEE comparison, so NaN only matches [`Ne`](Comparison::Ne).
+	pub fn compare<T: PartialOrd>(&self, value: &T, reference: &T) -> bool {
+		match self {
+			Comparison::Eq => value == reference,
+			Comparison::Ne => value != reference,
+			Comparison::Gt => value > reference,
+			Comparison::Ge => value >= reference,
+			Comparison::Lt => value < reference,
+			Comparison::Le => value <= reference,
+		}
+	}
+}
+impl std::fmt::Display for Comparison {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		let symbol = match self {
+			Comparison::Eq => "==",
+			Comparison::Ne => "!=",
+			Comparison::Gt => ">",
+			Comparison::Ge => ">=",
+			Comparison::Lt => "<",
+			Comparison::Le => "<=",
+		};
+
+		f.write_str(symbol)
+	}
+}
+impl std::str::FromStr for Comparison {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s {
+			"==" | "=" | "eq" => Ok(Comparison::Eq),
+			"!=" | "ne" => Ok(Comparison::Ne),
+			">" | "gt" => Ok(Comparison::Gt),
+			">=" | "ge" => Ok(Comparison::Ge),
+			"<" | "lt" => Ok(Comparison::Lt),
+			"<=" | "le" => Ok(Comparison::Le),
+			_ => Err(format!("unknown comparison {}", s)),
+		}
+	}
+}
+
+/// Predicate scanning for numeric values which compare to a reference value.
+///
+/// Unlike [`ValuePredicate`](super::value::ValuePredicate) the whole value has to be read before deciding,
+/// so every (aligned) offset starts a candidate. Prefer aligned scans, unaligned ones are considerably slower.
+pub struct ComparisonPredicate<T: NumericScalar> {
+	comparison: Comparison,
+	reference: T,
+	endianness: Endianness,
+	aligned: bool,
+	alignment_base: u64,
+}
+impl<T: NumericScalar> ComparisonPredicate<T> {
+	/// Creates a new predicate matching values `value <comparison> reference`.
+	///
+	/// If `aligned` is true then candidates are only generated at offsets that are divisible by the alignment of `T`.
+	pub fn new(comparison: Comparison, reference: T, aligned: bool) -> Self {
+		debug_assert!(std::mem::size_of::<T>() <= CANDIDATE_RECORDED_BYTES + 1);
+
+		ComparisonPredicate {
+			comparison,
+			reference,
+			endianness: Endianness::NATIVE,
+			aligned,
+			alignment_base: 0,
+		}
+	}
+
+	/// Measures the alignment from `base` instead of from address zero, see [`ValuePredicate::with_alignment_base`](super::value::ValuePredicate::with_alignment_base).
+	pub fn with_alignment_base(mut self, base: u64) -> Self {
+		self.alignment_base = base;
+		self
+	}
+
+	/// Sets the byte order of the values in the target memory.
+	pub fn with_endianness(mut self, endianness: Endianness) -> Self {
+		self.endianness = endianness;
+		self
+	}
+
+	pub const fn comparison(&self) -> Comparison {
+		self.comparison
+	}
+
+	/// Returns whether the value stored as `bytes` in the target byte order matches.
+	pub fn matches(&self, bytes: &[u8]) -> bool {
+		let mut native = [0u8; 16];
+		let native = &mut native[..bytes.len()];
+		native.copy_from_slice(bytes);
+		self.endianness.convert_scalar(native);
+
+		self.comparison
+			.compare(&T::from_ne_slice(native), &self.reference)
+	}
+
+	fn offset_aligned(&self, offset: OffsetType) -> bool {
+		!self.aligned || is_aligned(offset, self.alignment_base, std::mem::align_of::<T>())
+	}
+}
+impl<T: NumericScalar> ScannerPredicate for ComparisonPredicate<T> {
+	fn try_start_candidate(&self, offset: OffsetType, byte: u8) -> Option<ScannerCandidate> {
+		if !self.offset_aligned(offset) {
+			return None;
+		}
+
+		if std::mem::size_of::<T>() == 1 {
+			return self
+				.matches(&[byte])
+				.then(|| ScannerCandidate::resolved(offset, NonZeroUsize::new(1).unwrap()));
+		}
+
+		Some(ScannerCandidate::normal(offset))
+	}
+
+	fn update_candidate(
+		&self,
+		_offset: OffsetType,
+		byte: u8,
+		candidate: &ScannerCandidate,
+	) -> UpdateCandidateResult {
+		let width = std::mem::size_of::<T>();
+		let position = candidate.length().get();
+		debug_assert!(position < width);
+
+		if position < width - 1 {
+			return UpdateCandidateResult::Advance;
+		}
+
+		let mut bytes = [0u8; CANDIDATE_RECORDED_BYTES + 1];
+		bytes[..position].copy_from_slice(candidate.bytes());
+		bytes[position] = byte;
+
+		if self.matches(&bytes[..width]) {
+			UpdateCandidateResult::Resolve
+		} else {
+			UpdateCandidateResult::Remove
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use procmem_access::{common::Endianness, prelude::OffsetType};
+
+	use super::{Comparison, ComparisonPredicate};
+	use crate::stream::StreamScanner;
+
+	fn scan(predicate: ComparisonPredicate<impl super::NumericScalar>, data: &[u8]) -> Vec<u64> {
+		StreamScanner::new(predicate)
+			.scan_once(OffsetType::new_unwrap(0x1000), data.iter().copied())
+			.map(|(offset, _)| offset.get() - 0x1000)
+			.collect()
+	}
+
+	#[test]
+	fn test_comparison_predicate_scan() {
+		let values = [10i32, 49, 50, -3];
+		let data: Vec<u8> = values.iter().flat_map(|v| v.to_ne_bytes()).collect();
+
+		assert_eq!(
+			scan(ComparisonPredicate::new(Comparison::Lt, 50i32, true), &data),
+			[0, 4, 12]
+		);
+		assert_eq!(
+			scan(ComparisonPredicate::new(Comparison::Ge, 49i32, true), &data),
+			[4, 8]
+		);
+		assert_eq!(
+			scan(ComparisonPredicate::new(Comparison::Ne, 10i32, true), &data),
+			[4, 8, 12]
+		);
+		assert_eq!(
+			scan(
+				ComparisonPredicate::new(Comparison::Gt, 200u8, false),
+				&data
+			),
+			[12, 13, 14, 15]
+		);
+
+		let data: Vec<u8> = [1.5f64, -2.0]
+			.iter()
+			.flat_map(|v| v.to_be_bytes())
+			.collect();
+		assert_eq!(
+			scan(
+				ComparisonPredicate::new(Comparison::Le, 0.0f64, true)
+					.with_endianness(Endianness::Big),
+				&data
+			),
+			[8]
+		);
+
+		assert_eq!("<=".parse::<Comparison>(), Ok(Comparison::Le));
+		assert_eq!(Comparison::Ne.to_string(), "!=");
+	}
+}
@@ -0,0 +1,206 @@
+use procmem_access::{common::Endianness, prelude::OffsetType};
+
+use crate::{
+	candidate::ScannerCandidate,
+	predicate::{is_aligned, value::NumericScalar, ScannerPredicate, UpdateCandidateResult},
+};
+
+/// Floating point types scanned by [`FloatPredicate`].
+pub trait FloatScalar: NumericScalar {
+	fn to_f64(self) -> f64;
+}
+impl FloatScalar for f32 {
+	fn to_f64(self) -> f64 {
+		self as f64
+	}
+}
+impl FloatScalar for f64 {
+	fn to_f64(self) -> f64 {
+		self
+	}
+}
+
+/// How close a value in memory has to be to the searched value.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FloatTolerance {
+	/// Matches values with `|found - value| <= epsilon`.
+	Absolute(f64),
+	/// Matches values with `|found - value| <= epsilon * max(|found|, |value|)`.
+	Relative(f64),
+	/// Matches values which round to the same integer, as when the program displays `2` for `2.4`.
+	Rounded,
+	/// Matches values which truncate to the same integer, as when the program displays `2` for `2.9`.
+	Truncated,
+}
+
+/// Predicate scanning for floats approximately equal to a value.
+///
+/// The whole value has to be read before deciding, so every (aligned) offset starts a candidate and the predicate
+/// cannot be used for partial scans. Only finite values match.
+pub struct FloatPredicate<T: FloatScalar> {
+	value: f64,
+	tolerance: FloatTolerance,
+	endianness: Endianness,
+	aligned: bool,
+	alignment_base: u64,
+	ty: std::marker::PhantomData<T>,
+}
+impl<T: FloatScalar> FloatPredicate<T> {
+	/// Creates a new predicate matching values of type `T` within `tolerance` of `value`.
+	///
+	/// If `aligned` is true then candidates are only generated at offsets that are divisible by the alignment of `T`.
+	pub fn new(value: T, tolerance: FloatTolerance, aligned: bool) -> Self {
+		FloatPredicate {
+			value: value.to_f64(),
+			tolerance,
+			endianness: Endianness::NATIVE,
+			aligned,
+			alignment_base: 0,
+			ty: std::marker::PhantomData,
+		}
+	}
+
+	/// Measures the alignment from `base` instead of from address zero, see [`ValuePredicate::with_alignment_base`](super::value::ValuePredicate::with_alignment_base).
+	pub fn with_alignment_base(mut self, base: u64) -> Self {
+		self.alignment_base = base;
+		self
+	}
+
+	/// Sets the byte order of the values in the target memory.
+	pub fn with_endianness(mut self, endianness: Endianness) -> Self {
+		self.endianness = endianness;
+		self
+	}
+
+	pub const fn tolerance(&self) -> FloatTolerance {
+		self.tolerance
+	}
+
+	/// Returns whether `found` is within the tolerance of the searched value.
+	pub fn matches_value(&self, found: T) -> bool {
+		let found = found.to_f64();
+		if !found.is_finite() {
+			return false;
+		}
+
+		match self.tolerance {
+			FloatTolerance::Absolute(epsilon) => (found - self.value).abs() <= epsilon.abs(),
+			FloatTolerance::Relative(epsilon) => {
+				(found - self.value).abs() <= epsilon.abs() * found.abs().max(self.value.abs())
+			}
+			FloatTolerance::Rounded => found.round() == self.value.round(),
+			FloatTolerance::Truncated => found.trunc() == self.value.trunc(),
+		}
+	}
+
+	/// Returns whether the value stored as `bytes` in the target byte order matches.
+	pub fn matches(&self, bytes: &[u8]) -> bool {
+		let mut native = [0u8; 8];
+		let native = &mut native[..bytes.len()];
+		native.copy_from_slice(bytes);
+		self.endianness.convert_scalar(native);
+
+		self.matches_value(T::from_ne_slice(native))
+	}
+
+	fn offset_aligned(&self, offset: OffsetType) -> bool {
+		!self.aligned || is_aligned(offset, self.alignment_base, std::mem::align_of::<T>())
+	}
+}
+impl<T: FloatScalar> ScannerPredicate for FloatPredicate<T> {
+	fn try_start_candidate(&self, offset: OffsetType, _byte: u8) -> Option<ScannerCandidate> {
+		if !self.offset_aligned(offset) {
+			return None;
+		}
+
+		Some(ScannerCandidate::normal(offset))
+	}
+
+	fn update_candidate(
+		&self,
+		_offset: OffsetType,
+		byte: u8,
+		candidate: &ScannerCandidate,
+	) -> UpdateCandidateResult {
+		let width = std::mem::size_of::<T>();
+		let position = candidate.length().get();
+		debug_assert!(position < width);
+
+		if position < width - 1 {
+			return UpdateCandidateResult::Advance;
+		}
+
+		let mut bytes = [0u8; 8];
+		bytes[..position].copy_from_slice(candidate.bytes());
+		bytes[position] = byte;
+
+		if self.matches(&bytes[..width]) {
+			UpdateCandidateResult::Resolve
+		} else {
+			UpdateCandidateResult::Remove
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use procmem_access::{common::Endianness, prelude::OffsetType};
+
+	use super::{FloatPredicate, FloatScalar, FloatTolerance};
+	use crate::stream::StreamScanner;
+
+	fn scan<T: FloatScalar>(predicate: FloatPredicate<T>, data: &[u8]) -> Vec<u64> {
+		StreamScanner::new(predicate)
+			.scan_once(OffsetType::new_unwrap(0x1000), data.iter().copied())
+			.map(|(offset, _)| offset.get() - 0x1000)
+			.collect()
+	}
+
+	#[test]
+	fn test_float_predicate_scan() {
+		let values = [99.97f32, 100.4, 100.6, f32::NAN, -100.0];
+		let data: Vec<u8> = values.iter().flat_map(|v| v.to_ne_bytes()).collect();
+
+		assert_eq!(
+			scan(
+				FloatPredicate::new(100.0f32, FloatTolerance::Absolute(0.05), true),
+				&data
+			),
+			[0]
+		);
+		assert_eq!(
+			scan(
+				FloatPredicate::new(100.0f32, FloatTolerance::Relative(0.01), true),
+				&data
+			),
+			[0, 4, 8]
+		);
+		assert_eq!(
+			scan(
+				FloatPredicate::new(100.0f32, FloatTolerance::Rounded, true),
+				&data
+			),
+			[0, 4]
+		);
+		assert_eq!(
+			scan(
+				FloatPredicate::new(100.0f32, FloatTolerance::Truncated, true),
+				&data
+			),
+			[4, 8]
+		);
+
+		let data: Vec<u8> = [0.5f64, -2.25]
+			.iter()
+			.flat_map(|v| v.to_be_bytes())
+			.collect();
+		assert_eq!(
+			scan(
+				FloatPredicate::new(-2.0f64, FloatTolerance::Absolute(0.5), true)
+					.with_endianness(Endianness::Big),
+				&data
+			),
+			[8]
+		);
+	}
+}
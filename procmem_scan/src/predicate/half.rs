@@ -0,0 +1,117 @@
+use std::num::NonZeroUsize;
+
+use procmem_access::{
+	common::{Endianness, F16},
+	prelude::OffsetType,
+};
+
+use crate::{
+	candidate::ScannerCandidate,
+	predicate::{ScannerPredicate, UpdateCandidateResult},
+};
+
+/// Predicate scanning for half-precision floats in a range of values.
+///
+/// Values are converted to `f32` for comparison. For exact values use [`ValuePredicate`](super::value::ValuePredicate) with [`F16`].
+pub struct F16Predicate {
+	min: f32,
+	max: f32,
+	endianness: Endianness,
+	aligned: bool,
+}
+impl F16Predicate {
+	/// Creates a new predicate matching values in `min..=max`.
+	///
+	/// If `aligned` is true then candidates are only generated at even offsets.
+	pub fn range(min: f32, max: f32, aligned: bool) -> Self {
+		debug_assert!(min <= max);
+
+		F16Predicate {
+			min,
+			max,
+			endianness: Endianness::NATIVE,
+			aligned,
+		}
+	}
+
+	/// Creates a new predicate matching values within `epsilon` of `value`.
+	///
+	/// Half precision only has about three significant digits, so `epsilon` should be at least the precision at `value`.
+	pub fn approx(value: f32, epsilon: f32, aligned: bool) -> Self {
+		Self::range(value - epsilon.abs(), value + epsilon.abs(), aligned)
+	}
+
+	/// Sets the byte order of the values in the target memory.
+	pub fn with_endianness(mut self, endianness: Endianness) -> Self {
+		self.endianness = endianness;
+		self
+	}
+
+	pub fn matches(&self, value: F16) -> bool {
+		let value = value.to_f32();
+
+		value >= self.min && value <= self.max
+	}
+}
+impl ScannerPredicate for F16Predicate {
+	fn try_start_candidate(&self, offset: OffsetType, _byte: u8) -> Option<ScannerCandidate> {
+		if self.aligned && !offset.get().is_multiple_of(2) {
+			return None;
+		}
+
+		Some(ScannerCandidate::normal(offset))
+	}
+
+	fn update_candidate(
+		&self,
+		_offset: OffsetType,
+		byte: u8,
+		candidate: &ScannerCandidate,
+	) -> UpdateCandidateResult {
+		debug_assert_eq!(candidate.length(), NonZeroUsize::new(1).unwrap());
+
+		let mut bytes = [candidate.bytes()[0], byte];
+		self.endianness.convert_scalar(&mut bytes);
+
+		if self.matches(F16::from_ne_bytes(bytes)) {
+			UpdateCandidateResult::Resolve
+		} else {
+			UpdateCandidateResult::Remove
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use procmem_access::{
+		common::{Endianness, F16},
+		prelude::OffsetType,
+	};
+
+	use super::F16Predicate;
+	use crate::stream::StreamScanner;
+
+	#[test]
+	fn test_f16_predicate_scan() {
+		let mut data = [0u8; 16];
+		data[2..4].copy_from_slice(&F16::from_f32(1.5).to_ne_bytes());
+		data[8..10].copy_from_slice(&F16::from_f32(100.0).to_ne_bytes());
+		data[12..14].copy_from_slice(&F16::from_f32(1.25).to_bits().to_be_bytes());
+
+		let mut scanner = StreamScanner::new(F16Predicate::approx(1.5, 0.01, true));
+		let found: Vec<u64> = scanner
+			.scan_once(OffsetType::new_unwrap(0x1000), data.iter().copied())
+			.map(|(offset, _)| offset.get())
+			.collect();
+		assert_eq!(found, [0x1002]);
+
+		let mut scanner = StreamScanner::new(
+			F16Predicate::range(1.0, 2.0, true).with_endianness(Endianness::Big),
+		);
+		let found: Vec<u64> = scanner
+			.scan_once(OffsetType::new_unwrap(0x1000), data.iter().copied())
+			.map(|(offset, _)| offset.get())
+			.collect();
+		assert_eq!(found, [0x100C]);
+	}
+}
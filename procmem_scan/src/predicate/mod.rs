@@ -2,7 +2,14 @@ use procmem_access::prelude::OffsetType;
 
 use crate::candidate::ScannerCandidate;
 
+pub mod ascii;
+pub mod comparison;
+pub mod float;
+pub mod half;
+pub mod pattern;
+pub mod scaled;
 pub mod value;
+pub mod xor;
 
 #[derive(Debug, Copy, Clone, PartialEq)]
 pub enum UpdateCandidateResult {
@@ -31,6 +38,13 @@ pub trait ScannerPredicate {
 		byte: u8,
 		candidate: &ScannerCandidate,
 	) -> UpdateCandidateResult;
+
+	/// Returns the only byte value which can start a candidate, if there is one.
+	///
+	/// This allows [`StreamScanner::scan_slice`](crate::stream::StreamScanner::scan_slice) to skip directly to occurrences of this byte.
+	fn first_byte(&self) -> Option<u8> {
+		None
+	}
 }
 impl<T: ScannerPredicate, U: std::ops::Deref<Target = T>> ScannerPredicate for U {
 	fn try_start_candidate(&self, offset: OffsetType, byte: u8) -> Option<ScannerCandidate> {
@@ -45,6 +59,15 @@ impl<T: ScannerPredicate, U: std::ops::Deref<Target = T>> ScannerPredicate for U
 	) -> UpdateCandidateResult {
 		(**self).update_candidate(offset, byte, candidate)
 	}
+
+	fn first_byte(&self) -> Option<u8> {
+		(**self).first_byte()
+	}
+}
+
+/// Returns whether `offset - base` is a multiple of `align`, which must be a power of two.
+pub(crate) fn is_aligned(offset: OffsetType, base: u64, align: usize) -> bool {
+	offset.get().wrapping_sub(base).is_multiple_of(align as u64)
 }
 
 /// Partial scanner predicate builds on scanner predicate and extends the interface with
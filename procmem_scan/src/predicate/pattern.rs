@@ -0,0 +1,206 @@
+use std::num::NonZeroUsize;
+
+use procmem_access::prelude::OffsetType;
+
+use crate::{
+	candidate::ScannerCandidate,
+	pattern::{BytePattern, BytePatternParseError},
+	predicate::{PartialScannerPredicate, ScannerPredicate, UpdateCandidateResult},
+};
+
+/// Predicate scanning for a byte pattern where each byte is compared under a bit mask.
+///
+/// A zero mask makes the byte a wildcard, a mask of `0xF0` compares only the high nibble.
+/// Patterns can be parsed from the text syntax of [`BytePattern`], for example `48 8B ?? ?? 05`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PatternPredicate {
+	/// Pattern bytes, already masked.
+	values: Vec<u8>,
+	masks: Vec<u8>,
+}
+impl PatternPredicate {
+	/// Creates a new predicate matching bytes `b` for which `b & masks[i] == values[i] & masks[i]`.
+	///
+	/// Panics if the pattern is empty or the lengths differ.
+	pub fn new(values: &[u8], masks: &[u8]) -> Self {
+		assert!(!values.is_empty(), "pattern must not be empty");
+		assert_eq!(values.len(), masks.len());
+
+		PatternPredicate {
+			values: values
+				.iter()
+				.zip(masks.iter())
+				.map(|(value, mask)| value & mask)
+				.collect(),
+			masks: masks.to_vec(),
+		}
+	}
+
+	/// Length of the matches.
+	pub fn len(&self) -> usize {
+		self.values.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.values.is_empty()
+	}
+
+	pub fn masks(&self) -> &[u8] {
+		&self.masks
+	}
+
+	fn matches_at(&self, index: usize, byte: u8) -> bool {
+		byte & self.masks[index] == self.values[index]
+	}
+}
+impl From<&BytePattern> for PatternPredicate {
+	/// Panics if the pattern is empty.
+	fn from(pattern: &BytePattern) -> Self {
+		let (values, masks): (Vec<u8>, Vec<u8>) = pattern
+			.bytes()
+			.iter()
+			.map(|byte| match byte {
+				Some(byte) => (*byte, 0xFF),
+				None => (0, 0),
+			})
+			.unzip();
+
+		PatternPredicate::new(&values, &masks)
+	}
+}
+impl std::str::FromStr for PatternPredicate {
+	type Err = BytePatternParseError;
+
+	/// Parses the pattern using the [`BytePattern`] syntax.
+	fn from_str(string: &str) -> Result<Self, Self::Err> {
+		string
+			.parse::<BytePattern>()
+			.map(|pattern| PatternPredicate::from(&pattern))
+	}
+}
+impl ScannerPredicate for PatternPredicate {
+	fn try_start_candidate(&self, offset: OffsetType, byte: u8) -> Option<ScannerCandidate> {
+		if !self.matches_at(0, byte) {
+			return None;
+		}
+
+		let candidate = if self.len() == 1 {
+			ScannerCandidate::resolved(offset, NonZeroUsize::new(1).unwrap())
+		} else {
+			ScannerCandidate::normal(offset)
+		};
+
+		Some(candidate)
+	}
+
+	fn update_candidate(
+		&self,
+		_offset: OffsetType,
+		byte: u8,
+		candidate: &ScannerCandidate,
+	) -> UpdateCandidateResult {
+		let index = candidate.length().get();
+		debug_assert!(index < self.len());
+
+		if !self.matches_at(index, byte) {
+			return UpdateCandidateResult::Remove;
+		}
+
+		if index == self.len() - 1 {
+			return UpdateCandidateResult::Resolve;
+		}
+
+		UpdateCandidateResult::Advance
+	}
+
+	fn first_byte(&self) -> Option<u8> {
+		(self.masks[0] == 0xFF).then_some(self.values[0])
+	}
+}
+impl PartialScannerPredicate for PatternPredicate {
+	fn try_start_partial_candidates(&self, offset: OffsetType, byte: u8) -> Vec<ScannerCandidate> {
+		(1..self.len())
+			.rev()
+			.filter(|&index| self.matches_at(index, byte))
+			.filter_map(|index| {
+				// skip candidates which would start at a non-positive offset
+				let start = OffsetType::new(offset.get().saturating_sub(index as u64))?;
+
+				let length = NonZeroUsize::new(index + 1).unwrap();
+				let candidate = if length.get() == self.len() {
+					ScannerCandidate::partial_resolved(start, length)
+				} else {
+					ScannerCandidate::partial(start, length)
+				};
+
+				Some(candidate)
+			})
+			.collect()
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use std::convert::TryInto;
+
+	use procmem_access::prelude::OffsetType;
+
+	use super::PatternPredicate;
+	use crate::{pattern::BytePattern, stream::StreamScanner};
+
+	#[test]
+	fn test_pattern_predicate_scan() {
+		let data = [
+			0x00, 0x48, 0x8B, 0x11, 0x22, 0x05, 0x48, 0x8B, 0x33, 0x05, 0x48, 0x8B, 0x44, 0x55,
+			0x05,
+		];
+		let predicate: PatternPredicate = "48 8B ?? ?? 05".parse().unwrap();
+		assert_eq!(predicate.masks(), [0xFF, 0xFF, 0, 0, 0xFF]);
+
+		let mut scanner = StreamScanner::new(&predicate);
+		let found: Vec<_> = scanner
+			.scan_once(OffsetType::new_unwrap(1), data.iter().copied())
+			.collect();
+		let expected = [
+			(OffsetType::new_unwrap(2), 5.try_into().unwrap()),
+			(OffsetType::new_unwrap(11), 5.try_into().unwrap()),
+		];
+		assert_eq!(found, expected);
+		let pattern: BytePattern = "48 8B ?? ?? 05".parse().unwrap();
+		assert_eq!(
+			pattern
+				.find_iter(&data)
+				.map(|index| index as u64 + 1)
+				.collect::<Vec<_>>(),
+			[2, 11]
+		);
+
+		let mut found = Vec::new();
+		for split in [3, 9, 13] {
+			let mut scanner_1 = StreamScanner::new(&predicate);
+			let mut scanner_2 = StreamScanner::new(&predicate);
+
+			found.clear();
+			found.extend(scanner_2.scan_partial(
+				OffsetType::new_unwrap(1 + split as u64),
+				data[split..].iter().copied(),
+			));
+			found.extend(
+				scanner_1.scan_partial(OffsetType::new_unwrap(1), data[..split].iter().copied()),
+			);
+			scanner_1.merge_partial_mut(scanner_2);
+			found.extend(scanner_1.resolve_partial());
+			found.sort_unstable();
+
+			assert_eq!(found, expected, "split at {}", split);
+		}
+
+		// only the high nibble of the second byte is compared
+		let predicate = PatternPredicate::new(&[0x48, 0x80], &[0xFF, 0xF0]);
+		let found: Vec<_> = StreamScanner::new(&predicate)
+			.scan_once(OffsetType::new_unwrap(1), data.iter().copied())
+			.map(|(offset, _)| offset.get())
+			.collect();
+		assert_eq!(found, [2, 7, 11]);
+	}
+}
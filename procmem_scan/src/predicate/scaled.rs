@@ -0,0 +1,266 @@
+use std::num::NonZeroUsize;
+
+use procmem_access::{
+	common::Endianness,
+	memory::access::ReadError,
+	prelude::{MemoryAccess, OffsetType},
+};
+
+use crate::{
+	candidate::ScannerCandidate,
+	predicate::{
+		is_aligned,
+		value::{ByteComparable, EndianScalar},
+		ScannerPredicate, UpdateCandidateResult,
+	},
+	stream::ScanResult,
+};
+
+/// Transform `stored = value * scale + offset` applied by the target before storing a value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ScaledEncoding {
+	pub scale: i64,
+	pub offset: i64,
+}
+impl ScaledEncoding {
+	pub const fn new(scale: i64, offset: i64) -> Self {
+		ScaledEncoding { scale, offset }
+	}
+}
+impl std::fmt::Display for ScaledEncoding {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		write!(f, "value * {}", self.scale)?;
+		match self.offset {
+			0 => Ok(()),
+			offset if offset < 0 => write!(f, " - {:#x}", offset.unsigned_abs()),
+			offset => write!(f, " + {:#x}", offset),
+		}
+	}
+}
+
+/// Scalar types which can be encoded by a [`ScaledEncoding`].
+///
+/// Integers wrap around in their own width, floats are computed in their own precision.
+pub trait ScaledScalar: EndianScalar {
+	fn encode(self, encoding: ScaledEncoding) -> Self;
+}
+macro_rules! impl_scaled_scalar {
+	(
+		int: $( $int_type: ty )+;
+		float: $( $float_type: ty )+
+	) => {
+		$(
+			impl ScaledScalar for $int_type {
+				fn encode(self, encoding: ScaledEncoding) -> Self {
+					self.wrapping_mul(encoding.scale as $int_type)
+						.wrapping_add(encoding.offset as $int_type)
+				}
+			}
+		)+
+		$(
+			impl ScaledScalar for $float_type {
+				fn encode(self, encoding: ScaledEncoding) -> Self {
+					self * encoding.scale as $float_type + encoding.offset as $float_type
+				}
+			}
+		)+
+	};
+}
+impl_scaled_scalar! {
+	int: u8 i8 u16 i16 u32 i32 u64 i64;
+	float: f32 f64
+}
+
+/// Match of a [`ScaledPredicate`] together with the encoding which produced it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScaledMatch {
+	pub offset: OffsetType,
+	pub encoding: ScaledEncoding,
+}
+
+/// Predicate scanning for a value stored under one of several [`ScaledEncoding`]s.
+///
+/// Matches are plain [`ScanResult`]s, the encoding can be recovered from the matched bytes with [`encoding_of`](ScaledPredicate::encoding_of)
+/// or for all results at once with [`resolve_encodings`].
+pub struct ScaledPredicate {
+	/// Encoded values in the target byte order, all of the same width.
+	encoded: Vec<(ScaledEncoding, Vec<u8>)>,
+	align: usize,
+	aligned: bool,
+	alignment_base: u64,
+}
+impl ScaledPredicate {
+	/// Creates a new predicate matching `value` encoded by any of `encodings`, in native byte order.
+	///
+	/// If `aligned` is true then candidates are only generated at offsets that are divisible by the alignment of `T`.
+	pub fn new<T: ScaledScalar>(value: T, encodings: &[ScaledEncoding], aligned: bool) -> Self {
+		Self::new_as(value, encodings, Endianness::NATIVE, aligned)
+	}
+
+	/// Creates a new predicate like [`new`](ScaledPredicate::new) with values stored in `endianness` byte order.
+	pub fn new_as<T: ScaledScalar>(
+		value: T,
+		encodings: &[ScaledEncoding],
+		endianness: Endianness,
+		aligned: bool,
+	) -> Self {
+		debug_assert!(!encodings.is_empty());
+
+		let encoded = encodings
+			.iter()
+			.map(|encoding| {
+				let mut bytes = value.encode(*encoding).as_bytes().to_vec();
+				endianness.convert_scalar(&mut bytes);
+
+				(*encoding, bytes)
+			})
+			.collect();
+
+		ScaledPredicate {
+			encoded,
+			align: std::mem::align_of::<T>(),
+			aligned,
+			alignment_base: 0,
+		}
+	}
+
+	/// Measures the alignment from `base` instead of from address zero, see [`ValuePredicate::with_alignment_base`](super::value::ValuePredicate::with_alignment_base).
+	pub fn with_alignment_base(mut self, base: u64) -> Self {
+		self.alignment_base = base;
+		self
+	}
+
+	/// Returns the first encoding under which the value is stored as `stored`.
+	pub fn encoding_of(&self, stored: &[u8]) -> Option<ScaledEncoding> {
+		self.encoded
+			.iter()
+			.find(|(_, bytes)| bytes.as_slice() == stored)
+			.map(|(encoding, _)| *encoding)
+	}
+
+	fn width(&self) -> usize {
+		self.encoded[0].1.len()
+	}
+
+	fn offset_aligned(&self, offset: OffsetType) -> bool {
+		!self.aligned || is_aligned(offset, self.alignment_base, self.align)
+	}
+}
+impl ScannerPredicate for ScaledPredicate {
+	fn try_start_candidate(&self, offset: OffsetType, byte: u8) -> Option<ScannerCandidate> {
+		if !self.offset_aligned(offset) || !self.encoded.iter().any(|(_, bytes)| bytes[0] == byte) {
+			return None;
+		}
+
+		let result = if self.width() == 1 {
+			ScannerCandidate::resolved(offset, NonZeroUsize::new(1).unwrap())
+		} else {
+			ScannerCandidate::normal(offset)
+		};
+
+		Some(result)
+	}
+
+	fn update_candidate(
+		&self,
+		_offset: OffsetType,
+		byte: u8,
+		candidate: &ScannerCandidate,
+	) -> UpdateCandidateResult {
+		let position = candidate.length().get();
+		debug_assert!(position < self.width());
+
+		// scalars are at most as wide as the recorded prefix
+		let recorded = candidate.bytes();
+		let matches = self
+			.encoded
+			.iter()
+			.any(|(_, bytes)| bytes.starts_with(recorded) && bytes[position] == byte);
+
+		if !matches {
+			return UpdateCandidateResult::Remove;
+		}
+
+		if position == self.width() - 1 {
+			return UpdateCandidateResult::Resolve;
+		}
+
+		UpdateCandidateResult::Advance
+	}
+}
+
+/// Reads the bytes of each of `results` and recovers the encoding they were stored with.
+///
+/// Results whose bytes no longer match any encoding are skipped.
+///
+/// ## Safety
+/// * See [`MemoryAccess::read`].
+pub unsafe fn resolve_encodings<A: MemoryAccess + ?Sized>(
+	access: &mut A,
+	predicate: &ScaledPredicate,
+	results: impl IntoIterator<Item = ScanResult>,
+) -> Result<Vec<ScaledMatch>, ReadError> {
+	let mut matches = Vec::new();
+
+	let mut buffer = Vec::new();
+	for (offset, length) in results {
+		buffer.resize(length.get(), 0);
+		access.read(offset, &mut buffer)?;
+
+		if let Some(encoding) = predicate.encoding_of(&buffer) {
+			matches.push(ScaledMatch { offset, encoding });
+		}
+	}
+
+	Ok(matches)
+}
+
+#[cfg(test)]
+mod test {
+	use procmem_access::{
+		common::Endianness, platform::mock::MockMemoryAccess, prelude::OffsetType,
+	};
+
+	use super::{resolve_encodings, ScaledEncoding, ScaledMatch, ScaledPredicate};
+	use crate::stream::StreamScanner;
+
+	#[test]
+	fn test_scaled_predicate() {
+		const TIMES_EIGHT: ScaledEncoding = ScaledEncoding::new(8, 0);
+		const PLUS_MAGIC: ScaledEncoding = ScaledEncoding::new(1, 0xDEADBEEF);
+
+		let mut data = vec![0u8; 32];
+		data[3..7].copy_from_slice(&800u32.to_ne_bytes());
+		data[15..19].copy_from_slice(&100u32.wrapping_add(0xDEADBEEF).to_be_bytes());
+		data[23..27].copy_from_slice(&100u32.wrapping_add(0xDEADBEEF).to_ne_bytes());
+
+		let predicate = ScaledPredicate::new(100u32, &[TIMES_EIGHT, PLUS_MAGIC], true);
+		let results: Vec<_> = StreamScanner::new(&predicate)
+			.scan_once(OffsetType::new_unwrap(1), data.iter().copied())
+			.collect();
+		let mut access =
+			MockMemoryAccess::new().with_region(OffsetType::new_unwrap(1), data.clone());
+		let matches = unsafe { resolve_encodings(&mut access, &predicate, results).unwrap() };
+		assert_eq!(
+			matches,
+			[
+				ScaledMatch {
+					offset: OffsetType::new_unwrap(4),
+					encoding: TIMES_EIGHT
+				},
+				ScaledMatch {
+					offset: OffsetType::new_unwrap(24),
+					encoding: PLUS_MAGIC
+				}
+			]
+		);
+		assert_eq!(PLUS_MAGIC.to_string(), "value * 1 + 0xdeadbeef");
+
+		let predicate = ScaledPredicate::new_as(100u32, &[PLUS_MAGIC], Endianness::Big, true);
+		let found: Vec<u64> = StreamScanner::new(&predicate)
+			.scan_once(OffsetType::new_unwrap(1), data.iter().copied())
+			.map(|(offset, _)| offset.get())
+			.collect();
+		assert_eq!(found, [16]);
+	}
+}
@@ -1,14 +1,20 @@
-use std::num::NonZeroUsize;
+use std::num::{NonZeroU64, NonZeroUsize};
 
-use procmem_access::prelude::OffsetType;
+use procmem_access::{
+	common::{Endianness, F16},
+	prelude::OffsetType,
+};
 
 use crate::{
 	candidate::ScannerCandidate,
-	predicate::{ScannerPredicate, UpdateCandidateResult},
+	predicate::{is_aligned, ScannerPredicate, UpdateCandidateResult},
 };
 
 use super::PartialScannerPredicate;
 
+#[cfg(feature = "derive")]
+pub use procmem_scan_derive::ByteComparable;
+
 pub trait ByteComparable {
 	fn as_bytes(&self) -> &[u8];
 
@@ -17,74 +23,83 @@ pub trait ByteComparable {
 	/// If `Self` is a reference then this returns the alignment of the type behind reference.
 	fn align_of(&self) -> usize;
 }
-macro_rules! impl_byte_comparable {
+
+/// Marker for types whose values can be viewed as raw bytes.
+///
+/// All types implementing this trait also implement [`ByteComparable`].
+/// For user-defined structs prefer `#[derive(ByteComparable)]`, which checks the requirements below at compile time.
+///
+/// ## Safety
+/// * The type must not contain any padding bytes
+/// * All fields must also be `AsRawBytes`
+pub unsafe trait AsRawBytes: Sized {}
+macro_rules! impl_as_raw_bytes {
 	(
-		Pod:
 		$( $pod_type: ty )+
 	) => {
 		$(
-			impl ByteComparable for $pod_type {
-				fn as_bytes(&self) -> &[u8] {
-					unsafe {
-						std::slice::from_raw_parts(
-							self as *const _ as *const u8,
-							std::mem::size_of::<Self>()
-						)
-					}
-				}
+			unsafe impl AsRawBytes for $pod_type {}
+		)+
+	};
+}
+impl_as_raw_bytes! {
+	u8 i8 u16 i16 u32 i32 u64 i64 u128 i128 usize isize f32 f64 F16
+}
+unsafe impl<T: AsRawBytes, const N: usize> AsRawBytes for [T; N] {}
 
-				fn align_of(&self) -> usize {
-					std::mem::align_of::<Self>()
-				}
-			}
-			impl<const N: usize> ByteComparable for [$pod_type; N] {
-				fn as_bytes(&self) -> &[u8] {
-					unsafe {
-						std::slice::from_raw_parts(
-							self.as_slice().as_ptr() as *const u8,
-							std::mem::size_of::<$pod_type>() * N
-						)
-					}
-				}
+impl<T: AsRawBytes> ByteComparable for T {
+	fn as_bytes(&self) -> &[u8] {
+		unsafe {
+			std::slice::from_raw_parts(self as *const T as *const u8, std::mem::size_of::<T>())
+		}
+	}
 
-				fn align_of(&self) -> usize {
-					std::mem::align_of::<$pod_type>()
-				}
-			}
-			impl ByteComparable for [$pod_type] {
-				fn as_bytes(&self) -> &[u8] {
-					unsafe {
-						std::slice::from_raw_parts(
-							self.as_ptr() as *const u8,
-							std::mem::size_of::<$pod_type>() * self.len()
-						)
-					}
-				}
+	fn align_of(&self) -> usize {
+		std::mem::align_of::<T>()
+	}
+}
+impl<T: AsRawBytes> ByteComparable for [T] {
+	fn as_bytes(&self) -> &[u8] {
+		unsafe {
+			std::slice::from_raw_parts(self.as_ptr() as *const u8, std::mem::size_of_val(self))
+		}
+	}
 
-				fn align_of(&self) -> usize {
-					std::mem::align_of::<$pod_type>()
-				}
-			}
-			impl ByteComparable for &'_ [$pod_type] {
-				fn as_bytes(&self) -> &[u8] {
-					unsafe {
-						std::slice::from_raw_parts(
-							self.as_ptr() as *const u8,
-							std::mem::size_of::<$pod_type>() * self.len()
-						)
-					}
-				}
+	fn align_of(&self) -> usize {
+		std::mem::align_of::<T>()
+	}
+}
+impl<T: AsRawBytes> ByteComparable for &'_ [T] {
+	fn as_bytes(&self) -> &[u8] {
+		(**self).as_bytes()
+	}
 
-				fn align_of(&self) -> usize {
-					std::mem::align_of::<$pod_type>()
-				}
-			}
-		)+
-	};
+	fn align_of(&self) -> usize {
+		std::mem::align_of::<T>()
+	}
 }
-impl_byte_comparable! {
-	Pod: u8 i8 u16 i16 u32 i32 u64 i64 u128 i128 usize isize f32 f64
+/// Bridge from [`bytemuck`] to [`ByteComparable`].
+///
+/// Wrapping any [`bytemuck::NoUninit`] (and thus any [`bytemuck::Pod`]) type makes it usable with [`ValuePredicate`],
+/// including slices, arrays and vectors of the wrapper.
+///
+/// A blanket impl over `bytemuck` traits is not possible because it would conflict with the impls for `&str`, `&[T]` and `Vec<T>`.
+#[cfg(feature = "bytemuck")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[repr(transparent)]
+pub struct Bytemuck<T>(pub T);
+#[cfg(feature = "bytemuck")]
+impl<T: bytemuck::NoUninit> Bytemuck<T> {
+	/// Wraps a slice of `T` without copying.
+	pub fn wrap_slice(slice: &[T]) -> &[Self] {
+		// Safe because `Bytemuck` is `repr(transparent)`
+		unsafe { std::slice::from_raw_parts(slice.as_ptr() as *const Self, slice.len()) }
+	}
 }
+// Safe because `NoUninit` guarantees no padding and `Bytemuck` is `repr(transparent)`
+#[cfg(feature = "bytemuck")]
+unsafe impl<T: bytemuck::NoUninit> AsRawBytes for Bytemuck<T> {}
+
 impl ByteComparable for &'_ str {
 	fn as_bytes(&self) -> &[u8] {
 		str::as_bytes(self)
@@ -107,41 +122,173 @@ where
 	}
 }
 
+/// Scalar types whose byte order can be converted.
+pub trait EndianScalar: AsRawBytes + Copy {
+	/// Returns the bytes of `self` in the `endianness` byte order, see [`TargetBytes`].
+	fn to_target_bytes(self, endianness: Endianness) -> TargetBytes {
+		TargetBytes::new(self, endianness)
+	}
+}
+macro_rules! impl_endian_scalar {
+	(
+		$( $scalar_type: ty )+
+	) => {
+		$(
+			impl EndianScalar for $scalar_type {}
+		)+
+	};
+}
+impl_endian_scalar! {
+	u8 i8 u16 i16 u32 i32 u64 i64 u128 i128 usize isize f32 f64 F16
+}
+
+/// Numeric scalar types which can be ordered and offset, used by scans comparing values.
+pub trait NumericScalar: EndianScalar + PartialOrd {
+	/// Reads a value from the native-endian `bytes`, which must be exactly the size of `Self`.
+	fn from_ne_slice(bytes: &[u8]) -> Self;
+
+	/// Returns `self + delta`, integers wrap around.
+	fn offset_by(self, delta: Self) -> Self;
+}
+macro_rules! impl_numeric_scalar {
+	(
+		int: $( $int_type: ty )+;
+		float: $( $float_type: ty )+
+	) => {
+		$(
+			impl NumericScalar for $int_type {
+				fn from_ne_slice(bytes: &[u8]) -> Self {
+					Self::from_ne_bytes(bytes.try_into().unwrap())
+				}
+
+				fn offset_by(self, delta: Self) -> Self {
+					self.wrapping_add(delta)
+				}
+			}
+		)+
+		$(
+			impl NumericScalar for $float_type {
+				fn from_ne_slice(bytes: &[u8]) -> Self {
+					Self::from_ne_bytes(bytes.try_into().unwrap())
+				}
+
+				fn offset_by(self, delta: Self) -> Self {
+					self + delta
+				}
+			}
+		)+
+	};
+}
+impl_numeric_scalar! {
+	int: u8 i8 u16 i16 u32 i32 u64 i64;
+	float: f32 f64
+}
+
+/// Scalar values converted to the byte order of the target.
+///
+/// Use this with [`ValuePredicate`] when the target byte order differs from the host, since the plain [`ByteComparable`]
+/// implementations always use the native byte order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TargetBytes {
+	bytes: Vec<u8>,
+	align: usize,
+}
+impl TargetBytes {
+	pub fn new<T: EndianScalar>(value: T, endianness: Endianness) -> Self {
+		Self::from_slice(&[value], endianness)
+	}
+
+	/// Converts each element of `values` separately, keeping their order.
+	pub fn from_slice<T: EndianScalar>(values: &[T], endianness: Endianness) -> Self {
+		let mut bytes = values.as_bytes().to_vec();
+		for scalar in bytes.chunks_exact_mut(std::mem::size_of::<T>()) {
+			endianness.convert_scalar(scalar);
+		}
+
+		TargetBytes {
+			bytes,
+			align: std::mem::align_of::<T>(),
+		}
+	}
+}
+impl ByteComparable for TargetBytes {
+	fn as_bytes(&self) -> &[u8] {
+		&self.bytes
+	}
+
+	fn align_of(&self) -> usize {
+		self.align
+	}
+}
+
 /// Predicate scanning for a concrete value in memory.
 ///
 /// The value may be anything but is constrained to `ByteComparable` because it needs to be accessed as raw bytes safely.
 pub struct ValuePredicate<T: ByteComparable> {
 	value: T,
 	aligned: bool,
+	alignment_base: u64,
+	stride: Option<(NonZeroU64, u64)>,
 }
 impl<T: ByteComparable> ValuePredicate<T> {
 	/// Creates a new predicate.
 	///
 	/// If `aligned` is true then candidates are only generated at offsets that are divisible by [`T::align_of`](ByteComparable::align_of)
 	pub fn new(value: T, aligned: bool) -> Self {
-		debug_assert!(value.as_bytes().len() > 0);
+		debug_assert!(!value.as_bytes().is_empty());
+
+		ValuePredicate {
+			value,
+			aligned,
+			alignment_base: 0,
+			stride: None,
+		}
+	}
 
-		ValuePredicate { value, aligned }
+	/// Measures the alignment from `base` instead of from address zero, so `aligned` accepts offsets where `offset - base`
+	/// is divisible by the alignment.
+	///
+	/// Mappings and their file offsets are page aligned, so this only matters for data whose layout starts elsewhere,
+	/// for example an array of records at an unaligned offset of a file. The base of a stride is given by its remainder instead.
+	pub fn with_alignment_base(mut self, base: u64) -> Self {
+		self.alignment_base = base;
+		self
+	}
+
+	/// Only generates candidates at offsets where `offset % stride == remainder`, regardless of `aligned`.
+	///
+	/// This trades completeness for speed, for example a stride of 4 finds 8-byte values which are only 4-byte aligned.
+	/// `remainder` is reduced modulo `stride`.
+	pub fn with_stride(mut self, stride: NonZeroU64, remainder: u64) -> Self {
+		self.stride = Some((stride, remainder % stride.get()));
+		self
 	}
 
 	fn offset_aligned(&self, offset: OffsetType) -> bool {
-		!self.aligned || (offset.get() % self.value.align_of() as u64) == 0
+		match self.stride {
+			Some((stride, remainder)) => offset.get() % stride.get() == remainder,
+			None => !self.aligned || is_aligned(offset, self.alignment_base, self.value.align_of()),
+		}
+	}
+}
+impl ValuePredicate<TargetBytes> {
+	/// Creates a predicate scanning for a scalar `value` stored in the `endianness` byte order.
+	pub fn new_scalar<S: EndianScalar>(value: S, endianness: Endianness, aligned: bool) -> Self {
+		Self::new(value.to_target_bytes(endianness), aligned)
 	}
 }
 impl<T: ByteComparable> ScannerPredicate for ValuePredicate<T> {
 	fn try_start_candidate(&self, offset: OffsetType, byte: u8) -> Option<ScannerCandidate> {
 		let bytes = self.value.as_bytes();
 
-		if self.offset_aligned(offset) {
-			if bytes[0] == byte {
-				let result = if bytes.len() == 1 {
-					ScannerCandidate::resolved(offset, NonZeroUsize::new(1).unwrap())
-				} else {
-					ScannerCandidate::normal(offset)
-				};
+		if self.offset_aligned(offset) && bytes[0] == byte {
+			let result = if bytes.len() == 1 {
+				ScannerCandidate::resolved(offset, NonZeroUsize::new(1).unwrap())
+			} else {
+				ScannerCandidate::normal(offset)
+			};
 
-				return Some(result);
-			}
+			return Some(result);
 		}
 
 		None
@@ -166,6 +313,10 @@ impl<T: ByteComparable> ScannerPredicate for ValuePredicate<T> {
 
 		UpdateCandidateResult::Advance
 	}
+
+	fn first_byte(&self) -> Option<u8> {
+		Some(self.value.as_bytes()[0])
+	}
 }
 impl<T: ByteComparable> PartialScannerPredicate for ValuePredicate<T> {
 	fn try_start_partial_candidates(&self, offset: OffsetType, byte: u8) -> Vec<ScannerCandidate> {
@@ -204,16 +355,17 @@ impl<T: ByteComparable> PartialScannerPredicate for ValuePredicate<T> {
 
 #[cfg(test)]
 mod test {
-	use std::num::NonZeroUsize;
+	use std::num::{NonZeroU64, NonZeroUsize};
 
-	use procmem_access::prelude::OffsetType;
+	use procmem_access::{common::Endianness, prelude::OffsetType};
 
-	use super::ValuePredicate;
+	use super::{EndianScalar, TargetBytes, ValuePredicate};
 	use crate::{
 		candidate::ScannerCandidate,
 		predicate::{
 			value::ByteComparable, PartialScannerPredicate, ScannerPredicate, UpdateCandidateResult,
 		},
+		stream::StreamScanner,
 	};
 
 	#[test]
@@ -245,6 +397,95 @@ mod test {
 		);
 	}
 
+	#[test]
+	fn test_value_predicate_target_bytes() {
+		let value = TargetBytes::from_slice(&[0x0102u16, 0x0304], Endianness::Big);
+		assert_eq!(value.as_bytes(), [1, 2, 3, 4]);
+		assert_eq!(value.align_of(), 2);
+
+		let value = TargetBytes::new(0x0102u16, Endianness::Little);
+		assert_eq!(value.as_bytes(), [2, 1]);
+
+		let predicate = ValuePredicate::new(TargetBytes::new(0x0102u16, Endianness::Big), true);
+		assert!(predicate
+			.try_start_candidate(OffsetType::new_unwrap(100), 1)
+			.is_some());
+
+		assert_eq!(
+			0x01020304u32.to_target_bytes(Endianness::Big).as_bytes(),
+			[1, 2, 3, 4]
+		);
+
+		let predicate = ValuePredicate::new_scalar(0x0102u16, Endianness::Big, true);
+		assert!(predicate
+			.try_start_candidate(OffsetType::new_unwrap(100), 1)
+			.is_some());
+		// aligned to the scalar, not to its bytes
+		assert!(predicate
+			.try_start_candidate(OffsetType::new_unwrap(101), 1)
+			.is_none());
+	}
+
+	#[test]
+	fn test_value_predicate_scalar_byte_order() {
+		// the same value stored little-endian at 4 and big-endian at 12
+		let mut data = [0u8; 16];
+		data[4..8].copy_from_slice(&0x11223344u32.to_le_bytes());
+		data[12..16].copy_from_slice(&0x11223344u32.to_be_bytes());
+
+		let found = |endianness| {
+			let predicate = ValuePredicate::new_scalar(0x11223344u32, endianness, true);
+			StreamScanner::new(&predicate)
+				.scan_once(OffsetType::new_unwrap(0x1000), data.iter().copied())
+				.map(|(offset, length)| (offset.get(), length.get()))
+				.collect::<Vec<_>>()
+		};
+		assert_eq!(found(Endianness::Little), [(0x1004, 4)]);
+		assert_eq!(found(Endianness::Big), [(0x100C, 4)]);
+	}
+
+	#[test]
+	fn test_value_predicate_alignment_base() {
+		let predicate = ValuePredicate::new(1u32, true).with_alignment_base(0x1002);
+
+		assert!(predicate
+			.try_start_candidate(OffsetType::new_unwrap(0x1006), 1)
+			.is_some());
+		assert!(predicate
+			.try_start_candidate(OffsetType::new_unwrap(0x1004), 1)
+			.is_none());
+		// offsets before the base are aligned the same way
+		assert!(predicate
+			.try_start_candidate(OffsetType::new_unwrap(0xFFE), 1)
+			.is_some());
+	}
+
+	#[test]
+	fn test_value_predicate_stride() {
+		let predicate = ValuePredicate::new(1u64, true).with_stride(NonZeroU64::new(4).unwrap(), 6);
+
+		// 4-byte stride overrides the 8-byte alignment
+		for offset in [102, 106] {
+			assert!(predicate
+				.try_start_candidate(OffsetType::new_unwrap(offset), 1)
+				.is_some());
+		}
+		for offset in [100, 101, 104] {
+			assert!(predicate
+				.try_start_candidate(OffsetType::new_unwrap(offset), 1)
+				.is_none());
+		}
+
+		// partial candidates respect the stride as well
+		let candidates = ValuePredicate::new([1u8, 1, 1, 1], false)
+			.with_stride(NonZeroU64::new(2).unwrap(), 0)
+			.try_start_partial_candidates(OffsetType::new_unwrap(103), 1);
+		assert_eq!(candidates.len(), 2);
+		assert!(candidates
+			.iter()
+			.all(|candidate| candidate.offset().get() % 2 == 0));
+	}
+
 	#[test]
 	fn test_value_predicate_normal_length_1() {
 		let data = 1u8;
@@ -276,9 +517,81 @@ mod test {
 		assert!(result.is_resolved());
 	}
 
+	#[test]
+	fn test_value_predicate_derived_struct() {
+		#[derive(procmem_scan_derive::ByteComparable)]
+		#[repr(C)]
+		struct Vector {
+			x: f32,
+			y: f32,
+		}
+
+		#[derive(procmem_scan_derive::ByteComparable)]
+		#[repr(C)]
+		struct Entity {
+			position: Vector,
+			health: u32,
+			ammo: [u16; 2],
+		}
+
+		let entity = Entity {
+			position: Vector { x: 1.0, y: -2.5 },
+			health: 100,
+			ammo: [12, 48],
+		};
+		assert_eq!(entity.as_bytes().len(), 16);
+		assert_eq!(entity.align_of(), 4);
+		assert_eq!(&entity.as_bytes()[8..12], &100u32.to_ne_bytes());
+
+		let data = entity.as_bytes().to_vec();
+		let predicate = ValuePredicate::new(entity, true);
+		let mut candidate = predicate
+			.try_start_candidate(OffsetType::new_unwrap(100), data[0])
+			.unwrap();
+		for (i, byte) in data.iter().copied().enumerate().skip(1) {
+			let result = predicate.update_candidate(
+				OffsetType::new_unwrap(100 + i as u64),
+				byte,
+				&candidate,
+			);
+			if i == data.len() - 1 {
+				assert_eq!(result, UpdateCandidateResult::Resolve);
+			} else {
+				assert_eq!(result, UpdateCandidateResult::Advance);
+				candidate.advance();
+			}
+		}
+	}
+
+	#[cfg(feature = "bytemuck")]
+	#[test]
+	fn test_value_predicate_bytemuck() {
+		use super::Bytemuck;
+
+		#[derive(Clone, Copy, bytemuck::Zeroable, bytemuck::Pod)]
+		#[repr(C)]
+		struct Pair {
+			a: u32,
+			b: u32,
+		}
+
+		let pair = Bytemuck(Pair { a: 1, b: 2 });
+		assert_eq!(pair.as_bytes(), bytemuck::bytes_of(&pair.0));
+		assert_eq!(pair.align_of(), 4);
+
+		let values = [Pair { a: 3, b: 4 }, Pair { a: 5, b: 6 }];
+		let wrapped = Bytemuck::wrap_slice(&values);
+		assert_eq!(wrapped.as_bytes(), bytemuck::cast_slice::<_, u8>(&values));
+
+		let predicate = ValuePredicate::new(wrapped, true);
+		assert!(predicate
+			.try_start_candidate(OffsetType::new_unwrap(100), 3)
+			.is_some());
+	}
+
 	#[test]
 	fn test_value_predicate_update() {
-		let data_u16 = [1, std::u16::MAX];
+		let data_u16 = [1, u16::MAX];
 		let data = unsafe {
 			std::slice::from_raw_parts(
 				&data_u16 as *const u16 as *const u8,
@@ -286,7 +599,7 @@ mod test {
 			)
 		};
 
-		let predicate = ValuePredicate::new([1, std::u16::MAX], true);
+		let predicate = ValuePredicate::new([1, u16::MAX], true);
 
 		// Works correctly
 		assert_eq!(
@@ -0,0 +1,264 @@
+use std::num::NonZeroUsize;
+
+use procmem_access::{
+	memory::access::ReadError,
+	prelude::{MemoryAccess, OffsetType},
+};
+
+use crate::{
+	candidate::{ScannerCandidate, CANDIDATE_RECORDED_BYTES},
+	predicate::{is_aligned, value::ByteComparable, ScannerPredicate, UpdateCandidateResult},
+	stream::ScanResult,
+};
+
+#[derive(Debug, Clone)]
+enum XorKeys {
+	/// Every non-zero single byte key.
+	AnyByte,
+	/// Listed keys, repeated over the value from its start.
+	Fixed(Vec<Vec<u8>>),
+}
+
+/// Match of a [`XorPredicate`] together with the key the value was obfuscated with.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct XorMatch {
+	pub offset: OffsetType,
+	pub key: Vec<u8>,
+}
+
+/// Predicate scanning for a value stored XOR-ed with a key.
+///
+/// Matches are plain [`ScanResult`]s, the key can be recovered from the matched bytes with [`key_of`](XorPredicate::key_of)
+/// or for all results at once with [`resolve_keys`].
+pub struct XorPredicate<T: ByteComparable> {
+	value: T,
+	keys: XorKeys,
+	aligned: bool,
+	alignment_base: u64,
+}
+impl<T: ByteComparable> XorPredicate<T> {
+	/// Creates a new predicate which brute-forces a single byte key for each candidate.
+	///
+	/// The zero key is skipped, plain values are found by [`ValuePredicate`](super::value::ValuePredicate).
+	/// Values should be several bytes long, since any single byte matches under some key.
+	pub fn any_byte_key(value: T, aligned: bool) -> Self {
+		debug_assert!(!value.as_bytes().is_empty());
+
+		XorPredicate {
+			value,
+			keys: XorKeys::AnyByte,
+			aligned,
+			alignment_base: 0,
+		}
+	}
+
+	/// Creates a new predicate trying each of `keys`.
+	///
+	/// Keys longer than one byte, such as word-sized keys, are repeated over the value starting at its first byte.
+	/// Panics if a key is empty or longer than [`CANDIDATE_RECORDED_BYTES`].
+	pub fn with_keys(value: T, keys: Vec<Vec<u8>>, aligned: bool) -> Self {
+		debug_assert!(!value.as_bytes().is_empty());
+		assert!(keys
+			.iter()
+			.all(|key| (1..=CANDIDATE_RECORDED_BYTES).contains(&key.len())));
+
+		XorPredicate {
+			value,
+			keys: XorKeys::Fixed(keys),
+			aligned,
+			alignment_base: 0,
+		}
+	}
+
+	/// Measures the alignment from `base` instead of from address zero, see [`ValuePredicate::with_alignment_base`](super::value::ValuePredicate::with_alignment_base).
+	pub fn with_alignment_base(mut self, base: u64) -> Self {
+		self.alignment_base = base;
+		self
+	}
+
+	/// Returns the key under which `stored` decodes to the value, if there is one.
+	///
+	/// `stored` must be the matched bytes.
+	pub fn key_of(&self, stored: &[u8]) -> Option<Vec<u8>> {
+		let bytes = self.value.as_bytes();
+		if stored.len() != bytes.len() {
+			return None;
+		}
+
+		match &self.keys {
+			XorKeys::AnyByte => {
+				let key = stored[0] ^ bytes[0];
+				let matches = key != 0
+					&& stored
+						.iter()
+						.zip(bytes)
+						.all(|(stored, byte)| stored ^ key == *byte);
+
+				matches.then(|| vec![key])
+			}
+			XorKeys::Fixed(keys) => keys
+				.iter()
+				.find(|key| Self::key_matches(key, bytes, stored))
+				.cloned(),
+		}
+	}
+
+	/// Returns whether `stored` is a prefix of the value encoded using `key`.
+	fn key_matches(key: &[u8], bytes: &[u8], stored: &[u8]) -> bool {
+		stored
+			.iter()
+			.enumerate()
+			.all(|(index, stored)| stored ^ key[index % key.len()] == bytes[index])
+	}
+
+	fn offset_aligned(&self, offset: OffsetType) -> bool {
+		!self.aligned || is_aligned(offset, self.alignment_base, self.value.align_of())
+	}
+}
+impl<T: ByteComparable> ScannerPredicate for XorPredicate<T> {
+	fn try_start_candidate(&self, offset: OffsetType, byte: u8) -> Option<ScannerCandidate> {
+		if !self.offset_aligned(offset) {
+			return None;
+		}
+
+		let bytes = self.value.as_bytes();
+		let starts = match &self.keys {
+			XorKeys::AnyByte => byte != bytes[0],
+			XorKeys::Fixed(keys) => keys
+				.iter()
+				.any(|key| Self::key_matches(key, bytes, &[byte])),
+		};
+		if !starts {
+			return None;
+		}
+
+		let result = if bytes.len() == 1 {
+			ScannerCandidate::resolved(offset, NonZeroUsize::new(1).unwrap())
+		} else {
+			ScannerCandidate::normal(offset)
+		};
+
+		Some(result)
+	}
+
+	fn update_candidate(
+		&self,
+		_offset: OffsetType,
+		byte: u8,
+		candidate: &ScannerCandidate,
+	) -> UpdateCandidateResult {
+		let bytes = self.value.as_bytes();
+		let position = candidate.length().get();
+		debug_assert!(position < bytes.len());
+
+		let recorded = candidate.bytes();
+		let matches = match &self.keys {
+			XorKeys::AnyByte => byte ^ recorded[0] ^ bytes[0] == bytes[position],
+			// keys are at most as long as the recorded prefix, which therefore selects them exactly
+			XorKeys::Fixed(keys) => keys.iter().any(|key| {
+				Self::key_matches(key, bytes, recorded)
+					&& byte ^ key[position % key.len()] == bytes[position]
+			}),
+		};
+
+		if !matches {
+			return UpdateCandidateResult::Remove;
+		}
+
+		if position == bytes.len() - 1 {
+			return UpdateCandidateResult::Resolve;
+		}
+
+		UpdateCandidateResult::Advance
+	}
+}
+
+/// Reads the bytes of each of `results` and recovers the key they were obfuscated with.
+///
+/// Results whose bytes no longer decode to the value under any key are skipped.
+///
+/// ## Safety
+/// * See [`MemoryAccess::read`].
+pub unsafe fn resolve_keys<A: MemoryAccess + ?Sized, T: ByteComparable>(
+	access: &mut A,
+	predicate: &XorPredicate<T>,
+	results: impl IntoIterator<Item = ScanResult>,
+) -> Result<Vec<XorMatch>, ReadError> {
+	let mut matches = Vec::new();
+
+	let mut buffer = Vec::new();
+	for (offset, length) in results {
+		buffer.resize(length.get(), 0);
+		access.read(offset, &mut buffer)?;
+
+		if let Some(key) = predicate.key_of(&buffer) {
+			matches.push(XorMatch { offset, key });
+		}
+	}
+
+	Ok(matches)
+}
+
+#[cfg(test)]
+mod test {
+	use procmem_access::{platform::mock::MockMemoryAccess, prelude::OffsetType};
+
+	use super::{resolve_keys, XorMatch, XorPredicate};
+	use crate::{predicate::value::ByteComparable, stream::StreamScanner};
+
+	fn xor(value: u32, key: &[u8]) -> Vec<u8> {
+		value
+			.as_bytes()
+			.iter()
+			.enumerate()
+			.map(|(index, byte)| byte ^ key[index % key.len()])
+			.collect()
+	}
+
+	#[test]
+	fn test_xor_predicate() {
+		let mut data = vec![0u8; 32];
+		data[3..7].copy_from_slice(&xor(1000, &[0x5A]));
+		data[11..15].copy_from_slice(1000u32.as_bytes());
+		data[19..23].copy_from_slice(&xor(1000, &0xDEADBEEFu32.to_ne_bytes()));
+
+		// offset 4 is aligned since memory starts at offset 1
+		let predicate = XorPredicate::any_byte_key(1000u32, true);
+		let results: Vec<_> = StreamScanner::new(&predicate)
+			.scan_once(OffsetType::new_unwrap(1), data.iter().copied())
+			.collect();
+		let mut access =
+			MockMemoryAccess::new().with_region(OffsetType::new_unwrap(1), data.clone());
+		let matches = unsafe { resolve_keys(&mut access, &predicate, results).unwrap() };
+		assert_eq!(
+			matches,
+			[XorMatch {
+				offset: OffsetType::new_unwrap(4),
+				key: vec![0x5A]
+			}]
+		);
+
+		let predicate = XorPredicate::with_keys(
+			1000u32,
+			vec![vec![0x5A], 0xDEADBEEFu32.to_ne_bytes().to_vec()],
+			true,
+		);
+		let results: Vec<_> = StreamScanner::new(&predicate)
+			.scan_once(OffsetType::new_unwrap(1), data.iter().copied())
+			.collect();
+		let matches = unsafe { resolve_keys(&mut access, &predicate, results).unwrap() };
+		assert_eq!(
+			matches,
+			[
+				XorMatch {
+					offset: OffsetType::new_unwrap(4),
+					key: vec![0x5A]
+				},
+				XorMatch {
+					offset: OffsetType::new_unwrap(20),
+					key: 0xDEADBEEFu32.to_ne_bytes().to_vec()
+				}
+			]
+		);
+	}
+}
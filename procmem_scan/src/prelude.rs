@@ -1,8 +1,20 @@
 pub use crate::{
 	candidate::ScannerCandidate,
+	driver::{ScanConfig, ScanDriver, ScanTask, ScanThrottle, StepResult},
+	group::{GroupField, GroupPredicate},
+	parallel::ParallelScanner,
 	predicate::{
-		value::{ByteComparable, ValuePredicate},
+		comparison::{Comparison, ComparisonPredicate},
+		float::{FloatPredicate, FloatTolerance},
+		pattern::PatternPredicate,
+		value::{AsRawBytes, ByteComparable, EndianScalar, TargetBytes, ValuePredicate},
 		PartialScannerPredicate, ScannerPredicate,
 	},
+	progress::ScanProgress,
+	results::ScanResultSet,
+	session::ScanSession,
+	snapshot::{MemorySnapshot, SnapshotComparison},
 	stream::StreamScanner,
+	targets::Targets,
+	tracker::MatchTracker,
 };
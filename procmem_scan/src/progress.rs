@@ -0,0 +1,88 @@
+//! Progress reporting and cancellation of running scans.
+//!
+//! A [`ScanProgress`] is a cheaply cloneable handle shared between the thread running a scan and the threads observing it.
+//! The scan updates the counters after each chunk and stops at the next chunk boundary once the handle is [cancelled](ScanProgress::cancel),
+//! so even scans of a single huge page can be aborted.
+
+use std::sync::{
+	atomic::{AtomicBool, AtomicU64, Ordering},
+	Arc,
+};
+
+#[derive(Debug, Default)]
+struct ProgressState {
+	total_bytes: AtomicU64,
+	bytes_scanned: AtomicU64,
+	pages_done: AtomicU64,
+	matches: AtomicU64,
+	cancelled: AtomicBool,
+}
+
+/// Shared handle to the progress of a scan, see [`ScanDriver::scan_with_progress`](crate::driver::ScanDriver::scan_with_progress).
+#[derive(Debug, Clone, Default)]
+pub struct ScanProgress {
+	state: Arc<ProgressState>,
+}
+impl ScanProgress {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Total number of bytes in the scanned ranges.
+	pub fn total_bytes(&self) -> u64 {
+		self.state.total_bytes.load(Ordering::Relaxed)
+	}
+
+	/// Number of bytes scanned or skipped so far.
+	pub fn bytes_scanned(&self) -> u64 {
+		self.state.bytes_scanned.load(Ordering::Relaxed)
+	}
+
+	/// Number of scanned ranges, usually memory pages, which were completely scanned or skipped.
+	pub fn pages_done(&self) -> u64 {
+		self.state.pages_done.load(Ordering::Relaxed)
+	}
+
+	/// Number of matches found so far.
+	pub fn matches(&self) -> u64 {
+		self.state.matches.load(Ordering::Relaxed)
+	}
+
+	/// Returns the scanned fraction between `0.0` and `1.0`.
+	pub fn fraction(&self) -> f64 {
+		match self.total_bytes() {
+			0 => 0.0,
+			total => self.bytes_scanned() as f64 / total as f64,
+		}
+	}
+
+	/// Requests the scan to stop at the next chunk boundary.
+	///
+	/// The handle stays cancelled, use a new one for the next scan.
+	pub fn cancel(&self) {
+		self.state.cancelled.store(true, Ordering::Relaxed);
+	}
+
+	pub fn is_cancelled(&self) -> bool {
+		self.state.cancelled.load(Ordering::Relaxed)
+	}
+
+	/// Resets the counters before a new scan of `total_bytes`.
+	pub(crate) fn start(&self, total_bytes: u64) {
+		self.state.total_bytes.store(total_bytes, Ordering::Relaxed);
+		self.state.bytes_scanned.store(0, Ordering::Relaxed);
+		self.state.pages_done.store(0, Ordering::Relaxed);
+		self.state.matches.store(0, Ordering::Relaxed);
+	}
+
+	pub(crate) fn set_position(&self, bytes_scanned: u64, pages_done: u64) {
+		self.state
+			.bytes_scanned
+			.store(bytes_scanned, Ordering::Relaxed);
+		self.state.pages_done.store(pages_done, Ordering::Relaxed);
+	}
+
+	pub(crate) fn add_match(&self) {
+		self.state.matches.fetch_add(1, Ordering::Relaxed);
+	}
+}
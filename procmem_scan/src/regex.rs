@@ -0,0 +1,337 @@
+//! Scanning for byte-oriented regular expressions.
+//!
+//! Predicates only see the offset and length of their candidates, which is not enough to track the state of a regular expression.
+//! The [`RegexScanner`] is therefore a dedicated scanner. It compiles the expression into a DFA and advances one DFA state per
+//! possible match start, so the memory is streamed byte by byte without backtracking and matches may span chunk boundaries.
+//!
+//! Matches are reported leftmost-first and non-overlapping, the same way as `regex::bytes::Regex::find_iter`, except empty matches are skipped.
+
+use std::num::NonZeroUsize;
+
+use regex_automata::{
+	dfa::{dense, Automaton, StartKind},
+	nfa::thompson,
+	util::{primitives::StateID, start, syntax},
+	Anchored,
+};
+use thiserror::Error;
+
+use procmem_access::{
+	memory::{access::ReadError, chunked::ChunkedReader},
+	prelude::{ErrorKind, MemoryAccess, OffsetType, ProcmemError},
+};
+
+use crate::stream::ScanResult;
+
+#[derive(Debug, Error)]
+pub enum RegexError {
+	#[error("could not compile regular expression")]
+	Build(#[source] Box<dense::BuildError>),
+}
+impl RegexError {
+	pub fn kind(&self) -> ErrorKind {
+		ErrorKind::Parse
+	}
+}
+impl From<RegexError> for ProcmemError {
+	fn from(err: RegexError) -> Self {
+		ProcmemError::new(err.kind(), err)
+	}
+}
+
+/// Possible match starting at `start`.
+#[derive(Debug, Clone, Copy)]
+struct Thread {
+	start: OffsetType,
+	state: StateID,
+	/// End of the longest match found so far, exclusive.
+	match_end: Option<OffsetType>,
+}
+
+/// Scans streams of bytes for matches of a regular expression.
+///
+/// The expression is matched against raw bytes, Unicode classes are disabled and `.` matches any byte except `\n`
+/// (use `(?s)` to match it too).
+pub struct RegexScanner {
+	dfa: dense::DFA<Vec<u32>>,
+	max_length: usize,
+	/// Threads ordered by start offset.
+	threads: Vec<Thread>,
+	/// Offset of the next byte of the current sequence and the byte before it.
+	position: Option<(OffsetType, u8)>,
+}
+impl RegexScanner {
+	/// Default value of [`max_length`](RegexScanner::max_length).
+	pub const DEFAULT_MAX_LENGTH: usize = 4096;
+
+	pub fn new(pattern: &str) -> Result<Self, RegexError> {
+		let dfa = dense::Builder::new()
+			.syntax(syntax::Config::new().unicode(false).utf8(false))
+			.thompson(thompson::Config::new().utf8(false))
+			.configure(dense::Config::new().start_kind(StartKind::Anchored))
+			.build(pattern)
+			.map_err(|err| RegexError::Build(Box::new(err)))?;
+
+		Ok(RegexScanner {
+			dfa,
+			max_length: Self::DEFAULT_MAX_LENGTH,
+			threads: Vec::new(),
+			position: None,
+		})
+	}
+
+	/// Sets the maximum length of a match.
+	///
+	/// Only matches up to this length are found, which bounds the time spent on expressions like `.*`.
+	///
+	/// ## Panics
+	/// * If `max_length` is zero.
+	pub fn with_max_length(mut self, max_length: usize) -> Self {
+		assert!(max_length > 0);
+
+		self.max_length = max_length;
+		self
+	}
+
+	pub const fn max_length(&self) -> usize {
+		self.max_length
+	}
+
+	/// Drops the progress of the current sequence without reporting its pending matches.
+	pub fn reset(&mut self) {
+		self.threads.clear();
+		self.position = None;
+	}
+
+	/// Scans `bytes` as one whole sequence.
+	pub fn scan_once(
+		&mut self,
+		offset: OffsetType,
+		bytes: impl IntoIterator<Item = u8>,
+	) -> Vec<ScanResult> {
+		self.reset();
+
+		let mut found = self.scan_continue(offset, bytes);
+		found.extend(self.finish());
+
+		found
+	}
+
+	/// Scans `bytes` continuing the current sequence, returning the matches which are already certain.
+	///
+	/// If `offset` does not follow the previous call then the current sequence is [finished](RegexScanner::finish) first.
+	pub fn scan_continue(
+		&mut self,
+		offset: OffsetType,
+		bytes: impl IntoIterator<Item = u8>,
+	) -> Vec<ScanResult> {
+		let mut found = Vec::new();
+		if matches!(self.position, Some((next, _)) if next != offset) {
+			found.extend(self.finish());
+		}
+
+		let mut offset = offset;
+		for byte in bytes {
+			self.on_byte(offset, byte, &mut found);
+			offset = offset.saturating_add(1);
+		}
+
+		found
+	}
+
+	/// Ends the current sequence and returns the remaining matches.
+	pub fn finish(&mut self) -> Vec<ScanResult> {
+		let mut found = Vec::new();
+
+		if let Some((end, _)) = self.position.take() {
+			for thread in self.threads.iter_mut() {
+				let length = end.get() - thread.start.get();
+				if length > 0
+					&& length <= self.max_length as u64
+					&& self
+						.dfa
+						.is_match_state(self.dfa.next_eoi_state(thread.state))
+				{
+					thread.match_end = Some(end);
+				}
+			}
+			self.emit(true, &mut found);
+		}
+		self.threads.clear();
+
+		found
+	}
+
+	/// Scans `ranges` of memory read through `access` in chunks of `chunk_size` bytes, calling `on_result` for each match.
+	///
+	/// Each range is a separate sequence, matches do not span across ranges.
+	///
+	/// ## Safety
+	/// * See [`MemoryAccess::read`].
+	pub unsafe fn scan<A: MemoryAccess + ?Sized>(
+		&mut self,
+		access: &mut A,
+		ranges: impl IntoIterator<Item = [OffsetType; 2]>,
+		chunk_size: usize,
+		mut on_result: impl FnMut(ScanResult),
+	) -> Result<(), ReadError> {
+		let mut reader = ChunkedReader::new(chunk_size);
+
+		for range in ranges {
+			self.reset();
+			reader.read(access, range, |chunk| {
+				self.scan_continue(chunk.offset, chunk.bytes.iter().copied())
+					.into_iter()
+					.for_each(&mut on_result)
+			})?;
+			self.finish().into_iter().for_each(&mut on_result);
+		}
+
+		Ok(())
+	}
+
+	fn on_byte(&mut self, offset: OffsetType, byte: u8, found: &mut Vec<ScanResult>) {
+		let look_behind = self.position.map(|(_, previous)| previous);
+		let config = start::Config::new()
+			.anchored(Anchored::Yes)
+			.look_behind(look_behind);
+		if let Ok(state) = self.dfa.start_state(&config) {
+			self.threads.push(Thread {
+				start: offset,
+				state,
+				match_end: None,
+			});
+		}
+
+		let dfa = &self.dfa;
+		let max_length = self.max_length as u64;
+		self.threads.retain_mut(|thread| {
+			thread.state = dfa.next_state(thread.state, byte);
+			// matches are reported by the DFA one byte late
+			let length = offset.get() - thread.start.get();
+			if length > 0 && length <= max_length && dfa.is_match_state(thread.state) {
+				thread.match_end = Some(offset);
+			}
+
+			!dfa.is_dead_state(thread.state) && !dfa.is_quit_state(thread.state)
+				|| thread.match_end.is_some()
+		});
+
+		// threads in the same state without a match so far are equivalent, keep the earliest one
+		let mut index = 1;
+		while index < self.threads.len() {
+			let thread = self.threads[index];
+			let duplicate = thread.match_end.is_none()
+				&& self.threads[..index]
+					.iter()
+					.any(|earlier| earlier.state == thread.state && earlier.match_end.is_none());
+			if duplicate {
+				self.threads.remove(index);
+			} else {
+				index += 1;
+			}
+		}
+
+		self.position = Some((offset.saturating_add(1), byte));
+		self.emit(false, found);
+	}
+
+	/// Emits matches of the leading threads which cannot grow anymore.
+	fn emit(&mut self, finished: bool, found: &mut Vec<ScanResult>) {
+		let next = self.position.map(|(next, _)| next);
+
+		while let Some(first) = self.threads.first().copied() {
+			// a longer match would end after the next byte
+			let length_exceeded = next
+				.map(|next| next.get() - first.start.get() > self.max_length as u64)
+				.unwrap_or(false);
+			let done = finished
+				|| length_exceeded
+				|| self.dfa.is_dead_state(first.state)
+				|| self.dfa.is_quit_state(first.state);
+
+			match first.match_end {
+				// the leftmost match is certain, threads starting inside it can never be reported
+				Some(end) => {
+					if !done {
+						self.threads
+							.retain(|thread| thread.start == first.start || thread.start >= end);
+						return;
+					}
+
+					let length = (end.get() - first.start.get()) as usize;
+					found.push((first.start, NonZeroUsize::new(length).unwrap()));
+
+					self.threads.retain(|thread| thread.start >= end);
+				}
+				None if done => {
+					self.threads.remove(0);
+				}
+				None => return,
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use procmem_access::{platform::mock::MockMemoryAccess, prelude::OffsetType};
+
+	use super::RegexScanner;
+
+	fn found(results: Vec<super::ScanResult>) -> Vec<(u64, usize)> {
+		results
+			.into_iter()
+			.map(|(offset, length)| (offset.get() - 0x1000, length.get()))
+			.collect()
+	}
+
+	#[test]
+	fn test_regex_scanner_chunks() {
+		let data = b"xx user=alice; user=bob;\x00\xFFuser=\x01";
+		let base = OffsetType::new_unwrap(0x1000);
+
+		let mut scanner = RegexScanner::new(r"user=[a-z]+;").unwrap();
+		let whole = found(scanner.scan_once(base, data.iter().copied()));
+		assert_eq!(whole, [(3, 11), (15, 9)]);
+
+		// the same matches are found when the data is split at any point
+		for split in 1..data.len() {
+			let mut results = scanner.scan_continue(base, data[..split].iter().copied());
+			results.extend(scanner.scan_continue(
+				base.saturating_add(split as u64),
+				data[split..].iter().copied(),
+			));
+			results.extend(scanner.finish());
+			assert_eq!(found(results), whole, "split at {}", split);
+		}
+
+		// leftmost-first, non-overlapping and byte oriented
+		let mut scanner = RegexScanner::new(r"a+|\xFF").unwrap();
+		assert_eq!(
+			found(scanner.scan_once(base, b"baaab\xFFaa".iter().copied())),
+			[(1, 3), (5, 1), (6, 2)]
+		);
+
+		let mut scanner = RegexScanner::new(r"(?s).+").unwrap().with_max_length(4);
+		assert_eq!(
+			found(scanner.scan_once(base, [1u8; 10])),
+			[(0, 4), (4, 4), (8, 2)]
+		);
+
+		let mut access = MockMemoryAccess::new().with_region(base, data.to_vec());
+		let mut scanner = RegexScanner::new(r"user=[a-z]+").unwrap();
+		let mut results = Vec::new();
+		unsafe {
+			scanner
+				.scan(
+					&mut access,
+					[[base, base.saturating_add(data.len() as u64)]],
+					4,
+					|result| results.push(result),
+				)
+				.unwrap();
+		}
+		assert_eq!(found(results), [(3, 10), (15, 8)]);
+	}
+}
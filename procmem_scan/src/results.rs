@@ -0,0 +1,509 @@
+//! Sets of scan results.
+//!
+//! A [`ScanResultSet`] keeps results sorted by offset, so combining scans is a linear merge of two sets.
+//! Results are identified by their offset, when both operands contain an offset the length of the left one is kept.
+//!
+//! The current values of the results can be re-read through a [`MemoryAccess`], either to filter the set by a new predicate
+//! or to inspect them as typed values. With the `serde` feature the set serializes as a list of `[offset, length]` pairs.
+
+use std::{
+	iter::FromIterator,
+	num::NonZeroUsize,
+	ops::{BitAnd, BitOr, BitXor, Sub},
+};
+
+use procmem_access::{
+	memory::access::ReadError,
+	prelude::{MemoryAccess, OffsetType},
+	util::KMergeIter,
+};
+
+use crate::{
+	predicate::{value::NumericScalar, ScannerPredicate},
+	stream::{ScanResult, StreamScanner},
+};
+
+/// Sorted set of [`ScanResult`]s with unique offsets.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct ScanResultSet {
+	results: Vec<ScanResult>,
+}
+impl ScanResultSet {
+	pub const fn new() -> Self {
+		ScanResultSet {
+			results: Vec::new(),
+		}
+	}
+
+	pub fn len(&self) -> usize {
+		self.results.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.results.is_empty()
+	}
+
+	/// Returns the results ordered by offset.
+	pub fn as_slice(&self) -> &[ScanResult] {
+		&self.results
+	}
+
+	pub fn iter(&self) -> std::slice::Iter<'_, ScanResult> {
+		self.results.iter()
+	}
+
+	pub fn offsets(&self) -> impl Iterator<Item = OffsetType> + '_ {
+		self.results.iter().map(|(offset, _)| *offset)
+	}
+
+	pub fn contains(&self, offset: OffsetType) -> bool {
+		self.get(offset).is_some()
+	}
+
+	/// Returns the result at `offset`.
+	pub fn get(&self, offset: OffsetType) -> Option<ScanResult> {
+		self.results
+			.binary_search_by_key(&offset, |(offset, _)| *offset)
+			.ok()
+			.map(|index| self.results[index])
+	}
+
+	/// Inserts a result, returning `false` if a result at the same offset was already present.
+	pub fn insert(&mut self, result: ScanResult) -> bool {
+		match self
+			.results
+			.binary_search_by_key(&result.0, |(offset, _)| *offset)
+		{
+			Ok(_) => false,
+			Err(index) => {
+				self.results.insert(index, result);
+				true
+			}
+		}
+	}
+
+	pub fn remove(&mut self, offset: OffsetType) -> Option<ScanResult> {
+		let index = self
+			.results
+			.binary_search_by_key(&offset, |(offset, _)| *offset)
+			.ok()?;
+
+		Some(self.results.remove(index))
+	}
+
+	pub fn retain(&mut self, predicate: impl FnMut(&ScanResult) -> bool) {
+		self.results.retain(predicate);
+	}
+
+	pub fn clear(&mut self) {
+		self.results.clear();
+	}
+
+	/// Returns results present in either set.
+	pub fn union(&self, other: &Self) -> Self {
+		self.merge(other, true, true, true)
+	}
+
+	/// Returns results present in both sets.
+	pub fn intersection(&self, other: &Self) -> Self {
+		self.merge(other, false, true, false)
+	}
+
+	/// Returns results present in `self` but not in `other`.
+	pub fn difference(&self, other: &Self) -> Self {
+		self.merge(other, true, false, false)
+	}
+
+	/// Returns results present in exactly one of the sets.
+	pub fn symmetric_difference(&self, other: &Self) -> Self {
+		self.merge(other, true, false, true)
+	}
+
+	/// Returns results which start inside any of the sorted, non-overlapping `ranges`.
+	///
+	/// Use this with the pages of a module to restrict results to it.
+	pub fn within_ranges(&self, ranges: impl IntoIterator<Item = [OffsetType; 2]>) -> Self {
+		self.split_by_ranges(ranges).0
+	}
+
+	/// Returns results which do not start inside any of the sorted, non-overlapping `ranges`.
+	///
+	/// Use this with the pages of a module to remove everything in it.
+	pub fn outside_ranges(&self, ranges: impl IntoIterator<Item = [OffsetType; 2]>) -> Self {
+		self.split_by_ranges(ranges).1
+	}
+
+	fn split_by_ranges(&self, ranges: impl IntoIterator<Item = [OffsetType; 2]>) -> (Self, Self) {
+		let (mut inside, mut outside) = (Vec::new(), Vec::new());
+
+		let mut ranges = ranges.into_iter().peekable();
+		for result in self.results.iter().copied() {
+			while ranges.next_if(|range| range[1] <= result.0).is_some() {}
+
+			match ranges.peek() {
+				Some(range) if range[0] <= result.0 => inside.push(result),
+				_ => outside.push(result),
+			}
+		}
+
+		(
+			ScanResultSet { results: inside },
+			ScanResultSet { results: outside },
+		)
+	}
+
+	/// Number of results read with a single [`MemoryAccess::read_v`] call.
+	pub const READ_BATCH_SIZE: usize = 256;
+
+	/// Reads the current bytes of each result, `length` bytes at its offset, and calls `on_value` with them.
+	///
+	/// Results are read in batches of [`READ_BATCH_SIZE`](ScanResultSet::READ_BATCH_SIZE) through [`MemoryAccess::read_v`].
+	/// If a batch fails its results are read one by one, so results which cannot be read are passed their own read error.
+	///
+	/// ## Safety
+	/// * See [`MemoryAccess::read`].
+	pub unsafe fn read_values<A: MemoryAccess + ?Sized>(
+		&self,
+		access: &mut A,
+		mut on_value: impl FnMut(ScanResult, Result<&[u8], ReadError>),
+	) {
+		let mut buffer = Vec::new();
+		for batch in self.results.chunks(Self::READ_BATCH_SIZE) {
+			buffer.resize(batch.iter().map(|(_, length)| length.get()).sum(), 0);
+
+			let mut requests = Vec::with_capacity(batch.len());
+			let mut rest = buffer.as_mut_slice();
+			for (offset, length) in batch.iter().copied() {
+				let (value, tail) = rest.split_at_mut(length.get());
+				requests.push((offset, value));
+				rest = tail;
+			}
+
+			if access.read_v(&mut requests).is_ok() {
+				for (result, (_, value)) in batch.iter().copied().zip(requests) {
+					on_value(result, Ok(value));
+				}
+				continue;
+			}
+
+			for (result, (offset, value)) in batch.iter().copied().zip(requests) {
+				match access.read(offset, value) {
+					Ok(()) => on_value(result, Ok(value)),
+					Err(err) => on_value(result, Err(err)),
+				}
+			}
+		}
+	}
+
+	/// Re-reads the results and returns those at which `predicate` still matches, with the length of the new match.
+	///
+	/// Only `length` bytes of each result are read, so the predicate must not match more bytes than the original scan.
+	/// Results which cannot be read are dropped.
+	///
+	/// ## Safety
+	/// * See [`MemoryAccess::read`].
+	pub unsafe fn filter<A: MemoryAccess + ?Sized, P: ScannerPredicate>(
+		&self,
+		access: &mut A,
+		predicate: P,
+	) -> Self {
+		let mut scanner = StreamScanner::new(predicate);
+
+		let mut results = Vec::new();
+		self.read_values(access, |(offset, _), bytes| {
+			if let Ok(bytes) = bytes {
+				if let Some(found) = scanner
+					.scan_once(offset, bytes.iter().copied())
+					.find(|(found, _)| *found == offset)
+				{
+					results.push(found);
+				}
+			}
+		});
+
+		ScanResultSet { results }
+	}
+
+	/// Reads the current value of each result as `T`, `None` if it cannot be read.
+	///
+	/// The values are read from the start of each result regardless of its length.
+	///
+	/// ## Safety
+	/// * See [`MemoryAccess::read`].
+	pub unsafe fn read_typed<T: NumericScalar, A: MemoryAccess + ?Sized>(
+		&self,
+		access: &mut A,
+	) -> Vec<(OffsetType, Option<T>)> {
+		let size = std::mem::size_of::<T>();
+		let typed = ScanResultSet {
+			results: self
+				.offsets()
+				.map(|offset| (offset, std::num::NonZeroUsize::new(size).unwrap()))
+				.collect(),
+		};
+
+		let mut values = Vec::with_capacity(self.results.len());
+		typed.read_values(access, |(offset, _), bytes| {
+			values.push((offset, bytes.ok().map(T::from_ne_slice)))
+		});
+
+		values
+	}
+
+	/// Merges two sorted sets, keeping results only in `self`, in both or only in `other` as requested.
+	fn merge(&self, other: &Self, keep_left: bool, keep_both: bool, keep_right: bool) -> Self {
+		// tagging with the side orders left before right on equal offsets, independent of the lengths
+		fn tagged(
+			results: &[ScanResult],
+			right: bool,
+		) -> impl Iterator<Item = (OffsetType, bool, NonZeroUsize)> + '_ {
+			results
+				.iter()
+				.map(move |&(offset, length)| (offset, right, length))
+		}
+
+		let mut merged =
+			KMergeIter::new([tagged(&self.results, false), tagged(&other.results, true)])
+				.peekable();
+		let mut results = Vec::new();
+		while let Some((offset, right, length)) = merged.next() {
+			let in_both = !right && merged.next_if(|&(next, ..)| next == offset).is_some();
+
+			let keep = match (in_both, right) {
+				(true, _) => keep_both,
+				(false, false) => keep_left,
+				(false, true) => keep_right,
+			};
+			if keep {
+				results.push((offset, length));
+			}
+		}
+
+		ScanResultSet { results }
+	}
+}
+impl FromIterator<ScanResult> for ScanResultSet {
+	/// Collects results in any order, keeping the first result for each offset.
+	fn from_iter<I: IntoIterator<Item = ScanResult>>(iter: I) -> Self {
+		let mut results: Vec<ScanResult> = iter.into_iter().collect();
+		// stable sort keeps the first of duplicates in front for dedup
+		results.sort_by_key(|(offset, _)| *offset);
+		results.dedup_by_key(|(offset, _)| *offset);
+
+		ScanResultSet { results }
+	}
+}
+impl Extend<ScanResult> for ScanResultSet {
+	fn extend<I: IntoIterator<Item = ScanResult>>(&mut self, iter: I) {
+		*self = self.union(&iter.into_iter().collect());
+	}
+}
+impl IntoIterator for ScanResultSet {
+	type Item = ScanResult;
+	type IntoIter = std::vec::IntoIter<ScanResult>;
+
+	fn into_iter(self) -> Self::IntoIter {
+		self.results.into_iter()
+	}
+}
+impl<'a> IntoIterator for &'a ScanResultSet {
+	type Item = &'a ScanResult;
+	type IntoIter = std::slice::Iter<'a, ScanResult>;
+
+	fn into_iter(self) -> Self::IntoIter {
+		self.results.iter()
+	}
+}
+impl BitOr for &ScanResultSet {
+	type Output = ScanResultSet;
+
+	fn bitor(self, rhs: Self) -> ScanResultSet {
+		self.union(rhs)
+	}
+}
+impl BitAnd for &ScanResultSet {
+	type Output = ScanResultSet;
+
+	fn bitand(self, rhs: Self) -> ScanResultSet {
+		self.intersection(rhs)
+	}
+}
+impl Sub for &ScanResultSet {
+	type Output = ScanResultSet;
+
+	fn sub(self, rhs: Self) -> ScanResultSet {
+		self.difference(rhs)
+	}
+}
+impl BitXor for &ScanResultSet {
+	type Output = ScanResultSet;
+
+	fn bitxor(self, rhs: Self) -> ScanResultSet {
+		self.symmetric_difference(rhs)
+	}
+}
+
+#[cfg(feature = "serde")]
+mod serialization {
+	use std::num::NonZeroUsize;
+
+	use serde::{de::Error, ser::SerializeSeq, Deserialize, Deserializer, Serialize, Serializer};
+
+	use procmem_access::prelude::OffsetType;
+
+	use super::ScanResultSet;
+
+	impl Serialize for ScanResultSet {
+		fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+			let mut seq = serializer.serialize_seq(Some(self.len()))?;
+			for (offset, length) in self.iter() {
+				seq.serialize_element(&(offset.get(), length.get()))?;
+			}
+
+			seq.end()
+		}
+	}
+
+	impl<'de> Deserialize<'de> for ScanResultSet {
+		fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+			let pairs = Vec::<(u64, usize)>::deserialize(deserializer)?;
+
+			pairs
+				.into_iter()
+				.map(|(offset, length)| {
+					match (OffsetType::new(offset), NonZeroUsize::new(length)) {
+						(Some(offset), Some(length)) => Ok((offset, length)),
+						_ => Err(D::Error::custom("offset and length must not be zero")),
+					}
+				})
+				.collect()
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use std::num::NonZeroUsize;
+
+	use procmem_access::{
+		platform::mock::MockMemoryAccess,
+		prelude::{ErrorKind, MemoryAccess, OffsetType},
+	};
+
+	use super::ScanResultSet;
+	use crate::predicate::value::ValuePredicate;
+
+	fn set(results: &[(u64, usize)]) -> ScanResultSet {
+		results
+			.iter()
+			.map(|(offset, length)| {
+				(
+					OffsetType::new_unwrap(*offset),
+					NonZeroUsize::new(*length).unwrap(),
+				)
+			})
+			.collect()
+	}
+
+	fn offsets(set: &ScanResultSet) -> Vec<u64> {
+		set.offsets().map(|offset| offset.get()).collect()
+	}
+
+	#[test]
+	fn test_result_set_algebra() {
+		let a = set(&[(40, 4), (10, 4), (20, 4), (30, 4), (10, 8)]);
+		let b = set(&[(20, 2), (30, 2), (50, 2)]);
+		assert_eq!(a.len(), 4);
+		assert_eq!(a.get(OffsetType::new_unwrap(10)).unwrap().1.get(), 4);
+
+		assert_eq!(offsets(&(&a | &b)), [10, 20, 30, 40, 50]);
+		assert_eq!(offsets(&(&a & &b)), [20, 30]);
+		assert_eq!((&a & &b).as_slice()[0].1.get(), 4);
+		assert_eq!(offsets(&(&a - &b)), [10, 40]);
+		assert_eq!(offsets(&(&a ^ &b)), [10, 40, 50]);
+
+		let module = [
+			[OffsetType::new_unwrap(15), OffsetType::new_unwrap(21)],
+			[OffsetType::new_unwrap(40), OffsetType::new_unwrap(60)],
+		];
+		assert_eq!(offsets(&(&a | &b).outside_ranges(module)), [10, 30]);
+		assert_eq!(offsets(&(&a | &b).within_ranges(module)), [20, 40, 50]);
+	}
+
+	#[test]
+	fn test_result_set_reread() {
+		let mut data = vec![0u8; 32];
+		data[0..4].copy_from_slice(&5u32.to_ne_bytes());
+		data[8..12].copy_from_slice(&7u32.to_ne_bytes());
+		data[16..20].copy_from_slice(&5u32.to_ne_bytes());
+		let mut access = MockMemoryAccess::new().with_region(OffsetType::new_unwrap(0x100), data);
+
+		let results = set(&[(0x100, 4), (0x108, 4), (0x110, 4), (0x200, 4)]);
+		let filtered = unsafe { results.filter(&mut access, ValuePredicate::new(5u32, true)) };
+		assert_eq!(offsets(&filtered), [0x100, 0x110]);
+
+		let values = unsafe { results.read_typed::<u32, _>(&mut access) };
+		assert_eq!(
+			values
+				.iter()
+				.map(|(offset, value)| (offset.get(), *value))
+				.collect::<Vec<_>>(),
+			[
+				(0x100, Some(5)),
+				(0x108, Some(7)),
+				(0x110, Some(5)),
+				(0x200, None)
+			]
+		);
+
+		#[cfg(feature = "serde")]
+		{
+			let json = serde_json::to_string(&filtered).unwrap();
+			assert_eq!(json, "[[256,4],[272,4]]");
+			assert_eq!(
+				serde_json::from_str::<ScanResultSet>(&json).unwrap(),
+				filtered
+			);
+			assert!(serde_json::from_str::<ScanResultSet>("[[0,4]]").is_err());
+		}
+	}
+
+	#[test]
+	fn test_result_set_reread_partly_unmapped() {
+		// the middle of the region is unreadable and the last result crosses its end
+		let data: Vec<u8> = (0..0x40).collect();
+		let mut access = MockMemoryAccess::new()
+			.with_region(OffsetType::new_unwrap(0x1000), data)
+			.with_fault([
+				OffsetType::new_unwrap(0x1010),
+				OffsetType::new_unwrap(0x1020),
+			]);
+		let results = set(&[(0x1004, 2), (0x1012, 2), (0x1030, 2), (0x103F, 2)]);
+
+		// the whole batch fails, so the results are read one by one
+		let mut buffer = [0u8; 2];
+		assert!(
+			unsafe { access.read_v(&mut [(OffsetType::new_unwrap(0x1012), &mut buffer[..])]) }
+				.is_err()
+		);
+
+		let mut values = Vec::new();
+		unsafe {
+			results.read_values(&mut access, |(offset, _), bytes| {
+				values.push((
+					offset.get(),
+					bytes.map(<[u8]>::to_vec).map_err(|err| err.kind()),
+				))
+			})
+		};
+		assert_eq!(
+			values,
+			[
+				(0x1004, Ok(vec![4, 5])),
+				(0x1012, Err(ErrorKind::NotMapped)),
+				(0x1030, Ok(vec![0x30, 0x31])),
+				(0x103F, Err(ErrorKind::NotMapped))
+			]
+		);
+	}
+}
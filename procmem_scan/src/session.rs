@@ -0,0 +1,148 @@
+//! Iterative scan sessions.
+//!
+//! A session holds the current set of results which is narrowed down by consecutive scans.
+//! The current set can be saved as a named checkpoint and restored later, so an over-aggressive narrowing step does not lose the session.
+
+use crate::{results::ScanResultSet, stream::ScanResult};
+
+/// Current results of an iterative scan together with named checkpoints.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ScanSession {
+	results: ScanResultSet,
+	/// Checkpoints in the order they were created.
+	checkpoints: Vec<(String, ScanResultSet)>,
+}
+impl ScanSession {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Creates a new session starting with `results`, for example from an initial scan.
+	pub fn with_results(results: ScanResultSet) -> Self {
+		ScanSession {
+			results,
+			checkpoints: Vec::new(),
+		}
+	}
+
+	pub fn results(&self) -> &ScanResultSet {
+		&self.results
+	}
+
+	/// Replaces the current results, for example with the results of a rescan.
+	pub fn set_results(&mut self, results: ScanResultSet) {
+		self.results = results;
+	}
+
+	/// Keeps only results for which `predicate` returns true.
+	pub fn narrow(&mut self, predicate: impl FnMut(&ScanResult) -> bool) {
+		self.results.retain(predicate);
+	}
+
+	/// Saves the current results as checkpoint `name`, replacing any checkpoint of the same name.
+	pub fn checkpoint(&mut self, name: impl Into<String>) {
+		let name = name.into();
+		self.remove_checkpoint(&name);
+
+		self.checkpoints.push((name, self.results.clone()));
+	}
+
+	/// Restores the results saved in checkpoint `name`.
+	///
+	/// The checkpoint is kept so it can be rolled back to again. Returns `false` if there is no such checkpoint.
+	pub fn rollback(&mut self, name: &str) -> bool {
+		match self.checkpoint_results(name) {
+			None => false,
+			Some(results) => {
+				self.results = results.clone();
+				true
+			}
+		}
+	}
+
+	/// Returns the results saved in checkpoint `name`.
+	pub fn checkpoint_results(&self, name: &str) -> Option<&ScanResultSet> {
+		self.checkpoints
+			.iter()
+			.find(|(checkpoint, _)| checkpoint == name)
+			.map(|(_, results)| results)
+	}
+
+	/// Returns checkpoint names in the order they were created.
+	pub fn checkpoints(&self) -> impl Iterator<Item = &str> {
+		self.checkpoints.iter().map(|(name, _)| name.as_str())
+	}
+
+	pub fn remove_checkpoint(&mut self, name: &str) -> Option<ScanResultSet> {
+		let index = self
+			.checkpoints
+			.iter()
+			.position(|(checkpoint, _)| checkpoint == name)?;
+
+		Some(self.checkpoints.remove(index).1)
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use std::num::NonZeroUsize;
+
+	use procmem_access::prelude::OffsetType;
+
+	use super::ScanSession;
+
+	#[test]
+	fn test_session_rollback() {
+		let mut session = ScanSession::with_results(
+			(1..=8)
+				.map(|offset| {
+					(
+						OffsetType::new_unwrap(offset * 4),
+						NonZeroUsize::new(4).unwrap(),
+					)
+				})
+				.collect(),
+		);
+
+		session.checkpoint("start");
+		session.narrow(|(offset, _)| offset.get() % 8 == 0);
+		assert_eq!(session.results().len(), 4);
+
+		session.checkpoint("even");
+		session.narrow(|_| false);
+		assert!(session.results().is_empty());
+
+		assert!(session.rollback("even"));
+		assert_eq!(session.results().len(), 4);
+		assert!(session.rollback("start"));
+		assert_eq!(session.results().len(), 8);
+		assert!(!session.rollback("missing"));
+
+		session.checkpoint("even");
+		assert_eq!(session.checkpoints().collect::<Vec<_>>(), ["start", "even"]);
+		assert_eq!(session.checkpoint_results("even").unwrap().len(), 8);
+	}
+	#[cfg(feature = "serde")]
+	#[test]
+	fn test_session_serde() {
+		let mut session = ScanSession::with_results(
+			[3, 9, 27]
+				.into_iter()
+				.map(|offset| {
+					(
+						OffsetType::new_unwrap(offset),
+						NonZeroUsize::new(2).unwrap(),
+					)
+				})
+				.collect(),
+		);
+		session.checkpoint("all");
+		session.narrow(|(offset, _)| offset.get() > 3);
+
+		let json = serde_json::to_string(&session).unwrap();
+		let loaded: ScanSession = serde_json::from_str(&json).unwrap();
+		assert_eq!(loaded, session);
+		assert_eq!(loaded.checkpoint_results("all").unwrap().len(), 3);
+	}
+}
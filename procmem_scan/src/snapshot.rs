@@ -0,0 +1,316 @@
+//! Snapshots of memory contents for scanning values with an unknown initial value.
+//!
+//! The usual workflow is to capture a [`MemorySnapshot`] of the interesting pages, let the value change in the target,
+//! capture another snapshot and [`compare`](MemorySnapshot::compare) the two, for example keeping only values which decreased.
+//! Consecutive comparisons narrow down the results with [`compare_results`](MemorySnapshot::compare_results).
+//!
+//! Snapshots implement [`MemoryAccess`] and [`MemoryMap`], so they can also be scanned by the [`ScanDriver`](crate::driver::ScanDriver)
+//! and their pages written as a maps index with [`MemoryMap::write_maps`].
+
+use std::num::NonZeroUsize;
+
+use procmem_access::{
+	memory::access::{ReadError, WriteError},
+	prelude::{MemoryAccess, MemoryMap, MemoryPage, OffsetType},
+};
+
+use crate::{predicate::value::NumericScalar, results::ScanResultSet};
+
+/// Comparison between the value in an older and a newer snapshot.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SnapshotComparison<T> {
+	/// The bytes of the value changed.
+	Changed,
+	/// The bytes of the value are the same.
+	Unchanged,
+	Increased,
+	Decreased,
+	/// The new value equals the old one plus the amount, floats are compared exactly.
+	IncreasedBy(T),
+	/// The new value equals the old one minus the amount, floats are compared exactly.
+	DecreasedBy(T),
+}
+impl<T: NumericScalar> SnapshotComparison<T> {
+	/// Returns whether the value changed from `old` to `new` as described by this comparison.
+	pub fn matches(&self, old: &[u8], new: &[u8]) -> bool {
+		let values = || (T::from_ne_slice(old), T::from_ne_slice(new));
+
+		match self {
+			SnapshotComparison::Changed => old != new,
+			SnapshotComparison::Unchanged => old == new,
+			SnapshotComparison::Increased => {
+				let (old, new) = values();
+				new > old
+			}
+			SnapshotComparison::Decreased => {
+				let (old, new) = values();
+				new < old
+			}
+			SnapshotComparison::IncreasedBy(delta) => {
+				let (old, new) = values();
+				new == old.offset_by(*delta)
+			}
+			SnapshotComparison::DecreasedBy(delta) => {
+				let (old, new) = values();
+				old == new.offset_by(*delta)
+			}
+		}
+	}
+}
+
+/// Contents of memory pages at one point in time.
+#[derive(Debug, Clone, Default)]
+pub struct MemorySnapshot {
+	/// Captured pages sorted by start offset.
+	pages: Vec<MemoryPage>,
+	/// Contents of each page.
+	contents: Vec<Vec<u8>>,
+}
+impl MemorySnapshot {
+	/// Creates a snapshot from `pages` and their contents, in any order.
+	///
+	/// Panics if the contents are not the size of their page.
+	pub fn from_contents(pages: impl IntoIterator<Item = (MemoryPage, Vec<u8>)>) -> Self {
+		let mut pages: Vec<(MemoryPage, Vec<u8>)> = pages.into_iter().collect();
+		assert!(pages
+			.iter()
+			.all(|(page, contents)| page.size() == contents.len() as u64));
+		pages.sort_by_key(|(page, _)| page.start());
+
+		let (pages, contents) = pages.into_iter().unzip();
+		MemorySnapshot { pages, contents }
+	}
+
+	/// Captures the contents of `pages`.
+	///
+	/// Pages which cannot be read are left out of the snapshot.
+	///
+	/// ## Safety
+	/// * See [`MemoryAccess::read`].
+	pub unsafe fn capture<A: MemoryAccess + ?Sized>(
+		access: &mut A,
+		pages: impl IntoIterator<Item = MemoryPage>,
+	) -> Self {
+		let captured = pages.into_iter().filter_map(|page| {
+			let mut contents = vec![0u8; page.size() as usize];
+			access.read(page.start(), &mut contents).ok()?;
+
+			Some((page, contents))
+		});
+
+		Self::from_contents(captured)
+	}
+
+	/// Total number of captured bytes.
+	pub fn total_bytes(&self) -> u64 {
+		self.pages.iter().map(MemoryPage::size).sum()
+	}
+
+	/// Returns `length` bytes captured at `offset`, if they are all inside one page.
+	pub fn get(&self, offset: OffsetType, length: usize) -> Option<&[u8]> {
+		let index = self.pages.partition_point(|page| page.end() <= offset);
+		let page = self.pages.get(index)?;
+		if offset < page.start() {
+			return None;
+		}
+
+		let start = (offset.get() - page.start().get()) as usize;
+		self.contents[index].get(start..start.checked_add(length)?)
+	}
+
+	/// Compares values of type `T` in pages present in both snapshots, returning the offsets which satisfy `comparison`.
+	///
+	/// Pages are matched by their address range. If `aligned` is true, only offsets divisible by the alignment of `T` are compared.
+	pub fn compare<T: NumericScalar>(
+		&self,
+		newer: &MemorySnapshot,
+		comparison: SnapshotComparison<T>,
+		aligned: bool,
+	) -> ScanResultSet {
+		let size = std::mem::size_of::<T>();
+		let step = if aligned {
+			std::mem::align_of::<T>() as u64
+		} else {
+			1
+		};
+
+		let mut results = Vec::new();
+		for (page, old) in self.pages.iter().zip(self.contents.iter()) {
+			let new = match newer
+				.pages
+				.binary_search_by_key(&page.start(), MemoryPage::start)
+			{
+				Ok(index) if newer.pages[index].end() == page.end() => &newer.contents[index],
+				_ => continue,
+			};
+
+			let first = match page.start().align_up(step) {
+				Some(first) => first,
+				None => continue,
+			};
+			for offset in OffsetType::iter_stride(first, page.end(), step) {
+				let start = (offset.get() - page.start().get()) as usize;
+				let (old, new) = match (old.get(start..start + size), new.get(start..start + size))
+				{
+					(Some(old), Some(new)) => (old, new),
+					_ => break,
+				};
+
+				if comparison.matches(old, new) {
+					results.push((offset, NonZeroUsize::new(size).unwrap()));
+				}
+			}
+		}
+
+		results.into_iter().collect()
+	}
+
+	/// Keeps those `results` whose values of type `T` satisfy `comparison` between this and the `newer` snapshot.
+	///
+	/// Results which are not captured in both snapshots are dropped.
+	pub fn compare_results<T: NumericScalar>(
+		&self,
+		newer: &MemorySnapshot,
+		results: &ScanResultSet,
+		comparison: SnapshotComparison<T>,
+	) -> ScanResultSet {
+		let size = std::mem::size_of::<T>();
+
+		results
+			.iter()
+			.copied()
+			.filter(
+				|(offset, _)| match (self.get(*offset, size), newer.get(*offset, size)) {
+					(Some(old), Some(new)) => comparison.matches(old, new),
+					_ => false,
+				},
+			)
+			.map(|(offset, _)| (offset, NonZeroUsize::new(size).unwrap()))
+			.collect()
+	}
+}
+impl MemoryMap for MemorySnapshot {
+	fn pages(&self) -> &[MemoryPage] {
+		&self.pages
+	}
+}
+impl MemoryAccess for MemorySnapshot {
+	/// Reads from the captured contents, reads must not cross page boundaries.
+	unsafe fn read(&mut self, offset: OffsetType, buffer: &mut [u8]) -> Result<(), ReadError> {
+		let contents = self.get(offset, buffer.len()).ok_or(ReadError::NotMapped)?;
+		buffer.copy_from_slice(contents);
+
+		Ok(())
+	}
+
+	/// Snapshots are immutable, writing always fails.
+	unsafe fn write(&mut self, _offset: OffsetType, _data: &[u8]) -> Result<(), WriteError> {
+		Err(WriteError::NotPermitted)
+	}
+}
+
+/// Snapshots are written as a sequence of `(page, contents)` pairs, the contents are checked to be the size of the page.
+#[cfg(feature = "serde")]
+mod serialization {
+	use serde::{de::Error, ser::SerializeSeq, Deserialize, Deserializer, Serialize, Serializer};
+
+	use procmem_access::prelude::MemoryPage;
+
+	use super::MemorySnapshot;
+
+	impl Serialize for MemorySnapshot {
+		fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+			let mut seq = serializer.serialize_seq(Some(self.pages.len()))?;
+			for pair in self.pages.iter().zip(self.contents.iter()) {
+				seq.serialize_element(&pair)?;
+			}
+
+			seq.end()
+		}
+	}
+
+	impl<'de> Deserialize<'de> for MemorySnapshot {
+		fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+			let pages = Vec::<(MemoryPage, Vec<u8>)>::deserialize(deserializer)?;
+			if pages
+				.iter()
+				.any(|(page, contents)| page.size() != contents.len() as u64)
+			{
+				return Err(D::Error::custom("contents must be the size of their page"));
+			}
+
+			Ok(MemorySnapshot::from_contents(pages))
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use procmem_access::{
+		platform::mock::MockMemoryAccess,
+		prelude::{MemoryMap, OffsetType},
+	};
+
+	use super::{MemorySnapshot, SnapshotComparison};
+
+	#[test]
+	fn test_snapshot_compare() {
+		let base = OffsetType::new_unwrap(0x1000);
+		let mut data = vec![0u8; 64];
+		data[8..12].copy_from_slice(&100i32.to_ne_bytes());
+		data[16..20].copy_from_slice(&50i32.to_ne_bytes());
+		data[24..28].copy_from_slice(&7i32.to_ne_bytes());
+
+		let mut access = MockMemoryAccess::new().with_region(base, data.clone());
+		let pages = access.memory_map().pages().to_vec();
+		let first = unsafe { MemorySnapshot::capture(&mut access, pages.clone()) };
+		assert_eq!(first.total_bytes(), 64);
+
+		data[8..12].copy_from_slice(&90i32.to_ne_bytes());
+		data[16..20].copy_from_slice(&55i32.to_ne_bytes());
+		let mut access = MockMemoryAccess::new().with_region(base, data);
+		let second = unsafe { MemorySnapshot::capture(&mut access, pages) };
+
+		let offsets = |results: super::ScanResultSet| -> Vec<u64> {
+			results
+				.offsets()
+				.map(|offset| offset.get() - 0x1000)
+				.collect()
+		};
+		let decreased = first.compare(&second, SnapshotComparison::<i32>::Decreased, true);
+		assert_eq!(offsets(decreased.clone()), [8]);
+		assert_eq!(
+			offsets(first.compare(&second, SnapshotComparison::IncreasedBy(5i32), true)),
+			[16]
+		);
+
+		let changed = first.compare(&second, SnapshotComparison::<i32>::Changed, true);
+		assert_eq!(offsets(changed.clone()), [8, 16]);
+		let unchanged =
+			first.compare_results(&second, &changed, SnapshotComparison::<i32>::Unchanged);
+		assert!(unchanged.is_empty());
+		assert_eq!(
+			offsets(first.compare_results(
+				&second,
+				&changed,
+				SnapshotComparison::DecreasedBy(10i32)
+			)),
+			[8]
+		);
+
+		#[cfg(feature = "serde")]
+		{
+			let json = serde_json::to_string(&first).unwrap();
+			let loaded: MemorySnapshot = serde_json::from_str(&json).unwrap();
+			assert_eq!(loaded.pages(), first.pages());
+			assert_eq!(
+				offsets(loaded.compare(&second, SnapshotComparison::<i32>::Changed, true)),
+				[8, 16]
+			);
+
+			let json = json.replacen("[0,0,", "[0,", 1);
+			assert!(serde_json::from_str::<MemorySnapshot>(&json).is_err());
+		}
+	}
+}
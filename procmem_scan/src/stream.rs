@@ -44,6 +44,65 @@ impl<P: ScannerPredicate> StreamScanner<P> {
 		StreamScannerIter::new(self, offset, stream)
 	}
 
+	/// Runs the scanner on a stream, continuing candidates left over by the previous call.
+	///
+	/// Running this scan multiple times on consecutive chunks of a sequence will find matches
+	/// the same way as if it was run on the whole sequence using [`scan_once`](StreamScanner::scan_once).
+	/// Call [`reset`](StreamScanner::reset) before scanning a sequence which does not follow the previous one.
+	pub fn scan_continue<I: Iterator<Item = u8>>(
+		&mut self,
+		offset: OffsetType,
+		stream: I,
+	) -> StreamScannerIter<'_, P, I> {
+		StreamScannerIter {
+			reset_after: false,
+			..StreamScannerIter::new(self, offset, stream)
+		}
+	}
+
+	/// Runs the scanner on a slice, continuing candidates left over by the previous call.
+	///
+	/// Finds the same matches as [`scan_continue`](StreamScanner::scan_continue), but when the predicate has a
+	/// [`first_byte`](ScannerPredicate::first_byte) and no candidate is pending, it jumps straight to the next occurrence
+	/// of that byte using `memchr`. Predicates without a first byte are scanned byte by byte.
+	pub fn scan_slice(&mut self, offset: OffsetType, bytes: &[u8]) -> Vec<ScanResult> {
+		let first_byte = match self.predicate.first_byte() {
+			Some(byte) => byte,
+			None => return self.scan_continue(offset, bytes.iter().copied()).collect(),
+		};
+
+		let mut found = Vec::new();
+		let mut index = 0;
+		while index < bytes.len() {
+			if self.candidates.iter().all(ScannerCandidate::is_resolved) {
+				match memchr::memchr(first_byte, &bytes[index..]) {
+					None => break,
+					Some(skip) => index += skip,
+				}
+			}
+
+			self.on_byte(
+				offset.saturating_add(index as u64),
+				bytes[index],
+				&mut found,
+			);
+			index += 1;
+		}
+
+		found
+	}
+
+	/// Returns the lowest offset of a candidate which is still waiting for more bytes.
+	///
+	/// Continuing a scan from this offset with a fresh scanner finds the same matches as continuing with this scanner.
+	pub fn pending_offset(&self) -> Option<OffsetType> {
+		self.candidates
+			.iter()
+			.filter(|candidate| !candidate.is_resolved())
+			.map(|candidate| candidate.offset())
+			.min()
+	}
+
 	fn on_byte(
 		&mut self,
 		offset: OffsetType,
@@ -62,7 +121,9 @@ impl<P: ScannerPredicate> StreamScanner<P> {
 
 			match self.predicate.update_candidate(offset, byte, current) {
 				UpdateCandidateResult::Advance => {
-					self.candidates[i].advance();
+					let candidate = &mut self.candidates[i];
+					candidate.record_byte(candidate.length().get(), byte);
+					candidate.advance();
 					i += 1;
 				}
 				UpdateCandidateResult::Skip => {
@@ -72,7 +133,8 @@ impl<P: ScannerPredicate> StreamScanner<P> {
 					self.candidates.remove(i);
 				}
 				UpdateCandidateResult::Resolve if current.is_partial() => {
-					// Treat resolve as skip if it is partial
+					// Partial candidates are kept until their start is confirmed by a merge
+					self.candidates[i].resolve();
 					i += 1;
 				}
 				UpdateCandidateResult::Resolve => {
@@ -89,7 +151,10 @@ impl<P: ScannerPredicate> StreamScanner<P> {
 			Some(candidate) if candidate.is_resolved() => {
 				found.push((candidate.offset(), candidate.length()));
 			}
-			Some(candidate) => self.candidates.push(candidate),
+			Some(mut candidate) => {
+				candidate.record_byte(0, byte);
+				self.candidates.push(candidate)
+			}
 		};
 	}
 }
@@ -232,7 +297,7 @@ impl<'a, P: ScannerPredicate, I: Iterator<Item = u8>> Iterator for StreamScanner
 			}
 
 			// loop until there are some results then yield the first
-			if self.found.len() > 0 {
+			if !self.found.is_empty() {
 				return Some(self.get_buffered());
 			}
 			byte = self.stream.next();
@@ -247,7 +312,10 @@ mod test {
 	use procmem_access::prelude::OffsetType;
 
 	use super::StreamScanner;
-	use crate::predicate::value::{ByteComparable, ValuePredicate};
+	use crate::predicate::{
+		pattern::PatternPredicate,
+		value::{ByteComparable, ValuePredicate},
+	};
 
 	#[test]
 	fn test_stream_scanner() {
@@ -304,6 +372,59 @@ mod test {
 		);
 	}
 
+	#[test]
+	fn test_stream_scanner_continue() {
+		let data = [2u64, 1, 0, 1, 0, 1, 0, 0, 1, 0, 1, 0, 2];
+		let bytes = data.as_bytes();
+
+		let predicate = ValuePredicate::new([1u64, 0, 1, 0], true);
+		let mut scanner = StreamScanner::new(predicate);
+
+		let mut found = Vec::new();
+		for (i, chunk) in bytes.chunks(5).enumerate() {
+			found.extend(scanner.scan_continue(
+				OffsetType::new_unwrap(8 + i as u64 * 5),
+				chunk.iter().copied(),
+			));
+		}
+
+		let found_once: Vec<_> = scanner
+			.scan_once(OffsetType::new_unwrap(8), bytes.iter().copied())
+			.collect();
+		assert_eq!(found, found_once);
+	}
+
+	#[test]
+	fn test_stream_scanner_slice() {
+		let data = [2u64, 1, 0, 1, 0, 1, 0, 0, 1, 0, 1, 0, 2];
+		let bytes = data.as_bytes();
+
+		let predicate = ValuePredicate::new([1u64, 0, 1, 0], true);
+		let mut scanner = StreamScanner::new(predicate);
+		let found_once: Vec<_> = scanner
+			.scan_once(OffsetType::new_unwrap(8), bytes.iter().copied())
+			.collect();
+
+		// candidates pending across chunks must not be skipped over
+		let mut found = Vec::new();
+		for (i, chunk) in bytes.chunks(5).enumerate() {
+			found.extend(scanner.scan_slice(OffsetType::new_unwrap(8 + i as u64 * 5), chunk));
+		}
+		assert_eq!(found, found_once);
+
+		// wildcard first byte falls back to the byte by byte scan
+		let predicate: PatternPredicate = "?? 00 00 00 00 00 00 00 00".parse().unwrap();
+		let mut scanner = StreamScanner::new(predicate);
+		let found_once: Vec<_> = scanner
+			.scan_once(OffsetType::new_unwrap(8), bytes.iter().copied())
+			.collect();
+		scanner.reset();
+		assert_eq!(
+			scanner.scan_slice(OffsetType::new_unwrap(8), bytes),
+			found_once
+		);
+	}
+
 	#[test]
 	fn test_stream_scanner_partial_multiple_pages_sorted() {
 		let data = [2u64, 1, 0, 1, 0, 0, 0, 1, 0, 1, 0, 0, 1];
@@ -384,4 +505,27 @@ mod test {
 			]
 		);
 	}
+	#[test]
+	fn test_stream_scanner_partial_resolve_in_chunk() {
+		// the match at 3 is completed two bytes into the second chunk
+		let data = [0u8, 0, 1, 2, 3, 4, 0, 0];
+		let predicate = ValuePredicate::new([1u8, 2, 3, 4], false);
+
+		let mut scanner_1 = StreamScanner::new(&predicate);
+		let mut scanner_2 = StreamScanner::new(&predicate);
+
+		let mut found_scan_partial = Vec::new();
+		found_scan_partial
+			.extend(scanner_1.scan_partial(OffsetType::new_unwrap(1), data[..4].iter().copied()));
+		found_scan_partial
+			.extend(scanner_2.scan_partial(OffsetType::new_unwrap(5), data[4..].iter().copied()));
+
+		scanner_1.merge_partial_mut(scanner_2);
+		found_scan_partial.extend(scanner_1.resolve_partial());
+
+		assert_eq!(
+			found_scan_partial,
+			&[(OffsetType::new_unwrap(3), NonZeroUsize::new(4).unwrap())]
+		);
+	}
 }
@@ -0,0 +1,502 @@
+//! Address tables, the persistent list of interesting locations in a process.
+//!
+//! An [`AddressTable`] is a versioned JSON document. Entries locate their value by an absolute address,
+//! by a pointer chain starting in a module or by a byte signature, so that they can be found again after the process restarts.
+
+use std::{io, path::Path};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use procmem_access::{
+	layout::{format::ValueFormatter, FieldType, PrimitiveType, Value},
+	memory::access::ReadError,
+	prelude::{ErrorKind, MemoryAccess, MemoryMap, MemoryPage, OffsetType, ProcmemError},
+	symbols::StableAddress,
+};
+
+use crate::pattern::BytePattern;
+
+/// Version of the document written by this library.
+pub const ADDRESS_TABLE_VERSION: u32 = 1;
+
+#[derive(Debug, Error)]
+pub enum AddressTableError {
+	#[error("could not access address table file")]
+	Io(#[from] io::Error),
+	#[error("invalid address table document")]
+	Json(#[from] serde_json::Error),
+	#[error("unsupported address table version {0}")]
+	UnsupportedVersion(u32),
+}
+impl AddressTableError {
+	pub fn kind(&self) -> ErrorKind {
+		match self {
+			AddressTableError::Io(err) => ErrorKind::from_io(err),
+			AddressTableError::Json(_) => ErrorKind::Parse,
+			AddressTableError::UnsupportedVersion(_) => ErrorKind::Parse,
+		}
+	}
+}
+impl From<AddressTableError> for ProcmemError {
+	fn from(err: AddressTableError) -> Self {
+		ProcmemError::new(err.kind(), err)
+	}
+}
+
+#[derive(Debug, Error)]
+pub enum ResolveError {
+	#[error("module {0} is not mapped")]
+	ModuleNotFound(String),
+	#[error("could not read pointer at {offset}")]
+	Read {
+		offset: OffsetType,
+		#[source]
+		source: ReadError,
+	},
+	#[error("pointer chain resolves to null")]
+	NullPointer,
+	#[error("invalid signature pattern \"{0}\"")]
+	InvalidPattern(String),
+	#[error("signature {0} was not found")]
+	SignatureNotFound(String),
+	#[error("invalid value \"{value}\" for type {value_type}")]
+	InvalidValue {
+		value: String,
+		value_type: EntryType,
+	},
+}
+impl ResolveError {
+	pub fn kind(&self) -> ErrorKind {
+		match self {
+			ResolveError::ModuleNotFound(_) => ErrorKind::NotMapped,
+			ResolveError::Read { source, .. } => source.kind(),
+			ResolveError::NullPointer => ErrorKind::NotMapped,
+			ResolveError::InvalidPattern(_) => ErrorKind::Parse,
+			ResolveError::SignatureNotFound(_) => ErrorKind::NotMapped,
+			ResolveError::InvalidValue { .. } => ErrorKind::Parse,
+		}
+	}
+}
+impl From<ResolveError> for ProcmemError {
+	fn from(err: ResolveError) -> Self {
+		ProcmemError::new(err.kind(), err)
+	}
+}
+
+/// Type of the value of an entry.
+///
+/// Serialized as `"i32"`, `"ptr"`, `{"bytes": 16}` and so on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EntryType {
+	U8,
+	I8,
+	U16,
+	I16,
+	U32,
+	I32,
+	U64,
+	I64,
+	F16,
+	F32,
+	F64,
+	Bool,
+	#[serde(rename = "ptr")]
+	Pointer,
+	/// Raw bytes of the given length.
+	Bytes(usize),
+	/// Nul-terminated string of at most the given length.
+	String(usize),
+}
+impl EntryType {
+	pub fn size(&self) -> usize {
+		FieldType::from(*self).size()
+	}
+
+	/// Encodes a value written in its textual form, such as a freeze value.
+	///
+	/// The syntax is that of [`ValueFormatter`] with native byte order.
+	pub fn encode(&self, value: &str) -> Option<Vec<u8>> {
+		ValueFormatter::new(*self).parse(value).ok()
+	}
+}
+impl From<EntryType> for FieldType {
+	fn from(value_type: EntryType) -> Self {
+		match value_type {
+			EntryType::U8 => PrimitiveType::U8.into(),
+			EntryType::I8 => PrimitiveType::I8.into(),
+			EntryType::U16 => PrimitiveType::U16.into(),
+			EntryType::I16 => PrimitiveType::I16.into(),
+			EntryType::U32 => PrimitiveType::U32.into(),
+			EntryType::I32 => PrimitiveType::I32.into(),
+			EntryType::U64 => PrimitiveType::U64.into(),
+			EntryType::I64 => PrimitiveType::I64.into(),
+			EntryType::F16 => PrimitiveType::F16.into(),
+			EntryType::F32 => PrimitiveType::F32.into(),
+			EntryType::F64 => PrimitiveType::F64.into(),
+			EntryType::Bool => PrimitiveType::Bool.into(),
+			EntryType::Pointer => PrimitiveType::Pointer.into(),
+			EntryType::Bytes(len) => FieldType::Bytes(len),
+			EntryType::String(len) => FieldType::CString(len),
+		}
+	}
+}
+impl std::fmt::Display for EntryType {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		match self {
+			EntryType::Bytes(len) => write!(f, "bytes[{}]", len),
+			EntryType::String(len) => write!(f, "string[{}]", len),
+			EntryType::Pointer => write!(f, "ptr"),
+			primitive => write!(f, "{}", format!("{:?}", primitive).to_lowercase()),
+		}
+	}
+}
+
+/// How an entry finds its address.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum EntryLocation {
+	/// Absolute address, only valid for the current run of the process.
+	Address { address: u64 },
+	/// Pointer chain starting at `base` bytes from the start of `module`, or at the absolute address `base` without a module.
+	///
+	/// Each offset is added to the pointer read at the current address, so `[[module + base] + a] + b` is written as `offsets: [a, b]`.
+	PointerChain {
+		#[serde(default, skip_serializing_if = "Option::is_none")]
+		module: Option<String>,
+		base: u64,
+		#[serde(default)]
+		offsets: Vec<i64>,
+	},
+	/// First match of a byte signature such as `"48 8B ?? C3"` plus `offset`.
+	///
+	/// Only pages of `module` are searched, or all executable pages without a module.
+	Signature {
+		#[serde(default, skip_serializing_if = "Option::is_none")]
+		module: Option<String>,
+		pattern: String,
+		#[serde(default)]
+		offset: i64,
+	},
+}
+
+impl From<StableAddress> for EntryLocation {
+	fn from(address: StableAddress) -> Self {
+		EntryLocation::PointerChain {
+			module: Some(address.module),
+			base: address.rva,
+			offsets: address.offsets,
+		}
+	}
+}
+
+fn offset_by(offset: u64, by: i64) -> Result<OffsetType, ResolveError> {
+	offset
+		.checked_add_signed(by)
+		.and_then(OffsetType::new)
+		.ok_or(ResolveError::NullPointer)
+}
+
+impl EntryLocation {
+	/// Resolves the address against a live process with memory map `map`.
+	///
+	/// ## Safety
+	/// * See [`MemoryAccess::read`].
+	pub unsafe fn resolve<A: MemoryAccess + ?Sized, M: MemoryMap + ?Sized>(
+		&self,
+		access: &mut A,
+		map: &M,
+	) -> Result<OffsetType, ResolveError> {
+		match self {
+			EntryLocation::Address { address } => {
+				OffsetType::new(*address).ok_or(ResolveError::NullPointer)
+			}
+			EntryLocation::PointerChain {
+				module,
+				base,
+				offsets,
+			} => {
+				let mut address = match module {
+					None => OffsetType::new(*base).ok_or(ResolveError::NullPointer)?,
+					Some(module) => map
+						.module_base(module)
+						.ok_or_else(|| ResolveError::ModuleNotFound(module.clone()))?
+						.saturating_add(*base),
+				};
+
+				for &offset in offsets {
+					let mut pointer = [0u8; std::mem::size_of::<usize>()];
+					access
+						.read(address, &mut pointer)
+						.map_err(|source| ResolveError::Read {
+							offset: address,
+							source,
+						})?;
+
+					address = offset_by(usize::from_ne_bytes(pointer) as u64, offset)?;
+				}
+
+				Ok(address)
+			}
+			EntryLocation::Signature {
+				module,
+				pattern,
+				offset,
+			} => {
+				let parsed = pattern
+					.parse::<BytePattern>()
+					.map_err(|_| ResolveError::InvalidPattern(pattern.clone()))?;
+
+				if let Some(module) = module {
+					if map.module_base(module).is_none() {
+						return Err(ResolveError::ModuleNotFound(module.clone()));
+					}
+				}
+				let ranges = MemoryPage::merge_sorted(
+					map.pages()
+						.iter()
+						.filter(|page| page.permissions.read())
+						.filter(|page| match module {
+							Some(module) => page.page_type.module_name() == Some(module.as_str()),
+							None => page.permissions.exec(),
+						})
+						.cloned(),
+				)
+				.map(|page| page.address_range);
+
+				let mut found = None;
+				parsed.scan(access, ranges, |address| {
+					found.get_or_insert(address);
+				});
+
+				let found =
+					found.ok_or_else(|| ResolveError::SignatureNotFound(pattern.clone()))?;
+				offset_by(found.get(), *offset)
+			}
+		}
+	}
+}
+
+/// One location in an [`AddressTable`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TableEntry {
+	pub label: String,
+	pub location: EntryLocation,
+	#[serde(rename = "type")]
+	pub value_type: EntryType,
+	/// Value to keep writing to the entry, in the textual form accepted by [`EntryType::encode`].
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub freeze: Option<String>,
+	#[serde(default, skip_serializing_if = "String::is_empty")]
+	pub notes: String,
+}
+impl TableEntry {
+	pub fn new(label: impl Into<String>, location: EntryLocation, value_type: EntryType) -> Self {
+		TableEntry {
+			label: label.into(),
+			location,
+			value_type,
+			freeze: None,
+			notes: String::new(),
+		}
+	}
+
+	/// Resolves the entry against a live process and reads its current value.
+	///
+	/// ## Safety
+	/// * See [`MemoryAccess::read`].
+	pub unsafe fn materialize<A: MemoryAccess + ?Sized, M: MemoryMap + ?Sized>(
+		&self,
+		access: &mut A,
+		map: &M,
+	) -> Result<MaterializedEntry, ResolveError> {
+		let address = self.location.resolve(access, map)?;
+
+		let freeze =
+			match self.freeze.as_deref() {
+				None => None,
+				Some(value) => Some(self.value_type.encode(value).ok_or_else(|| {
+					ResolveError::InvalidValue {
+						value: value.to_string(),
+						value_type: self.value_type,
+					}
+				})?),
+			};
+
+		let mut buffer = vec![0u8; self.value_type.size()];
+		access
+			.read(address, &mut buffer)
+			.map_err(|source| ResolveError::Read {
+				offset: address,
+				source,
+			})?;
+
+		Ok(MaterializedEntry {
+			address,
+			value: FieldType::from(self.value_type).decode(&buffer),
+			freeze,
+		})
+	}
+}
+
+/// [`TableEntry`] resolved against a live process.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MaterializedEntry {
+	pub address: OffsetType,
+	/// Value at the time of materialization.
+	pub value: Value,
+	/// Encoded freeze value, ready to be written to `address`.
+	pub freeze: Option<Vec<u8>>,
+}
+
+/// Versioned list of [`TableEntry`]s.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AddressTable {
+	pub version: u32,
+	pub entries: Vec<TableEntry>,
+}
+impl AddressTable {
+	pub fn new(entries: Vec<TableEntry>) -> Self {
+		AddressTable {
+			version: ADDRESS_TABLE_VERSION,
+			entries,
+		}
+	}
+
+	/// Reads a table, rejecting documents written by a newer version of this library.
+	pub fn from_reader(reader: impl io::Read) -> Result<Self, AddressTableError> {
+		let table: AddressTable = serde_json::from_reader(reader)?;
+		if table.version > ADDRESS_TABLE_VERSION {
+			return Err(AddressTableError::UnsupportedVersion(table.version));
+		}
+
+		Ok(table)
+	}
+
+	pub fn to_writer(&self, writer: impl io::Write) -> Result<(), AddressTableError> {
+		serde_json::to_writer_pretty(writer, self)?;
+
+		Ok(())
+	}
+
+	pub fn load(path: impl AsRef<Path>) -> Result<Self, AddressTableError> {
+		Self::from_reader(io::BufReader::new(std::fs::File::open(path)?))
+	}
+
+	pub fn save(&self, path: impl AsRef<Path>) -> Result<(), AddressTableError> {
+		let mut writer = io::BufWriter::new(std::fs::File::create(path)?);
+		self.to_writer(&mut writer)?;
+		io::Write::flush(&mut writer)?;
+
+		Ok(())
+	}
+
+	pub fn entry(&self, label: &str) -> Option<&TableEntry> {
+		self.entries.iter().find(|entry| entry.label == label)
+	}
+
+	/// Materializes all entries, failures of individual entries are returned in their place.
+	///
+	/// ## Safety
+	/// * See [`MemoryAccess::read`].
+	pub unsafe fn materialize<A: MemoryAccess + ?Sized, M: MemoryMap + ?Sized>(
+		&self,
+		access: &mut A,
+		map: &M,
+	) -> Vec<Result<MaterializedEntry, ResolveError>> {
+		self.entries
+			.iter()
+			.map(|entry| entry.materialize(access, map))
+			.collect()
+	}
+}
+impl Default for AddressTable {
+	fn default() -> Self {
+		Self::new(Vec::new())
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use std::path::PathBuf;
+
+	use procmem_access::{
+		layout::Value,
+		platform::mock::MockMemoryAccess,
+		prelude::{MemoryPage, MemoryPagePermissions, MemoryPageType, OffsetType},
+		symbols::StableAddress,
+	};
+
+	use super::{
+		AddressTable, AddressTableError, EntryLocation, EntryType, ResolveError, TableEntry,
+	};
+
+	#[test]
+	fn test_address_table_materialize() {
+		// game.bin at 0x1000 holds a pointer at +0x10 to a structure at 0x1040 whose field +0x8 is 1234
+		let mut memory = vec![0u8; 0x100];
+		memory[0x10..0x18].copy_from_slice(&0x1040u64.to_ne_bytes());
+		memory[0x48..0x4C].copy_from_slice(&1234i32.to_ne_bytes());
+		memory[0x80..0x84].copy_from_slice(&[0x48, 0x8B, 0x05, 0xC3]);
+		let mut access =
+			MockMemoryAccess::new().with_region(OffsetType::new_unwrap(0x1000), memory);
+
+		let pages = [MemoryPage {
+			address_range: [
+				OffsetType::new_unwrap(0x1000),
+				OffsetType::new_unwrap(0x1100),
+			],
+			permissions: MemoryPagePermissions::new(true, false, true, false),
+			offset: 0,
+			page_type: MemoryPageType::File(PathBuf::from("/opt/game/game.bin")),
+		}];
+
+		let health_location: StableAddress = "[game.bin+0x10]+0x8".parse().unwrap();
+		let mut health = TableEntry::new("health", health_location.into(), EntryType::I32);
+		health.freeze = Some("9999".to_string());
+		let table = AddressTable::new(vec![
+			health,
+			TableEntry::new(
+				"code",
+				EntryLocation::Signature {
+					module: None,
+					pattern: "48 8B ?? C3".to_string(),
+					offset: 2,
+				},
+				EntryType::U8,
+			),
+			TableEntry::new(
+				"missing",
+				EntryLocation::PointerChain {
+					module: Some("other.so".to_string()),
+					base: 0,
+					offsets: vec![],
+				},
+				EntryType::U8,
+			),
+		]);
+
+		let mut json = Vec::new();
+		table.to_writer(&mut json).unwrap();
+		let table = AddressTable::from_reader(json.as_slice()).unwrap();
+
+		let results = unsafe { table.materialize(&mut access, &pages[..]) };
+		let health = results[0].as_ref().unwrap();
+		assert_eq!(health.address.get(), 0x1048);
+		assert_eq!(health.value, Value::I32(1234));
+		assert_eq!(health.freeze.as_deref(), Some(&9999i32.to_ne_bytes()[..]));
+
+		let code = results[1].as_ref().unwrap();
+		assert_eq!(code.address.get(), 0x1082);
+		assert_eq!(code.value, Value::U8(0x05));
+
+		assert!(matches!(results[2], Err(ResolveError::ModuleNotFound(_))));
+
+		let future = br#"{"version": 99, "entries": []}"#;
+		assert!(matches!(
+			AddressTable::from_reader(&future[..]),
+			Err(AddressTableError::UnsupportedVersion(99))
+		));
+	}
+}
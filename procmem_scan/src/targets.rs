@@ -0,0 +1,345 @@
+//! Scanning multiple processes at once.
+//!
+//! Applications such as browsers or games often spread their state across several worker processes.
+//! [`Targets`] runs the same scan across all of them concurrently and tags each match with its process.
+
+use std::num::NonZeroUsize;
+
+use thiserror::Error;
+
+use procmem_access::{
+	memory::lock::{LockError, UnlockError},
+	prelude::{
+		ErrorKind, MemoryAccess, MemoryLock, MemoryMap, MemoryPage, OffsetType, ProcmemError,
+	},
+};
+
+use crate::{driver::ScanDriver, predicate::ScannerPredicate};
+
+#[derive(Debug, Error)]
+pub enum TargetsError {
+	#[error("could not attach to process {pid}")]
+	Attach {
+		pid: i32,
+		#[source]
+		source: ProcmemError,
+	},
+	#[error("could not lock process {pid}")]
+	Lock {
+		pid: i32,
+		#[source]
+		source: LockError,
+	},
+	#[error("could not unlock process {pid}")]
+	Unlock {
+		pid: i32,
+		#[source]
+		source: UnlockError,
+	},
+	#[error("could not scan process {pid}")]
+	Scan {
+		pid: i32,
+		#[source]
+		source: crate::driver::ScanDriverError,
+	},
+}
+impl TargetsError {
+	/// Returns the pid of the process this error relates to.
+	pub const fn pid(&self) -> i32 {
+		match self {
+			TargetsError::Attach { pid, .. } => *pid,
+			TargetsError::Lock { pid, .. } => *pid,
+			TargetsError::Unlock { pid, .. } => *pid,
+			TargetsError::Scan { pid, .. } => *pid,
+		}
+	}
+
+	pub fn kind(&self) -> ErrorKind {
+		match self {
+			TargetsError::Attach { source, .. } => source.kind(),
+			TargetsError::Lock { source, .. } => source.kind(),
+			TargetsError::Unlock { source, .. } => source.kind(),
+			TargetsError::Scan { source, .. } => source.kind(),
+		}
+	}
+}
+impl From<TargetsError> for ProcmemError {
+	fn from(err: TargetsError) -> Self {
+		ProcmemError::new(err.kind(), err)
+	}
+}
+
+/// One process of [`Targets`].
+pub struct Target<A, L, M> {
+	pub pid: i32,
+	pub access: A,
+	pub lock: L,
+	pub map: M,
+}
+
+/// Match found in one of the [`Targets`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct TargetMatch {
+	pub pid: i32,
+	pub offset: OffsetType,
+	pub length: NonZeroUsize,
+}
+
+/// Result of scanning [`Targets`].
+///
+/// A failure in one process does not stop the scan of the other processes.
+#[derive(Debug, Default)]
+pub struct TargetsScan {
+	/// Matches ordered by target and then by offset.
+	pub matches: Vec<TargetMatch>,
+	pub errors: Vec<TargetsError>,
+}
+
+/// Set of processes scanned together.
+pub struct Targets<A, L, M> {
+	targets: Vec<Target<A, L, M>>,
+	driver: ScanDriver,
+}
+impl<A, L, M> Targets<A, L, M> {
+	/// Creates an empty set of targets which scan using `driver`.
+	///
+	/// The buffer pool of the driver is shared by all targets.
+	pub fn new(driver: ScanDriver) -> Self {
+		Targets {
+			targets: Vec::new(),
+			driver,
+		}
+	}
+
+	pub fn add(&mut self, target: Target<A, L, M>) {
+		self.targets.push(target);
+	}
+
+	/// Removes and returns the target with `pid`.
+	pub fn remove(&mut self, pid: i32) -> Option<Target<A, L, M>> {
+		let index = self.targets.iter().position(|target| target.pid == pid)?;
+
+		Some(self.targets.remove(index))
+	}
+
+	pub fn targets(&self) -> &[Target<A, L, M>] {
+		&self.targets
+	}
+
+	pub fn targets_mut(&mut self) -> &mut [Target<A, L, M>] {
+		&mut self.targets
+	}
+
+	pub fn pids(&self) -> impl Iterator<Item = i32> + '_ {
+		self.targets.iter().map(|target| target.pid)
+	}
+
+	pub fn len(&self) -> usize {
+		self.targets.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.targets.is_empty()
+	}
+
+	pub const fn driver(&self) -> &ScanDriver {
+		&self.driver
+	}
+}
+impl<A: MemoryAccess + Send, L: MemoryLock, M: MemoryMap + Sync> Targets<A, L, M> {
+	/// Scans pages accepted by `page_filter` in all targets using `predicate`.
+	///
+	/// All targets are locked from the calling thread before the scan and unlocked after it,
+	/// since ptrace based locks can only be used from the thread which attached them.
+	/// Only the reading and scanning itself runs concurrently, one thread per target.
+	///
+	/// ## Safety
+	/// * See [`MemoryAccess::read`].
+	pub unsafe fn scan<P: ScannerPredicate + Sync>(
+		&mut self,
+		page_filter: impl Fn(&MemoryPage) -> bool + Sync,
+		predicate: &P,
+	) -> TargetsScan {
+		let mut result = TargetsScan::default();
+
+		let mut locked = Vec::with_capacity(self.targets.len());
+		for target in self.targets.iter_mut() {
+			match target.lock.lock() {
+				Ok(_) => locked.push(true),
+				Err(source) => {
+					result.errors.push(TargetsError::Lock {
+						pid: target.pid,
+						source,
+					});
+					locked.push(false);
+				}
+			}
+		}
+
+		let driver = &self.driver;
+		let page_filter = &page_filter;
+		let scans: Vec<_> = std::thread::scope(|scope| {
+			let handles: Vec<_> = self
+				.targets
+				.iter_mut()
+				.zip(locked.iter())
+				.filter(|(_, &locked)| locked)
+				.map(|(target, _)| {
+					// only the access and the map are sent to the thread, the lock stays on this one
+					let (pid, access, map) = (target.pid, &mut target.access, &target.map);
+
+					scope.spawn(move || {
+						let ranges = MemoryPage::merge_sorted(
+							map.pages().iter().filter(|page| page_filter(page)).cloned(),
+						)
+						.map(|page| page.address_range);
+
+						let mut matches = Vec::new();
+						driver
+							.scan(access, ranges, predicate, |(offset, length)| {
+								matches.push(TargetMatch {
+									pid,
+									offset,
+									length,
+								})
+							})
+							.map(|_| matches)
+							.map_err(|source| TargetsError::Scan { pid, source })
+					})
+				})
+				.collect();
+
+			handles
+				.into_iter()
+				.map(|handle| handle.join().unwrap())
+				.collect()
+		});
+		for scan in scans {
+			match scan {
+				Ok(matches) => result.matches.extend(matches),
+				Err(err) => result.errors.push(err),
+			}
+		}
+
+		for (target, locked) in self.targets.iter_mut().zip(locked) {
+			if !locked {
+				continue;
+			}
+
+			if let Err(source) = target.lock.unlock() {
+				result.errors.push(TargetsError::Unlock {
+					pid: target.pid,
+					source,
+				});
+			}
+		}
+
+		result
+	}
+}
+
+#[cfg(any(target_os = "linux", target_os = "android", target_os = "macos"))]
+impl
+	Targets<
+		procmem_access::platform::simple::SimpleMemoryAccess,
+		procmem_access::platform::simple::SimpleMemoryLock,
+		procmem_access::platform::simple::SimpleMemoryMap,
+	>
+{
+	/// Attaches to all `pids` using the simple platform implementations.
+	///
+	/// Processes which cannot be attached are reported as errors and skipped.
+	pub fn attach(
+		pids: impl IntoIterator<Item = i32>,
+		driver: ScanDriver,
+	) -> (Self, Vec<TargetsError>) {
+		use procmem_access::platform::simple::{
+			SimpleMemoryAccess, SimpleMemoryLock, SimpleMemoryMap,
+		};
+
+		let mut targets = Targets::new(driver);
+		let mut errors = Vec::new();
+		for pid in pids {
+			let target = (|| -> Result<_, ProcmemError> {
+				Ok(Target {
+					pid,
+					lock: SimpleMemoryLock::new(pid)?,
+					map: SimpleMemoryMap::new(pid)?,
+					access: SimpleMemoryAccess::new(pid)?,
+				})
+			})();
+
+			match target {
+				Ok(target) => targets.add(target),
+				Err(source) => errors.push(TargetsError::Attach { pid, source }),
+			}
+		}
+
+		(targets, errors)
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use procmem_access::{
+		platform::mock::{MockMemoryAccess, MockMemoryLock, MockMemoryMap},
+		prelude::OffsetType,
+	};
+
+	use super::{Target, Targets, TargetsError};
+	use crate::{
+		driver::{ScanConfig, ScanDriver},
+		predicate::value::ValuePredicate,
+	};
+
+	fn target(
+		pid: i32,
+		data: Vec<u8>,
+		fail: bool,
+	) -> Target<MockMemoryAccess, MockMemoryLock, MockMemoryMap> {
+		let access = MockMemoryAccess::new().with_region(OffsetType::new_unwrap(1), data);
+		let mut lock = MockMemoryLock::new();
+		lock.set_failing(fail);
+
+		Target {
+			pid,
+			map: access.memory_map(),
+			access,
+			lock,
+		}
+	}
+
+	#[test]
+	fn test_targets_scan() {
+		let mut targets = Targets::new(ScanDriver::new(ScanConfig {
+			chunk_size: 4,
+			..Default::default()
+		}));
+		targets.add(target(10, vec![0, 7, 0, 0, 7, 0, 0, 0, 7], false));
+		targets.add(target(20, vec![7, 0, 0], false));
+		targets.add(target(30, vec![7, 7, 7], true));
+
+		let predicate = ValuePredicate::new(7u8, false);
+		let result = unsafe { targets.scan(|page| page.permissions.write(), &predicate) };
+
+		let found: Vec<_> = result
+			.matches
+			.iter()
+			.map(|m| (m.pid, m.offset.get()))
+			.collect();
+		assert_eq!(found, [(10, 2), (10, 5), (10, 9), (20, 1)]);
+
+		assert_eq!(result.errors.len(), 1);
+		assert!(matches!(
+			result.errors[0],
+			TargetsError::Lock { pid: 30, .. }
+		));
+		assert!(targets
+			.targets()
+			.iter()
+			.all(|target| !target.lock.is_locked()));
+
+		assert!(targets.remove(30).is_some());
+		assert_eq!(targets.pids().collect::<Vec<_>>(), [10, 20]);
+	}
+}
@@ -0,0 +1,246 @@
+//! History of the values at scan results.
+//!
+//! A [`MatchTracker`] re-reads a set of results on each [`refresh`](MatchTracker::refresh) and keeps the values with the time they were read.
+//! Placing a [`mark`](MatchTracker::mark) between refreshes, for example right before pressing a key in the target,
+//! allows asking which results changed across the mark, or which changed across the mark and at no other time.
+//!
+//! ```text
+//! refresh, refresh, ... // idle, values which change on their own are recorded
+//! mark("jump")          // press the key
+//! refresh, refresh, ... // idle again
+//! changed_only_at("jump")
+//! ```
+
+use std::{collections::VecDeque, time::Instant};
+
+use procmem_access::prelude::{MemoryAccess, OffsetType};
+
+use crate::results::ScanResultSet;
+
+/// Values of all tracked results read by one refresh.
+#[derive(Debug, Clone)]
+struct Sample {
+	time: Instant,
+	/// Values in the order of the tracked results, `None` where the value could not be read.
+	values: Vec<Option<Box<[u8]>>>,
+}
+
+/// Records the values at scan results over time.
+#[derive(Debug, Clone)]
+pub struct MatchTracker {
+	results: ScanResultSet,
+	samples: VecDeque<Sample>,
+	/// Number of samples dropped from the front of `samples`, so that marks stay valid.
+	dropped_samples: usize,
+	max_samples: usize,
+	/// Marks with the number of samples taken before them, in the order they were placed.
+	marks: Vec<(String, usize)>,
+}
+impl MatchTracker {
+	/// Number of samples kept by default, see [`with_max_samples`](Self::with_max_samples).
+	pub const DEFAULT_MAX_SAMPLES: usize = 64;
+
+	pub fn new(results: ScanResultSet) -> Self {
+		MatchTracker {
+			results,
+			samples: VecDeque::new(),
+			dropped_samples: 0,
+			max_samples: Self::DEFAULT_MAX_SAMPLES,
+			marks: Vec::new(),
+		}
+	}
+
+	/// Keeps at most `max_samples` samples, dropping the oldest ones. At least two samples are always kept.
+	pub fn with_max_samples(mut self, max_samples: usize) -> Self {
+		self.max_samples = max_samples.max(2);
+
+		self
+	}
+
+	pub fn results(&self) -> &ScanResultSet {
+		&self.results
+	}
+
+	/// Number of samples currently kept.
+	pub fn samples(&self) -> usize {
+		self.samples.len()
+	}
+
+	/// Reads the values of all results and records them as a new sample.
+	///
+	/// ## Safety
+	/// * See [`MemoryAccess::read`].
+	pub unsafe fn refresh<A: MemoryAccess + ?Sized>(&mut self, access: &mut A) {
+		let mut values = Vec::with_capacity(self.results.len());
+		self.results.read_values(access, |_, value| {
+			values.push(value.ok().map(Box::from));
+		});
+
+		self.samples.push_back(Sample {
+			time: Instant::now(),
+			values,
+		});
+		if self.samples.len() > self.max_samples {
+			self.samples.pop_front();
+			self.dropped_samples += 1;
+		}
+	}
+
+	/// Places mark `name` after the last sample, replacing any mark of the same name.
+	pub fn mark(&mut self, name: impl Into<String>) {
+		let name = name.into();
+		self.marks.retain(|(mark, _)| *mark != name);
+
+		self.marks
+			.push((name, self.dropped_samples + self.samples.len()));
+	}
+
+	/// Returns mark names in the order they were placed.
+	pub fn marks(&self) -> impl Iterator<Item = &str> {
+		self.marks.iter().map(|(name, _)| name.as_str())
+	}
+
+	/// Returns the values recorded at `offset` with the time they were read, oldest first.
+	///
+	/// Values which could not be read are `None`.
+	pub fn history(
+		&self,
+		offset: OffsetType,
+	) -> Option<impl Iterator<Item = (Instant, Option<&[u8]>)>> {
+		let index = self
+			.results
+			.iter()
+			.position(|(result, _)| *result == offset)?;
+
+		Some(
+			self.samples
+				.iter()
+				.map(move |sample| (sample.time, sample.values[index].as_deref())),
+		)
+	}
+
+	/// Returns the results whose value differs between the samples right before and right after mark `name`.
+	///
+	/// Returns `None` if there is no such mark, or if either of the samples is not available.
+	pub fn changed_across(&self, name: &str) -> Option<ScanResultSet> {
+		let [before, after] = self.samples_around(name)?;
+
+		Some(self.filter_results(|index| changed(before, after, index) == Some(true)))
+	}
+
+	/// Returns the results whose value changed across mark `name` and did not change between any other two consecutive samples.
+	///
+	/// This finds values which only react to the action performed at the mark. Returns `None` like [`changed_across`](Self::changed_across).
+	pub fn changed_only_at(&self, name: &str) -> Option<ScanResultSet> {
+		let [before, after] = self.samples_around(name)?;
+
+		Some(self.filter_results(|index| {
+			changed(before, after, index) == Some(true)
+				&& self
+					.samples
+					.iter()
+					.zip(self.samples.iter().skip(1))
+					.filter(|(older, _)| !std::ptr::eq(*older, before))
+					.all(|(older, newer)| changed(older, newer, index) != Some(true))
+		}))
+	}
+
+	/// Returns the samples right before and after the mark.
+	fn samples_around(&self, name: &str) -> Option<[&Sample; 2]> {
+		let (_, position) = self.marks.iter().find(|(mark, _)| mark == name)?;
+		let after = position.checked_sub(self.dropped_samples)?;
+		let before = after.checked_sub(1)?;
+
+		Some([self.samples.get(before)?, self.samples.get(after)?])
+	}
+
+	fn filter_results(&self, mut predicate: impl FnMut(usize) -> bool) -> ScanResultSet {
+		self.results
+			.iter()
+			.enumerate()
+			.filter(|(index, _)| predicate(*index))
+			.map(|(_, result)| *result)
+			.collect()
+	}
+}
+
+/// Returns whether the value at `index` changed, or `None` if it could not be read in either sample.
+fn changed(older: &Sample, newer: &Sample, index: usize) -> Option<bool> {
+	match (&older.values[index], &newer.values[index]) {
+		(Some(older), Some(newer)) => Some(older != newer),
+		_ => None,
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use std::num::NonZeroUsize;
+
+	use procmem_access::{
+		platform::mock::MockMemoryAccess,
+		prelude::{MemoryAccess, OffsetType},
+	};
+
+	use super::MatchTracker;
+	use crate::results::ScanResultSet;
+
+	fn write(access: &mut MockMemoryAccess, offset: OffsetType, value: u32) {
+		unsafe { access.write(offset, &value.to_ne_bytes()).unwrap() };
+	}
+
+	#[test]
+	fn test_match_tracker() {
+		let base = OffsetType::new_unwrap(0x1000);
+		let at = |index: u64| base.saturating_add(index * 4);
+		let mut access = MockMemoryAccess::new().with_region(base, vec![0u8; 16]);
+
+		let results: ScanResultSet = (0..4)
+			.map(|index| (at(index), NonZeroUsize::new(4).unwrap()))
+			.collect();
+		let mut tracker = MatchTracker::new(results).with_max_samples(8);
+
+		// value 0 changes on every refresh, value 1 only at the mark, value 2 at the mark and later, value 3 never
+		let mut ticks = 0;
+		let mut refresh = |tracker: &mut MatchTracker, access: &mut MockMemoryAccess| {
+			ticks += 1;
+			write(access, at(0), ticks);
+			unsafe { tracker.refresh(access) };
+		};
+
+		refresh(&mut tracker, &mut access);
+		refresh(&mut tracker, &mut access);
+		tracker.mark("press");
+		assert!(tracker.changed_across("press").is_none());
+
+		write(&mut access, at(1), 7);
+		write(&mut access, at(2), 7);
+		refresh(&mut tracker, &mut access);
+		write(&mut access, at(2), 8);
+		refresh(&mut tracker, &mut access);
+
+		let offsets = |results: ScanResultSet| -> Vec<OffsetType> { results.offsets().collect() };
+		assert_eq!(
+			offsets(tracker.changed_across("press").unwrap()),
+			[at(0), at(1), at(2)]
+		);
+		assert_eq!(offsets(tracker.changed_only_at("press").unwrap()), [at(1)]);
+		assert!(tracker.changed_only_at("release").is_none());
+
+		let history: Vec<Option<Vec<u8>>> = tracker
+			.history(at(2))
+			.unwrap()
+			.map(|(_, value)| value.map(<[u8]>::to_vec))
+			.collect();
+		assert_eq!(
+			history,
+			[0u32, 0, 7, 8].map(|value| Some(value.to_ne_bytes().to_vec()))
+		);
+
+		// the mark is lost once the samples around it are dropped
+		for _ in 0..8 {
+			refresh(&mut tracker, &mut access);
+		}
+		assert_eq!(tracker.samples(), 8);
+		assert!(tracker.changed_across("press").is_none());
+	}
+}
@@ -1,3 +0,0 @@
-pub mod merge;
-
-pub use merge::MergeIter;
@@ -0,0 +1,107 @@
+//! Derive macros for `procmem_scan`.
+//!
+//! Use these through the `derive` feature of `procmem_scan` rather than depending on this crate directly.
+
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{parse_macro_input, spanned::Spanned, Data, DeriveInput, Error, Fields};
+
+/// Derives `AsRawBytes` (and thus `ByteComparable`) for a struct.
+///
+/// The struct must be `#[repr(C)]` or `#[repr(transparent)]`, must not be generic and all of its fields must be `AsRawBytes`.
+/// The absence of padding is checked at compile time by comparing the size of the struct to the sum of the sizes of its fields.
+#[proc_macro_derive(ByteComparable)]
+pub fn derive_byte_comparable(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+	let input = parse_macro_input!(input as DeriveInput);
+
+	match expand_byte_comparable(input) {
+		Ok(tokens) => tokens.into(),
+		Err(err) => err.to_compile_error().into(),
+	}
+}
+
+fn expand_byte_comparable(input: DeriveInput) -> Result<TokenStream, Error> {
+	let data = match input.data {
+		Data::Struct(ref data) => data,
+		Data::Enum(ref data) => {
+			return Err(Error::new(
+				data.enum_token.span(),
+				"ByteComparable can only be derived for structs",
+			))
+		}
+		Data::Union(ref data) => {
+			return Err(Error::new(
+				data.union_token.span(),
+				"ByteComparable can only be derived for structs",
+			))
+		}
+	};
+
+	if !input.generics.params.is_empty() {
+		return Err(Error::new(
+			input.generics.span(),
+			"ByteComparable cannot be derived for generic structs",
+		));
+	}
+
+	if !has_stable_repr(&input)? {
+		return Err(Error::new(
+			input.ident.span(),
+			"ByteComparable requires #[repr(C)] or #[repr(transparent)]",
+		));
+	}
+
+	let field_types: Vec<_> = match data.fields {
+		Fields::Named(ref fields) => fields.named.iter().map(|f| &f.ty).collect(),
+		Fields::Unnamed(ref fields) => fields.unnamed.iter().map(|f| &f.ty).collect(),
+		Fields::Unit => Vec::new(),
+	};
+	if field_types.is_empty() {
+		return Err(Error::new(
+			input.ident.span(),
+			"ByteComparable cannot be derived for structs without fields",
+		));
+	}
+
+	let name = &input.ident;
+	let padding_message = format!("`{}` must not contain padding", name);
+
+	Ok(quote! {
+		const _: () = {
+			fn assert_as_raw_bytes<T: ::procmem_scan::predicate::value::AsRawBytes>() {}
+			#[allow(dead_code)]
+			fn assert_fields() {
+				#( assert_as_raw_bytes::<#field_types>(); )*
+			}
+
+			assert!(
+				::core::mem::size_of::<#name>() == 0 #( + ::core::mem::size_of::<#field_types>() )*,
+				#padding_message
+			);
+		};
+
+		unsafe impl ::procmem_scan::predicate::value::AsRawBytes for #name {}
+	})
+}
+
+/// Returns whether the struct has a `repr` which makes its layout well defined.
+fn has_stable_repr(input: &DeriveInput) -> Result<bool, Error> {
+	let mut stable = false;
+
+	for attr in input.attrs.iter().filter(|a| a.path().is_ident("repr")) {
+		attr.parse_nested_meta(|meta| {
+			if meta.path.is_ident("C") || meta.path.is_ident("transparent") {
+				stable = true;
+			} else if meta.input.peek(syn::token::Paren) {
+				// skip arguments, e.g. `align(8)` or `packed(2)`
+				let content;
+				syn::parenthesized!(content in meta.input);
+				content.parse::<TokenStream>()?;
+			}
+
+			Ok(())
+		})?;
+	}
+
+	Ok(stable)
+}